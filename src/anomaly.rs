@@ -0,0 +1,86 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use crate::db::Finding;
+
+/// A rule whose detection count deviates sharply from its baseline
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RuleAnomaly {
+    pub rule_name: String,
+    pub baseline: f64,
+    pub current: usize,
+}
+
+impl RuleAnomaly {
+    /// Ratio of the current count to the baseline (infinite for brand new matches)
+    pub fn ratio(&self) -> f64 {
+        if self.baseline == 0.0 {
+            f64::INFINITY
+        } else {
+            self.current as f64 / self.baseline
+        }
+    }
+}
+
+impl fmt::Display for RuleAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} matched {} domains ({:.1}x the baseline of {:.1})",
+            self.rule_name,
+            self.current,
+            self.ratio(),
+            self.baseline
+        )
+    }
+}
+
+/// Count detected domains per rule
+pub fn count_by_rule(findings: &[Finding]) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for finding in findings.iter().filter(|f| f.detected) {
+        *counts.entry(finding.rule_name.clone()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Compare per-rule counts against their baseline averages.
+///
+/// A rule is anomalous when its count grows or shrinks by at least `factor`
+/// relative to the baseline. Rules below `min_count` on both sides are ignored
+/// so that tiny absolute changes (1 -> 2) don't trigger alerts.
+pub fn detect_anomalies(
+    current: &BTreeMap<String, usize>,
+    baseline: &HashMap<String, f64>,
+    factor: f64,
+    min_count: usize,
+) -> Vec<RuleAnomaly> {
+    let rules: BTreeSet<&String> = current.keys().chain(baseline.keys()).collect();
+    let min_count = min_count.max(1) as f64;
+
+    rules
+        .into_iter()
+        .filter_map(|rule_name| {
+            let current = current.get(rule_name).copied().unwrap_or(0);
+            let baseline = baseline.get(rule_name).copied().unwrap_or(0.0);
+
+            if (current as f64) < min_count && baseline < min_count {
+                return None;
+            }
+
+            let spiked = current as f64 >= baseline * factor;
+            let dropped = (current as f64) * factor <= baseline;
+
+            if spiked || dropped {
+                Some(RuleAnomaly {
+                    rule_name: rule_name.clone(),
+                    baseline,
+                    current,
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,280 @@
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::collections::HashSet;
+use std::future::Future;
+use std::net::IpAddr;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::utils;
+
+/// Default most DNS queries per second of the brute force, across all apexes
+pub const DEFAULT_RATE: u32 = 50;
+
+/// Queries of one apex's brute force in flight at once, within the rate
+const CONCURRENCY: usize = 16;
+
+/// Names under which registrations are made, so `example.co.uk` is an apex
+const SECOND_LEVEL_SUFFIXES: &[&str] = &[
+    "co.uk", "org.uk", "ac.uk", "gov.uk", "me.uk", "ltd.uk", "plc.uk", "com.au", "net.au",
+    "org.au", "edu.au", "gov.au", "co.nz", "org.nz", "co.jp", "or.jp", "ne.jp", "co.kr", "or.kr",
+    "co.in", "net.in", "org.in", "co.za", "org.za", "co.il", "com.br", "net.br", "com.cn",
+    "net.cn", "org.cn", "com.mx", "com.tr", "com.sg", "com.hk", "com.tw", "com.ar", "com.my",
+    "com.ph", "com.pk", "com.ua", "com.vn", "co.id", "co.th",
+];
+
+/// Most common subdomain labels, tried under every apex
+const SUBDOMAINS: &[&str] = &[
+    "www",
+    "mail",
+    "remote",
+    "blog",
+    "webmail",
+    "server",
+    "ns1",
+    "ns2",
+    "smtp",
+    "secure",
+    "vpn",
+    "m",
+    "shop",
+    "ftp",
+    "mail2",
+    "test",
+    "portal",
+    "ns",
+    "host",
+    "support",
+    "dev",
+    "web",
+    "bbs",
+    "mx",
+    "email",
+    "cloud",
+    "mail1",
+    "forum",
+    "owa",
+    "www2",
+    "gw",
+    "admin",
+    "store",
+    "mx1",
+    "cdn",
+    "api",
+    "exchange",
+    "app",
+    "news",
+    "vps",
+    "ns3",
+    "mobile",
+    "pop",
+    "imap",
+    "demo",
+    "beta",
+    "staging",
+    "stage",
+    "git",
+    "gitlab",
+    "jenkins",
+    "jira",
+    "confluence",
+    "wiki",
+    "docs",
+    "status",
+    "monitor",
+    "grafana",
+    "kibana",
+    "prometheus",
+    "auth",
+    "sso",
+    "login",
+    "id",
+    "accounts",
+    "account",
+    "dashboard",
+    "panel",
+    "cpanel",
+    "whm",
+    "autodiscover",
+    "autoconfig",
+    "intranet",
+    "internal",
+    "extranet",
+    "files",
+    "backup",
+    "db",
+    "sql",
+    "mysql",
+    "old",
+    "new",
+    "v2",
+    "assets",
+    "static",
+    "media",
+    "img",
+    "images",
+    "video",
+    "uat",
+    "qa",
+    "sandbox",
+    "preprod",
+    "prod",
+    "origin",
+    "proxy",
+    "gateway",
+    "crm",
+    "erp",
+    "hr",
+    "billing",
+    "pay",
+    "payments",
+    "help",
+    "helpdesk",
+    "ticket",
+    "tickets",
+    "chat",
+    "meet",
+    "calendar",
+    "owncloud",
+    "nextcloud",
+    "s3",
+    "storage",
+    "upload",
+    "download",
+    "downloads",
+    "relay",
+    "lists",
+    "smtp2",
+    "webdisk",
+    "search",
+    "analytics",
+    "events",
+    "careers",
+    "jobs",
+    "partners",
+    "developer",
+    "developers",
+    "registry",
+    "repo",
+    "ci",
+    "build",
+    "k8s",
+    "kubernetes",
+    "office",
+    "lab",
+];
+
+/// Whether a target is a bare apex domain such as `example.com` or `example.co.uk`,
+/// rather than a subdomain, an address or a host with a port
+pub fn is_apex(target: &str) -> bool {
+    let (host, port) = utils::split_host_port(target);
+    if port.is_some() || host.parse::<IpAddr>().is_ok() || !utils::is_valid_domain(host) {
+        return false;
+    }
+
+    let host = host.to_ascii_lowercase();
+    match host.split('.').count() {
+        2 => !SECOND_LEVEL_SUFFIXES.contains(&host.as_str()),
+        3 => SECOND_LEVEL_SUFFIXES.iter().any(|suffix| {
+            host.split_once('.')
+                .is_some_and(|(_, rest)| rest == *suffix)
+        }),
+        _ => false,
+    }
+}
+
+/// Spaces queries evenly to stay under a rate shared by every caller
+#[derive(Debug)]
+struct Pacer {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl Pacer {
+    fn new(per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / per_second.max(1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for the next free slot
+    async fn wait(&self) {
+        let at = {
+            let mut next = self.next.lock().await;
+            let at = (*next).max(Instant::now());
+            *next = at + self.interval;
+            at
+        };
+        tokio::time::sleep_until(at).await;
+    }
+}
+
+/// Rate-limited brute force of apex domains' subdomains from an embedded list of
+/// common names
+#[derive(Debug)]
+pub struct DnsBrute {
+    pacer: Pacer,
+}
+
+impl DnsBrute {
+    /// A brute force sending at most `per_second` queries a second
+    pub fn new(per_second: u32) -> Self {
+        Self {
+            pacer: Pacer::new(per_second),
+        }
+    }
+
+    /// Subdomains of `apex` that resolve, looked up with `lookup`.
+    ///
+    /// Names that don't exist are first resolved to learn the apex's wildcard
+    /// addresses, if it has any; a name resolving only to those is left out, as
+    /// every guess would otherwise be "found".
+    pub async fn discover<F, Fut>(&self, apex: &str, lookup: F) -> Vec<String>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Vec<IpAddr>>,
+    {
+        let apex = apex.to_ascii_lowercase();
+
+        let mut wildcard = HashSet::new();
+        for _ in 0..2 {
+            let label = uuid::Uuid::new_v4().simple().to_string();
+            self.pacer.wait().await;
+            wildcard.extend(lookup(format!("fatt-{}.{}", &label[..12], apex)).await);
+        }
+        if !wildcard.is_empty() {
+            debug!("🃏 {} has wildcard DNS: {:?}", apex, wildcard);
+        }
+
+        let check = |host: String| {
+            let (lookup, wildcard) = (&lookup, &wildcard);
+            async move {
+                self.pacer.wait().await;
+                let ips = lookup(host.clone()).await;
+                let real = ips.iter().any(|ip| !wildcard.contains(ip));
+                real.then_some(host)
+            }
+        };
+
+        let mut found = Vec::new();
+        let mut labels = SUBDOMAINS.iter();
+        let mut in_flight = FuturesUnordered::new();
+        loop {
+            while in_flight.len() < CONCURRENCY {
+                let Some(label) = labels.next() else {
+                    break;
+                };
+                in_flight.push(check(format!("{}.{}", label, apex)));
+            }
+            match in_flight.next().await {
+                Some(host) => found.extend(host),
+                None => break,
+            }
+        }
+        found.sort();
+
+        found
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use tracing::{error, info};
+
+use crate::context::ScanContext;
+use crate::rules::{RuleSet, TemplateVars};
+use crate::scanner::{self, ScanResources};
+
+/// A domain the operator owns, with known findings, scanned before the real scan
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Canary {
+    pub domain: String,
+
+    /// Rules that must detect something on the domain
+    #[serde(default)]
+    pub expect: Vec<String>,
+
+    /// Rules that must detect nothing on the domain. A canary listing no rules at all
+    /// must come back clean for every rule.
+    #[serde(default)]
+    pub absent: Vec<String>,
+}
+
+/// Error a scan ends with when a canary didn't come back as expected
+#[derive(Debug, thiserror::Error)]
+#[error("Canary check failed: {0} problems, scan aborted")]
+pub struct CanaryCheckFailed(pub usize);
+
+/// Canary domains checked before a scan starts, so a misconfigured proxy, resolver or
+/// rules file aborts the scan instead of quietly finding nothing
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct Canaries {
+    pub canaries: Vec<Canary>,
+}
+
+impl Canaries {
+    /// Load canaries from a YAML file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path.as_ref()).context(format!(
+            "Failed to open canaries file: {}",
+            path.as_ref().display()
+        ))?;
+
+        let canaries: Canaries = serde_yaml::from_reader(BufReader::new(file)).context(format!(
+            "Failed to parse canaries file: {}",
+            path.as_ref().display()
+        ))?;
+        if canaries.canaries.is_empty() {
+            anyhow::bail!("No canaries in {}", path.as_ref().display());
+        }
+
+        Ok(canaries)
+    }
+
+    /// Check that every rule the canaries name is in the ruleset
+    pub fn validate(&self, ruleset: &RuleSet) -> Result<()> {
+        for canary in &self.canaries {
+            for name in canary.expect.iter().chain(&canary.absent) {
+                if !ruleset.rules.iter().any(|rule| &rule.name == name) {
+                    anyhow::bail!("Canary {} names unknown rule: {}", canary.domain, name);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Load canaries from a YAML file
+pub fn load_canaries(canaries_file: &str) -> Result<Canaries> {
+    Canaries::from_file(canaries_file)
+}
+
+/// Scan every canary and compare the outcome with what it should be, returning a
+/// description of each mismatch. Nothing is recorded in the result store.
+pub async fn check_canaries(canaries: &Canaries, resources: &ScanResources) -> Vec<String> {
+    let snapshot = resources.ruleset.snapshot();
+    let mut problems = Vec::new();
+
+    for canary in &canaries.canaries {
+        let ctx = match ScanContext::prepare(&canary.domain, resources).await {
+            Ok(ctx) => ctx,
+            Err(e) => {
+                problems.push(format!("{}: could not be resolved: {:#}", canary.domain, e));
+                continue;
+            }
+        };
+
+        let negative = canary.expect.is_empty() && canary.absent.is_empty();
+        let vars = TemplateVars::new(&canary.domain, Utc::now().date_naive());
+        for rule in &snapshot.ruleset.rules {
+            let expected = canary.expect.contains(&rule.name);
+            if !expected && !negative && !canary.absent.contains(&rule.name) {
+                continue;
+            }
+
+            let rule = rule.expand(&vars);
+            let detected = match scanner::check_rule(&ctx, resources, &rule).await {
+                Ok(outcome) => outcome.is_some_and(|outcome| outcome.detected),
+                Err(e) if expected => {
+                    problems.push(format!(
+                        "{}: {} failed to check: {:#}",
+                        canary.domain, rule.name, e
+                    ));
+                    continue;
+                }
+                Err(_) => false,
+            };
+
+            match (expected, detected) {
+                (true, false) => problems.push(format!(
+                    "{}: {} should detect {} but didn't",
+                    canary.domain, rule.name, rule.path
+                )),
+                (false, true) => problems.push(format!(
+                    "{}: {} detected {}, which should be absent",
+                    canary.domain, rule.name, rule.path
+                )),
+                _ => {}
+            }
+        }
+    }
+
+    problems
+}
+
+/// Scan the canaries, failing if any of them didn't come back as expected
+pub async fn preflight(canaries: &Canaries, resources: &ScanResources) -> Result<()> {
+    info!(
+        "🐤 Checking {} canary domains before the scan",
+        canaries.canaries.len()
+    );
+
+    let problems = check_canaries(canaries, resources).await;
+    if problems.is_empty() {
+        info!("✅ Canaries came back as expected");
+        return Ok(());
+    }
+
+    for problem in &problems {
+        error!("🐤 Canary check failed: {}", problem);
+    }
+    Err(CanaryCheckFailed(problems.len()).into())
+}
@@ -0,0 +1,163 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::db;
+use crate::retention;
+use crate::store::{self, StoreKind};
+
+/// TLS certificate a scanned host presented, kept by host as a side effect of scanning
+/// it over https
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SiteCertificate {
+    /// Domain the certificate was presented for, as scanned
+    pub host: String,
+
+    /// Subject's distinguished name, such as `CN=example.com`
+    pub subject: String,
+
+    /// Issuer's distinguished name
+    pub issuer: String,
+
+    /// DNS names and IP addresses the certificate is valid for
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sans: Vec<String>,
+
+    /// Serial number, in hex
+    pub serial: String,
+
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+
+    /// When a scan last saw the certificate
+    pub seen_at: DateTime<Utc>,
+}
+
+impl SiteCertificate {
+    /// Read a DER-encoded certificate presented by `host`
+    pub fn parse(host: &str, der: &[u8], seen_at: DateTime<Utc>) -> Result<Self> {
+        let (_, cert) = X509Certificate::from_der(der)
+            .map_err(|e| anyhow::anyhow!("Invalid certificate: {}", e))?;
+        let validity = cert.validity();
+        let timestamp = |seconds: i64| {
+            DateTime::from_timestamp(seconds, 0).context("Certificate validity out of range")
+        };
+
+        let mut sans = Vec::new();
+        if let Ok(Some(extension)) = cert.subject_alternative_name() {
+            for name in &extension.value.general_names {
+                match name {
+                    GeneralName::DNSName(name) => sans.push(name.to_string()),
+                    GeneralName::IPAddress(bytes) => {
+                        if let Some(ip) = ip_address(bytes) {
+                            sans.push(ip);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(SiteCertificate {
+            host: host.to_string(),
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            sans,
+            serial: hex::encode(cert.raw_serial()),
+            not_before: timestamp(validity.not_before.timestamp())?,
+            not_after: timestamp(validity.not_after.timestamp())?,
+            seen_at,
+        })
+    }
+
+    /// Time left until the certificate expires; negative once it has
+    pub fn remaining(&self, now: DateTime<Utc>) -> Duration {
+        self.not_after - now
+    }
+
+    /// Whether the certificate expires, or has expired, within `window` of `now`
+    pub fn expires_within(&self, window: Duration, now: DateTime<Utc>) -> bool {
+        self.not_after <= now + window
+    }
+}
+
+/// An IP address SAN, four bytes for IPv4 and sixteen for IPv6
+fn ip_address(bytes: &[u8]) -> Option<String> {
+    match bytes.len() {
+        4 => Some(std::net::Ipv4Addr::from(<[u8; 4]>::try_from(bytes).ok()?).to_string()),
+        16 => Some(std::net::Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?).to_string()),
+        _ => None,
+    }
+}
+
+/// Print the certificates scanned hosts presented, soonest to expire first, optionally
+/// only those expiring within an age such as `30d`
+pub fn show_certificates(
+    kind: StoreKind,
+    database: &str,
+    shards: usize,
+    expiring_within: Option<&str>,
+    format: &str,
+) -> Result<()> {
+    if kind.resolve(database) != StoreKind::Postgres && !Path::new(database).exists() {
+        anyhow::bail!("Results not found: {}", database);
+    }
+    let window = expiring_within.map(retention::parse_age).transpose()?;
+
+    let now = Utc::now();
+    let mut certificates = store::open_results(kind, database, shards)?.certificates()?;
+    if let Some(window) = window {
+        certificates.retain(|certificate| certificate.expires_within(window, now));
+    }
+    certificates.sort_by(|a, b| (a.not_after, &a.host).cmp(&(b.not_after, &b.host)));
+
+    match format.to_lowercase().as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&certificates)?),
+        "text" => print_certificates(&certificates, expiring_within, now),
+        _ => anyhow::bail!("Unsupported certificates format: {}", format),
+    }
+
+    Ok(())
+}
+
+fn print_certificates(
+    certificates: &[SiteCertificate],
+    expiring_within: Option<&str>,
+    now: DateTime<Utc>,
+) {
+    match expiring_within {
+        Some(window) => println!(
+            "🔏 {} certificates expiring within {}",
+            certificates.len(),
+            window
+        ),
+        None => println!("🔏 {} certificates", certificates.len()),
+    }
+    if certificates.is_empty() {
+        return;
+    }
+
+    println!(
+        "\n{:<35} {:<12} {:<10} {:<35} Names",
+        "Host", "Expires", "Days Left", "Issuer"
+    );
+    for certificate in certificates {
+        let days = certificate.remaining(now).num_days();
+        let left = if certificate.not_after <= now {
+            "expired".to_string()
+        } else {
+            days.to_string()
+        };
+        println!(
+            "{:<35} {:<12} {:<10} {:<35} {}",
+            certificate.host,
+            certificate.not_after.format("%Y-%m-%d"),
+            left,
+            db::truncate_string(&certificate.issuer, 35),
+            certificate.sans.join(", ")
+        );
+    }
+}
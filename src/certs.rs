@@ -0,0 +1,466 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, Utc};
+use rcgen::{
+    BasicConstraints, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair, KeyUsagePurpose, SerialNumber,
+};
+use rustls::crypto::ring;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+/// CA certificate, handed to workers so they can verify the master
+pub const CA_CERT_FILE: &str = "ca.pem";
+
+/// CA private key, which never leaves the master
+pub const CA_KEY_FILE: &str = "ca-key.pem";
+
+/// Certificates the CA has issued, and which of them are revoked
+pub const REGISTRY_FILE: &str = "workers.json";
+
+/// Days a worker certificate is valid for unless told otherwise
+pub const DEFAULT_VALIDITY_DAYS: u32 = 365;
+
+/// Days the CA certificate is valid for
+const CA_VALIDITY_DAYS: u32 = 3650;
+
+/// A certificate issued to a worker
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IssuedCert {
+    /// Worker the certificate was issued to, also its common name
+    pub worker_id: String,
+
+    /// Serial number, in hex
+    pub serial: String,
+
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+
+    /// When the certificate was revoked, if it was
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Certificate and key files written for a worker
+#[derive(Debug, Clone)]
+pub struct WorkerCertFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// The master's certificate authority.
+///
+/// Each worker gets its own client certificate, with the worker ID as its common
+/// name, so one compromised worker can be revoked without touching the others. The
+/// CA directory keeps the CA certificate and key, and a registry of every
+/// certificate issued; registration checks the registry, so a revocation applies to
+/// the next connection without restarting the master.
+#[derive(Debug, Clone)]
+pub struct CertAuthority {
+    dir: PathBuf,
+}
+
+impl CertAuthority {
+    /// Create a CA in `dir`, or open the one already there
+    pub fn init(dir: &Path) -> Result<Self> {
+        let ca = Self {
+            dir: dir.to_path_buf(),
+        };
+        if ca.cert_path().exists() {
+            info!("🔐 Using existing CA in {}", dir.display());
+            return Ok(ca);
+        }
+
+        fs::create_dir_all(dir)
+            .context(format!("Failed to create CA directory: {}", dir.display()))?;
+
+        let mut params = CertificateParams::default();
+        params.distinguished_name = distinguished_name("fatt master CA");
+        params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+        params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+        params.serial_number = Some(new_serial());
+        set_validity(&mut params, CA_VALIDITY_DAYS);
+
+        let key = KeyPair::generate().context("Failed to generate CA key")?;
+        let cert = params
+            .self_signed(&key)
+            .context("Failed to create CA certificate")?;
+
+        write_file(&ca.cert_path(), &cert.pem(), false)?;
+        write_file(&dir.join(CA_KEY_FILE), &key.serialize_pem(), true)?;
+        ca.save_registry(&[])?;
+
+        info!("🔐 Created CA in {}", dir.display());
+        Ok(ca)
+    }
+
+    /// Open the CA in `dir`
+    pub fn open(dir: &Path) -> Result<Self> {
+        let ca = Self {
+            dir: dir.to_path_buf(),
+        };
+        if !ca.cert_path().exists() {
+            anyhow::bail!(
+                "No CA in {} (create one with `fatt master init-ca`)",
+                dir.display()
+            );
+        }
+
+        Ok(ca)
+    }
+
+    /// Path of the CA certificate
+    pub fn cert_path(&self) -> PathBuf {
+        self.dir.join(CA_CERT_FILE)
+    }
+
+    /// Issue a client certificate for `worker_id`, valid for `days`, writing it and
+    /// its key to `<out_dir>/<worker_id>.pem` and `<out_dir>/<worker_id>-key.pem`
+    pub fn issue(&self, worker_id: &str, days: u32, out_dir: &Path) -> Result<WorkerCertFiles> {
+        validate_worker_id(worker_id)?;
+
+        let mut params = CertificateParams::default();
+        params.distinguished_name = distinguished_name(worker_id);
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        params.use_authority_key_identifier_extension = true;
+        let serial = new_serial();
+        params.serial_number = Some(serial.clone());
+        let (issued_at, expires_at) = set_validity(&mut params, days);
+
+        let key = KeyPair::generate().context("Failed to generate worker key")?;
+        let cert = self.sign(params, &key)?;
+
+        fs::create_dir_all(out_dir).context(format!(
+            "Failed to create certificate directory: {}",
+            out_dir.display()
+        ))?;
+        let files = WorkerCertFiles {
+            cert: out_dir.join(format!("{}.pem", worker_id)),
+            key: out_dir.join(format!("{}-key.pem", worker_id)),
+        };
+        write_file(&files.cert, &cert.pem(), false)?;
+        write_file(&files.key, &key.serialize_pem(), true)?;
+
+        let mut issued = self.issued()?;
+        issued.push(IssuedCert {
+            worker_id: worker_id.to_string(),
+            serial: hex::encode(serial.to_bytes()),
+            issued_at,
+            expires_at,
+            revoked_at: None,
+        });
+        self.save_registry(&issued)?;
+
+        info!(
+            "📜 Issued certificate {} for worker {}",
+            hex::encode(serial.to_bytes()),
+            worker_id
+        );
+        Ok(files)
+    }
+
+    /// Revoke every certificate issued to `worker_id`, returning how many were revoked
+    pub fn revoke(&self, worker_id: &str) -> Result<usize> {
+        let mut issued = self.issued()?;
+        let now = Utc::now();
+        let mut revoked = 0;
+        for cert in issued
+            .iter_mut()
+            .filter(|cert| cert.worker_id == worker_id && cert.revoked_at.is_none())
+        {
+            cert.revoked_at = Some(now);
+            revoked += 1;
+        }
+        self.save_registry(&issued)?;
+
+        Ok(revoked)
+    }
+
+    /// Every certificate the CA has issued
+    pub fn issued(&self) -> Result<Vec<IssuedCert>> {
+        let path = self.dir.join(REGISTRY_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&path).context(format!(
+            "Failed to read certificate registry: {}",
+            path.display()
+        ))?;
+
+        serde_json::from_str(&content).context(format!(
+            "Failed to parse certificate registry: {}",
+            path.display()
+        ))
+    }
+
+    /// Check that a client certificate, already verified to chain to the CA, belongs
+    /// to the worker registering with it and hasn't been revoked
+    pub fn verify_worker(&self, cert_der: &[u8], worker_id: &str) -> Result<()> {
+        let (_, cert) = X509Certificate::from_der(cert_der)
+            .map_err(|e| anyhow::anyhow!("Failed to parse worker certificate: {}", e))?;
+
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .context("Worker certificate has no common name")?;
+        if common_name != worker_id {
+            anyhow::bail!(
+                "Certificate for worker {} presented by worker {}",
+                common_name,
+                worker_id
+            );
+        }
+
+        let serial = hex::encode(cert.raw_serial());
+        let issued = self.issued()?;
+        let record = issued
+            .iter()
+            .find(|issued| issued.serial == serial && issued.worker_id == worker_id)
+            .context(format!(
+                "Certificate {} of worker {} was not issued by this CA",
+                serial, worker_id
+            ))?;
+        if let Some(revoked_at) = record.revoked_at {
+            anyhow::bail!(
+                "Certificate {} of worker {} was revoked at {}",
+                serial,
+                worker_id,
+                revoked_at.to_rfc3339()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// TLS configuration for the master: a certificate for `server_names` signed by
+    /// the CA, and a client certificate required from every worker
+    pub fn server_config(&self, server_names: &[String]) -> Result<Arc<ServerConfig>> {
+        let mut params =
+            CertificateParams::new(server_names.to_vec()).context("Invalid master server name")?;
+        params.distinguished_name = distinguished_name("fatt master");
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        params.use_authority_key_identifier_extension = true;
+        params.serial_number = Some(new_serial());
+        set_validity(&mut params, CA_VALIDITY_DAYS);
+
+        let key = KeyPair::generate().context("Failed to generate master key")?;
+        let cert = self.sign(params, &key)?;
+
+        self.verifying_server_config(
+            vec![cert.der().clone()],
+            PrivateKeyDer::Pkcs8(key.serialize_der().into()),
+        )
+    }
+
+    /// TLS configuration for a master with a certificate of its own, issued by any CA
+    /// the workers trust, still requiring a client certificate from every worker
+    pub fn server_config_with_cert(&self, cert: &Path, key: &Path) -> Result<Arc<ServerConfig>> {
+        let (certs, key) = load_identity(cert, key, "master")?;
+        self.verifying_server_config(certs, key)
+    }
+
+    fn verifying_server_config(
+        &self,
+        certs: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> Result<Arc<ServerConfig>> {
+        let provider = Arc::new(ring::default_provider());
+        let verifier =
+            WebPkiClientVerifier::builder_with_provider(Arc::new(self.roots()?), provider.clone())
+                .build()
+                .context("Failed to configure worker certificate verification")?;
+        let config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .context("Failed to configure TLS")?
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(certs, key)
+            .context("Failed to configure master certificate")?;
+
+        Ok(Arc::new(config))
+    }
+
+    /// Sign `params` with the CA key
+    fn sign(&self, params: CertificateParams, key: &KeyPair) -> Result<rcgen::Certificate> {
+        let ca_pem = read_file(&self.cert_path())?;
+        let ca_key = KeyPair::from_pem(&read_file(&self.dir.join(CA_KEY_FILE))?)
+            .context("Failed to parse CA key")?;
+        let ca_cert = CertificateParams::from_ca_cert_pem(&ca_pem)
+            .context("Failed to parse CA certificate")?
+            .self_signed(&ca_key)
+            .context("Failed to load CA certificate")?;
+
+        params
+            .signed_by(key, &ca_cert, &ca_key)
+            .context("Failed to sign certificate")
+    }
+
+    fn roots(&self) -> Result<RootCertStore> {
+        load_roots(&self.cert_path())
+    }
+
+    fn save_registry(&self, issued: &[IssuedCert]) -> Result<()> {
+        let content = serde_json::to_string_pretty(issued)?;
+        write_file(&self.dir.join(REGISTRY_FILE), &content, false)
+    }
+}
+
+/// TLS configuration for a master without a CA of its own: its certificate and key,
+/// with no certificate asked of workers. The channel is encrypted, but any worker
+/// can register.
+pub fn server_config(cert: &Path, key: &Path) -> Result<Arc<ServerConfig>> {
+    let (certs, key) = load_identity(cert, key, "master")?;
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .context("Failed to configure TLS")?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("Failed to configure master certificate")?;
+
+    Ok(Arc::new(config))
+}
+
+/// TLS configuration for a worker: the CA certificate the master's certificate must
+/// chain to, and the worker's certificate and key if it has one
+pub fn client_config(
+    ca_cert: &Path,
+    identity: Option<(&Path, &Path)>,
+) -> Result<Arc<ClientConfig>> {
+    let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .context("Failed to configure TLS")?
+        .with_root_certificates(load_roots(ca_cert)?);
+    let config = match identity {
+        Some((cert, key)) => {
+            let (certs, key) = load_identity(cert, key, "worker")?;
+            builder
+                .with_client_auth_cert(certs, key)
+                .context("Failed to configure worker certificate")?
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    Ok(Arc::new(config))
+}
+
+/// Read a PEM certificate chain and its private key, of the `role` named in errors
+fn load_identity(
+    cert: &Path,
+    key: &Path,
+    role: &str,
+) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .context(format!(
+            "Failed to read {} certificate: {}",
+            role,
+            cert.display()
+        ))?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificate in {}", cert.display());
+    }
+    let key = PrivateKeyDer::from_pem_file(key).context(format!(
+        "Failed to read {} key: {}",
+        role,
+        key.display()
+    ))?;
+
+    Ok((certs, key))
+}
+
+fn load_roots(ca_cert: &Path) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(ca_cert).context(format!(
+        "Failed to read CA certificate: {}",
+        ca_cert.display()
+    ))? {
+        roots
+            .add(cert.context("Failed to parse CA certificate")?)
+            .context("Invalid CA certificate")?;
+    }
+
+    Ok(roots)
+}
+
+/// Worker IDs name certificate files, so they're kept to filename-safe characters
+fn validate_worker_id(worker_id: &str) -> Result<()> {
+    let valid = !worker_id.is_empty()
+        && !worker_id.starts_with('.')
+        && worker_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        anyhow::bail!(
+            "Invalid worker ID: {} (use letters, digits, '-', '_' and '.')",
+            worker_id
+        );
+    }
+
+    Ok(())
+}
+
+fn distinguished_name(common_name: &str) -> DistinguishedName {
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, common_name);
+    name
+}
+
+/// A random positive serial number
+fn new_serial() -> SerialNumber {
+    let mut bytes = *uuid::Uuid::new_v4().as_bytes();
+    // Keep the high bit clear so the number is positive, and the top byte non-zero so
+    // its encoding keeps all 16 bytes
+    bytes[0] = (bytes[0] & 0x7f) | 0x40;
+    SerialNumber::from_slice(&bytes)
+}
+
+/// Make a certificate valid from yesterday, to allow for clock skew, for `days`
+fn set_validity(params: &mut CertificateParams, days: u32) -> (DateTime<Utc>, DateTime<Utc>) {
+    let issued_at = Utc::now();
+    let expires_at = issued_at + Duration::days(i64::from(days));
+    let not_before = issued_at - Duration::days(1);
+    params.not_before = rcgen::date_time_ymd(
+        not_before.year(),
+        not_before.month() as u8,
+        not_before.day() as u8,
+    );
+    params.not_after = rcgen::date_time_ymd(
+        expires_at.year(),
+        expires_at.month() as u8,
+        expires_at.day() as u8,
+    );
+
+    (issued_at, expires_at)
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    fs::read_to_string(path).context(format!("Failed to read {}", path.display()))
+}
+
+/// Write `content` to `path`, readable only by its owner if it's `private`
+fn write_file(path: &Path, content: &str, private: bool) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .context(format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,99 @@
+//! Fault injection between the master and its workers, so tests can check that the
+//! protocol survives an unreliable network. Only built with the `chaos` feature.
+
+use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::distributed::WorkerMessage;
+
+/// Bytes of a frame before the message: its length and checksum
+const FRAME_HEADER_BYTES: usize = 8;
+
+/// How often each kind of fault is injected into the messages sent between the master
+/// and its workers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChaosPolicy {
+    /// Chance that sending a message fails, dropping the connection
+    pub disconnect: f64,
+
+    /// Chance that a byte of a message is flipped in transit
+    pub corrupt: f64,
+
+    /// Longest a heartbeat is held back before it's sent
+    pub heartbeat_delay: Duration,
+
+    /// Seed of the random faults
+    pub seed: u64,
+}
+
+static CHAOS: Mutex<Option<(ChaosPolicy, StdRng)>> = Mutex::new(None);
+
+/// Inject faults into every message sent from now on, in this process
+#[allow(dead_code)]
+pub fn install(policy: ChaosPolicy) {
+    let rng = StdRng::seed_from_u64(policy.seed);
+    *CHAOS.lock().unwrap() = Some((policy, rng));
+}
+
+/// Stop injecting faults
+#[allow(dead_code)]
+pub fn clear() {
+    *CHAOS.lock().unwrap() = None;
+}
+
+/// What's done to one message
+struct Fault {
+    delay: Duration,
+    corrupt: Option<(usize, u8)>,
+    disconnect: bool,
+}
+
+/// Disturb a message's frame before it's written: hold back a heartbeat, flip a byte
+/// of the message, or fail as if the connection dropped
+pub(crate) async fn disturb(message: &WorkerMessage, frame: &mut [u8]) -> Result<()> {
+    let Some(fault) = roll(message, frame.len()) else {
+        return Ok(());
+    };
+
+    if !fault.delay.is_zero() {
+        debug!("🐒 Delaying heartbeat by {:?}", fault.delay);
+        tokio::time::sleep(fault.delay).await;
+    }
+    if fault.disconnect {
+        anyhow::bail!("Connection dropped by chaos");
+    }
+    if let Some((offset, mask)) = fault.corrupt {
+        debug!("🐒 Corrupting byte {} of a message", offset);
+        frame[offset] ^= mask;
+    }
+
+    Ok(())
+}
+
+fn roll(message: &WorkerMessage, frame_len: usize) -> Option<Fault> {
+    let mut chaos = CHAOS.lock().unwrap();
+    let (policy, rng) = chaos.as_mut()?;
+
+    let delay = match message {
+        WorkerMessage::Heartbeat { .. } if !policy.heartbeat_delay.is_zero() => {
+            rng.gen_range(Duration::ZERO..policy.heartbeat_delay)
+        }
+        _ => Duration::ZERO,
+    };
+    let corrupt = (frame_len > FRAME_HEADER_BYTES && rng.gen_bool(policy.corrupt)).then(|| {
+        (
+            rng.gen_range(FRAME_HEADER_BYTES..frame_len),
+            rng.gen_range(1..=u8::MAX),
+        )
+    });
+
+    Some(Fault {
+        delay,
+        corrupt,
+        disconnect: rng.gen_bool(policy.disconnect),
+    })
+}
@@ -0,0 +1,220 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use crate::db::{self, Finding};
+use crate::store::{self, StoreKind};
+
+/// Fewest findings that make a cluster unless asked otherwise
+pub const DEFAULT_MIN_SIZE: usize = 2;
+
+/// Characters of the body hash a cluster is identified by
+const ID_LENGTH: usize = 12;
+
+/// Detected findings of one rule whose responses had byte-identical bodies, such as
+/// parked pages or hosts serving the same template
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Cluster {
+    /// Start of the body hash, which `results clusters --dismiss` takes
+    pub id: String,
+    pub rule_name: String,
+    /// SHA-256 of the shared body, in hex
+    pub body_hash: String,
+    /// Domains that returned the body, sorted
+    pub domains: Vec<String>,
+    /// Part of the body the signature matched in, from one of the findings
+    pub snippet: String,
+}
+
+/// Group detected findings by rule and body hash, keeping groups of at least
+/// `min_size` domains, largest first. Findings without a hash of their whole body,
+/// such as those whose download stopped at the match, are left out.
+pub fn find_clusters(findings: &[Finding], min_size: usize) -> Vec<Cluster> {
+    let mut groups: HashMap<(&str, &str), (BTreeSet<&str>, &str)> = HashMap::new();
+    for finding in findings.iter().filter(|finding| finding.detected) {
+        let Some(evidence) = &finding.evidence else {
+            continue;
+        };
+        let Some(hash) = evidence.body_hash.as_ref().filter(|_| !evidence.truncated) else {
+            continue;
+        };
+        groups
+            .entry((&finding.rule_name, hash))
+            .or_insert_with(|| (BTreeSet::new(), &evidence.snippet))
+            .0
+            .insert(&finding.domain);
+    }
+
+    let mut clusters: Vec<Cluster> = groups
+        .into_iter()
+        .filter(|(_, (domains, _))| domains.len() >= min_size.max(1))
+        .map(|((rule_name, hash), (domains, snippet))| Cluster {
+            id: cluster_id(hash),
+            rule_name: rule_name.to_string(),
+            body_hash: hash.to_string(),
+            domains: domains.into_iter().map(String::from).collect(),
+            snippet: snippet.to_string(),
+        })
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.domains
+            .len()
+            .cmp(&a.domains.len())
+            .then_with(|| (&a.rule_name, &a.body_hash).cmp(&(&b.rule_name, &b.body_hash)))
+    });
+    clusters
+}
+
+fn cluster_id(hash: &str) -> String {
+    hash.chars().take(ID_LENGTH).collect()
+}
+
+/// Print clusters of findings that returned identical content, leaving out those
+/// marked as false positives, optionally for one rule only
+pub fn show_clusters(
+    kind: StoreKind,
+    database: &str,
+    shards: usize,
+    rule: Option<&str>,
+    min_size: usize,
+    format: &str,
+) -> Result<()> {
+    if kind.resolve(database) != StoreKind::Postgres && !Path::new(database).exists() {
+        anyhow::bail!("Results not found: {}", database);
+    }
+
+    let results = store::open_results(kind, database, shards)?;
+    let dismissed: HashSet<(String, String)> = results
+        .false_positives()?
+        .into_iter()
+        .map(|fp| (fp.domain, fp.rule_name))
+        .collect();
+    let mut findings = results.findings()?;
+    findings.retain(|finding| {
+        rule.is_none_or(|rule| finding.rule_name == rule)
+            && !dismissed.contains(&(finding.domain.clone(), finding.rule_name.clone()))
+    });
+    let clusters = find_clusters(&findings, min_size);
+
+    match format.to_lowercase().as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&clusters)?),
+        "text" => print_clusters(&clusters),
+        _ => anyhow::bail!("Unsupported clusters format: {}", format),
+    }
+
+    Ok(())
+}
+
+fn print_clusters(clusters: &[Cluster]) {
+    let findings: usize = clusters.iter().map(|cluster| cluster.domains.len()).sum();
+    println!(
+        "🧬 {} clusters of identical content covering {} findings",
+        clusters.len(),
+        findings
+    );
+    if clusters.is_empty() {
+        return;
+    }
+
+    println!(
+        "\n{:<14} {:<30} {:<8} {:<40} Domains",
+        "Cluster", "Rule", "Size", "Snippet"
+    );
+    for cluster in clusters {
+        let mut domains = cluster
+            .domains
+            .iter()
+            .take(3)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        if cluster.domains.len() > 3 {
+            domains.push_str(&format!(" and {} more", cluster.domains.len() - 3));
+        }
+        println!(
+            "{:<14} {:<30} {:<8} {:<40} {}",
+            cluster.id,
+            db::truncate_string(&cluster.rule_name, 30),
+            cluster.domains.len(),
+            db::truncate_string(&cluster.snippet.replace(['\n', '\r'], " "), 40),
+            domains
+        );
+    }
+}
+
+/// Mark every detected finding whose body hash starts with `id` as a false positive,
+/// optionally only those of one rule, returning how many were marked. Each mark goes
+/// in the shard holding its finding, as `results fp` does.
+pub fn dismiss_cluster(
+    database: &str,
+    shards: usize,
+    id: &str,
+    rule: Option<&str>,
+    skip_scans: bool,
+) -> Result<usize> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("Invalid cluster ID: {}", id);
+    }
+    let files = db::findings_files(database, shards);
+    if !files.iter().all(|path| Path::new(path).exists()) {
+        anyhow::bail!("Results not found: {}", database);
+    }
+    let id = id.to_lowercase();
+    let matches = |finding: &Finding| {
+        finding.detected
+            && rule.is_none_or(|rule| finding.rule_name == rule)
+            && finding
+                .evidence
+                .as_ref()
+                .filter(|evidence| !evidence.truncated)
+                .and_then(|evidence| evidence.body_hash.as_deref())
+                .is_some_and(|hash| hash.starts_with(&id))
+    };
+
+    let mut shard_findings = Vec::new();
+    for path in files {
+        let conn = db::init_db(&path)?;
+        let findings: Vec<Finding> = db::get_all_findings(&conn)?
+            .into_iter()
+            .filter(|finding| matches(finding))
+            .collect();
+        shard_findings.push((conn, findings));
+    }
+
+    let hashes: HashSet<&str> = shard_findings
+        .iter()
+        .flat_map(|(_, findings)| findings)
+        .filter_map(|finding| finding.evidence.as_ref()?.body_hash.as_deref())
+        .collect();
+    if hashes.is_empty() {
+        anyhow::bail!("No cluster matches {}", id);
+    }
+    if hashes.len() > 1 {
+        anyhow::bail!(
+            "{} matches {} clusters; give more of the hash",
+            id,
+            hashes.len()
+        );
+    }
+
+    let mut marked = 0;
+    for (conn, findings) in &shard_findings {
+        for finding in findings {
+            db::mark_false_positive(conn, &finding.domain, &finding.rule_name, skip_scans)?;
+            marked += 1;
+        }
+    }
+    println!(
+        "🙈 Marked {} findings in cluster {} as false positives{}",
+        marked,
+        id,
+        if skip_scans {
+            "; future scans skip them"
+        } else {
+            ""
+        }
+    );
+
+    Ok(marked)
+}
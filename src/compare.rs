@@ -0,0 +1,275 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+
+use crate::db::Finding;
+use crate::store::{self, StoreKind};
+use crate::utils;
+
+/// Where the fields of another scanner's findings are in its JSON output. Fields are
+/// dotted paths into each finding object, such as `info.name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMapping {
+    /// Host the finding is on; unused when the path field holds a full URL
+    pub domain: String,
+
+    /// Path, or full URL, the finding was observed at
+    pub path: String,
+
+    /// What the scanner calls the finding, such as its template ID
+    pub name: Option<String>,
+}
+
+impl FieldMapping {
+    /// The fields of Nuclei's `-jsonl` and `-json-export` output
+    pub fn nuclei() -> Self {
+        Self {
+            domain: "host".to_string(),
+            path: "matched-at".to_string(),
+            name: Some("template-id".to_string()),
+        }
+    }
+}
+
+/// A finding of another scanner, mapped onto what FATT findings are matched by
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExternalFinding {
+    pub domain: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// A path both scanners found on a domain
+#[derive(Debug, Clone, Serialize)]
+pub struct SharedFinding {
+    pub domain: String,
+    pub path: String,
+    /// The FATT rules that found it
+    pub rules: Vec<String>,
+    /// What the other scanner called it
+    pub other: Vec<String>,
+}
+
+/// How FATT's detected findings compare with another scanner's, matched by domain and
+/// path
+#[derive(Debug, Default, Serialize)]
+pub struct Comparison {
+    /// Found by both
+    pub both: Vec<SharedFinding>,
+    /// Found by FATT only
+    pub fatt_only: Vec<Finding>,
+    /// Found by the other scanner only
+    pub other_only: Vec<ExternalFinding>,
+}
+
+impl Comparison {
+    /// Share of the findings on either side found by both, from 0 to 1
+    pub fn overlap(&self) -> f64 {
+        let total = self.both.len() + self.fatt_only.len() + self.other_only.len();
+        if total == 0 {
+            return 0.0;
+        }
+        self.both.len() as f64 / total as f64
+    }
+}
+
+/// The key findings are matched by: the host without its port, and the path without
+/// a trailing slash. A full URL is reduced to its path, and its host is used when
+/// `domain` is empty.
+fn match_key(domain: &str, path: &str) -> Option<(String, String)> {
+    let (domain, path) = match url::Url::parse(path) {
+        Ok(url) if url.has_host() => {
+            let mut path = url.path().to_string();
+            if let Some(query) = url.query() {
+                path = format!("{}?{}", path, query);
+            }
+            (url.host_str().unwrap_or_default().to_string(), path)
+        }
+        _ => (domain.to_string(), path.to_string()),
+    };
+
+    // The domain may itself be a URL or carry a port
+    let domain = match url::Url::parse(&domain) {
+        Ok(url) if url.has_host() => url.host_str().unwrap_or_default().to_string(),
+        _ => utils::split_host_port(&domain).0.to_string(),
+    };
+    let domain = utils::normalize_domain(domain.trim_matches(|c| c == '[' || c == ']'));
+    if domain.is_empty() {
+        return None;
+    }
+
+    let path = match path.trim_end_matches('/') {
+        "" => "/".to_string(),
+        path if path.starts_with('/') => path.to_string(),
+        path => format!("/{}", path),
+    };
+
+    Some((domain, path))
+}
+
+/// A dotted field of a JSON object as text
+fn field<'a>(value: &'a serde_json::Value, path: &str) -> Option<&'a str> {
+    path.split('.')
+        .try_fold(value, |value, key| value.get(key))
+        .and_then(serde_json::Value::as_str)
+}
+
+/// Read another scanner's findings from its JSON output: an array of objects, or one
+/// object per line. Entries without a path, or without a domain when the path isn't a
+/// URL, are skipped.
+pub fn parse_external(text: &str, mapping: &FieldMapping) -> Result<Vec<ExternalFinding>> {
+    let entries: Vec<serde_json::Value> = if text.trim_start().starts_with('[') {
+        serde_json::from_str(text).context("Invalid JSON array")?
+    } else {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .enumerate()
+            .map(|(index, line)| {
+                serde_json::from_str(line).context(format!("Invalid JSON on entry {}", index + 1))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            let path = field(entry, &mapping.path)?;
+            let domain = field(entry, &mapping.domain).unwrap_or_default();
+            let (domain, path) = match_key(domain, path)?;
+            Some(ExternalFinding {
+                domain,
+                path,
+                name: mapping
+                    .name
+                    .as_deref()
+                    .and_then(|name| field(entry, name))
+                    .map(str::to_string),
+            })
+        })
+        .collect())
+}
+
+/// Match detected FATT findings against another scanner's findings by domain and path
+pub fn compare_findings(findings: &[Finding], external: &[ExternalFinding]) -> Comparison {
+    let mut ours: BTreeMap<(String, String), Vec<&Finding>> = BTreeMap::new();
+    for finding in findings.iter().filter(|finding| finding.detected) {
+        if let Some(key) = match_key(&finding.domain, &finding.matched_path) {
+            ours.entry(key).or_default().push(finding);
+        }
+    }
+    let mut theirs: BTreeMap<(String, String), Vec<&ExternalFinding>> = BTreeMap::new();
+    for finding in external {
+        theirs
+            .entry((finding.domain.clone(), finding.path.clone()))
+            .or_default()
+            .push(finding);
+    }
+
+    let mut comparison = Comparison::default();
+    for (key, findings) in ours {
+        match theirs.remove(&key) {
+            Some(external) => {
+                let (domain, path) = key;
+                let mut other: Vec<String> = external
+                    .iter()
+                    .filter_map(|finding| finding.name.clone())
+                    .collect();
+                other.sort();
+                other.dedup();
+                comparison.both.push(SharedFinding {
+                    domain,
+                    path,
+                    rules: findings
+                        .iter()
+                        .map(|finding| finding.rule_name.clone())
+                        .collect(),
+                    other,
+                });
+            }
+            None => comparison.fatt_only.extend(findings.into_iter().cloned()),
+        }
+    }
+    comparison.other_only = theirs.into_values().flatten().cloned().collect();
+
+    comparison
+}
+
+/// Print how the findings in a store compare with another scanner's output. Findings
+/// marked as false positives are left out.
+pub fn compare_results(
+    kind: StoreKind,
+    database: &str,
+    shards: usize,
+    other_file: &str,
+    mapping: &FieldMapping,
+    format: &str,
+) -> Result<()> {
+    let text = fs::read_to_string(other_file).context(format!("Failed to read {}", other_file))?;
+    let external =
+        parse_external(&text, mapping).context(format!("Failed to parse {}", other_file))?;
+
+    let results = store::open_results(kind, database, shards)?;
+    let false_positives: HashSet<(String, String)> = results
+        .false_positives()?
+        .into_iter()
+        .map(|fp| (fp.domain, fp.rule_name))
+        .collect();
+    let findings: Vec<Finding> = results
+        .findings()?
+        .into_iter()
+        .filter(|finding| !store::is_false_positive(&false_positives, finding))
+        .collect();
+    let comparison = compare_findings(&findings, &external);
+
+    match format.to_lowercase().as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&comparison)?),
+        "text" => print_comparison(database, other_file, &comparison),
+        _ => anyhow::bail!("Unsupported compare format: {}", format),
+    }
+
+    Ok(())
+}
+
+fn print_comparison(database: &str, other_file: &str, comparison: &Comparison) {
+    println!("🆚 {} ↔ {}", store::redact_url(database), other_file);
+
+    println!("\n🤝 Both ({}):", comparison.both.len());
+    for shared in &comparison.both {
+        println!(
+            "  {:<30} {:<30} {:<20} {}",
+            shared.domain,
+            shared.path,
+            shared.rules.join(", "),
+            shared.other.join(", ")
+        );
+    }
+
+    println!("\n🔵 FATT only ({}):", comparison.fatt_only.len());
+    for finding in &comparison.fatt_only {
+        println!(
+            "  {:<30} {:<30} {}",
+            finding.domain, finding.matched_path, finding.rule_name
+        );
+    }
+
+    println!("\n🟠 Other scanner only ({}):", comparison.other_only.len());
+    for finding in &comparison.other_only {
+        println!(
+            "  {:<30} {:<30} {}",
+            finding.domain,
+            finding.path,
+            finding.name.as_deref().unwrap_or("-")
+        );
+    }
+
+    println!(
+        "\n{} in both, {} FATT only, {} other only ({:.0}% overlap)",
+        comparison.both.len(),
+        comparison.fatt_only.len(),
+        comparison.other_only.len(),
+        comparison.overlap() * 100.0
+    );
+}
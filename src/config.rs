@@ -1,5 +1,32 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::brute;
+use crate::db::SplitBy;
+use crate::email::EmailConfig;
+use crate::exit::Failure;
+use crate::expand;
+use crate::incident::IncidentConfig;
+use crate::notify::WebhookConfig;
+use crate::profile::ScanProfile;
+use crate::redact::RedactionPolicy;
+use crate::retention;
+use crate::risk::RiskWeights;
+use crate::rules;
+use crate::scanner::{
+    DEFAULT_INTERESTING_STATUS, DEFAULT_MAX_BODY_BYTES, DEFAULT_RULE_CONCURRENCY,
+};
+use crate::store::{self, StoreKind};
+use crate::throttle::ThrottlePolicy;
+use crate::timeouts::Timeouts;
+use crate::upload::{S3Config, S3Url};
+use crate::utils::RetryPolicy;
+use crate::window::ScanWindow;
 
 /// Configuration for scanning
 #[derive(Debug, Clone)]
@@ -7,12 +34,33 @@ pub struct ScanConfig {
     /// Path to input file with domains, one per line
     pub input_file: String,
 
+    /// Tag carried by findings for domains from `input_file`
+    pub input_tag: Option<String>,
+
+    /// Additional domain files scanned after the primary one
+    pub extra_inputs: Vec<InputSource>,
+
     /// Path to rules file
     pub rules_file: String,
 
+    /// Additional rules files merged into the primary one
+    pub extra_rules_files: Vec<String>,
+
+    /// Tuning preset the scan was configured with, recorded with the scan session
+    pub profile: Option<ScanProfile>,
+
+    /// Project the scan is run for, recorded with the scan session and its findings
+    pub project: Option<String>,
+
     /// Number of concurrent scanners
     pub concurrency: usize,
 
+    /// Capacity of each queue between scan pipeline stages
+    pub queue_capacity: usize,
+
+    /// Threads matching signatures against response bodies (0 = one per core)
+    pub match_threads: usize,
+
     /// Verbosity level: 0=error, 1=warn, 2=info, 3=debug, 4=trace
     pub verbosity: u8,
 
@@ -22,18 +70,50 @@ pub struct ScanConfig {
     /// Path to output file
     pub output_file: Option<String>,
 
-    /// Path to database file
+    /// Path to database file (a directory for the JSONL store)
     pub db_path: String,
 
+    /// Working directory holding the scan's results, evidence, logs, state and
+    /// summary; overrides `db_path`
+    pub workdir: Option<String>,
+
+    /// Backend results are stored in
+    pub store: StoreKind,
+
+    /// Number of SQLite files findings are sharded across by domain hash (1 disables)
+    pub db_shards: usize,
+
+    /// Put SQLite databases in WAL mode with relaxed syncing; off keeps SQLite's
+    /// rollback journal, for filesystems WAL doesn't work on
+    pub db_tuning: bool,
+
+    /// Purge findings last scanned longer ago than this (e.g. `30d`) after every scan
+    pub purge_older_than: Option<String>,
+
+    /// Only purge findings where nothing was detected
+    pub purge_not_detected: bool,
+
     /// DNS timeout in seconds
     pub dns_timeout: u64,
 
-    /// HTTP timeout in seconds
+    /// Total HTTP request timeout in seconds, body included
     pub http_timeout: u64,
 
     /// TCP connection timeout in seconds
     pub connect_timeout: u64,
 
+    /// TLS handshake timeout in seconds, once connected
+    pub tls_timeout: u64,
+
+    /// Time to wait for the response headers in seconds, once connected
+    pub first_byte_timeout: u64,
+
+    /// Most bytes of a response body downloaded and matched
+    pub max_body_bytes: usize,
+
+    /// Error statuses that show a rule's path exists but is protected, e.g. 401 and 403
+    pub interesting_status: Vec<u16>,
+
     /// Size of DNS cache
     pub dns_cache_size: usize,
 
@@ -43,27 +123,320 @@ pub struct ScanConfig {
     /// Only perform DNS resolution (no HTTP requests)
     pub dns_only: bool,
 
+    /// Brute force common subdomains of bare apex domains in the input, scanning the
+    /// ones that resolve in the same run
+    pub dns_brute: bool,
+
+    /// Most DNS queries per second the subdomain brute force sends
+    pub dns_brute_rate: u32,
+
+    /// Shell command expanding each input entry into the targets it stands for
+    pub expand_command: Option<String>,
+
+    /// Most input entries expanded at once
+    pub expand_concurrency: usize,
+
     /// Verbose mode
     pub verbose: bool,
+
+    /// Path to expected findings (accepted risk) file
+    pub expected_file: Option<String>,
+
+    /// Canary domains to check before the scan, aborting it if they misbehave
+    pub canaries_file: Option<String>,
+
+    /// Severity weights used to compute risk scores
+    pub risk_weights: RiskWeights,
+
+    /// Change factor in per-rule match counts that is reported as an anomaly
+    pub anomaly_factor: f64,
+
+    /// Minimum match count (current or baseline) before a rule is checked for anomalies
+    pub anomaly_min_count: usize,
+
+    /// Number of previous scans averaged into the anomaly baseline
+    pub anomaly_baseline_scans: usize,
+
+    /// Number of retries for failed HTTP requests
+    pub retries: u32,
+
+    /// Base backoff between retries in milliseconds (doubles on each retry)
+    pub retry_backoff_ms: u64,
+
+    /// Most random delay before each request in milliseconds, spreading requests out
+    /// (0 disables)
+    pub jitter_ms: u64,
+
+    /// URL schemes to check each domain over
+    pub schemes: Vec<String>,
+
+    /// Ports to check each domain on (empty means the scheme's default port)
+    pub ports: Vec<u16>,
+
+    /// Proxy URL (http://, https:// or socks5://) for all scan traffic
+    pub proxy: Option<String>,
+
+    /// Proxy credentials as `user:password`
+    pub proxy_auth: Option<String>,
+
+    /// Consecutive failed checks before a domain's remaining checks are skipped (0 disables)
+    pub circuit_breaker: usize,
+
+    /// Rule checks in flight against one domain at a time
+    pub rule_concurrency: usize,
+
+    /// Count response header bytes in the network statistics
+    pub capture_headers: bool,
+
+    /// File of User-Agent strings to rotate through, one per line
+    pub user_agent_file: Option<String>,
+
+    /// Keep one User-Agent per domain instead of rotating on every check
+    pub sticky_user_agent: bool,
+
+    /// Randomize TLS fingerprint and header order to get past naive bot filters
+    pub evasion: bool,
+
+    /// Confirmation that the targets may be tested; evasion is refused without it,
+    /// since it only exists to stop bot filters hiding exposures on permitted assets
+    pub authorized_testing: bool,
+
+    /// Address (`host:port` or `:port`) to serve a live Server-Sent Events stream of
+    /// findings and progress on
+    pub stream_listen: Option<String>,
+
+    /// Token subscribers of the event stream must send, required unless it only
+    /// listens on localhost
+    pub stream_token: Option<String>,
+
+    /// Reload the rules files when they change during the scan
+    pub hot_reload_rules: bool,
+
+    /// Daily window (`HH:MM-HH:MM [ZONE]`) outside which the scan sends no traffic
+    pub scan_window: Option<String>,
+
+    /// Most concurrent requests to domains under one top-level domain (0 is unlimited)
+    pub throttle_tld: usize,
+
+    /// Most concurrent requests to one autonomous system (0 is unlimited)
+    pub throttle_asn: usize,
+
+    /// Most concurrent requests to one IPv4 /24 or IPv6 /48 (0 is unlimited)
+    pub throttle_subnet: usize,
+
+    /// IP-to-ASN ranges (iptoasn TSV) used by `throttle_asn`
+    pub asn_file: Option<String>,
+
+    /// Patterns of secrets masked in evidence, logs and exports, on top of the
+    /// built-in ones
+    pub redact_patterns: Vec<String>,
+
+    /// Result exports written after every scan
+    pub sinks: Vec<SinkConfig>,
+
+    /// Webhooks notified after every scan
+    pub webhooks: Vec<WebhookConfig>,
+
+    /// Emails alerting on severe findings, or with a digest after every scan
+    pub emails: Vec<EmailConfig>,
+
+    /// Incident management services alerted on severe findings
+    pub incidents: Vec<IncidentConfig>,
+}
+
+/// A file of domains to scan, optionally tagged so its findings can be told apart
+/// from those of other inputs in the same scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct InputSource {
+    /// File with one domain per line
+    pub file: String,
+
+    /// Tag recorded with every finding for a domain from this file
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+impl InputSource {
+    pub fn new(file: &str, tag: Option<&str>) -> Self {
+        Self {
+            file: file.to_string(),
+            tag: tag.map(str::to_string),
+        }
+    }
+
+    /// Check that the tag is usable in export file names
+    pub fn validate(&self) -> Result<()> {
+        if let Some(tag) = &self.tag {
+            if !is_valid_tag(tag) {
+                anyhow::bail!(
+                    "Invalid input tag '{}': use letters, digits, '-', '_' or '.'",
+                    tag
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+impl FromStr for InputSource {
+    type Err = anyhow::Error;
+
+    /// Parse `FILE` or `FILE:TAG`; a colon followed by anything but a valid tag (such as
+    /// a Windows drive separator) is part of the file name
+    fn from_str(s: &str) -> Result<Self> {
+        let source = match s.rsplit_once(':') {
+            Some((file, tag)) if !file.is_empty() && is_valid_tag(tag) => {
+                InputSource::new(file, Some(tag))
+            }
+            _ => InputSource::new(s, None),
+        };
+        if source.file.is_empty() {
+            anyhow::bail!("Input file must not be empty");
+        }
+
+        Ok(source)
+    }
+}
+
+impl fmt::Display for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.tag {
+            Some(tag) => write!(f, "{}:{}", self.file, tag),
+            None => write!(f, "{}", self.file),
+        }
+    }
+}
+
+/// A results export written after every scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SinkConfig {
+    /// Export format (csv, json or jsonl)
+    pub format: String,
+
+    /// Output file path, or an `s3://bucket/key` object to upload the export to
+    pub path: String,
+
+    /// Write one file per group of findings instead of a single file
+    #[serde(default)]
+    pub split_by: Option<SplitBy>,
+
+    /// Endpoint and credentials for an `s3://` path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s3: Option<S3Config>,
+}
+
+impl SinkConfig {
+    /// An export uploaded to `target` after every scan, in the format of the key's
+    /// extension
+    pub fn upload(target: &S3Url, s3: S3Config) -> Result<Self> {
+        let format = Path::new(&target.key)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        if !matches!(format.as_str(), "csv" | "json" | "jsonl" | "ndjson") {
+            anyhow::bail!(
+                "Can't tell the export format of {} (expected a .csv, .json or .jsonl key)",
+                target
+            );
+        }
+        Ok(SinkConfig {
+            format,
+            path: target.to_string(),
+            split_by: None,
+            s3: Some(s3),
+        })
+    }
+
+    /// Object the export is uploaded to, if it isn't written to a local file
+    pub fn upload_target(&self) -> Result<Option<S3Url>> {
+        if !S3Url::is_s3(&self.path) {
+            return Ok(None);
+        }
+        self.path.parse().map(Some)
+    }
 }
 
 impl Default for ScanConfig {
     fn default() -> Self {
         Self {
             input_file: "domains.txt".to_string(),
+            input_tag: None,
+            extra_inputs: Vec::new(),
             rules_file: "rules.yaml".to_string(),
+            extra_rules_files: Vec::new(),
+            profile: None,
+            project: None,
             concurrency: 10,
+            queue_capacity: 1000,
+            match_threads: 0,
             verbosity: 0,
             distributed: false,
             output_file: Some("output.txt".to_string()),
             db_path: "results.sqlite".to_string(),
+            workdir: None,
+            store: StoreKind::Sqlite,
+            db_shards: 1,
+            db_tuning: true,
+            purge_older_than: None,
+            purge_not_detected: false,
             dns_timeout: 5,
             http_timeout: 10,
             connect_timeout: 5,
+            tls_timeout: 5,
+            first_byte_timeout: 10,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            interesting_status: DEFAULT_INTERESTING_STATUS.to_vec(),
             dns_cache_size: 10000,
             quiet: false,
             dns_only: false,
+            dns_brute: false,
+            dns_brute_rate: brute::DEFAULT_RATE,
+            expand_command: None,
+            expand_concurrency: expand::DEFAULT_CONCURRENCY,
             verbose: false,
+            expected_file: None,
+            canaries_file: None,
+            risk_weights: RiskWeights::default(),
+            anomaly_factor: 10.0,
+            anomaly_min_count: 5,
+            anomaly_baseline_scans: 5,
+            retries: 2,
+            retry_backoff_ms: 250,
+            jitter_ms: 0,
+            schemes: vec!["http".to_string()],
+            ports: Vec::new(),
+            proxy: None,
+            proxy_auth: None,
+            circuit_breaker: 10,
+            rule_concurrency: DEFAULT_RULE_CONCURRENCY,
+            capture_headers: false,
+            user_agent_file: None,
+            sticky_user_agent: false,
+            evasion: false,
+            authorized_testing: false,
+            stream_listen: None,
+            stream_token: None,
+            hot_reload_rules: false,
+            scan_window: None,
+            throttle_tld: 0,
+            throttle_asn: 0,
+            throttle_subnet: 0,
+            asn_file: None,
+            redact_patterns: Vec::new(),
+            sinks: Vec::new(),
+            webhooks: Vec::new(),
+            emails: Vec::new(),
+            incidents: Vec::new(),
         }
     }
 }
@@ -74,32 +447,262 @@ impl ScanConfig {
     pub fn new(input_file: String, rules_file: String) -> Self {
         Self {
             input_file,
+            input_tag: None,
+            extra_inputs: Vec::new(),
             rules_file,
+            extra_rules_files: Vec::new(),
+            profile: None,
+            project: None,
             concurrency: 50,
+            queue_capacity: 1000,
+            match_threads: 0,
             verbosity: 2, // info level
             distributed: false,
             output_file: None,
             db_path: "data/fatt.db".to_string(),
+            workdir: None,
+            store: StoreKind::Sqlite,
+            db_shards: 1,
+            db_tuning: true,
+            purge_older_than: None,
+            purge_not_detected: false,
             dns_timeout: 5,
             http_timeout: 10,
             connect_timeout: 5,
+            tls_timeout: 5,
+            first_byte_timeout: 10,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            interesting_status: DEFAULT_INTERESTING_STATUS.to_vec(),
             dns_cache_size: 10000,
             quiet: false,
             dns_only: false,
+            dns_brute: false,
+            dns_brute_rate: brute::DEFAULT_RATE,
+            expand_command: None,
+            expand_concurrency: expand::DEFAULT_CONCURRENCY,
             verbose: false,
+            expected_file: None,
+            canaries_file: None,
+            risk_weights: RiskWeights::default(),
+            anomaly_factor: 10.0,
+            anomaly_min_count: 5,
+            anomaly_baseline_scans: 5,
+            retries: 2,
+            retry_backoff_ms: 250,
+            jitter_ms: 0,
+            schemes: vec!["http".to_string()],
+            ports: Vec::new(),
+            proxy: None,
+            proxy_auth: None,
+            circuit_breaker: 10,
+            rule_concurrency: DEFAULT_RULE_CONCURRENCY,
+            capture_headers: false,
+            user_agent_file: None,
+            sticky_user_agent: false,
+            evasion: false,
+            authorized_testing: false,
+            stream_listen: None,
+            stream_token: None,
+            hot_reload_rules: false,
+            scan_window: None,
+            throttle_tld: 0,
+            throttle_asn: 0,
+            throttle_subnet: 0,
+            asn_file: None,
+            redact_patterns: Vec::new(),
+            sinks: Vec::new(),
+            webhooks: Vec::new(),
+            emails: Vec::new(),
+            incidents: Vec::new(),
+        }
+    }
+
+    /// Every domain file to scan, the primary one first
+    pub fn inputs(&self) -> Vec<InputSource> {
+        let mut inputs = vec![InputSource {
+            file: self.input_file.clone(),
+            tag: self.input_tag.clone(),
+        }];
+        inputs.extend(self.extra_inputs.iter().cloned());
+        inputs
+    }
+
+    /// Retry policy for HTTP checks
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::new(self.retries, self.retry_backoff_ms)
+    }
+
+    /// Concurrency limits per TLD, ASN and subnet
+    pub fn throttle_policy(&self) -> ThrottlePolicy {
+        ThrottlePolicy {
+            per_tld: self.throttle_tld,
+            per_asn: self.throttle_asn,
+            per_subnet: self.throttle_subnet,
+        }
+    }
+
+    /// Time budget of each phase of a request
+    pub fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            dns: Duration::from_secs(self.dns_timeout),
+            connect: Duration::from_secs(self.connect_timeout),
+            tls: Duration::from_secs(self.tls_timeout),
+            first_byte: Duration::from_secs(self.first_byte_timeout),
+            total: Duration::from_secs(self.http_timeout),
         }
     }
 
+    /// Backend the results are stored in, given the database setting
+    pub fn store_kind(&self) -> StoreKind {
+        self.store.resolve(&self.db_path)
+    }
+
+    /// Secrets masked in what the scan writes: the built-in patterns and `redact_patterns`
+    pub fn redaction_policy(&self) -> Result<RedactionPolicy> {
+        RedactionPolicy::new(&self.redact_patterns)
+    }
+
     /// Validate the configuration
     pub fn validate(&self) -> Result<()> {
-        // Check if input file exists
-        if !Path::new(&self.input_file).exists() {
-            anyhow::bail!("input file does not exist: {}", self.input_file);
+        // Check if input files exist
+        for input in self.inputs() {
+            if !Path::new(&input.file).exists() {
+                return Err(
+                    Failure::Input.because(format!("input file does not exist: {}", input.file))
+                );
+            }
+            input.validate()?;
         }
 
         // Check if rules file exists
         if !Path::new(&self.rules_file).exists() {
-            anyhow::bail!("Rules file does not exist: {}", self.rules_file);
+            return Err(
+                Failure::Rules.because(format!("Rules file does not exist: {}", self.rules_file))
+            );
+        }
+
+        for rules_file in &self.extra_rules_files {
+            if !Path::new(rules_file).exists() {
+                return Err(
+                    Failure::Rules.because(format!("Rules file does not exist: {}", rules_file))
+                );
+            }
+        }
+
+        for sink in &self.sinks {
+            if !matches!(
+                sink.format.to_lowercase().as_str(),
+                "csv" | "json" | "jsonl" | "ndjson"
+            ) {
+                anyhow::bail!("Unsupported sink format: {}", sink.format);
+            }
+            sink.upload_target()?;
+        }
+        for webhook in &self.webhooks {
+            webhook.validate()?;
+        }
+        for email in &self.emails {
+            email.validate()?;
+        }
+        for incident in &self.incidents {
+            incident.validate()?;
+        }
+
+        // Check if expected findings file exists
+        if let Some(expected_file) = &self.expected_file {
+            if !Path::new(expected_file).exists() {
+                anyhow::bail!("Expected findings file does not exist: {}", expected_file);
+            }
+        }
+
+        // Check if the canaries file exists
+        if let Some(canaries_file) = &self.canaries_file {
+            if !Path::new(canaries_file).exists() {
+                anyhow::bail!("Canaries file does not exist: {}", canaries_file);
+            }
+        }
+
+        // Check if the User-Agent file exists
+        if let Some(user_agent_file) = &self.user_agent_file {
+            if !Path::new(user_agent_file).exists() {
+                anyhow::bail!("User-Agent file does not exist: {}", user_agent_file);
+            }
+        }
+
+        // ASN throttling needs addresses mapped to their ASNs
+        if let Some(asn_file) = &self.asn_file {
+            if !Path::new(asn_file).exists() {
+                anyhow::bail!("ASN file does not exist: {}", asn_file);
+            }
+        }
+        if self.throttle_asn > 0 && self.asn_file.is_none() {
+            anyhow::bail!("--throttle-asn requires --asn-file to look up each address's ASN");
+        }
+
+        if self.dns_brute && self.dns_brute_rate == 0 {
+            anyhow::bail!("Invalid DNS brute force rate: must be greater than 0");
+        }
+        if self
+            .expand_command
+            .as_deref()
+            .is_some_and(|command| command.trim().is_empty())
+        {
+            anyhow::bail!("The expansion command must not be empty");
+        }
+        if self.expand_concurrency == 0 {
+            anyhow::bail!("Invalid expansion concurrency: must be greater than 0");
+        }
+        if let Some(window) = &self.scan_window {
+            window.parse::<ScanWindow>()?;
+        }
+
+        // Evasion is only for targets the operator has permission to test
+        if self.evasion && !self.authorized_testing {
+            anyhow::bail!(
+                "--evasion requires --authorized-testing to confirm you have permission to test the targets"
+            );
+        }
+
+        // Check result store settings
+        if self.db_shards == 0 {
+            anyhow::bail!("Invalid shard count: must be greater than 0");
+        }
+        if self.store == StoreKind::Postgres && !store::is_postgres_url(&self.db_path) {
+            anyhow::bail!("The postgres result store needs a postgres:// database URL");
+        }
+        if self.db_shards > 1 && self.store_kind() != StoreKind::Sqlite {
+            anyhow::bail!("Sharding is only supported by the sqlite result store");
+        }
+        if let Some(age) = &self.purge_older_than {
+            retention::parse_age(age)?;
+        } else if self.purge_not_detected {
+            anyhow::bail!("--purge-not-detected requires --purge-older-than");
+        }
+
+        // Check anomaly detection settings
+        if self.anomaly_factor <= 1.0 {
+            anyhow::bail!("Invalid anomaly factor: must be greater than 1");
+        }
+
+        // Check scheme/port matrix
+        if self.schemes.is_empty() {
+            anyhow::bail!("At least one URL scheme must be configured");
+        }
+        if let Some(scheme) = self
+            .schemes
+            .iter()
+            .find(|s| s.as_str() != "http" && s.as_str() != "https")
+        {
+            anyhow::bail!("Unsupported URL scheme: {}", scheme);
+        }
+
+        // Check proxy URL
+        if let Some(proxy) = &self.proxy {
+            let url = url::Url::parse(proxy)
+                .map_err(|e| anyhow::anyhow!("Invalid proxy URL {}: {}", proxy, e))?;
+            if !["http", "https", "socks5", "socks5h"].contains(&url.scheme()) {
+                anyhow::bail!("Unsupported proxy scheme: {}", url.scheme());
+            }
         }
 
         // Check concurrency value
@@ -107,6 +710,29 @@ impl ScanConfig {
             anyhow::bail!("Invalid concurrency value: must be greater than 0");
         }
 
+        if self.rule_concurrency == 0 {
+            anyhow::bail!("Invalid rule concurrency: must be greater than 0");
+        }
+        let timeouts = [
+            ("DNS", self.dns_timeout),
+            ("connect", self.connect_timeout),
+            ("TLS", self.tls_timeout),
+            ("first byte", self.first_byte_timeout),
+            ("HTTP", self.http_timeout),
+        ];
+        if let Some((name, _)) = timeouts.iter().find(|(_, secs)| *secs == 0) {
+            anyhow::bail!("Invalid {} timeout: must be greater than 0", name);
+        }
+        if self.max_body_bytes == 0 {
+            anyhow::bail!("Invalid max body bytes: must be greater than 0");
+        }
+        if self.queue_capacity == 0 {
+            anyhow::bail!("Invalid queue capacity: must be greater than 0");
+        }
+        rules::validate_interesting_status(&self.interesting_status)
+            .context("Invalid interesting status")?;
+        self.redaction_policy()?;
+
         Ok(())
     }
 
@@ -116,21 +742,96 @@ impl ScanConfig {
         tracing::event!(tracing::Level::INFO, message = "Configuration:");
 
         // Log each configuration value as a separate event for better test capturing
-        tracing::event!(
-            tracing::Level::INFO,
-            input_file = %self.input_file,
-            message = format!("  input file: {}", self.input_file)
-        );
+        for input in self.inputs() {
+            tracing::event!(
+                tracing::Level::INFO,
+                input_file = %input.file,
+                input_tag = ?input.tag,
+                message = format!("  input file: {}", input)
+            );
+        }
         tracing::event!(
             tracing::Level::INFO,
             rules_file = %self.rules_file,
             message = format!("  rules file: {}", self.rules_file)
         );
+        if let Some(workdir) = &self.workdir {
+            tracing::event!(
+                tracing::Level::INFO,
+                workdir = %workdir,
+                message = format!("  working directory: {}", workdir)
+            );
+        }
+        if self.dns_brute {
+            tracing::event!(
+                tracing::Level::INFO,
+                dns_brute_rate = self.dns_brute_rate,
+                message = format!("  DNS brute force: {}/s", self.dns_brute_rate)
+            );
+        }
+        if let Some(expand_command) = &self.expand_command {
+            tracing::event!(
+                tracing::Level::INFO,
+                expand_command = %expand_command,
+                message = format!("  target expansion: {}", expand_command)
+            );
+        }
+        if self.hot_reload_rules {
+            tracing::event!(
+                tracing::Level::INFO,
+                hot_reload_rules = true,
+                message = "  hot-reload rules: enabled"
+            );
+        }
+        if let Some(stream_listen) = &self.stream_listen {
+            tracing::event!(
+                tracing::Level::INFO,
+                stream_listen = %stream_listen,
+                message = format!("  event stream: {}", stream_listen)
+            );
+        }
+        if let Some(scan_window) = &self.scan_window {
+            tracing::event!(
+                tracing::Level::INFO,
+                scan_window = %scan_window,
+                message = format!("  scan window: {}", scan_window)
+            );
+        }
+        if let Some(profile) = self.profile {
+            tracing::event!(
+                tracing::Level::INFO,
+                profile = %profile,
+                message = format!("  profile: {}", profile)
+            );
+        }
+        if let Some(project) = &self.project {
+            tracing::event!(
+                tracing::Level::INFO,
+                project = %project,
+                message = format!("  project: {}", project)
+            );
+        }
         tracing::event!(
             tracing::Level::INFO,
             concurrency = self.concurrency,
             message = format!("  concurrency: {}", self.concurrency)
         );
+        tracing::event!(
+            tracing::Level::INFO,
+            queue_capacity = self.queue_capacity,
+            message = format!("  queue capacity: {}", self.queue_capacity)
+        );
+        tracing::event!(
+            tracing::Level::INFO,
+            match_threads = self.match_threads,
+            message = format!(
+                "  match threads: {}",
+                match self.match_threads {
+                    0 => "auto".to_string(),
+                    threads => threads.to_string(),
+                }
+            )
+        );
         tracing::event!(
             tracing::Level::INFO,
             dns_timeout = self.dns_timeout,
@@ -139,13 +840,30 @@ impl ScanConfig {
         tracing::event!(
             tracing::Level::INFO,
             http_timeout = self.http_timeout,
-            message = format!("  HTTP timeout: {}s", self.http_timeout)
+            message = format!("  HTTP timeout: {}s total", self.http_timeout)
         );
         tracing::event!(
             tracing::Level::INFO,
             connect_timeout = self.connect_timeout,
-            message = format!("  connect timeout: {}s", self.connect_timeout)
+            tls_timeout = self.tls_timeout,
+            first_byte_timeout = self.first_byte_timeout,
+            message = format!(
+                "  connect timeout: {}s, TLS handshake: {}s, first byte: {}s",
+                self.connect_timeout, self.tls_timeout, self.first_byte_timeout
+            )
         );
+        tracing::event!(
+            tracing::Level::INFO,
+            max_body_bytes = self.max_body_bytes,
+            message = format!("  max body bytes: {}", self.max_body_bytes)
+        );
+        if !self.interesting_status.is_empty() {
+            tracing::event!(
+                tracing::Level::INFO,
+                interesting_status = ?self.interesting_status,
+                message = format!("  interesting statuses: {:?}", self.interesting_status)
+            );
+        }
         tracing::event!(
             tracing::Level::INFO,
             verbosity = self.verbosity,
@@ -163,9 +881,35 @@ impl ScanConfig {
         );
         tracing::event!(
             tracing::Level::INFO,
-            db_path = %self.db_path,
-            message = format!("  database: {}", self.db_path)
+            db_path = %store::redact_url(&self.db_path),
+            message = format!("  database: {}", store::redact_url(&self.db_path))
         );
+        tracing::event!(
+            tracing::Level::INFO,
+            store = %self.store_kind(),
+            db_shards = self.db_shards,
+            message = format!(
+                "  result store: {} ({} shards)",
+                self.store_kind(),
+                self.db_shards
+            )
+        );
+        if let Some(age) = &self.purge_older_than {
+            tracing::event!(
+                tracing::Level::INFO,
+                purge_older_than = %age,
+                purge_not_detected = self.purge_not_detected,
+                message = format!(
+                    "  purge after scan: {} findings older than {}",
+                    if self.purge_not_detected {
+                        "not-detected"
+                    } else {
+                        "all"
+                    },
+                    age
+                )
+            );
+        }
         tracing::event!(
             tracing::Level::INFO,
             dns_cache_size = self.dns_cache_size,
@@ -186,6 +930,108 @@ impl ScanConfig {
             verbose = self.verbose,
             message = format!("  verbose: {}", self.verbose)
         );
+        tracing::event!(
+            tracing::Level::INFO,
+            expected_file = ?self.expected_file,
+            message = format!("  expected findings: {:?}", self.expected_file)
+        );
+        tracing::event!(
+            tracing::Level::INFO,
+            canaries_file = ?self.canaries_file,
+            message = format!("  canaries: {:?}", self.canaries_file)
+        );
+        tracing::event!(
+            tracing::Level::INFO,
+            risk_weights = ?self.risk_weights,
+            message = format!("  risk weights: {:?}", self.risk_weights)
+        );
+        tracing::event!(
+            tracing::Level::INFO,
+            anomaly_factor = self.anomaly_factor,
+            message = format!(
+                "  anomaly detection: {}x over {} scans (min {} matches)",
+                self.anomaly_factor, self.anomaly_baseline_scans, self.anomaly_min_count
+            )
+        );
+        tracing::event!(
+            tracing::Level::INFO,
+            retries = self.retries,
+            message = format!(
+                "  retries: {} (backoff {}ms)",
+                self.retries, self.retry_backoff_ms
+            )
+        );
+        tracing::event!(
+            tracing::Level::INFO,
+            jitter_ms = self.jitter_ms,
+            message = format!("  jitter: up to {}ms before each request", self.jitter_ms)
+        );
+        tracing::event!(
+            tracing::Level::INFO,
+            schemes = ?self.schemes,
+            ports = ?self.ports,
+            message = format!("  targets: schemes {:?}, ports {:?}", self.schemes, self.ports)
+        );
+        // Never log proxy credentials
+        let proxy = self.proxy.as_deref().map(redact_url_credentials);
+        tracing::event!(
+            tracing::Level::INFO,
+            proxy = ?proxy,
+            message = format!("  proxy: {:?}", proxy)
+        );
+        tracing::event!(
+            tracing::Level::INFO,
+            circuit_breaker = self.circuit_breaker,
+            message = format!(
+                "  circuit breaker: {} consecutive failures",
+                self.circuit_breaker
+            )
+        );
+        tracing::event!(
+            tracing::Level::INFO,
+            rule_concurrency = self.rule_concurrency,
+            message = format!("  rule checks per domain: {}", self.rule_concurrency)
+        );
+        tracing::event!(
+            tracing::Level::INFO,
+            user_agent_file = ?self.user_agent_file,
+            sticky_user_agent = self.sticky_user_agent,
+            message = format!(
+                "  user agents: {:?} (sticky per domain: {})",
+                self.user_agent_file, self.sticky_user_agent
+            )
+        );
+        if !self.throttle_policy().is_unlimited() {
+            tracing::event!(
+                tracing::Level::INFO,
+                throttle_tld = self.throttle_tld,
+                throttle_asn = self.throttle_asn,
+                throttle_subnet = self.throttle_subnet,
+                message = format!(
+                    "  throttle: {} per TLD, {} per ASN, {} per subnet (0 is unlimited)",
+                    self.throttle_tld, self.throttle_asn, self.throttle_subnet
+                )
+            );
+        }
+        tracing::event!(
+            tracing::Level::INFO,
+            evasion = self.evasion,
+            message = format!("  evasion: {}", self.evasion)
+        );
+        tracing::event!(
+            tracing::Level::INFO,
+            sinks = self.sinks.len(),
+            webhooks = self.webhooks.len(),
+            emails = self.emails.len(),
+            incidents = self.incidents.len(),
+            message = format!(
+                "  outputs: {} sinks, {} webhooks, {} emails, {} incident services",
+                self.sinks.len(),
+                self.webhooks.len(),
+                self.emails.len(),
+                self.incidents.len()
+            )
+        );
 
         tracing::event!(
             tracing::Level::DEBUG,
@@ -193,3 +1039,169 @@ impl ScanConfig {
         );
     }
 }
+/// Builds a [`ScanConfig`] from the settings embedders commonly change, leaving the
+/// rest at their defaults
+// Only library embedders use the builder
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct ScanConfigBuilder {
+    config: ScanConfig,
+}
+
+#[allow(dead_code)]
+impl ScanConfig {
+    /// Start from the default configuration
+    pub fn builder() -> ScanConfigBuilder {
+        ScanConfigBuilder::default()
+    }
+}
+
+#[allow(dead_code)]
+impl ScanConfigBuilder {
+    /// File with one domain per line
+    pub fn input_file(mut self, path: impl Into<String>) -> Self {
+        self.config.input_file = path.into();
+        self
+    }
+
+    /// Another file of domains, with the tag its findings are recorded with
+    pub fn extra_input(mut self, path: &str, tag: Option<&str>) -> Self {
+        self.config.extra_inputs.push(InputSource::new(path, tag));
+        self
+    }
+
+    /// Rules file to scan with
+    pub fn rules_file(mut self, path: impl Into<String>) -> Self {
+        self.config.rules_file = path.into();
+        self
+    }
+
+    /// Another rules file, merged with the first
+    pub fn extra_rules_file(mut self, path: impl Into<String>) -> Self {
+        self.config.extra_rules_files.push(path.into());
+        self
+    }
+
+    /// Domains scanned at once
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.config.concurrency = concurrency;
+        self
+    }
+
+    /// Tuning preset setting concurrency, jitter, retries, timeouts and throttling;
+    /// settings changed after it override its values
+    pub fn profile(mut self, profile: ScanProfile) -> Self {
+        profile.apply(&mut self.config);
+        self
+    }
+
+    /// Project the scan's session and findings are labelled with
+    pub fn project(mut self, project: impl Into<String>) -> Self {
+        self.config.project = Some(project.into());
+        self
+    }
+
+    /// Database file, JSONL directory or `postgres://` URL results are stored in
+    pub fn database(mut self, database: impl Into<String>) -> Self {
+        self.config.db_path = database.into();
+        self
+    }
+
+    /// Result store backend
+    pub fn store(mut self, store: StoreKind) -> Self {
+        self.config.store = store;
+        self
+    }
+
+    /// Total time allowed for each request, in seconds
+    pub fn http_timeout(mut self, secs: u64) -> Self {
+        self.config.http_timeout = secs;
+        self
+    }
+
+    /// URL schemes each domain is checked over
+    pub fn schemes(mut self, schemes: &[&str]) -> Self {
+        self.config.schemes = schemes.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Ports each domain is checked on, instead of the scheme's default
+    pub fn ports(mut self, ports: &[u16]) -> Self {
+        self.config.ports = ports.to_vec();
+        self
+    }
+
+    /// Proxy URL requests are sent through
+    pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.config.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Error statuses reported as protected findings
+    pub fn interesting_status(mut self, statuses: &[u16]) -> Self {
+        self.config.interesting_status = statuses.to_vec();
+        self
+    }
+
+    /// Expected findings (accepted risk) file
+    pub fn expected_file(mut self, path: impl Into<String>) -> Self {
+        self.config.expected_file = Some(path.into());
+        self
+    }
+
+    /// Canary domains checked before the scan
+    pub fn canaries_file(mut self, path: impl Into<String>) -> Self {
+        self.config.canaries_file = Some(path.into());
+        self
+    }
+
+    /// Purge findings older than `age` after the scan, optionally only not-detected ones
+    pub fn purge_older_than(mut self, age: impl Into<String>, not_detected: bool) -> Self {
+        self.config.purge_older_than = Some(age.into());
+        self.config.purge_not_detected = not_detected;
+        self
+    }
+
+    /// Brute force subdomains of apex domains in the input at most `per_second`
+    /// queries a second, scanning those that resolve
+    pub fn dns_brute(mut self, per_second: u32) -> Self {
+        self.config.dns_brute = true;
+        self.config.dns_brute_rate = per_second;
+        self
+    }
+
+    /// Expand each input entry into targets with a shell command
+    pub fn expand_command(mut self, command: &str) -> Self {
+        self.config.expand_command = Some(command.to_string());
+        self
+    }
+
+    /// Only send traffic during a daily window such as `22:00-06:00 Europe/Berlin`
+    pub fn scan_window(mut self, window: &str) -> Self {
+        self.config.scan_window = Some(window.to_string());
+        self
+    }
+
+    /// Run with minimal output
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.config.quiet = quiet;
+        self
+    }
+
+    /// The configuration; [`ScanConfig::validate`] checks it
+    pub fn build(self) -> ScanConfig {
+        self.config
+    }
+}
+
+/// Replace any credentials in a URL with a placeholder
+fn redact_url_credentials(value: &str) -> String {
+    match url::Url::parse(value) {
+        Ok(mut url) if !url.username().is_empty() || url.password().is_some() => {
+            let _ = url.set_username("***");
+            let _ = url.set_password(None);
+            url.to_string()
+        }
+        _ => value.to_string(),
+    }
+}
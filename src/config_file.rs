@@ -0,0 +1,707 @@
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::config::{InputSource, ScanConfig};
+use crate::exit::{Classify, Failure};
+use crate::profile::ScanProfile;
+use crate::secrets;
+use crate::store::{self, StoreKind};
+
+/// Config file read when `--config` isn't given, if it exists
+pub const DEFAULT_CONFIG_FILE: &str = "fatt.toml";
+
+/// Prefix of environment variables that override config keys (`FATT_CONCURRENCY`)
+const ENV_PREFIX: &str = "FATT_";
+
+/// A configurable key
+struct Key {
+    name: &'static str,
+
+    /// Relative values from a file are resolved against the file's directory
+    path: bool,
+
+    /// Masked when printing the effective configuration
+    secret: bool,
+}
+
+const fn key(name: &'static str) -> Key {
+    Key {
+        name,
+        path: false,
+        secret: false,
+    }
+}
+
+const fn path_key(name: &'static str) -> Key {
+    Key {
+        name,
+        path: true,
+        secret: false,
+    }
+}
+
+const fn secret_key(name: &'static str) -> Key {
+    Key {
+        name,
+        path: false,
+        secret: true,
+    }
+}
+
+const KEYS: &[Key] = &[
+    path_key("input"),
+    path_key("rules"),
+    path_key("database"),
+    path_key("workdir"),
+    key("store"),
+    key("shards"),
+    key("db_tuning"),
+    key("purge_older_than"),
+    key("purge_not_detected"),
+    path_key("expected"),
+    path_key("canaries"),
+    key("profile"),
+    key("project"),
+    key("concurrency"),
+    key("queue_capacity"),
+    key("match_threads"),
+    key("timeout"),
+    key("connect_timeout"),
+    key("tls_timeout"),
+    key("first_byte_timeout"),
+    key("max_body_bytes"),
+    key("dns_timeout"),
+    key("dns_brute"),
+    key("dns_brute_rate"),
+    key("expand_command"),
+    key("expand_concurrency"),
+    key("retries"),
+    key("retry_backoff_ms"),
+    key("jitter_ms"),
+    key("schemes"),
+    key("ports"),
+    key("interesting_status"),
+    key("circuit_breaker"),
+    key("rule_concurrency"),
+    key("proxy"),
+    secret_key("proxy_auth"),
+    path_key("user_agent_file"),
+    key("sticky_user_agent"),
+    key("capture_headers"),
+    key("risk_weights"),
+    key("anomaly_factor"),
+    key("anomaly_min_count"),
+    key("evasion"),
+    key("authorized_testing"),
+    key("stream_listen"),
+    secret_key("stream_token"),
+    key("hot_reload_rules"),
+    key("scan_window"),
+    key("throttle_tld"),
+    key("throttle_asn"),
+    key("throttle_subnet"),
+    path_key("asn_file"),
+    key("redact_patterns"),
+];
+
+/// Keys a scan `profile` sets, which other keys in the same layer override
+const PROFILE_KEYS: &[&str] = &[
+    "concurrency",
+    "rule_concurrency",
+    "jitter_ms",
+    "retries",
+    "retry_backoff_ms",
+    "timeout",
+    "connect_timeout",
+    "first_byte_timeout",
+    "circuit_breaker",
+    "throttle_subnet",
+];
+
+/// Keys holding a list, which a flag given once still sets to a one-item list
+const LIST_KEYS: &[&str] = &[
+    "input",
+    "rules",
+    "schemes",
+    "ports",
+    "interesting_status",
+    "redact_patterns",
+];
+
+fn find_key(name: &str) -> Option<&'static Key> {
+    KEYS.iter().find(|key| key.name == name)
+}
+
+/// Where an effective configuration value came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Default,
+    File(String),
+    Profile(String),
+    Env(String),
+    Flag,
+    CommandLine,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "file {}", path),
+            Source::Profile(name) => write!(f, "profile {}", name),
+            Source::Env(var) => write!(f, "env {}", var),
+            Source::Flag => write!(f, "flag --set"),
+            Source::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+/// Scan configuration merged from defaults, a config file and profile, environment
+/// variables, `--set` flags and a scan's own flags, in increasing order of precedence
+#[derive(Debug)]
+pub struct EffectiveConfig {
+    pub config: ScanConfig,
+    sources: BTreeMap<&'static str, Source>,
+}
+
+impl EffectiveConfig {
+    /// Merge every configuration layer. Unknown keys in the file, profile or flags are errors.
+    pub fn load(path: Option<&str>, profile: Option<&str>, overrides: &[String]) -> Result<Self> {
+        Self::load_with(path, profile, overrides, toml::Table::new())
+    }
+
+    /// Merge every configuration layer under the keys given on a command line, as
+    /// [`command_line`] reads them
+    pub fn load_with(
+        path: Option<&str>,
+        profile: Option<&str>,
+        overrides: &[String],
+        command_line: toml::Table,
+    ) -> Result<Self> {
+        let path = path.map(str::to_string).or_else(|| {
+            Path::new(DEFAULT_CONFIG_FILE)
+                .exists()
+                .then(|| DEFAULT_CONFIG_FILE.to_string())
+        });
+
+        let mut layers: Vec<(Source, toml::Table)> = Vec::new();
+
+        match &path {
+            Some(path) => {
+                let (base, profiles) = read_file(path)?;
+                layers.push((Source::File(path.clone()), base));
+
+                if let Some(name) = profile {
+                    let table = profiles.get(name).cloned().with_context(|| {
+                        let available: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                        format!(
+                            "Profile {} not found in {} (available: {})",
+                            name,
+                            path,
+                            available.join(", ")
+                        )
+                    })?;
+                    layers.push((Source::Profile(name.to_string()), table));
+                }
+            }
+            None if profile.is_some() => {
+                anyhow::bail!("--config-profile requires a config file");
+            }
+            None => {}
+        }
+
+        layers.push((Source::Env(String::new()), env_layer()));
+        layers.push((Source::Flag, flag_layer(overrides)?));
+        layers.push((Source::CommandLine, command_line));
+
+        let mut config = defaults();
+        let mut sources = BTreeMap::new();
+
+        for (source, table) in layers {
+            // A profile only sets defaults for the keys next to it
+            let mut entries: Vec<(String, toml::Value)> = table.into_iter().collect();
+            entries.sort_by_key(|(name, _)| name != "profile");
+
+            for (name, value) in entries {
+                let key = find_key(&name)
+                    .with_context(|| format!("Unknown config key {} ({})", name, source))?;
+                apply(&mut config, key.name, &value)
+                    .with_context(|| format!("Invalid value for {} ({})", key.name, source))?;
+
+                let source = match &source {
+                    Source::Env(_) => Source::Env(env_var(key.name)),
+                    source => source.clone(),
+                };
+                if key.name == "profile" {
+                    for &tuned in PROFILE_KEYS {
+                        sources.insert(tuned, source.clone());
+                    }
+                }
+                sources.insert(key.name, source);
+            }
+        }
+
+        Ok(Self { config, sources })
+    }
+
+    /// Where a key's effective value came from
+    pub fn source(&self, key: &str) -> &Source {
+        self.sources.get(key).unwrap_or(&Source::Default)
+    }
+
+    /// Every key with its effective value (secrets masked) and source
+    pub fn values(&self) -> Vec<(&'static str, String, &Source)> {
+        KEYS.iter()
+            .map(|key| {
+                let value = match display_value(&self.config, key.name) {
+                    Some(_) if key.secret => "********".to_string(),
+                    Some(value) => value,
+                    None => "(unset)".to_string(),
+                };
+                (key.name, value, self.source(key.name))
+            })
+            .collect()
+    }
+}
+
+/// What a scan is configured with when nothing is set: the `normal` profile's tuning,
+/// without recording the profile
+fn defaults() -> ScanConfig {
+    let mut config = ScanConfig::default();
+    ScanProfile::Normal.apply(&mut config);
+    config.profile = None;
+    config.output_file = None;
+    config
+}
+
+/// The keys given as flags on a command line whose arguments are named after them,
+/// such as `fatt scan --concurrency`. Flags left at their default aren't included, so
+/// they don't override the config file.
+pub fn command_line(matches: &ArgMatches) -> toml::Table {
+    KEYS.iter()
+        .filter_map(|key| {
+            // Keys the command has no flag for aren't given
+            let raw = matches.try_get_raw(key.name).ok()??;
+            if matches.value_source(key.name) != Some(ValueSource::CommandLine) {
+                return None;
+            }
+            let values: Vec<String> = raw
+                .map(|value| value.to_string_lossy().to_string())
+                .collect();
+            let value = match values.as_slice() {
+                [value] if !LIST_KEYS.contains(&key.name) => parse_scalar(value),
+                _ => toml::Value::Array(values.into_iter().map(toml::Value::String).collect()),
+            };
+            Some((key.name.to_string(), value))
+        })
+        .collect()
+}
+
+/// Read a config file, returning its top-level keys and its `[profiles.*]` tables
+fn read_file(path: &str) -> Result<(toml::Table, BTreeMap<String, toml::Table>)> {
+    let contents =
+        fs::read_to_string(path).context(format!("Failed to read config file: {}", path))?;
+    let mut base: toml::Table =
+        toml::from_str(&contents).context(format!("Failed to parse config file: {}", path))?;
+
+    let mut profiles = BTreeMap::new();
+    if let Some(value) = base.remove("profiles") {
+        let toml::Value::Table(tables) = value else {
+            anyhow::bail!("[profiles] in {} must be a table of profiles", path);
+        };
+        for (name, table) in tables {
+            let toml::Value::Table(table) = table else {
+                anyhow::bail!("Profile {} in {} must be a table", name, path);
+            };
+            profiles.insert(name, table);
+        }
+    }
+
+    let base_dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    prepare_file_table(&mut base, base_dir)?;
+    for table in profiles.values_mut() {
+        prepare_file_table(table, base_dir)?;
+    }
+
+    Ok((base, profiles))
+}
+
+/// Expand secret references and resolve relative paths in values read from a file
+fn prepare_file_table(table: &mut toml::Table, base_dir: &Path) -> Result<()> {
+    for (name, value) in table.iter_mut() {
+        interpolate(value).with_context(|| format!("Failed to interpolate {}", name))?;
+
+        if find_key(name).is_some_and(|key| key.path) {
+            resolve_paths(value, base_dir);
+        }
+    }
+    Ok(())
+}
+
+fn interpolate(value: &mut toml::Value) -> Result<()> {
+    match value {
+        toml::Value::String(s) => *s = secrets::interpolate(s)?,
+        toml::Value::Array(items) => {
+            for item in items {
+                interpolate(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn resolve_paths(value: &mut toml::Value, base_dir: &Path) {
+    match value {
+        toml::Value::String(path)
+            if !Path::new(path.as_str()).is_absolute() && !store::is_postgres_url(path) =>
+        {
+            *path = base_dir.join(path.as_str()).to_string_lossy().to_string();
+        }
+        toml::Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| resolve_paths(item, base_dir)),
+        _ => {}
+    }
+}
+
+fn env_var(key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, key.to_uppercase())
+}
+
+/// Known keys set through `FATT_*` environment variables
+fn env_layer() -> toml::Table {
+    KEYS.iter()
+        .filter_map(|key| {
+            let value = std::env::var(env_var(key.name)).ok()?;
+            Some((key.name.to_string(), parse_scalar(&value)))
+        })
+        .collect()
+}
+
+/// Keys set through `--set key=value`
+fn flag_layer(overrides: &[String]) -> Result<toml::Table> {
+    overrides
+        .iter()
+        .map(|entry| {
+            let (name, value) = entry
+                .split_once('=')
+                .with_context(|| format!("Invalid --set {}: expected key=value", entry))?;
+            Ok((name.trim().to_string(), parse_scalar(value.trim())))
+        })
+        .collect()
+}
+
+/// Interpret a command-line or environment value as a TOML scalar, falling back to a string
+fn parse_scalar(value: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", value))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
+
+fn as_string(value: &toml::Value) -> Result<String> {
+    match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        other => anyhow::bail!("expected a string, got {}", other.type_str()),
+    }
+}
+
+fn as_u64(value: &toml::Value) -> Result<u64> {
+    match value {
+        toml::Value::Integer(i) if *i >= 0 => Ok(*i as u64),
+        other => anyhow::bail!("expected a non-negative integer, got {}", other),
+    }
+}
+
+fn as_f64(value: &toml::Value) -> Result<f64> {
+    match value {
+        toml::Value::Float(f) => Ok(*f),
+        toml::Value::Integer(i) => Ok(*i as f64),
+        other => anyhow::bail!("expected a number, got {}", other),
+    }
+}
+
+fn as_bool(value: &toml::Value) -> Result<bool> {
+    match value {
+        toml::Value::Boolean(b) => Ok(*b),
+        other => anyhow::bail!("expected true or false, got {}", other),
+    }
+}
+
+/// A list given as an array or a comma-separated string
+fn as_list(value: &toml::Value) -> Result<Vec<String>> {
+    match value {
+        toml::Value::Array(items) => items.iter().map(as_string).collect(),
+        other => Ok(as_string(other)?
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()),
+    }
+}
+
+/// A list of regular expressions, given as an array or a single pattern: unlike other
+/// lists, a string isn't split on commas, which patterns can contain
+fn as_patterns(value: &toml::Value) -> Result<Vec<String>> {
+    match value {
+        toml::Value::Array(items) => items.iter().map(as_string).collect(),
+        other => Ok(vec![as_string(other)?]),
+    }
+}
+
+/// A list of numbers such as ports or status codes
+fn as_u16_list(value: &toml::Value, what: &str) -> Result<Vec<u16>> {
+    as_list(value)?
+        .iter()
+        .map(|item| {
+            item.parse::<u16>()
+                .context(format!("invalid {}: {}", what, item))
+        })
+        .collect()
+}
+
+fn apply(config: &mut ScanConfig, key: &str, value: &toml::Value) -> Result<()> {
+    match key {
+        "input" => {
+            let mut inputs = as_list(value)?
+                .iter()
+                .map(|input| input.parse::<InputSource>())
+                .collect::<Result<Vec<_>>>()?;
+            if inputs.is_empty() {
+                anyhow::bail!("at least one input file is required");
+            }
+            let primary = inputs.remove(0);
+            config.input_file = primary.file;
+            config.input_tag = primary.tag;
+            config.extra_inputs = inputs;
+        }
+        "rules" => {
+            let mut rules = as_list(value)?;
+            if rules.is_empty() {
+                anyhow::bail!("at least one rules file is required");
+            }
+            config.rules_file = rules.remove(0);
+            config.extra_rules_files = rules;
+        }
+        "database" => config.db_path = as_string(value)?,
+        "workdir" => config.workdir = Some(as_string(value)?),
+        "store" => config.store = as_string(value)?.parse()?,
+        "shards" => config.db_shards = as_u64(value)? as usize,
+        "db_tuning" => config.db_tuning = as_bool(value)?,
+        "purge_older_than" => config.purge_older_than = Some(as_string(value)?),
+        "purge_not_detected" => config.purge_not_detected = as_bool(value)?,
+        "expected" => config.expected_file = Some(as_string(value)?),
+        "canaries" => config.canaries_file = Some(as_string(value)?),
+        "profile" => as_string(value)?.parse::<ScanProfile>()?.apply(config),
+        "project" => config.project = Some(as_string(value)?),
+        "concurrency" => config.concurrency = as_u64(value)? as usize,
+        "queue_capacity" => config.queue_capacity = as_u64(value)? as usize,
+        "match_threads" => config.match_threads = as_u64(value)? as usize,
+        "timeout" => config.http_timeout = as_u64(value)?,
+        "connect_timeout" => config.connect_timeout = as_u64(value)?,
+        "tls_timeout" => config.tls_timeout = as_u64(value)?,
+        "first_byte_timeout" => config.first_byte_timeout = as_u64(value)?,
+        "max_body_bytes" => config.max_body_bytes = as_u64(value)? as usize,
+        "dns_timeout" => config.dns_timeout = as_u64(value)?,
+        "dns_brute" => config.dns_brute = as_bool(value)?,
+        "dns_brute_rate" => config.dns_brute_rate = as_u64(value)?.try_into()?,
+        "expand_command" => config.expand_command = Some(as_string(value)?),
+        "expand_concurrency" => config.expand_concurrency = as_u64(value)? as usize,
+        "retries" => config.retries = as_u64(value)?.try_into()?,
+        "retry_backoff_ms" => config.retry_backoff_ms = as_u64(value)?,
+        "jitter_ms" => config.jitter_ms = as_u64(value)?,
+        "schemes" => config.schemes = as_list(value)?,
+        "ports" => config.ports = as_u16_list(value, "port")?,
+        "interesting_status" => config.interesting_status = as_u16_list(value, "status")?,
+        "circuit_breaker" => config.circuit_breaker = as_u64(value)? as usize,
+        "rule_concurrency" => config.rule_concurrency = as_u64(value)? as usize,
+        "proxy" => config.proxy = Some(as_string(value)?),
+        "proxy_auth" => config.proxy_auth = Some(as_string(value)?),
+        "user_agent_file" => config.user_agent_file = Some(as_string(value)?),
+        "sticky_user_agent" => config.sticky_user_agent = as_bool(value)?,
+        "capture_headers" => config.capture_headers = as_bool(value)?,
+        "risk_weights" => config.risk_weights = as_string(value)?.parse()?,
+        "anomaly_factor" => config.anomaly_factor = as_f64(value)?,
+        "anomaly_min_count" => config.anomaly_min_count = as_u64(value)? as usize,
+        "evasion" => config.evasion = as_bool(value)?,
+        "authorized_testing" => config.authorized_testing = as_bool(value)?,
+        "stream_listen" => config.stream_listen = Some(as_string(value)?),
+        "stream_token" => config.stream_token = Some(as_string(value)?),
+        "hot_reload_rules" => config.hot_reload_rules = as_bool(value)?,
+        "scan_window" => config.scan_window = Some(as_string(value)?),
+        "throttle_tld" => config.throttle_tld = as_u64(value)? as usize,
+        "throttle_asn" => config.throttle_asn = as_u64(value)? as usize,
+        "throttle_subnet" => config.throttle_subnet = as_u64(value)? as usize,
+        "asn_file" => config.asn_file = Some(as_string(value)?),
+        "redact_patterns" => config.redact_patterns = as_patterns(value)?,
+        _ => anyhow::bail!("unhandled config key {}", key),
+    }
+    Ok(())
+}
+
+fn display_value(config: &ScanConfig, key: &str) -> Option<String> {
+    let join = |items: &[String]| items.join(",");
+    let join_numbers = |items: &[u16]| {
+        items
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    Some(match key {
+        "input" => config
+            .inputs()
+            .iter()
+            .map(InputSource::to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+        "rules" => {
+            let mut rules = vec![config.rules_file.clone()];
+            rules.extend(config.extra_rules_files.iter().cloned());
+            join(&rules)
+        }
+        "database" => store::redact_url(&config.db_path),
+        "workdir" => config.workdir.clone()?,
+        "store" => config.store.to_string(),
+        "shards" => config.db_shards.to_string(),
+        "db_tuning" => config.db_tuning.to_string(),
+        "purge_older_than" => config.purge_older_than.clone()?,
+        "purge_not_detected" => config.purge_not_detected.to_string(),
+        "expected" => config.expected_file.clone()?,
+        "canaries" => config.canaries_file.clone()?,
+        "profile" => config.profile?.to_string(),
+        "project" => config.project.clone()?,
+        "concurrency" => config.concurrency.to_string(),
+        "queue_capacity" => config.queue_capacity.to_string(),
+        "match_threads" => config.match_threads.to_string(),
+        "timeout" => config.http_timeout.to_string(),
+        "connect_timeout" => config.connect_timeout.to_string(),
+        "tls_timeout" => config.tls_timeout.to_string(),
+        "first_byte_timeout" => config.first_byte_timeout.to_string(),
+        "max_body_bytes" => config.max_body_bytes.to_string(),
+        "dns_timeout" => config.dns_timeout.to_string(),
+        "dns_brute" => config.dns_brute.to_string(),
+        "dns_brute_rate" => config.dns_brute_rate.to_string(),
+        "expand_command" => config.expand_command.clone()?,
+        "expand_concurrency" => config.expand_concurrency.to_string(),
+        "retries" => config.retries.to_string(),
+        "retry_backoff_ms" => config.retry_backoff_ms.to_string(),
+        "jitter_ms" => config.jitter_ms.to_string(),
+        "schemes" => join(&config.schemes),
+        "ports" => join_numbers(&config.ports),
+        "interesting_status" => join_numbers(&config.interesting_status),
+        "circuit_breaker" => config.circuit_breaker.to_string(),
+        "rule_concurrency" => config.rule_concurrency.to_string(),
+        "proxy" => config.proxy.clone()?,
+        "proxy_auth" => config.proxy_auth.clone()?,
+        "user_agent_file" => config.user_agent_file.clone()?,
+        "sticky_user_agent" => config.sticky_user_agent.to_string(),
+        "capture_headers" => config.capture_headers.to_string(),
+        "risk_weights" => format!("{:?}", config.risk_weights),
+        "anomaly_factor" => config.anomaly_factor.to_string(),
+        "anomaly_min_count" => config.anomaly_min_count.to_string(),
+        "evasion" => config.evasion.to_string(),
+        "authorized_testing" => config.authorized_testing.to_string(),
+        "stream_listen" => config.stream_listen.clone()?,
+        "stream_token" => config.stream_token.clone()?,
+        "hot_reload_rules" => config.hot_reload_rules.to_string(),
+        "scan_window" => config.scan_window.clone()?,
+        "throttle_tld" => config.throttle_tld.to_string(),
+        "throttle_asn" => config.throttle_asn.to_string(),
+        "throttle_subnet" => config.throttle_subnet.to_string(),
+        "asn_file" => config.asn_file.clone()?,
+        "redact_patterns" => config.redact_patterns.join(" "),
+        _ => return None,
+    })
+}
+
+/// JSON Schema of a config file: every key, and the same keys in each `[profiles.*]` table
+pub fn schema() -> Result<serde_json::Value> {
+    let mut generator = SchemaSettings::draft07()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator();
+    let keys: serde_json::Map<String, serde_json::Value> = KEYS
+        .iter()
+        .map(|key| Ok((key.name.to_string(), key_schema(&mut generator, key.name)?)))
+        .collect::<Result<_>>()?;
+
+    let mut properties = keys.clone();
+    properties.insert(
+        "profiles".to_string(),
+        json!({
+            "type": "object",
+            "additionalProperties": {
+                "type": "object",
+                "properties": keys,
+                "additionalProperties": false,
+            },
+        }),
+    );
+
+    Ok(json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "fatt config file",
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    }))
+}
+
+/// Values `apply` accepts for a key
+fn key_schema(generator: &mut SchemaGenerator, key: &str) -> Result<serde_json::Value> {
+    let string = json!({ "type": "string" });
+    let integer = json!({ "type": "integer", "minimum": 0 });
+    let boolean = json!({ "type": "boolean" });
+    // Lists are arrays, or a string
+    let list = |item: serde_json::Value| json!({ "anyOf": [{ "type": "array", "items": item }, { "type": "string" }] });
+
+    Ok(match key {
+        "input" | "rules" | "schemes" | "redact_patterns" => list(string),
+        "ports" | "interesting_status" => list(integer),
+        "store" => json!(generator.subschema_for::<StoreKind>()),
+        "profile" => json!(generator.subschema_for::<ScanProfile>()),
+        "anomaly_factor" => json!({ "type": "number" }),
+        "shards" | "concurrency" | "queue_capacity" | "match_threads" | "timeout"
+        | "connect_timeout" | "tls_timeout" | "first_byte_timeout" | "max_body_bytes"
+        | "dns_timeout" | "retries" | "retry_backoff_ms" | "jitter_ms" | "circuit_breaker"
+        | "rule_concurrency" | "anomaly_min_count" | "throttle_tld" | "throttle_asn"
+        | "throttle_subnet" | "dns_brute_rate" | "expand_concurrency" => integer,
+        "db_tuning" | "purge_not_detected" | "sticky_user_agent" | "capture_headers"
+        | "evasion" | "authorized_testing" | "hot_reload_rules" | "dns_brute" => boolean,
+        "database" | "workdir" | "purge_older_than" | "expected" | "canaries" | "proxy"
+        | "proxy_auth" | "user_agent_file" | "risk_weights" | "stream_listen" | "stream_token"
+        | "asn_file" | "project" | "expand_command" | "scan_window" => string,
+        _ => anyhow::bail!("unhandled config key {}", key),
+    })
+}
+
+/// Print the effective configuration with each value's source, then validate it
+pub fn check_config(path: Option<&str>, profile: Option<&str>, overrides: &[String]) -> Result<()> {
+    let effective = EffectiveConfig::load(path, profile, overrides).classify(Failure::Config)?;
+
+    println!("⚙️  Effective configuration:");
+    println!("{:<20} {:<40} Source", "Key", "Value");
+    println!("{:-<90}", "");
+    for (key, value, source) in effective.values() {
+        println!("{:<20} {:<40} {}", key, value, source);
+    }
+
+    effective.config.validate().classify(Failure::Config)?;
+
+    println!("\n✅ Configuration is valid");
+
+    Ok(())
+}
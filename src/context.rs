@@ -0,0 +1,449 @@
+use anyhow::Result;
+use memchr::memmem;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::{debug, info, Instrument};
+use url::Url;
+
+use crate::cert_inventory::SiteCertificate;
+use crate::otel;
+use crate::rules::RuleSnapshot;
+use crate::scanner::{Page, ScanResources};
+use crate::throttle::ThrottleKey;
+use crate::timeouts::{PhaseTimeout, TimeoutPhase};
+use crate::utils;
+
+/// Fingerprint of a server's response to a path that cannot exist.
+///
+/// Hosts with wildcard routing answer every path with the same page, which makes
+/// "the path exists" meaningless unless responses are compared against this baseline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Soft404 {
+    pub status: u16,
+    pub body_hash: u64,
+}
+
+impl Soft404 {
+    /// Build a fingerprint from the response to `probe_path`
+    pub fn from_page(page: &Page, probe_path: &str) -> Self {
+        Self {
+            status: page.status,
+            body_hash: body_hash(&page.body, probe_path),
+        }
+    }
+
+    /// Check whether a response for `path` is the wildcard page
+    pub fn matches(&self, page: &Page, path: &str) -> bool {
+        page.status == self.status && body_hash(&page.body, path) == self.body_hash
+    }
+}
+
+/// Hash a body with the requested path blanked out, since many error pages echo it back
+fn body_hash(body: &[u8], path: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    if path.is_empty() {
+        hasher.write(body);
+    } else {
+        // Hash the pieces between occurrences of the path rather than copying the body
+        let mut start = 0;
+        for found in memmem::find_iter(body, path.as_bytes()) {
+            hasher.write(&body[start..found]);
+            start = found + path.len();
+        }
+        hasher.write(&body[start..]);
+    }
+    hasher.finish()
+}
+
+/// Stops checking a domain after too many consecutive failures.
+///
+/// A host that times out on every request would otherwise cost `rules x retries`
+/// timeouts before the scan moves on.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: usize,
+    consecutive_failures: AtomicUsize,
+}
+
+impl CircuitBreaker {
+    /// Create a breaker that opens after `threshold` consecutive failures (0 never opens)
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            consecutive_failures: AtomicUsize::new(0),
+        }
+    }
+
+    /// Record a successful request, closing the breaker
+    pub fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+    }
+
+    /// Record a failed request
+    pub fn record_failure(&self) {
+        self.consecutive_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Whether further requests should be skipped
+    pub fn is_open(&self) -> bool {
+        self.threshold > 0 && self.consecutive_failures.load(Ordering::Relaxed) >= self.threshold
+    }
+}
+
+/// Per-domain state shared by every check against that domain
+#[derive(Debug)]
+pub struct ScanContext {
+    /// Domain being scanned
+    pub domain: String,
+
+    /// Resolved IP addresses
+    pub ips: Vec<IpAddr>,
+
+    /// Base URLs (scheme, host and port) to check each rule against
+    pub targets: Vec<String>,
+
+    /// The plain-http base URL the domain was switched to https from, when the host
+    /// turned out to only speak TLS
+    pub scheme_switch: Option<String>,
+
+    /// Wildcard/soft-404 fingerprint, if the host answers unknown paths successfully
+    pub soft_404: Option<Soft404>,
+
+    /// Status the host answers a random, surely missing path with
+    pub missing_status: Option<u16>,
+
+    /// Technologies detected from the front page
+    pub technologies: Vec<String>,
+
+    /// Certificate the host presented, when it's scanned over https
+    pub certificate: Option<SiteCertificate>,
+
+    /// Paths disallowed by robots.txt
+    pub robots_disallow: Vec<String>,
+
+    /// Tag of the input the domain was read from
+    pub tag: Option<String>,
+
+    /// Version of the rules the domain is checked against, fixed when it's created
+    pub rules: Arc<RuleSnapshot>,
+
+    /// Consecutive failure tracking for the domain
+    pub breaker: CircuitBreaker,
+
+    /// Throttling groups (TLD, ASN, subnet) the domain's requests count against
+    pub throttle_keys: Vec<ThrottleKey>,
+
+    /// Slots for the domain's rule checks, so a large ruleset doesn't flood the host
+    rule_slots: Semaphore,
+}
+
+impl ScanContext {
+    /// Create an empty context for a domain without touching the network
+    pub fn new(domain: &str, resources: &ScanResources) -> Self {
+        Self {
+            domain: domain.to_string(),
+            ips: Vec::new(),
+            targets: resources.options.targets(domain),
+            scheme_switch: None,
+            soft_404: None,
+            missing_status: None,
+            technologies: Vec::new(),
+            certificate: None,
+            robots_disallow: Vec::new(),
+            tag: None,
+            rules: resources.ruleset.snapshot(),
+            breaker: CircuitBreaker::new(resources.options.circuit_breaker),
+            throttle_keys: Vec::new(),
+            rule_slots: Semaphore::new(resources.options.rule_concurrency.max(1)),
+        }
+    }
+
+    /// Wait for a free slot to run one of the domain's rule checks in
+    pub async fn rule_slot(&self) -> SemaphorePermit<'_> {
+        self.rule_slots
+            .acquire()
+            .await
+            .expect("rule slots are never closed")
+    }
+
+    /// Resolve the domain and fingerprint the host once for all checks
+    pub async fn prepare(domain: &str, resources: &ScanResources) -> Result<Self> {
+        let mut ctx = Self::new(domain, resources);
+        let dns = resources.options.timeouts.dns;
+        let lookup = resources
+            .resolver
+            .lookup_all(domain)
+            .instrument(otel::span!("dns", domain));
+        ctx.ips = match tokio::time::timeout(dns, lookup).await {
+            Ok(ips) => ips?,
+            Err(_) => {
+                resources.stats.record_timeout(TimeoutPhase::Dns);
+                return Err(PhaseTimeout {
+                    phase: TimeoutPhase::Dns,
+                    budget: dns,
+                }
+                .into());
+            }
+        };
+        ctx.throttle_keys = resources.throttle.keys(domain, &ctx.ips);
+
+        let Some(mut base_url) = ctx.targets.first().cloned() else {
+            return Ok(ctx);
+        };
+        let _permit = resources.throttle.acquire(&ctx.throttle_keys).await;
+
+        let mut probe = ctx.probe_missing_path(&base_url, resources).await;
+        if suggests_tls_only(probe.as_ref().map(|(_, page)| page.status)) {
+            if let Some((https_url, page)) = ctx.https_fallback(resources).await {
+                info!(
+                    "🔐 {} doesn't answer plain http, switching to {}",
+                    domain, https_url
+                );
+                ctx.scheme_switch = Some(std::mem::replace(&mut base_url, https_url.clone()));
+                ctx.targets = vec![https_url];
+                probe = Some(page);
+            }
+        }
+        if let Some((probe_path, page)) = probe {
+            ctx.record_certificate(&page);
+            ctx.missing_status = Some(page.status);
+            ctx.soft_404 = page
+                .is_success()
+                .then(|| Soft404::from_page(&page, &probe_path));
+        }
+        if ctx.soft_404.is_some() {
+            debug!("🃏 Wildcard responses detected for {}", domain);
+        }
+
+        // Don't spend more requests on a host that is already failing
+        if ctx.breaker.is_open() {
+            return Ok(ctx);
+        }
+
+        let user_agent = resources.user_agent(domain);
+
+        if let Ok(page) = resources
+            .fetch(&utils::build_url(&base_url, "/")?, user_agent.as_deref())
+            .await
+        {
+            ctx.record_certificate(&page);
+            ctx.technologies = detect_technologies(&page);
+            if !ctx.technologies.is_empty() {
+                debug!("🧩 Technologies on {}: {:?}", domain, ctx.technologies);
+            }
+        }
+
+        if let Ok(page) = resources
+            .fetch(
+                &utils::build_url(&base_url, "/robots.txt")?,
+                user_agent.as_deref(),
+            )
+            .await
+        {
+            if page.is_success() && !ctx.is_soft_404(&page, "/robots.txt") {
+                ctx.robots_disallow = parse_robots_disallow(&page.text());
+            }
+        }
+
+        Ok(ctx)
+    }
+
+    /// Keep the certificate of the first response that came straight from the host
+    /// over https, without redirects to another host's
+    fn record_certificate(&mut self, page: &Page) {
+        if self.certificate.is_some() || !page.redirects.is_empty() {
+            return;
+        }
+        let Some(der) = &page.certificate else {
+            return;
+        };
+
+        match SiteCertificate::parse(&self.domain, der, chrono::Utc::now()) {
+            Ok(certificate) => self.certificate = Some(certificate),
+            Err(e) => debug!("Failed to read the certificate of {}: {:#}", self.domain, e),
+        }
+    }
+
+    /// First resolved IP address, if any
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.ips.first().copied()
+    }
+
+    /// Check whether a response is the host's wildcard page rather than real content
+    pub fn is_soft_404(&self, page: &Page, path: &str) -> bool {
+        self.soft_404
+            .as_ref()
+            .is_some_and(|fingerprint| fingerprint.matches(page, path))
+    }
+
+    /// Whether the host answers every path with `status`, so it says nothing about a
+    /// particular path existing
+    pub fn is_wildcard_status(&self, status: u16) -> bool {
+        self.missing_status == Some(status)
+    }
+
+    /// Whether `path` is disallowed by the host's robots.txt
+    pub fn is_disallowed(&self, path: &str) -> bool {
+        self.robots_disallow
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_str()))
+    }
+
+    /// Retry a domain checked over plain http only on https, when its TLS port accepts
+    /// connections and answers the missing-path probe. Returns the https base URL with
+    /// the probe's response.
+    async fn https_fallback(&self, resources: &ScanResources) -> Option<(String, (String, Page))> {
+        let [target] = self.targets.as_slice() else {
+            return None;
+        };
+        let https_url = https_equivalent(target)?;
+        let port = Url::parse(&https_url).ok()?.port_or_known_default()?;
+
+        // Only spend a probe's retries on a port that's open
+        let connect = resources.options.timeouts.connect;
+        let open = match self.ip() {
+            Some(ip) => {
+                tokio::time::timeout(connect, TcpStream::connect(SocketAddr::new(ip, port))).await
+            }
+            None => {
+                let (host, _) = utils::split_host_port(&self.domain);
+                tokio::time::timeout(connect, TcpStream::connect((host, port))).await
+            }
+        };
+        if !matches!(open, Ok(Ok(_))) {
+            debug!(
+                "Port {} isn't open on {}, staying on http",
+                port, self.domain
+            );
+            return None;
+        }
+
+        let probe = self.probe_missing_path(&https_url, resources).await?;
+        Some((https_url, probe))
+    }
+
+    /// Request a random path, returning it with the response so the host's answer for
+    /// missing paths can be recognised
+    async fn probe_missing_path(
+        &self,
+        base_url: &str,
+        resources: &ScanResources,
+    ) -> Option<(String, Page)> {
+        let probe_path = format!("/fatt-{}", uuid::Uuid::new_v4().simple());
+        let url = utils::build_url(base_url, &probe_path).ok()?;
+        let user_agent = resources.user_agent(&self.domain);
+
+        match resources
+            .options
+            .retry
+            .run(|| resources.fetch(&url, user_agent.as_deref()))
+            .await
+        {
+            Ok(page) => {
+                self.breaker.record_success();
+                Some((probe_path, page))
+            }
+            Err(e) => {
+                debug!("Soft-404 probe failed for {}: {}", base_url, e);
+                self.breaker.record_failure();
+                None
+            }
+        }
+    }
+}
+
+/// Whether the missing-path probe over plain http suggests the host only speaks TLS:
+/// the connection failed or was reset (no status), or the server rejected the request
+/// with 400, as nginx does when plain http reaches a TLS port
+pub fn suggests_tls_only(probe_status: Option<u16>) -> bool {
+    matches!(probe_status, None | Some(400))
+}
+
+/// The https base URL of a plain-http one, on the same port when it has its own and on
+/// 443 otherwise; none for any other scheme
+pub fn https_equivalent(base_url: &str) -> Option<String> {
+    let mut url = Url::parse(base_url).ok()?;
+    if url.scheme() != "http" {
+        return None;
+    }
+    url.set_scheme("https").ok()?;
+    Some(url.as_str().trim_end_matches('/').to_string())
+}
+
+/// Body markers of common platforms
+const BODY_MARKERS: &[(&str, &str)] = &[
+    ("wp-content/", "WordPress"),
+    ("Drupal.settings", "Drupal"),
+    ("/media/jui/", "Joomla"),
+    ("/_next/static/", "Next.js"),
+    ("ng-version=", "Angular"),
+    ("csrfmiddlewaretoken", "Django"),
+    ("__VIEWSTATE", "ASP.NET"),
+];
+
+/// Detect technologies from response headers and well-known body markers
+pub fn detect_technologies(page: &Page) -> Vec<String> {
+    let mut technologies = Vec::new();
+
+    for header in ["server", "x-powered-by"] {
+        if let Some(value) = page.headers.get(header).and_then(|v| v.to_str().ok()) {
+            // "nginx/1.25.3" -> "nginx"
+            let name = value.split(['/', ' ']).next().unwrap_or(value).trim();
+            if !name.is_empty() {
+                technologies.push(name.to_string());
+            }
+        }
+    }
+
+    for (marker, technology) in BODY_MARKERS {
+        if page.contains(marker) {
+            technologies.push(technology.to_string());
+        }
+    }
+
+    technologies.sort();
+    technologies.dedup();
+    technologies
+}
+
+/// Collect `Disallow` paths that apply to all user agents
+pub fn parse_robots_disallow(body: &str) -> Vec<String> {
+    let mut disallow = Vec::new();
+    let mut applies = false;
+    let mut in_agents = false;
+
+    for line in body.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some((field, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+
+        match field.trim().to_lowercase().as_str() {
+            "user-agent" => {
+                // Consecutive User-agent lines share one group
+                if !in_agents {
+                    applies = false;
+                }
+                applies |= value == "*";
+                in_agents = true;
+            }
+            "disallow" => {
+                in_agents = false;
+                if applies && !value.is_empty() {
+                    disallow.push(value.to_string());
+                }
+            }
+            _ => in_agents = false,
+        }
+    }
+
+    disallow.sort();
+    disallow.dedup();
+    disallow
+}
@@ -1,13 +1,28 @@
 use anyhow::{Context, Result};
-use chrono::{DateTime, Local, NaiveDateTime, Utc};
-use rusqlite::{params, Connection, Row};
-use serde::Serialize;
-use std::fs::create_dir_all;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 use tracing::{debug, info};
 
+use crate::cert_inventory::SiteCertificate;
+use crate::evidence::Evidence;
+use crate::migrate;
+use crate::profile::ScanProfile;
+use crate::retention;
+use crate::risk::{risk_change_percent, RiskReport};
+use crate::rules::{RuleSet, RuleSnapshot, Severity};
+use crate::store::FindingDetails;
+
 /// Represents a finding from a scan
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct Finding {
     pub id: i64,
     pub domain: String,
@@ -15,13 +30,42 @@ pub struct Finding {
     pub matched_path: String,
     pub detected: bool,
     pub scanned_at: DateTime<Utc>,
+    /// URLs the request was redirected through, ending with the final one
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redirect_chain: Vec<String>,
+    /// Tag of the input the domain was read from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Project of the scan that last checked the domain and rule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Digest of the rules the finding was checked with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ruleset_digest: Option<String>,
+    /// HTTP status of the response the finding was observed in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Version of the scan's rules the finding was checked with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ruleset_version: Option<u32>,
+    /// Part of the response body the signature matched in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evidence: Option<Evidence>,
+    /// What to do about the finding, from its rule. Not stored: filled in from a rules
+    /// file when findings are listed or exported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+    /// Analysts' notes on the finding's domain and rule, oldest first. Not stored with
+    /// the finding: filled in from the notes when findings are exported.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
 }
 
 impl Finding {
     fn from_row(row: &Row) -> Result<Self, rusqlite::Error> {
         let scanned_at: String = row.get(5)?;
-        let naive_dt = NaiveDateTime::parse_from_str(&scanned_at, "%Y-%m-%d %H:%M:%S")
-            .unwrap_or_else(|_| Local::now().naive_local());
+        let redirect_chain: Option<String> = row.get(6)?;
+        let evidence: Option<String> = row.get(10)?;
 
         Ok(Finding {
             id: row.get(0)?,
@@ -29,13 +73,251 @@ impl Finding {
             rule_name: row.get(2)?,
             matched_path: row.get(3)?,
             detected: row.get::<_, i64>(4)? != 0,
-            scanned_at: DateTime::from_naive_utc_and_offset(naive_dt, Utc),
+            scanned_at: parse_timestamp(&scanned_at),
+            redirect_chain: redirect_chain
+                .and_then(|chain| serde_json::from_str(&chain).ok())
+                .unwrap_or_default(),
+            tag: row.get(7)?,
+            project: row.get(11)?,
+            ruleset_digest: row.get(12)?,
+            status: row.get(8)?,
+            ruleset_version: row.get(9)?,
+            evidence: evidence.and_then(|evidence| serde_json::from_str(&evidence).ok()),
+            remediation: None,
+            notes: Vec::new(),
+        })
+    }
+}
+
+/// A recorded scan session
+#[derive(Debug, Serialize)]
+pub struct ScanSession {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub domains: usize,
+    pub findings: usize,
+    pub risk_score: f64,
+    /// Whether the scan was stopped before every domain was scanned
+    pub incomplete: bool,
+    /// Tuning profile the scan ran with, if one was chosen
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<ScanProfile>,
+    /// Project the scan was run for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Digest of the rules the scan started with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ruleset_digest: Option<String>,
+}
+
+impl ScanSession {
+    fn from_row(row: &Row) -> Result<Self, rusqlite::Error> {
+        let started_at: String = row.get(1)?;
+        let finished_at: Option<String> = row.get(2)?;
+        let profile: Option<String> = row.get(7)?;
+
+        Ok(ScanSession {
+            id: row.get(0)?,
+            started_at: parse_timestamp(&started_at),
+            finished_at: finished_at.as_deref().map(parse_timestamp),
+            domains: row.get::<_, i64>(3)? as usize,
+            findings: row.get::<_, i64>(4)? as usize,
+            risk_score: row.get(5)?,
+            incomplete: row.get::<_, i64>(6)? != 0,
+            profile: profile.and_then(|profile| profile.parse().ok()),
+            project: row.get(8)?,
+            ruleset_digest: row.get(9)?,
+        })
+    }
+}
+
+/// What a scan saw of a domain besides its findings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainScan {
+    pub scan_id: String,
+    pub domain: String,
+    pub scanned_at: DateTime<Utc>,
+    /// Addresses the domain resolved to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ips: Vec<String>,
+    /// Technologies detected from the front page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub technologies: Vec<String>,
+    /// Why the domain couldn't be scanned, if it couldn't
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Certificate the host presented, when it was scanned over https. Stored in the
+    /// certificates table, by host, rather than with each scan.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<SiteCertificate>,
+}
+
+impl DomainScan {
+    fn from_row(row: &Row) -> Result<Self, rusqlite::Error> {
+        let scanned_at: String = row.get(2)?;
+        let ips: Option<String> = row.get(3)?;
+        let technologies: Option<String> = row.get(4)?;
+
+        Ok(DomainScan {
+            scan_id: row.get(0)?,
+            domain: row.get(1)?,
+            scanned_at: parse_timestamp(&scanned_at),
+            ips: ips
+                .and_then(|ips| serde_json::from_str(&ips).ok())
+                .unwrap_or_default(),
+            technologies: technologies
+                .and_then(|technologies| serde_json::from_str(&technologies).ok())
+                .unwrap_or_default(),
+            error: row.get(5)?,
+            certificate: None,
+        })
+    }
+}
+
+/// An analyst's note on a finding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    pub note: String,
+    /// Who wrote the note; notes from before authors were recorded have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.created_at.format("%Y-%m-%d %H:%M"))?;
+        if let Some(author) = &self.author {
+            write!(f, " {}", author)?;
+        }
+        write!(f, ": {}", self.note)
+    }
+}
+
+/// A note with the domain and rule of the finding it's on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FindingNote {
+    pub domain: String,
+    pub rule_name: String,
+    #[serde(flatten)]
+    pub note: Note,
+}
+
+/// A domain and rule pair marked as a false positive: left out of exports, and of
+/// scans too with `skip_scans`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FalsePositive {
+    pub domain: String,
+    pub rule_name: String,
+    pub skip_scans: bool,
+    pub marked_at: DateTime<Utc>,
+}
+
+/// Parse a stored timestamp: SQLite's CURRENT_TIMESTAMP format, or RFC 3339 for rows
+/// written by other tools. Anything else is read as the Unix epoch, so a row with a
+/// broken timestamp sorts as the oldest rather than passing for a fresh one.
+fn parse_timestamp(value: &str) -> DateTime<Utc> {
+    if let Ok(naive_dt) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f") {
+        return DateTime::from_naive_utc_and_offset(naive_dt, Utc);
+    }
+
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| {
+            debug!("Unreadable timestamp in database: {}", value);
+            DateTime::UNIX_EPOCH
         })
+}
+
+/// Bounds on when findings were last scanned; either end may be left open
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeRange {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl TimeRange {
+    /// Parse `--since` and `--until` values (see [`parse_time`])
+    pub fn parse(since: Option<&str>, until: Option<&str>) -> Result<Self> {
+        let range = Self {
+            since: since.map(parse_time).transpose()?,
+            until: until.map(parse_time).transpose()?,
+        };
+        if let (Some(since), Some(until)) = (range.since, range.until) {
+            if since > until {
+                anyhow::bail!("--since {} is after --until {}", since, until);
+            }
+        }
+
+        Ok(range)
+    }
+
+    /// Whether a time falls in the range; both ends are inclusive
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        self.since.is_none_or(|since| time >= since) && self.until.is_none_or(|until| time <= until)
+    }
+
+    /// The bounds formatted for comparing with stored timestamps
+    fn bounds(&self) -> (Option<String>, Option<String>) {
+        (
+            self.since.as_ref().map(format_timestamp),
+            self.until.as_ref().map(format_timestamp),
+        )
+    }
+}
+
+/// Parse a point in time given on the command line: RFC 3339, `YYYY-MM-DD HH:MM:SS` or
+/// `YYYY-MM-DD` in UTC, or an age such as `12h` or `7d` meaning that long ago
+pub fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc));
     }
+    if let Ok(naive_dt) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+        return Ok(DateTime::from_naive_utc_and_offset(naive_dt, Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(DateTime::from_naive_utc_and_offset(
+            date.and_time(NaiveTime::MIN),
+            Utc,
+        ));
+    }
+
+    let age = retention::parse_age(value).map_err(|_| {
+        anyhow::anyhow!(
+            "Invalid time: {} (expected e.g. 2024-05-01, 2024-05-01T00:00:00Z or 12h)",
+            value
+        )
+    })?;
+    Ok(Utc::now() - age)
+}
+
+/// Format a timestamp the way SQLite's CURRENT_TIMESTAMP does
+pub(crate) fn format_timestamp(value: &DateTime<Utc>) -> String {
+    value.format("%Y-%m-%d %H:%M:%S").to_string()
 }
 
-/// Initialize the SQLite database
+/// How long a write waits for another connection's lock before failing
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Prepared statements kept per connection, enough for every statement a scan repeats
+pub const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+/// Bytes of the database file memory-mapped for reads
+const MMAP_SIZE: i64 = 256 * 1024 * 1024;
+
+/// Initialize the SQLite database, tuned for high-volume writes
 pub fn init_db(db_file: &str) -> Result<Connection> {
+    init_db_with(db_file, true)
+}
+
+/// Initialize the SQLite database, with or without the performance tuning.
+///
+/// Tuning puts the database in WAL mode with `synchronous=NORMAL`, so a write
+/// doesn't wait for an fsync, and memory-maps it for reads. Without it the database
+/// keeps a rollback journal, which works on filesystems WAL doesn't, such as NFS.
+pub fn init_db_with(db_file: &str, tuning: bool) -> Result<Connection> {
     // Ensure parent directory exists
     if let Some(parent) = Path::new(db_file).parent() {
         if !parent.exists() {
@@ -44,42 +326,48 @@ pub fn init_db(db_file: &str) -> Result<Connection> {
     }
 
     // Open or create the database
-    let conn =
+    let mut conn =
         Connection::open(db_file).context(format!("Failed to open database: {}", db_file))?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .context("Failed to set busy timeout")?;
+    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+    if tuning {
+        tune(&conn).context(format!("Failed to tune database: {}", db_file))?;
+    } else {
+        // WAL mode persists in the file, so a database tuned before is switched back
+        set_journal_mode(&conn, "DELETE")
+            .context(format!("Failed to set journal mode: {}", db_file))?;
+    }
 
-    // Create necessary tables if they don't exist
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS findings (
-            id INTEGER PRIMARY KEY,
-            domain TEXT,
-            rule_name TEXT,
-            matched_path TEXT,
-            detected INTEGER,
-            scanned_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            UNIQUE(domain, rule_name)
-        )",
-        [],
-    )
-    .context("Failed to create findings table")?;
-
-    // Create index for faster lookups
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_findings_domain ON findings (domain)",
-        [],
-    )
-    .context("Failed to create domain index")?;
-
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_findings_rule ON findings (rule_name)",
-        [],
-    )
-    .context("Failed to create rule_name index")?;
+    // Bring the schema up to date
+    let applied = migrate::migrate(&mut conn)?;
+    for (version, name) in &applied.applied {
+        debug!("Applied migration {} to {}: {}", version, db_file, name);
+    }
 
     debug!("Database initialized: {}", db_file);
 
     Ok(conn)
 }
 
+/// Apply the performance pragmas to a connection
+fn tune(conn: &Connection) -> Result<()> {
+    set_journal_mode(conn, "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    conn.pragma_update(None, "mmap_size", MMAP_SIZE)?;
+
+    Ok(())
+}
+
+/// Switch the journal mode; an in-memory database keeps its own
+fn set_journal_mode(conn: &Connection, mode: &str) -> Result<()> {
+    let mode: String =
+        conn.pragma_update_and_check(None, "journal_mode", mode, |row| row.get(0))?;
+    debug!("Database journal mode: {}", mode);
+
+    Ok(())
+}
+
 /// Insert a new finding into the database
 pub fn insert_finding(
     conn: &Connection,
@@ -91,7 +379,7 @@ pub fn insert_finding(
     // Use upsert pattern to update if exists, insert if not
     let detected_int = if detected { 1 } else { 0 };
 
-    conn.execute(
+    conn.prepare_cached(
         "INSERT INTO findings (domain, rule_name, matched_path, detected, scanned_at)
          VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)
          ON CONFLICT(domain, rule_name) 
@@ -99,8 +387,10 @@ pub fn insert_finding(
             matched_path = excluded.matched_path,
             detected = excluded.detected,
             scanned_at = CURRENT_TIMESTAMP",
-        params![domain, rule_name, matched_path, detected_int],
     )
+    .and_then(|mut statement| {
+        statement.execute(params![domain, rule_name, matched_path, detected_int])
+    })
     .context("Failed to insert finding")?;
 
     // Return the ID of the inserted or updated row
@@ -109,223 +399,1509 @@ pub fn insert_finding(
     Ok(id)
 }
 
-/// Get findings by domain pattern
+/// Write the outcome of a rule check with all its details in one statement, replacing
+/// the earlier outcome for the same domain and rule. The User-Agent is kept when the
+/// check didn't rotate one.
+pub fn upsert_finding(
+    conn: &Connection,
+    domain: &str,
+    rule_name: &str,
+    matched_path: &str,
+    detected: bool,
+    details: &FindingDetails,
+) -> Result<()> {
+    let redirect_chain = if details.redirect_chain.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(details.redirect_chain)?)
+    };
+    let evidence = details.evidence.map(serde_json::to_string).transpose()?;
+
+    conn.prepare_cached(
+        "INSERT INTO findings (domain, rule_name, matched_path, detected, scanned_at, user_agent, redirect_chain, tag, status, ruleset_version, evidence, project, ruleset_digest)
+         VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP, ?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(domain, rule_name)
+         DO UPDATE SET
+            matched_path = excluded.matched_path,
+            detected = excluded.detected,
+            scanned_at = CURRENT_TIMESTAMP,
+            user_agent = COALESCE(excluded.user_agent, findings.user_agent),
+            redirect_chain = excluded.redirect_chain,
+            tag = excluded.tag,
+            status = excluded.status,
+            ruleset_version = excluded.ruleset_version,
+            evidence = excluded.evidence,
+            project = excluded.project,
+            ruleset_digest = excluded.ruleset_digest",
+    )
+    .and_then(|mut statement| {
+        statement.execute(params![
+            domain,
+            rule_name,
+            matched_path,
+            detected as i64,
+            details.user_agent,
+            redirect_chain,
+            details.tag,
+            details.status,
+            details.ruleset_version,
+            evidence,
+            details.project,
+            details.ruleset_digest,
+        ])
+    })
+    .context("Failed to insert finding")?;
+
+    Ok(())
+}
+
+/// Write a finding checked elsewhere, such as by a worker that left its results in a
+/// file, keeping the time it was checked at. An existing finding for the same domain
+/// and rule is only replaced by a newer one; returns whether the finding was written.
+pub fn import_finding(
+    conn: &Connection,
+    finding: &Finding,
+    user_agent: Option<&str>,
+) -> Result<bool> {
+    let redirect_chain = if finding.redirect_chain.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&finding.redirect_chain)?)
+    };
+    let evidence = finding
+        .evidence
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()?;
+
+    let written = conn
+        .prepare_cached(
+            "INSERT INTO findings (domain, rule_name, matched_path, detected, scanned_at, user_agent, redirect_chain, tag, status, ruleset_version, evidence, project, ruleset_digest)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(domain, rule_name)
+             DO UPDATE SET
+                matched_path = excluded.matched_path,
+                detected = excluded.detected,
+                scanned_at = excluded.scanned_at,
+                user_agent = excluded.user_agent,
+                redirect_chain = excluded.redirect_chain,
+                tag = excluded.tag,
+                status = excluded.status,
+                ruleset_version = excluded.ruleset_version,
+                evidence = excluded.evidence,
+                project = excluded.project,
+                ruleset_digest = excluded.ruleset_digest
+             WHERE datetime(excluded.scanned_at) >= datetime(findings.scanned_at)",
+        )
+        .and_then(|mut statement| {
+            statement.execute(params![
+                finding.domain,
+                finding.rule_name,
+                finding.matched_path,
+                finding.detected as i64,
+                format_timestamp(&finding.scanned_at),
+                user_agent,
+                redirect_chain,
+                finding.tag,
+                finding.status,
+                finding.ruleset_version,
+                evidence,
+                finding.project,
+                finding.ruleset_digest,
+            ])
+        })
+        .context("Failed to import finding")?;
+
+    Ok(written > 0)
+}
+
+/// Get findings by domain pattern, newest first
 #[allow(dead_code)]
 pub fn get_findings_by_domain(
     conn: &Connection,
     domain_pattern: Option<&str>,
+    range: &TimeRange,
     limit: usize,
 ) -> Result<Vec<Finding>> {
-    let mut stmt;
-    let findings = if let Some(pattern) = domain_pattern {
-        conn.prepare(
-            "SELECT id, domain, rule_name, matched_path, detected, scanned_at 
-             FROM findings 
-             WHERE domain LIKE ? 
-             ORDER BY scanned_at DESC 
-             LIMIT ?",
-        )?
-        .query_map(params![format!("%{}%", pattern), limit as i64], Finding::from_row)?
-        .collect::<Result<Vec<_>, _>>()
-        .context("Failed to collect findings by domain")?
-    } else {
-        stmt = conn.prepare(
-            "SELECT id, domain, rule_name, matched_path, detected, scanned_at 
-             FROM findings 
-             ORDER BY scanned_at DESC 
-             LIMIT ?",
-        )?;
-
-        stmt.query_map(params![limit as i64], Finding::from_row)?
-            .collect::<Result<Vec<_>, _>>()
-            .context("Failed to collect findings")?
+    let query = ListQuery {
+        domain_pattern,
+        range: *range,
+        ..Default::default()
     };
-
-    Ok(findings)
+    query_findings(conn, &query, NEWEST_FIRST, limit)
 }
 
-/// Get findings by rule name pattern
+/// Get findings by rule name pattern, newest first
 #[allow(dead_code)]
 pub fn get_findings_by_rule(
     conn: &Connection,
     rule_pattern: Option<&str>,
+    range: &TimeRange,
     limit: usize,
 ) -> Result<Vec<Finding>> {
-    let mut stmt;
-    let findings = if let Some(pattern) = rule_pattern {
-        conn.prepare(
-            "SELECT id, domain, rule_name, matched_path, detected, scanned_at 
+    let query = ListQuery {
+        rule_pattern,
+        range: *range,
+        ..Default::default()
+    };
+    query_findings(conn, &query, NEWEST_FIRST, limit)
+}
+
+/// Get detected findings recorded at or after the given time
+pub fn get_detected_since(conn: &Connection, since: DateTime<Utc>) -> Result<Vec<Finding>> {
+    let findings = conn
+        .prepare(
+            "SELECT id, domain, rule_name, matched_path, detected, scanned_at, redirect_chain, tag, status, ruleset_version, evidence, project, ruleset_digest 
              FROM findings 
-             WHERE rule_name LIKE ? 
-             ORDER BY scanned_at DESC 
-             LIMIT ?",
+             WHERE detected = 1 AND scanned_at >= ? 
+             ORDER BY domain, rule_name",
         )?
-        .query_map(params![format!("%{}%", pattern), limit as i64], Finding::from_row)?
+        .query_map(params![format_timestamp(&since)], Finding::from_row)?
         .collect::<Result<Vec<_>, _>>()
-        .context("Failed to collect findings by rule")?
-    } else {
-        stmt = conn.prepare(
-            "SELECT id, domain, rule_name, matched_path, detected, scanned_at 
-             FROM findings 
-             ORDER BY scanned_at DESC 
-             LIMIT ?",
-        )?;
-
-        stmt.query_map(params![limit as i64], Finding::from_row)?
-            .collect::<Result<Vec<_>, _>>()
-            .context("Failed to collect findings")?
-    };
+        .context("Failed to collect detected findings")?;
 
     Ok(findings)
 }
 
-/// List findings in the database with optional filtering
-pub fn list_results(
-    db_file: &str,
-    domain_pattern: Option<&str>,
-    rule_pattern: Option<&str>,
-    limit: usize,
-) -> Result<()> {
-    let conn =
-        Connection::open(db_file).context(format!("Failed to open database: {}", db_file))?;
-
-    // Get findings
-    let findings = if let Some(domain_pattern) = domain_pattern {
-        conn.prepare(
-            "SELECT id, domain, rule_name, matched_path, detected, scanned_at 
+/// Get detected findings whose evidence carries cache validators, which a scan can
+/// request conditionally
+pub fn get_revalidatable(conn: &Connection) -> Result<Vec<Finding>> {
+    let findings = conn
+        .prepare(
+            "SELECT id, domain, rule_name, matched_path, detected, scanned_at, redirect_chain, tag, status, ruleset_version, evidence, project, ruleset_digest 
              FROM findings 
-             WHERE domain LIKE ? 
-             ORDER BY scanned_at DESC 
-             LIMIT ?",
-        )?
-        .query_map(params![format!("%{}%", domain_pattern), limit as i64], Finding::from_row)?
-        .collect::<Result<Vec<_>, _>>()
-        .context("Failed to collect findings")?
-    } else if let Some(rule_pattern) = rule_pattern {
-        conn.prepare(
-            "SELECT id, domain, rule_name, matched_path, detected, scanned_at 
-             FROM findings 
-             WHERE rule_name LIKE ? 
-             ORDER BY scanned_at DESC 
-             LIMIT ?",
+             WHERE detected = 1 AND json_extract(evidence, '$.validators') IS NOT NULL 
+             ORDER BY domain, rule_name",
         )?
-        .query_map(params![format!("%{}%", rule_pattern), limit as i64], Finding::from_row)?
+        .query_map([], Finding::from_row)?
         .collect::<Result<Vec<_>, _>>()
-        .context("Failed to collect findings")?
-    } else {
-        conn.prepare(
-            "SELECT id, domain, rule_name, matched_path, detected, scanned_at 
-             FROM findings 
-             ORDER BY scanned_at DESC 
-             LIMIT ?",
+        .context("Failed to collect revalidatable findings")?;
+
+    Ok(findings)
+}
+
+/// Get a finding by its ID
+pub fn get_finding(conn: &Connection, id: i64) -> Result<Option<Finding>> {
+    conn.query_row(
+        "SELECT id, domain, rule_name, matched_path, detected, scanned_at, redirect_chain, tag, status, ruleset_version, evidence, project, ruleset_digest
+         FROM findings
+         WHERE id = ?",
+        params![id],
+        Finding::from_row,
+    )
+    .optional()
+    .context("Failed to get finding")
+}
+
+/// Get every finding for a domain, ordered by rule
+pub fn get_domain_findings(conn: &Connection, domain: &str) -> Result<Vec<Finding>> {
+    let findings = conn
+        .prepare(
+            "SELECT id, domain, rule_name, matched_path, detected, scanned_at, redirect_chain, tag, status, ruleset_version, evidence, project, ruleset_digest
+             FROM findings
+             WHERE domain = ?
+             ORDER BY rule_name",
         )?
-        .query_map(params![limit as i64], Finding::from_row)?
+        .query_map(params![domain], Finding::from_row)?
         .collect::<Result<Vec<_>, _>>()
-        .context("Failed to collect findings")?
-    };
+        .context("Failed to collect domain findings")?;
 
-    // Print results in a table format
-    println!("📋 Scan Results:");
-    println!(
-        "{:<5} {:<30} {:<25} {:<30} {:<10} {:<20}",
-        "ID", "Domain", "Rule", "Path", "Detected", "Scanned At"
-    );
-    println!("{:-<120}", "");
+    Ok(findings)
+}
 
-    for finding in &findings {
-        println!(
-            "{:<5} {:<30} {:<25} {:<30} {:<10} {:<20}",
-            finding.id,
-            truncate_string(&finding.domain, 29),
-            truncate_string(&finding.rule_name, 24),
-            truncate_string(&finding.matched_path, 29),
-            if finding.detected {
-                "✅ Yes"
-            } else {
-                "❌ No"
-            },
-            finding.scanned_at.format("%Y-%m-%d %H:%M:%S").to_string()
-        );
-    }
+/// Record what a scan saw of a domain
+pub fn record_domain_scan(conn: &Connection, scan: &DomainScan) -> Result<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO domain_scans (scan_id, domain, scanned_at, ips, technologies, error)
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .and_then(|mut statement| {
+        statement.execute(params![
+            scan.scan_id,
+            scan.domain,
+            format_timestamp(&scan.scanned_at),
+            serde_json::to_string(&scan.ips).ok(),
+            serde_json::to_string(&scan.technologies).ok(),
+            scan.error,
+        ])
+    })
+    .context("Failed to record domain scan")?;
 
-    println!("\nTotal results: {}", findings.len());
+    if let Some(certificate) = &scan.certificate {
+        record_certificate(conn, certificate)?;
+    }
 
     Ok(())
 }
 
-/// Export findings to a file
-pub fn export_results(db_file: &str, output_file: &str, format: &str) -> Result<()> {
-    let conn =
-        Connection::open(db_file).context(format!("Failed to open database: {}", db_file))?;
+/// Record the certificate a host presented, replacing the one seen before
+pub fn record_certificate(conn: &Connection, certificate: &SiteCertificate) -> Result<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO certificates (host, subject, issuer, sans, serial, not_before, not_after, seen_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .and_then(|mut statement| {
+        statement.execute(params![
+            certificate.host,
+            certificate.subject,
+            certificate.issuer,
+            serde_json::to_string(&certificate.sans).ok(),
+            certificate.serial,
+            format_timestamp(&certificate.not_before),
+            format_timestamp(&certificate.not_after),
+            format_timestamp(&certificate.seen_at),
+        ])
+    })
+    .context("Failed to record certificate")?;
 
-    // Get all findings
-    let mut stmt = conn.prepare(
-        "SELECT id, domain, rule_name, matched_path, detected, scanned_at 
-         FROM findings 
-         ORDER BY domain, rule_name",
-    )?;
+    Ok(())
+}
 
-    let findings = stmt
-        .query_map([], Finding::from_row)?
+/// The certificate each scanned host last presented, ordered by host
+pub fn get_certificates(conn: &Connection) -> Result<Vec<SiteCertificate>> {
+    let certificates = conn
+        .prepare(
+            "SELECT host, subject, issuer, sans, serial, not_before, not_after, seen_at
+             FROM certificates
+             ORDER BY host",
+        )?
+        .query_map([], |row| {
+            let sans: Option<String> = row.get(3)?;
+            Ok(SiteCertificate {
+                host: row.get(0)?,
+                subject: row.get(1)?,
+                issuer: row.get(2)?,
+                sans: sans
+                    .and_then(|sans| serde_json::from_str(&sans).ok())
+                    .unwrap_or_default(),
+                serial: row.get(4)?,
+                not_before: parse_timestamp(&row.get::<_, String>(5)?),
+                not_after: parse_timestamp(&row.get::<_, String>(6)?),
+                seen_at: parse_timestamp(&row.get::<_, String>(7)?),
+            })
+        })?
         .collect::<Result<Vec<_>, _>>()
-        .context("Failed to collect findings")?;
+        .context("Failed to collect certificates")?;
 
-    // Ensure parent directory exists
-    if let Some(parent) = Path::new(output_file).parent() {
-        if !parent.exists() {
-            create_dir_all(parent).context("Failed to create output directory")?;
-        }
-    }
+    Ok(certificates)
+}
 
-    match format.to_lowercase().as_str() {
-        "csv" => export_to_csv(&findings, output_file)?,
-        "json" => export_to_json(&findings, output_file)?,
-        _ => anyhow::bail!("Unsupported export format: {}", format),
-    }
+/// What every recorded scan saw of a domain, newest first
+pub fn get_domain_scans(conn: &Connection, domain: &str) -> Result<Vec<DomainScan>> {
+    let scans = conn
+        .prepare(
+            "SELECT scan_id, domain, scanned_at, ips, technologies, error
+             FROM domain_scans
+             WHERE domain = ?
+             ORDER BY datetime(scanned_at) DESC, scan_id",
+        )?
+        .query_map(params![domain], DomainScan::from_row)?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect domain scans")?;
 
-    info!("✅ Exported {} findings to {}", findings.len(), output_file);
+    Ok(scans)
+}
+
+/// Add an analyst's note to the finding for a domain and rule
+pub fn add_note(
+    conn: &Connection,
+    domain: &str,
+    rule_name: &str,
+    note: &str,
+    author: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO finding_notes (domain, rule_name, note, author, created_at)
+         VALUES (?, ?, ?, ?, ?)",
+        params![
+            domain,
+            rule_name,
+            note,
+            author,
+            format_timestamp(&Utc::now())
+        ],
+    )
+    .context("Failed to add note")?;
 
     Ok(())
 }
 
-/// Export findings to CSV format
-fn export_to_csv(findings: &[Finding], output_file: &str) -> Result<()> {
-    let path = PathBuf::from(output_file);
-    let mut writer = csv::Writer::from_path(path)?;
+/// Notes on the finding for a domain and rule, oldest first
+pub fn get_notes(conn: &Connection, domain: &str, rule_name: &str) -> Result<Vec<Note>> {
+    let notes = conn
+        .prepare(
+            "SELECT note, author, created_at
+             FROM finding_notes
+             WHERE domain = ? AND rule_name = ?
+             ORDER BY datetime(created_at), id",
+        )?
+        .query_map(params![domain, rule_name], note_from_row)?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect notes")?;
 
-    // Write header
-    writer.write_record(["ID", "Domain", "Rule", "Path", "Detected", "Scanned At"])?;
+    Ok(notes)
+}
+
+/// Every note, by domain and rule and oldest first within a finding
+pub fn get_all_notes(conn: &Connection) -> Result<Vec<FindingNote>> {
+    let notes = conn
+        .prepare(
+            "SELECT note, author, created_at, domain, rule_name
+             FROM finding_notes
+             ORDER BY domain, rule_name, datetime(created_at), id",
+        )?
+        .query_map([], |row| {
+            Ok(FindingNote {
+                note: note_from_row(row)?,
+                domain: row.get(3)?,
+                rule_name: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect notes")?;
+
+    Ok(notes)
+}
+
+fn note_from_row(row: &Row) -> Result<Note, rusqlite::Error> {
+    let created_at: String = row.get(2)?;
+    Ok(Note {
+        note: row.get(0)?,
+        author: row.get(1)?,
+        created_at: parse_timestamp(&created_at),
+    })
+}
+
+/// Fill in each finding's notes from those on its domain and rule
+pub fn attach_notes(findings: &mut [Finding], notes: Vec<FindingNote>) {
+    let mut by_finding: HashMap<(String, String), Vec<Note>> = HashMap::new();
+    for note in notes {
+        by_finding
+            .entry((note.domain, note.rule_name))
+            .or_default()
+            .push(note.note);
+    }
 
-    // Write findings
     for finding in findings {
-        writer.write_record([
-            &finding.id.to_string(),
-            &finding.domain,
-            &finding.rule_name,
-            &finding.matched_path,
-            &finding.detected.to_string(),
-            &finding.scanned_at.to_rfc3339(),
-        ])?;
+        finding.notes = by_finding
+            .get(&(finding.domain.clone(), finding.rule_name.clone()))
+            .cloned()
+            .unwrap_or_default();
     }
+}
 
-    writer.flush()?;
+/// Mark the finding for a domain and rule as a false positive, replacing any earlier
+/// mark of the pair
+pub fn mark_false_positive(
+    conn: &Connection,
+    domain: &str,
+    rule_name: &str,
+    skip_scans: bool,
+) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO false_positives (domain, rule_name, skip_scans, marked_at)
+         VALUES (?, ?, ?, ?)",
+        params![domain, rule_name, skip_scans, format_timestamp(&Utc::now())],
+    )
+    .context("Failed to mark false positive")?;
 
     Ok(())
 }
 
-/// Export findings to JSON format
-pub fn export_to_json(findings: &[Finding], output_file: &str) -> Result<()> {
-    let json =
-        serde_json::to_string_pretty(findings).context("Failed to serialize findings to JSON")?;
-
-    std::fs::write(output_file, json).context("Failed to write JSON to output file")?;
+/// Remove the false positive mark of a domain and rule, returning whether it had one
+pub fn unmark_false_positive(conn: &Connection, domain: &str, rule_name: &str) -> Result<bool> {
+    let removed = conn
+        .execute(
+            "DELETE FROM false_positives WHERE domain = ? AND rule_name = ?",
+            params![domain, rule_name],
+        )
+        .context("Failed to unmark false positive")?;
 
-    Ok(())
+    Ok(removed > 0)
 }
 
-/// Record a finding in the database (alias for insert_finding with severity)
-#[allow(dead_code)]
-pub fn record_finding(
+/// The false positive mark of a domain and rule, if it has one
+pub fn get_false_positive(
+    conn: &Connection,
+    domain: &str,
+    rule_name: &str,
+) -> Result<Option<FalsePositive>> {
+    conn.query_row(
+        "SELECT skip_scans, marked_at FROM false_positives WHERE domain = ? AND rule_name = ?",
+        params![domain, rule_name],
+        |row| {
+            let marked_at: String = row.get(1)?;
+            Ok(FalsePositive {
+                domain: domain.to_string(),
+                rule_name: rule_name.to_string(),
+                skip_scans: row.get(0)?,
+                marked_at: parse_timestamp(&marked_at),
+            })
+        },
+    )
+    .optional()
+    .context("Failed to look up false positive")
+}
+
+/// Every domain and rule pair marked as a false positive, ordered by domain and rule
+pub fn get_false_positives(conn: &Connection) -> Result<Vec<FalsePositive>> {
+    let false_positives = conn
+        .prepare(
+            "SELECT domain, rule_name, skip_scans, marked_at
+             FROM false_positives
+             ORDER BY domain, rule_name",
+        )?
+        .query_map([], |row| {
+            let marked_at: String = row.get(3)?;
+            Ok(FalsePositive {
+                domain: row.get(0)?,
+                rule_name: row.get(1)?,
+                skip_scans: row.get(2)?,
+                marked_at: parse_timestamp(&marked_at),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect false positives")?;
+
+    Ok(false_positives)
+}
+
+/// Record the start of a scan session
+pub fn start_scan_session(
+    conn: &Connection,
+    scan_id: &str,
+    started_at: DateTime<Utc>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO scans (id, started_at) VALUES (?, ?)",
+        params![scan_id, format_timestamp(&started_at)],
+    )
+    .context("Failed to record scan session")?;
+
+    Ok(())
+}
+
+/// Whether a scan session is recorded
+pub fn scan_session_exists(conn: &Connection, scan_id: &str) -> Result<bool> {
+    conn.prepare("SELECT 1 FROM scans WHERE id = ?")?
+        .exists([scan_id])
+        .context("Failed to look up scan session")
+}
+
+/// Set when a scan session finished, for sessions recorded after the fact
+pub fn record_scan_finished_at(
+    conn: &Connection,
+    scan_id: &str,
+    finished_at: DateTime<Utc>,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE scans SET finished_at = ? WHERE id = ?",
+        params![format_timestamp(&finished_at), scan_id],
+    )
+    .context("Failed to record when the scan session finished")?;
+
+    Ok(())
+}
+
+/// Record the outcome of a scan session, including per-domain risk scores
+pub fn finish_scan_session(
+    conn: &Connection,
+    scan_id: &str,
+    domains: usize,
+    risk: &RiskReport,
+) -> Result<()> {
+    let findings: usize = risk.per_domain.values().map(|d| d.findings).sum();
+
+    conn.execute(
+        "UPDATE scans 
+         SET finished_at = CURRENT_TIMESTAMP, domains = ?, findings = ?, risk_score = ? 
+         WHERE id = ?",
+        params![domains as i64, findings as i64, risk.total, scan_id],
+    )
+    .context("Failed to update scan session")?;
+
+    let mut stmt = conn.prepare(
+        "INSERT OR REPLACE INTO domain_risk (scan_id, domain, risk_score, findings) 
+         VALUES (?, ?, ?, ?)",
+    )?;
+    for (domain, domain_risk) in &risk.per_domain {
+        stmt.execute(params![
+            scan_id,
+            domain,
+            domain_risk.score,
+            domain_risk.findings as i64
+        ])
+        .context("Failed to record domain risk score")?;
+    }
+
+    Ok(())
+}
+
+/// Record a version of the rules a scan session runs with, marking where it took over
+pub fn record_ruleset(conn: &Connection, scan_id: &str, rules: &RuleSnapshot) -> Result<()> {
+    record_loaded_ruleset(
+        conn,
+        scan_id,
+        rules.version,
+        rules.loaded_at,
+        rules.ruleset.rules.len(),
+        &rules.digest,
+    )
+}
+
+/// Record a version of the rules a scan session ran with from what's known of it
+/// without the rules themselves: when it was loaded, how many rules it had and its
+/// digest
+pub fn record_loaded_ruleset(
+    conn: &Connection,
+    scan_id: &str,
+    version: u32,
+    loaded_at: DateTime<Utc>,
+    rules: usize,
+    digest: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO ruleset_versions (scan_id, version, loaded_at, rules, digest) 
+         VALUES (?, ?, ?, ?, ?)",
+        params![
+            scan_id,
+            version,
+            format_timestamp(&loaded_at),
+            rules as i64,
+            digest
+        ],
+    )
+    .context("Failed to record ruleset version")?;
+
+    // The rules the scan started with identify it; reloads are kept per version
+    if version == 1 {
+        conn.execute(
+            "UPDATE scans SET ruleset_digest = ? WHERE id = ?",
+            params![digest, scan_id],
+        )
+        .context("Failed to record scan ruleset digest")?;
+    }
+
+    Ok(())
+}
+
+/// Record the tuning profile a scan session runs with
+pub fn record_scan_profile(conn: &Connection, scan_id: &str, profile: ScanProfile) -> Result<()> {
+    conn.execute(
+        "UPDATE scans SET profile = ? WHERE id = ?",
+        params![profile.to_string(), scan_id],
+    )
+    .context("Failed to record scan profile")?;
+
+    Ok(())
+}
+
+/// Record the project a scan session is run for
+pub fn record_scan_project(conn: &Connection, scan_id: &str, project: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE scans SET project = ? WHERE id = ?",
+        params![project, scan_id],
+    )
+    .context("Failed to record scan project")?;
+
+    Ok(())
+}
+
+/// Mark a scan session as stopped before every domain was scanned
+pub fn mark_scan_incomplete(conn: &Connection, scan_id: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE scans SET incomplete = 1 WHERE id = ?",
+        params![scan_id],
+    )
+    .context("Failed to mark scan session incomplete")?;
+
+    Ok(())
+}
+
+/// Record per-rule detection counts for a scan session
+pub fn record_rule_counts(
+    conn: &Connection,
+    scan_id: &str,
+    counts: &BTreeMap<String, usize>,
+) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "INSERT OR REPLACE INTO rule_stats (scan_id, rule_name, detected) VALUES (?, ?, ?)",
+    )?;
+    for (rule_name, detected) in counts {
+        stmt.execute(params![scan_id, rule_name, *detected as i64])
+            .context("Failed to record rule counts")?;
+    }
+
+    Ok(())
+}
+
+/// Record the detected findings of a scan session
+pub fn record_scan_findings(conn: &Connection, scan_id: &str, findings: &[Finding]) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    write_scan_findings(&tx, scan_id, findings)?;
+    tx.commit().context("Failed to record scan findings")?;
+
+    Ok(())
+}
+
+/// Keep the detected findings of a scan session within a transaction the caller holds
+pub(crate) fn write_scan_findings(
+    conn: &Connection,
+    scan_id: &str,
+    findings: &[Finding],
+) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "INSERT OR REPLACE INTO scan_findings
+         (scan_id, domain, rule_name, matched_path, scanned_at, tag, status, ruleset_version, evidence, project, ruleset_digest)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )?;
+    for finding in findings.iter().filter(|finding| finding.detected) {
+        stmt.execute(params![
+            scan_id,
+            finding.domain,
+            finding.rule_name,
+            finding.matched_path,
+            format_timestamp(&finding.scanned_at),
+            finding.tag,
+            finding.status,
+            finding.ruleset_version,
+            finding
+                .evidence
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            finding.project,
+            finding.ruleset_digest,
+        ])
+        .context("Failed to record scan findings")?;
+    }
+
+    Ok(())
+}
+
+/// Detected findings of a scan session, ordered by domain and rule
+pub fn get_scan_findings(conn: &Connection, scan_id: &str) -> Result<Vec<Finding>> {
+    let recorded: Option<usize> = conn
+        .query_row(
+            "SELECT findings FROM scans WHERE id = ?",
+            params![scan_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(recorded) = recorded else {
+        anyhow::bail!("Unknown scan: {}", scan_id);
+    };
+
+    let findings = conn
+        .prepare(
+            "SELECT rowid, domain, rule_name, matched_path, 1, scanned_at, NULL, tag, status, ruleset_version, evidence, project, ruleset_digest 
+             FROM scan_findings 
+             WHERE scan_id = ? 
+             ORDER BY domain, rule_name",
+        )?
+        .query_map(params![scan_id], Finding::from_row)?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect scan findings")?;
+
+    // Scans from before findings were kept per scan only have a count
+    if findings.is_empty() && recorded > 0 {
+        anyhow::bail!(
+            "Scan {} has no findings kept to compare: it ran before findings were recorded per scan",
+            scan_id
+        );
+    }
+
+    Ok(findings)
+}
+
+/// Average per-rule detection counts over the most recent scans (excluding `scan_id`).
+///
+/// Returns the number of scans the baseline was computed from along with the averages.
+pub fn get_rule_baseline(
+    conn: &Connection,
+    scan_id: &str,
+    scans: usize,
+) -> Result<(usize, HashMap<String, f64>)> {
+    let scan_ids = conn
+        .prepare(
+            "SELECT DISTINCT rule_stats.scan_id 
+             FROM rule_stats JOIN scans ON scans.id = rule_stats.scan_id 
+             WHERE rule_stats.scan_id != ? 
+             ORDER BY scans.started_at DESC, scans.rowid DESC 
+             LIMIT ?",
+        )?
+        .query_map(params![scan_id, scans as i64], |row| {
+            row.get::<_, String>(0)
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect baseline scans")?;
+
+    let mut totals: HashMap<String, f64> = HashMap::new();
+    let mut stmt = conn.prepare("SELECT rule_name, detected FROM rule_stats WHERE scan_id = ?")?;
+    for id in &scan_ids {
+        let rows = stmt
+            .query_map(params![id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to collect rule counts")?;
+        for (rule_name, detected) in rows {
+            *totals.entry(rule_name).or_insert(0.0) += detected as f64;
+        }
+    }
+
+    let baseline = totals
+        .into_iter()
+        .map(|(rule_name, total)| (rule_name, total / scan_ids.len() as f64))
+        .collect();
+
+    Ok((scan_ids.len(), baseline))
+}
+
+/// Get the most recent scan sessions, newest first
+pub fn get_scan_sessions(conn: &Connection, limit: usize) -> Result<Vec<ScanSession>> {
+    let sessions = conn
+        .prepare(
+            "SELECT id, started_at, finished_at, domains, findings, risk_score, incomplete, profile, project, ruleset_digest 
+             FROM scans 
+             ORDER BY started_at DESC, rowid DESC 
+             LIMIT ?",
+        )?
+        .query_map(params![limit.min(i64::MAX as usize) as i64], ScanSession::from_row)?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect scan sessions")?;
+
+    Ok(sessions)
+}
+
+/// List scan sessions with their risk scores and the change from the previous scan
+pub fn list_scans(db_file: &str, limit: usize, project: Option<&str>) -> Result<()> {
+    let conn = init_db(db_file)?;
+
+    // Fetch one extra session so the oldest listed scan still gets a trend
+    let fetch = match project {
+        Some(_) => usize::MAX,
+        None => limit + 1,
+    };
+    let sessions = of_project(get_scan_sessions(&conn, fetch)?, project);
+    print_scans(&sessions, limit);
+
+    Ok(())
+}
+
+/// The scan sessions run for `project`, or all of them without one
+pub fn of_project(mut sessions: Vec<ScanSession>, project: Option<&str>) -> Vec<ScanSession> {
+    if let Some(project) = project {
+        sessions.retain(|session| session.project.as_deref() == Some(project));
+    }
+    sessions
+}
+
+/// Print up to `limit` scan sessions, newest first, each with the change in risk from
+/// the session after it
+pub fn print_scans(sessions: &[ScanSession], limit: usize) {
+    println!("📋 Scan Sessions:");
+    println!(
+        "{:<38} {:<20} {:<8} {:<9} {:<10} {:<10} {:<10} {:<15}",
+        "ID", "Started At", "Domains", "Findings", "Risk", "Change", "Profile", "Project"
+    );
+    println!("{:-<116}", "");
+
+    for (i, session) in sessions.iter().take(limit).enumerate() {
+        let change = sessions
+            .get(i + 1)
+            .and_then(|previous| risk_change_percent(previous.risk_score, session.risk_score))
+            .map(|pct| format!("{:+.1}%", pct))
+            .unwrap_or_else(|| "-".to_string());
+
+        println!(
+            "{:<38} {:<20} {:<8} {:<9} {:<10.1} {:<10} {:<10} {:<15}{}",
+            session.id,
+            session.started_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            session.domains,
+            session.findings,
+            session.risk_score,
+            change,
+            session
+                .profile
+                .map(|profile| profile.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            truncate_string(session.project.as_deref().unwrap_or("-"), 15),
+            if session.incomplete {
+                " incomplete"
+            } else {
+                ""
+            }
+        );
+    }
+
+    println!("\nTotal scans: {}", sessions.len().min(limit));
+}
+
+/// Query up to `limit` findings matching a query's filters, ignoring its paging
+fn query_findings(
+    conn: &Connection,
+    query: &ListQuery,
+    order_by: &str,
+    limit: usize,
+) -> Result<Vec<Finding>> {
+    let (since, until) = query.range.bounds();
+    let findings = conn
+        .prepare(&format!(
+            "SELECT id, domain, rule_name, matched_path, detected, scanned_at, redirect_chain, tag, status, ruleset_version, evidence, project, ruleset_digest 
+             FROM findings 
+             WHERE (?1 IS NULL OR domain LIKE ?1)
+               AND (?2 IS NULL OR rule_name LIKE ?2)
+               AND (?3 IS NULL OR datetime(scanned_at) >= ?3)
+               AND (?4 IS NULL OR datetime(scanned_at) <= ?4)
+               AND (?6 = 0 OR detected = 1)
+               AND (?7 IS NULL OR project = ?7)
+             ORDER BY {} 
+             LIMIT ?5",
+            order_by
+        ))?
+        .query_map(
+            params![
+                query.domain_pattern.map(|pattern| format!("%{}%", pattern)),
+                query.rule_pattern.map(|pattern| format!("%{}%", pattern)),
+                since,
+                until,
+                limit.min(i64::MAX as usize) as i64,
+                query.detected_only,
+                query.project,
+            ],
+            Finding::from_row,
+        )?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect findings")?;
+
+    Ok(findings)
+}
+
+/// Default ordering of findings queries
+const NEWEST_FIRST: &str = "scanned_at DESC";
+
+/// The page of findings a `results list` query asks for, across every shard
+pub fn find_results(
+    db_file: &str,
+    shards: usize,
+    query: &ListQuery,
+    ruleset: Option<&RuleSet>,
+) -> Result<Vec<Finding>> {
+    query.check(ruleset)?;
+
+    // Every shard contributes its first `offset + limit` findings in the requested
+    // order; severity isn't stored, so sorting by it needs every matching finding
+    let order_by = query.order_by();
+    let fetch = match order_by {
+        Some(_) => query.offset.saturating_add(query.limit),
+        None => usize::MAX,
+    };
+    let mut findings = Vec::new();
+    for path in findings_files(db_file, shards) {
+        let conn = Connection::open(&path).context(format!("Failed to open database: {}", path))?;
+        findings.extend(query_findings(
+            &conn,
+            query,
+            order_by.as_deref().unwrap_or(NEWEST_FIRST),
+            fetch,
+        )?);
+    }
+
+    Ok(query.page(findings, ruleset))
+}
+
+/// Which findings `results list` shows, in what order and from where
+#[derive(Debug, Clone, Default)]
+pub struct ListQuery<'a> {
+    pub domain_pattern: Option<&'a str>,
+    pub rule_pattern: Option<&'a str>,
+    pub range: TimeRange,
+    /// Leave out checks that didn't match
+    pub detected_only: bool,
+    /// Only findings last checked for this project
+    pub project: Option<&'a str>,
+    pub sort: SortBy,
+    pub order: SortOrder,
+    /// Number of findings to skip before the listed ones
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl ListQuery<'_> {
+    /// Sorting by severity needs the rules the findings came from
+    pub fn check(&self, ruleset: Option<&RuleSet>) -> Result<()> {
+        if self.sort == SortBy::Severity && ruleset.is_none() {
+            anyhow::bail!("Sorting by severity needs a rules file (--rules)");
+        }
+        Ok(())
+    }
+
+    /// SQL `ORDER BY` clause for the sort, or `None` when the database can't sort by it
+    pub fn order_by(&self) -> Option<String> {
+        let column = match self.sort {
+            SortBy::Domain => "domain",
+            SortBy::Rule => "rule_name",
+            SortBy::ScannedAt => "scanned_at",
+            SortBy::Severity => return None,
+        };
+        let direction = match self.order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        Some(format!("{} {}, scanned_at DESC, id", column, direction))
+    }
+
+    /// Sort findings and keep the requested page of them
+    pub fn page(&self, mut findings: Vec<Finding>, ruleset: Option<&RuleSet>) -> Vec<Finding> {
+        sort_findings(&mut findings, self.sort, self.order, ruleset);
+        findings
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .collect()
+    }
+}
+
+/// Sort findings by a key, breaking ties newest first; findings of rules without a
+/// severity sort below every known one
+pub fn sort_findings(
+    findings: &mut [Finding],
+    sort: SortBy,
+    order: SortOrder,
+    ruleset: Option<&RuleSet>,
+) {
+    let severities: HashMap<&str, &Severity> = ruleset
+        .map(|ruleset| {
+            ruleset
+                .rules
+                .iter()
+                .filter_map(|rule| Some((rule.name.as_str(), rule.severity.as_ref()?)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    findings.sort_by(|a, b| {
+        let ordering = match sort {
+            SortBy::Domain => a.domain.cmp(&b.domain),
+            SortBy::Rule => a.rule_name.cmp(&b.rule_name),
+            SortBy::Severity => severities
+                .get(a.rule_name.as_str())
+                .cmp(&severities.get(b.rule_name.as_str())),
+            SortBy::ScannedAt => a.scanned_at.cmp(&b.scanned_at),
+        };
+        let ordering = match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        };
+        ordering
+            .then_with(|| b.scanned_at.cmp(&a.scanned_at))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+}
+
+/// What `results list` sorts findings by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortBy {
+    Domain,
+    Rule,
+    /// Severity of the finding's rule, looked up in a rules file
+    Severity,
+    #[default]
+    ScannedAt,
+}
+
+impl SortBy {
+    /// Names read alphabetically, severities and times most pressing first
+    pub fn default_order(self) -> SortOrder {
+        match self {
+            SortBy::Domain | SortBy::Rule => SortOrder::Asc,
+            SortBy::Severity | SortBy::ScannedAt => SortOrder::Desc,
+        }
+    }
+}
+
+impl FromStr for SortBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "domain" => Ok(SortBy::Domain),
+            "rule" => Ok(SortBy::Rule),
+            "severity" => Ok(SortBy::Severity),
+            "scanned_at" => Ok(SortBy::ScannedAt),
+            other => anyhow::bail!(
+                "Unknown sort key: {} (expected domain, rule, severity or scanned_at)",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SortBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortBy::Domain => write!(f, "domain"),
+            SortBy::Rule => write!(f, "rule"),
+            SortBy::Severity => write!(f, "severity"),
+            SortBy::ScannedAt => write!(f, "scanned_at"),
+        }
+    }
+}
+
+/// Direction of a sort
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl FromStr for SortOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "asc" => Ok(SortOrder::Asc),
+            "desc" => Ok(SortOrder::Desc),
+            other => anyhow::bail!("Unknown sort order: {} (expected asc or desc)", other),
+        }
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortOrder::Asc => write!(f, "asc"),
+            SortOrder::Desc => write!(f, "desc"),
+        }
+    }
+}
+
+/// How `results list` prints findings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListFormat {
+    /// Fixed-width table for reading in a terminal
+    #[default]
+    Table,
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl FromStr for ListFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "table" => Ok(ListFormat::Table),
+            "json" => Ok(ListFormat::Json),
+            "csv" => Ok(ListFormat::Csv),
+            "tsv" => Ok(ListFormat::Tsv),
+            other => anyhow::bail!(
+                "Unknown list output: {} (expected table, json, csv or tsv)",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ListFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListFormat::Table => write!(f, "table"),
+            ListFormat::Json => write!(f, "json"),
+            ListFormat::Csv => write!(f, "csv"),
+            ListFormat::Tsv => write!(f, "tsv"),
+        }
+    }
+}
+
+/// Print findings to stdout in the given format
+pub fn print_findings(findings: &[Finding], format: ListFormat) -> Result<()> {
+    write_findings(findings, format, std::io::stdout().lock())
+}
+
+/// Write findings in the given format; CSV and TSV have the columns of a CSV export
+pub fn write_findings<W: Write>(
+    findings: &[Finding],
+    format: ListFormat,
+    mut out: W,
+) -> Result<()> {
+    match format {
+        ListFormat::Table => write_table(findings, &mut out)?,
+        ListFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, findings)
+                .context("Failed to serialize findings to JSON")?;
+            writeln!(out)?;
+        }
+        ListFormat::Csv => write_csv(findings, csv::Writer::from_writer(out))?,
+        ListFormat::Tsv => write_csv(
+            findings,
+            csv::WriterBuilder::new().delimiter(b'\t').from_writer(out),
+        )?,
+    }
+
+    Ok(())
+}
+
+/// Write findings in a table
+fn write_table<W: Write>(findings: &[Finding], out: &mut W) -> Result<()> {
+    writeln!(out, "📋 Scan Results:")?;
+    writeln!(
+        out,
+        "{:<5} {:<30} {:<25} {:<30} {:<10} {:<20}",
+        "ID", "Domain", "Rule", "Path", "Detected", "Scanned At"
+    )?;
+    writeln!(out, "{:-<120}", "")?;
+
+    for finding in findings {
+        writeln!(
+            out,
+            "{:<5} {:<30} {:<25} {:<30} {:<10} {:<20}",
+            finding.id,
+            truncate_string(&finding.domain, 29),
+            truncate_string(&finding.rule_name, 24),
+            truncate_string(&finding.matched_path, 29),
+            if finding.detected {
+                "✅ Yes"
+            } else {
+                "❌ No"
+            },
+            finding.scanned_at.format("%Y-%m-%d %H:%M:%S").to_string()
+        )?;
+    }
+
+    writeln!(out, "\nTotal results: {}", findings.len())?;
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Get every finding, ordered by domain and rule
+pub fn get_all_findings(conn: &Connection) -> Result<Vec<Finding>> {
+    let findings = conn
+        .prepare(
+            "SELECT id, domain, rule_name, matched_path, detected, scanned_at, redirect_chain, tag, status, ruleset_version, evidence, project, ruleset_digest 
+             FROM findings 
+             ORDER BY domain, rule_name",
+        )?
+        .query_map([], Finding::from_row)?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to collect findings")?;
+
+    Ok(findings)
+}
+
+/// Which findings a purge or delete removes: optionally only those last scanned
+/// before a cutoff, for one domain, rule or ID, or where nothing was detected
+#[derive(Debug, Clone, Default)]
+pub struct PurgeFilter {
+    pub before: Option<DateTime<Utc>>,
+    pub domain: Option<String>,
+    pub rule: Option<String>,
+    /// Row ID; SQLite shards number their findings separately
+    pub id: Option<i64>,
+    pub not_detected: bool,
+}
+
+impl PurgeFilter {
+    /// Findings last scanned longer than `age` ago
+    pub fn older_than(age: chrono::Duration) -> Self {
+        Self {
+            before: Some(Utc::now() - age),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the filter narrows the findings down at all
+    pub fn is_empty(&self) -> bool {
+        self.before.is_none()
+            && self.domain.is_none()
+            && self.rule.is_none()
+            && self.id.is_none()
+            && !self.not_detected
+    }
+
+    /// Whether a finding is one the purge removes
+    pub fn matches(&self, finding: &Finding) -> bool {
+        self.before.is_none_or(|before| finding.scanned_at < before)
+            && self
+                .domain
+                .as_ref()
+                .is_none_or(|domain| &finding.domain == domain)
+            && self
+                .rule
+                .as_ref()
+                .is_none_or(|rule| &finding.rule_name == rule)
+            && self.id.is_none_or(|id| finding.id == id)
+            && !(self.not_detected && finding.detected)
+    }
+}
+
+/// Delete the findings a filter selects, returning how many were removed
+pub fn purge_findings(conn: &Connection, filter: &PurgeFilter) -> Result<usize> {
+    let removed = conn
+        .execute(
+            "DELETE FROM findings
+             WHERE (?1 IS NULL OR scanned_at < ?1)
+               AND (?2 IS NULL OR domain = ?2)
+               AND (?3 IS NULL OR rule_name = ?3)
+               AND (?4 IS NULL OR id = ?4)
+               AND (?5 = 0 OR detected = 0)",
+            params![
+                filter.before.as_ref().map(format_timestamp),
+                filter.domain,
+                filter.rule,
+                filter.id,
+                filter.not_detected
+            ],
+        )
+        .context("Failed to purge findings")?;
+
+    Ok(removed)
+}
+
+/// Export findings to a file, or one file per group with `split_by`
+#[allow(dead_code)]
+pub fn export_results(
+    db_file: &str,
+    shards: usize,
+    output_file: &str,
+    format: &str,
+    split_by: Option<SplitBy>,
+) -> Result<()> {
+    export_split(
+        &load_findings(db_file, shards)?,
+        output_file,
+        format,
+        split_by,
+    )
+}
+
+/// Fill in each finding's remediation from the rule of the same name
+pub fn attach_remediation(findings: &mut [Finding], ruleset: &RuleSet) {
+    let remediations: HashMap<&str, &str> = ruleset
+        .rules
+        .iter()
+        .filter_map(|rule| Some((rule.name.as_str(), rule.remediation.as_deref()?)))
+        .collect();
+
+    for finding in findings {
+        finding.remediation = remediations
+            .get(finding.rule_name.as_str())
+            .map(|remediation| remediation.to_string());
+    }
+}
+
+/// Every finding in a database and its shards, by domain and rule
+pub fn load_findings(db_file: &str, shards: usize) -> Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    for path in findings_files(db_file, shards) {
+        let conn = Connection::open(&path).context(format!("Failed to open database: {}", path))?;
+        findings.extend(get_all_findings(&conn)?);
+    }
+    sort_by_domain_and_rule(&mut findings);
+
+    Ok(findings)
+}
+
+/// How an export is split into several files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitBy {
+    /// One file per input tag
+    Tag,
+}
+
+impl FromStr for SplitBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "tag" => Ok(SplitBy::Tag),
+            other => anyhow::bail!("Unknown export split: {} (expected tag)", other),
+        }
+    }
+}
+
+impl fmt::Display for SplitBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitBy::Tag => write!(f, "tag"),
+        }
+    }
+}
+
+/// Group name of findings without a tag
+const UNTAGGED: &str = "untagged";
+
+/// Write findings to `output_file`, or with `split_by` to one file per group, named by
+/// inserting the group before the extension (`findings.csv` → `findings-prod.csv`)
+pub fn export_split(
+    findings: &[Finding],
+    output_file: &str,
+    format: &str,
+    split_by: Option<SplitBy>,
+) -> Result<()> {
+    let Some(split_by) = split_by else {
+        return export_findings(findings, output_file, format);
+    };
+
+    let mut groups: BTreeMap<&str, Vec<Finding>> = BTreeMap::new();
+    for finding in findings {
+        let group = match split_by {
+            SplitBy::Tag => finding.tag.as_deref().unwrap_or(UNTAGGED),
+        };
+        groups.entry(group).or_default().push(finding.clone());
+    }
+
+    for (group, findings) in groups {
+        let path = split_path(output_file, group);
+        export_findings(&findings, &path, format)?;
+    }
+
+    Ok(())
+}
+
+/// `output_file` with `-<group>` inserted before its extension
+fn split_path(output_file: &str, group: &str) -> String {
+    let path = Path::new(output_file);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, group, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, group),
+    };
+
+    path.with_file_name(name).to_string_lossy().to_string()
+}
+
+/// Write findings to a file in the given format (csv, json or jsonl)
+pub fn export_findings(findings: &[Finding], output_file: &str, format: &str) -> Result<()> {
+    // Ensure parent directory exists
+    if let Some(parent) = Path::new(output_file).parent() {
+        if !parent.exists() {
+            create_dir_all(parent).context("Failed to create output directory")?;
+        }
+    }
+
+    match format.to_lowercase().as_str() {
+        "csv" => export_to_csv(findings, output_file)?,
+        "json" => export_to_json(findings, output_file)?,
+        "jsonl" | "ndjson" => export_to_jsonl(findings, output_file)?,
+        _ => anyhow::bail!("Unsupported export format: {}", format),
+    }
+
+    info!("✅ Exported {} findings to {}", findings.len(), output_file);
+
+    Ok(())
+}
+
+/// Export findings to CSV format
+fn export_to_csv(findings: &[Finding], output_file: &str) -> Result<()> {
+    let path = PathBuf::from(output_file);
+    write_csv(findings, csv::Writer::from_path(path)?)
+}
+
+/// Write findings as CSV records under a header
+fn write_csv<W: Write>(findings: &[Finding], mut writer: csv::Writer<W>) -> Result<()> {
+    // Write header
+    writer.write_record([
+        "ID",
+        "Domain",
+        "Rule",
+        "Path",
+        "Detected",
+        "Scanned At",
+        "Redirect Chain",
+        "Tag",
+        "Status",
+        "Ruleset Version",
+        "Evidence Offset",
+        "Evidence",
+        "Remediation",
+        "Notes",
+    ])?;
+
+    // Write findings
+    for finding in findings {
+        writer.write_record([
+            &finding.id.to_string(),
+            &finding.domain,
+            &finding.rule_name,
+            &finding.matched_path,
+            &finding.detected.to_string(),
+            &finding.scanned_at.to_rfc3339(),
+            &finding.redirect_chain.join(" -> "),
+            finding.tag.as_deref().unwrap_or(""),
+            &finding
+                .status
+                .map(|status| status.to_string())
+                .unwrap_or_default(),
+            &finding
+                .ruleset_version
+                .map(|version| version.to_string())
+                .unwrap_or_default(),
+            &finding
+                .evidence
+                .as_ref()
+                .map(|evidence| evidence.offset.to_string())
+                .unwrap_or_default(),
+            &finding
+                .evidence
+                .as_ref()
+                .map(|evidence| evidence.snippet.clone())
+                .unwrap_or_default(),
+            finding.remediation.as_deref().unwrap_or(""),
+            &finding
+                .notes
+                .iter()
+                .map(|note| note.to_string())
+                .collect::<Vec<_>>()
+                .join("; "),
+        ])?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Export findings to JSON format
+pub fn export_to_json(findings: &[Finding], output_file: &str) -> Result<()> {
+    let json =
+        serde_json::to_string_pretty(findings).context("Failed to serialize findings to JSON")?;
+
+    std::fs::write(output_file, json).context("Failed to write JSON to output file")?;
+
+    Ok(())
+}
+
+/// Export findings as JSON Lines: one compact finding per line
+pub fn export_to_jsonl(findings: &[Finding], output_file: &str) -> Result<()> {
+    let file = File::create(output_file).context("Failed to create JSONL output file")?;
+    let mut writer = BufWriter::new(file);
+
+    for finding in findings {
+        serde_json::to_writer(&mut writer, finding)
+            .context("Failed to serialize finding to JSON")?;
+        writer
+            .write_all(b"\n")
+            .context("Failed to write JSONL to output file")?;
+    }
+    writer
+        .flush()
+        .context("Failed to write JSONL to output file")?;
+
+    Ok(())
+}
+
+/// Record a finding in the database (alias for insert_finding with severity)
+#[allow(dead_code)]
+pub fn record_finding(
     conn: &Connection,
     domain: &str,
     matched_path: &str,
@@ -363,6 +1939,340 @@ pub fn get_unique_domains_count(conn: &Connection) -> Result<usize> {
     Ok(count as usize)
 }
 
+/// Path of one shard of a database: `results.sqlite` becomes `results-shard0.sqlite`
+pub fn shard_path(db_file: &str, index: usize) -> String {
+    let path = Path::new(db_file);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-shard{}.{}", stem, index, ext.to_string_lossy()),
+        None => format!("{}-shard{}", stem, index),
+    };
+
+    path.with_file_name(name).to_string_lossy().to_string()
+}
+
+/// Files holding findings: the database itself, or its shards
+pub(crate) fn findings_files(db_file: &str, shards: usize) -> Vec<String> {
+    if shards > 1 {
+        (0..shards).map(|i| shard_path(db_file, i)).collect()
+    } else {
+        vec![db_file.to_string()]
+    }
+}
+
+/// Shard a domain's findings belong in.
+///
+/// Uses FNV-1a rather than the std hasher so a domain maps to the same shard
+/// across builds and runs.
+pub fn shard_index(domain: &str, shards: usize) -> usize {
+    let hash = domain
+        .to_lowercase()
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+
+    (hash % shards.max(1) as u64) as usize
+}
+
+fn sort_by_domain_and_rule(findings: &mut [Finding]) {
+    findings.sort_by(|a, b| {
+        a.domain
+            .cmp(&b.domain)
+            .then_with(|| a.rule_name.cmp(&b.rule_name))
+    });
+}
+
+/// A results database with findings split across several files by domain hash.
+///
+/// Scan sessions, risk scores and rule counts stay in the primary database file;
+/// each shard holds the findings of the domains that hash to it.
+pub struct ShardedDb {
+    pub primary: Connection,
+    pub shards: Vec<Connection>,
+}
+
+impl ShardedDb {
+    /// Open (creating if needed) a database and its `shards` shard files
+    pub fn open(db_file: &str, shards: usize) -> Result<Self> {
+        Self::open_with(db_file, shards, true)
+    }
+
+    /// Open (creating if needed) a database and its `shards` shard files, with or
+    /// without the performance tuning
+    pub fn open_with(db_file: &str, shards: usize, tuning: bool) -> Result<Self> {
+        if shards < 2 {
+            anyhow::bail!("A sharded database needs at least 2 shards");
+        }
+
+        Ok(Self {
+            primary: init_db_with(db_file, tuning)?,
+            shards: (0..shards)
+                .map(|i| init_db_with(&shard_path(db_file, i), tuning))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Connection to the shard holding a domain's findings
+    pub fn shard(&mut self, domain: &str) -> &mut Connection {
+        let index = shard_index(domain, self.shards.len());
+        &mut self.shards[index]
+    }
+
+    /// Detected findings recorded at or after the given time, across all shards
+    pub fn get_detected_since(&self, since: DateTime<Utc>) -> Result<Vec<Finding>> {
+        let mut findings = Vec::new();
+        for shard in &self.shards {
+            findings.extend(get_detected_since(shard, since)?);
+        }
+        sort_by_domain_and_rule(&mut findings);
+
+        Ok(findings)
+    }
+
+    /// Detected findings with cache validators across all shards, ordered by domain and rule
+    pub fn get_revalidatable(&self) -> Result<Vec<Finding>> {
+        let mut findings = Vec::new();
+        for shard in &self.shards {
+            findings.extend(get_revalidatable(shard)?);
+        }
+        sort_by_domain_and_rule(&mut findings);
+
+        Ok(findings)
+    }
+
+    /// Every finding across all shards, ordered by domain and rule
+    pub fn get_all_findings(&self) -> Result<Vec<Finding>> {
+        let mut findings = Vec::new();
+        for shard in &self.shards {
+            findings.extend(get_all_findings(shard)?);
+        }
+        sort_by_domain_and_rule(&mut findings);
+
+        Ok(findings)
+    }
+
+    /// Delete the findings a filter selects from every shard
+    pub fn purge_findings(&self, filter: &PurgeFilter) -> Result<usize> {
+        let mut removed = 0;
+        for shard in &self.shards {
+            removed += purge_findings(shard, filter)?;
+        }
+
+        Ok(removed)
+    }
+}
+
+/// Copy the findings of every shard into the primary database, keeping the newest
+/// result for each domain and rule. Returns the number of findings merged.
+pub fn merge_shards(db_file: &str, shards: usize) -> Result<usize> {
+    let paths: Vec<String> = (0..shards).map(|i| shard_path(db_file, i)).collect();
+    if let Some(missing) = paths.iter().find(|path| !Path::new(path).exists()) {
+        anyhow::bail!("Shard not found: {}", missing);
+    }
+
+    let conn = init_db(db_file)?;
+    let mut merged = 0;
+
+    for path in &paths {
+        merged += attached(&conn, path, || {
+            let merged = merge_findings(&conn)?;
+            merge_notes(&conn)?;
+            merge_false_positives(&conn)?;
+            Ok(merged)
+        })
+        .context(format!("Failed to merge shard: {}", path))?;
+    }
+
+    info!(
+        "✅ Merged {} findings from {} shards into {}",
+        merged, shards, db_file
+    );
+
+    Ok(merged)
+}
+
+/// Merge separate results databases, such as those of several ad-hoc runs, into one
+/// (created if missing). Findings keep the newest result for each domain and rule;
+/// scan sessions are copied along with their risk scores, rule counts and findings.
+/// Returns the number of findings merged.
+pub fn merge_databases(into: &str, sources: &[String]) -> Result<usize> {
+    if let Some(missing) = sources.iter().find(|path| !Path::new(path).exists()) {
+        anyhow::bail!("Database not found: {}", missing);
+    }
+    if let Some(same) = sources.iter().find(|path| is_same_file(path, into)) {
+        anyhow::bail!("Can't merge a database into itself: {}", same);
+    }
+
+    let conn = init_db(into)?;
+    let mut merged = 0;
+
+    for path in sources {
+        merged += attached(&conn, path, || {
+            let tx = conn.unchecked_transaction()?;
+            let merged = merge_findings(&tx)?;
+            merge_notes(&tx)?;
+            merge_false_positives(&tx)?;
+            merge_certificates(&tx)?;
+            for (table, columns) in SCAN_COLUMNS {
+                tx.execute(
+                    &format!(
+                        "INSERT OR IGNORE INTO {table} ({columns}) SELECT {columns} FROM source.{table}"
+                    ),
+                    [],
+                )
+                .context(format!("Failed to merge {}", table))?;
+            }
+            tx.commit()?;
+            Ok(merged)
+        })
+        .context(format!("Failed to merge database: {}", path))?;
+    }
+
+    info!(
+        "✅ Merged {} findings from {} databases into {}",
+        merged,
+        sources.len(),
+        into
+    );
+
+    Ok(merged)
+}
+
+/// Copy the attached database's notes on findings, skipping any already copied by an
+/// earlier merge
+fn merge_notes(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "INSERT INTO finding_notes (domain, rule_name, note, author, created_at)
+         SELECT domain, rule_name, note, author, created_at
+         FROM source.finding_notes AS source_note
+         WHERE NOT EXISTS (
+             SELECT 1 FROM finding_notes
+             WHERE domain = source_note.domain
+               AND rule_name = source_note.rule_name
+               AND note = source_note.note
+               AND created_at = source_note.created_at
+         )
+         ORDER BY id",
+        [],
+    )
+    .context("Failed to merge notes")?;
+
+    Ok(())
+}
+
+/// Copy the attached database's false positive marks, the latest mark of a domain and
+/// rule winning
+fn merge_false_positives(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO false_positives (domain, rule_name, skip_scans, marked_at)
+         SELECT domain, rule_name, skip_scans, marked_at
+         FROM source.false_positives AS source_fp
+         WHERE NOT EXISTS (
+             SELECT 1 FROM false_positives
+             WHERE domain = source_fp.domain
+               AND rule_name = source_fp.rule_name
+               AND datetime(marked_at) > datetime(source_fp.marked_at)
+         )",
+        [],
+    )
+    .context("Failed to merge false positives")?;
+
+    Ok(())
+}
+
+/// Copy the attached database's certificates, the one seen last for a host winning
+fn merge_certificates(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO certificates (host, subject, issuer, sans, serial, not_before, not_after, seen_at)
+         SELECT host, subject, issuer, sans, serial, not_before, not_after, seen_at
+         FROM source.certificates AS source_cert
+         WHERE NOT EXISTS (
+             SELECT 1 FROM certificates
+             WHERE host = source_cert.host
+               AND datetime(seen_at) > datetime(source_cert.seen_at)
+         )",
+        [],
+    )
+    .context("Failed to merge certificates")?;
+
+    Ok(())
+}
+
+/// Columns of the per-scan tables `merge_databases` copies, scan sessions first
+const SCAN_COLUMNS: &[(&str, &str)] = &[
+    (
+        "scans",
+        "id, started_at, finished_at, domains, findings, risk_score, incomplete, profile, project, ruleset_digest",
+    ),
+    ("domain_risk", "scan_id, domain, risk_score, findings"),
+    ("rule_stats", "scan_id, rule_name, detected"),
+    (
+        "ruleset_versions",
+        "scan_id, version, loaded_at, rules, digest",
+    ),
+    (
+        "scan_findings",
+        "scan_id, domain, rule_name, matched_path, scanned_at, tag, status, ruleset_version, evidence, project, ruleset_digest",
+    ),
+    (
+        "domain_scans",
+        "scan_id, domain, scanned_at, ips, technologies, error",
+    ),
+];
+
+/// Run `merge` with another database attached as `source`, brought up to the current
+/// schema first
+fn attached<T>(conn: &Connection, path: &str, merge: impl FnOnce() -> Result<T>) -> Result<T> {
+    drop(init_db(path)?);
+
+    conn.execute("ATTACH DATABASE ? AS source", params![path])
+        .context(format!("Failed to attach database: {}", path))?;
+    let result = merge();
+    conn.execute("DETACH DATABASE source", [])
+        .context("Failed to detach database")?;
+
+    result
+}
+
+/// Copy the attached database's findings, keeping the newest result for each domain
+/// and rule
+fn merge_findings(conn: &Connection) -> Result<usize> {
+    let merged = conn.execute(
+        "INSERT INTO findings (domain, rule_name, matched_path, detected, scanned_at, user_agent, redirect_chain, tag, status, ruleset_version, evidence, project, ruleset_digest)
+         SELECT domain, rule_name, matched_path, detected, scanned_at, user_agent, redirect_chain, tag, status, ruleset_version, evidence, project, ruleset_digest 
+         FROM source.findings WHERE true
+         ON CONFLICT(domain, rule_name) 
+         DO UPDATE SET 
+            matched_path = excluded.matched_path,
+            detected = excluded.detected,
+            scanned_at = excluded.scanned_at,
+            user_agent = excluded.user_agent,
+            redirect_chain = excluded.redirect_chain,
+            tag = excluded.tag,
+            status = excluded.status,
+            ruleset_version = excluded.ruleset_version,
+            evidence = excluded.evidence,
+            project = excluded.project,
+            ruleset_digest = excluded.ruleset_digest
+         WHERE datetime(excluded.scanned_at) >= datetime(findings.scanned_at)",
+        [],
+    )?;
+
+    Ok(merged)
+}
+
+/// Whether two paths name the same file, as far as can be told
+fn is_same_file(a: &str, b: &str) -> bool {
+    match (Path::new(a).canonicalize(), Path::new(b).canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
 /// Helper to truncate a string to max_length with ellipsis if needed
 pub fn truncate_string(s: &str, max_length: usize) -> String {
     if s.len() <= max_length {
@@ -0,0 +1,298 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::path::Path;
+
+use crate::db::{self, FalsePositive, Finding, Note};
+use crate::rules::{RuleSet, Severity};
+
+/// Everything known about one finding, for `results show`
+#[derive(Debug, Serialize)]
+pub struct FindingDetail {
+    #[serde(flatten)]
+    pub finding: Finding,
+    /// Description of the finding's rule, from the rules file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Severity of the finding's rule, from the rules file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    /// Other detected findings for the same domain, by rule
+    pub related: Vec<Finding>,
+    /// Analysts' notes on the finding, oldest first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+    /// The finding's false positive mark, if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub false_positive: Option<FalsePositive>,
+}
+
+/// Look up a finding by ID in a database or its shards, returning it with the
+/// database it's in.
+///
+/// Shards number their findings separately, so an ID found in more than one shard
+/// is refused rather than guessed at.
+fn locate(db_file: &str, shards: usize, id: i64) -> Result<(Connection, Finding)> {
+    let mut found = Vec::new();
+    for path in db::findings_files(db_file, shards) {
+        if !Path::new(&path).exists() {
+            anyhow::bail!("Results not found: {}", path);
+        }
+        // Brought up to the current schema, which notes need
+        let conn = db::init_db(&path)?;
+        if let Some(finding) = db::get_finding(&conn, id)? {
+            found.push((conn, finding));
+        }
+    }
+
+    if found.len() > 1 {
+        anyhow::bail!(
+            "Finding {} is in {} shards; merge them first with `fatt results merge`",
+            id,
+            found.len()
+        );
+    }
+    found
+        .pop()
+        .with_context(|| format!("Finding not found: {}", id))
+}
+
+/// Look up a finding by ID in a database or its shards, with the related findings
+/// of its domain, its notes and, given the rules, its rule's details
+pub fn find_finding(
+    db_file: &str,
+    shards: usize,
+    id: i64,
+    ruleset: Option<&RuleSet>,
+) -> Result<FindingDetail> {
+    let (conn, mut finding) = locate(db_file, shards, id)?;
+    let mut related: Vec<Finding> = db::get_domain_findings(&conn, &finding.domain)?
+        .into_iter()
+        .filter(|related| related.id != id && related.detected)
+        .collect();
+    let notes = db::get_notes(&conn, &finding.domain, &finding.rule_name)?;
+    let false_positive = db::get_false_positive(&conn, &finding.domain, &finding.rule_name)?;
+
+    let rule = ruleset.and_then(|ruleset| {
+        ruleset
+            .rules
+            .iter()
+            .find(|rule| rule.name == finding.rule_name)
+    });
+    if let Some(ruleset) = ruleset {
+        db::attach_remediation(std::slice::from_mut(&mut finding), ruleset);
+        db::attach_remediation(&mut related, ruleset);
+    }
+
+    Ok(FindingDetail {
+        description: rule.and_then(|rule| rule.description.clone()),
+        severity: rule.and_then(|rule| rule.severity.clone()),
+        finding,
+        related,
+        notes,
+        false_positive,
+    })
+}
+
+/// Author of notes added without one: `FATT_AUTHOR`, or else the login user
+pub fn default_author() -> Option<String> {
+    ["FATT_AUTHOR", "USER", "USERNAME"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .map(|author| author.trim().to_string())
+        .find(|author| !author.is_empty())
+}
+
+/// Add an analyst's note to a finding, such as why it was triaged the way it was.
+/// The note is kept with the finding's domain and rule, so it stays attached when
+/// the finding is checked again.
+pub fn annotate(
+    db_file: &str,
+    shards: usize,
+    id: i64,
+    note: &str,
+    author: Option<&str>,
+) -> Result<Finding> {
+    let note = note.trim();
+    if note.is_empty() {
+        anyhow::bail!("The note is empty");
+    }
+
+    let (conn, finding) = locate(db_file, shards, id)?;
+    db::add_note(&conn, &finding.domain, &finding.rule_name, note, author)?;
+    println!(
+        "📝 Added a note to finding {} ({} on {})",
+        finding.id, finding.rule_name, finding.domain
+    );
+
+    Ok(finding)
+}
+
+/// Mark a finding as a false positive, leaving its domain and rule out of exports and,
+/// with `skip_scans`, out of future scans; `clear` removes the mark instead. Like a
+/// note, the mark is kept with the domain and rule rather than the finding.
+pub fn mark_false_positive(
+    db_file: &str,
+    shards: usize,
+    id: i64,
+    skip_scans: bool,
+    clear: bool,
+) -> Result<Finding> {
+    let (conn, finding) = locate(db_file, shards, id)?;
+    if clear {
+        if db::unmark_false_positive(&conn, &finding.domain, &finding.rule_name)? {
+            println!(
+                "↩️  Finding {} ({} on {}) is no longer a false positive",
+                finding.id, finding.rule_name, finding.domain
+            );
+        } else {
+            println!("Finding {} isn't marked as a false positive", finding.id);
+        }
+        return Ok(finding);
+    }
+
+    db::mark_false_positive(&conn, &finding.domain, &finding.rule_name, skip_scans)?;
+    println!(
+        "🙈 Marked finding {} ({} on {}) as a false positive{}",
+        finding.id,
+        finding.rule_name,
+        finding.domain,
+        if skip_scans {
+            "; future scans skip it"
+        } else {
+            ""
+        }
+    );
+
+    Ok(finding)
+}
+
+/// Print everything about a finding
+pub fn show_finding(
+    db_file: &str,
+    shards: usize,
+    id: i64,
+    ruleset: Option<&RuleSet>,
+    format: &str,
+) -> Result<()> {
+    let detail = find_finding(db_file, shards, id, ruleset)?;
+
+    match format.to_lowercase().as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&detail)?),
+        "text" => print_detail(&detail),
+        _ => anyhow::bail!("Unsupported show format: {}", format),
+    }
+
+    Ok(())
+}
+
+fn print_detail(detail: &FindingDetail) {
+    let finding = &detail.finding;
+
+    println!("🔎 Finding {}", finding.id);
+    println!("\nDomain:      {}", finding.domain);
+    println!("Rule:        {}", finding.rule_name);
+    if let Some(severity) = &detail.severity {
+        println!("Severity:    {}", severity);
+    }
+    if let Some(description) = &detail.description {
+        println!("Description: {}", description);
+    }
+    println!("Path:        {}", finding.matched_path);
+    println!(
+        "Detected:    {}",
+        if finding.detected {
+            "✅ Yes"
+        } else {
+            "❌ No"
+        }
+    );
+    if let Some(status) = finding.status {
+        println!("Status:      {}", status);
+    }
+    if let Some(tag) = &finding.tag {
+        println!("Tag:         {}", tag);
+    }
+    println!(
+        "Scanned at:  {}",
+        finding.scanned_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    match (finding.ruleset_version, &finding.ruleset_digest) {
+        (Some(version), Some(digest)) => println!("Rules:       version {} ({})", version, digest),
+        (Some(version), None) => println!("Rules:       version {}", version),
+        (None, Some(digest)) => println!("Rules:       {}", digest),
+        (None, None) => {}
+    }
+    if let Some(false_positive) = &detail.false_positive {
+        println!(
+            "Triage:      false positive since {}{}",
+            false_positive.marked_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            if false_positive.skip_scans {
+                ", skipped by scans"
+            } else {
+                ""
+            }
+        );
+    }
+
+    if !finding.redirect_chain.is_empty() {
+        println!("\nRedirects:");
+        for url in &finding.redirect_chain {
+            println!("  → {}", url);
+        }
+    }
+
+    if let Some(evidence) = &finding.evidence {
+        println!(
+            "\nEvidence (status {}, match at byte {}{}):",
+            evidence.status,
+            evidence.offset,
+            if evidence.unchanged {
+                ", unchanged since first observed"
+            } else {
+                ""
+            }
+        );
+        for line in evidence.snippet.lines() {
+            println!("  {}", line);
+        }
+    }
+
+    if let Some(remediation) = &finding.remediation {
+        println!("\nRemediation: {}", remediation);
+    }
+
+    if !detail.notes.is_empty() {
+        println!("\nNotes:");
+        for note in &detail.notes {
+            match &note.author {
+                Some(author) => println!(
+                    "  {}  {}: {}",
+                    note.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    author,
+                    note.note
+                ),
+                None => println!(
+                    "  {}  {}",
+                    note.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    note.note
+                ),
+            }
+        }
+    }
+
+    println!(
+        "\nRelated findings for {}: {}",
+        finding.domain,
+        detail.related.len()
+    );
+    for related in &detail.related {
+        println!(
+            "  {:<5} {:<25} {:<30} {}",
+            related.id,
+            db::truncate_string(&related.rule_name, 24),
+            db::truncate_string(&related.matched_path, 29),
+            related.scanned_at.format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+}
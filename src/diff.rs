@@ -0,0 +1,195 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::db::Finding;
+use crate::risk::{self, RiskChange, RiskWeights};
+use crate::rules::RuleSet;
+use crate::store::{self, StoreKind};
+
+/// How the detected findings of two scans compare
+#[derive(Debug, Default, Serialize)]
+pub struct FindingsDiff {
+    /// Detected in the newer scan only
+    pub new: Vec<Finding>,
+    /// Detected in the older scan only
+    pub resolved: Vec<Finding>,
+    /// Detected in both scans, as seen by the newer one
+    pub unchanged: Vec<Finding>,
+    /// Digests of the rules the older scan's findings were checked with
+    pub from_rulesets: Vec<String>,
+    /// Digests of the rules the newer scan's findings were checked with
+    pub to_rulesets: Vec<String>,
+    /// Risk score of each side's detected findings; unknown without the rules
+    pub risk: Option<RiskChange>,
+}
+
+impl FindingsDiff {
+    /// Whether the two sides were checked with different rules, so a resolved
+    /// finding may be down to a changed rule rather than a fix
+    pub fn rules_changed(&self) -> bool {
+        !self.from_rulesets.is_empty()
+            && !self.to_rulesets.is_empty()
+            && self.from_rulesets != self.to_rulesets
+    }
+}
+
+/// Distinct digests of the rules findings were checked with
+fn rulesets(findings: &[Finding]) -> Vec<String> {
+    findings
+        .iter()
+        .filter(|finding| finding.detected)
+        .filter_map(|finding| finding.ruleset_digest.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Compare the detected findings of an older and a newer scan by domain and rule
+pub fn diff_findings(from: &[Finding], to: &[Finding]) -> FindingsDiff {
+    let detected = |findings: &[Finding]| -> BTreeMap<(String, String), Finding> {
+        findings
+            .iter()
+            .filter(|finding| finding.detected)
+            .map(|finding| {
+                (
+                    (finding.domain.clone(), finding.rule_name.clone()),
+                    finding.clone(),
+                )
+            })
+            .collect()
+    };
+    let mut diff = FindingsDiff {
+        from_rulesets: rulesets(from),
+        to_rulesets: rulesets(to),
+        ..Default::default()
+    };
+    let mut from = detected(from);
+    let to = detected(to);
+
+    for (key, finding) in to {
+        match from.remove(&key) {
+            Some(_) => diff.unchanged.push(finding),
+            None => diff.new.push(finding),
+        }
+    }
+    diff.resolved = from.into_values().collect();
+
+    diff
+}
+
+/// One side of a diff: a scan session, or the current findings of a database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffSide {
+    pub database: String,
+    pub scan_id: Option<String>,
+}
+
+impl DiffSide {
+    fn describe(&self) -> String {
+        let database = store::redact_url(&self.database);
+        match &self.scan_id {
+            Some(scan_id) => format!("scan {} in {}", scan_id, database),
+            None => database,
+        }
+    }
+
+    /// Detected findings of this side
+    fn findings(&self, kind: StoreKind, shards: usize) -> Result<Vec<Finding>> {
+        let store = store::open_results(kind, &self.database, shards)?;
+
+        let findings = match &self.scan_id {
+            Some(scan_id) => store.scan_findings(scan_id)?,
+            None => store.findings()?,
+        };
+
+        Ok(findings
+            .into_iter()
+            .filter(|finding| finding.detected)
+            .collect())
+    }
+}
+
+/// Print the new, resolved and unchanged findings between two scans or databases;
+/// `ruleset` gives the rules' severities for the change in risk
+pub fn diff_results(
+    kind: StoreKind,
+    shards: usize,
+    from: &DiffSide,
+    to: &DiffSide,
+    ruleset: Option<&RuleSet>,
+    weights: &RiskWeights,
+    format: &str,
+) -> Result<()> {
+    if from == to {
+        anyhow::bail!("Nothing to compare: both sides are {}", from.describe());
+    }
+
+    let old = from
+        .findings(kind, shards)
+        .context(format!("Failed to read {}", from.describe()))?;
+    let new = to
+        .findings(kind, shards)
+        .context(format!("Failed to read {}", to.describe()))?;
+    let mut diff = diff_findings(&old, &new);
+    diff.risk = ruleset.map(|ruleset| {
+        RiskChange::new(
+            risk::score_findings(&old, ruleset, weights).total,
+            risk::score_findings(&new, ruleset, weights).total,
+        )
+    });
+
+    match format.to_lowercase().as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&diff)?),
+        "text" => print_diff(from, to, &diff),
+        _ => anyhow::bail!("Unsupported diff format: {}", format),
+    }
+
+    Ok(())
+}
+
+fn print_diff(from: &DiffSide, to: &DiffSide, diff: &FindingsDiff) {
+    println!("🔀 {} → {}", from.describe(), to.describe());
+    if !diff.from_rulesets.is_empty() || !diff.to_rulesets.is_empty() {
+        println!(
+            "Rules: {} → {}",
+            describe_rulesets(&diff.from_rulesets),
+            describe_rulesets(&diff.to_rulesets)
+        );
+    }
+    if diff.rules_changed() {
+        println!("⚠️ The rules changed in between: a resolved finding may be a changed rule rather than a fix");
+    }
+
+    for (title, findings) in [("➕ New", &diff.new), ("✅ Resolved", &diff.resolved)] {
+        println!("\n{} ({}):", title, findings.len());
+        for finding in findings {
+            println!(
+                "  {:<30} {:<20} {:<30} {}",
+                finding.domain,
+                finding.rule_name,
+                finding.matched_path,
+                finding.ruleset_digest.as_deref().unwrap_or("-")
+            );
+        }
+    }
+
+    println!(
+        "\n{} new, {} resolved, {} unchanged",
+        diff.new.len(),
+        diff.resolved.len(),
+        diff.unchanged.len()
+    );
+    if let Some(risk) = &diff.risk {
+        println!("Risk: {}", risk);
+    }
+}
+
+/// Digests of one side's rules for display, `unknown` for findings stored without one
+fn describe_rulesets(digests: &[String]) -> String {
+    if digests.is_empty() {
+        "unknown".to_string()
+    } else {
+        digests.join(", ")
+    }
+}
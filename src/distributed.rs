@@ -1,14 +1,71 @@
 use anyhow::{Context, Result};
 use bincode::{config, Decode, Encode};
+use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
+use reqwest::Client;
+use rustls::pki_types::ServerName;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::tcp::OwnedWriteHalf;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::Mutex;
-use tracing::{debug, error, info};
+use tokio::sync::{Mutex, OnceCell};
+use tokio::task::JoinHandle;
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use tracing::{debug, error, info, warn};
+
+use crate::certs::CertAuthority;
+use crate::config::ScanConfig;
+use crate::db;
+use crate::evidence::Evidence;
+use crate::pipeline::{self, PipelineOptions};
+use crate::resolver::{self, DnsResolver};
+use crate::risk::{self, RiskWeights};
+use crate::rules::{self, Rule, RuleSet, RuleSnapshot};
+use crate::scanner::{self, HttpClientConfig, ScanOptions, ScanResources};
+use crate::store::{self, FindingDetails, PendingFinding, SharedStore, StoreKind};
+use crate::stream::{BatchProgress, EventStream, StreamEvent, WorkerSummary};
+use crate::utils::{self, RetryPolicy};
+
+/// Interval between updates of the registered workers on the master's event stream
+const WORKERS_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Domains a master hands a worker at once unless told otherwise
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// How long a worker has to report a batch's results unless told otherwise, before the
+/// master hands the batch to another worker
+pub const DEFAULT_BATCH_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Attempts a worker makes to reconnect to the master unless told otherwise
+pub const DEFAULT_RECONNECT_ATTEMPTS: u32 = 20;
+
+/// Delay before a worker's first attempt to reconnect unless told otherwise, doubled
+/// after every failed one
+pub const DEFAULT_RECONNECT_BACKOFF_MS: u64 = 1000;
+
+/// Longest a worker waits between attempts to reconnect
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Interval between the heartbeats a worker sends the master with its status
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Interval between checks for batches whose workers have gone quiet
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Largest message accepted from the other end, so a corrupted length can't make a
+/// reader allocate gigabytes
+const MAX_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Read half of a connection, plain TCP or TLS
+type MessageReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Write half of a connection, plain TCP or TLS
+type MessageWriter = Box<dyn AsyncWrite + Send + Unpin>;
 
 /// Configuration for a worker node
 #[derive(Debug, Clone)]
@@ -21,6 +78,587 @@ pub struct WorkerConfig {
 
     /// Maximum concurrency
     pub concurrency: usize,
+
+    /// How to connect to the master over TLS; without it the connection is plain TCP
+    pub tls: Option<WorkerTls>,
+
+    /// Attempts to reconnect after losing the master, and the backoff between them;
+    /// with no retries the worker stops instead
+    pub reconnect: RetryPolicy,
+
+    /// Settings the worker checks domains with, as `fatt scan` takes them: timeouts,
+    /// retries, schemes, ports, proxy and the like
+    pub scan: ScanConfig,
+}
+
+/// How a worker verifies the master, and the certificate it proves its own identity
+/// with, if any
+#[derive(Debug, Clone)]
+pub struct WorkerTls {
+    /// CA certificate the master's certificate must chain to
+    pub ca_cert: PathBuf,
+
+    /// Worker certificate, issued by `fatt master issue-cert`. Without one the
+    /// connection is still encrypted, but a master with a CA refuses it.
+    pub cert: Option<PathBuf>,
+
+    /// Worker private key
+    pub key: Option<PathBuf>,
+
+    /// Name to verify the master's certificate against (default: the master's host)
+    pub server_name: Option<String>,
+}
+
+/// Configuration for the master node
+#[derive(Debug, Clone)]
+pub struct MasterConfig {
+    /// Address to listen for workers on
+    pub listen: String,
+
+    /// CA that issued the worker certificates. With one, workers connect over TLS and
+    /// register only with an unrevoked certificate issued for their ID.
+    pub ca: Option<CertAuthority>,
+
+    /// Names the master's certificate is issued for
+    pub server_names: Vec<String>,
+
+    /// Certificate the master presents instead of one issued by its CA. Without a CA,
+    /// workers connect over TLS but register without certificates.
+    pub tls: Option<MasterTls>,
+
+    /// Address to stream the registered workers on, for `fatt tui --connect`
+    pub stream_listen: Option<String>,
+
+    /// Token subscribers of the workers stream must send, required unless it only
+    /// listens on localhost
+    pub stream_token: Option<String>,
+
+    /// Scan the master hands out to its workers; without one it only registers them
+    pub scan: Option<MasterScan>,
+}
+
+/// Certificate and key a master presents to workers, from any CA they trust
+#[derive(Debug, Clone)]
+pub struct MasterTls {
+    /// Master certificate chain, in PEM
+    pub cert: PathBuf,
+
+    /// Master private key, in PEM
+    pub key: PathBuf,
+}
+
+/// Files of the scan a master runs across its workers
+#[derive(Debug, Clone)]
+pub struct MasterScan {
+    /// Rules file the domains are checked against
+    pub rules_file: String,
+
+    /// Domains to scan, one per line
+    pub input_file: String,
+
+    /// Tag recorded with the findings of the input's domains
+    pub input_tag: Option<String>,
+
+    /// Results database the findings workers report are written to
+    pub database: String,
+
+    /// Project the scan session and its findings are labelled with
+    pub project: Option<String>,
+
+    /// Domains handed to a worker at once
+    pub batch_size: usize,
+
+    /// How long a worker has to report a batch before it's handed to another
+    pub batch_timeout: Duration,
+}
+
+/// A scan a master runs: its domains and rules, the batches they're handed out in,
+/// and the store the findings go to
+pub struct MasterJob {
+    /// Scan session the findings are recorded under
+    pub scan_id: String,
+
+    pub domains: Vec<String>,
+
+    pub ruleset: RuleSet,
+
+    /// Digest of the rules, recorded with every finding
+    digest: String,
+
+    /// The rules as sent to workers with each batch
+    rules: Vec<ScanRule>,
+
+    tag: Option<String>,
+
+    project: Option<String>,
+
+    queue: Mutex<WorkQueue>,
+
+    batch_timeout: Duration,
+
+    started_at: DateTime<Utc>,
+
+    store: SharedStore,
+
+    /// Detected findings the workers have reported
+    findings: AtomicUsize,
+
+    /// Event stream the scan's progress and findings are published on, if any
+    events: OnceLock<Arc<EventStream>>,
+}
+
+impl MasterJob {
+    /// Load the rules and domains and open the results database, so a bad file stops
+    /// the master before any worker registers
+    pub async fn load(scan: &MasterScan) -> Result<Self> {
+        let ruleset = rules::load_rules(&scan.rules_file)
+            .context(format!("Failed to load rules: {}", scan.rules_file))?;
+        if ruleset.rules.is_empty() {
+            anyhow::bail!("No rules in {}", scan.rules_file);
+        }
+        let domains = utils::read_domains(&scan.input_file)
+            .context(format!("Failed to read domains: {}", scan.input_file))?;
+        if domains.is_empty() {
+            anyhow::bail!("No domains in {}", scan.input_file);
+        }
+        let rules = ruleset
+            .rules
+            .iter()
+            .map(ScanRule::from_rule)
+            .collect::<Result<_>>()?;
+        let queue = WorkQueue::new(&domains, scan.batch_size);
+
+        let store = store::open_store(StoreKind::Sqlite, &scan.database, 1, true)?;
+        let scan_id = uuid::Uuid::new_v4().to_string();
+        let started_at = Utc::now();
+        let snapshot = RuleSnapshot::new(1, ruleset.clone());
+        {
+            let mut results = store.lock().await;
+            results.start_scan(&scan_id, started_at)?;
+            results.record_ruleset(&scan_id, &snapshot)?;
+            if let Some(project) = &scan.project {
+                results.record_project(&scan_id, project)?;
+            }
+        }
+
+        info!(
+            "📋 Scan {} of {} domains in {} batches with {} rules, writing findings to {}",
+            scan_id,
+            domains.len(),
+            queue.progress().pending,
+            ruleset.rules.len(),
+            store::redact_url(&scan.database)
+        );
+
+        Ok(Self {
+            scan_id,
+            domains,
+            ruleset,
+            digest: snapshot.digest,
+            rules,
+            tag: scan.input_tag.clone(),
+            project: scan.project.clone(),
+            queue: Mutex::new(queue),
+            batch_timeout: scan.batch_timeout,
+            started_at,
+            store,
+            findings: AtomicUsize::new(0),
+            events: OnceLock::new(),
+        })
+    }
+
+    /// Publish the scan's progress and findings on an event stream from now on
+    pub fn publish_to(&self, events: Arc<EventStream>) {
+        let _ = self.events.set(events);
+    }
+
+    /// How many of the scan's batches are waiting, with workers and done
+    pub async fn progress(&self) -> QueueProgress {
+        self.queue.lock().await.progress()
+    }
+
+    /// Hand the next waiting batch to a worker, unless it already has one. Returns
+    /// whether a batch was sent; one that couldn't be goes back to the queue.
+    pub async fn dispatch(&self, worker: &ConnectedWorker) -> Result<bool> {
+        let (batch_id, domains) = {
+            let mut queue = self.queue.lock().await;
+            if queue.assigned_to(worker.connection).is_some() {
+                return Ok(false);
+            }
+            match queue.assign(&worker.id, worker.connection, Instant::now()) {
+                Some(batch) => (batch.id.clone(), batch.domains.clone()),
+                None => return Ok(false),
+            }
+        };
+
+        let request = WorkerMessage::ScanRequest {
+            domains,
+            batch_id: batch_id.clone(),
+            rules: self.rules.clone(),
+        };
+        if let Err(e) = send_message(&worker.writer, &request).await {
+            self.queue.lock().await.release(worker.connection);
+            return Err(e.context(format!(
+                "Failed to send batch {} to worker {}",
+                batch_id, worker.id
+            )));
+        }
+
+        debug!("📤 Sent batch {} to worker {}", batch_id, worker.id);
+        Ok(true)
+    }
+
+    /// Hand waiting batches to the registered workers that have none
+    async fn dispatch_idle(&self) {
+        let workers: Vec<Arc<ConnectedWorker>> = WORKERS.lock().await.values().cloned().collect();
+        for worker in workers {
+            if let Err(e) = self.dispatch(&worker).await {
+                error!("❌ {:#}", e);
+            }
+        }
+    }
+
+    /// Put the batches a worker's connection had back in the queue, as it's gone
+    /// without reporting them, and hand them to the registered workers
+    pub async fn release(&self, worker: &ConnectedWorker) {
+        let released = self.queue.lock().await.release(worker.connection);
+        for batch_id in &released {
+            warn!(
+                "↩️ Worker {} left without reporting batch {}, queueing it again",
+                worker.id, batch_id
+            );
+        }
+        if !released.is_empty() {
+            self.dispatch_idle().await;
+        }
+    }
+
+    /// Put batches whose workers have gone quiet for longer than the batch timeout
+    /// back in the queue, and hand them out again
+    async fn expire(&self) {
+        let expired = self
+            .queue
+            .lock()
+            .await
+            .expire(self.batch_timeout, Instant::now());
+        for (batch_id, worker_id) in &expired {
+            warn!(
+                "⏰ Worker {} didn't report batch {} in time, queueing it again",
+                worker_id, batch_id
+            );
+        }
+        if !expired.is_empty() {
+            self.dispatch_idle().await;
+        }
+    }
+
+    /// Record the findings a worker reported for a batch and mark the batch done,
+    /// finishing the scan session once every batch is. Results for a batch that's
+    /// already done, such as one handed out again after its worker went quiet, are
+    /// ignored.
+    pub async fn record_results(
+        &self,
+        worker_id: &str,
+        batch_id: &str,
+        findings: &[ScanFinding],
+    ) -> Result<()> {
+        match self.queue.lock().await.state(batch_id) {
+            None => anyhow::bail!("Worker {} reported unknown batch {}", worker_id, batch_id),
+            Some(BatchState::Done) => {
+                debug!(
+                    "Ignoring results of batch {} from worker {}: already recorded",
+                    batch_id, worker_id
+                );
+                return Ok(());
+            }
+            Some(_) => {}
+        }
+
+        let batch: Vec<PendingFinding> = findings
+            .iter()
+            .map(|finding| {
+                let evidence = finding.evidence()?;
+                Ok(PendingFinding::new(
+                    &finding.domain,
+                    &finding.rule_name,
+                    &finding.matched_path,
+                    finding.detected,
+                    &FindingDetails {
+                        redirect_chain: &finding.redirect_chain,
+                        tag: self.tag.as_deref(),
+                        status: finding.status,
+                        ruleset_version: Some(1),
+                        ruleset_digest: Some(&self.digest),
+                        evidence: evidence.as_ref(),
+                        project: self.project.as_deref(),
+                        ..Default::default()
+                    },
+                ))
+            })
+            .collect::<Result<_>>()
+            .context(format!(
+                "Invalid findings in batch {} from worker {}",
+                batch_id, worker_id
+            ))?;
+        self.store
+            .lock()
+            .await
+            .record_findings(&batch)
+            .context(format!(
+                "Failed to record batch {} from worker {}",
+                batch_id, worker_id
+            ))?;
+
+        let (finished, domains_done) = {
+            let mut queue = self.queue.lock().await;
+            let finished = queue.complete(batch_id)? && queue.is_finished();
+            (finished, queue.domains_done())
+        };
+        let progress = self.progress().await;
+        let detected = findings.iter().filter(|finding| finding.detected).count();
+        let total_findings = self.findings.fetch_add(detected, Ordering::Relaxed) + detected;
+        let batches = BatchProgress {
+            done: progress.done,
+            total: progress.pending + progress.assigned + progress.done,
+        };
+        info!(
+            "📥 Worker {} finished batch {}: {} findings ({} of {} batches done)",
+            worker_id, batch_id, detected, batches.done, batches.total
+        );
+        if let Some(events) = self.events.get() {
+            self.publish_findings(events, findings);
+            let checks_per_domain = self.ruleset.rules.len();
+            events.publish(StreamEvent::Progress {
+                domains_processed: domains_done,
+                domains_total: self.domains.len(),
+                checks_completed: domains_done * checks_per_domain,
+                checks_total: self.domains.len() * checks_per_domain,
+                findings: total_findings,
+                batches: Some(batches),
+            });
+        }
+        if finished {
+            self.finish().await?;
+        }
+        Ok(())
+    }
+
+    /// Publish each detected finding of a batch, as a local scan does when it finds one
+    fn publish_findings(&self, events: &EventStream, findings: &[ScanFinding]) {
+        for finding in findings.iter().filter(|finding| finding.detected) {
+            let rule = self
+                .ruleset
+                .rules
+                .iter()
+                .find(|rule| rule.name == finding.rule_name);
+            events.publish(StreamEvent::Finding {
+                domain: finding.domain.clone(),
+                rule: finding.rule_name.clone(),
+                severity: rule.and_then(|rule| rule.severity.clone()),
+                path: finding.matched_path.clone(),
+                tag: self.tag.clone(),
+                status: finding.status.unwrap_or_default(),
+                redirect_chain: finding.redirect_chain.clone(),
+                remediation: rule.and_then(|rule| rule.remediation.clone()),
+                found_at: Utc::now(),
+            });
+        }
+    }
+
+    /// Score the scan's findings and close its session, as a local scan does
+    async fn finish(&self) -> Result<()> {
+        let mut results = self.store.lock().await;
+        let detected = results
+            .detected_since(self.started_at)
+            .context("Failed to query findings from this scan")?;
+        let risk_report = risk::score_findings(&detected, &self.ruleset, &RiskWeights::default());
+        results.finish_scan(&self.scan_id, self.domains.len(), &risk_report)?;
+        results.record_scan_findings(&self.scan_id, &detected)?;
+
+        info!(
+            "🏁 Scan {} finished: {} domains, {} findings",
+            self.scan_id,
+            self.domains.len(),
+            detected.len()
+        );
+        Ok(())
+    }
+}
+
+/// Where a batch of a master's scan is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchState {
+    /// Waiting for a worker
+    Pending,
+
+    /// Handed to a worker over one of its connections, and not reported yet
+    Assigned {
+        worker_id: String,
+        connection: u64,
+        since: Instant,
+    },
+
+    /// Reported and recorded
+    Done,
+}
+
+/// Domains of a master's scan handed to one worker at a time
+#[derive(Debug, Clone)]
+pub struct Batch {
+    pub id: String,
+    pub domains: Vec<String>,
+    pub state: BatchState,
+}
+
+/// How many of a scan's batches are in each state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueProgress {
+    pub pending: usize,
+    pub assigned: usize,
+    pub done: usize,
+}
+
+/// A master's domains split into batches, tracking which worker connection has each.
+/// Batches are handed out in order, with those taken back from workers first.
+#[derive(Debug)]
+pub struct WorkQueue {
+    batches: Vec<Batch>,
+
+    /// Indexes of the pending batches, next first
+    pending: VecDeque<usize>,
+}
+
+impl WorkQueue {
+    /// Split domains into batches of `batch_size`, all pending
+    pub fn new(domains: &[String], batch_size: usize) -> Self {
+        let batches: Vec<Batch> = domains
+            .chunks(batch_size.max(1))
+            .enumerate()
+            .map(|(i, chunk)| Batch {
+                id: format!("batch-{}", i + 1),
+                domains: chunk.to_vec(),
+                state: BatchState::Pending,
+            })
+            .collect();
+        let pending = (0..batches.len()).collect();
+
+        Self { batches, pending }
+    }
+
+    fn index(&self, batch_id: &str) -> Option<usize> {
+        let number = batch_id.strip_prefix("batch-")?.parse::<usize>().ok()?;
+        number
+            .checked_sub(1)
+            .filter(|&index| index < self.batches.len())
+    }
+
+    /// State of a batch, or `None` if the scan has no such batch
+    pub fn state(&self, batch_id: &str) -> Option<&BatchState> {
+        self.index(batch_id).map(|i| &self.batches[i].state)
+    }
+
+    /// The batch a worker connection has, if any
+    pub fn assigned_to(&self, connection: u64) -> Option<&Batch> {
+        self.batches.iter().find(|batch| {
+            matches!(&batch.state, BatchState::Assigned { connection: c, .. } if *c == connection)
+        })
+    }
+
+    /// Hand the next pending batch to a worker over one of its connections
+    pub fn assign(&mut self, worker_id: &str, connection: u64, now: Instant) -> Option<&Batch> {
+        let index = self.pending.pop_front()?;
+        let batch = &mut self.batches[index];
+        batch.state = BatchState::Assigned {
+            worker_id: worker_id.to_string(),
+            connection,
+            since: now,
+        };
+        Some(batch)
+    }
+
+    /// Mark a batch done, returning whether it wasn't already. A batch taken back from
+    /// a worker can still be completed by it, before it's handed out again.
+    pub fn complete(&mut self, batch_id: &str) -> Result<bool> {
+        let index = self
+            .index(batch_id)
+            .context(format!("Unknown batch: {}", batch_id))?;
+        match self.batches[index].state {
+            BatchState::Done => return Ok(false),
+            BatchState::Pending => self.pending.retain(|&pending| pending != index),
+            BatchState::Assigned { .. } => {}
+        }
+        self.batches[index].state = BatchState::Done;
+        Ok(true)
+    }
+
+    /// Take back the batches a worker connection has, returning their IDs. Another
+    /// connection of the same worker keeps its own.
+    pub fn release(&mut self, connection: u64) -> Vec<String> {
+        self.take_back(|c, _| c == connection)
+            .into_iter()
+            .map(|(batch_id, _)| batch_id)
+            .collect()
+    }
+
+    /// Take back the batches assigned longer than `timeout` before `now`, returning
+    /// their IDs and the workers that had them
+    pub fn expire(&mut self, timeout: Duration, now: Instant) -> Vec<(String, String)> {
+        self.take_back(|_, since| now.saturating_duration_since(since) > timeout)
+    }
+
+    fn take_back(&mut self, take: impl Fn(u64, Instant) -> bool) -> Vec<(String, String)> {
+        let mut taken = Vec::new();
+        let mut indexes = Vec::new();
+        for (index, batch) in self.batches.iter_mut().enumerate() {
+            let BatchState::Assigned {
+                worker_id,
+                connection,
+                since,
+            } = &batch.state
+            else {
+                continue;
+            };
+            if take(*connection, *since) {
+                taken.push((batch.id.clone(), worker_id.clone()));
+                batch.state = BatchState::Pending;
+                indexes.push(index);
+            }
+        }
+        for index in indexes.into_iter().rev() {
+            self.pending.push_front(index);
+        }
+        taken
+    }
+
+    pub fn progress(&self) -> QueueProgress {
+        let mut progress = QueueProgress::default();
+        for batch in &self.batches {
+            match batch.state {
+                BatchState::Pending => progress.pending += 1,
+                BatchState::Assigned { .. } => progress.assigned += 1,
+                BatchState::Done => progress.done += 1,
+            }
+        }
+        progress
+    }
+
+    /// Domains of the batches that are done
+    pub fn domains_done(&self) -> usize {
+        self.batches
+            .iter()
+            .filter(|batch| batch.state == BatchState::Done)
+            .map(|batch| batch.domains.len())
+            .sum()
+    }
+
+    /// Whether every batch is done
+    pub fn is_finished(&self) -> bool {
+        self.pending.is_empty()
+            && self
+                .batches
+                .iter()
+                .all(|batch| batch.state == BatchState::Done)
+    }
 }
 
 /// Message types for worker-master communication
@@ -38,10 +676,11 @@ pub enum WorkerMessage {
         status: WorkerStatus,
     },
 
-    /// Domain scan request
+    /// Domain scan request, with the rules to check the domains against
     ScanRequest {
         domains: Vec<String>,
         batch_id: String,
+        rules: Vec<ScanRule>,
     },
 
     /// Domain scan result
@@ -65,16 +704,16 @@ pub struct WorkerCapabilities {
     pub version: String,
 }
 
-/// Worker status
-#[derive(Debug, Clone, Serialize, Deserialize, Default, Encode, Decode)]
+/// Worker status, counting the domains of the batches it was sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default, Encode, Decode)]
 pub struct WorkerStatus {
-    /// Number of active scans
+    /// Domains being scanned
     pub active_scans: usize,
 
-    /// Number of completed scans
+    /// Domains scanned
     pub completed_scans: usize,
 
-    /// Number of findings
+    /// Detected findings
     pub findings: usize,
 
     /// Uptime in seconds
@@ -95,28 +734,62 @@ pub struct ScanFinding {
 
     /// Whether the target was detected
     pub detected: bool,
+
+    /// HTTP status of the response the outcome was observed in, which tells whether
+    /// the path is protected
+    pub status: Option<u16>,
+
+    /// URLs the request was redirected through, ending with the final one
+    pub redirect_chain: Vec<String>,
+
+    /// Part of the body the signature matched in, with the body's hash, as JSON
+    pub evidence: Option<String>,
+}
+
+impl ScanFinding {
+    /// The finding's evidence back from its JSON
+    pub fn evidence(&self) -> Result<Option<Evidence>> {
+        self.evidence
+            .as_deref()
+            .map(|evidence| {
+                serde_json::from_str(evidence).context(format!(
+                    "Invalid evidence of {} on {}",
+                    self.rule_name, self.domain
+                ))
+            })
+            .transpose()
+    }
 }
 
 /// Connected worker information
 pub struct ConnectedWorker {
     /// Worker ID
-    #[allow(dead_code)]
     pub id: String,
 
+    /// Number of the connection the worker registered over, told apart from an earlier
+    /// one of the same worker that the master hasn't noticed is gone
+    pub connection: u64,
+
     /// Worker capabilities
     pub capabilities: WorkerCapabilities,
 
-    /// Write half of the TCP stream
-    pub writer: Arc<Mutex<OwnedWriteHalf>>,
+    /// Write half of the connection
+    pub writer: Arc<Mutex<MessageWriter>>,
 
-    /// Worker status
-    pub status: WorkerStatus,
+    /// Status the worker last sent in a heartbeat
+    pub status: Mutex<WorkerStatus>,
 }
 
 lazy_static! {
     static ref WORKERS: Mutex<HashMap<String, Arc<ConnectedWorker>>> = Mutex::new(HashMap::new());
 }
 
+/// Number of the next worker connection
+static NEXT_CONNECTION: AtomicU64 = AtomicU64::new(1);
+
+/// DNS resolver of a worker's scans, opened once so its cache survives reconnecting
+static WORKER_RESOLVER: OnceCell<Arc<DnsResolver>> = OnceCell::const_new();
+
 /// Stop a worker by ID
 pub async fn stop_worker(worker_id: &str) -> Result<()> {
     let workers = WORKERS.lock().await;
@@ -140,6 +813,31 @@ pub async fn stop_worker(worker_id: &str) -> Result<()> {
     }
 }
 
+/// IDs of the registered workers
+#[allow(dead_code)]
+pub async fn connected_workers() -> Vec<String> {
+    let mut ids: Vec<String> = WORKERS.lock().await.keys().cloned().collect();
+    ids.sort();
+    ids
+}
+
+/// The registered workers, as published on the master's event stream
+pub async fn worker_summaries() -> Vec<WorkerSummary> {
+    let mut workers = Vec::new();
+    for (id, worker) in WORKERS.lock().await.iter() {
+        let status = worker.status.lock().await;
+        workers.push(WorkerSummary {
+            id: id.clone(),
+            max_concurrency: worker.capabilities.max_concurrency,
+            active_scans: status.active_scans,
+            completed_scans: status.completed_scans,
+            findings: status.findings,
+        });
+    }
+    workers.sort_by(|a, b| a.id.cmp(&b.id));
+    workers
+}
+
 /// Get status of all workers
 pub async fn worker_status() -> Result<()> {
     let workers = WORKERS.lock().await;
@@ -152,12 +850,13 @@ pub async fn worker_status() -> Result<()> {
     info!("🔍 Connected Workers: {}", workers.len());
 
     for (id, worker) in workers.iter() {
+        let status = worker.status.lock().await;
         info!(
             "👷 Worker {}: Active={}, Completed={}, Findings={}, MaxConcurrency={}",
             id,
-            worker.status.active_scans,
-            worker.status.completed_scans,
-            worker.status.findings,
+            status.active_scans,
+            status.completed_scans,
+            status.findings,
             worker.capabilities.max_concurrency
         );
     }
@@ -165,18 +864,53 @@ pub async fn worker_status() -> Result<()> {
     Ok(())
 }
 
-/// Start a worker node
+/// Start a worker node. The first connection to the master has to succeed, so a
+/// misconfigured worker stops at once; after that, a lost connection is retried with
+/// exponential backoff and the worker registers again, so it outlives a master restart.
 pub async fn start_worker(config: &WorkerConfig) -> Result<()> {
     info!("🚀 Starting worker node with ID: {}", config.worker_id);
 
-    // Connect to master
+    let mut connection = connect_to_master(config).await?;
+    let scanner = Arc::new(WorkerScanner::from_config(config).await?);
+
+    loop {
+        match run_worker_session(config, &scanner, connection).await {
+            Ok(()) => return Ok(()),
+            Err(e) if config.reconnect.retries > 0 => {
+                warn!("🔌 Lost connection to master: {:#}", e);
+                connection = reconnect_to_master(config).await?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// A worker's registered connection to the master, with the master's first message
+struct MasterConnection {
+    reader: MessageReader,
+    writer: Arc<Mutex<MessageWriter>>,
+    first: WorkerMessage,
+}
+
+/// Connect to the master and register. The master answers a registration it accepts
+/// straight away, so a connection closed before any answer is a refusal.
+async fn connect_to_master(config: &WorkerConfig) -> Result<MasterConnection> {
     let stream = TcpStream::connect(&config.master)
         .await
         .context(format!("Failed to connect to master at {}", config.master))?;
 
-    // Split the stream
-    let (mut reader, write_half) = stream.into_split();
-    let writer = Arc::new(Mutex::new(write_half));
+    let (mut reader, writer) = match &config.tls {
+        Some(tls) => connect_tls(stream, &config.master, tls).await?,
+        None => {
+            warn!("⚠️ Connecting to master over plain TCP");
+            let (reader, writer) = stream.into_split();
+            (
+                Box::new(reader) as MessageReader,
+                Box::new(writer) as MessageWriter,
+            )
+        }
+    };
+    let writer = Arc::new(Mutex::new(writer));
 
     // Register with master
     let capabilities = WorkerCapabilities {
@@ -186,106 +920,391 @@ pub async fn start_worker(config: &WorkerConfig) -> Result<()> {
 
     let register_msg = WorkerMessage::Register {
         worker_id: config.worker_id.clone(),
-        capabilities: capabilities.clone(),
+        capabilities,
     };
 
     send_message(&writer, &register_msg)
         .await
         .context("Failed to register with master")?;
+    let first = read_message(&mut reader)
+        .await
+        .context(format!("Master at {} refused registration", config.master))?;
 
     info!("✅ Registered with master at {}", config.master);
 
-    // Handle messages
+    Ok(MasterConnection {
+        reader,
+        writer,
+        first,
+    })
+}
+
+/// Connect to the master again after losing it, waiting longer after every failed
+/// attempt, up to the worker's reconnect attempts
+async fn reconnect_to_master(config: &WorkerConfig) -> Result<MasterConnection> {
+    let policy = config.reconnect;
+    let mut attempt = 0;
+
     loop {
-        // Read message length (4 bytes)
-        let mut len_bytes = [0u8; 4];
-        reader
-            .read_exact(&mut len_bytes)
-            .await
-            .context("Failed to read message length")?;
-        let len = u32::from_be_bytes(len_bytes) as usize;
+        let (min_ms, max_ms) = policy.backoff_range(attempt);
+        let cap = MAX_RECONNECT_DELAY.as_millis() as u64;
+        info!(
+            "🔁 Reconnecting to master at {} (attempt {} of {})",
+            config.master,
+            attempt + 1,
+            policy.retries
+        );
+        utils::random_backoff(min_ms.min(cap), max_ms.min(cap)).await;
 
-        // Read message
-        let mut buffer = vec![0u8; len];
-        reader
-            .read_exact(&mut buffer)
-            .await
-            .context("Failed to read message")?;
+        match connect_to_master(config).await {
+            Ok(connection) => return Ok(connection),
+            Err(e) if attempt + 1 < policy.retries => {
+                warn!("⚠️ Failed to reconnect to master: {:#}", e);
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(e.context(format!(
+                    "Gave up reconnecting to master after {} attempts",
+                    policy.retries
+                )))
+            }
+        }
+    }
+}
 
-        // Deserialize message
-        let message: WorkerMessage =
-            bincode::decode_from_slice(&buffer, bincode::config::standard())
-                .context("Failed to deserialize message")?
-                .0;
+/// Handle what the master sends over one connection, returning once it asks the
+/// worker to shut down and failing once the connection does
+async fn run_worker_session(
+    config: &WorkerConfig,
+    scanner: &Arc<WorkerScanner>,
+    connection: MasterConnection,
+) -> Result<()> {
+    let MasterConnection {
+        mut reader,
+        writer,
+        first,
+    } = connection;
+    let mut next = Some(first);
+
+    // Keep the master up to date with what the worker is doing until the session ends
+    let heartbeats = tokio::spawn(send_heartbeats(
+        config.worker_id.clone(),
+        scanner.clone(),
+        writer.clone(),
+    ));
+    let _heartbeats = AbortOnDrop(heartbeats);
+
+    // Handle messages
+    loop {
+        let message = match next.take() {
+            Some(message) => message,
+            None => read_message(&mut reader)
+                .await
+                .context("Failed to read message from master")?,
+        };
 
         debug!("📩 Received message: {:?}", message);
 
         // Handle message
         match message {
-            WorkerMessage::ScanRequest { domains, batch_id } => {
+            WorkerMessage::ScanRequest {
+                domains,
+                batch_id,
+                rules,
+            } => {
                 info!(
                     "🔍 Received scan request for {} domains (batch: {})",
                     domains.len(),
                     batch_id
                 );
 
-                // TODO: Implement scan logic
-                let _scan_config = config.clone();
-
-                // For now, just send back empty results
-                let result_msg = WorkerMessage::ScanResult {
-                    worker_id: config.worker_id.clone(),
-                    batch_id,
-                    findings: vec![],
-                };
-
-                send_message(&writer, &result_msg)
-                    .await
-                    .context("Failed to send scan results")?;
+                // Scanned in the background, so a shutdown is still read mid-batch. If
+                // the connection is lost meanwhile the results are too, and the master
+                // hands the batch out again.
+                let scanner = scanner.clone();
+                let writer = writer.clone();
+                let worker_id = config.worker_id.clone();
+                tokio::spawn(async move {
+                    let findings = match scanner.scan_batch(&domains, &rules).await {
+                        Ok(findings) => findings,
+                        Err(e) => {
+                            // Left unanswered, so the master can hand the batch out again
+                            error!("❌ Failed to scan batch {}: {:#}", batch_id, e);
+                            return;
+                        }
+                    };
+                    info!(
+                        "✅ Finished batch {}: {} findings",
+                        batch_id,
+                        findings.iter().filter(|finding| finding.detected).count()
+                    );
+
+                    let result_msg = WorkerMessage::ScanResult {
+                        worker_id,
+                        batch_id,
+                        findings,
+                    };
+                    if let Err(e) = send_message(&writer, &result_msg).await {
+                        error!("❌ Failed to send scan results: {:#}", e);
+                    }
+                });
             }
+            WorkerMessage::Heartbeat { .. } => debug!("💓 Heartbeat from master"),
             WorkerMessage::Shutdown { .. } => {
                 info!("⏹️ Received shutdown request, stopping worker");
-                break;
+                return Ok(());
             }
             _ => {
                 error!("❓ Received unexpected message type");
             }
         }
     }
+}
 
-    Ok(())
+/// Send the master the worker's status every `HEARTBEAT_INTERVAL`, until the
+/// connection fails
+async fn send_heartbeats(
+    worker_id: String,
+    scanner: Arc<WorkerScanner>,
+    writer: Arc<Mutex<MessageWriter>>,
+) {
+    let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+    loop {
+        interval.tick().await;
+        let heartbeat = WorkerMessage::Heartbeat {
+            worker_id: worker_id.clone(),
+            status: scanner.status(),
+        };
+        if let Err(e) = send_message(&writer, &heartbeat).await {
+            debug!("💓 Stopped sending heartbeats: {:#}", e);
+            return;
+        }
+    }
+}
+
+/// What a worker checks its batches with: the HTTP client, DNS resolver and
+/// pipeline of a local scan, with its default settings
+pub struct WorkerScanner {
+    client: Client,
+    resolver: Arc<DnsResolver>,
+    options: ScanOptions,
+
+    /// Stages a batch is scanned through, as a local scan's domains are
+    pipeline: PipelineOptions,
+
+    /// When the scanner was set up, for the worker's uptime
+    started: Instant,
+
+    /// Domains being scanned, domains scanned and detected findings, for heartbeats
+    active: AtomicUsize,
+    completed: AtomicUsize,
+    findings: AtomicUsize,
+}
+
+impl WorkerScanner {
+    pub fn new(
+        client: Client,
+        resolver: Arc<DnsResolver>,
+        options: ScanOptions,
+        pipeline: PipelineOptions,
+    ) -> Self {
+        Self {
+            client,
+            resolver,
+            options,
+            pipeline,
+            started: Instant::now(),
+            active: AtomicUsize::new(0),
+            completed: AtomicUsize::new(0),
+            findings: AtomicUsize::new(0),
+        }
+    }
+
+    /// What the worker has scanned since it started, as sent in its heartbeats
+    pub fn status(&self) -> WorkerStatus {
+        WorkerStatus {
+            active_scans: self.active.load(Ordering::Relaxed),
+            completed_scans: self.completed.load(Ordering::Relaxed),
+            findings: self.findings.load(Ordering::Relaxed),
+            uptime_seconds: self.started.elapsed().as_secs(),
+        }
+    }
+
+    /// Scanner for a worker, set up as `fatt scan` would be with the worker's settings
+    pub async fn from_config(config: &WorkerConfig) -> Result<Self> {
+        let scan_config = &config.scan;
+        let client = scanner::build_http_client(&HttpClientConfig::from_config(scan_config))?;
+        let resolver = WORKER_RESOLVER
+            .get_or_try_init(|| async {
+                DnsResolver::new(resolver::CACHE_DIR, scan_config.dns_cache_size)
+                    .await
+                    .map(Arc::new)
+                    .context("Failed to initialize DNS resolver")
+            })
+            .await?;
+
+        Ok(Self::new(
+            client,
+            resolver.clone(),
+            ScanOptions::from_config(scan_config),
+            PipelineOptions {
+                resolvers: config.concurrency,
+                fetchers: config.concurrency,
+                ..PipelineOptions::from_config(scan_config)
+            },
+        ))
+    }
+
+    /// Check a batch of domains against the rules the master sent, returning the
+    /// outcome of every check that reached a target
+    pub async fn scan_batch(
+        &self,
+        domains: &[String],
+        rules: &[ScanRule],
+    ) -> Result<Vec<ScanFinding>> {
+        if rules.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ruleset = RuleSet {
+            rules: rules.iter().map(ScanRule::to_rule).collect::<Result<_>>()?,
+        };
+
+        self.active.fetch_add(domains.len(), Ordering::Relaxed);
+        let findings = self.check_batch(domains, ruleset).await;
+        self.active.fetch_sub(domains.len(), Ordering::Relaxed);
+        let findings = findings?;
+        self.completed.fetch_add(domains.len(), Ordering::Relaxed);
+        self.findings.fetch_add(
+            findings.iter().filter(|finding| finding.detected).count(),
+            Ordering::Relaxed,
+        );
+
+        Ok(findings)
+    }
+
+    /// Check every domain of a batch against the rules
+    async fn check_batch(&self, domains: &[String], ruleset: RuleSet) -> Result<Vec<ScanFinding>> {
+        // Outcomes are collected in a database of the batch's own, then sent on
+        let store: SharedStore = Arc::new(Mutex::new(db::init_db(":memory:")?));
+        let resources = ScanResources::new(
+            self.client.clone(),
+            ruleset,
+            self.resolver.clone(),
+            store.clone(),
+            self.options.clone(),
+        );
+
+        pipeline::scan_domains(domains, &resources, &self.pipeline).await?;
+
+        let findings = store.lock().await.findings()?;
+        findings
+            .into_iter()
+            .map(|finding| {
+                Ok(ScanFinding {
+                    evidence: finding
+                        .evidence
+                        .as_ref()
+                        .map(serde_json::to_string)
+                        .transpose()
+                        .context("Failed to serialize evidence")?,
+                    domain: finding.domain,
+                    rule_name: finding.rule_name,
+                    matched_path: finding.matched_path,
+                    detected: finding.detected,
+                    status: finding.status,
+                    redirect_chain: finding.redirect_chain,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Open a TLS connection to the master, presenting the worker's certificate
+async fn connect_tls(
+    stream: TcpStream,
+    master: &str,
+    tls: &WorkerTls,
+) -> Result<(MessageReader, MessageWriter)> {
+    let identity = match (&tls.cert, &tls.key) {
+        (Some(cert), Some(key)) => Some((cert.as_path(), key.as_path())),
+        _ => {
+            warn!("⚠️ Connecting to master over TLS without a worker certificate");
+            None
+        }
+    };
+    let client_config = crate::certs::client_config(&tls.ca_cert, identity)?;
+    let host = match &tls.server_name {
+        Some(name) => name.clone(),
+        None => master_host(master).to_string(),
+    };
+    let server_name =
+        ServerName::try_from(host.clone()).context(format!("Invalid master name: {}", host))?;
+
+    let stream = TlsConnector::from(client_config)
+        .connect(server_name, stream)
+        .await
+        .context(format!("TLS handshake with master at {} failed", master))?;
+    let (reader, writer) = tokio::io::split(stream);
+
+    Ok((Box::new(reader), Box::new(writer)))
+}
+
+/// Host part of a `HOST:PORT` address
+fn master_host(master: &str) -> &str {
+    let host = match master.rsplit_once(':') {
+        Some((host, port)) if port.parse::<u16>().is_ok() => host,
+        _ => master,
+    };
+    host.trim_start_matches('[').trim_end_matches(']')
 }
 
 /// Send a message to a worker
-async fn send_message(writer: &Arc<Mutex<OwnedWriteHalf>>, message: &WorkerMessage) -> Result<()> {
+async fn send_message(writer: &Arc<Mutex<MessageWriter>>, message: &WorkerMessage) -> Result<()> {
     let mut writer_guard = writer.lock().await;
+    write_message(&mut *writer_guard, message).await
+}
 
+/// Write a message to a connection: its length and checksum as big-endian u32s, then
+/// the message itself
+pub async fn write_message<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    message: &WorkerMessage,
+) -> Result<()> {
     // Serialize the message using bincode
     let config = config::standard();
     let encoded = bincode::encode_to_vec(message, config)?;
 
-    // Write the message length as u32 first
-    let msg_len = encoded.len() as u32;
-    writer_guard.write_all(&msg_len.to_be_bytes()).await?;
+    let mut frame = Vec::with_capacity(encoded.len() + 8);
+    frame.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&checksum(&encoded).to_be_bytes());
+    frame.extend_from_slice(&encoded);
+
+    #[cfg(feature = "chaos")]
+    crate::chaos::disturb(message, &mut frame).await?;
 
-    // Then write the actual message
-    writer_guard.write_all(&encoded).await?;
-    writer_guard.flush().await?;
+    stream.write_all(&frame).await?;
+    stream.flush().await?;
 
     Ok(())
 }
 
-#[allow(dead_code)]
-/// Read a worker message from a TCP stream
-async fn read_message(stream: &mut TcpStream) -> Result<WorkerMessage> {
-    // Read message length
-    let mut len_bytes = [0u8; 4];
-    stream.read_exact(&mut len_bytes).await?;
-    let msg_len = u32::from_be_bytes(len_bytes) as usize;
+/// Read a worker message from a connection, rejecting one whose checksum doesn't match
+pub async fn read_message<R: AsyncRead + Unpin>(stream: &mut R) -> Result<WorkerMessage> {
+    // Read message length and checksum
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header).await?;
+    let msg_len = u32::from_be_bytes(header[..4].try_into()?) as usize;
+    let expected = u32::from_be_bytes(header[4..].try_into()?);
+    if msg_len > MAX_MESSAGE_BYTES {
+        anyhow::bail!("Message of {} bytes is too large", msg_len);
+    }
 
     // Read the actual message
     let mut buffer = vec![0u8; msg_len];
     stream.read_exact(&mut buffer).await?;
+    if checksum(&buffer) != expected {
+        anyhow::bail!("Corrupted message: checksum mismatch");
+    }
 
     // Deserialize using bincode
     let config = config::standard();
@@ -294,23 +1313,63 @@ async fn read_message(stream: &mut TcpStream) -> Result<WorkerMessage> {
     Ok(message)
 }
 
-#[allow(dead_code)]
+/// First four bytes of a message's SHA-256, sent with it so corruption in transit is
+/// caught rather than decoded as a different message
+fn checksum(encoded: &[u8]) -> u32 {
+    let digest = Sha256::digest(encoded);
+    u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
 /// Start a master node that distributes scanning work to connected workers
-pub async fn start_master(
-    listen_addr: &str,
-    _scan_config: crate::config::ScanConfig,
-) -> Result<()> {
-    info!("🌐 Starting master node on {}", listen_addr);
+pub async fn start_master(config: &MasterConfig) -> Result<()> {
+    info!("🌐 Starting master node on {}", config.listen);
 
     // Create our TCP listener
-    let listener = TcpListener::bind(listen_addr)
+    let listener = TcpListener::bind(&config.listen)
         .await
-        .context(format!("Failed to bind to {}", listen_addr))?;
+        .context(format!("Failed to bind to {}", config.listen))?;
 
-    info!("✅ Master node started, waiting for workers to connect");
+    let job = match &config.scan {
+        Some(scan) => Some(Arc::new(MasterJob::load(scan).await?)),
+        None => None,
+    };
 
-    // Create a shared list of connected workers
-    let workers = Arc::new(Mutex::new(Vec::new()));
+    serve_master(listener, config, job).await
+}
+
+/// Accept workers on `listener`, recording the results they report for `job`
+pub async fn serve_master(
+    listener: TcpListener,
+    config: &MasterConfig,
+    job: Option<Arc<MasterJob>>,
+) -> Result<()> {
+    let server_config = match (&config.ca, &config.tls) {
+        (Some(ca), Some(tls)) => Some(ca.server_config_with_cert(&tls.cert, &tls.key)?),
+        (Some(ca), None) => Some(ca.server_config(&config.server_names)?),
+        (None, Some(tls)) => {
+            warn!("⚠️ No CA configured, workers register over TLS without certificates");
+            Some(crate::certs::server_config(&tls.cert, &tls.key)?)
+        }
+        (None, None) => {
+            warn!("⚠️ No CA or certificate configured, workers connect over plain TCP");
+            None
+        }
+    };
+    let acceptor = server_config.map(TlsAcceptor::from);
+
+    let _workers_stream = match &config.stream_listen {
+        Some(addr) => {
+            let events = Arc::new(EventStream::listen(addr, config.stream_token.as_deref()).await?);
+            if let Some(job) = &job {
+                job.publish_to(events.clone());
+            }
+            Some(AbortOnDrop(stream_workers(events)))
+        }
+        None => None,
+    };
+    let _expiry = job.clone().map(|job| AbortOnDrop(expire_batches(job)));
+
+    info!("✅ Master node started, waiting for workers to connect");
 
     loop {
         // Accept connections
@@ -321,55 +1380,133 @@ pub async fn start_master(
 
         info!("✅ New connection from: {}", addr);
 
-        // Clone the workers for this connection
-        let workers_clone = workers.clone();
+        let acceptor = acceptor.clone();
+        let ca = config.ca.clone();
+        let job = job.clone();
 
         // Handle connection in separate task
         tokio::spawn(async move {
-            if let Err(e) = handle_worker_connection(socket, workers_clone).await {
-                error!("❌ Error handling worker connection: {}", e);
+            let result = match acceptor {
+                Some(acceptor) => accept_tls(acceptor, socket, ca.as_ref(), job).await,
+                None => {
+                    let (reader, writer) = socket.into_split();
+                    handle_worker_connection(Box::new(reader), Box::new(writer), None, job).await
+                }
+            };
+            if let Err(e) = result {
+                error!("❌ Error handling worker connection from {}: {:#}", addr, e);
             }
         });
     }
 }
 
-#[allow(dead_code)]
-/// Handle a single worker connection
-async fn handle_worker_connection(
-    mut stream: TcpStream,
-    _workers: Arc<Mutex<Vec<ConnectedWorker>>>,
+/// Publish the registered workers on an event stream every few seconds
+fn stream_workers(events: Arc<EventStream>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(WORKERS_INTERVAL);
+        loop {
+            interval.tick().await;
+            events.publish(StreamEvent::Workers {
+                workers: worker_summaries().await,
+            });
+        }
+    })
+}
+
+/// Check for batches whose workers have gone quiet every few seconds, handing them
+/// out again
+fn expire_batches(job: Arc<MasterJob>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+        loop {
+            interval.tick().await;
+            job.expire().await;
+        }
+    })
+}
+
+/// Stops a background task when the master, or a worker's session, stops
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Complete the TLS handshake with a worker and handle its registration. With a CA,
+/// the worker has to present a certificate it issued.
+async fn accept_tls(
+    acceptor: TlsAcceptor,
+    socket: TcpStream,
+    ca: Option<&CertAuthority>,
+    job: Option<Arc<MasterJob>>,
 ) -> Result<()> {
-    info!("🔌 Worker connected from: {}", stream.peer_addr()?);
+    let stream = acceptor
+        .accept(socket)
+        .await
+        .context("TLS handshake with worker failed")?;
+    let cert = match ca {
+        Some(_) => Some(
+            stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .context("Worker presented no certificate")?
+                .to_vec(),
+        ),
+        None => None,
+    };
 
+    let (reader, writer) = tokio::io::split(stream);
+    let verify = ca.zip(cert.as_deref());
+    handle_worker_connection(Box::new(reader), Box::new(writer), verify, job).await
+}
+
+/// Handle a single worker connection. With a certificate, registration is refused
+/// unless it was issued to the worker ID being registered and isn't revoked. Once
+/// registered, the worker is handed batches of `job` one at a time and its results
+/// recorded until it disconnects, when the batch it had is handed to another.
+async fn handle_worker_connection(
+    mut reader: MessageReader,
+    writer: MessageWriter,
+    cert: Option<(&CertAuthority, &[u8])>,
+    job: Option<Arc<MasterJob>>,
+) -> Result<()> {
     // Read initial message
-    let message = read_message(&mut stream).await?;
+    let message = read_message(&mut reader).await?;
 
     match message {
         WorkerMessage::Register {
             worker_id,
             capabilities,
         } => {
+            if let Some((ca, cert)) = cert {
+                ca.verify_worker(cert, &worker_id)
+                    .context(format!("Rejected registration of worker {}", worker_id))?;
+            }
+
             info!(
                 "👷 Worker registered: {} (concurrency={})",
                 worker_id, capabilities.max_concurrency
             );
 
-            // Split the stream and store the write half for sending messages
-            let (_read_half, write_half) = stream.into_split();
-
-            // Create the connected worker
+            // Create the connected worker, keeping the write half for sending messages
             let worker = Arc::new(ConnectedWorker {
                 id: worker_id.clone(),
+                connection: NEXT_CONNECTION.fetch_add(1, Ordering::Relaxed),
                 capabilities,
-                writer: Arc::new(Mutex::new(write_half)),
-                status: WorkerStatus::default(),
+                writer: Arc::new(Mutex::new(writer)),
+                status: Mutex::new(WorkerStatus::default()),
             });
 
-            // Store in global workers map
-            {
-                let mut workers = WORKERS.lock().await;
-                workers.insert(worker_id.clone(), worker.clone());
-            }
+            // Store in global workers map, replacing an earlier connection of a worker
+            // that reconnected before the master noticed it was gone
+            let replaced = WORKERS
+                .lock()
+                .await
+                .insert(worker_id.clone(), worker.clone());
 
             // Send a heartbeat request
             let heartbeat = WorkerMessage::Heartbeat {
@@ -377,9 +1514,35 @@ async fn handle_worker_connection(
                 status: WorkerStatus::default(),
             };
 
-            send_message(&worker.writer, &heartbeat).await?;
+            let result = async {
+                send_message(&worker.writer, &heartbeat).await?;
+                if let Some(job) = &job {
+                    // Whatever the earlier connection had is lost with it
+                    if let Some(replaced) = &replaced {
+                        info!("🔁 Worker {} registered again", worker_id);
+                        job.release(replaced).await;
+                    }
+                    job.dispatch(&worker).await?;
+                }
+                handle_worker_messages(&worker, &mut reader, job.as_deref()).await
+            }
+            .await;
 
-            Ok(())
+            // Unless the worker has registered again since, it's gone
+            {
+                let mut workers = WORKERS.lock().await;
+                if workers
+                    .get(&worker_id)
+                    .is_some_and(|current| Arc::ptr_eq(current, &worker))
+                {
+                    workers.remove(&worker_id);
+                    info!("👋 Worker disconnected: {}", worker_id);
+                }
+            }
+            if let Some(job) = &job {
+                job.release(&worker).await;
+            }
+            result
         }
         _ => {
             error!("❌ Expected Register message from worker, got something else");
@@ -388,7 +1551,59 @@ async fn handle_worker_connection(
     }
 }
 
+/// Handle what a registered worker sends until it disconnects, handing it the next
+/// batch of `job` whenever it reports one
+async fn handle_worker_messages(
+    worker: &ConnectedWorker,
+    reader: &mut MessageReader,
+    job: Option<&MasterJob>,
+) -> Result<()> {
+    let worker_id = worker.id.as_str();
+    loop {
+        let message = match read_message(reader).await {
+            Ok(message) => message,
+            // A closed connection is how a worker leaves
+            Err(e)
+                if e.downcast_ref::<std::io::Error>()
+                    .is_some_and(|e| e.kind() == std::io::ErrorKind::UnexpectedEof) =>
+            {
+                return Ok(());
+            }
+            Err(e) => return Err(e.context(format!("Failed to read from worker {}", worker_id))),
+        };
+
+        match message {
+            WorkerMessage::ScanResult {
+                batch_id, findings, ..
+            } => match job {
+                Some(job) => {
+                    if let Err(e) = job.record_results(worker_id, &batch_id, &findings).await {
+                        error!("❌ {:#}", e);
+                    }
+                    job.dispatch(worker).await?;
+                }
+                None => warn!(
+                    "⚠️ Ignoring results of batch {} from worker {}: no scan is running",
+                    batch_id, worker_id
+                ),
+            },
+            WorkerMessage::Heartbeat { status, .. } => {
+                debug!(
+                    "💓 Worker {}: {} active, {} completed scans",
+                    worker_id, status.active_scans, status.completed_scans
+                );
+                *worker.status.lock().await = status;
+            }
+            other => warn!(
+                "❓ Unexpected message from worker {}: {:?}",
+                worker_id, other
+            ),
+        }
+    }
+}
+
 /// Message types for master-worker communication
+#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MasterMessage {
     /// Registration response
@@ -411,10 +1626,31 @@ pub enum MasterMessage {
     Shutdown { reason: Option<String> },
 }
 
-/// Simplified rule representation for distribution
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A rule as sent to workers, carrying its whole definition so they check it exactly
+/// as a local scan would
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
 pub struct ScanRule {
     pub name: String,
-    pub paths: Vec<String>,
-    pub severity: String,
+
+    /// The rule as JSON, with the fields of a rules file
+    pub definition: String,
+}
+
+impl ScanRule {
+    pub fn from_rule(rule: &Rule) -> Result<Self> {
+        Ok(Self {
+            name: rule.name.clone(),
+            definition: serde_json::to_string(rule)
+                .context(format!("Failed to serialize rule: {}", rule.name))?,
+        })
+    }
+
+    /// The rule back from its definition, checked as a rules file's would be
+    pub fn to_rule(&self) -> Result<Rule> {
+        let rule: Rule = serde_json::from_str(&self.definition)
+            .context(format!("Invalid definition of rule: {}", self.name))?;
+        rule.validate()
+            .context(format!("Invalid rule: {}", self.name))?;
+        Ok(rule)
+    }
 }
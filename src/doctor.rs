@@ -0,0 +1,393 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use rusqlite::{Connection, OpenFlags};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use trust_dns_resolver::{
+    config::{ResolverConfig, ResolverOpts},
+    TokioAsyncResolver,
+};
+
+use crate::resolver;
+use crate::scanner::{self, HttpClientConfig};
+use crate::timeouts::Timeouts;
+
+/// File descriptors kept free for the database, DNS cache, logs and exports
+const SPARE_FILE_DESCRIPTORS: u64 = 64;
+
+/// Clock skew worth a warning: signed webhooks are rejected past a few minutes
+const CLOCK_SKEW_WARNING_SECS: i64 = 5 * 60;
+
+/// Clock skew that fails TLS certificate validation
+const CLOCK_SKEW_FAILURE_SECS: i64 = 24 * 60 * 60;
+
+/// Outcome of one environment check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Failed,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckStatus::Ok => write!(f, "✅"),
+            CheckStatus::Warning => write!(f, "⚠️ "),
+            CheckStatus::Failed => write!(f, "❌"),
+        }
+    }
+}
+
+/// Result of one environment check, with a hint on how to fix it when it didn't pass
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: String) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Ok,
+            detail,
+            hint: None,
+        }
+    }
+
+    fn warning(name: &'static str, detail: String, hint: String) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warning,
+            detail,
+            hint: Some(hint),
+        }
+    }
+
+    fn failed(name: &'static str, detail: String, hint: String) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Failed,
+            detail,
+            hint: Some(hint),
+        }
+    }
+}
+
+/// What `fatt doctor` checks the environment against
+#[derive(Debug, Clone)]
+pub struct DoctorOptions {
+    /// Concurrency the scan will run with
+    pub concurrency: usize,
+
+    /// Results database the scan will write to
+    pub database: String,
+
+    /// Host name to resolve
+    pub dns_host: String,
+
+    /// URLs to fetch, to check outbound HTTP and HTTPS
+    pub urls: Vec<String>,
+
+    /// Proxy URL the scan will send its requests through
+    pub proxy: Option<String>,
+
+    /// Timeout of each network check
+    pub timeout: Duration,
+}
+
+impl Default for DoctorOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 100,
+            database: "results.sqlite".to_string(),
+            dns_host: "example.com".to_string(),
+            urls: vec![
+                "http://example.com".to_string(),
+                "https://example.com".to_string(),
+            ],
+            proxy: None,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Check that `host` resolves with the resolver scans use
+pub async fn check_dns(host: &str, timeout: Duration) -> Check {
+    const NAME: &str = "DNS resolution";
+    let resolver = TokioAsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default());
+
+    match tokio::time::timeout(timeout, resolver.lookup_ip(host)).await {
+        Ok(Ok(ips)) => match ips.iter().next() {
+            Some(ip) => Check::ok(NAME, format!("{} resolved to {}", host, ip)),
+            None => Check::failed(
+                NAME,
+                format!("{} resolved to no addresses", host),
+                "Check that the DNS servers answer for public names".to_string(),
+            ),
+        },
+        Ok(Err(e)) => Check::failed(
+            NAME,
+            format!("Failed to resolve {}: {}", host, e),
+            "Check the network connection and the DNS servers the resolver uses".to_string(),
+        ),
+        Err(_) => Check::failed(
+            NAME,
+            format!("Resolving {} timed out after {:?}", host, timeout),
+            "Check that outbound DNS (UDP and TCP port 53) isn't blocked".to_string(),
+        ),
+    }
+}
+
+/// Check that `url` can be fetched, returning the server's clock from its `Date` header
+pub async fn check_http(client: &Client, url: &str) -> (Check, Option<DateTime<Utc>>) {
+    let name = if url.starts_with("https://") {
+        "Outbound HTTPS"
+    } else {
+        "Outbound HTTP"
+    };
+
+    match client.get(url).send().await {
+        Ok(response) => {
+            let server_time = response
+                .headers()
+                .get(reqwest::header::DATE)
+                .and_then(|date| date.to_str().ok())
+                .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+                .map(|date| date.with_timezone(&Utc));
+            let check = Check::ok(name, format!("{} answered HTTP {}", url, response.status()));
+            (check, server_time)
+        }
+        Err(e) => {
+            let hint = if e.is_timeout() {
+                "Check that outbound traffic isn't dropped by a firewall, or set --proxy"
+            } else if url.starts_with("https://") {
+                "Check the connection; an intercepting proxy needs --proxy to be scanned through"
+            } else {
+                "Check the connection and any firewall rules for outbound traffic, or set --proxy"
+            };
+            let check = Check::failed(
+                name,
+                format!("Failed to fetch {}: {:#}", url, anyhow::Error::from(e)),
+                hint.to_string(),
+            );
+            (check, None)
+        }
+    }
+}
+
+/// Soft limit on open files of this process, where the platform reports it
+pub fn open_files_limit() -> Option<u64> {
+    let limits = fs::read_to_string("/proc/self/limits").ok()?;
+    let line = limits
+        .lines()
+        .find(|line| line.starts_with("Max open files"))?;
+    let soft = line
+        .trim_start_matches("Max open files")
+        .split_whitespace()
+        .next()?;
+
+    match soft {
+        "unlimited" => Some(u64::MAX),
+        soft => soft.parse().ok(),
+    }
+}
+
+/// Check that the open files limit leaves a socket for each concurrent request
+pub fn check_open_files(concurrency: usize, limit: Option<u64>) -> Check {
+    const NAME: &str = "Open files limit";
+    let needed = concurrency as u64 + SPARE_FILE_DESCRIPTORS;
+
+    match limit {
+        Some(u64::MAX) => Check::ok(NAME, "Unlimited".to_string()),
+        Some(limit) if limit >= needed => Check::ok(
+            NAME,
+            format!("{} allows a concurrency of {}", limit, concurrency),
+        ),
+        Some(limit) => Check::failed(
+            NAME,
+            format!(
+                "{} is too low for a concurrency of {} (needs {})",
+                limit, concurrency, needed
+            ),
+            format!(
+                "Raise it with `ulimit -n {}` or lower --concurrency to {}",
+                needed,
+                limit.saturating_sub(SPARE_FILE_DESCRIPTORS)
+            ),
+        ),
+        None => Check::warning(
+            NAME,
+            "Couldn't read the open files limit".to_string(),
+            format!("Make sure `ulimit -n` is at least {}", needed),
+        ),
+    }
+}
+
+/// Check that files can be created in the directory
+pub fn check_writable_dir(name: &'static str, dir: &Path) -> Check {
+    let hint = format!(
+        "Make {} writable by this user, or run from a directory that is",
+        dir.display()
+    );
+
+    if let Err(e) = fs::create_dir_all(dir) {
+        return Check::failed(
+            name,
+            format!("Failed to create {}: {}", dir.display(), e),
+            hint,
+        );
+    }
+    match tempfile::NamedTempFile::new_in(dir) {
+        Ok(_) => Check::ok(name, format!("{} is writable", dir.display())),
+        Err(e) => Check::failed(
+            name,
+            format!("Failed to write to {}: {}", dir.display(), e),
+            hint,
+        ),
+    }
+}
+
+/// Check that the results database can be written, without creating it
+pub fn check_database(path: &str) -> Check {
+    const NAME: &str = "Results database";
+
+    if !Path::new(path).exists() {
+        let dir = match Path::new(path).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let check = check_writable_dir(NAME, dir);
+        return match check.status {
+            CheckStatus::Ok => Check::ok(NAME, format!("{} can be created", path)),
+            _ => check,
+        };
+    }
+
+    let writable = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE)
+        .and_then(|conn| conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK;"));
+    match writable {
+        Ok(()) => Check::ok(NAME, format!("{} is writable", path)),
+        Err(e) => Check::failed(
+            NAME,
+            format!("Failed to write to {}: {}", path, e),
+            format!(
+                "Check the permissions of {}, and that no other scan holds a lock on it",
+                path
+            ),
+        ),
+    }
+}
+
+/// Check the local clock against a server's
+pub fn check_clock(local: DateTime<Utc>, server: Option<DateTime<Utc>>) -> Check {
+    const NAME: &str = "Clock";
+    let hint = "Sync the clock with NTP (e.g. `timedatectl set-ntp true`)".to_string();
+
+    let Some(server) = server else {
+        return Check::warning(
+            NAME,
+            "No server reported its time to compare against".to_string(),
+            "Check outbound HTTPS first; the clock is compared with a server's Date header"
+                .to_string(),
+        );
+    };
+
+    let skew = (local - server).num_seconds();
+    let detail = match skew {
+        0 => "In sync with the server".to_string(),
+        skew if skew > 0 => format!("{}s ahead of the server", skew),
+        skew => format!("{}s behind the server", -skew),
+    };
+    match skew.abs() {
+        skew if skew >= CLOCK_SKEW_FAILURE_SECS => Check::failed(
+            NAME,
+            format!("{}; TLS certificates will fail to validate", detail),
+            hint,
+        ),
+        skew if skew >= CLOCK_SKEW_WARNING_SECS => Check::warning(
+            NAME,
+            format!("{}; signed webhooks may be rejected", detail),
+            hint,
+        ),
+        _ => Check::ok(NAME, detail),
+    }
+}
+
+/// Run every check
+pub async fn run_checks(options: &DoctorOptions) -> Vec<Check> {
+    let mut checks = vec![check_dns(&options.dns_host, options.timeout).await];
+
+    let client = scanner::build_http_client(&HttpClientConfig {
+        timeouts: Timeouts {
+            total: options.timeout,
+            ..Default::default()
+        },
+        proxy: options.proxy.clone(),
+        ..Default::default()
+    });
+    let mut server_time = None;
+    match client {
+        Ok(client) => {
+            for url in &options.urls {
+                let (check, time) = check_http(&client, url).await;
+                checks.push(check);
+                server_time = server_time.or(time);
+            }
+        }
+        Err(e) => checks.push(Check::failed(
+            "Outbound HTTP",
+            format!("{:#}", e),
+            "Fix the --proxy URL".to_string(),
+        )),
+    }
+
+    checks.push(check_open_files(options.concurrency, open_files_limit()));
+    checks.push(check_writable_dir(
+        "DNS cache directory",
+        Path::new(resolver::CACHE_DIR),
+    ));
+    checks.push(check_database(&options.database));
+    checks.push(check_clock(Utc::now(), server_time));
+
+    checks
+}
+
+/// Check the environment a scan would run in and print what needs fixing
+pub async fn run_doctor(options: &DoctorOptions) -> Result<()> {
+    println!("🩺 Checking the scan environment...\n");
+
+    let checks = run_checks(options).await;
+    for check in &checks {
+        println!("{} {:<22} {}", check.status, check.name, check.detail);
+        if let Some(hint) = &check.hint {
+            println!("   💡 {}", hint);
+        }
+    }
+
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Failed)
+        .count();
+    let warnings = checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Warning)
+        .count();
+
+    if failed > 0 {
+        anyhow::bail!("{} checks failed", failed);
+    }
+    if warnings > 0 {
+        println!("\n⚠️  Ready to scan, with {} warnings", warnings);
+    } else {
+        println!("\n✅ Ready to scan");
+    }
+
+    Ok(())
+}
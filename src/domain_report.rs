@@ -0,0 +1,322 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::db::{self, Finding};
+use crate::risk::{self, RiskWeights};
+use crate::rules::{RuleSet, Severity};
+use crate::store::{self, ResultStore};
+
+/// Everything known about one domain, for `results domain`
+#[derive(Debug, Serialize)]
+pub struct DomainReport {
+    pub domain: String,
+    /// When the domain was last scanned
+    pub last_scanned_at: Option<DateTime<Utc>>,
+    /// Addresses the domain resolved to in its last successful scan
+    pub ips: Vec<String>,
+    /// Technologies detected in its last successful scan
+    pub technologies: Vec<String>,
+    /// Detected findings, most severe first
+    pub findings: Vec<ReportFinding>,
+    /// Weighted risk score of the detected findings; unknown without the rules
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub risk_score: Option<f64>,
+    /// Recorded scans of the domain, newest first
+    pub history: Vec<DomainHistory>,
+    /// Rules detected in some of the scans in `history` but not in others
+    pub flaps: Vec<Flap>,
+}
+
+/// A current finding with its rule's severity
+#[derive(Debug, Serialize)]
+pub struct ReportFinding {
+    #[serde(flatten)]
+    pub finding: Finding,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+}
+
+/// One scan of the domain
+#[derive(Debug, Serialize)]
+pub struct DomainHistory {
+    pub scan_id: String,
+    pub scanned_at: DateTime<Utc>,
+    /// Rules detected in the scan
+    pub detected: Vec<String>,
+    /// Weighted risk score of the rules detected; unknown without the rules, or
+    /// if the scan failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub risk_score: Option<f64>,
+    /// Why the domain couldn't be scanned, if it couldn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A rule whose detection changed between scans
+#[derive(Debug, Serialize)]
+pub struct Flap {
+    pub rule_name: String,
+    /// Scans the rule was detected in
+    pub detected: usize,
+    /// Scans that completed for the domain
+    pub scans: usize,
+    /// Times the rule went from detected to not, or back, from one scan to the next
+    pub changes: usize,
+}
+
+/// Put together everything a store knows about a domain, looking at its `history`
+/// most recent scans; `ruleset` gives the findings' severities, which `weights`
+/// turn into risk scores
+pub fn build_report(
+    store: &dyn ResultStore,
+    domain: &str,
+    history: usize,
+    ruleset: Option<&RuleSet>,
+    weights: &RiskWeights,
+) -> Result<DomainReport> {
+    let severities: HashMap<&str, &Severity> = ruleset
+        .map(|ruleset| {
+            ruleset
+                .rules
+                .iter()
+                .filter_map(|rule| Some((rule.name.as_str(), rule.severity.as_ref()?)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut findings: Vec<Finding> = store
+        .findings()?
+        .into_iter()
+        .filter(|finding| finding.domain == domain && finding.detected)
+        .collect();
+    if let Some(ruleset) = ruleset {
+        db::attach_remediation(&mut findings, ruleset);
+    }
+    let score = |findings: &[Finding]| {
+        ruleset.map(|ruleset| risk::score_findings(findings, ruleset, weights).total)
+    };
+    let risk_score = score(&findings);
+    let mut findings: Vec<ReportFinding> = findings
+        .into_iter()
+        .map(|finding| ReportFinding {
+            severity: severities.get(finding.rule_name.as_str()).cloned().cloned(),
+            finding,
+        })
+        .collect();
+    findings.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.finding.rule_name.cmp(&b.finding.rule_name))
+    });
+
+    let scans: Vec<_> = store
+        .domain_scans(domain)?
+        .into_iter()
+        .take(history)
+        .collect();
+    let mut entries = Vec::with_capacity(scans.len());
+    for scan in &scans {
+        let (detected, risk_score) = match scan.error {
+            Some(_) => (Vec::new(), None),
+            None => {
+                let findings: Vec<Finding> = store
+                    .scan_findings(&scan.scan_id)?
+                    .into_iter()
+                    .filter(|finding| finding.domain == domain)
+                    .collect();
+                let risk_score = score(&findings);
+                (
+                    findings
+                        .into_iter()
+                        .map(|finding| finding.rule_name)
+                        .collect(),
+                    risk_score,
+                )
+            }
+        };
+        entries.push(DomainHistory {
+            scan_id: scan.scan_id.clone(),
+            scanned_at: scan.scanned_at,
+            detected,
+            risk_score,
+            error: scan.error.clone(),
+        });
+    }
+
+    // Addresses and technologies of a failed scan are unknown rather than gone
+    let last_ok = scans.iter().find(|scan| scan.error.is_none());
+    let last_scanned_at = scans.first().map(|scan| scan.scanned_at).or_else(|| {
+        // Scans from before domains were recorded only left their findings
+        findings
+            .iter()
+            .map(|finding| finding.finding.scanned_at)
+            .max()
+    });
+
+    Ok(DomainReport {
+        domain: domain.to_string(),
+        last_scanned_at,
+        ips: last_ok.map(|scan| scan.ips.clone()).unwrap_or_default(),
+        technologies: last_ok
+            .map(|scan| scan.technologies.clone())
+            .unwrap_or_default(),
+        findings,
+        risk_score,
+        flaps: flaps(&entries),
+        history: entries,
+    })
+}
+
+/// Rules detected in some but not all of the domain's completed scans
+fn flaps(history: &[DomainHistory]) -> Vec<Flap> {
+    let completed: Vec<BTreeSet<&str>> = history
+        .iter()
+        .rev()
+        .filter(|entry| entry.error.is_none())
+        .map(|entry| entry.detected.iter().map(String::as_str).collect())
+        .collect();
+
+    let mut seen: BTreeMap<&str, Flap> = BTreeMap::new();
+    for (i, detected) in completed.iter().enumerate() {
+        for rule_name in detected {
+            seen.entry(rule_name).or_insert_with(|| Flap {
+                rule_name: rule_name.to_string(),
+                detected: 0,
+                scans: completed.len(),
+                changes: 0,
+            });
+        }
+        for (rule_name, flap) in seen.iter_mut() {
+            let now = detected.contains(rule_name);
+            flap.detected += now as usize;
+            if i > 0 && now != completed[i - 1].contains(rule_name) {
+                flap.changes += 1;
+            }
+        }
+    }
+
+    seen.into_values()
+        .filter(|flap| flap.detected < flap.scans)
+        .collect()
+}
+
+/// Print everything known about a domain from the results `store` opened at `database`
+pub fn show_domain(
+    store: &dyn ResultStore,
+    database: &str,
+    domain: &str,
+    history: usize,
+    ruleset: Option<&RuleSet>,
+    weights: &RiskWeights,
+    format: &str,
+) -> Result<()> {
+    let report = build_report(store, domain, history, ruleset, weights)?;
+    if report.last_scanned_at.is_none() {
+        anyhow::bail!(
+            "No results for {} in {}",
+            domain,
+            store::redact_url(database)
+        );
+    }
+
+    match format.to_lowercase().as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&report)?),
+        "text" => print_report(&report),
+        _ => anyhow::bail!("Unsupported domain report format: {}", format),
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &DomainReport) {
+    println!("🌐 {}", report.domain);
+    if let Some(last_scanned_at) = report.last_scanned_at {
+        println!(
+            "\nLast scanned: {}",
+            last_scanned_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+    }
+    if !report.ips.is_empty() {
+        println!("Addresses:    {}", report.ips.join(", "));
+    }
+    if !report.technologies.is_empty() {
+        println!("Technologies: {}", report.technologies.join(", "));
+    }
+
+    match report.risk_score {
+        Some(risk_score) => println!(
+            "\nFindings: {}  Risk: {:.1}",
+            report.findings.len(),
+            risk_score
+        ),
+        None => println!("\nFindings: {}", report.findings.len()),
+    }
+    for entry in &report.findings {
+        let finding = &entry.finding;
+        println!(
+            "  {:<5} {:<10} {:<25} {:<30} {}",
+            finding.id,
+            entry
+                .severity
+                .as_ref()
+                .map_or_else(|| "-".to_string(), |severity| severity.to_string()),
+            db::truncate_string(&finding.rule_name, 24),
+            db::truncate_string(&finding.matched_path, 29),
+            finding.scanned_at.format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+
+    if !report.history.is_empty() {
+        println!("\nScans:");
+        for (i, entry) in report.history.iter().enumerate() {
+            let outcome = match &entry.error {
+                Some(error) => format!("❌ {}", error),
+                None if entry.detected.is_empty() => "nothing detected".to_string(),
+                None => entry.detected.join(", "),
+            };
+            println!(
+                "  {}  {}  {}{}",
+                entry.scanned_at.format("%Y-%m-%d %H:%M:%S"),
+                truncate_id(&entry.scan_id),
+                describe_risk(entry, previous_scored(&report.history[i + 1..])),
+                outcome
+            );
+        }
+    }
+
+    if !report.flaps.is_empty() {
+        println!("\nFlapping:");
+        for flap in &report.flaps {
+            println!(
+                "  {:<25} detected in {} of {} scans, changed {} times",
+                db::truncate_string(&flap.rule_name, 24),
+                flap.detected,
+                flap.scans,
+                flap.changes
+            );
+        }
+    }
+}
+
+/// The most recent completed and scored scan of older `history`
+fn previous_scored(history: &[DomainHistory]) -> Option<f64> {
+    history.iter().find_map(|entry| entry.risk_score)
+}
+
+/// A scan's risk score, and its change from the `previous` one, for a history line
+fn describe_risk(entry: &DomainHistory, previous: Option<f64>) -> String {
+    let Some(risk_score) = entry.risk_score else {
+        return String::new();
+    };
+    match previous.and_then(|previous| risk::risk_change_percent(previous, risk_score)) {
+        Some(percent) => format!("risk {:.1} ({:+.1}%)  ", risk_score, percent),
+        None => format!("risk {:.1}  ", risk_score),
+    }
+}
+
+/// First block of a scan ID, enough to tell scans apart in a listing
+fn truncate_id(scan_id: &str) -> &str {
+    scan_id.split('-').next().unwrap_or(scan_id)
+}
@@ -0,0 +1,274 @@
+use anyhow::{Context, Result};
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::db::{self, Finding, ListFormat};
+use crate::notify::{ChatCard, FindingNotification, ScanNotification};
+use crate::rules::Severity;
+use crate::secrets;
+
+/// Findings listed in the body of a digest; the attached CSV has all of them
+const DIGEST_LISTED: usize = 20;
+
+/// Longest an SMTP command may take before the email is given up on
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection with STARTTLS, on port 587 by default
+    #[default]
+    Starttls,
+    /// TLS from the first byte, on port 465 by default
+    Tls,
+    /// No encryption, on port 25 by default; only for a relay on a trusted network
+    None,
+}
+
+impl SmtpSecurity {
+    fn default_port(self) -> u16 {
+        match self {
+            SmtpSecurity::Starttls => 587,
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::None => 25,
+        }
+    }
+}
+
+/// SMTP server emails are sent through
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SmtpConfig {
+    pub host: String,
+
+    /// Port to connect to; defaults to the usual port of `security`
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "secrets::option_from_str_or_value"
+    )]
+    pub port: Option<u16>,
+
+    #[serde(default)]
+    pub security: SmtpSecurity,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// What an email notification is sent for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailMode {
+    /// One email when the scan completes, with every finding in an attached CSV
+    #[default]
+    Digest,
+    /// One email per finding of at least `min_severity`, as soon as it's detected
+    Alerts,
+}
+
+/// Email notifications sent through an SMTP server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    pub smtp: SmtpConfig,
+
+    /// Sender address, such as `FATT <fatt@example.com>`
+    pub from: String,
+
+    /// Recipient addresses
+    pub to: Vec<String>,
+
+    /// Send a digest after every scan, or an alert for every severe finding
+    #[serde(default)]
+    pub mode: EmailMode,
+
+    /// Lowest severity alerted on in `alerts` mode
+    #[serde(default = "default_min_severity")]
+    pub min_severity: Severity,
+
+    /// Only send the digest when the scan detected something or saw an anomaly
+    #[serde(default, deserialize_with = "secrets::from_str_or_value")]
+    pub only_on_findings: bool,
+}
+
+fn default_min_severity() -> Severity {
+    Severity::Critical
+}
+
+impl EmailConfig {
+    /// Check that the addresses parse and the SMTP credentials are complete
+    pub fn validate(&self) -> Result<()> {
+        if self.smtp.host.trim().is_empty() {
+            anyhow::bail!("Email notifications need an SMTP host");
+        }
+        if self.smtp.username.is_some() != self.smtp.password.is_some() {
+            anyhow::bail!(
+                "SMTP credentials for {} need both a username and a password",
+                self.smtp.host
+            );
+        }
+        self.sender()?;
+        if self.recipients()?.is_empty() {
+            anyhow::bail!("Email notifications need at least one recipient");
+        }
+        Ok(())
+    }
+
+    /// Whether a finding is alerted on by email as soon as it's detected
+    pub fn alerts_on(&self, finding: &FindingNotification) -> bool {
+        self.mode == EmailMode::Alerts
+            && finding
+                .severity
+                .as_ref()
+                .is_some_and(|severity| *severity >= self.min_severity)
+    }
+
+    fn sender(&self) -> Result<Mailbox> {
+        self.from
+            .parse()
+            .context(format!("Invalid sender address: {}", self.from))
+    }
+
+    fn recipients(&self) -> Result<Vec<Mailbox>> {
+        self.to
+            .iter()
+            .map(|to| {
+                to.parse()
+                    .context(format!("Invalid recipient address: {}", to))
+            })
+            .collect()
+    }
+
+    fn message(&self, subject: String) -> Result<lettre::message::MessageBuilder> {
+        let mut message = Message::builder().from(self.sender()?).subject(subject);
+        for to in self.recipients()? {
+            message = message.to(to);
+        }
+        Ok(message)
+    }
+
+    /// The email alerting on one finding
+    pub fn alert_message(&self, finding: &FindingNotification) -> Result<Message> {
+        let severity = finding
+            .severity
+            .as_ref()
+            .map_or_else(|| "unknown".to_string(), Severity::to_string);
+        self.message(format!("FATT {} finding: {}", severity, finding.title()))?
+            .header(ContentType::TEXT_PLAIN)
+            .body(fact_lines(finding))
+            .context("Failed to build alert email")
+    }
+
+    /// The end-of-scan digest: the scan's summary and its first findings in the body,
+    /// and every finding in an attached CSV
+    pub fn digest_message(
+        &self,
+        summary: &ScanNotification,
+        findings: &[Finding],
+    ) -> Result<Message> {
+        let mut body = fact_lines(summary);
+        if !findings.is_empty() {
+            body.push_str("\nFindings:\n");
+            for finding in findings.iter().take(DIGEST_LISTED) {
+                body.push_str(&format!(
+                    "  {}  {}  {}\n",
+                    finding.domain, finding.rule_name, finding.matched_path
+                ));
+            }
+            if findings.len() > DIGEST_LISTED {
+                body.push_str(&format!(
+                    "  ... and {} more in the attached CSV\n",
+                    findings.len() - DIGEST_LISTED
+                ));
+            }
+        }
+
+        let mut csv = Vec::new();
+        db::write_findings(findings, ListFormat::Csv, &mut csv)?;
+        let attachment = Attachment::new(format!("fatt-{}.csv", summary.scan_id))
+            .body(csv, ContentType::parse("text/csv")?);
+
+        self.message(summary.title())?
+            .multipart(
+                MultiPart::mixed()
+                    .singlepart(SinglePart::plain(body))
+                    .singlepart(attachment),
+            )
+            .context("Failed to build digest email")
+    }
+
+    /// Send an email through the configured SMTP server
+    pub async fn send(&self, message: Message) -> Result<()> {
+        let smtp = &self.smtp;
+        let transport = match smtp.security {
+            SmtpSecurity::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host)?,
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host)
+            }
+        };
+        let mut transport = transport
+            .port(smtp.port.unwrap_or(smtp.security.default_port()))
+            .timeout(Some(SMTP_TIMEOUT));
+        if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+            transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        transport
+            .build()
+            .send(message)
+            .await
+            .context(format!("Failed to send email through {}", smtp.host))?;
+
+        Ok(())
+    }
+
+    /// Email an alert on a finding
+    pub async fn send_alert(&self, finding: &FindingNotification) -> Result<()> {
+        self.send(self.alert_message(finding)?).await
+    }
+}
+
+/// A notification's facts, one `Label: value` per line
+fn fact_lines(card: &impl ChatCard) -> String {
+    card.facts()
+        .into_iter()
+        .map(|(label, value)| format!("{}: {}\n", label, value))
+        .collect()
+}
+
+/// Send the end-of-scan digest to every digest email, logging failures rather than
+/// failing the scan
+pub async fn send_digests(
+    emails: &[EmailConfig],
+    summary: &ScanNotification,
+    findings: &[Finding],
+) {
+    for email in emails {
+        if email.mode != EmailMode::Digest || (email.only_on_findings && !summary.is_noteworthy()) {
+            continue;
+        }
+
+        let sent = match email.digest_message(summary, findings) {
+            Ok(message) => email.send(message).await,
+            Err(e) => Err(e),
+        };
+        match sent {
+            Ok(()) => info!("📧 Emailed the scan digest to {}", email.to.join(", ")),
+            Err(e) => warn!("⚠️ Failed to email the scan digest: {:#}", e),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::config::ScanConfig;
+use crate::db::Finding;
+use crate::error::FattError;
+use crate::exit::{Classify, Failure};
+use crate::scanner;
+use crate::store::{self, StoreKind};
+use crate::workdir::Workdir;
+
+/// Runs scans for a program embedding fatt, with the configuration checked up front.
+///
+/// Results go to the configured result store, as with `fatt scan`.
+#[derive(Debug, Clone)]
+pub struct ScanEngine {
+    config: ScanConfig,
+}
+
+impl ScanEngine {
+    /// An engine for `config`, or why the configuration can't be scanned with
+    pub fn new(config: ScanConfig) -> Result<Self, FattError> {
+        config.validate().classify(Failure::Config)?;
+
+        Ok(Self { config })
+    }
+
+    pub fn config(&self) -> &ScanConfig {
+        &self.config
+    }
+
+    /// Scan every input domain with every rule
+    pub async fn run(&self) -> Result<(), FattError> {
+        Ok(scanner::run_scan(self.config.clone()).await?)
+    }
+
+    /// Findings in the result store, from this and earlier scans
+    pub fn findings(&self) -> Result<Vec<Finding>, FattError> {
+        let config = &self.config;
+        let database = match &config.workdir {
+            // A shared PostgreSQL database stays where it is
+            Some(dir) if config.store_kind() != StoreKind::Postgres => Workdir::new(dir)
+                .results_path(config.store)
+                .to_string_lossy()
+                .into_owned(),
+            _ => config.db_path.clone(),
+        };
+
+        Ok(
+            store::load_findings(config.store, &database, config.db_shards)
+                .classify(Failure::Database)?,
+        )
+    }
+}
@@ -0,0 +1,83 @@
+use crate::canary::CanaryCheckFailed;
+use crate::exit;
+use crate::expected::UnexpectedFindings;
+use crate::shutdown::ScanInterrupted;
+
+/// Error returned by the library's stable API, in the classes the CLI's exit
+/// codes distinguish
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum FattError {
+    /// The configuration is invalid
+    #[error("{0:#}")]
+    Config(anyhow::Error),
+
+    /// An input file of domains couldn't be read
+    #[error("{0:#}")]
+    Input(anyhow::Error),
+
+    /// The rules couldn't be read or are invalid
+    #[error("{0:#}")]
+    Rules(anyhow::Error),
+
+    /// The result store couldn't be opened, read or written
+    #[error("{0:#}")]
+    Database(anyhow::Error),
+
+    /// The scan was stopped before every domain was scanned
+    #[error("Scan interrupted after {processed} of {total} domains")]
+    Interrupted { processed: usize, total: usize },
+
+    /// The scan found something not in the expected findings
+    #[error("Scan produced {0} unexpected findings")]
+    UnexpectedFindings(usize),
+
+    /// The canary domains didn't come back as expected, so the scan didn't start
+    #[error("Canary check failed: {0} problems, scan aborted")]
+    CanaryFailed(usize),
+
+    /// Any other failure
+    #[error("{0:#}")]
+    Other(anyhow::Error),
+}
+
+impl FattError {
+    /// Code the CLI exits with for this error
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            FattError::Config(_) => exit::CONFIG_ERROR,
+            FattError::Input(_) => exit::INPUT_ERROR,
+            FattError::Rules(_) => exit::RULES_ERROR,
+            FattError::Database(_) => exit::DATABASE_ERROR,
+            FattError::Interrupted { .. } => exit::INTERRUPTED,
+            FattError::UnexpectedFindings(_) => exit::UNEXPECTED_FINDINGS,
+            FattError::CanaryFailed(_) => exit::CANARY_FAILED,
+            FattError::Other(_) => exit::FAILURE,
+        }
+    }
+}
+
+impl From<anyhow::Error> for FattError {
+    fn from(error: anyhow::Error) -> Self {
+        if let Some(interrupted) = error.downcast_ref::<ScanInterrupted>() {
+            return FattError::Interrupted {
+                processed: interrupted.processed,
+                total: interrupted.total,
+            };
+        }
+        if let Some(UnexpectedFindings(count)) = error.downcast_ref::<UnexpectedFindings>() {
+            return FattError::UnexpectedFindings(*count);
+        }
+        if let Some(CanaryCheckFailed(problems)) = error.downcast_ref::<CanaryCheckFailed>() {
+            return FattError::CanaryFailed(*problems);
+        }
+
+        match exit::exit_code(&error) {
+            exit::CONFIG_ERROR => FattError::Config(error),
+            exit::INPUT_ERROR => FattError::Input(error),
+            exit::RULES_ERROR => FattError::Rules(error),
+            exit::DATABASE_ERROR => FattError::Database(error),
+            _ => FattError::Other(error),
+        }
+    }
+}
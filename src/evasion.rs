@@ -0,0 +1,65 @@
+use anyhow::Result;
+use rand::seq::SliceRandom;
+use reqwest::header::{HeaderName, HeaderValue};
+use rustls::crypto::ring;
+use rustls::{ClientConfig, RootCertStore};
+use std::sync::Arc;
+
+/// User-Agent sent instead of the scanner's own when evasion is enabled
+pub const BROWSER_USER_AGENT: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0 Safari/537.36";
+
+/// Browser-like headers sent with every request when evasion is enabled
+const BROWSER_HEADERS: &[(&str, &str)] = &[
+    (
+        "accept",
+        "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+    ),
+    ("accept-language", "en-US,en;q=0.9"),
+    ("cache-control", "no-cache"),
+    ("pragma", "no-cache"),
+    ("upgrade-insecure-requests", "1"),
+];
+
+/// Browser-like headers in a random order
+pub fn browser_headers() -> Vec<(HeaderName, HeaderValue)> {
+    let mut headers: Vec<(HeaderName, HeaderValue)> = BROWSER_HEADERS
+        .iter()
+        .map(|(name, value)| {
+            (
+                HeaderName::from_static(name),
+                HeaderValue::from_static(value),
+            )
+        })
+        .collect();
+    headers.shuffle(&mut rand::thread_rng());
+    headers
+}
+
+/// Build a rustls configuration with randomized cipher suite and key exchange order.
+///
+/// The order of offered cipher suites and groups is what JA3-style fingerprints key
+/// on, so every client built from this presents a different fingerprint.
+pub fn tls_config() -> Result<ClientConfig> {
+    let mut rng = rand::thread_rng();
+
+    let mut provider = ring::default_provider();
+    provider.cipher_suites.shuffle(&mut rng);
+    provider.kx_groups.shuffle(&mut rng);
+
+    let mut versions = vec![&rustls::version::TLS13, &rustls::version::TLS12];
+    versions.shuffle(&mut rng);
+
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+    let mut config = ClientConfig::builder_with_provider(Arc::new(provider))
+        .with_protocol_versions(&versions)?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    // Keep to HTTP/1.1 so header order goes on the wire as sent
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(config)
+}
@@ -0,0 +1,132 @@
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Body bytes kept before the match, so the snippet shows what led up to it
+const CONTEXT_BEFORE: usize = 64;
+
+/// Longest snippet of a response body kept with a finding
+pub const MAX_SNIPPET_BYTES: usize = 256;
+
+/// What a finding was observed in, kept so it can be triaged without probing the
+/// target again
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Evidence {
+    /// HTTP status of the matching response
+    pub status: u16,
+
+    /// Byte offset of the first signature match in the response body
+    pub offset: usize,
+
+    /// Part of the body around the match: at most [`MAX_SNIPPET_BYTES`] of it, decoded
+    /// as UTF-8 with invalid bytes replaced
+    pub snippet: String,
+
+    /// Cache validators of the matching response, sent with the next scan's request so
+    /// an unchanged response needn't be downloaded again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validators: Option<CacheValidators>,
+
+    /// Whether the response was revalidated as unchanged (304 Not Modified) rather than
+    /// matched again; the snippet is then the one first observed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unchanged: bool,
+
+    /// SHA-256 of the body, in hex, so findings that returned byte-identical content
+    /// can be grouped. Only a body read to its end is hashed: one cut short is
+    /// `truncated` instead, and evidence from before bodies were hashed has neither.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_hash: Option<String>,
+
+    /// Whether the download stopped before the end of the body, at the match or the
+    /// size limit, leaving it unhashed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl Evidence {
+    /// Evidence of a match at `offset` in a whole response body
+    pub fn capture(status: u16, body: &[u8], offset: usize) -> Self {
+        let start = offset.min(body.len()).saturating_sub(CONTEXT_BEFORE);
+        let end = (start + MAX_SNIPPET_BYTES).min(body.len());
+
+        // The bounds can split a multi-byte character, which decodes as a
+        // replacement character at either end of the snippet
+        let snippet = String::from_utf8_lossy(&body[start..end])
+            .trim_matches(char::REPLACEMENT_CHARACTER)
+            .to_string();
+
+        Self {
+            status,
+            offset,
+            snippet,
+            validators: None,
+            unchanged: false,
+            body_hash: Some(hex::encode(Sha256::digest(body))),
+            truncated: false,
+        }
+    }
+
+    /// Mark the body the evidence was captured from as only partly read, so its hash,
+    /// which would cover an arbitrary prefix, is dropped
+    pub fn with_truncated_body(mut self) -> Self {
+        self.body_hash = None;
+        self.truncated = true;
+        self
+    }
+
+    /// Keep the cache validators of the response the evidence was captured from
+    pub fn with_validators(mut self, headers: &HeaderMap) -> Self {
+        self.validators = CacheValidators::from_headers(headers);
+        self
+    }
+
+    /// The evidence carried over to a scan that found the response unchanged
+    pub fn revalidated(&self) -> Self {
+        Self {
+            unchanged: true,
+            ..self.clone()
+        }
+    }
+}
+
+/// A response's `ETag` and `Last-Modified`, which a conditional request can send back
+/// to learn whether it changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheValidators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    /// The validators of a response, if it has any
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let validators = Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+        (validators.etag.is_some() || validators.last_modified.is_some()).then_some(validators)
+    }
+
+    /// Make a request conditional: `If-None-Match` with the ETag, which takes
+    /// precedence, and `If-Modified-Since` with the last modification time
+    pub fn apply(&self, mut builder: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            builder = builder.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            builder = builder.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        builder
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::Result;
+
+use crate::canary::CanaryCheckFailed;
+use crate::expected::UnexpectedFindings;
+use crate::shutdown::ScanInterrupted;
+
+/// The command succeeded
+pub const SUCCESS: u8 = 0;
+
+/// A failure that doesn't fall in any of the classes below
+pub const FAILURE: u8 = 1;
+
+/// The configuration (flags, config file, manifest) is invalid
+pub const CONFIG_ERROR: u8 = 3;
+
+/// An input file of domains couldn't be read
+pub const INPUT_ERROR: u8 = 4;
+
+/// The rules couldn't be read or are invalid
+pub const RULES_ERROR: u8 = 5;
+
+/// The results database couldn't be opened, read or written
+pub const DATABASE_ERROR: u8 = 6;
+
+/// The scan completed but found something not in the expected findings
+pub const UNEXPECTED_FINDINGS: u8 = 10;
+
+/// The canary domains didn't come back as expected, so the scan was not started
+pub const CANARY_FAILED: u8 = 11;
+
+/// The scan was stopped before every domain was scanned
+pub const INTERRUPTED: u8 = 130;
+
+/// Class of an operational failure, attached to an error as context so the process
+/// can exit with a code scripts can branch on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum Failure {
+    #[error("Invalid configuration")]
+    Config,
+    #[error("Failed to read input")]
+    Input,
+    #[error("Failed to load rules")]
+    Rules,
+    #[error("Database error")]
+    Database,
+}
+
+impl Failure {
+    pub fn code(self) -> u8 {
+        match self {
+            Failure::Config => CONFIG_ERROR,
+            Failure::Input => INPUT_ERROR,
+            Failure::Rules => RULES_ERROR,
+            Failure::Database => DATABASE_ERROR,
+        }
+    }
+
+    /// An error of this class, reported with `message`
+    pub fn because(self, message: String) -> anyhow::Error {
+        anyhow::Error::new(self).context(message)
+    }
+}
+
+/// Attach a failure class to an error, unless a more specific one is already attached
+pub trait Classify<T> {
+    fn classify(self, failure: Failure) -> Result<T>;
+}
+
+impl<T> Classify<T> for Result<T> {
+    fn classify(self, failure: Failure) -> Result<T> {
+        self.map_err(|e| {
+            if e.downcast_ref::<Failure>().is_some() {
+                e
+            } else {
+                e.context(failure)
+            }
+        })
+    }
+}
+
+/// Exit code for a command that failed with `error`
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    if error.is::<ScanInterrupted>() {
+        return INTERRUPTED;
+    }
+    if error.is::<UnexpectedFindings>() {
+        return UNEXPECTED_FINDINGS;
+    }
+    if error.is::<CanaryCheckFailed>() {
+        return CANARY_FAILED;
+    }
+    if let Some(failure) = error.downcast_ref::<Failure>() {
+        return failure.code();
+    }
+
+    // SQLite and PostgreSQL errors from anywhere are database failures
+    if error.chain().any(is_database_error) {
+        return DATABASE_ERROR;
+    }
+
+    FAILURE
+}
+
+fn is_database_error(cause: &(dyn std::error::Error + 'static)) -> bool {
+    #[cfg(feature = "postgres")]
+    if cause.is::<postgres::Error>() {
+        return true;
+    }
+    cause.is::<rusqlite::Error>()
+}
@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+use tracing::debug;
+
+/// Default most input entries expanded at once
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Longest an expansion command may run for one entry
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Turns an input entry into the targets it stands for, such as a service name into
+/// its hostnames from an inventory. Expansion runs as domains are read, so the
+/// targets of an entry are scanned as soon as it's expanded.
+#[async_trait]
+pub trait TargetExpander: Send + Sync {
+    /// Targets to scan for an input entry; none leaves the entry out of the scan
+    async fn expand(&self, entry: &str) -> Result<Vec<String>>;
+}
+
+/// Expands each entry by running a shell command, which gets the entry as `$1` and in
+/// `FATT_ENTRY` and prints the targets one per line. Blank lines and lines starting
+/// with `#` are ignored.
+#[derive(Debug)]
+pub struct CommandExpander {
+    command: String,
+    permits: Semaphore,
+    timeout: Duration,
+}
+
+impl CommandExpander {
+    /// An expander running `command`, at most `concurrency` at once
+    pub fn new(command: &str, concurrency: usize) -> Self {
+        Self {
+            command: command.to_string(),
+            permits: Semaphore::new(concurrency.max(1)),
+            timeout: COMMAND_TIMEOUT,
+        }
+    }
+
+    /// Give up on a command after `timeout` instead of the default 30 seconds
+    #[allow(dead_code)]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[async_trait]
+impl TargetExpander for CommandExpander {
+    async fn expand(&self, entry: &str) -> Result<Vec<String>> {
+        let _permit = self.permits.acquire().await?;
+
+        // The entry is an argument rather than part of the script, so it's never
+        // interpreted by the shell
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .arg("fatt-expand")
+            .arg(entry)
+            .env("FATT_ENTRY", entry)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output();
+        let output = tokio::time::timeout(self.timeout, output)
+            .await
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Expansion command timed out after {}s",
+                    self.timeout.as_secs()
+                )
+            })?
+            .context(format!("Failed to run expansion command: {}", self.command))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Expansion command failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let stdout =
+            String::from_utf8(output.stdout).context("Expansion command printed invalid UTF-8")?;
+        let targets: Vec<String> = stdout
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect();
+        debug!("🧩 Expanded {} into {} targets", entry, targets.len());
+
+        Ok(targets)
+    }
+}
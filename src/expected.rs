@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use tracing::info;
+
+use crate::db::Finding;
+use crate::utils;
+
+/// A single accepted-risk finding
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ExpectedFinding {
+    pub domain: String,
+    pub rule: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Error a scan ends with when it found something not in the expected findings
+#[derive(Debug, thiserror::Error)]
+#[error("Scan produced {0} unexpected findings")]
+pub struct UnexpectedFindings(pub usize);
+
+/// Collection of findings that are expected (accepted risk) for a scan
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ExpectedFindings {
+    pub expected: Vec<ExpectedFinding>,
+}
+
+impl ExpectedFindings {
+    /// Load expected findings from a YAML file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path.as_ref()).context(format!(
+            "Failed to open expected findings file: {}",
+            path.as_ref().display()
+        ))?;
+
+        let reader = BufReader::new(file);
+        let expected: ExpectedFindings = serde_yaml::from_reader(reader).context(format!(
+            "Failed to parse expected findings file: {}",
+            path.as_ref().display()
+        ))?;
+
+        info!(
+            "📋 Loaded {} expected findings from {}",
+            expected.expected.len(),
+            path.as_ref().display()
+        );
+
+        Ok(expected)
+    }
+
+    /// Check whether a (domain, rule) pair is an accepted finding
+    #[allow(dead_code)]
+    pub fn is_expected(&self, domain: &str, rule_name: &str) -> bool {
+        self.expected.iter().any(|e| {
+            utils::normalize_domain(&e.domain) == utils::normalize_domain(domain)
+                && e.rule == rule_name
+        })
+    }
+
+    /// Return the findings that are not covered by the allowlist
+    pub fn unexpected<'a>(&self, findings: &'a [Finding]) -> Vec<&'a Finding> {
+        let allowed: HashSet<(String, &str)> = self
+            .expected
+            .iter()
+            .map(|e| (utils::normalize_domain(&e.domain), e.rule.as_str()))
+            .collect();
+
+        findings
+            .iter()
+            .filter(|f| {
+                !allowed.contains(&(utils::normalize_domain(&f.domain), f.rule_name.as_str()))
+            })
+            .collect()
+    }
+}
+
+/// Load expected findings from a YAML file
+pub fn load_expected(expected_file: &str) -> Result<ExpectedFindings> {
+    ExpectedFindings::from_file(expected_file)
+}
@@ -0,0 +1,191 @@
+use anyhow::{Context, Result};
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::notify::{self, ChatCard, FindingNotification};
+use crate::rules::Severity;
+
+/// PagerDuty Events API v2 endpoint alerts are triggered through
+const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Opsgenie Alert API endpoint in the US region
+const OPSGENIE_ALERTS_URL: &str = "https://api.opsgenie.com/v2/alerts";
+
+/// Longest deduplication key PagerDuty accepts; Opsgenie's alias limit is longer
+const DEDUP_KEY_MAX: usize = 255;
+
+/// Longest message Opsgenie keeps for an alert
+const OPSGENIE_MESSAGE_MAX: usize = 130;
+
+/// Incident management service alerts are raised in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IncidentService {
+    /// PagerDuty, through the Events API v2 with an integration's routing key
+    Pagerduty,
+    /// Opsgenie, through the Alert API with an API integration's key
+    Opsgenie,
+}
+
+/// An incident management service alerted on every finding of at least a severity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct IncidentConfig {
+    pub service: IncidentService,
+
+    /// PagerDuty integration routing key, or Opsgenie API key
+    pub key: String,
+
+    /// Lowest severity alerted on
+    #[serde(default = "default_min_severity")]
+    pub min_severity: Severity,
+
+    /// Endpoint to send alerts to instead of the service's default, such as
+    /// `https://api.eu.opsgenie.com/v2/alerts` for Opsgenie's EU region
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+fn default_min_severity() -> Severity {
+    Severity::High
+}
+
+impl IncidentConfig {
+    /// Check that the key is set and the endpoint parses
+    pub fn validate(&self) -> Result<()> {
+        if self.key.trim().is_empty() {
+            anyhow::bail!("{} alerts need a key", self.service_name());
+        }
+        url::Url::parse(self.url()).context(format!(
+            "Invalid {} URL: {}",
+            self.service_name(),
+            self.url()
+        ))?;
+        Ok(())
+    }
+
+    /// Whether a finding raises an alert
+    pub fn alerts_on(&self, finding: &FindingNotification) -> bool {
+        finding
+            .severity
+            .as_ref()
+            .is_some_and(|severity| *severity >= self.min_severity)
+    }
+
+    fn service_name(&self) -> &'static str {
+        match self.service {
+            IncidentService::Pagerduty => "PagerDuty",
+            IncidentService::Opsgenie => "Opsgenie",
+        }
+    }
+
+    fn url(&self) -> &str {
+        self.url.as_deref().unwrap_or(match self.service {
+            IncidentService::Pagerduty => PAGERDUTY_EVENTS_URL,
+            IncidentService::Opsgenie => OPSGENIE_ALERTS_URL,
+        })
+    }
+
+    /// The request body raising an alert on a finding
+    pub fn payload(&self, finding: &FindingNotification) -> serde_json::Value {
+        let details: serde_json::Map<String, serde_json::Value> = finding
+            .facts()
+            .into_iter()
+            .map(|(label, value)| (label.to_string(), value.into()))
+            .collect();
+        let dedup_key = dedup_key(&finding.domain, &finding.rule);
+
+        match self.service {
+            IncidentService::Pagerduty => json!({
+                "routing_key": self.key,
+                "event_action": "trigger",
+                "dedup_key": dedup_key,
+                "payload": {
+                    "summary": format!("FATT: {}", finding.title()),
+                    "source": finding.domain,
+                    "severity": pagerduty_severity(finding.severity.as_ref()),
+                    "timestamp": finding.found_at.to_rfc3339(),
+                    "component": finding.path,
+                    "class": finding.rule,
+                    "custom_details": details,
+                },
+            }),
+            IncidentService::Opsgenie => json!({
+                "message": notify::truncate_chars(
+                    &format!("FATT: {}", finding.title()),
+                    OPSGENIE_MESSAGE_MAX
+                ),
+                "alias": dedup_key,
+                "description": format!("{} found at {}{}", finding.rule, finding.domain, finding.path),
+                "source": "fatt",
+                "entity": finding.domain,
+                "priority": opsgenie_priority(finding.severity.as_ref()),
+                "tags": ["fatt"],
+                "details": details,
+            }),
+        }
+    }
+
+    /// Raise an alert on a finding
+    pub async fn send_alert(&self, client: &Client, finding: &FindingNotification) -> Result<()> {
+        let mut request = client.post(self.url()).json(&self.payload(finding));
+        if self.service == IncidentService::Opsgenie {
+            request = request.header(
+                reqwest::header::AUTHORIZATION,
+                format!("GenieKey {}", self.key),
+            );
+        }
+
+        let response = request
+            .send()
+            .await
+            .context(format!("Failed to send {} alert", self.service_name()))?;
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "{} returned HTTP {}",
+                self.service_name(),
+                response.status()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Key alerts on the same domain and rule share, so a finding seen by every scan of a
+/// continuous monitor updates one open incident instead of raising a new one. Keys too
+/// long for PagerDuty are hashed.
+pub fn dedup_key(domain: &str, rule: &str) -> String {
+    let key = format!("fatt:{}:{}", domain, rule);
+    if key.len() <= DEDUP_KEY_MAX {
+        return key;
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(domain.as_bytes());
+    hasher.update([0]);
+    hasher.update(rule.as_bytes());
+    format!("fatt:{}", hex::encode(hasher.finalize()))
+}
+
+fn pagerduty_severity(severity: Option<&Severity>) -> &'static str {
+    match severity {
+        Some(Severity::Critical) => "critical",
+        Some(Severity::High) => "error",
+        Some(Severity::Medium) => "warning",
+        _ => "info",
+    }
+}
+
+fn opsgenie_priority(severity: Option<&Severity>) -> &'static str {
+    match severity {
+        Some(Severity::Critical) => "P1",
+        Some(Severity::High) => "P2",
+        Some(Severity::Medium) => "P3",
+        Some(Severity::Low) => "P4",
+        _ => "P5",
+    }
+}
@@ -1,13 +1,145 @@
-// Export internal modules for testing
+//! Find All The Things: scan many domains for exposed files and directories.
+//!
+//! Programs embedding the scanner should use [`prelude`]: the types it exports
+//! follow semver. The hidden modules back the CLI and the integration tests, and
+//! can change in any release.
+
 pub mod config;
+pub mod engine;
+pub mod error;
+pub mod rules;
+
+/// The library's stable API
+pub mod prelude {
+    pub use crate::config::{ScanConfig, ScanConfigBuilder};
+    pub use crate::db::Finding;
+    pub use crate::engine::ScanEngine;
+    pub use crate::error::FattError;
+    pub use crate::evidence::Evidence;
+    pub use crate::rules::{Rule, RuleSet, Severity};
+    pub use crate::store::StoreKind;
+}
+
+// Internal modules, exported for the CLI and for testing
+#[doc(hidden)]
+pub mod anomaly;
+#[doc(hidden)]
+pub mod brute;
+#[doc(hidden)]
+pub mod canary;
+#[doc(hidden)]
+pub mod cert_inventory;
+#[cfg(feature = "distributed")]
+#[doc(hidden)]
+pub mod certs;
+#[cfg(feature = "chaos")]
+#[doc(hidden)]
+pub mod chaos;
+#[doc(hidden)]
+pub mod clusters;
+#[doc(hidden)]
+pub mod compare;
+#[doc(hidden)]
+pub mod config_file;
+#[doc(hidden)]
+pub mod context;
+#[doc(hidden)]
 pub mod db;
+#[doc(hidden)]
+pub mod detail;
+#[doc(hidden)]
+pub mod diff;
+#[cfg(feature = "distributed")]
+#[doc(hidden)]
 pub mod distributed;
+#[doc(hidden)]
+pub mod doctor;
+#[doc(hidden)]
+pub mod domain_report;
+#[doc(hidden)]
+pub mod email;
+#[doc(hidden)]
+pub mod evasion;
+#[doc(hidden)]
+pub mod evidence;
+#[doc(hidden)]
+pub mod exit;
+#[doc(hidden)]
+pub mod expand;
+#[doc(hidden)]
+pub mod expected;
+#[doc(hidden)]
+pub mod incident;
+#[doc(hidden)]
 pub mod logger;
+#[doc(hidden)]
+pub mod manifest;
+#[doc(hidden)]
+pub mod migrate;
+#[doc(hidden)]
+pub mod notify;
+#[doc(hidden)]
+pub mod otel;
+#[cfg(feature = "postgres")]
+#[doc(hidden)]
+pub mod pg;
+#[doc(hidden)]
+pub mod pipeline;
+#[doc(hidden)]
+pub mod profile;
+#[doc(hidden)]
+pub mod redact;
+#[doc(hidden)]
 pub mod resolver;
-pub mod rules;
+#[doc(hidden)]
+pub mod retention;
+#[doc(hidden)]
+pub mod risk;
+#[doc(hidden)]
+pub mod rule_lint;
+#[doc(hidden)]
+pub mod rule_watch;
+#[doc(hidden)]
 pub mod scanner;
+#[doc(hidden)]
+pub mod schema;
+#[doc(hidden)]
+pub mod secrets;
+#[doc(hidden)]
+pub mod shutdown;
+#[doc(hidden)]
+pub mod sources;
+#[doc(hidden)]
+pub mod stats;
+#[doc(hidden)]
+pub mod store;
+#[doc(hidden)]
+pub mod stream;
+#[doc(hidden)]
+pub mod summary;
+#[doc(hidden)]
+pub mod throttle;
+#[doc(hidden)]
+pub mod timeline;
+#[doc(hidden)]
+pub mod timeouts;
+#[doc(hidden)]
+pub mod tui;
+#[doc(hidden)]
+pub mod upload;
+#[doc(hidden)]
+pub mod user_agent;
+#[doc(hidden)]
 pub mod utils;
+#[doc(hidden)]
+pub mod window;
+#[doc(hidden)]
+pub mod workdir;
+#[doc(hidden)]
+pub mod writer;
 
 // Re-export common types for easier access
 pub use config::ScanConfig;
+pub use engine::ScanEngine;
+pub use error::FattError;
 pub use rules::{Rule, RuleSet, Severity};
@@ -1,7 +1,32 @@
+use once_cell::sync::OnceCell;
 use std::path::Path;
+use std::sync::Mutex;
 use tracing::{debug, info, warn, Level};
+use tracing_appender::non_blocking::WorkerGuard;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
-use tracing_subscriber::{fmt, prelude::*, EnvFilter, Registry};
+use tracing_subscriber::layer::Layered;
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter, Layer, Registry};
+
+use crate::stats::StatsSnapshot;
+use crate::utils::format_bytes;
+
+type FileLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// The registry with the file layer, which the telemetry layer is stacked on
+pub type TelemetryBase = Layered<reload::Layer<Option<FileLayer>, Registry>, Registry>;
+
+/// Layer exporting spans, such as to OpenTelemetry
+pub type TelemetryLayer = Box<dyn Layer<TelemetryBase> + Send + Sync>;
+
+/// Swaps the file layer in and out after the subscriber is installed
+static FILE_LAYER: OnceCell<reload::Handle<Option<FileLayer>, Registry>> = OnceCell::new();
+
+/// Swaps the telemetry layer in once an exporter is configured
+static TELEMETRY_LAYER: OnceCell<reload::Handle<Option<TelemetryLayer>, TelemetryBase>> =
+    OnceCell::new();
+
+/// Keeps the file writer's background thread alive; dropping it flushes the file
+static FILE_GUARD: Mutex<Option<WorkerGuard>> = Mutex::new(None);
 
 /// Initialize logger with file and console output
 pub fn init_logger(debug_mode: bool, log_file: Option<&str>) -> anyhow::Result<()> {
@@ -21,38 +46,76 @@ pub fn init_logger(debug_mode: bool, log_file: Option<&str>) -> anyhow::Result<(
         .with_file(true)
         .with_line_number(true);
 
+    // File logging starts disabled and can be switched on later, e.g. once a
+    // scan's working directory is known
+    let (file_layer, handle) = reload::Layer::new(None::<FileLayer>);
+    let (telemetry_layer, telemetry_handle) = reload::Layer::new(None::<TelemetryLayer>);
+
     // Build our subscriber
-    let subscriber = Registry::default().with(filter_layer).with(fmt_layer);
+    let subscriber = Registry::default()
+        .with(file_layer)
+        .with(telemetry_layer)
+        .with(filter_layer)
+        .with(fmt_layer);
+
+    tracing::subscriber::set_global_default(subscriber)
+        .expect("Failed to set global default subscriber");
+    let _ = FILE_LAYER.set(handle);
+    let _ = TELEMETRY_LAYER.set(telemetry_handle);
 
     // Add file logging if specified
     if let Some(log_path) = log_file {
-        // Create directory if it doesn't exist
-        if let Some(parent) = Path::new(log_path).parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent)?;
-            }
-        }
+        set_log_file(Path::new(log_path))?;
+    }
 
-        // Create rolling file appender
-        let file_appender = RollingFileAppender::new(
-            Rotation::DAILY,
-            Path::new(log_path).parent().unwrap_or(Path::new(".")),
-            Path::new(log_path).file_name().unwrap_or_default(),
-        );
+    Ok(())
+}
 
-        let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
-        let file_layer = fmt::layer().with_ansi(false).with_writer(non_blocking);
+/// Also write logs to a file, replacing any previous log file.
+///
+/// Does nothing when the logger hasn't been initialized.
+pub fn set_log_file(log_path: &Path) -> anyhow::Result<()> {
+    let Some(handle) = FILE_LAYER.get() else {
+        return Ok(());
+    };
 
-        tracing::subscriber::set_global_default(subscriber.with(file_layer))
-            .expect("Failed to set global default subscriber");
-    } else {
-        tracing::subscriber::set_global_default(subscriber)
-            .expect("Failed to set global default subscriber");
-    }
+    // Create directory if it doesn't exist
+    let dir = log_path.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir)?;
+
+    let file_appender = RollingFileAppender::new(
+        Rotation::NEVER,
+        dir,
+        log_path.file_name().unwrap_or_default(),
+    );
+    let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
+    let layer = fmt::layer().with_ansi(false).with_writer(non_blocking);
+
+    handle.reload(Some(Box::new(layer) as FileLayer))?;
+    *FILE_GUARD.lock().unwrap() = Some(guard);
 
     Ok(())
 }
 
+/// Also hand spans to a telemetry layer, replacing any previous one.
+///
+/// Does nothing when the logger hasn't been initialized.
+#[cfg_attr(not(feature = "otel"), allow(dead_code))]
+pub fn set_telemetry_layer(layer: Option<TelemetryLayer>) -> anyhow::Result<()> {
+    if let Some(handle) = TELEMETRY_LAYER.get() {
+        handle.reload(layer)?;
+    }
+    Ok(())
+}
+
+/// Stop writing logs to a file, flushing what was written
+pub fn close_log_file() {
+    if let Some(handle) = FILE_LAYER.get() {
+        let _ = handle.reload(None);
+    }
+    FILE_GUARD.lock().unwrap().take();
+}
+
 /// Set the logging verbosity level
 pub fn set_verbosity(verbose: bool) {
     let level = if verbose { Level::DEBUG } else { Level::INFO };
@@ -96,6 +159,48 @@ pub fn log_scan_stats(
     info!("────────────────────────────────────────────────────────────────");
 }
 
+/// Log the network footprint of a scan
+pub fn log_network_stats(stats: &StatsSnapshot) {
+    info!(
+        "🌐 Network: {} requests, {} sent, {} received",
+        stats.requests,
+        format_bytes(stats.bytes_sent),
+        format_bytes(stats.bytes_received)
+    );
+
+    if !stats.status_counts.is_empty() {
+        let statuses: Vec<String> = stats
+            .status_counts
+            .iter()
+            .map(|(status, count)| format!("{}: {}", status, count))
+            .collect();
+        info!("🌐 Status codes: {}", statuses.join(", "));
+    }
+
+    if stats.tls_failures + stats.timeouts + stats.connect_failures > 0 {
+        info!(
+            "🌐 Failures: {} TLS handshake, {} timeouts, {} connection",
+            stats.tls_failures, stats.timeouts, stats.connect_failures
+        );
+    }
+    if !stats.timeout_phases.is_empty() {
+        let phases: Vec<String> = stats
+            .timeout_phases
+            .iter()
+            .map(|(phase, count)| format!("{}: {}", phase, count))
+            .collect();
+        info!("🌐 Timeouts by phase: {}", phases.join(", "));
+    }
+}
+
+/// Log the risk score of a completed scan
+pub fn log_risk_score(risk_score: f64, domains_at_risk: usize) {
+    info!(
+        "⚖️ Risk score: {:.1} across {} domains with findings",
+        risk_score, domains_at_risk
+    );
+}
+
 /// Log a successful finding
 pub fn log_success(domain: &str, rule_name: &str, matched_path: &str) {
     info!(
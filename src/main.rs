@@ -1,16 +1,71 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use tracing::info;
-use uuid::Uuid;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::process::ExitCode;
 
+mod anomaly;
+mod brute;
+mod canary;
+mod cert_inventory;
+#[cfg(feature = "distributed")]
+mod certs;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod clusters;
+mod compare;
 mod config;
+mod config_file;
+mod context;
 mod db;
+mod detail;
+mod diff;
+#[cfg(feature = "distributed")]
 mod distributed;
+mod doctor;
+mod domain_report;
+mod email;
+mod evasion;
+mod evidence;
+mod exit;
+mod expand;
+mod expected;
+mod incident;
 mod logger;
+mod manifest;
+mod migrate;
+mod notify;
+mod otel;
+#[cfg(feature = "postgres")]
+mod pg;
+mod pipeline;
+mod profile;
+mod redact;
 mod resolver;
+mod retention;
+mod risk;
+mod rule_lint;
+mod rule_watch;
 mod rules;
 mod scanner;
+mod schema;
+mod secrets;
+mod shutdown;
+mod sources;
+mod stats;
+mod store;
+mod stream;
+mod summary;
+mod throttle;
+mod timeline;
+mod timeouts;
+mod tui;
+mod upload;
+mod user_agent;
 mod utils;
+mod window;
+mod workdir;
+mod writer;
+
+use exit::{Classify, Failure};
 
 #[derive(Parser)]
 #[command(
@@ -23,15 +78,41 @@ mod utils;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Export traces and metrics over OTLP/HTTP to a collector such as Jaeger or
+    /// Tempo, e.g. http://localhost:4318
+    #[arg(long, global = true, value_name = "URL")]
+    otel_endpoint: Option<String>,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Scan domains for sensitive files and directories
     Scan {
-        /// Input file containing domains to scan (one per line)
-        #[arg(short, long, value_name = "FILE")]
-        input: String,
+        /// Config file (TOML) to take settings from, which flags given here override;
+        /// defaults to fatt.toml if present
+        #[arg(long, value_name = "FILE")]
+        config: Option<String>,
+
+        /// Profile from the config file's [profiles] section to apply
+        #[arg(long, value_name = "NAME")]
+        config_profile: Option<String>,
+
+        /// Override a config key (repeatable), under the flags given here
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        overrides: Vec<String>,
+
+        /// Input file containing domains to scan (one per line), optionally tagged as
+        /// FILE:TAG to record the tag with its findings (repeatable)
+        #[arg(short, long, value_name = "FILE[:TAG]")]
+        input: Vec<config::InputSource>,
+
+        /// Live source of domains to follow with --watch: file:PATH, stdin, ct:DOMAIN,
+        /// an http(s) URL or kafka://BROKERS/TOPIC (repeatable). Each poll scans only
+        /// the domains not seen before.
+        #[arg(long, value_name = "SOURCE")]
+        source: Vec<sources::SourceSpec>,
 
         /// Rules file in YAML format
         #[arg(short, long, value_name = "FILE", default_value = "rules.yaml")]
@@ -41,25 +122,278 @@ enum Commands {
         #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
         database: String,
 
-        /// Concurrency level (number of simultaneous requests)
-        #[arg(short, long, default_value = "100")]
-        concurrency: usize,
+        /// Working directory for the scan's results, evidence, logs, state and summary
+        #[arg(long, value_name = "DIR", conflicts_with = "database")]
+        workdir: Option<String>,
+
+        /// Result store backend (sqlite, or jsonl to write a directory of JSONL files)
+        #[arg(long, default_value = "sqlite")]
+        store: store::StoreKind,
+
+        /// Shard SQLite findings across this many files by domain hash
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Put SQLite databases in WAL mode with relaxed syncing (false keeps a
+        /// rollback journal, e.g. on NFS)
+        #[arg(long, value_name = "BOOL", default_value_t = true, action = clap::ArgAction::Set)]
+        db_tuning: bool,
+
+        /// After the scan, purge findings last scanned longer ago than this (e.g. 30d)
+        #[arg(long, value_name = "AGE")]
+        purge_older_than: Option<String>,
+
+        /// Only purge findings where nothing was detected
+        #[arg(long)]
+        purge_not_detected: bool,
+
+        /// Tuning preset for concurrency, jitter, retries, timeouts and throttling
+        /// (stealth, normal, aggressive); flags below override its values
+        #[arg(long, value_name = "PROFILE")]
+        profile: Option<profile::ScanProfile>,
+
+        /// Label the scan session and its findings with a project (e.g. a client or
+        /// engagement), to filter results by later
+        #[arg(long, value_name = "NAME")]
+        project: Option<String>,
+
+        /// Concurrency level (number of simultaneous requests) [default: 100, or the profile's]
+        #[arg(short, long)]
+        concurrency: Option<usize>,
+
+        /// Capacity of each queue between scan pipeline stages
+        #[arg(long, default_value = "1000")]
+        queue_capacity: usize,
+
+        /// Threads matching signatures against response bodies (0 = one per core)
+        #[arg(long, default_value = "0")]
+        match_threads: usize,
 
         /// Batch size for domain processing
         #[arg(short, long, default_value = "1000")]
         batch_size: usize,
 
-        /// Connect timeout in seconds
-        #[arg(long, default_value = "10")]
-        timeout: u64,
+        /// Total request timeout in seconds, body included [default: 10, or the profile's]
+        #[arg(long)]
+        timeout: Option<u64>,
+
+        /// DNS resolution timeout in seconds
+        #[arg(long, default_value = "5")]
+        dns_timeout: u64,
+
+        /// Brute force common subdomains of bare apex domains in the input (e.g.
+        /// example.com), scanning the ones that resolve in the same run
+        #[arg(long)]
+        dns_brute: bool,
+
+        /// Most DNS queries per second of --dns-brute
+        #[arg(long, default_value_t = brute::DEFAULT_RATE, value_name = "N")]
+        dns_brute_rate: u32,
+
+        /// Shell command expanding each input entry into targets: it gets the entry as
+        /// $1 and prints the targets to scan one per line (e.g. a CMDB lookup)
+        #[arg(long, value_name = "COMMAND")]
+        expand_command: Option<String>,
+
+        /// Most input entries expanded at once by --expand-command
+        #[arg(long, default_value_t = expand::DEFAULT_CONCURRENCY, value_name = "N")]
+        expand_concurrency: usize,
+
+        /// TCP connect timeout in seconds [default: 5, or the profile's]
+        #[arg(long)]
+        connect_timeout: Option<u64>,
+
+        /// TLS handshake timeout in seconds, once connected
+        #[arg(long, default_value = "5")]
+        tls_timeout: u64,
+
+        /// Time to wait for the response headers in seconds, once connected [default: 10,
+        /// or the profile's]
+        #[arg(long)]
+        first_byte_timeout: Option<u64>,
 
         /// Number of worker threads
         #[arg(short, long, default_value = "0")]
         threads: usize,
 
+        /// Most bytes of a response body downloaded and matched
+        #[arg(long, default_value_t = scanner::DEFAULT_MAX_BODY_BYTES)]
+        max_body_bytes: usize,
+
+        /// Error statuses that show a path exists but is protected (comma-separated)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_name = "STATUS",
+            default_values_t = scanner::DEFAULT_INTERESTING_STATUS.to_vec()
+        )]
+        interesting_status: Vec<u16>,
+
+        /// Report successes only: no error status shows a path exists
+        #[arg(long, conflicts_with = "interesting_status")]
+        no_interesting_status: bool,
+
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
+
+        /// Expected findings (accepted risk) file; fail if anything else is found
+        #[arg(long, value_name = "FILE")]
+        expected: Option<String>,
+
+        /// Canary domains to scan first; the scan is aborted if they don't come back as
+        /// the file says
+        #[arg(long, value_name = "FILE")]
+        canaries: Option<String>,
+
+        /// Risk score weights per severity (e.g. critical=10,high=5,medium=2,low=1,info=0)
+        #[arg(long, value_name = "WEIGHTS", default_value = "")]
+        risk_weights: risk::RiskWeights,
+
+        /// Re-run the scan every N seconds until interrupted
+        #[arg(long, value_name = "SECONDS")]
+        watch: Option<u64>,
+
+        /// Only send traffic during this daily window, pausing outside it
+        /// (e.g. "22:00-06:00" or "22:00-06:00 Europe/Berlin"; UTC by default)
+        #[arg(long, value_name = "HH:MM-HH:MM [ZONE]")]
+        scan_window: Option<String>,
+
+        /// Report rules whose match count changes by this factor versus recent scans
+        #[arg(long, default_value = "10")]
+        anomaly_factor: f64,
+
+        /// Ignore rules with fewer matches than this when detecting anomalies
+        #[arg(long, default_value = "5")]
+        anomaly_min_count: usize,
+
+        /// Number of retries for failed HTTP requests [default: 2, or the profile's]
+        #[arg(long)]
+        retries: Option<u32>,
+
+        /// Base backoff between retries in milliseconds (doubles on each retry) [default:
+        /// 250, or the profile's]
+        #[arg(long)]
+        retry_backoff_ms: Option<u64>,
+
+        /// Wait a random time up to this many milliseconds before each request [default:
+        /// 0, or the profile's]
+        #[arg(long, value_name = "MS")]
+        jitter_ms: Option<u64>,
+
+        /// URL schemes to check each domain over (comma-separated: http,https)
+        #[arg(long, value_delimiter = ',', default_value = "http")]
+        schemes: Vec<String>,
+
+        /// Ports to check each domain on (comma-separated; defaults to the scheme's port)
+        #[arg(long, value_delimiter = ',')]
+        ports: Vec<u16>,
+
+        /// Proxy URL for all scan traffic (http://, https:// or socks5://)
+        #[arg(long, value_name = "URL")]
+        proxy: Option<String>,
+
+        /// Proxy credentials as user:password
+        #[arg(long, value_name = "USER:PASS")]
+        proxy_auth: Option<String>,
+
+        /// Skip a domain's remaining checks after this many consecutive failures (0
+        /// disables) [default: 10, or the profile's]
+        #[arg(long)]
+        circuit_breaker: Option<usize>,
+
+        /// Rule checks in flight against one domain at a time [default: 4, or the profile's]
+        #[arg(long)]
+        rule_concurrency: Option<usize>,
+
+        /// Count response header bytes in the network statistics
+        #[arg(long)]
+        capture_headers: bool,
+
+        /// File of User-Agent strings to rotate through, one per line
+        #[arg(long, value_name = "FILE")]
+        user_agent_file: Option<String>,
+
+        /// Keep one User-Agent per domain instead of rotating on every check
+        #[arg(long)]
+        sticky_user_agent: bool,
+
+        /// Randomize TLS fingerprint and header order (requires --authorized-testing)
+        #[arg(long)]
+        evasion: bool,
+
+        /// Confirm you have permission to test every target in the input
+        #[arg(long)]
+        authorized_testing: bool,
+
+        /// Serve findings and progress as Server-Sent Events at /events on this address
+        /// (e.g. :8099 for localhost only)
+        #[arg(long, value_name = "ADDR")]
+        stream_listen: Option<String>,
+
+        /// Token --stream-listen subscribers must send as a bearer token, required
+        /// unless it listens on localhost only (or set FATT_STREAM_TOKEN)
+        #[arg(long, value_name = "TOKEN")]
+        stream_token: Option<String>,
+
+        /// POST every finding to this webhook as soon as it's detected (repeatable)
+        #[arg(long, value_name = "URL")]
+        notify_finding: Vec<String>,
+
+        /// Upload an export of the results to S3 after every scan, in the format of the
+        /// key's extension (s3://bucket/key.csv; repeatable)
+        #[arg(long, value_name = "S3_URL")]
+        upload: Vec<upload::S3Url>,
+
+        /// S3-compatible endpoint for --upload, such as a MinIO server (default: AWS S3)
+        #[arg(long, value_name = "URL")]
+        s3_endpoint: Option<String>,
+
+        /// Region --upload requests are signed for (default: AWS_REGION, or us-east-1)
+        #[arg(long, value_name = "REGION")]
+        s3_region: Option<String>,
+
+        /// Pick up changes to the rules files for domains not scanned yet
+        #[arg(long)]
+        hot_reload_rules: bool,
+
+        /// Most concurrent requests to domains under one TLD (0 is unlimited)
+        #[arg(long, default_value = "0", value_name = "N")]
+        throttle_tld: usize,
+
+        /// Most concurrent requests to one autonomous system (0 is unlimited; needs --asn-file)
+        #[arg(long, default_value = "0", value_name = "N")]
+        throttle_asn: usize,
+
+        /// Most concurrent requests to one IPv4 /24 or IPv6 /48 (0 is unlimited) [default:
+        /// 0, or the profile's]
+        #[arg(long, value_name = "N")]
+        throttle_subnet: Option<usize>,
+
+        /// IP-to-ASN ranges as an iptoasn.com TSV file, for --throttle-asn
+        #[arg(long, value_name = "FILE")]
+        asn_file: Option<String>,
+
+        /// Also mask secrets matching this regex in evidence, logs and exports (repeatable)
+        #[arg(long = "redact", value_name = "REGEX")]
+        redact_patterns: Vec<String>,
+    },
+
+    /// Run a scan defined in a manifest file
+    Run {
+        /// Scan manifest (YAML)
+        #[arg(value_name = "MANIFEST")]
+        manifest: String,
+
+        /// Enable verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Inspect scan configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
     },
 
     /// Manage scanning rules
@@ -74,6 +408,12 @@ enum Commands {
         action: ResultsCommands,
     },
 
+    /// Maintain the results database
+    Db {
+        #[command(subcommand)]
+        action: DbCommands,
+    },
+
     /// Manage DNS cache
     Dns {
         #[command(subcommand)]
@@ -81,10 +421,90 @@ enum Commands {
     },
 
     /// Control distributed worker nodes
+    #[cfg(feature = "distributed")]
     Worker {
         #[command(subcommand)]
         action: WorkerCommands,
     },
+
+    /// Follow a scan or master running elsewhere: progress, workers and findings
+    Tui {
+        /// Event stream to follow: the `--stream-listen` address of a scan or master
+        #[arg(short, long, value_name = "HOST:PORT")]
+        connect: String,
+
+        /// Token the event stream requires [default: FATT_STREAM_TOKEN]
+        #[arg(long, value_name = "TOKEN")]
+        token: Option<String>,
+    },
+
+    /// Run the master node and manage the certificates workers register with
+    #[cfg(feature = "distributed")]
+    Master {
+        #[command(subcommand)]
+        action: MasterCommands,
+    },
+
+    /// Check that DNS, outbound HTTP(S), file limits, the cache and database directories
+    /// and the clock are ready for a scan
+    Doctor {
+        /// Concurrency the scan will run with
+        #[arg(short, long, default_value = "100")]
+        concurrency: usize,
+
+        /// Output database file the scan will write to
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Host name to resolve
+        #[arg(long, default_value = "example.com")]
+        dns_host: String,
+
+        /// URL to fetch to check outbound connectivity (repeatable)
+        #[arg(
+            long = "url",
+            value_name = "URL",
+            default_values = ["http://example.com", "https://example.com"]
+        )]
+        urls: Vec<String>,
+
+        /// Proxy URL the scan will use (http://, https:// or socks5://)
+        #[arg(long, value_name = "URL")]
+        proxy: Option<String>,
+
+        /// Timeout of each network check in seconds
+        #[arg(long, default_value = "10")]
+        timeout: u64,
+    },
+
+    /// Print a JSON Schema for rules files, scan manifests or config files, for editors
+    /// and CI validators
+    Schema {
+        /// File format to describe (rules, manifest, config)
+        kind: schema::SchemaKind,
+
+        /// Write the schema to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Validate the merged configuration and print each effective value with its source
+    Check {
+        /// Config file (TOML); defaults to fatt.toml if present
+        #[arg(short, long, value_name = "FILE")]
+        config: Option<String>,
+
+        /// Profile from the config file's [profiles] section to apply
+        #[arg(short, long)]
+        profile: Option<String>,
+
+        /// Override a key (repeatable)
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        overrides: Vec<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -109,6 +529,17 @@ enum RulesCommands {
         #[arg(short, long, value_name = "FILE", default_value = "rules.yaml")]
         file: String,
     },
+
+    /// Check a rules file, reporting every invalid rule with its line
+    Validate {
+        /// Rules YAML file
+        #[arg(short, long, value_name = "FILE", default_value = "rules.yaml")]
+        file: String,
+
+        /// Report format (text, json)
+        #[arg(long, default_value = "text")]
+        format: rule_lint::IssueFormat,
+    },
 }
 
 #[derive(Subcommand)]
@@ -116,35 +547,611 @@ enum ResultsCommands {
     /// Export results to a file
     Export {
         /// Output file for results
-        #[arg(short, long, value_name = "FILE")]
-        output: String,
+        #[arg(short, long, value_name = "FILE", required_unless_present = "upload")]
+        output: Option<String>,
 
-        /// Database file containing results
+        /// Upload the export to S3 instead of writing a local file (s3://bucket/key;
+        /// credentials from AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY)
+        #[arg(long, value_name = "S3_URL", conflicts_with = "output")]
+        upload: Option<upload::S3Url>,
+
+        /// S3-compatible endpoint for --upload, such as a MinIO server (default: AWS S3)
+        #[arg(long, value_name = "URL", requires = "upload")]
+        s3_endpoint: Option<String>,
+
+        /// Region --upload requests are signed for (default: AWS_REGION, or us-east-1)
+        #[arg(long, value_name = "REGION", requires = "upload")]
+        s3_region: Option<String>,
+
+        /// Database file (or JSONL directory) containing results; repeat it, or give a
+        /// glob, to export several as one
         #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
-        database: String,
+        database: Vec<String>,
 
-        /// Export format (csv, json)
+        /// Result store backend the results are in (sqlite, jsonl)
+        #[arg(long, default_value = "sqlite")]
+        store: store::StoreKind,
+
+        /// Number of shards the SQLite findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Export format (csv, json, jsonl)
         #[arg(short, long, default_value = "csv")]
         format: String,
+
+        /// Write one file per group instead of a single file (tag: one per input tag)
+        #[arg(long, value_name = "GROUP")]
+        split_output_by: Option<db::SplitBy>,
+
+        /// Write one row per rule, domain or severity, with counts and first/last seen,
+        /// instead of the findings (severity needs the rules)
+        #[arg(long, value_name = "GROUP", conflicts_with = "split_output_by")]
+        group_by: Option<summary::GroupBy>,
+
+        /// Only findings last checked by a scan run with --project NAME
+        #[arg(long, value_name = "NAME")]
+        project: Option<String>,
+
+        /// Also mask secrets matching this regex in the export (repeatable)
+        #[arg(long = "redact", value_name = "REGEX")]
+        redact_patterns: Vec<String>,
+
+        /// Rules file giving the findings' remediation (default: rules.yaml, if present)
+        #[arg(short, long, value_name = "FILE")]
+        rules: Option<String>,
+
+        /// Only findings last scanned at or after this time (RFC 3339, YYYY-MM-DD, or an age like 12h)
+        #[arg(long, value_name = "TIME")]
+        since: Option<String>,
+
+        /// Only findings last scanned at or before this time
+        #[arg(long, value_name = "TIME")]
+        until: Option<String>,
+
+        /// Only checks that matched (the default)
+        #[arg(long, conflicts_with = "include_negative")]
+        detected_only: bool,
+
+        /// Also include checks that didn't match
+        #[arg(long)]
+        include_negative: bool,
+
+        /// Also include findings marked with `results fp`
+        #[arg(long)]
+        include_false_positives: bool,
     },
 
     /// List scan results
     List {
-        /// Database file containing results
+        /// Database file containing results; repeat it, or give a glob, to list
+        /// several as one
         #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
-        database: String,
+        database: Vec<String>,
+
+        /// Number of shards the findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
 
         /// Filter by domain pattern
-        #[arg(short, long)]
+        #[arg(long)]
         domain: Option<String>,
 
         /// Filter by rule name pattern
         #[arg(short, long)]
         rule: Option<String>,
 
+        /// Only findings last scanned at or after this time (RFC 3339, YYYY-MM-DD, or an age like 12h)
+        #[arg(long, value_name = "TIME")]
+        since: Option<String>,
+
+        /// Only findings last scanned at or before this time
+        #[arg(long, value_name = "TIME")]
+        until: Option<String>,
+
+        /// Only checks that matched (the default)
+        #[arg(long, conflicts_with = "include_negative")]
+        detected_only: bool,
+
+        /// Also include checks that didn't match
+        #[arg(long)]
+        include_negative: bool,
+
+        /// Only findings last checked by a scan run with --project NAME
+        #[arg(long, value_name = "NAME")]
+        project: Option<String>,
+
         /// Limit number of results
         #[arg(short, long, default_value = "100")]
         limit: usize,
+
+        /// Number of results to skip, for paging through large result sets
+        #[arg(long, default_value = "0")]
+        offset: usize,
+
+        /// Sort results by domain, rule, severity or scanned_at
+        #[arg(long, default_value = "scanned_at")]
+        sort: db::SortBy,
+
+        /// Sort direction (asc, desc; default: asc for domain and rule, desc otherwise)
+        #[arg(long)]
+        order: Option<db::SortOrder>,
+
+        /// Rules file giving the rules' severities (default: rules.yaml, if present)
+        #[arg(long, value_name = "FILE")]
+        rules: Option<String>,
+
+        /// Output format (table, json, csv, tsv)
+        #[arg(short, long, default_value = "table")]
+        output: db::ListFormat,
+    },
+
+    /// Show everything about one finding: its rule, evidence, timestamps and the other
+    /// findings for its domain
+    Show {
+        /// ID of the finding, as listed by `results list`
+        id: i64,
+
+        /// Database file containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Number of shards the findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Rules file giving the rule's description, severity and remediation (default: rules.yaml, if present)
+        #[arg(short, long, value_name = "FILE")]
+        rules: Option<String>,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Add an analyst's note to a finding, shown by `results show` and included in
+    /// exports
+    #[command(alias = "note")]
+    Annotate {
+        /// ID of the finding, as listed by `results list`
+        id: i64,
+
+        /// Note to add, such as triage context or a ticket reference
+        #[arg(short, long, alias = "text")]
+        note: String,
+
+        /// Who wrote the note [default: $FATT_AUTHOR, or else $USER]
+        #[arg(long)]
+        author: Option<String>,
+
+        /// Database file containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Number of shards the findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+    },
+
+    /// Mark a finding as a false positive, leaving its domain and rule out of exports
+    Fp {
+        /// ID of the finding, as listed by `results list`
+        id: i64,
+
+        /// Also skip the domain and rule in future scans
+        #[arg(long)]
+        skip_scans: bool,
+
+        /// Remove the mark instead
+        #[arg(long, conflicts_with = "skip_scans")]
+        clear: bool,
+
+        /// Database file containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Number of shards the findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+    },
+
+    /// Report card for one domain: addresses, technologies, findings and scan history
+    Domain {
+        /// Domain to report on
+        domain: String,
+
+        /// Database file (or JSONL directory, or postgres:// URL) containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Result store backend the results are in (sqlite, jsonl)
+        #[arg(long, default_value = "sqlite")]
+        store: store::StoreKind,
+
+        /// Number of shards the SQLite findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Rules file giving the rules' severities and remediation (default: rules.yaml, if present)
+        #[arg(short, long, value_name = "FILE")]
+        rules: Option<String>,
+
+        /// Most recent scans of the domain shown, and checked for flapping rules
+        #[arg(long, default_value = "10")]
+        history: usize,
+
+        /// Risk score weights per severity (e.g. critical=10,high=5,medium=2,low=1,info=0)
+        #[arg(long, value_name = "WEIGHTS", default_value = "")]
+        risk_weights: risk::RiskWeights,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Merge sharded findings back into the primary database, or separate databases
+    /// into one
+    Merge {
+        /// Primary database file of the shards
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Number of shards the findings are split across
+        #[arg(long, required_unless_present = "into", conflicts_with = "into")]
+        shards: Option<usize>,
+
+        /// Database to merge the given databases into, created if missing
+        #[arg(long, value_name = "FILE", requires = "sources")]
+        into: Option<String>,
+
+        /// Databases merged into --into; the newest result for each domain and rule wins
+        #[arg(value_name = "DATABASE", requires = "into", conflicts_with = "shards")]
+        sources: Vec<String>,
+    },
+
+    /// Compare the findings of two scans, or of two databases
+    Diff {
+        /// Scan ID to compare from (default: the current findings of --from-db)
+        #[arg(long, value_name = "SCAN_ID")]
+        from: Option<String>,
+
+        /// Scan ID to compare to (default: the current findings of --to-db)
+        #[arg(long, value_name = "SCAN_ID")]
+        to: Option<String>,
+
+        /// Database file (or JSONL directory) of the older side (default: --database)
+        #[arg(long, value_name = "FILE")]
+        from_db: Option<String>,
+
+        /// Database file (or JSONL directory) of the newer side (default: --database)
+        #[arg(long, value_name = "FILE")]
+        to_db: Option<String>,
+
+        /// Database file (or JSONL directory) containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Result store backend the results are in (sqlite, jsonl)
+        #[arg(long, default_value = "sqlite")]
+        store: store::StoreKind,
+
+        /// Number of shards the SQLite findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Rules file giving the rules' severities for the change in risk (default:
+        /// rules.yaml, if present)
+        #[arg(short, long, value_name = "FILE")]
+        rules: Option<String>,
+
+        /// Risk score weights per severity (e.g. critical=10,high=5,medium=2,low=1,info=0)
+        #[arg(long, value_name = "WEIGHTS", default_value = "")]
+        risk_weights: risk::RiskWeights,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Match the findings against another scanner's output by domain and path, listing
+    /// what both found and what only one of them did
+    Compare {
+        /// Nuclei output, from -jsonl or -json-export
+        #[arg(
+            long,
+            value_name = "FILE",
+            required_unless_present = "json",
+            conflicts_with = "json"
+        )]
+        nuclei_json: Option<String>,
+
+        /// Another scanner's JSON output, as an array or one object per line, read with
+        /// --domain-field, --path-field and --name-field
+        #[arg(long, value_name = "FILE")]
+        json: Option<String>,
+
+        /// Field of --json holding the host (dotted for nested fields); unused when the
+        /// path field is a full URL
+        #[arg(long, value_name = "FIELD", default_value = "domain")]
+        domain_field: String,
+
+        /// Field of --json holding the path or full URL
+        #[arg(long, value_name = "FIELD", default_value = "path")]
+        path_field: String,
+
+        /// Field of --json naming the finding, such as a check or template ID
+        #[arg(long, value_name = "FIELD")]
+        name_field: Option<String>,
+
+        /// Database file (or JSONL directory, or postgres:// URL) containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Result store backend the results are in (sqlite, jsonl)
+        #[arg(long, default_value = "sqlite")]
+        store: store::StoreKind,
+
+        /// Number of shards the SQLite findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Summarise the results: findings per severity, rule and TLD, hit rate and scan timing
+    Stats {
+        /// Database file (or JSONL directory, or postgres:// URL) containing results;
+        /// repeat it, or give a glob, to summarise several as one
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: Vec<String>,
+
+        /// Result store backend the results are in (sqlite, jsonl)
+        #[arg(long, default_value = "sqlite")]
+        store: store::StoreKind,
+
+        /// Number of shards the SQLite findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Rules file giving the rules' severities (default: rules.yaml, if present)
+        #[arg(short, long, value_name = "FILE")]
+        rules: Option<String>,
+
+        /// Rules, TLDs and scans listed in text output
+        #[arg(long, default_value = "10")]
+        top: usize,
+
+        /// Only findings and scans of a project, as labelled with scan --project
+        #[arg(long, value_name = "NAME")]
+        project: Option<String>,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Remove stale findings, e.g. the not-detected rows long-running installs pile up
+    Purge {
+        /// Database file (or JSONL directory, or postgres:// URL) containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Result store backend the results are in (sqlite, jsonl)
+        #[arg(long, default_value = "sqlite")]
+        store: store::StoreKind,
+
+        /// Number of shards the SQLite findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Remove findings last scanned longer ago than this (e.g. 30d, 12h, 2w)
+        #[arg(long, value_name = "AGE")]
+        older_than: String,
+
+        /// Only remove findings of this rule
+        #[arg(long, value_name = "RULE")]
+        rule: Option<String>,
+
+        /// Only remove findings where nothing was detected
+        #[arg(long)]
+        not_detected: bool,
+    },
+
+    /// Remove specific findings, such as false positives or out-of-scope hosts
+    Delete {
+        /// Database file (or JSONL directory, or postgres:// URL) containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Result store backend the results are in (sqlite, jsonl)
+        #[arg(long, default_value = "sqlite")]
+        store: store::StoreKind,
+
+        /// Number of shards the SQLite findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Remove the findings of this domain
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// Remove the findings of this rule
+        #[arg(short, long, value_name = "RULE")]
+        rule: Option<String>,
+
+        /// Remove the finding with this ID, as listed by `results list`
+        #[arg(long)]
+        id: Option<i64>,
+    },
+
+    /// List scan sessions with their risk scores
+    Scans {
+        /// Database file containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Limit number of scans
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+
+        /// Only scans run with --project NAME
+        #[arg(long, value_name = "NAME")]
+        project: Option<String>,
+    },
+
+    /// Count new and fixed findings per hour, day, week or month and severity, replaying
+    /// the findings each scan kept, for charting in Grafana or a spreadsheet
+    Timeline {
+        /// Database file (or JSONL directory, or postgres:// URL) containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Result store backend the results are in (sqlite, jsonl)
+        #[arg(long, default_value = "sqlite")]
+        store: store::StoreKind,
+
+        /// Number of shards the SQLite findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Period findings are counted over (hour, day, week, month)
+        #[arg(long, default_value = "day")]
+        bucket: timeline::Bucket,
+
+        /// Rules file giving the rules' severities (default: rules.yaml, if present)
+        #[arg(short, long, value_name = "FILE")]
+        rules: Option<String>,
+
+        /// Only scans run with --project NAME
+        #[arg(long, value_name = "NAME")]
+        project: Option<String>,
+
+        /// Output format (text, json, csv)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// List the TLS certificates hosts presented while being scanned over https,
+    /// soonest to expire first
+    Certs {
+        /// Database file (or JSONL directory, or postgres:// URL) containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Result store backend the results are in (sqlite, jsonl)
+        #[arg(long, default_value = "sqlite")]
+        store: store::StoreKind,
+
+        /// Number of shards the SQLite findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Only certificates expiring, or expired, within this long (e.g. 30d, 2w)
+        #[arg(long, value_name = "AGE")]
+        expiring_within: Option<String>,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Group findings whose responses had byte-identical bodies, such as parked pages
+    Clusters {
+        /// Database file (or JSONL directory, or postgres:// URL) containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Result store backend the results are in (sqlite, jsonl)
+        #[arg(long, default_value = "sqlite")]
+        store: store::StoreKind,
+
+        /// Number of shards the SQLite findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Only findings of this rule
+        #[arg(long)]
+        rule: Option<String>,
+
+        /// Fewest domains a group needs to be listed
+        #[arg(long, default_value_t = clusters::DEFAULT_MIN_SIZE)]
+        min_size: usize,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+
+        /// Mark every finding in this cluster as a false positive instead (SQLite only)
+        #[arg(long, value_name = "CLUSTER")]
+        dismiss: Option<String>,
+
+        /// With --dismiss, also skip the cluster's domains and rule in future scans
+        #[arg(long, requires = "dismiss")]
+        skip_scans: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbCommands {
+    /// Remove findings and scan sessions older than a retention period
+    Prune {
+        /// Database file containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Number of shards the findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+
+        /// Remove rows older than this many days
+        #[arg(long, value_name = "DAYS")]
+        older_than: u64,
+
+        /// Archive the removed rows to this gzip-compressed JSONL file (or s3:// URL) first
+        #[arg(long, value_name = "PATH")]
+        archive: Option<String>,
+    },
+
+    /// Check a database's integrity and shrink it, giving back the space removed rows
+    /// left behind
+    #[command(alias = "compact")]
+    Vacuum {
+        /// Database file containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Number of shards the findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+    },
+
+    /// Upgrade a database's schema to the one this version of fatt writes
+    Migrate {
+        /// Database file (or postgres:// URL) containing results
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Number of shards the findings are split across
+        #[arg(long, default_value = "1")]
+        shards: usize,
+    },
+
+    /// Import results scanned elsewhere, such as by workers that can only leave files
+    /// behind: scan files written with `--store jsonl`, or directories of them
+    Import {
+        /// Scan files or directories of them
+        #[arg(required = true, value_name = "FILE")]
+        files: Vec<String>,
+
+        /// Format of the files (jsonl)
+        #[arg(short, long, default_value = "jsonl")]
+        format: String,
+
+        /// Database file to import into
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
     },
 }
 
@@ -157,7 +1164,9 @@ enum DnsCommands {
     Status,
 }
 
+#[cfg(feature = "distributed")]
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum WorkerCommands {
     /// Start a worker node
     Start {
@@ -172,6 +1181,46 @@ enum WorkerCommands {
         /// Listen port for worker
         #[arg(short, long, default_value = "8080")]
         port: u16,
+
+        /// Worker certificate issued by `fatt master issue-cert`, for a master with a CA
+        #[arg(long, value_name = "FILE", requires_all = ["key", "ca_cert"])]
+        cert: Option<String>,
+
+        /// Worker private key
+        #[arg(long, value_name = "FILE", requires = "cert")]
+        key: Option<String>,
+
+        /// CA certificate the master's certificate must chain to; without one the
+        /// worker connects over plain TCP
+        #[arg(long, value_name = "FILE")]
+        ca_cert: Option<String>,
+
+        /// Name to verify the master's certificate against (default: the master's host)
+        #[arg(long, value_name = "NAME", requires = "ca_cert")]
+        server_name: Option<String>,
+
+        /// Attempts to reconnect after losing the master before stopping (0 stops at once)
+        #[arg(long, default_value_t = distributed::DEFAULT_RECONNECT_ATTEMPTS)]
+        reconnect_attempts: u32,
+
+        /// Delay before the first attempt to reconnect in milliseconds, doubled after
+        /// every failed one up to a minute
+        #[arg(long, value_name = "MS", default_value_t = distributed::DEFAULT_RECONNECT_BACKOFF_MS)]
+        reconnect_backoff_ms: u64,
+
+        /// Config file (TOML) with the settings to check domains with, as `fatt scan`
+        /// takes them (concurrency, timeouts, retries, schemes, ports, proxy...);
+        /// defaults to fatt.toml if present
+        #[arg(long, value_name = "FILE")]
+        config: Option<String>,
+
+        /// Profile from the config file's [profiles] section to apply
+        #[arg(long, value_name = "NAME")]
+        config_profile: Option<String>,
+
+        /// Override a config key (repeatable)
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        overrides: Vec<String>,
     },
 
     /// Stop a worker node
@@ -185,67 +1234,662 @@ enum WorkerCommands {
     Status,
 }
 
-fn main() -> Result<()> {
-    // Parse command line arguments
-    let args = Cli::parse();
+#[cfg(feature = "distributed")]
+#[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
+enum MasterCommands {
+    /// Start the master node
+    Start {
+        /// Address to listen for workers on
+        #[arg(short, long, value_name = "HOST:PORT", default_value = "0.0.0.0:8080")]
+        listen: String,
+
+        /// CA directory; with one, workers must register with a certificate it issued
+        #[arg(long, value_name = "DIR")]
+        ca_dir: Option<String>,
+
+        /// Names workers reach the master by, for its certificate
+        #[arg(long, value_name = "NAME", default_value = "localhost")]
+        server_name: Vec<String>,
+
+        /// Certificate to present to workers instead of one issued by the CA; without
+        /// --ca-dir, workers connect over TLS but register without certificates
+        #[arg(long, value_name = "FILE", requires = "tls_key")]
+        tls_cert: Option<String>,
+
+        /// Private key of --tls-cert
+        #[arg(long, value_name = "FILE", requires = "tls_cert")]
+        tls_key: Option<String>,
+
+        /// Stream the registered workers on this address (e.g. :9000 for localhost
+        /// only), for `fatt tui`
+        #[arg(long, value_name = "ADDR")]
+        stream_listen: Option<String>,
+
+        /// Token --stream-listen subscribers must send as a bearer token, required
+        /// unless it listens on localhost only [default: FATT_STREAM_TOKEN]
+        #[arg(long, value_name = "TOKEN")]
+        stream_token: Option<String>,
+
+        /// Rules file in YAML format
+        #[arg(short, long, value_name = "FILE", default_value = "rules.yaml")]
+        rules: String,
+
+        /// Input file containing the domains to scan, one per line, optionally tagged
+        /// as FILE:TAG to record the tag with its findings
+        #[arg(short, long, value_name = "FILE[:TAG]", default_value = "domains.txt")]
+        input: config::InputSource,
+
+        /// Results database the workers' findings are written to
+        #[arg(short, long, value_name = "FILE", default_value = "results.sqlite")]
+        database: String,
+
+        /// Label the scan session and its findings with a project
+        #[arg(long, value_name = "NAME")]
+        project: Option<String>,
+
+        /// Domains handed to a worker at once
+        #[arg(long, default_value_t = distributed::DEFAULT_BATCH_SIZE)]
+        batch_size: usize,
+
+        /// Seconds a worker has to report a batch before it's handed to another
+        #[arg(long, value_name = "SECONDS", default_value_t = distributed::DEFAULT_BATCH_TIMEOUT.as_secs())]
+        batch_timeout: u64,
+
+        /// Only register workers, without running a scan
+        #[arg(long, conflicts_with_all = ["rules", "input", "database", "project", "batch_size", "batch_timeout"])]
+        no_scan: bool,
+    },
+
+    /// Create the CA that issues worker certificates
+    InitCa {
+        /// Directory to keep the CA certificate, key and certificate registry in
+        #[arg(long, value_name = "DIR", default_value = "fatt-ca")]
+        ca_dir: String,
+    },
+
+    /// Issue a client certificate for one worker
+    IssueCert {
+        /// Worker the certificate is for; it must register with this ID
+        #[arg(long)]
+        worker_id: String,
+
+        /// CA directory
+        #[arg(long, value_name = "DIR", default_value = "fatt-ca")]
+        ca_dir: String,
+
+        /// Directory to write the certificate and key to
+        #[arg(short, long, value_name = "DIR", default_value = ".")]
+        out: String,
+
+        /// Days the certificate is valid for
+        #[arg(long, default_value_t = certs::DEFAULT_VALIDITY_DAYS)]
+        days: u32,
+    },
+
+    /// Revoke the certificates of one worker
+    RevokeCert {
+        /// Worker whose certificates to revoke
+        #[arg(long)]
+        worker_id: String,
+
+        /// CA directory
+        #[arg(long, value_name = "DIR", default_value = "fatt-ca")]
+        ca_dir: String,
+    },
+}
+
+fn main() -> ExitCode {
+    // Parse command line arguments, keeping which ones were given
+    let matches = Cli::command().get_matches();
+    let args = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
+    // Exit with a code that tells scripts what kind of failure ended the command
+    match run(args, &matches) {
+        Ok(()) => ExitCode::from(exit::SUCCESS),
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(exit::exit_code(&e))
+        }
+    }
+}
+
+/// The rules file given, or rules.yaml if there is one
+fn default_rules_path(rules: Option<String>) -> Option<String> {
+    rules.or_else(|| {
+        std::path::Path::new("rules.yaml")
+            .exists()
+            .then(|| "rules.yaml".to_string())
+    })
+}
+
+fn run(args: Cli, matches: &ArgMatches) -> Result<()> {
     // Initialize logger
     logger::init_logger(false, None)?;
 
+    // The exporters start and stop outside the runtime, as they block
+    let telemetry = args
+        .otel_endpoint
+        .as_deref()
+        .map(otel::Telemetry::start)
+        .transpose()
+        .classify(Failure::Config)?;
+
     // Run command based on subcommand
     let rt = tokio::runtime::Runtime::new()?;
-    rt.block_on(async {
+    let result = rt.block_on(async {
         match args.command {
             Commands::Scan {
-                input,
-                rules,
-                database,
-                concurrency,
-                batch_size: _,
-                timeout,
-                threads: _,
+                source,
+                config,
+                config_profile,
+                overrides,
+                no_interesting_status,
                 verbose,
+                watch,
+                notify_finding,
+                upload,
+                s3_endpoint,
+                s3_region,
+                ..
             } => {
                 logger::set_verbosity(verbose);
 
-                let scan_config = config::ScanConfig {
-                    input_file: input,
-                    rules_file: rules,
-                    concurrency,
-                    verbosity: if verbose { 3 } else { 2 }, // 3 for debug, 2 for info
-                    verbose,
-                    distributed: false,
-                    output_file: None,
-                    db_path: database,
-                    dns_timeout: 5, // default value
-                    http_timeout: timeout,
-                    connect_timeout: timeout,
-                    dns_cache_size: 10000, // default value
-                    quiet: false,
-                    dns_only: false,
-                };
-
-                scanner::run_scan(scan_config).await
+                // Flags left at their default don't override the config file
+                let command_line = matches
+                    .subcommand_matches("scan")
+                    .map(config_file::command_line)
+                    .unwrap_or_default();
+                let effective = config_file::EffectiveConfig::load_with(
+                    config.as_deref(),
+                    config_profile.as_deref(),
+                    &overrides,
+                    command_line,
+                )
+                .classify(Failure::Config)?;
+                let has_input = *effective.source("input") != config_file::Source::Default;
+                if !has_input && source.is_empty() {
+                    return Err(Failure::Config.because(
+                        "At least one --input (or input in the config file) is required"
+                            .to_string(),
+                    ));
+                }
+
+                let mut scan_config = effective.config;
+                scan_config.verbosity = if verbose { 3 } else { 2 }; // 3 for debug, 2 for info
+                scan_config.verbose = verbose;
+                if no_interesting_status {
+                    scan_config.interesting_status.clear();
+                }
+                scan_config.sinks = upload
+                    .iter()
+                    .map(|target| {
+                        let s3 = upload::S3Config {
+                            endpoint: s3_endpoint.clone(),
+                            region: s3_region.clone(),
+                            ..Default::default()
+                        };
+                        config::SinkConfig::upload(target, s3)
+                    })
+                    .collect::<Result<_>>()
+                    .classify(Failure::Config)?;
+                scan_config.webhooks = notify_finding
+                    .iter()
+                    .map(|url| notify::WebhookConfig::on_finding(url))
+                    .collect();
+
+                // Input files are followed as they grow, like any other live source
+                let mut sources = source;
+                if !sources.is_empty() {
+                    if has_input {
+                        sources.extend(
+                            scan_config
+                                .inputs()
+                                .into_iter()
+                                .map(|input| sources::SourceSpec::File(input.file)),
+                        );
+                    }
+                    scan_config.input_file = String::new();
+                    scan_config.input_tag = None;
+                    scan_config.extra_inputs.clear();
+                }
+
+                match watch {
+                    Some(interval) if !sources.is_empty() => {
+                        sources::follow_sources(scan_config, &sources, interval).await
+                    }
+                    Some(interval) => scanner::watch_scan(scan_config, interval).await,
+                    None if !sources.is_empty() => Err(Failure::Config
+                        .because("--source needs --watch SECONDS to poll the sources".to_string())),
+                    None => scanner::run_scan(scan_config).await,
+                }
             }
 
+            Commands::Run { manifest, verbose } => {
+                logger::set_verbosity(verbose);
+                manifest::run_manifest(&manifest, verbose).await
+            }
+
+            Commands::Config { action } => match action {
+                ConfigCommands::Check {
+                    config,
+                    profile,
+                    overrides,
+                } => config_file::check_config(config.as_deref(), profile.as_deref(), &overrides),
+            },
+
             Commands::Rules { action } => match action {
-                RulesCommands::Add { file } => rules::add_rule(&file),
-                RulesCommands::Remove { name } => rules::remove_rule(&name),
-                RulesCommands::List { file } => rules::list_rules(&file),
+                RulesCommands::Add { file } => rules::add_rule(&file).classify(Failure::Rules),
+                RulesCommands::Remove { name } => {
+                    rules::remove_rule(&name).classify(Failure::Rules)
+                }
+                RulesCommands::List { file } => rules::list_rules(&file).classify(Failure::Rules),
+                RulesCommands::Validate { file, format } => {
+                    rule_lint::validate_rules(&file, format).classify(Failure::Rules)
+                }
             },
 
             Commands::Results { action } => match action {
                 ResultsCommands::Export {
                     output,
+                    upload,
+                    s3_endpoint,
+                    s3_region,
                     database,
+                    store,
+                    shards,
                     format,
-                } => db::export_results(&database, &output, &format),
+                    split_output_by,
+                    redact_patterns,
+                    rules,
+                    since,
+                    until,
+                    detected_only,
+                    include_negative,
+                    group_by,
+                    project,
+                    include_false_positives,
+                } => {
+                    let ruleset = default_rules_path(rules)
+                        .map(|file| {
+                            rules::load_rules(&file)
+                                .context(format!("Failed to load rules: {}", file))
+                        })
+                        .transpose()
+                        .classify(Failure::Config)?;
+                    let options = store::ExportOptions {
+                        format: &format,
+                        split_by: split_output_by,
+                        redaction: &redact::RedactionPolicy::new(&redact_patterns)
+                            .classify(Failure::Config)?,
+                        range: db::TimeRange::parse(since.as_deref(), until.as_deref())
+                            .classify(Failure::Config)?,
+                        detected_only: detected_only || !include_negative,
+                        ruleset: ruleset.as_ref(),
+                        group_by,
+                        project: project.as_deref(),
+                        include_false_positives,
+                    };
+                    let write = |output: &str| {
+                        store::export_results(store, &database, shards, output, &options)
+                    };
+                    match (output, upload) {
+                        (_, Some(target)) => {
+                            let s3 = upload::S3Config {
+                                endpoint: s3_endpoint,
+                                region: s3_region,
+                                ..Default::default()
+                            };
+                            upload::upload_export(&s3, &target, write).await?;
+                            Ok(())
+                        }
+                        (Some(output), None) => write(&output),
+                        (None, None) => unreachable!("clap requires --output or --upload"),
+                    }
+                }
                 ResultsCommands::List {
                     database,
+                    shards,
+                    domain,
+                    rule,
+                    since,
+                    until,
+                    detected_only,
+                    include_negative,
+                    project,
+                    limit,
+                    offset,
+                    sort,
+                    order,
+                    rules,
+                    output,
+                } => {
+                    // Only sorting by severity needs the rules
+                    let rules = match (rules, sort) {
+                        (Some(file), _) => Some(file),
+                        (None, db::SortBy::Severity) => default_rules_path(None),
+                        (None, _) => None,
+                    };
+                    let ruleset = rules
+                        .map(|file| {
+                            rules::load_rules(&file)
+                                .context(format!("Failed to load rules: {}", file))
+                        })
+                        .transpose()
+                        .classify(Failure::Config)?;
+                    let query = db::ListQuery {
+                        domain_pattern: domain.as_deref(),
+                        rule_pattern: rule.as_deref(),
+                        range: db::TimeRange::parse(since.as_deref(), until.as_deref())
+                            .classify(Failure::Config)?,
+                        detected_only: detected_only || !include_negative,
+                        project: project.as_deref(),
+                        sort,
+                        order: order.unwrap_or_else(|| sort.default_order()),
+                        offset,
+                        limit,
+                    };
+                    query.check(ruleset.as_ref()).classify(Failure::Config)?;
+                    store::list_results(&database, shards, &query, ruleset.as_ref(), output)
+                }
+                ResultsCommands::Show {
+                    id,
+                    database,
+                    shards,
+                    rules,
+                    format,
+                } => {
+                    let ruleset = default_rules_path(rules)
+                        .map(|file| {
+                            rules::load_rules(&file)
+                                .context(format!("Failed to load rules: {}", file))
+                        })
+                        .transpose()
+                        .classify(Failure::Config)?;
+                    detail::show_finding(&database, shards, id, ruleset.as_ref(), &format)
+                }
+                ResultsCommands::Annotate {
+                    id,
+                    note,
+                    author,
+                    database,
+                    shards,
+                } => {
+                    let author = author.or_else(detail::default_author);
+                    detail::annotate(&database, shards, id, &note, author.as_deref()).map(|_| ())
+                }
+                ResultsCommands::Fp {
+                    id,
+                    skip_scans,
+                    clear,
+                    database,
+                    shards,
+                } => detail::mark_false_positive(&database, shards, id, skip_scans, clear)
+                    .map(|_| ()),
+                ResultsCommands::Domain {
+                    domain,
+                    database,
+                    store,
+                    shards,
+                    rules,
+                    history,
+                    risk_weights,
+                    format,
+                } => {
+                    let ruleset = default_rules_path(rules)
+                        .map(|file| {
+                            rules::load_rules(&file)
+                                .context(format!("Failed to load rules: {}", file))
+                        })
+                        .transpose()
+                        .classify(Failure::Config)?;
+                    let results = store::open_results(store, &database, shards)?;
+                    domain_report::show_domain(
+                        results.as_ref(),
+                        &database,
+                        &utils::normalize_domain(&domain),
+                        history,
+                        ruleset.as_ref(),
+                        &risk_weights,
+                        &format,
+                    )
+                }
+                ResultsCommands::Merge {
+                    database,
+                    shards,
+                    into,
+                    sources,
+                } => match (into, shards) {
+                    (Some(into), _) => db::merge_databases(&into, &sources).map(|_| ()),
+                    (None, Some(shards)) => db::merge_shards(&database, shards).map(|_| ()),
+                    (None, None) => unreachable!("clap requires --shards without --into"),
+                },
+                ResultsCommands::Diff {
+                    from,
+                    to,
+                    from_db,
+                    to_db,
+                    database,
+                    store,
+                    shards,
+                    rules,
+                    risk_weights,
+                    format,
+                } => {
+                    let ruleset = default_rules_path(rules)
+                        .map(|file| {
+                            rules::load_rules(&file)
+                                .context(format!("Failed to load rules: {}", file))
+                        })
+                        .transpose()
+                        .classify(Failure::Config)?;
+                    let from = diff::DiffSide {
+                        database: from_db.unwrap_or_else(|| database.clone()),
+                        scan_id: from,
+                    };
+                    let to = diff::DiffSide {
+                        database: to_db.unwrap_or(database),
+                        scan_id: to,
+                    };
+                    diff::diff_results(
+                        store,
+                        shards,
+                        &from,
+                        &to,
+                        ruleset.as_ref(),
+                        &risk_weights,
+                        &format,
+                    )
+                }
+                ResultsCommands::Compare {
+                    nuclei_json,
+                    json,
+                    domain_field,
+                    path_field,
+                    name_field,
+                    database,
+                    store,
+                    shards,
+                    format,
+                } => {
+                    let (other_file, mapping) = match (nuclei_json, json) {
+                        (Some(file), _) => (file, compare::FieldMapping::nuclei()),
+                        (None, Some(file)) => (
+                            file,
+                            compare::FieldMapping {
+                                domain: domain_field,
+                                path: path_field,
+                                name: name_field,
+                            },
+                        ),
+                        (None, None) => unreachable!("clap requires --nuclei-json or --json"),
+                    };
+                    compare::compare_results(
+                        store,
+                        &database,
+                        shards,
+                        &other_file,
+                        &mapping,
+                        &format,
+                    )
+                }
+                ResultsCommands::Stats {
+                    database,
+                    store,
+                    shards,
+                    rules,
+                    top,
+                    project,
+                    format,
+                } => {
+                    let rules = default_rules_path(rules);
+                    summary::show_stats(
+                        store,
+                        &database,
+                        shards,
+                        rules.as_deref(),
+                        top,
+                        project.as_deref(),
+                        &format,
+                    )
+                }
+                ResultsCommands::Purge {
+                    database,
+                    store,
+                    shards,
+                    older_than,
+                    rule,
+                    not_detected,
+                } => retention::purge(
+                    store,
+                    &database,
+                    shards,
+                    &older_than,
+                    rule.as_deref(),
+                    not_detected,
+                )
+                .map(|_| ()),
+                ResultsCommands::Delete {
+                    database,
+                    store,
+                    shards,
                     domain,
                     rule,
+                    id,
+                } => {
+                    let filter = db::PurgeFilter {
+                        domain: domain.as_deref().map(utils::normalize_domain),
+                        rule,
+                        id,
+                        ..Default::default()
+                    };
+                    retention::delete(store, &database, shards, &filter).map(|_| ())
+                }
+                ResultsCommands::Scans {
+                    database,
                     limit,
-                } => db::list_results(&database, domain.as_deref(), rule.as_deref(), limit),
+                    project,
+                } => store::list_scans(&database, limit, project.as_deref()),
+                ResultsCommands::Timeline {
+                    database,
+                    store,
+                    shards,
+                    bucket,
+                    rules,
+                    project,
+                    format,
+                } => {
+                    let rules = default_rules_path(rules);
+                    timeline::show_timeline(
+                        store,
+                        &database,
+                        shards,
+                        rules.as_deref(),
+                        bucket,
+                        project.as_deref(),
+                        &format,
+                    )
+                }
+                ResultsCommands::Certs {
+                    database,
+                    store,
+                    shards,
+                    expiring_within,
+                    format,
+                } => cert_inventory::show_certificates(
+                    store,
+                    &database,
+                    shards,
+                    expiring_within.as_deref(),
+                    &format,
+                ),
+                ResultsCommands::Clusters {
+                    database,
+                    store,
+                    shards,
+                    rule,
+                    min_size,
+                    format,
+                    dismiss,
+                    skip_scans,
+                } => match dismiss {
+                    Some(id) => clusters::dismiss_cluster(
+                        &database,
+                        shards,
+                        &id,
+                        rule.as_deref(),
+                        skip_scans,
+                    )
+                    .map(|_| ()),
+                    None => clusters::show_clusters(
+                        store,
+                        &database,
+                        shards,
+                        rule.as_deref(),
+                        min_size,
+                        &format,
+                    ),
+                },
+            },
+
+            Commands::Db { action } => match action {
+                DbCommands::Prune {
+                    database,
+                    shards,
+                    older_than,
+                    archive,
+                } => {
+                    retention::prune(&database, shards, older_than, archive.as_deref()).map(|_| ())
+                }
+                DbCommands::Vacuum { database, shards } => {
+                    let reports = retention::vacuum(&database, shards)?;
+                    let damaged: Vec<&str> = reports
+                        .iter()
+                        .filter(|report| !report.problems.is_empty())
+                        .map(|report| report.path.as_str())
+                        .collect();
+                    if !damaged.is_empty() {
+                        anyhow::bail!("Integrity check failed: {}", damaged.join(", "));
+                    }
+                    Ok(())
+                }
+                DbCommands::Migrate { database, shards } => {
+                    store::migrate_database(&database, shards).map(|_| ())
+                }
+                DbCommands::Import {
+                    files,
+                    format,
+                    database,
+                } => {
+                    let reports = store::import_results(&database, &files, &format)?;
+                    println!(
+                        "✅ Imported {} findings from {} scan files into {}",
+                        reports.iter().map(|report| report.written).sum::<usize>(),
+                        reports.len(),
+                        database
+                    );
+                    Ok(())
+                }
             },
 
             Commands::Dns { action } => match action {
@@ -257,15 +1901,52 @@ fn main() -> Result<()> {
                     .context("Failed to show DNS cache status"),
             },
 
+            #[cfg(feature = "distributed")]
             Commands::Worker { action } => match action {
-                WorkerCommands::Start { master, id, port } => {
-                    let worker_id = id.unwrap_or_else(|| Uuid::new_v4().to_string());
-                    info!("Starting worker with ID: {}", worker_id);
+                WorkerCommands::Start {
+                    master,
+                    id,
+                    port,
+                    cert,
+                    key,
+                    ca_cert,
+                    server_name,
+                    reconnect_attempts,
+                    reconnect_backoff_ms,
+                    config,
+                    config_profile,
+                    overrides,
+                } => {
+                    let worker_id = id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+                    tracing::info!("Starting worker with ID: {}", worker_id);
 
+                    let scan = config_file::EffectiveConfig::load(
+                        config.as_deref(),
+                        config_profile.as_deref(),
+                        &overrides,
+                    )
+                    .classify(Failure::Config)?
+                    .config;
+                    if scan.evasion && !scan.authorized_testing {
+                        return Err(Failure::Config.because(
+                            "evasion requires authorized_testing to confirm you have permission to test the targets".to_string(),
+                        ));
+                    }
                     let worker_config = distributed::WorkerConfig {
                         worker_id,
                         master: format!("{}:{}", master, port),
-                        concurrency: 10, // Default concurrency
+                        concurrency: scan.concurrency,
+                        tls: ca_cert.map(|ca_cert| distributed::WorkerTls {
+                            ca_cert: ca_cert.into(),
+                            cert: cert.map(Into::into),
+                            key: key.map(Into::into),
+                            server_name,
+                        }),
+                        reconnect: utils::RetryPolicy::new(
+                            reconnect_attempts,
+                            reconnect_backoff_ms,
+                        ),
+                        scan,
                     };
 
                     distributed::start_worker(&worker_config)
@@ -279,8 +1960,118 @@ fn main() -> Result<()> {
                     .await
                     .context("Failed to get worker status"),
             },
+
+            Commands::Tui { connect, token } => {
+                let token = token.or_else(|| std::env::var(stream::TOKEN_VAR).ok());
+                tui::run_tui(&connect, token.as_deref()).await
+            }
+
+            #[cfg(feature = "distributed")]
+            Commands::Master { action } => match action {
+                MasterCommands::Start {
+                    listen,
+                    ca_dir,
+                    server_name,
+                    tls_cert,
+                    tls_key,
+                    stream_listen,
+                    stream_token,
+                    rules,
+                    input,
+                    database,
+                    project,
+                    batch_size,
+                    batch_timeout,
+                    no_scan,
+                } => {
+                    let ca = ca_dir
+                        .map(|dir| certs::CertAuthority::open(dir.as_ref()))
+                        .transpose()
+                        .classify(Failure::Config)?;
+                    distributed::start_master(&distributed::MasterConfig {
+                        listen,
+                        ca,
+                        server_names: server_name,
+                        tls: tls_cert
+                            .zip(tls_key)
+                            .map(|(cert, key)| distributed::MasterTls {
+                                cert: cert.into(),
+                                key: key.into(),
+                            }),
+                        stream_listen,
+                        stream_token: stream_token
+                            .or_else(|| std::env::var(stream::TOKEN_VAR).ok()),
+                        scan: (!no_scan).then_some(distributed::MasterScan {
+                            rules_file: rules,
+                            input_file: input.file,
+                            input_tag: input.tag,
+                            database,
+                            project,
+                            batch_size,
+                            batch_timeout: std::time::Duration::from_secs(batch_timeout),
+                        }),
+                    })
+                    .await
+                    .context("Failed to start master")
+                }
+                MasterCommands::InitCa { ca_dir } => {
+                    let ca = certs::CertAuthority::init(ca_dir.as_ref())?;
+                    println!("CA certificate: {}", ca.cert_path().display());
+                    Ok(())
+                }
+                MasterCommands::IssueCert {
+                    worker_id,
+                    ca_dir,
+                    out,
+                    days,
+                } => {
+                    let ca =
+                        certs::CertAuthority::open(ca_dir.as_ref()).classify(Failure::Config)?;
+                    let files = ca.issue(&worker_id, days, out.as_ref())?;
+                    println!("Certificate: {}", files.cert.display());
+                    println!("Key: {}", files.key.display());
+                    println!("CA certificate: {}", ca.cert_path().display());
+                    Ok(())
+                }
+                MasterCommands::RevokeCert { worker_id, ca_dir } => {
+                    let ca =
+                        certs::CertAuthority::open(ca_dir.as_ref()).classify(Failure::Config)?;
+                    match ca.revoke(&worker_id)? {
+                        0 => anyhow::bail!("No unrevoked certificates for worker {}", worker_id),
+                        revoked => {
+                            println!("Revoked {} certificate(s) of worker {}", revoked, worker_id);
+                            Ok(())
+                        }
+                    }
+                }
+            },
+
+            Commands::Doctor {
+                concurrency,
+                database,
+                dns_host,
+                urls,
+                proxy,
+                timeout,
+            } => {
+                doctor::run_doctor(&doctor::DoctorOptions {
+                    concurrency,
+                    database,
+                    dns_host,
+                    urls,
+                    proxy,
+                    timeout: std::time::Duration::from_secs(timeout),
+                })
+                .await
+            }
+
+            Commands::Schema { kind, output } => schema::write_schema(kind, output.as_deref()),
         }
-    })?;
+    });
+    drop(rt);
 
-    Ok(())
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown();
+    }
+    result
 }
@@ -0,0 +1,437 @@
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::config::{InputSource, ScanConfig, SinkConfig};
+use crate::email::EmailConfig;
+use crate::exit::{Classify, Failure};
+use crate::incident::IncidentConfig;
+use crate::notify::WebhookConfig;
+use crate::profile::ScanProfile;
+use crate::scanner;
+use crate::secrets;
+use crate::store::{self, StoreKind};
+use crate::upload::S3Url;
+
+/// A declarative scan definition: targets, rules, tuning, outputs and schedule in one file.
+///
+/// Relative paths are resolved against the manifest's directory, so a manifest
+/// committed next to its inputs runs the same from anywhere.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Human-readable name for the scan
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Project the scan's session and findings are labelled with
+    #[serde(default)]
+    pub project: Option<String>,
+
+    /// Domains to scan
+    pub targets: Targets,
+
+    /// Rules files, merged in order
+    pub rules: Vec<String>,
+
+    /// Results database
+    #[serde(default = "default_database")]
+    pub database: String,
+
+    /// Working directory for the scan's results, evidence, logs, state and summary;
+    /// replaces `database` when set
+    #[serde(default)]
+    pub workdir: Option<String>,
+
+    /// Result store backend; `jsonl` treats `database` as a directory
+    #[serde(default)]
+    pub store: StoreKind,
+
+    /// Number of SQLite files to shard findings across by domain hash
+    #[serde(
+        default = "default_shards",
+        deserialize_with = "secrets::from_str_or_value"
+    )]
+    pub shards: usize,
+
+    /// Expected findings (accepted risk) file
+    #[serde(default)]
+    pub expected: Option<String>,
+
+    /// Canary domains checked before every scan
+    #[serde(default)]
+    pub canaries: Option<String>,
+
+    /// Scan tuning; anything omitted keeps the scanner default
+    #[serde(default)]
+    pub tuning: Tuning,
+
+    /// Result exports written after every scan
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+
+    /// Notifications sent after every scan
+    #[serde(default)]
+    pub notifications: Notifications,
+
+    /// Stale findings purged after every scan
+    #[serde(default)]
+    pub retention: Option<Retention>,
+
+    /// Repeat the scan on a schedule instead of running once
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+}
+
+/// Where the domains to scan come from: one file, or a list of files that are usually
+/// tagged so their findings can be exported separately
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Targets {
+    One(InputSource),
+    Many(Vec<InputSource>),
+}
+
+impl Targets {
+    /// Every domain file, in order
+    pub fn sources(&self) -> &[InputSource] {
+        match self {
+            Targets::One(source) => std::slice::from_ref(source),
+            Targets::Many(sources) => sources,
+        }
+    }
+
+    fn sources_mut(&mut self) -> &mut [InputSource] {
+        match self {
+            Targets::One(source) => std::slice::from_mut(source),
+            Targets::Many(sources) => sources,
+        }
+    }
+}
+
+/// Scan tuning overrides
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Tuning {
+    /// Preset the other tuning values override: stealth, normal or aggressive
+    pub profile: Option<ScanProfile>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub concurrency: Option<usize>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub queue_capacity: Option<usize>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub match_threads: Option<usize>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub timeout: Option<u64>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub connect_timeout: Option<u64>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub tls_timeout: Option<u64>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub first_byte_timeout: Option<u64>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub dns_timeout: Option<u64>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub dns_brute: Option<bool>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub dns_brute_rate: Option<u32>,
+    pub expand_command: Option<String>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub expand_concurrency: Option<usize>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub max_body_bytes: Option<usize>,
+    pub interesting_status: Option<Vec<u16>>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub retries: Option<u32>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub retry_backoff_ms: Option<u64>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub jitter_ms: Option<u64>,
+    pub schemes: Option<Vec<String>>,
+    pub ports: Option<Vec<u16>>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub circuit_breaker: Option<usize>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub rule_concurrency: Option<usize>,
+    pub proxy: Option<String>,
+    pub proxy_auth: Option<String>,
+    pub user_agent_file: Option<String>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub sticky_user_agent: Option<bool>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub capture_headers: Option<bool>,
+    pub risk_weights: Option<String>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub anomaly_factor: Option<f64>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub anomaly_min_count: Option<usize>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub evasion: Option<bool>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub authorized_testing: Option<bool>,
+    pub stream_listen: Option<String>,
+    pub stream_token: Option<String>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub hot_reload_rules: Option<bool>,
+    pub scan_window: Option<String>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub throttle_tld: Option<usize>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub throttle_asn: Option<usize>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub throttle_subnet: Option<usize>,
+    pub asn_file: Option<String>,
+    pub redact_patterns: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "secrets::option_from_str_or_value")]
+    pub db_tuning: Option<bool>,
+}
+
+/// Notification targets
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Notifications {
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+
+    #[serde(default)]
+    pub emails: Vec<EmailConfig>,
+
+    #[serde(default)]
+    pub incidents: Vec<IncidentConfig>,
+}
+
+/// Findings purged after every scan
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Retention {
+    /// Age of the findings purged, such as `30d`
+    pub older_than: String,
+
+    /// Only purge findings where nothing was detected
+    #[serde(default, deserialize_with = "secrets::from_str_or_value")]
+    pub not_detected: bool,
+}
+
+/// How often to repeat the scan
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Schedule {
+    /// Seconds between the end of one scan and the start of the next
+    #[serde(deserialize_with = "secrets::from_str_or_value")]
+    pub interval_secs: u64,
+}
+
+fn default_database() -> String {
+    "results.sqlite".to_string()
+}
+
+fn default_shards() -> usize {
+    1
+}
+
+impl Manifest {
+    /// Load a manifest from a YAML file, resolving its paths against the file's directory
+    pub fn from_file(path: &str) -> Result<Self> {
+        let contents =
+            fs::read_to_string(path).context(format!("Failed to read manifest: {}", path))?;
+        let mut document: serde_yaml::Value = serde_yaml::from_str(&contents)
+            .context(format!("Failed to parse manifest: {}", path))?;
+
+        // Expand ${...} references after parsing so secret values can't alter the structure
+        secrets::interpolate_value(&mut document)
+            .context(format!("Failed to interpolate manifest: {}", path))?;
+
+        let mut manifest: Manifest = serde_yaml::from_value(document)
+            .context(format!("Failed to parse manifest: {}", path))?;
+
+        let base_dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        manifest.resolve_paths(base_dir);
+
+        Ok(manifest)
+    }
+
+    /// Make every relative path relative to `base_dir`
+    fn resolve_paths(&mut self, base_dir: &Path) {
+        let resolve = |path: &mut String| *path = resolve_path(base_dir, path);
+
+        self.targets
+            .sources_mut()
+            .iter_mut()
+            .for_each(|source| resolve(&mut source.file));
+        self.rules.iter_mut().for_each(resolve);
+        resolve(&mut self.database);
+        self.workdir.iter_mut().for_each(resolve);
+        self.expected.iter_mut().for_each(resolve);
+        self.canaries.iter_mut().for_each(resolve);
+        self.tuning.user_agent_file.iter_mut().for_each(resolve);
+        self.tuning.asn_file.iter_mut().for_each(resolve);
+        self.sinks
+            .iter_mut()
+            .filter(|sink| !S3Url::is_s3(&sink.path))
+            .for_each(|sink| resolve(&mut sink.path));
+    }
+
+    /// Build the scan configuration this manifest describes
+    pub fn to_scan_config(&self) -> Result<ScanConfig> {
+        let (rules_file, extra_rules_files) = self
+            .rules
+            .split_first()
+            .context("Manifest must list at least one rules file")?;
+        let (input, extra_inputs) = self
+            .targets
+            .sources()
+            .split_first()
+            .context("Manifest must list at least one targets file")?;
+
+        let mut config = ScanConfig {
+            input_file: input.file.clone(),
+            input_tag: input.tag.clone(),
+            extra_inputs: extra_inputs.to_vec(),
+            rules_file: rules_file.clone(),
+            extra_rules_files: extra_rules_files.to_vec(),
+            db_path: self.database.clone(),
+            workdir: self.workdir.clone(),
+            store: self.store,
+            db_shards: self.shards,
+            output_file: None,
+            expected_file: self.expected.clone(),
+            canaries_file: self.canaries.clone(),
+            sinks: self.sinks.clone(),
+            webhooks: self.notifications.webhooks.clone(),
+            emails: self.notifications.emails.clone(),
+            incidents: self.notifications.incidents.clone(),
+            project: self.project.clone(),
+            ..Default::default()
+        };
+
+        let tuning = &self.tuning;
+        if let Some(profile) = tuning.profile {
+            profile.apply(&mut config);
+        }
+        if let Some(concurrency) = tuning.concurrency {
+            config.concurrency = concurrency;
+        }
+        if let Some(queue_capacity) = tuning.queue_capacity {
+            config.queue_capacity = queue_capacity;
+        }
+        if let Some(match_threads) = tuning.match_threads {
+            config.match_threads = match_threads;
+        }
+        if let Some(timeout) = tuning.timeout {
+            config.http_timeout = timeout;
+        }
+        if let Some(connect_timeout) = tuning.connect_timeout {
+            config.connect_timeout = connect_timeout;
+        }
+        if let Some(tls_timeout) = tuning.tls_timeout {
+            config.tls_timeout = tls_timeout;
+        }
+        if let Some(first_byte_timeout) = tuning.first_byte_timeout {
+            config.first_byte_timeout = first_byte_timeout;
+        }
+        if let Some(dns_timeout) = tuning.dns_timeout {
+            config.dns_timeout = dns_timeout;
+        }
+        if let Some(max_body_bytes) = tuning.max_body_bytes {
+            config.max_body_bytes = max_body_bytes;
+        }
+        if let Some(interesting_status) = &tuning.interesting_status {
+            config.interesting_status = interesting_status.clone();
+        }
+        if let Some(retries) = tuning.retries {
+            config.retries = retries;
+        }
+        if let Some(retry_backoff_ms) = tuning.retry_backoff_ms {
+            config.retry_backoff_ms = retry_backoff_ms;
+        }
+        if let Some(jitter_ms) = tuning.jitter_ms {
+            config.jitter_ms = jitter_ms;
+        }
+        if let Some(schemes) = &tuning.schemes {
+            config.schemes = schemes.clone();
+        }
+        if let Some(ports) = &tuning.ports {
+            config.ports = ports.clone();
+        }
+        if let Some(circuit_breaker) = tuning.circuit_breaker {
+            config.circuit_breaker = circuit_breaker;
+        }
+        if let Some(rule_concurrency) = tuning.rule_concurrency {
+            config.rule_concurrency = rule_concurrency;
+        }
+        config.proxy = tuning.proxy.clone();
+        config.proxy_auth = tuning.proxy_auth.clone();
+        config.user_agent_file = tuning.user_agent_file.clone();
+        config.sticky_user_agent = tuning.sticky_user_agent.unwrap_or(false);
+        config.capture_headers = tuning.capture_headers.unwrap_or(false);
+        if let Some(risk_weights) = &tuning.risk_weights {
+            config.risk_weights = risk_weights.parse()?;
+        }
+        if let Some(anomaly_factor) = tuning.anomaly_factor {
+            config.anomaly_factor = anomaly_factor;
+        }
+        if let Some(anomaly_min_count) = tuning.anomaly_min_count {
+            config.anomaly_min_count = anomaly_min_count;
+        }
+        config.evasion = tuning.evasion.unwrap_or(false);
+        config.authorized_testing = tuning.authorized_testing.unwrap_or(false);
+        config.stream_listen = tuning.stream_listen.clone();
+        config.stream_token = tuning.stream_token.clone();
+        config.hot_reload_rules = tuning.hot_reload_rules.unwrap_or(false);
+        config.scan_window = tuning.scan_window.clone();
+        config.dns_brute = tuning.dns_brute.unwrap_or(false);
+        if let Some(dns_brute_rate) = tuning.dns_brute_rate {
+            config.dns_brute_rate = dns_brute_rate;
+        }
+        config.expand_command = tuning.expand_command.clone();
+        if let Some(expand_concurrency) = tuning.expand_concurrency {
+            config.expand_concurrency = expand_concurrency;
+        }
+        config.throttle_tld = tuning.throttle_tld.unwrap_or(0);
+        config.throttle_asn = tuning.throttle_asn.unwrap_or(0);
+        if let Some(throttle_subnet) = tuning.throttle_subnet {
+            config.throttle_subnet = throttle_subnet;
+        }
+        config.asn_file = tuning.asn_file.clone();
+        config.redact_patterns = tuning.redact_patterns.clone().unwrap_or_default();
+        config.db_tuning = tuning.db_tuning.unwrap_or(true);
+        if let Some(retention) = &self.retention {
+            config.purge_older_than = Some(retention.older_than.clone());
+            config.purge_not_detected = retention.not_detected;
+        }
+
+        Ok(config)
+    }
+}
+
+fn resolve_path(base_dir: &Path, path: &str) -> String {
+    if Path::new(path).is_absolute() || store::is_postgres_url(path) {
+        path.to_string()
+    } else {
+        PathBuf::from(base_dir)
+            .join(path)
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+/// Run the scan described by a manifest file
+pub async fn run_manifest(path: &str, verbose: bool) -> Result<()> {
+    let manifest = Manifest::from_file(path).classify(Failure::Config)?;
+    let mut config = manifest.to_scan_config().classify(Failure::Config)?;
+    config.verbose = verbose;
+    config.verbosity = if verbose { 3 } else { 2 };
+
+    info!(
+        "📜 Running manifest {}",
+        manifest.name.as_deref().unwrap_or(path)
+    );
+
+    match &manifest.schedule {
+        Some(schedule) => scanner::watch_scan(config, schedule.interval_secs).await,
+        None => scanner::run_scan(config).await,
+    }
+}
@@ -0,0 +1,420 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+
+use crate::db::format_timestamp;
+use crate::exit::Failure;
+
+/// A versioned change to the results database schema.
+///
+/// Migrations are applied in order, each in its own transaction, and never edited
+/// once released: a schema change is a new migration at the end of the list.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    apply: fn(&Connection) -> Result<()>,
+}
+
+/// Every migration, oldest first
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial schema",
+        apply: initial_schema,
+    },
+    Migration {
+        version: 2,
+        name: "finding evidence",
+        apply: finding_evidence,
+    },
+    Migration {
+        version: 3,
+        name: "scan profile",
+        apply: scan_profile,
+    },
+    Migration {
+        version: 4,
+        name: "domain scans",
+        apply: domain_scans,
+    },
+    Migration {
+        version: 5,
+        name: "projects",
+        apply: projects,
+    },
+    Migration {
+        version: 6,
+        name: "finding notes",
+        apply: finding_notes,
+    },
+    Migration {
+        version: 7,
+        name: "ruleset digests",
+        apply: ruleset_digests,
+    },
+    Migration {
+        version: 8,
+        name: "false positives",
+        apply: false_positives,
+    },
+    Migration {
+        version: 9,
+        name: "certificates",
+        apply: certificates,
+    },
+    Migration {
+        version: 10,
+        name: "note authors",
+        apply: note_authors,
+    },
+];
+
+/// Migrations applied to a database, and the version its schema ended up at
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub version: u32,
+    pub applied: Vec<(u32, &'static str)>,
+}
+
+/// Schema version this build writes
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Error for a database migrated by a newer build, which this one could corrupt
+pub(crate) fn newer_schema(database: &str, version: u32) -> anyhow::Error {
+    Failure::Database.because(format!(
+        "{} has schema version {}, newer than the {} this fatt supports; upgrade fatt",
+        database,
+        version,
+        latest_version()
+    ))
+}
+
+/// Version of a database's schema; 0 if it was never migrated
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    let tracked = conn
+        .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'")?
+        .exists([])?;
+    if !tracked {
+        return Ok(0);
+    }
+
+    let version: Option<u32> = conn
+        .query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+            row.get(0)
+        })
+        .optional()?
+        .flatten();
+
+    Ok(version.unwrap_or(0))
+}
+
+/// Apply the migrations a database is missing.
+///
+/// Each migration re-reads the version once it holds the write lock, so scans
+/// opening the same database together apply it only once.
+pub fn migrate(conn: &mut Connection) -> Result<MigrationReport> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT,
+            applied_at DATETIME
+        )",
+        [],
+    )
+    .context("Failed to create schema_migrations table")?;
+
+    let database = conn.path().unwrap_or("database").to_string();
+    let mut report = MigrationReport::default();
+
+    for migration in MIGRATIONS {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let version = schema_version(&tx)?;
+        if version > latest_version() {
+            return Err(newer_schema(&database, version));
+        }
+        if version >= migration.version {
+            continue;
+        }
+
+        (migration.apply)(&tx).context(format!(
+            "Failed to apply migration {} ({})",
+            migration.version, migration.name
+        ))?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?, ?, ?)",
+            params![
+                migration.version,
+                migration.name,
+                format_timestamp(&Utc::now())
+            ],
+        )?;
+        tx.commit()?;
+
+        report.applied.push((migration.version, migration.name));
+    }
+    report.version = schema_version(conn)?;
+
+    Ok(report)
+}
+
+/// The schema as it was before migrations were tracked.
+///
+/// Databases created before then may lack some of its tables and columns, so it
+/// only adds what's missing.
+fn initial_schema(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS findings (
+            id INTEGER PRIMARY KEY,
+            domain TEXT,
+            rule_name TEXT,
+            matched_path TEXT,
+            detected INTEGER,
+            scanned_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(domain, rule_name)
+        )",
+        [],
+    )
+    .context("Failed to create findings table")?;
+
+    // Columns added after the original schema
+    add_column_if_missing(conn, "findings", "user_agent", "TEXT")?;
+    add_column_if_missing(conn, "findings", "redirect_chain", "TEXT")?;
+    add_column_if_missing(conn, "findings", "tag", "TEXT")?;
+    add_column_if_missing(conn, "findings", "status", "INTEGER")?;
+    add_column_if_missing(conn, "findings", "ruleset_version", "INTEGER")?;
+
+    // Create index for faster lookups
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_findings_domain ON findings (domain)",
+        [],
+    )
+    .context("Failed to create domain index")?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_findings_rule ON findings (rule_name)",
+        [],
+    )
+    .context("Failed to create rule_name index")?;
+
+    // Scan sessions and their risk scores
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scans (
+            id TEXT PRIMARY KEY,
+            started_at DATETIME,
+            finished_at DATETIME,
+            domains INTEGER DEFAULT 0,
+            findings INTEGER DEFAULT 0,
+            risk_score REAL DEFAULT 0
+        )",
+        [],
+    )
+    .context("Failed to create scans table")?;
+    add_column_if_missing(conn, "scans", "incomplete", "INTEGER DEFAULT 0")?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS domain_risk (
+            scan_id TEXT,
+            domain TEXT,
+            risk_score REAL,
+            findings INTEGER,
+            PRIMARY KEY(scan_id, domain)
+        )",
+        [],
+    )
+    .context("Failed to create domain_risk table")?;
+
+    // Per-rule detection counts for each scan, used as an anomaly baseline
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS rule_stats (
+            scan_id TEXT,
+            rule_name TEXT,
+            detected INTEGER,
+            PRIMARY KEY(scan_id, rule_name)
+        )",
+        [],
+    )
+    .context("Failed to create rule_stats table")?;
+
+    // Versions of the rules each scan ran with; a scan reloading its rules has several
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ruleset_versions (
+            scan_id TEXT,
+            version INTEGER,
+            loaded_at DATETIME,
+            rules INTEGER,
+            digest TEXT,
+            PRIMARY KEY(scan_id, version)
+        )",
+        [],
+    )
+    .context("Failed to create ruleset_versions table")?;
+
+    // Detected findings of each scan, so scans can be compared after later ones
+    // replace the findings table's rows
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scan_findings (
+            scan_id TEXT,
+            domain TEXT,
+            rule_name TEXT,
+            matched_path TEXT,
+            scanned_at DATETIME,
+            tag TEXT,
+            status INTEGER,
+            ruleset_version INTEGER,
+            PRIMARY KEY(scan_id, domain, rule_name)
+        )",
+        [],
+    )
+    .context("Failed to create scan_findings table")?;
+
+    // Archives `db prune` wrote the rows it removed to
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS archives (
+            path TEXT,
+            created_at DATETIME,
+            cutoff DATETIME,
+            findings INTEGER,
+            scans INTEGER
+        )",
+        [],
+    )
+    .context("Failed to create archives table")?;
+
+    Ok(())
+}
+
+/// Response evidence kept with each finding
+fn finding_evidence(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE findings ADD COLUMN evidence TEXT;
+         ALTER TABLE scan_findings ADD COLUMN evidence TEXT;",
+    )
+    .context("Failed to add evidence columns")
+}
+
+/// Tuning profile each scan session ran with
+fn scan_profile(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE scans ADD COLUMN profile TEXT", [])
+        .context("Failed to add profile column")?;
+
+    Ok(())
+}
+
+/// What each scan saw of each domain: its addresses, technologies and errors
+fn domain_scans(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE domain_scans (
+            scan_id TEXT,
+            domain TEXT,
+            scanned_at DATETIME,
+            ips TEXT,
+            technologies TEXT,
+            error TEXT,
+            PRIMARY KEY(scan_id, domain)
+        );
+        CREATE INDEX idx_domain_scans_domain ON domain_scans (domain);",
+    )
+    .context("Failed to create domain_scans table")
+}
+
+/// Project each scan session, and the findings it recorded, belong to
+fn projects(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE scans ADD COLUMN project TEXT;
+         ALTER TABLE findings ADD COLUMN project TEXT;
+         ALTER TABLE scan_findings ADD COLUMN project TEXT;
+         CREATE INDEX idx_findings_project ON findings (project);",
+    )
+    .context("Failed to add project columns")
+}
+
+/// Analysts' notes on findings, kept by domain and rule so they outlive purges and
+/// re-detections of the finding
+fn finding_notes(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE finding_notes (
+            id INTEGER PRIMARY KEY,
+            domain TEXT NOT NULL,
+            rule_name TEXT NOT NULL,
+            note TEXT NOT NULL,
+            created_at DATETIME NOT NULL
+        );
+        CREATE INDEX idx_finding_notes_finding ON finding_notes (domain, rule_name);",
+    )
+    .context("Failed to create finding_notes table")
+}
+
+/// Digest of the rules each scan session started with, and of those each finding
+/// was checked with
+fn ruleset_digests(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE scans ADD COLUMN ruleset_digest TEXT;
+         ALTER TABLE findings ADD COLUMN ruleset_digest TEXT;
+         ALTER TABLE scan_findings ADD COLUMN ruleset_digest TEXT;",
+    )
+    .context("Failed to add ruleset digest columns")
+}
+
+/// Domain and rule pairs marked as false positives, kept apart from the findings so
+/// the mark survives purges and later checks of the pair
+fn false_positives(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE false_positives (
+            domain TEXT NOT NULL,
+            rule_name TEXT NOT NULL,
+            skip_scans INTEGER NOT NULL DEFAULT 0,
+            marked_at DATETIME NOT NULL,
+            PRIMARY KEY (domain, rule_name)
+        );",
+    )
+    .context("Failed to create false_positives table")
+}
+
+/// TLS certificates scanned hosts presented, the latest one per host
+fn certificates(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE certificates (
+            host TEXT PRIMARY KEY,
+            subject TEXT,
+            issuer TEXT,
+            sans TEXT,
+            serial TEXT,
+            not_before DATETIME,
+            not_after DATETIME,
+            seen_at DATETIME
+        );",
+    )
+    .context("Failed to create certificates table")
+}
+
+/// Who wrote each note; notes added before are left without an author
+fn note_authors(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "finding_notes", "author", "TEXT")
+}
+
+/// Add a column to an existing table unless it's already there
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let exists = conn
+        .prepare(&format!(
+            "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?",
+            table
+        ))?
+        .exists([column])?;
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )
+        .context(format!("Failed to add {}.{} column", table, column))?;
+    }
+
+    Ok(())
+}
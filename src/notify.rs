@@ -0,0 +1,599 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::anomaly::RuleAnomaly;
+use crate::email::{EmailConfig, EmailMode};
+use crate::incident::IncidentConfig;
+use crate::rules::Severity;
+use crate::secrets;
+use crate::utils::RetryPolicy;
+
+/// Header carrying the Unix time a signed webhook was sent at
+pub const TIMESTAMP_HEADER: &str = "X-Fatt-Timestamp";
+
+/// Header carrying the `sha256=<hex>` HMAC of a signed webhook
+pub const SIGNATURE_HEADER: &str = "X-Fatt-Signature";
+
+/// Findings waiting to be sent to the finding webhooks, alert emails and incident
+/// services before more are dropped
+const FINDING_QUEUE_CAPACITY: usize = 1000;
+
+/// Longest text Discord takes in an embed field's value
+const DISCORD_FIELD_MAX: usize = 1024;
+
+/// Retries of a failed finding notification, backing off from a second
+const FINDING_RETRY: RetryPolicy = RetryPolicy {
+    retries: 3,
+    backoff_ms: 1000,
+};
+
+/// A webhook notified when a scan completes, or of every finding as it's detected
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// URL the scan summary or finding is POSTed to
+    pub url: String,
+
+    /// What the webhook is notified of
+    #[serde(default)]
+    pub on: WebhookEvent,
+
+    /// Payload layout: plain JSON, or a Microsoft Teams or Discord message
+    #[serde(default)]
+    pub format: WebhookFormat,
+
+    /// Only notify when the scan detected something or saw an anomaly
+    #[serde(default, deserialize_with = "secrets::from_str_or_value")]
+    pub only_on_findings: bool,
+
+    /// Shared secret the payload is signed with, so the receiver can authenticate it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+
+    /// JSON body to send instead of the notification itself, with `{{field}}`
+    /// placeholders for the notification's fields (e.g. `{{domain}}`, `{{rule}}`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+impl WebhookConfig {
+    /// A webhook notified of every finding as it's detected, formatted for Teams or
+    /// Discord when the URL is one of theirs
+    pub fn on_finding(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            on: WebhookEvent::Finding,
+            format: WebhookFormat::for_url(url),
+            only_on_findings: false,
+            secret: None,
+            template: None,
+        }
+    }
+
+    /// Check that the URL parses and the template renders to JSON
+    pub fn validate(&self) -> Result<()> {
+        url::Url::parse(&self.url).context(format!("Invalid webhook URL: {}", self.url))?;
+        if let Some(template) = &self.template {
+            if self.format != WebhookFormat::Json {
+                anyhow::bail!(
+                    "The webhook at {} has a template, which only applies to the json format",
+                    webhook_host(&self.url)
+                );
+            }
+            let sample = match self.on {
+                WebhookEvent::ScanFinished => serde_json::to_value(ScanNotification::default())?,
+                WebhookEvent::Finding => serde_json::to_value(FindingNotification::default())?,
+            };
+            render_template(template, &sample).context(format!(
+                "Invalid template of the webhook at {}",
+                webhook_host(&self.url)
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+/// What a webhook is notified of
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// The scan's summary, once it completes
+    #[default]
+    ScanFinished,
+    /// Every detected finding, as soon as it's detected
+    Finding,
+}
+
+/// How a webhook's payload is laid out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// The notification as JSON, or the webhook's template
+    #[default]
+    Json,
+    /// A Microsoft Teams message with an Adaptive Card
+    Teams,
+    /// A Discord message with an embed
+    Discord,
+}
+
+impl WebhookFormat {
+    /// The format a webhook URL's service expects: Teams for Office 365 and Power
+    /// Automate webhooks, Discord for Discord's, JSON otherwise
+    pub fn for_url(url: &str) -> Self {
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+            .unwrap_or_default();
+        if host.ends_with(".webhook.office.com") || host.ends_with(".logic.azure.com") {
+            WebhookFormat::Teams
+        } else if host == "discord.com" || host == "discordapp.com" {
+            WebhookFormat::Discord
+        } else {
+            WebhookFormat::Json
+        }
+    }
+}
+
+/// What chat connectors show of a notification
+pub trait ChatCard {
+    /// Headline of the message
+    fn title(&self) -> String;
+
+    /// Labelled values shown under the headline
+    fn facts(&self) -> Vec<(&'static str, String)>;
+
+    /// Severity the message is coloured by, if it has one
+    fn severity(&self) -> Option<&Severity> {
+        None
+    }
+}
+
+/// Summary of a completed scan sent to notification targets
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScanNotification {
+    pub scan_id: String,
+    pub domains: usize,
+    pub findings: usize,
+    pub unexpected: usize,
+    pub risk_score: f64,
+    /// Rules whose match count jumped or fell sharply against recent scans
+    pub anomalies: Vec<RuleAnomaly>,
+}
+
+impl ScanNotification {
+    /// Whether the scan detected anything or saw an anomaly, which `only_on_findings`
+    /// targets are told about
+    pub fn is_noteworthy(&self) -> bool {
+        self.findings > 0 || !self.anomalies.is_empty()
+    }
+}
+
+/// A finding sent to finding webhooks as soon as it's detected
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FindingNotification {
+    pub scan_id: String,
+    pub domain: String,
+    pub rule: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    pub path: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    pub found_at: DateTime<Utc>,
+}
+
+impl ChatCard for ScanNotification {
+    fn title(&self) -> String {
+        format!("FATT scan finished: {} findings", self.findings)
+    }
+
+    fn facts(&self) -> Vec<(&'static str, String)> {
+        let mut facts = vec![
+            ("Domains", self.domains.to_string()),
+            ("Findings", self.findings.to_string()),
+            ("Unexpected", self.unexpected.to_string()),
+            ("Risk score", format!("{:.1}", self.risk_score)),
+            ("Scan", self.scan_id.clone()),
+        ];
+        if !self.anomalies.is_empty() {
+            let anomalies: Vec<String> = self.anomalies.iter().map(|a| a.to_string()).collect();
+            facts.push(("Anomalies", anomalies.join("; ")));
+        }
+        facts
+    }
+}
+
+impl ChatCard for FindingNotification {
+    fn title(&self) -> String {
+        format!("{} on {}", self.rule, self.domain)
+    }
+
+    fn facts(&self) -> Vec<(&'static str, String)> {
+        let mut facts = vec![
+            ("Domain", self.domain.clone()),
+            ("Rule", self.rule.clone()),
+            (
+                "Severity",
+                self.severity
+                    .as_ref()
+                    .map_or_else(|| "unknown".to_string(), Severity::to_string),
+            ),
+            ("Path", self.path.clone()),
+            ("Status", self.status.to_string()),
+        ];
+        if let Some(tag) = &self.tag {
+            facts.push(("Tag", tag.clone()));
+        }
+        if let Some(project) = &self.project {
+            facts.push(("Project", project.clone()));
+        }
+        facts.push(("Scan", self.scan_id.clone()));
+        facts
+    }
+
+    fn severity(&self) -> Option<&Severity> {
+        self.severity.as_ref()
+    }
+}
+
+/// A Microsoft Teams message holding an Adaptive Card of the notification
+pub fn teams_payload(card: &impl ChatCard) -> serde_json::Value {
+    let color = match card.severity() {
+        Some(Severity::Critical | Severity::High) => "attention",
+        Some(Severity::Medium) => "warning",
+        Some(_) => "accent",
+        None => "default",
+    };
+    let facts: Vec<serde_json::Value> = card
+        .facts()
+        .into_iter()
+        .map(|(title, value)| serde_json::json!({ "title": title, "value": value }))
+        .collect();
+
+    serde_json::json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "content": {
+                "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                "type": "AdaptiveCard",
+                "version": "1.4",
+                "body": [
+                    {
+                        "type": "TextBlock",
+                        "text": card.title(),
+                        "size": "Medium",
+                        "weight": "Bolder",
+                        "color": color,
+                        "wrap": true,
+                    },
+                    { "type": "FactSet", "facts": facts },
+                ],
+            },
+        }],
+    })
+}
+
+/// A Discord message holding an embed of the notification
+pub fn discord_payload(card: &impl ChatCard) -> serde_json::Value {
+    let color = match card.severity() {
+        Some(Severity::Critical) => 0xE74C3C,
+        Some(Severity::High) => 0xE67E22,
+        Some(Severity::Medium) => 0xF1C40F,
+        Some(Severity::Low) => 0x3498DB,
+        Some(Severity::Info) => 0x95A5A6,
+        None => 0x5865F2,
+    };
+    let fields: Vec<serde_json::Value> = card
+        .facts()
+        .into_iter()
+        .map(|(name, value)| {
+            // Discord rejects empty and overlong field values
+            let value = match value.as_str() {
+                "" => "-".to_string(),
+                _ => truncate_chars(&value, DISCORD_FIELD_MAX),
+            };
+            serde_json::json!({ "name": name, "value": value, "inline": true })
+        })
+        .collect();
+
+    serde_json::json!({
+        "username": "FATT",
+        "embeds": [{
+            "title": truncate_chars(&card.title(), 256),
+            "color": color,
+            "fields": fields,
+            "timestamp": Utc::now().to_rfc3339(),
+        }],
+    })
+}
+
+/// At most `max` characters of a text, ending in an ellipsis when cut
+pub(crate) fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// The body a webhook is sent for a notification, in the webhook's format
+pub fn webhook_payload<T: Serialize + ChatCard>(
+    webhook: &WebhookConfig,
+    notification: &T,
+) -> Result<Vec<u8>> {
+    let body = match (webhook.format, &webhook.template) {
+        (WebhookFormat::Json, Some(template)) => {
+            return render_template(template, &serde_json::to_value(notification)?)
+        }
+        (WebhookFormat::Json, None) => serde_json::to_value(notification)?,
+        (WebhookFormat::Teams, _) => teams_payload(notification),
+        (WebhookFormat::Discord, _) => discord_payload(notification),
+    };
+    serde_json::to_vec(&body).context("Failed to serialize notification")
+}
+
+/// Fill a webhook template's `{{field}}` placeholders from a notification. Strings are
+/// escaped for use inside a JSON string and other values are written as JSON, so
+/// `{"text": "{{rule}} on {{domain}}", "status": {{status}}}` renders to valid JSON.
+/// A field the notification doesn't have renders as empty.
+pub fn render_template(template: &str, notification: &serde_json::Value) -> Result<Vec<u8>> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let field = rest[start + 2..start + end].trim();
+        match notification.get(field) {
+            Some(serde_json::Value::String(value)) => {
+                let quoted = serde_json::to_string(value)?;
+                rendered.push_str(&quoted[1..quoted.len() - 1]);
+            }
+            Some(serde_json::Value::Null) | None => {}
+            Some(value) => rendered.push_str(&value.to_string()),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+
+    serde_json::from_str::<serde_json::Value>(&rendered)
+        .context("The template doesn't render to JSON")?;
+    Ok(rendered.into_bytes())
+}
+
+/// HMAC-SHA256 signature of a webhook body sent at `timestamp`, as `sha256=<hex>`.
+///
+/// The timestamp is signed along with the body (`<timestamp>.<body>`) so a captured
+/// request can't be replayed later with a fresh timestamp.
+pub fn sign_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let digest = payload_mac(secret, timestamp, body).finalize().into_bytes();
+    format!("sha256={}", hex::encode(digest))
+}
+
+fn payload_mac(secret: &str, timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// Check a signed webhook the way a receiver should: the signature must match and the
+/// timestamp must be within `tolerance_secs` of `now`
+#[allow(dead_code)]
+pub fn verify_signature(
+    secret: &str,
+    timestamp: i64,
+    body: &[u8],
+    signature: &str,
+    now: i64,
+    tolerance_secs: i64,
+) -> bool {
+    if (now - timestamp).abs() > tolerance_secs {
+        return false;
+    }
+    let Some(signature) = signature
+        .strip_prefix("sha256=")
+        .and_then(|hex| hex::decode(hex).ok())
+    else {
+        return false;
+    };
+
+    payload_mac(secret, timestamp, body)
+        .verify_slice(&signature)
+        .is_ok()
+}
+
+/// POST a notification to a webhook in its format, signed when the webhook has a secret
+pub async fn send_webhook<T: Serialize + ChatCard>(
+    client: &Client,
+    webhook: &WebhookConfig,
+    notification: &T,
+) -> Result<()> {
+    let body = webhook_payload(webhook, notification)?;
+    let mut request = client
+        .post(&webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    if let Some(secret) = &webhook.secret {
+        let timestamp = chrono::Utc::now().timestamp();
+        request = request
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(SIGNATURE_HEADER, sign_payload(secret, timestamp, &body));
+    }
+
+    let response = request
+        .body(body)
+        .send()
+        .await
+        .context("Failed to send webhook")?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("Webhook returned HTTP {}", response.status());
+    }
+
+    Ok(())
+}
+
+/// Notify every configured webhook, logging failures rather than failing the scan
+pub async fn notify_all(
+    client: &Client,
+    webhooks: &[WebhookConfig],
+    notification: &ScanNotification,
+) {
+    for webhook in webhooks {
+        if webhook.on != WebhookEvent::ScanFinished
+            || (webhook.only_on_findings && !notification.is_noteworthy())
+        {
+            continue;
+        }
+
+        // Webhook URLs often embed a secret token, so only log the host
+        let host = webhook_host(&webhook.url);
+        match send_webhook(client, webhook, notification).await {
+            Ok(()) => info!("📣 Notified webhook at {}", host),
+            Err(e) => warn!("⚠️ Failed to notify webhook at {}: {:#}", host, e),
+        }
+    }
+}
+
+/// Sends detected findings to the finding webhooks, alert emails and incident services
+/// from a queue, so a slow or failing target never holds up the scan. Failed deliveries are retried with
+/// backoff; when the queue is full, new findings are dropped and counted.
+#[derive(Debug)]
+pub struct FindingNotifier {
+    tx: Mutex<Option<mpsc::Sender<FindingNotification>>>,
+    task: Mutex<Option<JoinHandle<()>>>,
+    dropped: AtomicUsize,
+}
+
+impl FindingNotifier {
+    /// Start delivering to the webhooks notified of findings and the emails and
+    /// incident services alerting on them, if any are
+    pub fn start(
+        client: Client,
+        webhooks: &[WebhookConfig],
+        emails: &[EmailConfig],
+        incidents: &[IncidentConfig],
+    ) -> Option<Self> {
+        let webhooks: Vec<WebhookConfig> = webhooks
+            .iter()
+            .filter(|webhook| webhook.on == WebhookEvent::Finding)
+            .cloned()
+            .collect();
+        let emails: Vec<EmailConfig> = emails
+            .iter()
+            .filter(|email| email.mode == EmailMode::Alerts)
+            .cloned()
+            .collect();
+        let incidents = incidents.to_vec();
+        if webhooks.is_empty() && emails.is_empty() && incidents.is_empty() {
+            return None;
+        }
+
+        let (tx, mut rx) = mpsc::channel::<FindingNotification>(FINDING_QUEUE_CAPACITY);
+        let task = tokio::spawn(async move {
+            while let Some(notification) = rx.recv().await {
+                for webhook in &webhooks {
+                    let sent = FINDING_RETRY
+                        .run(|| send_webhook(&client, webhook, &notification))
+                        .await;
+                    let host = webhook_host(&webhook.url);
+                    match sent {
+                        Ok(()) => debug!("📣 Sent finding on {} to {}", notification.domain, host),
+                        Err(e) => warn!(
+                            "⚠️ Failed to send finding on {} to webhook at {}: {:#}",
+                            notification.domain, host, e
+                        ),
+                    }
+                }
+                for email in emails.iter().filter(|email| email.alerts_on(&notification)) {
+                    let sent = FINDING_RETRY.run(|| email.send_alert(&notification)).await;
+                    match sent {
+                        Ok(()) => debug!("📧 Emailed finding on {}", notification.domain),
+                        Err(e) => warn!(
+                            "⚠️ Failed to email finding on {}: {:#}",
+                            notification.domain, e
+                        ),
+                    }
+                }
+                for incident in incidents
+                    .iter()
+                    .filter(|incident| incident.alerts_on(&notification))
+                {
+                    let sent = FINDING_RETRY
+                        .run(|| incident.send_alert(&client, &notification))
+                        .await;
+                    match sent {
+                        Ok(()) => debug!("🚨 Raised an alert on {}", notification.domain),
+                        Err(e) => warn!(
+                            "⚠️ Failed to raise an alert on {}: {:#}",
+                            notification.domain, e
+                        ),
+                    }
+                }
+            }
+        });
+
+        Some(Self {
+            tx: Mutex::new(Some(tx)),
+            task: Mutex::new(Some(task)),
+            dropped: AtomicUsize::new(0),
+        })
+    }
+
+    /// Queue a finding for delivery, dropping it if the queue is full or closed
+    pub fn notify(&self, notification: FindingNotification) {
+        let tx = self.tx.lock().unwrap();
+        let queued = tx
+            .as_ref()
+            .is_some_and(|tx| tx.try_send(notification).is_ok());
+        if !queued && self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+            warn!("⚠️ Finding notifications are falling behind; dropping findings until they catch up");
+        }
+    }
+
+    /// Findings dropped because the queue was full
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Stop taking findings and wait for the queued ones to be delivered
+    pub async fn finish(&self) {
+        drop(self.tx.lock().unwrap().take());
+        let task = self.task.lock().unwrap().take();
+        if let Some(task) = task {
+            let _ = task.await;
+        }
+        if self.dropped() > 0 {
+            warn!(
+                "⚠️ {} findings were not sent to the finding webhooks, emails or incident services",
+                self.dropped()
+            );
+        }
+    }
+}
+
+fn webhook_host(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "<invalid url>".to_string())
+}
@@ -0,0 +1,241 @@
+//! Export of spans and metrics to an OpenTelemetry collector over OTLP/HTTP, such as
+//! Jaeger, Grafana Tempo or the OpenTelemetry Collector, so a scan's throughput can be
+//! broken down by phase
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::pipeline::QueueSnapshot;
+use crate::rules::Severity;
+
+/// Whether spans are being exported; they aren't created otherwise, so the console
+/// log isn't cluttered with span names
+static EXPORTING: AtomicBool = AtomicBool::new(false);
+
+/// Whether spans and metrics are being exported
+pub fn is_exporting() -> bool {
+    EXPORTING.load(Ordering::Relaxed)
+}
+
+/// An info-level span when spans are being exported, and a disabled span otherwise
+macro_rules! span {
+    ($name:expr $(, $($fields:tt)*)?) => {
+        if $crate::otel::is_exporting() {
+            tracing::info_span!($name $(, $($fields)*)?)
+        } else {
+            tracing::Span::none()
+        }
+    };
+}
+pub(crate) use span;
+
+/// URL of a signal's OTLP/HTTP endpoint under a collector's base URL, such as
+/// `http://localhost:4318/v1/traces`
+#[cfg_attr(not(feature = "otel"), allow(dead_code))]
+pub fn signal_url(endpoint: &str, signal: &str) -> String {
+    format!("{}/v1/{}", endpoint.trim_end_matches('/'), signal)
+}
+
+/// Count a response received from a target, by status class (`2xx`, `4xx`, ...)
+pub fn record_response(status: u16) {
+    #[cfg(feature = "otel")]
+    if let Some(instruments) = exporter::INSTRUMENTS.get() {
+        let class = format!("{}xx", status / 100);
+        instruments
+            .responses
+            .add(1, &[opentelemetry::KeyValue::new("status_class", class)]);
+    }
+    #[cfg(not(feature = "otel"))]
+    let _ = status;
+}
+
+/// Count a detected finding, by severity
+pub fn record_finding(severity: Option<&Severity>) {
+    #[cfg(feature = "otel")]
+    if let Some(instruments) = exporter::INSTRUMENTS.get() {
+        let severity = severity.map_or_else(|| "unknown".to_string(), Severity::to_string);
+        instruments
+            .findings
+            .add(1, &[opentelemetry::KeyValue::new("severity", severity)]);
+    }
+    #[cfg(not(feature = "otel"))]
+    let _ = severity;
+}
+
+/// Count a domain whose scan completed
+pub fn record_domain() {
+    #[cfg(feature = "otel")]
+    if let Some(instruments) = exporter::INSTRUMENTS.get() {
+        instruments.domains.add(1, &[]);
+    }
+}
+
+/// Record how many items wait in each pipeline queue, by queue
+pub fn record_queue_depths(queues: &QueueSnapshot) {
+    #[cfg(feature = "otel")]
+    if let Some(instruments) = exporter::INSTRUMENTS.get() {
+        let depths = [
+            ("domains", queues.domains),
+            ("checks", queues.checks),
+            ("pages", queues.pages),
+            ("outcomes", queues.outcomes),
+        ];
+        for (queue, depth) in depths {
+            instruments.queue_depth.record(
+                depth as u64,
+                &[opentelemetry::KeyValue::new("queue", queue)],
+            );
+        }
+    }
+    #[cfg(not(feature = "otel"))]
+    let _ = queues;
+}
+
+/// Exporters of a running process, flushed by [`Telemetry::shutdown`]
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    exporter: exporter::Exporter,
+}
+
+impl Telemetry {
+    /// Start exporting spans and metrics to the collector at `endpoint`, such as
+    /// `http://localhost:4318`.
+    ///
+    /// Has to be called outside the async runtime: the exporters send from threads of
+    /// their own with a blocking HTTP client.
+    pub fn start(endpoint: &str) -> Result<Self> {
+        #[cfg(feature = "otel")]
+        {
+            let exporter = exporter::Exporter::start(endpoint)?;
+            EXPORTING.store(true, Ordering::Relaxed);
+            tracing::info!("📡 Exporting traces and metrics to {}", endpoint);
+            Ok(Self { exporter })
+        }
+        #[cfg(not(feature = "otel"))]
+        {
+            let _ = endpoint;
+            anyhow::bail!(
+                "This build of fatt has no OpenTelemetry support (enable the `otel` feature)"
+            )
+        }
+    }
+
+    /// Stop exporting, sending what's still buffered. Also has to be called outside
+    /// the async runtime.
+    pub fn shutdown(self) {
+        EXPORTING.store(false, Ordering::Relaxed);
+        #[cfg(feature = "otel")]
+        self.exporter.shutdown();
+    }
+}
+
+#[cfg(feature = "otel")]
+mod exporter {
+    use anyhow::{Context, Result};
+    use opentelemetry::metrics::{Counter, Gauge, MeterProvider};
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use std::sync::OnceLock;
+    use std::time::Duration;
+    use tracing::warn;
+
+    use super::signal_url;
+    use crate::logger;
+
+    /// How often metrics are sent to the collector
+    const METRICS_INTERVAL: Duration = Duration::from_secs(10);
+
+    /// Metrics recorded while exporting
+    pub(super) struct Instruments {
+        pub responses: Counter<u64>,
+        pub findings: Counter<u64>,
+        pub domains: Counter<u64>,
+        pub queue_depth: Gauge<u64>,
+    }
+
+    pub(super) static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+
+    pub(super) struct Exporter {
+        tracer_provider: SdkTracerProvider,
+        meter_provider: SdkMeterProvider,
+    }
+
+    impl Exporter {
+        pub fn start(endpoint: &str) -> Result<Self> {
+            url::Url::parse(endpoint)
+                .context(format!("Invalid OpenTelemetry endpoint: {}", endpoint))?;
+            let resource = Resource::builder()
+                .with_service_name("fatt")
+                .with_attribute(opentelemetry::KeyValue::new(
+                    "service.version",
+                    env!("CARGO_PKG_VERSION"),
+                ))
+                .build();
+
+            let spans = SpanExporter::builder()
+                .with_http()
+                .with_endpoint(signal_url(endpoint, "traces"))
+                .build()
+                .context("Failed to create the OpenTelemetry span exporter")?;
+            let tracer_provider = SdkTracerProvider::builder()
+                .with_batch_exporter(spans)
+                .with_resource(resource.clone())
+                .build();
+
+            let metrics = MetricExporter::builder()
+                .with_http()
+                .with_endpoint(signal_url(endpoint, "metrics"))
+                .build()
+                .context("Failed to create the OpenTelemetry metric exporter")?;
+            let meter_provider = SdkMeterProvider::builder()
+                .with_reader(
+                    PeriodicReader::builder(metrics)
+                        .with_interval(METRICS_INTERVAL)
+                        .build(),
+                )
+                .with_resource(resource)
+                .build();
+
+            let meter = meter_provider.meter("fatt");
+            let _ = INSTRUMENTS.set(Instruments {
+                responses: meter
+                    .u64_counter("fatt.http.responses")
+                    .with_description("Responses received from targets")
+                    .build(),
+                findings: meter
+                    .u64_counter("fatt.findings")
+                    .with_description("Findings detected")
+                    .build(),
+                domains: meter
+                    .u64_counter("fatt.domains")
+                    .with_description("Domains scanned")
+                    .build(),
+                queue_depth: meter
+                    .u64_gauge("fatt.pipeline.queue_depth")
+                    .with_description("Items waiting in a pipeline queue")
+                    .build(),
+            });
+
+            let layer = tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("fatt"));
+            logger::set_telemetry_layer(Some(Box::new(layer)))?;
+
+            Ok(Self {
+                tracer_provider,
+                meter_provider,
+            })
+        }
+
+        pub fn shutdown(self) {
+            let _ = logger::set_telemetry_layer(None);
+            if let Err(e) = self.tracer_provider.shutdown() {
+                warn!("⚠️ Failed to export the last spans: {}", e);
+            }
+            if let Err(e) = self.meter_provider.shutdown() {
+                warn!("⚠️ Failed to export the last metrics: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,1056 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use postgres::{Client, Row};
+use rustls::crypto::ring;
+use rustls::{ClientConfig, RootCertStore};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use tokio_postgres_rustls::MakeRustlsConnect;
+use tracing::debug;
+
+use crate::cert_inventory::SiteCertificate;
+use crate::db::{
+    DomainScan, FalsePositive, Finding, FindingNote, ListQuery, Note, PurgeFilter, ScanSession,
+    TimeRange,
+};
+use crate::migrate::{self, MigrationReport};
+use crate::profile::ScanProfile;
+use crate::risk::RiskReport;
+use crate::rules::{RuleSet, RuleSnapshot};
+use crate::store::{redact_url, FindingDetails, PendingFinding, ResultStore};
+
+/// Key of the advisory lock held while migrating the schema, so workers starting
+/// together don't race to apply the same migrations
+const SCHEMA_LOCK: i64 = 0x6661_7474;
+
+/// Migrations of the PostgreSQL schema, oldest first, numbered like the SQLite
+/// ones in `migrate::MIGRATIONS`
+const MIGRATIONS: &[(u32, &str, &str)] = &[
+    (1, "initial schema", INITIAL_SCHEMA),
+    (
+        2,
+        "finding evidence",
+        "ALTER TABLE findings ADD COLUMN IF NOT EXISTS evidence TEXT;
+         ALTER TABLE scan_findings ADD COLUMN IF NOT EXISTS evidence TEXT;",
+    ),
+    (
+        3,
+        "scan profile",
+        "ALTER TABLE scans ADD COLUMN IF NOT EXISTS profile TEXT;",
+    ),
+    (
+        4,
+        "domain scans",
+        "CREATE TABLE IF NOT EXISTS domain_scans (
+            scan_id TEXT,
+            domain TEXT,
+            scanned_at TIMESTAMPTZ NOT NULL,
+            ips TEXT,
+            technologies TEXT,
+            error TEXT,
+            PRIMARY KEY(scan_id, domain)
+        );
+        CREATE INDEX IF NOT EXISTS idx_domain_scans_domain ON domain_scans (domain);",
+    ),
+    (
+        5,
+        "projects",
+        "ALTER TABLE scans ADD COLUMN IF NOT EXISTS project TEXT;
+         ALTER TABLE findings ADD COLUMN IF NOT EXISTS project TEXT;
+         ALTER TABLE scan_findings ADD COLUMN IF NOT EXISTS project TEXT;
+         CREATE INDEX IF NOT EXISTS idx_findings_project ON findings (project);",
+    ),
+    (
+        6,
+        "finding notes",
+        "CREATE TABLE IF NOT EXISTS finding_notes (
+            id BIGSERIAL PRIMARY KEY,
+            domain TEXT NOT NULL,
+            rule_name TEXT NOT NULL,
+            note TEXT NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_finding_notes_finding ON finding_notes (domain, rule_name);",
+    ),
+    (
+        7,
+        "ruleset digests",
+        "ALTER TABLE scans ADD COLUMN IF NOT EXISTS ruleset_digest TEXT;
+         ALTER TABLE findings ADD COLUMN IF NOT EXISTS ruleset_digest TEXT;
+         ALTER TABLE scan_findings ADD COLUMN IF NOT EXISTS ruleset_digest TEXT;",
+    ),
+    (
+        8,
+        "false positives",
+        "CREATE TABLE IF NOT EXISTS false_positives (
+            domain TEXT NOT NULL,
+            rule_name TEXT NOT NULL,
+            skip_scans BOOLEAN NOT NULL DEFAULT FALSE,
+            marked_at TIMESTAMPTZ NOT NULL,
+            PRIMARY KEY (domain, rule_name)
+        );",
+    ),
+    (
+        9,
+        "certificates",
+        "CREATE TABLE IF NOT EXISTS certificates (
+            host TEXT PRIMARY KEY,
+            subject TEXT,
+            issuer TEXT,
+            sans TEXT,
+            serial TEXT,
+            not_before TIMESTAMPTZ NOT NULL,
+            not_after TIMESTAMPTZ NOT NULL,
+            seen_at TIMESTAMPTZ NOT NULL
+        );",
+    ),
+    (
+        10,
+        "note authors",
+        "ALTER TABLE finding_notes ADD COLUMN IF NOT EXISTS author TEXT;",
+    ),
+];
+
+const INITIAL_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS findings (
+        id BIGSERIAL PRIMARY KEY,
+        domain TEXT NOT NULL,
+        rule_name TEXT NOT NULL,
+        matched_path TEXT NOT NULL,
+        detected BOOLEAN NOT NULL,
+        scanned_at TIMESTAMPTZ NOT NULL,
+        user_agent TEXT,
+        redirect_chain TEXT,
+        tag TEXT,
+        status INTEGER,
+        ruleset_version BIGINT,
+        UNIQUE(domain, rule_name)
+    );
+    CREATE INDEX IF NOT EXISTS idx_findings_domain ON findings (domain);
+    CREATE INDEX IF NOT EXISTS idx_findings_rule ON findings (rule_name);
+
+    CREATE TABLE IF NOT EXISTS scans (
+        id TEXT PRIMARY KEY,
+        started_at TIMESTAMPTZ NOT NULL,
+        finished_at TIMESTAMPTZ,
+        domains BIGINT NOT NULL DEFAULT 0,
+        findings BIGINT NOT NULL DEFAULT 0,
+        risk_score DOUBLE PRECISION NOT NULL DEFAULT 0,
+        incomplete BOOLEAN NOT NULL DEFAULT FALSE
+    );
+
+    CREATE TABLE IF NOT EXISTS domain_risk (
+        scan_id TEXT,
+        domain TEXT,
+        risk_score DOUBLE PRECISION NOT NULL,
+        findings BIGINT NOT NULL,
+        PRIMARY KEY(scan_id, domain)
+    );
+
+    CREATE TABLE IF NOT EXISTS rule_stats (
+        scan_id TEXT,
+        rule_name TEXT,
+        detected BIGINT NOT NULL,
+        PRIMARY KEY(scan_id, rule_name)
+    );
+
+    CREATE TABLE IF NOT EXISTS ruleset_versions (
+        scan_id TEXT,
+        version BIGINT,
+        loaded_at TIMESTAMPTZ NOT NULL,
+        rules BIGINT NOT NULL,
+        digest TEXT NOT NULL,
+        PRIMARY KEY(scan_id, version)
+    );
+
+    CREATE TABLE IF NOT EXISTS scan_findings (
+        scan_id TEXT,
+        domain TEXT,
+        rule_name TEXT,
+        matched_path TEXT NOT NULL,
+        scanned_at TIMESTAMPTZ NOT NULL,
+        tag TEXT,
+        status INTEGER,
+        ruleset_version BIGINT,
+        PRIMARY KEY(scan_id, domain, rule_name)
+    );
+";
+
+const FINDING_COLUMNS: &str =
+    "id, domain, rule_name, matched_path, detected, scanned_at, redirect_chain, tag, status, ruleset_version, evidence, project, ruleset_digest";
+
+fn finding_from_row(row: &Row) -> Finding {
+    let redirect_chain: Option<String> = row.get(6);
+    let evidence: Option<String> = row.get(10);
+
+    Finding {
+        id: row.get(0),
+        domain: row.get(1),
+        rule_name: row.get(2),
+        matched_path: row.get(3),
+        detected: row.get(4),
+        scanned_at: row.get(5),
+        redirect_chain: redirect_chain
+            .and_then(|chain| serde_json::from_str(&chain).ok())
+            .unwrap_or_default(),
+        tag: row.get(7),
+        status: row.get::<_, Option<i32>>(8).map(|status| status as u16),
+        ruleset_version: row.get::<_, Option<i64>>(9).map(|version| version as u32),
+        evidence: evidence.and_then(|evidence| serde_json::from_str(&evidence).ok()),
+        project: row.get(11),
+        ruleset_digest: row.get(12),
+        remediation: None,
+        notes: Vec::new(),
+    }
+}
+
+fn scan_session_from_row(row: &Row) -> ScanSession {
+    ScanSession {
+        id: row.get(0),
+        started_at: row.get(1),
+        finished_at: row.get(2),
+        domains: row.get::<_, i64>(3) as usize,
+        findings: row.get::<_, i64>(4) as usize,
+        risk_score: row.get(5),
+        incomplete: row.get(6),
+        profile: row
+            .get::<_, Option<String>>(7)
+            .and_then(|profile| profile.parse().ok()),
+        project: row.get(8),
+        ruleset_digest: row.get(9),
+    }
+}
+
+/// Connect to the server, over TLS when it offers it
+fn connect(url: &str) -> Result<Client> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let tls = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .context("Failed to configure TLS")?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    Client::connect(url, MakeRustlsConnect::new(tls)).context(format!(
+        "Failed to connect to PostgreSQL: {}",
+        redact_url(url)
+    ))
+}
+
+/// Apply the migrations the database is missing
+fn migrate_schema(client: &mut Client, url: &str) -> Result<MigrationReport> {
+    let mut tx = client.transaction()?;
+    tx.execute("SELECT pg_advisory_xact_lock($1)", &[&SCHEMA_LOCK])?;
+    tx.batch_execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL
+        )",
+    )
+    .context("Failed to create schema_migrations table")?;
+
+    let version: Option<i64> = tx
+        .query_one("SELECT MAX(version) FROM schema_migrations", &[])?
+        .get(0);
+    let version = version.unwrap_or(0) as u32;
+    if version > migrate::latest_version() {
+        return Err(migrate::newer_schema(&redact_url(url), version));
+    }
+
+    let mut report = MigrationReport {
+        version,
+        applied: Vec::new(),
+    };
+    for &(migration, name, sql) in MIGRATIONS.iter().filter(|(v, _, _)| *v > version) {
+        tx.batch_execute(sql).context(format!(
+            "Failed to apply migration {} ({})",
+            migration, name
+        ))?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES ($1, $2, $3)",
+            &[&(migration as i64), &name, &Utc::now()],
+        )?;
+        report.version = migration;
+        report.applied.push((migration, name));
+    }
+    tx.commit()?;
+
+    Ok(report)
+}
+
+/// Bring the database at `url` up to date with the schema
+pub fn migrate_database(url: &str) -> Result<MigrationReport> {
+    let url = url.to_string();
+
+    // The blocking client can't run on a runtime's thread
+    thread::spawn(move || {
+        let mut client = connect(&url)?;
+        migrate_schema(&mut client, &url)
+    })
+    .join()
+    .map_err(|_| anyhow::anyhow!("PostgreSQL migration thread panicked"))?
+}
+
+type Job = Box<dyn FnOnce(&mut Client) + Send>;
+
+/// Result store in a PostgreSQL database, which many workers can share.
+///
+/// The blocking client runs its own runtime, which can't be entered from a scan's
+/// tasks, so the connection lives on a dedicated thread that runs each call in turn.
+pub struct PgStore {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl PgStore {
+    /// Connect to the database at `url`, migrating its schema if needed
+    pub fn open(url: &str) -> Result<Self> {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let (ready_tx, ready) = mpsc::sync_channel(1);
+        let url = url.to_string();
+
+        thread::Builder::new()
+            .name("fatt-postgres".to_string())
+            .spawn(move || {
+                let opened = connect(&url).and_then(|mut client| {
+                    let report = migrate_schema(&mut client, &url)?;
+                    for (version, name) in &report.applied {
+                        debug!("Applied PostgreSQL migration {}: {}", version, name);
+                    }
+                    Ok(client)
+                });
+                let mut client = match opened {
+                    Ok(client) => client,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let _ = ready_tx.send(Ok(()));
+
+                for job in queue {
+                    job(&mut client);
+                }
+            })
+            .context("Failed to start PostgreSQL connection thread")?;
+
+        ready
+            .recv()
+            .context("PostgreSQL connection thread stopped")??;
+        debug!("PostgreSQL database initialized");
+
+        Ok(Self { jobs })
+    }
+
+    /// Run `f` with the connection and wait for its result
+    fn call<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Client) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let (reply_tx, reply) = mpsc::sync_channel(1);
+        self.jobs
+            .send(Box::new(move |client| {
+                let _ = reply_tx.send(f(client));
+            }))
+            .map_err(|_| anyhow::anyhow!("PostgreSQL connection closed"))?;
+
+        reply.recv().context("PostgreSQL connection closed")?
+    }
+
+    /// Findings, newest first, optionally filtered by domain and rule name pattern and
+    /// by when they were last scanned
+    #[allow(dead_code)]
+    pub fn query_findings(
+        &self,
+        domain_pattern: Option<&str>,
+        rule_pattern: Option<&str>,
+        range: &TimeRange,
+        limit: usize,
+    ) -> Result<Vec<Finding>> {
+        let query = ListQuery {
+            domain_pattern,
+            rule_pattern,
+            range: *range,
+            limit,
+            ..Default::default()
+        };
+        self.select_findings(&query, "scanned_at DESC".to_string())
+    }
+
+    /// Findings for `results list`, sorted and paged by the database unless they're
+    /// sorted by severity, which is looked up in `ruleset`
+    pub fn list_findings(
+        &self,
+        query: &ListQuery,
+        ruleset: Option<&RuleSet>,
+    ) -> Result<Vec<Finding>> {
+        query.check(ruleset)?;
+
+        match query.order_by() {
+            Some(order_by) => self.select_findings(query, order_by),
+            None => {
+                let everything = ListQuery {
+                    offset: 0,
+                    limit: usize::MAX,
+                    ..query.clone()
+                };
+                let findings = self.select_findings(&everything, "scanned_at DESC".to_string())?;
+                Ok(query.page(findings, ruleset))
+            }
+        }
+    }
+
+    fn select_findings(&self, query: &ListQuery, order_by: String) -> Result<Vec<Finding>> {
+        let domain_pattern = query.domain_pattern.map(|pattern| format!("%{}%", pattern));
+        let rule_pattern = query.rule_pattern.map(|pattern| format!("%{}%", pattern));
+        let range = query.range;
+        let detected_only = query.detected_only;
+        let project = query.project.map(str::to_string);
+        let limit = query.limit.min(i64::MAX as usize) as i64;
+        let offset = query.offset.min(i64::MAX as usize) as i64;
+
+        self.call(move |client| {
+            let rows = client
+                .query(
+                    &format!(
+                        "SELECT {} FROM findings
+                         WHERE ($1::TEXT IS NULL OR domain ILIKE $1)
+                           AND ($2::TEXT IS NULL OR rule_name ILIKE $2)
+                           AND ($3::TIMESTAMPTZ IS NULL OR scanned_at >= $3)
+                           AND ($4::TIMESTAMPTZ IS NULL OR scanned_at <= $4)
+                           AND (NOT $7 OR detected)
+                           AND ($8::TEXT IS NULL OR project = $8)
+                         ORDER BY {} LIMIT $5 OFFSET $6",
+                        FINDING_COLUMNS, order_by
+                    ),
+                    &[
+                        &domain_pattern,
+                        &rule_pattern,
+                        &range.since,
+                        &range.until,
+                        &limit,
+                        &offset,
+                        &detected_only,
+                        &project,
+                    ],
+                )
+                .context("Failed to collect findings")?;
+
+            Ok(rows.iter().map(finding_from_row).collect())
+        })
+    }
+
+    /// The most recent scan sessions, newest first
+    pub fn scan_sessions(&self, limit: usize) -> Result<Vec<ScanSession>> {
+        self.call(move |client| {
+            let rows = client
+                .query(
+                    "SELECT id, started_at, finished_at, domains, findings, risk_score, incomplete, profile, project, ruleset_digest
+                     FROM scans
+                     ORDER BY started_at DESC
+                     LIMIT $1",
+                    &[&(limit.min(i64::MAX as usize) as i64)],
+                )
+                .context("Failed to collect scan sessions")?;
+
+            Ok(rows.iter().map(scan_session_from_row).collect())
+        })
+    }
+}
+
+impl ResultStore for PgStore {
+    fn start_scan(&mut self, scan_id: &str, started_at: DateTime<Utc>) -> Result<()> {
+        let scan_id = scan_id.to_string();
+        self.call(move |client| {
+            client
+                .execute(
+                    "INSERT INTO scans (id, started_at) VALUES ($1, $2)",
+                    &[&scan_id, &started_at],
+                )
+                .context("Failed to record scan session")?;
+            Ok(())
+        })
+    }
+
+    fn record_ruleset(&mut self, scan_id: &str, rules: &RuleSnapshot) -> Result<()> {
+        let scan_id = scan_id.to_string();
+        let version = rules.version as i64;
+        let loaded_at = rules.loaded_at;
+        let count = rules.ruleset.rules.len() as i64;
+        let digest = rules.digest.clone();
+        let initial = rules.version == 1;
+        self.call(move |client| {
+            client
+                .execute(
+                    "INSERT INTO ruleset_versions (scan_id, version, loaded_at, rules, digest)
+                     VALUES ($1, $2, $3, $4, $5)
+                     ON CONFLICT (scan_id, version) DO UPDATE SET
+                        loaded_at = excluded.loaded_at,
+                        rules = excluded.rules,
+                        digest = excluded.digest",
+                    &[&scan_id, &version, &loaded_at, &count, &digest],
+                )
+                .context("Failed to record ruleset version")?;
+            if initial {
+                client
+                    .execute(
+                        "UPDATE scans SET ruleset_digest = $1 WHERE id = $2",
+                        &[&digest, &scan_id],
+                    )
+                    .context("Failed to record scan ruleset digest")?;
+            }
+            Ok(())
+        })
+    }
+
+    fn record_profile(&mut self, scan_id: &str, profile: ScanProfile) -> Result<()> {
+        let scan_id = scan_id.to_string();
+        let profile = profile.to_string();
+        self.call(move |client| {
+            client
+                .execute(
+                    "UPDATE scans SET profile = $1 WHERE id = $2",
+                    &[&profile, &scan_id],
+                )
+                .context("Failed to record scan profile")?;
+            Ok(())
+        })
+    }
+
+    fn record_project(&mut self, scan_id: &str, project: &str) -> Result<()> {
+        let scan_id = scan_id.to_string();
+        let project = project.to_string();
+        self.call(move |client| {
+            client
+                .execute(
+                    "UPDATE scans SET project = $1 WHERE id = $2",
+                    &[&project, &scan_id],
+                )
+                .context("Failed to record scan project")?;
+            Ok(())
+        })
+    }
+
+    fn record_finding(
+        &mut self,
+        domain: &str,
+        rule_name: &str,
+        matched_path: &str,
+        detected: bool,
+        details: &FindingDetails,
+    ) -> Result<()> {
+        self.record_findings(&[PendingFinding::new(
+            domain,
+            rule_name,
+            matched_path,
+            detected,
+            details,
+        )])
+    }
+
+    fn record_findings(&mut self, batch: &[PendingFinding]) -> Result<()> {
+        let batch = batch.to_vec();
+        let scanned_at = Utc::now();
+
+        self.call(move |client| {
+            let mut tx = client
+                .transaction()
+                .context("Failed to start a transaction")?;
+            let insert = tx
+                .prepare(
+                    "INSERT INTO findings
+                        (domain, rule_name, matched_path, detected, scanned_at, user_agent, redirect_chain, tag, status, ruleset_version, evidence, project, ruleset_digest)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                     ON CONFLICT (domain, rule_name) DO UPDATE SET
+                        matched_path = excluded.matched_path,
+                        detected = excluded.detected,
+                        scanned_at = excluded.scanned_at,
+                        user_agent = COALESCE(excluded.user_agent, findings.user_agent),
+                        redirect_chain = excluded.redirect_chain,
+                        tag = excluded.tag,
+                        status = excluded.status,
+                        ruleset_version = excluded.ruleset_version,
+                        evidence = excluded.evidence,
+                        project = excluded.project,
+                        ruleset_digest = excluded.ruleset_digest",
+                )
+                .context("Failed to insert finding")?;
+
+            for finding in &batch {
+                let redirect_chain = if finding.redirect_chain.is_empty() {
+                    None
+                } else {
+                    Some(serde_json::to_string(&finding.redirect_chain)?)
+                };
+                let status = finding.status.map(i32::from);
+                let ruleset_version = finding.ruleset_version.map(i64::from);
+                let evidence = finding
+                    .evidence
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?;
+                tx.execute(
+                    &insert,
+                    &[
+                        &finding.domain,
+                        &finding.rule_name,
+                        &finding.matched_path,
+                        &finding.detected,
+                        &scanned_at,
+                        &finding.user_agent,
+                        &redirect_chain,
+                        &finding.tag,
+                        &status,
+                        &ruleset_version,
+                        &evidence,
+                        &finding.project,
+                        &finding.ruleset_digest,
+                    ],
+                )
+                .context("Failed to insert finding")?;
+            }
+            tx.commit().context("Failed to commit findings")?;
+
+            Ok(())
+        })
+    }
+
+    fn record_domain(&mut self, scan: &DomainScan) -> Result<()> {
+        let scan = scan.clone();
+        let ips = serde_json::to_string(&scan.ips)?;
+        let technologies = serde_json::to_string(&scan.technologies)?;
+        self.call(move |client| {
+            client
+                .execute(
+                    "INSERT INTO domain_scans (scan_id, domain, scanned_at, ips, technologies, error)
+                     VALUES ($1, $2, $3, $4, $5, $6)
+                     ON CONFLICT (scan_id, domain) DO UPDATE SET
+                        scanned_at = excluded.scanned_at,
+                        ips = excluded.ips,
+                        technologies = excluded.technologies,
+                        error = excluded.error",
+                    &[
+                        &scan.scan_id,
+                        &scan.domain,
+                        &scan.scanned_at,
+                        &ips,
+                        &technologies,
+                        &scan.error,
+                    ],
+                )
+                .context("Failed to record domain scan")?;
+
+            if let Some(certificate) = &scan.certificate {
+                let sans = serde_json::to_string(&certificate.sans)?;
+                client
+                    .execute(
+                        "INSERT INTO certificates (host, subject, issuer, sans, serial, not_before, not_after, seen_at)
+                         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                         ON CONFLICT (host) DO UPDATE SET
+                            subject = excluded.subject,
+                            issuer = excluded.issuer,
+                            sans = excluded.sans,
+                            serial = excluded.serial,
+                            not_before = excluded.not_before,
+                            not_after = excluded.not_after,
+                            seen_at = excluded.seen_at",
+                        &[
+                            &certificate.host,
+                            &certificate.subject,
+                            &certificate.issuer,
+                            &sans,
+                            &certificate.serial,
+                            &certificate.not_before,
+                            &certificate.not_after,
+                            &certificate.seen_at,
+                        ],
+                    )
+                    .context("Failed to record certificate")?;
+            }
+            Ok(())
+        })
+    }
+
+    fn detected_since(&self, since: DateTime<Utc>) -> Result<Vec<Finding>> {
+        self.call(move |client| {
+            let rows = client
+                .query(
+                    &format!(
+                        "SELECT {} FROM findings WHERE detected AND scanned_at >= $1 ORDER BY domain, rule_name",
+                        FINDING_COLUMNS
+                    ),
+                    &[&since],
+                )
+                .context("Failed to collect detected findings")?;
+
+            Ok(rows.iter().map(finding_from_row).collect())
+        })
+    }
+
+    fn revalidatable(&self) -> Result<Vec<Finding>> {
+        self.call(|client| {
+            let rows = client
+                .query(
+                    &format!(
+                        "SELECT {} FROM findings WHERE detected AND evidence::jsonb ? 'validators' ORDER BY domain, rule_name",
+                        FINDING_COLUMNS
+                    ),
+                    &[],
+                )
+                .context("Failed to collect revalidatable findings")?;
+
+            Ok(rows.iter().map(finding_from_row).collect())
+        })
+    }
+
+    fn finish_scan(&mut self, scan_id: &str, domains: usize, risk: &RiskReport) -> Result<()> {
+        let scan_id = scan_id.to_string();
+        let findings: usize = risk.per_domain.values().map(|d| d.findings).sum();
+        let total = risk.total;
+        let per_domain: Vec<(String, f64, i64)> = risk
+            .per_domain
+            .iter()
+            .map(|(domain, risk)| (domain.clone(), risk.score, risk.findings as i64))
+            .collect();
+
+        self.call(move |client| {
+            let mut tx = client.transaction()?;
+            tx.execute(
+                "UPDATE scans
+                 SET finished_at = now(), domains = $1, findings = $2, risk_score = $3
+                 WHERE id = $4",
+                &[&(domains as i64), &(findings as i64), &total, &scan_id],
+            )
+            .context("Failed to update scan session")?;
+
+            let stmt = tx.prepare(
+                "INSERT INTO domain_risk (scan_id, domain, risk_score, findings)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (scan_id, domain) DO UPDATE SET
+                    risk_score = excluded.risk_score,
+                    findings = excluded.findings",
+            )?;
+            for (domain, score, findings) in &per_domain {
+                tx.execute(&stmt, &[&scan_id, domain, score, findings])
+                    .context("Failed to record domain risk score")?;
+            }
+            tx.commit()?;
+
+            Ok(())
+        })
+    }
+
+    fn mark_incomplete(&mut self, scan_id: &str) -> Result<()> {
+        let scan_id = scan_id.to_string();
+        self.call(move |client| {
+            client
+                .execute(
+                    "UPDATE scans SET incomplete = TRUE WHERE id = $1",
+                    &[&scan_id],
+                )
+                .context("Failed to mark scan session incomplete")?;
+            Ok(())
+        })
+    }
+
+    fn record_scan_findings(&mut self, scan_id: &str, findings: &[Finding]) -> Result<()> {
+        let scan_id = scan_id.to_string();
+        let findings: Vec<Finding> = findings
+            .iter()
+            .filter(|finding| finding.detected)
+            .cloned()
+            .collect();
+
+        self.call(move |client| {
+            let mut tx = client.transaction()?;
+            let stmt = tx.prepare(
+                "INSERT INTO scan_findings
+                    (scan_id, domain, rule_name, matched_path, scanned_at, tag, status, ruleset_version, evidence, project, ruleset_digest)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                 ON CONFLICT (scan_id, domain, rule_name) DO UPDATE SET
+                    matched_path = excluded.matched_path,
+                    scanned_at = excluded.scanned_at,
+                    tag = excluded.tag,
+                    status = excluded.status,
+                    ruleset_version = excluded.ruleset_version,
+                    evidence = excluded.evidence,
+                    project = excluded.project,
+                    ruleset_digest = excluded.ruleset_digest",
+            )?;
+            for finding in &findings {
+                let evidence = finding
+                    .evidence
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?;
+                tx.execute(
+                    &stmt,
+                    &[
+                        &scan_id,
+                        &finding.domain,
+                        &finding.rule_name,
+                        &finding.matched_path,
+                        &finding.scanned_at,
+                        &finding.tag,
+                        &finding.status.map(i32::from),
+                        &finding.ruleset_version.map(i64::from),
+                        &evidence,
+                        &finding.project,
+                        &finding.ruleset_digest,
+                    ],
+                )
+                .context("Failed to record scan findings")?;
+            }
+            tx.commit().context("Failed to record scan findings")?;
+
+            Ok(())
+        })
+    }
+
+    fn scan_findings(&self, scan_id: &str) -> Result<Vec<Finding>> {
+        let scan_id = scan_id.to_string();
+        self.call(move |client| {
+            let Some(scan) = client.query_opt("SELECT findings FROM scans WHERE id = $1", &[&scan_id])?
+            else {
+                anyhow::bail!("Unknown scan: {}", scan_id);
+            };
+            let recorded: i64 = scan.get(0);
+
+            let rows = client
+                .query(
+                    "SELECT row_number() OVER (ORDER BY domain, rule_name), domain, rule_name, matched_path, TRUE, scanned_at, NULL::TEXT, tag, status, ruleset_version, evidence, project, ruleset_digest
+                     FROM scan_findings
+                     WHERE scan_id = $1
+                     ORDER BY domain, rule_name",
+                    &[&scan_id],
+                )
+                .context("Failed to collect scan findings")?;
+            let findings: Vec<Finding> = rows.iter().map(finding_from_row).collect();
+
+            if findings.is_empty() && recorded > 0 {
+                anyhow::bail!("Scan {} has no findings kept to compare", scan_id);
+            }
+
+            Ok(findings)
+        })
+    }
+
+    fn record_rule_counts(
+        &mut self,
+        scan_id: &str,
+        counts: &BTreeMap<String, usize>,
+    ) -> Result<()> {
+        let scan_id = scan_id.to_string();
+        let counts = counts.clone();
+        self.call(move |client| {
+            let mut tx = client.transaction()?;
+            let stmt = tx.prepare(
+                "INSERT INTO rule_stats (scan_id, rule_name, detected) VALUES ($1, $2, $3)
+                 ON CONFLICT (scan_id, rule_name) DO UPDATE SET detected = excluded.detected",
+            )?;
+            for (rule_name, detected) in &counts {
+                tx.execute(&stmt, &[&scan_id, rule_name, &(*detected as i64)])
+                    .context("Failed to record rule counts")?;
+            }
+            tx.commit()?;
+
+            Ok(())
+        })
+    }
+
+    fn rule_baseline(&self, scan_id: &str, scans: usize) -> Result<(usize, HashMap<String, f64>)> {
+        let scan_id = scan_id.to_string();
+        self.call(move |client| {
+            let scan_ids: Vec<String> = client
+                .query(
+                    "SELECT scans.id
+                     FROM scans
+                     WHERE scans.id != $1
+                        AND EXISTS (SELECT 1 FROM rule_stats WHERE rule_stats.scan_id = scans.id)
+                     ORDER BY scans.started_at DESC
+                     LIMIT $2",
+                    &[&scan_id, &(scans as i64)],
+                )
+                .context("Failed to collect baseline scans")?
+                .iter()
+                .map(|row| row.get(0))
+                .collect();
+
+            let mut totals: HashMap<String, f64> = HashMap::new();
+            let rows = client
+                .query(
+                    "SELECT rule_name, detected FROM rule_stats WHERE scan_id = ANY($1)",
+                    &[&scan_ids],
+                )
+                .context("Failed to collect rule counts")?;
+            for row in rows {
+                let detected: i64 = row.get(1);
+                *totals.entry(row.get(0)).or_insert(0.0) += detected as f64;
+            }
+
+            let baseline = totals
+                .into_iter()
+                .map(|(rule_name, total)| (rule_name, total / scan_ids.len() as f64))
+                .collect();
+
+            Ok((scan_ids.len(), baseline))
+        })
+    }
+
+    fn findings(&self) -> Result<Vec<Finding>> {
+        self.call(|client| {
+            let rows = client
+                .query(
+                    &format!(
+                        "SELECT {} FROM findings ORDER BY domain, rule_name",
+                        FINDING_COLUMNS
+                    ),
+                    &[],
+                )
+                .context("Failed to collect findings")?;
+
+            Ok(rows.iter().map(finding_from_row).collect())
+        })
+    }
+    fn domain_scans(&self, domain: &str) -> Result<Vec<DomainScan>> {
+        let domain = domain.to_string();
+        self.call(move |client| {
+            let rows = client
+                .query(
+                    "SELECT scan_id, domain, scanned_at, ips, technologies, error
+                     FROM domain_scans
+                     WHERE domain = $1
+                     ORDER BY scanned_at DESC, scan_id",
+                    &[&domain],
+                )
+                .context("Failed to collect domain scans")?;
+
+            Ok(rows
+                .iter()
+                .map(|row| {
+                    let ips: Option<String> = row.get(3);
+                    let technologies: Option<String> = row.get(4);
+                    DomainScan {
+                        scan_id: row.get(0),
+                        domain: row.get(1),
+                        scanned_at: row.get(2),
+                        ips: ips
+                            .and_then(|ips| serde_json::from_str(&ips).ok())
+                            .unwrap_or_default(),
+                        technologies: technologies
+                            .and_then(|technologies| serde_json::from_str(&technologies).ok())
+                            .unwrap_or_default(),
+                        error: row.get(5),
+                        certificate: None,
+                    }
+                })
+                .collect())
+        })
+    }
+
+    fn purge_findings(&mut self, filter: &PurgeFilter) -> Result<usize> {
+        let filter = filter.clone();
+        self.call(move |client| {
+            let removed = client
+                .execute(
+                    "DELETE FROM findings
+                     WHERE ($1::TIMESTAMPTZ IS NULL OR scanned_at < $1)
+                       AND ($2::TEXT IS NULL OR domain = $2)
+                       AND ($3::TEXT IS NULL OR rule_name = $3)
+                       AND ($4::BIGINT IS NULL OR id = $4)
+                       AND (NOT $5 OR NOT detected)",
+                    &[
+                        &filter.before,
+                        &filter.domain,
+                        &filter.rule,
+                        &filter.id,
+                        &filter.not_detected,
+                    ],
+                )
+                .context("Failed to purge findings")?;
+            Ok(removed as usize)
+        })
+    }
+
+    fn false_positives(&self) -> Result<Vec<FalsePositive>> {
+        self.call(|client| {
+            let rows = client
+                .query(
+                    "SELECT domain, rule_name, skip_scans, marked_at
+                     FROM false_positives
+                     ORDER BY domain, rule_name",
+                    &[],
+                )
+                .context("Failed to collect false positives")?;
+
+            Ok(rows
+                .iter()
+                .map(|row| FalsePositive {
+                    domain: row.get(0),
+                    rule_name: row.get(1),
+                    skip_scans: row.get(2),
+                    marked_at: row.get(3),
+                })
+                .collect())
+        })
+    }
+
+    fn notes(&self) -> Result<Vec<FindingNote>> {
+        self.call(|client| {
+            let rows = client
+                .query(
+                    "SELECT domain, rule_name, note, author, created_at
+                     FROM finding_notes
+                     ORDER BY domain, rule_name, created_at, id",
+                    &[],
+                )
+                .context("Failed to collect notes")?;
+
+            Ok(rows
+                .iter()
+                .map(|row| FindingNote {
+                    domain: row.get(0),
+                    rule_name: row.get(1),
+                    note: Note {
+                        note: row.get(2),
+                        author: row.get(3),
+                        created_at: row.get(4),
+                    },
+                })
+                .collect())
+        })
+    }
+
+    fn certificates(&self) -> Result<Vec<SiteCertificate>> {
+        self.call(|client| {
+            let rows = client
+                .query(
+                    "SELECT host, subject, issuer, sans, serial, not_before, not_after, seen_at
+                     FROM certificates
+                     ORDER BY host",
+                    &[],
+                )
+                .context("Failed to collect certificates")?;
+
+            Ok(rows
+                .iter()
+                .map(|row| {
+                    let sans: Option<String> = row.get(3);
+                    SiteCertificate {
+                        host: row.get(0),
+                        subject: row.get(1),
+                        issuer: row.get(2),
+                        sans: sans
+                            .and_then(|sans| serde_json::from_str(&sans).ok())
+                            .unwrap_or_default(),
+                        serial: row.get(4),
+                        not_before: row.get(5),
+                        not_after: row.get(6),
+                        seen_at: row.get(7),
+                    }
+                })
+                .collect())
+        })
+    }
+}
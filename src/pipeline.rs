@@ -0,0 +1,821 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::future::Future;
+use std::io::{BufRead, BufReader};
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+use crate::brute;
+use crate::config::{InputSource, ScanConfig};
+use crate::context::ScanContext;
+use crate::otel;
+use crate::rules::{Rule, TemplateVars};
+use crate::scanner::{self, FetchedRule, RuleOutcome, ScanResources};
+use crate::shutdown::Shutdown;
+use crate::utils;
+
+/// Fetched pages queued for each matching thread
+const PAGES_PER_MATCHER: usize = 2;
+
+/// Worker counts and queue sizes of the scan pipeline
+#[derive(Debug, Clone)]
+pub struct PipelineOptions {
+    /// Capacity of each queue between stages
+    pub queue_capacity: usize,
+
+    /// Domains resolved and fingerprinted concurrently
+    pub resolvers: usize,
+
+    /// Rule checks fetched concurrently
+    pub fetchers: usize,
+
+    /// Threads in the pool matching signatures against fetched bodies
+    pub matchers: usize,
+}
+
+impl Default for PipelineOptions {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 1000,
+            resolvers: 10,
+            fetchers: 10,
+            matchers: default_matchers(),
+        }
+    }
+}
+
+impl PipelineOptions {
+    /// Build pipeline options from a scan configuration
+    pub fn from_config(config: &ScanConfig) -> Self {
+        Self {
+            queue_capacity: config.queue_capacity,
+            resolvers: config.concurrency,
+            fetchers: config.concurrency,
+            matchers: match config.match_threads {
+                0 => default_matchers(),
+                threads => threads,
+            },
+        }
+    }
+}
+
+/// Matching is CPU-bound, so one worker per core
+fn default_matchers() -> usize {
+    std::thread::available_parallelism().map_or(4, |n| n.get())
+}
+
+/// A rayon thread pool that runs signature matching off the async runtime, so
+/// scanning large bodies never stalls the tasks driving network I/O
+pub struct MatchPool {
+    pool: rayon::ThreadPool,
+}
+
+impl MatchPool {
+    /// Create a pool with `threads` matching threads
+    pub fn new(threads: usize) -> Result<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.max(1))
+            .thread_name(|i| format!("fatt-match-{}", i))
+            .panic_handler(|_| error!("Signature matching panicked"))
+            .build()
+            .context("Failed to start signature matching pool")?;
+        Ok(Self { pool })
+    }
+
+    /// Number of threads in the pool
+    pub fn threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    /// Run `job` on the pool and wait for its result without blocking the runtime
+    pub async fn run<T, F>(&self, job: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        self.pool.spawn(move || {
+            let _ = tx.send(job());
+        });
+        rx.await.context("Signature matching job was dropped")
+    }
+}
+
+/// Number of items waiting in a queue, with its high-water mark
+#[derive(Debug, Default)]
+pub struct QueueGauge {
+    depth: AtomicI64,
+    peak: AtomicI64,
+}
+
+impl QueueGauge {
+    fn push(&self) {
+        let depth = self.depth.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    fn pop(&self) {
+        self.depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Items currently queued
+    pub fn depth(&self) -> usize {
+        // A receiver can see an item before its sender records it
+        self.depth.load(Ordering::Relaxed).max(0) as usize
+    }
+
+    /// Most items queued at once
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed).max(0) as usize
+    }
+}
+
+/// Depth of the queue feeding each pipeline stage
+#[derive(Debug, Default)]
+pub struct PipelineMetrics {
+    /// Domains waiting to be resolved
+    pub domains: QueueGauge,
+
+    /// Rule checks waiting to be fetched
+    pub checks: QueueGauge,
+
+    /// Fetched responses waiting to be matched
+    pub pages: QueueGauge,
+
+    /// Outcomes waiting to be written
+    pub outcomes: QueueGauge,
+}
+
+/// Point-in-time copy of the pipeline queue depths
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct QueueSnapshot {
+    pub domains: usize,
+    pub checks: usize,
+    pub pages: usize,
+    pub outcomes: usize,
+}
+
+impl PipelineMetrics {
+    /// Current queue depths
+    pub fn snapshot(&self) -> QueueSnapshot {
+        QueueSnapshot {
+            domains: self.domains.depth(),
+            checks: self.checks.depth(),
+            pages: self.pages.depth(),
+            outcomes: self.outcomes.depth(),
+        }
+    }
+
+    /// Highest queue depths seen so far
+    pub fn peaks(&self) -> QueueSnapshot {
+        QueueSnapshot {
+            domains: self.domains.peak(),
+            checks: self.checks.peak(),
+            pages: self.pages.peak(),
+            outcomes: self.outcomes.peak(),
+        }
+    }
+}
+
+impl fmt::Display for QueueSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "domains {}, checks {}, pages {}, outcomes {}",
+            self.domains, self.checks, self.pages, self.outcomes
+        )
+    }
+}
+
+/// A bounded queue between two stages whose depth is tracked by a gauge
+struct Queue<T> {
+    tx: mpsc::Sender<T>,
+    metrics: Arc<PipelineMetrics>,
+    select: fn(&PipelineMetrics) -> &QueueGauge,
+}
+
+impl<T> Clone for Queue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            metrics: self.metrics.clone(),
+            select: self.select,
+        }
+    }
+}
+
+/// Receiving end of a queue, shared by a stage's workers
+struct QueueReceiver<T> {
+    rx: Arc<Mutex<mpsc::Receiver<T>>>,
+    metrics: Arc<PipelineMetrics>,
+    select: fn(&PipelineMetrics) -> &QueueGauge,
+}
+
+impl<T> Clone for QueueReceiver<T> {
+    fn clone(&self) -> Self {
+        Self {
+            rx: self.rx.clone(),
+            metrics: self.metrics.clone(),
+            select: self.select,
+        }
+    }
+}
+
+fn queue<T>(
+    capacity: usize,
+    metrics: &Arc<PipelineMetrics>,
+    select: fn(&PipelineMetrics) -> &QueueGauge,
+) -> (Queue<T>, QueueReceiver<T>) {
+    let (tx, rx) = mpsc::channel(capacity.max(1));
+
+    (
+        Queue {
+            tx,
+            metrics: metrics.clone(),
+            select,
+        },
+        QueueReceiver {
+            rx: Arc::new(Mutex::new(rx)),
+            metrics: metrics.clone(),
+            select,
+        },
+    )
+}
+
+impl<T> Queue<T> {
+    /// Wait for room in the queue, then enqueue. Fails once the receiving stage is gone.
+    async fn send(&self, item: T) -> Result<()> {
+        self.tx
+            .send(item)
+            .await
+            .map_err(|_| anyhow::anyhow!("Pipeline stage stopped"))?;
+        (self.select)(&self.metrics).push();
+
+        Ok(())
+    }
+
+    /// Enqueue from a blocking thread, waiting for room
+    fn blocking_send(&self, item: T) -> Result<()> {
+        self.tx
+            .blocking_send(item)
+            .map_err(|_| anyhow::anyhow!("Pipeline stage stopped"))?;
+        (self.select)(&self.metrics).push();
+
+        Ok(())
+    }
+}
+
+impl<T> QueueReceiver<T> {
+    /// Next item, or `None` once every sender is gone and the queue is drained
+    async fn recv(&self) -> Option<T> {
+        let item = self.rx.lock().await.recv().await;
+        if item.is_some() {
+            (self.select)(&self.metrics).pop();
+        }
+        item
+    }
+}
+
+/// A resolved domain and the number of its rule checks not yet written
+struct DomainState {
+    ctx: ScanContext,
+    remaining: AtomicUsize,
+    /// Templated rules expanded for this domain, by rule index
+    expanded: HashMap<usize, Rule>,
+    /// Found by the subdomain brute force or expanded from an input entry rather than
+    /// read from an input, so not counted in `domains_processed`
+    discovered: bool,
+}
+
+impl DomainState {
+    /// The rule at `index`, with its placeholders expanded for this domain
+    fn rule(&self, index: usize) -> &Rule {
+        self.expanded
+            .get(&index)
+            .unwrap_or(&self.ctx.rules.ruleset.rules[index])
+    }
+}
+
+/// Where the pipeline reads its domains from
+enum DomainSource {
+    /// Input files, a domain per line
+    Files(Vec<InputSource>),
+
+    /// Domains of a worker's batch
+    #[cfg(feature = "distributed")]
+    Domains(Vec<String>),
+}
+
+/// A domain read from an input, with the input's tag
+struct DomainInput {
+    domain: String,
+    tag: Option<String>,
+}
+
+/// One rule to check against one domain
+struct Check {
+    domain: Arc<DomainState>,
+    rule: usize,
+}
+
+/// A rule's responses, waiting to be matched
+struct Fetched {
+    domain: Arc<DomainState>,
+    rule: usize,
+    result: Result<FetchedRule>,
+}
+
+/// A decided rule check, waiting to be written
+struct Checked {
+    domain: Arc<DomainState>,
+    rule: usize,
+    result: Result<Option<RuleOutcome>>,
+}
+
+/// Scan the domains in `inputs` through a staged pipeline:
+/// reader → resolver → fetcher → matcher → writer.
+///
+/// Stages are connected by bounded queues, so a fast stage waits for a slow one
+/// instead of piling up work in memory. Every domain read (including duplicates,
+/// which are skipped, keeping the tag of the input they were first read from) is
+/// counted in `domains_processed`. With a target expander, each entry read is replaced
+/// by the targets it expands into. With a subdomain brute force, the subdomains found
+/// of each apex domain are scanned too, with the apex's tag. Returns the number of
+/// unique domains scanned.
+pub async fn run_pipeline(
+    inputs: &[InputSource],
+    resources: &ScanResources,
+    options: &PipelineOptions,
+    metrics: Arc<PipelineMetrics>,
+    domains_processed: Arc<AtomicUsize>,
+) -> Result<usize> {
+    let source = DomainSource::Files(inputs.to_vec());
+    run(source, resources, options, metrics, domains_processed).await
+}
+
+/// Scan a worker's batch of domains through the pipeline, as `run_pipeline` scans the
+/// domains of input files, returning the number of unique domains scanned
+#[cfg(feature = "distributed")]
+pub async fn scan_domains(
+    domains: &[String],
+    resources: &ScanResources,
+    options: &PipelineOptions,
+) -> Result<usize> {
+    let source = DomainSource::Domains(domains.to_vec());
+    let metrics = Arc::new(PipelineMetrics::default());
+    run(
+        source,
+        resources,
+        options,
+        metrics,
+        Arc::new(AtomicUsize::new(0)),
+    )
+    .await
+}
+
+async fn run(
+    source: DomainSource,
+    resources: &ScanResources,
+    options: &PipelineOptions,
+    metrics: Arc<PipelineMetrics>,
+    domains_processed: Arc<AtomicUsize>,
+) -> Result<usize> {
+    let shutdown = &resources.shutdown;
+    let capacity = options.queue_capacity;
+    let (domain_tx, domain_rx) = queue::<DomainInput>(capacity, &metrics, |m| &m.domains);
+    let (check_tx, check_rx) = queue::<Check>(capacity, &metrics, |m| &m.checks);
+    // Each fetched page can hold a body of up to `max_body_bytes`, so only a few per
+    // matcher wait, however large the other queues are
+    let pages = capacity.min(options.matchers.max(1) * PAGES_PER_MATCHER);
+    let (fetched_tx, fetched_rx) = queue::<Fetched>(pages, &metrics, |m| &m.pages);
+    let (checked_tx, checked_rx) = queue::<Checked>(capacity, &metrics, |m| &m.outcomes);
+
+    // Domains queued so far, read or discovered, so none is scanned twice. Unlike the
+    // queues this isn't bounded: it holds one normalized name per unique domain.
+    let seen = Arc::new(std::sync::Mutex::new(HashSet::new()));
+
+    // Reader: stream domains from the files or list, skipping duplicates
+    let reader = {
+        let domains_processed = domains_processed.clone();
+        let shutdown = shutdown.clone();
+        let seen = seen.clone();
+        let expanding = resources.expander.is_some();
+        tokio::task::spawn_blocking(move || {
+            read_domains(
+                source,
+                expanding,
+                domain_tx,
+                domains_processed,
+                &seen,
+                &shutdown,
+            )
+        })
+    };
+
+    // Resolver: resolve and fingerprint each domain, then queue its rule checks
+    let mut workers = Vec::new();
+    for _ in 0..options.resolvers.max(1) {
+        let (rx, tx) = (domain_rx.clone(), check_tx.clone());
+        let resources = resources.clone();
+        let domains_processed = domains_processed.clone();
+        let seen = seen.clone();
+        workers.push(until_forced(shutdown, async move {
+            while let Some(input) = rx.recv().await {
+                resources.wait_for_window().await;
+                resolve(input, &resources, &tx, &domains_processed, &seen).await?;
+            }
+            Ok(())
+        }));
+    }
+    drop((domain_rx, check_tx));
+
+    // Fetcher: request each rule's path from the domain's targets, holding one of the
+    // domain's rule slots and a slot in each of its throttling groups
+    for _ in 0..options.fetchers.max(1) {
+        let (rx, tx) = (check_rx.clone(), fetched_tx.clone());
+        let resources = resources.clone();
+        workers.push(until_forced(shutdown, async move {
+            while let Some(Check { domain, rule }) = rx.recv().await {
+                resources.wait_for_window().await;
+                let slot = domain.ctx.rule_slot().await;
+                let permit = resources.throttle.acquire(&domain.ctx.throttle_keys).await;
+                let result = scanner::fetch_rule(&domain.ctx, &resources, domain.rule(rule)).await;
+                drop((permit, slot));
+                tx.send(Fetched {
+                    domain,
+                    rule,
+                    result,
+                })
+                .await?;
+            }
+            Ok(())
+        }));
+    }
+    drop((check_rx, fetched_tx));
+
+    // Matcher: decide each check from its responses on the matching pool. Each
+    // task hands over one job at a time, so at most `matchers` bodies are in
+    // the pool and the rest wait in the bounded pages queue.
+    let pool = Arc::new(MatchPool::new(options.matchers)?);
+    debug!("🧮 Matching signatures on {} threads", pool.threads());
+    for _ in 0..pool.threads() {
+        let (rx, tx) = (fetched_rx.clone(), checked_tx.clone());
+        let pool = pool.clone();
+        workers.push(until_forced(shutdown, async move {
+            while let Some(Fetched {
+                domain,
+                rule,
+                result,
+            }) = rx.recv().await
+            {
+                // A job that panics fails its own check rather than the scan
+                let result = match result {
+                    Ok(fetched) => {
+                        let domain = domain.clone();
+                        pool.run(move || scanner::match_rule(domain.rule(rule), fetched))
+                            .await
+                    }
+                    Err(e) => Err(e),
+                };
+                tx.send(Checked {
+                    domain,
+                    rule,
+                    result,
+                })
+                .await?;
+            }
+            Ok(())
+        }));
+    }
+    drop((fetched_rx, checked_tx));
+
+    // Writer: hand outcomes to the result writer from a single task. It keeps going
+    // after a forced stop, so every outcome already decided is written.
+    let writer = {
+        let resources = resources.clone();
+        tokio::spawn(async move {
+            while let Some(Checked {
+                domain,
+                rule,
+                result,
+            }) = checked_rx.recv().await
+            {
+                let ctx = &domain.ctx;
+                let rule_def = domain.rule(rule);
+                let _ = scanner::record_outcome(ctx, &resources, rule_def, result).await;
+                resources.tasks_completed.fetch_add(1, Ordering::Relaxed);
+
+                if domain.remaining.fetch_sub(1, Ordering::Relaxed) == 1 {
+                    otel::record_domain();
+                    if ctx.breaker.is_open() {
+                        debug!(
+                            "⚡ Circuit breaker opened for {}, checks skipped",
+                            ctx.domain
+                        );
+                    }
+                    if !domain.discovered {
+                        domains_processed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        })
+    };
+
+    reader.await.context("Domain reader panicked")??;
+    for worker in workers {
+        worker.await.context("Pipeline worker panicked")??;
+    }
+    writer.await.context("Result writer panicked")?;
+    resources.writer.flush().await;
+
+    let scanned = seen.lock().unwrap().len();
+    Ok(scanned)
+}
+
+/// Run a pipeline stage until its work runs out or the scan is forced to stop, dropping
+/// the work in progress in the latter case
+fn until_forced<F>(shutdown: &Shutdown, stage: F) -> JoinHandle<Result<()>>
+where
+    F: Future<Output = Result<()>> + Send + 'static,
+{
+    let shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        // Checked first, so a stage failing because the next one was dropped by the
+        // forced stop doesn't fail the scan
+        tokio::select! {
+            biased;
+            _ = shutdown.forced() => Ok(()),
+            result = stage => result,
+        }
+    })
+}
+
+/// Send each unique domain of the source down the pipeline, tagged with its file's
+/// tag, until they run out or the scan is stopped, adding them to `seen`. Entries to
+/// be `expanding` into targets are sent as written and only deduplicated among
+/// themselves, as they aren't scanned.
+///
+/// Runs on a blocking thread, reading a line at a time so no file is ever held in memory;
+/// only `seen` grows with the input, by one name per unique domain.
+fn read_domains(
+    source: DomainSource,
+    expanding: bool,
+    tx: Queue<DomainInput>,
+    domains_processed: Arc<AtomicUsize>,
+    seen: &std::sync::Mutex<HashSet<String>>,
+    shutdown: &Shutdown,
+) -> Result<()> {
+    let entries = std::sync::Mutex::new(HashSet::new());
+    let seen = if expanding { &entries } else { seen };
+
+    // Send one entry, returning whether to read on
+    let send = |line: &str, tag: Option<&String>| -> Result<bool> {
+        if shutdown.is_stopping() {
+            debug!("⏹️ Stopped reading domains");
+            return Ok(false);
+        }
+
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(true);
+        }
+
+        // One spelling per host, so `Example.com.` isn't scanned again as `example.com`
+        let domain = if expanding {
+            line.to_string()
+        } else {
+            utils::normalize_domain(line)
+        };
+        if !seen.lock().unwrap().insert(domain.clone()) {
+            domains_processed.fetch_add(1, Ordering::Relaxed);
+            return Ok(true);
+        }
+        let sent = tx.blocking_send(DomainInput {
+            domain,
+            tag: tag.cloned(),
+        });
+        match sent {
+            // A forced stop closes the queue under a waiting reader
+            Err(_) if shutdown.is_stopping() => Ok(false),
+            sent => sent.map(|_| true),
+        }
+    };
+
+    match source {
+        DomainSource::Files(inputs) => {
+            for input in &inputs {
+                let file = File::open(&input.file)
+                    .context(format!("Failed to open domains file: {}", input.file))?;
+
+                for line in BufReader::new(file).lines() {
+                    let line = line.context(format!("Failed to read {}", input.file))?;
+                    if !send(&line, input.tag.as_ref())? {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "distributed")]
+        DomainSource::Domains(domains) => {
+            for domain in &domains {
+                if !send(domain, None)? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Queue the targets of an input entry: the entry itself, or with a target expander,
+/// the targets it expands into that weren't already seen
+async fn resolve(
+    input: DomainInput,
+    resources: &ScanResources,
+    tx: &Queue<Check>,
+    domains_processed: &AtomicUsize,
+    seen: &std::sync::Mutex<HashSet<String>>,
+) -> Result<()> {
+    let Some(expander) = &resources.expander else {
+        return resolve_target(input, false, resources, tx, domains_processed, seen).await;
+    };
+
+    let targets = match expander.expand(&input.domain).await {
+        Ok(targets) => targets,
+        Err(e) => {
+            warn!("⚠️ Failed to expand {}: {:#}", input.domain, e);
+            Vec::new()
+        }
+    };
+    for target in targets {
+        if resources.shutdown.is_stopping() {
+            break;
+        }
+        let target = utils::normalize_domain(&target);
+        if !seen.lock().unwrap().insert(target.clone()) {
+            continue;
+        }
+        let target = DomainInput {
+            domain: target,
+            tag: input.tag.clone(),
+        };
+        resolve_target(target, true, resources, tx, domains_processed, seen).await?;
+    }
+    domains_processed.fetch_add(1, Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Prepare a domain and queue a check for each rule. An apex domain is first brute
+/// forced for subdomains if enabled; the ones not already seen are queued after it.
+/// `expanded` is set for a target expanded from an input entry.
+async fn resolve_target(
+    input: DomainInput,
+    expanded: bool,
+    resources: &ScanResources,
+    tx: &Queue<Check>,
+    domains_processed: &AtomicUsize,
+    seen: &std::sync::Mutex<HashSet<String>>,
+) -> Result<()> {
+    let discovered = match &resources.brute {
+        Some(brute) if !resources.shutdown.is_stopping() && brute::is_apex(&input.domain) => {
+            let resolver = &resources.resolver;
+            let dns = resources.options.timeouts.dns;
+            let lookup = |host: String| async move {
+                tokio::time::timeout(dns, resolver.probe(&host))
+                    .await
+                    .unwrap_or_default()
+            };
+            brute.discover(&input.domain, lookup).await
+        }
+        _ => Vec::new(),
+    };
+    let tag = input.tag.clone();
+    let apex = input.domain.clone();
+
+    queue_domain(input, expanded, resources, tx, domains_processed).await?;
+
+    let mut found = 0;
+    for host in discovered {
+        if resources.shutdown.is_stopping() {
+            break;
+        }
+        if !seen.lock().unwrap().insert(host.clone()) {
+            continue;
+        }
+        found += 1;
+        let input = DomainInput {
+            domain: host,
+            tag: tag.clone(),
+        };
+        queue_domain(input, true, resources, tx, domains_processed).await?;
+    }
+    if found > 0 {
+        debug!("🔎 Found {} subdomains of {}", found, apex);
+    }
+
+    Ok(())
+}
+
+/// Prepare a domain and queue a check for each rule; `discovered` is set for a
+/// subdomain found by the brute force or a target expanded from an input entry
+async fn queue_domain(
+    input: DomainInput,
+    discovered: bool,
+    resources: &ScanResources,
+    tx: &Queue<Check>,
+    domains_processed: &AtomicUsize,
+) -> Result<()> {
+    let domain = input.domain.as_str();
+    // Only domains read from the inputs count towards progress
+    let processed = |count: &AtomicUsize| {
+        if !discovered {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    };
+
+    let prepared = ScanContext::prepare(domain, resources).await;
+    resources.record_domain(domain, &prepared).await;
+    let mut ctx = match prepared {
+        Ok(ctx) if !ctx.rules.ruleset.rules.is_empty() => ctx,
+        Ok(_) => {
+            processed(domains_processed);
+            return Ok(());
+        }
+        Err(e) => {
+            debug!("❌ Failed to resolve domain: {}: {}", domain, e);
+
+            // Count the checks that would have run
+            resources.tasks_completed.fetch_add(
+                resources.ruleset.snapshot().ruleset.rules.len(),
+                Ordering::Relaxed,
+            );
+            processed(domains_processed);
+            return Ok(());
+        }
+    };
+
+    debug!(
+        "🔍 Scanning domain: {} ({})",
+        domain,
+        ctx.ip()
+            .map_or_else(|| "unresolved".to_string(), |ip| ip.to_string())
+    );
+    ctx.tag = input.tag;
+
+    // Checks marked as false positives are counted as done without running
+    let rules: Vec<usize> = (0..ctx.rules.ruleset.rules.len())
+        .filter(|&rule| !resources.is_skipped(domain, &ctx.rules.ruleset.rules[rule].name))
+        .collect();
+    let skipped = ctx.rules.ruleset.rules.len() - rules.len();
+    if skipped > 0 {
+        debug!(
+            "🙈 Skipping {} checks of {} marked as false positives",
+            skipped, domain
+        );
+        resources
+            .tasks_completed
+            .fetch_add(skipped, Ordering::Relaxed);
+    }
+    if rules.is_empty() {
+        processed(domains_processed);
+        return Ok(());
+    }
+
+    let vars = TemplateVars::new(domain, chrono::Utc::now().date_naive());
+    let expanded = ctx
+        .rules
+        .ruleset
+        .rules
+        .iter()
+        .enumerate()
+        .filter_map(|(index, rule)| match rule.expand(&vars) {
+            Cow::Owned(rule) => Some((index, rule)),
+            Cow::Borrowed(_) => None,
+        })
+        .collect();
+
+    let state = Arc::new(DomainState {
+        ctx,
+        remaining: AtomicUsize::new(rules.len()),
+        expanded,
+        discovered,
+    });
+    for rule in rules {
+        tx.send(Check {
+            domain: state.clone(),
+            rule,
+        })
+        .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::ScanConfig;
+use crate::scanner::DEFAULT_RULE_CONCURRENCY;
+
+/// Named tuning preset setting a scan's pace: how many requests are in flight, how
+/// they're spread out, how failures are retried and how long each may take
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanProfile {
+    /// Few requests at a time, spread out and patient, for fragile or watchful targets
+    Stealth,
+    /// The defaults of `fatt scan`
+    #[default]
+    Normal,
+    /// As many requests as the network allows, giving up quickly on slow hosts
+    Aggressive,
+}
+
+/// The tuning values a profile stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileTuning {
+    pub concurrency: usize,
+    pub rule_concurrency: usize,
+    pub jitter_ms: u64,
+    pub retries: u32,
+    pub retry_backoff_ms: u64,
+    pub timeout: u64,
+    pub connect_timeout: u64,
+    pub first_byte_timeout: u64,
+    pub circuit_breaker: usize,
+    pub throttle_subnet: usize,
+}
+
+impl ScanProfile {
+    pub fn tuning(self) -> ProfileTuning {
+        match self {
+            ScanProfile::Stealth => ProfileTuning {
+                concurrency: 5,
+                rule_concurrency: 1,
+                jitter_ms: 2000,
+                retries: 1,
+                retry_backoff_ms: 2000,
+                timeout: 20,
+                connect_timeout: 10,
+                first_byte_timeout: 20,
+                circuit_breaker: 3,
+                throttle_subnet: 1,
+            },
+            ScanProfile::Normal => ProfileTuning {
+                concurrency: 100,
+                rule_concurrency: DEFAULT_RULE_CONCURRENCY,
+                jitter_ms: 0,
+                retries: 2,
+                retry_backoff_ms: 250,
+                timeout: 10,
+                connect_timeout: 5,
+                first_byte_timeout: 10,
+                circuit_breaker: 10,
+                throttle_subnet: 0,
+            },
+            ScanProfile::Aggressive => ProfileTuning {
+                concurrency: 500,
+                rule_concurrency: 8,
+                jitter_ms: 0,
+                retries: 0,
+                retry_backoff_ms: 100,
+                timeout: 5,
+                connect_timeout: 3,
+                first_byte_timeout: 5,
+                circuit_breaker: 5,
+                throttle_subnet: 0,
+            },
+        }
+    }
+
+    /// Set the profile's tuning on a configuration and record the profile with it
+    pub fn apply(self, config: &mut ScanConfig) {
+        let tuning = self.tuning();
+        config.profile = Some(self);
+        config.concurrency = tuning.concurrency;
+        config.rule_concurrency = tuning.rule_concurrency;
+        config.jitter_ms = tuning.jitter_ms;
+        config.retries = tuning.retries;
+        config.retry_backoff_ms = tuning.retry_backoff_ms;
+        config.http_timeout = tuning.timeout;
+        config.connect_timeout = tuning.connect_timeout;
+        config.first_byte_timeout = tuning.first_byte_timeout;
+        config.circuit_breaker = tuning.circuit_breaker;
+        config.throttle_subnet = tuning.throttle_subnet;
+    }
+}
+
+impl FromStr for ScanProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "stealth" => Ok(ScanProfile::Stealth),
+            "normal" => Ok(ScanProfile::Normal),
+            "aggressive" => Ok(ScanProfile::Aggressive),
+            other => anyhow::bail!(
+                "Unknown scan profile: {} (expected stealth, normal or aggressive)",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ScanProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanProfile::Stealth => write!(f, "stealth"),
+            ScanProfile::Normal => write!(f, "normal"),
+            ScanProfile::Aggressive => write!(f, "aggressive"),
+        }
+    }
+}
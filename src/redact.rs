@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+
+use crate::db::Finding;
+use crate::evidence::Evidence;
+
+/// What a redacted secret is replaced with
+pub const MASK: &str = "********";
+
+/// Secrets masked by every policy. A pattern with a `secret` group masks just that
+/// group, keeping the name it was assigned to; any other pattern masks its whole match.
+const DEFAULT_PATTERNS: &[&str] = &[
+    // Private keys, to the end of the text if the snippet cut the block short
+    r"(?s)-----BEGIN [A-Z ]*PRIVATE KEY-----.*?(?:-----END [A-Z ]*PRIVATE KEY-----|\z)",
+    // Assignments to credential-like names: DB_PASSWORD=..., "api_key": "...", token: ...
+    r#"(?i)\b(?:[a-z0-9]+[_.-])*(?:password|passwd|pwd|secret|token|key|credentials?)\b["']?\s*[:=]\s*["']?(?P<secret>[^\s"'&;,<>]+)"#,
+    // Authorization header values
+    r"(?i)\b(?:bearer|basic)\s+(?P<secret>[A-Za-z0-9._~+/=-]{8,})",
+    // Cookie headers and session cookies
+    r"(?im)^(?:set-)?cookie\s*:\s*(?P<secret>.+)$",
+    r#"(?i)\b(?:[a-z0-9_]*sess(?:ion)?[a-z0-9_]*|sid)=(?P<secret>[^\s;&"']+)"#,
+    // Credentials in URLs
+    r"://[^/\s:@]+:(?P<secret>[^/\s@]+)@",
+    // Well-known token formats
+    r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b",
+    r"\bgh[pousr]_[A-Za-z0-9]{36,}\b",
+    r"\bxox[abposr]-[A-Za-z0-9-]{10,}",
+    r"\beyJ[A-Za-z0-9_-]{8,}\.[A-Za-z0-9_-]{8,}\.[A-Za-z0-9_-]{8,}",
+];
+
+/// Secrets masked in everything a scan writes: evidence, findings, logs and exports.
+///
+/// The built-in patterns cover passwords, tokens, keys and cookies; more can be added
+/// for secrets specific to the targets.
+#[derive(Debug, Clone)]
+pub struct RedactionPolicy {
+    patterns: Vec<Regex>,
+}
+
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        Self {
+            patterns: DEFAULT_PATTERNS
+                .iter()
+                .map(|pattern| Regex::new(pattern).expect("built-in redaction pattern"))
+                .collect(),
+        }
+    }
+}
+
+impl RedactionPolicy {
+    /// The built-in patterns and `extra` ones
+    pub fn new(extra: &[String]) -> Result<Self> {
+        let mut policy = Self::default();
+        for pattern in extra {
+            policy.patterns.push(
+                Regex::new(pattern).context(format!("Invalid redaction pattern: {}", pattern))?,
+            );
+        }
+
+        Ok(policy)
+    }
+
+    /// `text` with every secret masked
+    pub fn redact<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        let mut text = Cow::Borrowed(text);
+        for pattern in &self.patterns {
+            let masked = match mask(pattern, &text) {
+                Cow::Owned(masked) => Some(masked),
+                Cow::Borrowed(_) => None,
+            };
+            if let Some(masked) = masked {
+                text = Cow::Owned(masked);
+            }
+        }
+
+        text
+    }
+
+    /// Evidence with the secrets in its snippet masked
+    pub fn redact_evidence(&self, evidence: &Evidence) -> Evidence {
+        Evidence {
+            snippet: self.redact(&evidence.snippet).into_owned(),
+            ..evidence.clone()
+        }
+    }
+
+    /// Mask the secrets in what a finding recorded: its path, redirects and evidence
+    pub fn redact_finding(&self, finding: &mut Finding) {
+        finding.matched_path = self.redact(&finding.matched_path).into_owned();
+        for url in &mut finding.redirect_chain {
+            *url = self.redact(url).into_owned();
+        }
+        if let Some(evidence) = &finding.evidence {
+            finding.evidence = Some(self.redact_evidence(evidence));
+        }
+    }
+}
+
+fn mask<'t>(pattern: &Regex, text: &'t str) -> Cow<'t, str> {
+    pattern.replace_all(text, |caps: &Captures| {
+        let whole = caps.get(0).expect("a match has a whole group");
+        match caps.name("secret") {
+            Some(secret) => format!(
+                "{}{}{}",
+                &text[whole.start()..secret.start()],
+                MASK,
+                &text[secret.end()..whole.end()]
+            ),
+            None => MASK.to_string(),
+        }
+    })
+}
@@ -9,6 +9,11 @@ use trust_dns_resolver::{
     TokioAsyncResolver,
 };
 
+use crate::utils;
+
+/// Directory scans keep the DNS cache in
+pub const CACHE_DIR: &str = "cache";
+
 /// DNS resolver for domain name resolution with caching
 #[derive(Debug, Clone)]
 pub struct DnsResolver {
@@ -91,7 +96,14 @@ impl DnsResolver {
     }
 
     /// Lookup a domain name and return its IP address if found
+    #[allow(dead_code)]
     pub async fn lookup(&self, domain: &str) -> Result<Option<String>> {
+        let ips = self.lookup_all(domain).await?;
+        Ok(ips.first().map(|ip| ip.to_string()))
+    }
+
+    /// Lookup a domain name and return all of its IP addresses
+    pub async fn lookup_all(&self, domain: &str) -> Result<Vec<IpAddr>> {
         // Check cache first
         if let Some(cached_result) = self.get_from_cache(domain)? {
             // Increment cache hits
@@ -99,7 +111,7 @@ impl DnsResolver {
             *hits += 1;
 
             debug!("🔍 Cache hit for domain: {}", domain);
-            return Ok(cached_result.ips.first().map(|ip| ip.to_string()));
+            return Ok(cached_result.ips);
         }
 
         // Perform actual DNS resolution
@@ -109,23 +121,25 @@ impl DnsResolver {
 
         // For test resolvers, return a predictable IP
         if self.is_test {
-            let test_ip = "192.0.2.1"; // TEST-NET-1 address for testing
+            let test_ip: IpAddr = "192.0.2.1".parse().unwrap(); // TEST-NET-1 address for testing
             debug!("🔍 Test resolver returning {} for {}", test_ip, domain);
 
             // Cache the result
             let result = ResolverResult {
-                ips: vec![test_ip.parse().unwrap()],
+                ips: vec![test_ip],
                 timestamp: Utc::now().timestamp() as u64,
                 ttl: 3600, // 1 hour
             };
 
             self.add_to_cache(domain, &result)?;
-            return Ok(Some(test_ip.to_string()));
+            return Ok(result.ips);
         }
 
-        // Attempt to lookup the A record first
-        let lookup_result = match self.resolver.lookup_ip(domain).await {
-            Ok(lookup) => lookup.iter().next().map(|addr| addr.to_string()),
+        // Look up all A/AAAA records of the host, leaving out any port; an address
+        // resolves to itself
+        let (host, _) = utils::split_host_port(domain);
+        let ips: Vec<IpAddr> = match self.resolver.lookup_ip(host).await {
+            Ok(lookup) => lookup.iter().collect(),
             Err(e) => {
                 warn!("❌ Failed to resolve domain {}: {}", domain, e);
 
@@ -138,16 +152,16 @@ impl DnsResolver {
 
                 self.add_to_cache(domain, &result)?;
 
-                None
+                vec![]
             }
         };
 
-        debug!("🔍 Resolved domain {} to {:?}", domain, lookup_result);
+        debug!("🔍 Resolved domain {} to {:?}", domain, ips);
 
-        if let Some(ip) = &lookup_result {
+        if !ips.is_empty() {
             // Cache the result
             let result = ResolverResult {
-                ips: vec![ip.parse().unwrap()],
+                ips: ips.clone(),
                 timestamp: Utc::now().timestamp() as u64,
                 ttl: 3600, // default TTL of 1 hour
             };
@@ -155,7 +169,23 @@ impl DnsResolver {
             self.add_to_cache(domain, &result)?;
         }
 
-        Ok(lookup_result)
+        Ok(ips)
+    }
+
+    /// Resolve a guessed name without the cache, so a brute force doesn't fill it
+    /// with names that don't exist; a failure is no addresses
+    pub async fn probe(&self, host: &str) -> Vec<IpAddr> {
+        if self.is_test {
+            return vec!["192.0.2.1".parse().unwrap()];
+        }
+
+        match self.resolver.lookup_ip(host).await {
+            Ok(lookup) => lookup.iter().collect(),
+            Err(e) => {
+                debug!("🔍 No records for {}: {}", host, e);
+                vec![]
+            }
+        }
     }
 
     /// Add a resolver result to the cache
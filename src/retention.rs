@@ -0,0 +1,433 @@
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection};
+use serde_json::{json, Map, Value};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::Command;
+use tracing::{info, warn};
+
+use crate::db::{self, PurgeFilter};
+use crate::store::{self, StoreKind};
+use crate::utils;
+
+/// Tables holding per-scan rows, removed along with their scan session
+const SCAN_TABLES: &[&str] = &[
+    "domain_risk",
+    "domain_scans",
+    "rule_stats",
+    "ruleset_versions",
+    "scan_findings",
+];
+
+/// What a prune removed
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    pub findings: usize,
+    pub scans: usize,
+    /// Where the removed rows were archived
+    pub archive: Option<String>,
+}
+
+/// Rows of one table selected for removal
+struct TableRows {
+    table: &'static str,
+    rows: Vec<Map<String, Value>>,
+}
+
+/// Remove findings and scan sessions older than `older_than_days`.
+///
+/// With `archive`, the rows are first written to a gzip-compressed JSONL file, one
+/// `{"table": ..., "row": {...}}` object per line, and nothing is removed unless that
+/// succeeds. An `s3://` archive is uploaded with the AWS CLI. The archive's location is
+/// recorded in the database's `archives` table.
+pub fn prune(
+    db_file: &str,
+    shards: usize,
+    older_than_days: u64,
+    archive: Option<&str>,
+) -> Result<PruneReport> {
+    if store::is_postgres_url(db_file) {
+        anyhow::bail!("db prune only supports SQLite databases");
+    }
+
+    let files = db::findings_files(db_file, shards);
+    if let Some(missing) = std::iter::once(db_file)
+        .chain(files.iter().map(String::as_str))
+        .find(|path| !Path::new(path).exists())
+    {
+        anyhow::bail!("Database not found: {}", missing);
+    }
+
+    let cutoff = db::format_timestamp(&(Utc::now() - Duration::days(older_than_days as i64)));
+    let primary = db::init_db(db_file)?;
+    let findings_dbs = files
+        .iter()
+        .map(|file| db::init_db(file))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut findings = Vec::new();
+    for conn in &findings_dbs {
+        findings.push(TableRows {
+            table: "findings",
+            rows: select_rows(conn, "SELECT * FROM findings WHERE scanned_at < ?", &cutoff)?,
+        });
+    }
+    let mut scans = vec![TableRows {
+        table: "scans",
+        rows: select_rows(
+            &primary,
+            "SELECT * FROM scans WHERE started_at < ?",
+            &cutoff,
+        )?,
+    }];
+    for table in SCAN_TABLES {
+        scans.push(TableRows {
+            table,
+            rows: select_rows(
+                &primary,
+                &format!(
+                    "SELECT * FROM {} WHERE scan_id IN (SELECT id FROM scans WHERE started_at < ?)",
+                    table
+                ),
+                &cutoff,
+            )?,
+        });
+    }
+
+    let mut report = PruneReport {
+        findings: findings.iter().map(|rows| rows.rows.len()).sum(),
+        scans: scans[0].rows.len(),
+        archive: None,
+    };
+    if report.findings == 0 && report.scans == 0 {
+        info!("Nothing older than {} to prune", cutoff);
+        return Ok(report);
+    }
+
+    if let Some(archive) = archive {
+        write_archive(archive, findings.iter().chain(&scans))?;
+        primary
+            .execute(
+                "INSERT INTO archives (path, created_at, cutoff, findings, scans)
+                 VALUES (?, CURRENT_TIMESTAMP, ?, ?, ?)",
+                params![archive, cutoff, report.findings as i64, report.scans as i64],
+            )
+            .context("Failed to record archive")?;
+        info!(
+            "📦 Archived {} findings and {} scans to {}",
+            report.findings, report.scans, archive
+        );
+        report.archive = Some(archive.to_string());
+    }
+
+    for conn in &findings_dbs {
+        conn.execute("DELETE FROM findings WHERE scanned_at < ?", params![cutoff])
+            .context("Failed to prune findings")?;
+    }
+    let tx = primary.unchecked_transaction()?;
+    for table in SCAN_TABLES {
+        tx.execute(
+            &format!(
+                "DELETE FROM {} WHERE scan_id IN (SELECT id FROM scans WHERE started_at < ?)",
+                table
+            ),
+            params![cutoff],
+        )
+        .context(format!("Failed to prune {}", table))?;
+    }
+    tx.execute("DELETE FROM scans WHERE started_at < ?", params![cutoff])
+        .context("Failed to prune scans")?;
+    tx.commit().context("Failed to prune scans")?;
+
+    info!(
+        "🧹 Pruned {} findings and {} scans older than {}",
+        report.findings, report.scans, cutoff
+    );
+
+    Ok(report)
+}
+
+/// Parse an age such as `30d`, `12h`, `2w`, `90m` or `45s`; a bare number is in days
+pub fn parse_age(age: &str) -> Result<Duration> {
+    let age = age.trim();
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (amount, unit) = age.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .ok()
+        .filter(|amount| *amount > 0)
+        .context(format!("Invalid age: {} (expected e.g. 30d)", age))?;
+
+    Ok(match unit {
+        "" | "d" => Duration::days(amount),
+        "w" => Duration::weeks(amount),
+        "h" => Duration::hours(amount),
+        "m" => Duration::minutes(amount),
+        "s" => Duration::seconds(amount),
+        _ => anyhow::bail!("Invalid age unit: {} (expected s, m, h, d or w)", unit),
+    })
+}
+
+/// Remove findings last scanned longer than `older_than` ago, optionally only for one
+/// rule or only those where nothing was detected. Unlike [`prune`], scan sessions are
+/// kept, and every result store is supported.
+pub fn purge(
+    kind: StoreKind,
+    database: &str,
+    shards: usize,
+    older_than: &str,
+    rule: Option<&str>,
+    not_detected: bool,
+) -> Result<usize> {
+    if kind.resolve(database) != StoreKind::Postgres && !Path::new(database).exists() {
+        anyhow::bail!("Results not found: {}", database);
+    }
+
+    let filter = PurgeFilter {
+        rule: rule.map(String::from),
+        not_detected,
+        ..PurgeFilter::older_than(parse_age(older_than)?)
+    };
+    let removed = store::purge_findings(kind, database, shards, &filter)?;
+    info!(
+        "🧹 Purged {} findings last scanned before {}",
+        removed,
+        filter
+            .before
+            .as_ref()
+            .map(db::format_timestamp)
+            .unwrap_or_default()
+    );
+
+    Ok(removed)
+}
+
+/// Remove the findings of a domain, a rule, or both, or one finding by ID, such as
+/// false positives or hosts that turned out to be out of scope. At least one filter
+/// is needed, so a bare `results delete` can't empty the store.
+pub fn delete(
+    kind: StoreKind,
+    database: &str,
+    shards: usize,
+    filter: &PurgeFilter,
+) -> Result<usize> {
+    if filter.is_empty() {
+        anyhow::bail!("Nothing to delete by: give --domain, --rule or --id");
+    }
+    let kind = kind.resolve(database);
+    if kind != StoreKind::Postgres && !Path::new(database).exists() {
+        anyhow::bail!("Results not found: {}", database);
+    }
+    if filter.id.is_some() {
+        if kind == StoreKind::Jsonl {
+            anyhow::bail!("JSONL findings have no IDs: delete by --domain and --rule instead");
+        }
+        // Each shard numbers its findings from 1, so an ID alone could match several
+        if kind == StoreKind::Sqlite && shards > 1 && filter.domain.is_none() {
+            anyhow::bail!("--id needs --domain with sharded findings");
+        }
+    }
+
+    let removed = store::purge_findings(kind, database, shards, filter)?;
+    info!("🗑️ Deleted {} findings", removed);
+
+    Ok(removed)
+}
+
+/// What compacting one database file did
+#[derive(Debug)]
+pub struct VacuumReport {
+    pub path: String,
+    pub size_before: u64,
+    pub size_after: u64,
+    /// Pages left unused by deleted rows before the vacuum
+    pub free_pages: i64,
+    pub indexes: usize,
+    /// Problems `PRAGMA integrity_check` found, including broken indexes; a file with
+    /// any is left as it was
+    pub problems: Vec<String>,
+}
+
+impl VacuumReport {
+    /// Bytes the file shrank by
+    pub fn reclaimed(&self) -> u64 {
+        self.size_before.saturating_sub(self.size_after)
+    }
+}
+
+/// Compact a SQLite database and its shards: check each file's integrity, then
+/// rebuild it with VACUUM to give back the space deleted rows left behind, and
+/// refresh the query planner's statistics with ANALYZE.
+///
+/// Deleting rows never shrinks a SQLite file on its own, so this is the step to
+/// follow a large prune, purge or delete with.
+pub fn vacuum(db_file: &str, shards: usize) -> Result<Vec<VacuumReport>> {
+    if store::is_postgres_url(db_file) {
+        anyhow::bail!("db vacuum only supports SQLite databases");
+    }
+
+    let mut files = vec![db_file.to_string()];
+    if shards > 1 {
+        files.extend(db::findings_files(db_file, shards));
+    }
+    if let Some(missing) = files.iter().find(|path| !Path::new(path).exists()) {
+        anyhow::bail!("Database not found: {}", missing);
+    }
+
+    let mut reports = Vec::with_capacity(files.len());
+    for path in files {
+        let conn = db::init_db(&path)?;
+        let size_before = file_size(&path)?;
+        let free_pages: i64 = conn.query_row("PRAGMA freelist_count", [], |row| row.get(0))?;
+        let indexes: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index'",
+            [],
+            |row| row.get(0),
+        )?;
+        let problems: Vec<String> = conn
+            .prepare("PRAGMA integrity_check")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()
+            .context(format!("Failed to check integrity: {}", path))?
+            .into_iter()
+            .filter(|result| result != "ok")
+            .collect();
+
+        if problems.is_empty() {
+            conn.execute_batch("VACUUM; ANALYZE;")
+                .context(format!("Failed to vacuum database: {}", path))?;
+        } else {
+            warn!(
+                "⚠️ {} failed its integrity check, leaving it as it is: {}",
+                path,
+                problems.join("; ")
+            );
+        }
+        drop(conn);
+
+        let report = VacuumReport {
+            size_after: file_size(&path)?,
+            path,
+            size_before,
+            free_pages,
+            indexes: indexes as usize,
+            problems,
+        };
+        if report.problems.is_empty() {
+            info!(
+                "🗜️ Vacuumed {}: {} → {}, reclaimed {} ({} free pages), {} indexes ok",
+                report.path,
+                utils::format_bytes(report.size_before),
+                utils::format_bytes(report.size_after),
+                utils::format_bytes(report.reclaimed()),
+                report.free_pages,
+                report.indexes
+            );
+        }
+        reports.push(report);
+    }
+
+    Ok(reports)
+}
+
+fn file_size(path: &str) -> Result<u64> {
+    Ok(std::fs::metadata(path)
+        .context(format!("Failed to read size of {}", path))?
+        .len())
+}
+
+/// Every column of the rows `sql` selects with the cutoff, as JSON objects
+fn select_rows(conn: &Connection, sql: &str, cutoff: &str) -> Result<Vec<Map<String, Value>>> {
+    let mut stmt = conn.prepare(sql)?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+
+    let rows = stmt
+        .query_map(params![cutoff], |row| {
+            let mut object = Map::new();
+            for (i, column) in columns.iter().enumerate() {
+                let value = match row.get_ref(i)? {
+                    ValueRef::Null => Value::Null,
+                    ValueRef::Integer(value) => json!(value),
+                    ValueRef::Real(value) => json!(value),
+                    ValueRef::Text(value) => json!(String::from_utf8_lossy(value)),
+                    ValueRef::Blob(value) => json!(hex::encode(value)),
+                };
+                object.insert(column.clone(), value);
+            }
+            Ok(object)
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to read rows to prune")?;
+
+    Ok(rows)
+}
+
+/// Write the rows to a compressed JSONL archive at a local path or an `s3://` URL
+fn write_archive<'a>(archive: &str, tables: impl Iterator<Item = &'a TableRows>) -> Result<()> {
+    if archive.starts_with("s3://") {
+        let staging = tempfile::Builder::new()
+            .suffix(".jsonl.gz")
+            .tempfile()
+            .context("Failed to create archive staging file")?;
+        write_gzip_jsonl(staging.reopen()?, archive, tables)?;
+        return upload_to_s3(staging.path(), archive);
+    }
+
+    // Never replace an earlier archive: it may hold the only copy of its rows
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(archive)
+        .context(format!("Failed to create archive: {}", archive))?;
+    write_gzip_jsonl(file, archive, tables)
+}
+
+fn write_gzip_jsonl<'a>(
+    file: File,
+    archive: &str,
+    tables: impl Iterator<Item = &'a TableRows>,
+) -> Result<()> {
+    let mut writer = GzEncoder::new(BufWriter::new(file), Compression::default());
+
+    for table in tables {
+        for row in &table.rows {
+            serde_json::to_writer(&mut writer, &json!({ "table": table.table, "row": row }))?;
+            writer.write_all(b"\n")?;
+        }
+    }
+
+    let file = writer
+        .finish()
+        .and_then(|writer| writer.into_inner().map_err(|e| e.into_error()))
+        .context(format!("Failed to write archive: {}", archive))?;
+    file.sync_all()
+        .context(format!("Failed to write archive: {}", archive))?;
+
+    Ok(())
+}
+
+fn upload_to_s3(path: &Path, url: &str) -> Result<()> {
+    let output = Command::new("aws")
+        .arg("s3")
+        .arg("cp")
+        .arg("--only-show-errors")
+        .arg(path)
+        .arg(url)
+        .output()
+        .context("Failed to run the AWS CLI to upload the archive")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to upload archive to {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::db::Finding;
+use crate::rules::{RuleSet, Severity};
+
+/// Weight applied to each detected finding, by rule severity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskWeights {
+    pub critical: f64,
+    pub high: f64,
+    pub medium: f64,
+    pub low: f64,
+    pub info: f64,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        Self {
+            critical: 10.0,
+            high: 5.0,
+            medium: 2.0,
+            low: 1.0,
+            info: 0.0,
+        }
+    }
+}
+
+impl RiskWeights {
+    /// Weight for a severity; rules without a severity count as info
+    pub fn weight(&self, severity: Option<&Severity>) -> f64 {
+        match severity {
+            Some(Severity::Critical) => self.critical,
+            Some(Severity::High) => self.high,
+            Some(Severity::Medium) => self.medium,
+            Some(Severity::Low) => self.low,
+            Some(Severity::Info) | None => self.info,
+        }
+    }
+}
+
+impl FromStr for RiskWeights {
+    type Err = anyhow::Error;
+
+    /// Parse weights from `critical=10,high=5,...`; omitted severities keep their default
+    fn from_str(s: &str) -> Result<Self> {
+        let mut weights = RiskWeights::default();
+
+        for pair in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, value) = pair.split_once('=').context(format!(
+                "Invalid risk weight '{}', expected name=value",
+                pair
+            ))?;
+            let value: f64 = value
+                .trim()
+                .parse()
+                .context(format!("Invalid risk weight value: {}", value))?;
+
+            match name.trim().to_lowercase().as_str() {
+                "critical" => weights.critical = value,
+                "high" => weights.high = value,
+                "medium" => weights.medium = value,
+                "low" => weights.low = value,
+                "info" => weights.info = value,
+                other => anyhow::bail!("Unknown severity in risk weights: {}", other),
+            }
+        }
+
+        Ok(weights)
+    }
+}
+
+/// Risk score for a scan, overall and per domain
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RiskReport {
+    pub total: f64,
+    pub per_domain: BTreeMap<String, DomainRisk>,
+}
+
+/// Risk score for a single domain
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DomainRisk {
+    pub score: f64,
+    pub findings: usize,
+}
+
+/// Compute the weighted risk score of detected findings
+pub fn score_findings(
+    findings: &[Finding],
+    ruleset: &RuleSet,
+    weights: &RiskWeights,
+) -> RiskReport {
+    let severities: HashMap<&str, Option<&Severity>> = ruleset
+        .rules
+        .iter()
+        .map(|r| (r.name.as_str(), r.severity.as_ref()))
+        .collect();
+
+    let mut report = RiskReport::default();
+
+    for finding in findings.iter().filter(|f| f.detected) {
+        let severity = severities
+            .get(finding.rule_name.as_str())
+            .copied()
+            .flatten();
+        let weight = weights.weight(severity);
+
+        let domain = report.per_domain.entry(finding.domain.clone()).or_default();
+        domain.score += weight;
+        domain.findings += 1;
+        report.total += weight;
+    }
+
+    report
+}
+
+/// Percentage change between two risk scores, if the previous one is non-zero
+pub fn risk_change_percent(previous: f64, current: f64) -> Option<f64> {
+    if previous == 0.0 {
+        None
+    } else {
+        Some((current - previous) / previous * 100.0)
+    }
+}
+
+/// Risk score of an older and a newer scan, and how it changed between them
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RiskChange {
+    pub from: f64,
+    pub to: f64,
+    /// Percentage change, if the older score is non-zero
+    pub change_percent: Option<f64>,
+}
+
+impl RiskChange {
+    pub fn new(from: f64, to: f64) -> Self {
+        Self {
+            from,
+            to,
+            change_percent: risk_change_percent(from, to),
+        }
+    }
+}
+
+impl fmt::Display for RiskChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} → {:.1}", self.from, self.to)?;
+        if let Some(percent) = self.change_percent {
+            write!(f, " ({:+.1}%)", percent)?;
+        }
+        Ok(())
+    }
+}
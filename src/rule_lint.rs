@@ -0,0 +1,414 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+use crate::rules::Rule;
+
+/// Fields a rule entry may have
+const RULE_FIELDS: &[&str] = &[
+    "name",
+    "path",
+    "signature",
+    "signatures",
+    "match",
+    "translations",
+    "negative_signature",
+    "not_contains",
+    "content_type",
+    "follow_redirects",
+    "interesting_status",
+    "exists_status",
+    "description",
+    "severity",
+    "remediation",
+];
+
+const SEVERITIES: &[&str] = &["critical", "high", "medium", "low", "info"];
+
+const MATCH_MODES: &[&str] = &["any", "all"];
+
+/// One problem with a rules file, placed as precisely as the file allows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleIssue {
+    /// Line of the problem, counting from 1
+    pub line: Option<usize>,
+    /// Position of the rule in the `rules` list, counting from 0
+    pub rule: Option<usize>,
+    /// Name of the rule, if it has one
+    pub name: Option<String>,
+    pub field: Option<String>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for RuleIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if let Some(rule) = self.rule {
+            write!(f, "rules[{}]", rule)?;
+            if let Some(name) = &self.name {
+                write!(f, " ({})", name)?;
+            }
+        }
+        if let Some(field) = &self.field {
+            if self.rule.is_some() {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", field)?;
+        }
+        if self.rule.is_some() || self.field.is_some() {
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean '{}'?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// How `rules validate` reports problems
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IssueFormat {
+    /// One `FILE:LINE: problem` line each, as compilers print them
+    #[default]
+    Text,
+    /// A JSON array of problems, for editors and CI
+    Json,
+}
+
+impl FromStr for IssueFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(IssueFormat::Text),
+            "json" => Ok(IssueFormat::Json),
+            other => anyhow::bail!("Unknown report format: {} (expected text or json)", other),
+        }
+    }
+}
+
+impl fmt::Display for IssueFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IssueFormat::Text => write!(f, "text"),
+            IssueFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Every problem in the YAML source of a rules file, rather than just the first one
+/// serde_yaml stops at
+pub fn lint_rules(source: &str) -> Vec<RuleIssue> {
+    let document: Value = match serde_yaml::from_str(source) {
+        Ok(document) => document,
+        Err(err) => {
+            return vec![file_issue(
+                err.location().map(|location| location.line()),
+                format!("invalid YAML: {}", err),
+            )]
+        }
+    };
+
+    let Some(entries) = document.get("rules") else {
+        return vec![file_issue(
+            None,
+            "missing the top-level 'rules' list".to_string(),
+        )];
+    };
+    let Some(entries) = entries.as_sequence() else {
+        return vec![file_issue(
+            rules_key_line(source),
+            "'rules' must be a list of rules".to_string(),
+        )];
+    };
+
+    let starts = entry_lines(source);
+    entries
+        .iter()
+        .enumerate()
+        .flat_map(|(index, entry)| {
+            let lines = EntryLines {
+                source,
+                start: starts.get(index).copied(),
+                end: starts.get(index + 1).copied(),
+            };
+            lint_entry(index, entry, &lines)
+        })
+        .collect()
+}
+
+/// Lint a rules file, reporting a file that can't be read as an error
+pub fn lint_file(path: &str) -> Result<Vec<RuleIssue>> {
+    let source =
+        fs::read_to_string(path).context(format!("Failed to open rules file: {}", path))?;
+    Ok(lint_rules(&source))
+}
+
+/// Print the problems found in a rules file to stdout
+pub fn print_issues(path: &str, issues: &[RuleIssue], format: IssueFormat) -> Result<()> {
+    match format {
+        IssueFormat::Text => {
+            for issue in issues {
+                match issue.line {
+                    Some(line) => println!("{}:{}: {}", path, line, without_line(issue)),
+                    None => println!("{}: {}", path, issue),
+                }
+            }
+            if issues.is_empty() {
+                println!("{}: ok", path);
+            }
+        }
+        IssueFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(issues).context("Failed to serialize problems")?
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Check a rules file, failing if it has any problems
+pub fn validate_rules(path: &str, format: IssueFormat) -> Result<()> {
+    let issues = lint_file(path)?;
+    print_issues(path, &issues, format)?;
+    if !issues.is_empty() {
+        anyhow::bail!("{} problem(s) in {}", issues.len(), path);
+    }
+    Ok(())
+}
+
+/// The problems, one per line, as part of an error message
+pub(crate) fn describe(issues: &[RuleIssue]) -> String {
+    issues
+        .iter()
+        .map(|issue| format!("  {}", issue))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn without_line(issue: &RuleIssue) -> RuleIssue {
+    RuleIssue {
+        line: None,
+        ..issue.clone()
+    }
+}
+
+fn file_issue(line: Option<usize>, message: String) -> RuleIssue {
+    RuleIssue {
+        line,
+        rule: None,
+        name: None,
+        field: None,
+        message,
+        suggestion: None,
+    }
+}
+
+fn lint_entry(index: usize, entry: &Value, lines: &EntryLines) -> Vec<RuleIssue> {
+    let Some(mapping) = entry.as_mapping() else {
+        return vec![RuleIssue {
+            line: lines.start,
+            rule: Some(index),
+            name: None,
+            field: None,
+            message: "expected a rule with name, path and signature fields".to_string(),
+            suggestion: None,
+        }];
+    };
+
+    let name = mapping
+        .get("name")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let issue = |field: Option<&str>, message: String, suggestion: Option<String>| RuleIssue {
+        line: field.and_then(|field| lines.field(field)).or(lines.start),
+        rule: Some(index),
+        name: name.clone(),
+        field: field.map(str::to_string),
+        message,
+        suggestion,
+    };
+
+    let mut issues = Vec::new();
+    for (key, value) in mapping {
+        match key.as_str() {
+            Some(field) if RULE_FIELDS.contains(&field) => {
+                if let Some(message) = check_field(field, value) {
+                    let suggestion = match field {
+                        "severity" => closest(value, SEVERITIES),
+                        "match" => closest(value, MATCH_MODES),
+                        _ => None,
+                    };
+                    issues.push(issue(Some(field), message, suggestion));
+                }
+            }
+            Some(field) => issues.push(issue(
+                Some(field),
+                "unknown field".to_string(),
+                closest_name(field, RULE_FIELDS),
+            )),
+            None => issues.push(issue(None, "field names must be text".to_string(), None)),
+        }
+    }
+    for field in ["name", "path"] {
+        if !mapping.contains_key(field) {
+            issues.push(issue(
+                None,
+                format!("missing required field '{}'", field),
+                None,
+            ));
+        }
+    }
+
+    // Checks across fields need the whole rule to have been read
+    if issues.is_empty() {
+        match serde_yaml::from_value::<Rule>(entry.clone()) {
+            Ok(rule) => {
+                if let Err(err) = rule.validate() {
+                    issues.push(issue(None, format!("{:#}", err), None));
+                }
+            }
+            Err(err) => issues.push(issue(None, err.to_string(), None)),
+        }
+    }
+
+    issues
+}
+
+/// Why a field's value can't be read into a rule, found by reading a rule with only
+/// that field set
+fn check_field(field: &str, value: &Value) -> Option<String> {
+    let mut probe = Mapping::new();
+    probe.insert("name".into(), "probe".into());
+    probe.insert("path".into(), "/".into());
+    probe.insert(field.into(), value.clone());
+
+    let err = serde_yaml::from_value::<Rule>(Value::Mapping(probe)).err()?;
+    Some(match field {
+        "severity" => format!(
+            "unknown severity {} (expected one of: {})",
+            describe_value(value),
+            SEVERITIES.join(", ")
+        ),
+        "match" => format!(
+            "unknown match mode {} (expected one of: {})",
+            describe_value(value),
+            MATCH_MODES.join(", ")
+        ),
+        _ => err.to_string(),
+    })
+}
+
+fn describe_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("'{}'", s),
+        other => serde_yaml::to_string(other)
+            .map(|yaml| yaml.trim().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// The allowed value nearest to a misspelt one
+fn closest(value: &Value, allowed: &[&str]) -> Option<String> {
+    closest_name(value.as_str()?, allowed)
+}
+
+fn closest_name(name: &str, allowed: &[&str]) -> Option<String> {
+    let name = name.trim().to_lowercase();
+    let (distance, nearest) = allowed
+        .iter()
+        .map(|candidate| (edit_distance(&name, candidate), *candidate))
+        .min()?;
+    (distance <= (name.len() / 3).max(2)).then(|| nearest.to_string())
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Where one entry of the `rules` list sits in the source
+struct EntryLines<'a> {
+    source: &'a str,
+    /// First line of the entry, counting from 1
+    start: Option<usize>,
+    /// First line of the next entry
+    end: Option<usize>,
+}
+
+impl EntryLines<'_> {
+    /// Line of one of the entry's fields
+    fn field(&self, field: &str) -> Option<usize> {
+        let start = self.start?;
+        let key = format!("{}:", field);
+        let quoted = format!("\"{}\":", field);
+        self.source
+            .lines()
+            .enumerate()
+            .skip(start - 1)
+            .take_while(|(number, _)| self.end.is_none_or(|end| number + 1 < end))
+            .find(|(_, line)| {
+                let line = line.trim_start().trim_start_matches('-').trim_start();
+                line.starts_with(&key) || line.starts_with(&quoted)
+            })
+            .map(|(number, _)| number + 1)
+    }
+}
+
+fn rules_key_line(source: &str) -> Option<usize> {
+    source
+        .lines()
+        .position(|line| line.starts_with("rules:"))
+        .map(|number| number + 1)
+}
+
+/// First line of each item of the block-style `rules` list
+fn entry_lines(source: &str) -> Vec<usize> {
+    let Some(rules_line) = rules_key_line(source) else {
+        return Vec::new();
+    };
+    // A flow-style list like `rules: [...]` has no item lines to point at
+    let after_key = source.lines().nth(rules_line - 1).unwrap_or("")["rules:".len()..].trim();
+    if !after_key.is_empty() && !after_key.starts_with('#') {
+        return Vec::new();
+    }
+
+    let mut starts = Vec::new();
+    let mut item_indent = None;
+    for (number, line) in source.lines().enumerate().skip(rules_line) {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - content.len();
+        let is_item = content.starts_with('-');
+        let item_indent = *item_indent.get_or_insert(indent);
+        if indent < item_indent || (indent == item_indent && !is_item) {
+            break;
+        }
+        if indent == item_indent && is_item {
+            starts.push(number + 1);
+        }
+    }
+    starts
+}
@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+use crate::rules::{self, LiveRuleSet};
+use crate::store::SharedStore;
+
+/// Quiet period after a change before the rules are reloaded, so an editor's burst of
+/// writes causes a single reload
+const SETTLE: Duration = Duration::from_millis(250);
+
+/// Watches a scan's rules files and reloads the rules when they change.
+///
+/// Domains whose checks haven't started yet pick up the new rules; the ones under way
+/// finish with the rules they started with. Each reload becomes a new ruleset version,
+/// recorded with the scan session. A reload that fails to load is logged and ignored,
+/// keeping the current rules.
+pub struct RuleWatcher {
+    _watcher: RecommendedWatcher,
+    task: JoinHandle<()>,
+}
+
+impl RuleWatcher {
+    /// Start watching `files`, replacing `rules` and recording each new version in `store`
+    pub fn start(
+        files: &[&str],
+        rules: Arc<LiveRuleSet>,
+        store: SharedStore,
+        scan_id: &str,
+    ) -> Result<Self> {
+        let files = files
+            .iter()
+            .map(|file| watched_path(file))
+            .collect::<Result<Vec<_>>>()?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let watched = files.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event)
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                        && event.paths.iter().any(|path| watched.contains(path)) =>
+                {
+                    let _ = tx.send(());
+                }
+                Ok(_) => {}
+                Err(e) => debug!("Rules watcher error: {}", e),
+            })
+            .context("Failed to create rules watcher")?;
+
+        // Watch the directories rather than the files: editors often save by replacing
+        // the file, which would end a watch on the file itself
+        let mut dirs: Vec<&Path> = files.iter().filter_map(|file| file.parent()).collect();
+        dirs.sort();
+        dirs.dedup();
+        for dir in dirs {
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .context(format!("Failed to watch {}", dir.display()))?;
+        }
+
+        let paths: Vec<String> = files
+            .iter()
+            .map(|file| file.to_string_lossy().into_owned())
+            .collect();
+        info!("👀 Reloading rules when {} change", paths.join(", "));
+
+        let scan_id = scan_id.to_string();
+        let task = tokio::spawn(async move {
+            while rx.recv().await.is_some() {
+                // Let a burst of writes finish
+                loop {
+                    match tokio::time::timeout(SETTLE, rx.recv()).await {
+                        Ok(Some(())) => continue,
+                        Ok(None) => return,
+                        Err(_) => break,
+                    }
+                }
+                reload(&paths, &rules, &store, &scan_id).await;
+            }
+        });
+
+        Ok(Self {
+            _watcher: watcher,
+            task,
+        })
+    }
+}
+
+impl Drop for RuleWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Absolute path a rules file's change events are reported under: its directory resolved,
+/// its name kept, so a symlinked file is still recognised
+fn watched_path(file: &str) -> Result<PathBuf> {
+    let path = Path::new(file);
+    let name = path
+        .file_name()
+        .context(format!("Invalid rules file: {}", file))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let dir = fs::canonicalize(dir).context(format!("Failed to resolve {}", dir.display()))?;
+
+    Ok(dir.join(name))
+}
+
+/// Load the rules files again and make them the next version if they changed
+async fn reload(paths: &[String], rules: &LiveRuleSet, store: &SharedStore, scan_id: &str) {
+    let files: Vec<&str> = paths.iter().map(String::as_str).collect();
+    let ruleset = match rules::load_rules_files(&files) {
+        Ok(ruleset) if ruleset.rules.is_empty() => {
+            warn!("⚠️ Keeping the current rules: the reloaded rules files have no rules");
+            return;
+        }
+        Ok(ruleset) => ruleset,
+        Err(e) => {
+            warn!("⚠️ Keeping the current rules: reload failed: {:#}", e);
+            return;
+        }
+    };
+
+    if ruleset.digest() == rules.snapshot().digest {
+        debug!("Rules files changed but their rules didn't, nothing to reload");
+        return;
+    }
+
+    let snapshot = rules.replace(ruleset);
+    info!(
+        "📋 Rules reloaded: version {} with {} rules ({}), used for domains not started yet",
+        snapshot.version,
+        snapshot.ruleset.rules.len(),
+        snapshot.digest
+    );
+    if let Err(e) = store.lock().await.record_ruleset(scan_id, &snapshot) {
+        error!("Failed to record ruleset version: {}", e);
+    }
+}
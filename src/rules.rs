@@ -1,15 +1,22 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use memchr::memmem;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{prelude::*, BufReader};
+use std::io::prelude::*;
 use std::path::Path;
-use tracing::{debug, info};
+use std::sync::{Arc, RwLock};
+use tracing::{debug, info, warn};
 
 use crate::logger;
+use crate::rule_lint;
 
 /// Severity levels for rules
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Severity {
     Critical,
@@ -34,7 +41,7 @@ impl Severity {
 
 impl PartialOrd for Severity {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.to_value().cmp(&other.to_value()))
+        Some(self.cmp(other))
     }
 }
 
@@ -56,16 +63,191 @@ impl std::fmt::Display for Severity {
     }
 }
 
+/// How a rule's signatures combine
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Any one signature in the body is a match
+    #[default]
+    Any,
+    /// Every signature must be in the body
+    All,
+}
+
+impl MatchMode {
+    fn is_any(&self) -> bool {
+        *self == MatchMode::Any
+    }
+}
+
+/// Redirects followed when a rule doesn't set `follow_redirects`
+pub const DEFAULT_MAX_REDIRECTS: usize = 3;
+
+/// Upper bound on `follow_redirects`
+pub const MAX_REDIRECTS: usize = 10;
+
+/// Whether, and how far, a rule's requests follow redirects
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(untagged)]
+pub enum FollowRedirects {
+    /// `true` follows up to the default number of redirects, `false` none
+    Enabled(bool),
+    /// Follow up to this many redirects
+    Limit(usize),
+}
+
+impl FollowRedirects {
+    /// Number of redirects to follow
+    pub fn limit(&self) -> usize {
+        match self {
+            FollowRedirects::Enabled(true) => DEFAULT_MAX_REDIRECTS,
+            FollowRedirects::Enabled(false) => 0,
+            FollowRedirects::Limit(limit) => *limit,
+        }
+    }
+}
+
+/// Placeholders that can appear in rule paths and signatures as `{{name}}`
+pub const TEMPLATE_VARIABLES: &[&str] = &["domain", "host", "date"];
+
+/// Values substituted for a rule's `{{...}}` placeholders when it is checked
+/// against a domain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateVars {
+    /// The domain as listed in the input, e.g. `www.example.com:8443`
+    pub domain: String,
+    /// The domain without its port, e.g. `www.example.com`
+    pub host: String,
+    /// The scan date as `YYYY-MM-DD`
+    pub date: String,
+}
+
+impl TemplateVars {
+    pub fn new(domain: &str, date: NaiveDate) -> Self {
+        Self {
+            domain: domain.to_string(),
+            host: strip_port(domain).to_string(),
+            date: date.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        match name {
+            "domain" => Some(&self.domain),
+            "host" => Some(&self.host),
+            "date" => Some(&self.date),
+            _ => None,
+        }
+    }
+
+    /// Replace every known placeholder in `text`; unknown ones are left as they are
+    pub fn expand(&self, text: &str) -> String {
+        let mut expanded = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some((before, name, after)) = next_placeholder(rest) {
+            expanded.push_str(before);
+            match self.get(name) {
+                Some(value) => expanded.push_str(value),
+                None => {
+                    expanded.push_str("{{");
+                    expanded.push_str(name);
+                    expanded.push_str("}}");
+                }
+            }
+            rest = after;
+        }
+
+        expanded.push_str(rest);
+        expanded
+    }
+}
+
+/// Split `text` around its first `{{name}}` placeholder
+fn next_placeholder(text: &str) -> Option<(&str, &str, &str)> {
+    let start = text.find("{{")?;
+    let end = text[start + 2..].find("}}")? + start + 2;
+    Some((
+        &text[..start],
+        text[start + 2..end].trim(),
+        &text[end + 2..],
+    ))
+}
+
+/// Remove a trailing `:port` from a domain, leaving bracketed IPv6 hosts intact
+pub(crate) fn strip_port(domain: &str) -> &str {
+    match domain.rsplit_once(':') {
+        Some((host, port))
+            if !port.is_empty()
+                && port.bytes().all(|b| b.is_ascii_digit())
+                && (!host.contains(':') || host.ends_with(']')) =>
+        {
+            host
+        }
+        _ => domain,
+    }
+}
+
+/// Top-level domain of a domain name, lowercased; IP addresses have none
+pub(crate) fn top_level_domain(domain: &str) -> Option<String> {
+    let host = strip_port(domain).trim_end_matches('.');
+    if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') {
+        return None;
+    }
+
+    host.rsplit_once('.').map(|(_, tld)| tld.to_lowercase())
+}
+
 /// A scanning rule definition
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+// Unknown fields are ignored when loading, but editors should still flag them
+#[schemars(deny_unknown_fields)]
 pub struct Rule {
     pub name: String,
     pub path: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub signature: String,
+    /// Additional signatures, combined with `signature` according to `match`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<String>,
+    #[serde(default, rename = "match", skip_serializing_if = "MatchMode::is_any")]
+    pub match_mode: MatchMode,
+    /// Localized versions of signatures, by signature and then language tag (e.g.
+    /// `de` or `pt-BR`); each is matched as an alternative to its signature, which
+    /// still matches pages in any other language
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub translations: BTreeMap<String, BTreeMap<String, String>>,
+    /// A string whose presence in the body rules the match out
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub negative_signature: String,
+    /// Additional strings that must not be in the body
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub not_contains: Vec<String>,
+    /// Only download and match bodies with this Content-Type, e.g. `application/json`
+    /// or `text/*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Redirect policy for the rule's requests: `false` or a maximum number of hops
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub follow_redirects: Option<FollowRedirects>,
+    /// Statuses that show the path exists, e.g. `[200, 401, 403]`: error statuses
+    /// listed report a protected finding, and 2xx always counts, so `[]` reports
+    /// successes only. Replaces the scan's `--interesting-status` list for this rule;
+    /// also read as `exists_status`.
+    #[serde(
+        default,
+        alias = "exists_status",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub interesting_status: Option<Vec<u16>>,
     #[serde(default)]
     pub description: Option<String>,
     #[serde(default)]
     pub severity: Option<Severity>,
+    /// What to do about a finding of the rule, carried into streamed findings and
+    /// exports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
 }
 
 impl Rule {
@@ -82,14 +264,386 @@ impl Rule {
             name: name.to_string(),
             path: path.to_string(),
             signature: signature.to_string(),
+            signatures: Vec::new(),
+            match_mode: MatchMode::Any,
+            translations: BTreeMap::new(),
+            negative_signature: String::new(),
+            not_contains: Vec::new(),
+            content_type: None,
+            follow_redirects: None,
+            interesting_status: None,
             description: Some(description.to_string()),
             severity: Some(severity),
+            remediation: None,
+        }
+    }
+
+    /// Add signatures and set how they combine
+    #[allow(dead_code)]
+    pub fn with_signatures(mut self, signatures: &[&str], match_mode: MatchMode) -> Self {
+        self.signatures
+            .extend(signatures.iter().map(|s| s.to_string()));
+        self.match_mode = match_mode;
+        self
+    }
+
+    /// Add localized versions of a signature, as language tag and text pairs
+    #[allow(dead_code)]
+    pub fn with_translations(mut self, signature: &str, translations: &[(&str, &str)]) -> Self {
+        self.translations
+            .entry(signature.to_string())
+            .or_default()
+            .extend(
+                translations
+                    .iter()
+                    .map(|(language, text)| (language.to_string(), text.to_string())),
+            );
+        self
+    }
+
+    /// Add strings that must not be in the body
+    #[allow(dead_code)]
+    pub fn with_not_contains(mut self, negatives: &[&str]) -> Self {
+        self.not_contains
+            .extend(negatives.iter().map(|s| s.to_string()));
+        self
+    }
+
+    /// Set how the rule's requests follow redirects
+    #[allow(dead_code)]
+    pub fn with_follow_redirects(mut self, follow_redirects: FollowRedirects) -> Self {
+        self.follow_redirects = Some(follow_redirects);
+        self
+    }
+
+    /// Only match bodies with the given Content-Type
+    #[allow(dead_code)]
+    pub fn with_content_type(mut self, content_type: &str) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self
+    }
+
+    /// Treat these error statuses as "exists but protected" for this rule
+    #[allow(dead_code)]
+    pub fn with_interesting_status(mut self, statuses: &[u16]) -> Self {
+        self.interesting_status = Some(statuses.to_vec());
+        self
+    }
+
+    /// Set what to do about a finding of the rule
+    #[allow(dead_code)]
+    pub fn with_remediation(mut self, remediation: &str) -> Self {
+        self.remediation = Some(remediation.to_string());
+        self
+    }
+
+    /// Whether a response with this Content-Type header should be downloaded and matched.
+    ///
+    /// Parameters such as `charset` are ignored; without a filter everything passes,
+    /// with one a response lacking the header doesn't.
+    pub fn accepts_content_type(&self, content_type: Option<&str>) -> bool {
+        let Some(filter) = &self.content_type else {
+            return true;
+        };
+        let Some(content_type) = content_type else {
+            return false;
+        };
+
+        let media_type = content_type.split(';').next().unwrap_or("").trim();
+        match filter.strip_suffix("/*") {
+            Some(top_level) => media_type
+                .split_once('/')
+                .is_some_and(|(kind, _)| kind.eq_ignore_ascii_case(top_level)),
+            None => media_type.eq_ignore_ascii_case(filter.trim()),
+        }
+    }
+
+    /// Number of redirects the rule's requests follow
+    pub fn max_redirects(&self) -> usize {
+        self.follow_redirects
+            .map_or(DEFAULT_MAX_REDIRECTS, |follow| follow.limit())
+    }
+
+    /// Whether `status` shows the path exists but is protected: one of the rule's
+    /// interesting statuses, or the scan's `default` ones if the rule sets none
+    pub fn is_interesting_status(&self, status: u16, default: &[u16]) -> bool {
+        self.interesting_status
+            .as_deref()
+            .unwrap_or(default)
+            .contains(&status)
+    }
+
+    /// Every signature of the rule, `signature` first
+    pub fn all_signatures(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.signature.as_str())
+            .filter(|s| !s.is_empty())
+            .chain(self.signatures.iter().map(String::as_str))
+    }
+
+    /// Every signature of the rule with its translations, `all_signatures` order: the
+    /// texts of one group are alternatives
+    pub fn signature_groups(&self) -> impl Iterator<Item = Vec<&str>> {
+        self.all_signatures().map(|signature| {
+            let translations = self
+                .translations
+                .get(signature)
+                .into_iter()
+                .flat_map(|translations| translations.values().map(String::as_str));
+            std::iter::once(signature).chain(translations).collect()
+        })
+    }
+
+    /// Every text that counts towards a match: the signatures and their translations
+    fn signature_texts(&self) -> impl Iterator<Item = &str> {
+        self.all_signatures().chain(
+            self.translations
+                .values()
+                .flat_map(|translations| translations.values().map(String::as_str)),
+        )
+    }
+
+    /// Every negative matcher of the rule, `negative_signature` first
+    pub fn negative_signatures(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.negative_signature.as_str())
+            .filter(|s| !s.is_empty())
+            .chain(self.not_contains.iter().map(String::as_str))
+    }
+
+    /// Every field that may hold `{{...}}` placeholders
+    fn templated_fields(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.path.as_str())
+            .chain(self.signature_texts())
+            .chain(self.negative_signatures())
+    }
+
+    /// Whether the rule has placeholders to expand per domain
+    pub fn is_template(&self) -> bool {
+        self.templated_fields()
+            .any(|f| next_placeholder(f).is_some())
+    }
+
+    /// The rule with its placeholders expanded for one domain, borrowed as-is
+    /// when it has none
+    pub fn expand(&self, vars: &TemplateVars) -> Cow<'_, Rule> {
+        if !self.is_template() {
+            return Cow::Borrowed(self);
+        }
+
+        let mut rule = self.clone();
+        rule.path = vars.expand(&self.path);
+        rule.signature = vars.expand(&self.signature);
+        rule.signatures = self.signatures.iter().map(|s| vars.expand(s)).collect();
+        rule.translations = self
+            .translations
+            .iter()
+            .map(|(signature, translations)| {
+                let translations = translations
+                    .iter()
+                    .map(|(language, text)| (language.clone(), vars.expand(text)))
+                    .collect();
+                (vars.expand(signature), translations)
+            })
+            .collect();
+        rule.negative_signature = vars.expand(&self.negative_signature);
+        rule.not_contains = self.not_contains.iter().map(|s| vars.expand(s)).collect();
+        Cow::Owned(rule)
+    }
+
+    /// Whether a response body matches the rule's signatures and none of its
+    /// negative matchers.
+    ///
+    /// Signatures are compared byte for byte, so binary bodies need no decoding.
+    pub fn matches(&self, body: impl AsRef<[u8]>) -> bool {
+        let body = body.as_ref();
+        let contains = |s: &str| memmem::find(body, s.as_bytes()).is_some();
+
+        let mut groups = self.signature_groups();
+        let positive = match self.match_mode {
+            MatchMode::Any => groups.any(|group| group.into_iter().any(contains)),
+            MatchMode::All => groups.all(|group| group.into_iter().any(contains)),
+        };
+        positive && !self.negative_signatures().any(contains)
+    }
+
+    /// Byte offset of the earliest signature, or translation of one, in a response body
+    pub fn match_offset(&self, body: impl AsRef<[u8]>) -> Option<usize> {
+        let body = body.as_ref();
+        self.signature_texts()
+            .filter_map(|s| memmem::find(body, s.as_bytes()))
+            .min()
+    }
+
+    /// Check that the rule can match anything
+    pub fn validate(&self) -> Result<()> {
+        if self.all_signatures().next().is_none() {
+            anyhow::bail!("Rule '{}' has no signature", self.name);
+        }
+        for (signature, translations) in &self.translations {
+            if !self.all_signatures().any(|s| s == signature) {
+                anyhow::bail!(
+                    "Rule '{}' translates '{}', which isn't one of its signatures",
+                    self.name,
+                    signature
+                );
+            }
+            for (language, text) in translations {
+                if !is_language_tag(language) {
+                    anyhow::bail!(
+                        "Rule '{}' has an invalid language tag '{}' (expected e.g. de or pt-BR)",
+                        self.name,
+                        language
+                    );
+                }
+                if text.is_empty() {
+                    anyhow::bail!(
+                        "Rule '{}' has an empty {} translation of '{}'",
+                        self.name,
+                        language,
+                        signature
+                    );
+                }
+            }
+        }
+        if let Some(s) = self
+            .negative_signatures()
+            .find(|n| self.signature_texts().any(|s| s == *n))
+        {
+            anyhow::bail!(
+                "Rule '{}' lists '{}' as both a signature and a negative signature",
+                self.name,
+                s
+            );
+        }
+        if let Some(content_type) = &self.content_type {
+            if !content_type.contains('/') {
+                anyhow::bail!(
+                    "Rule '{}' has an invalid content_type '{}' (expected e.g. application/json or text/*)",
+                    self.name,
+                    content_type
+                );
+            }
+        }
+        if let Some(statuses) = &self.interesting_status {
+            validate_interesting_status(statuses).context(format!(
+                "Rule '{}' has an invalid interesting_status",
+                self.name
+            ))?;
+        }
+        if self.max_redirects() > MAX_REDIRECTS {
+            anyhow::bail!(
+                "Rule '{}' follows {} redirects, at most {} are allowed",
+                self.name,
+                self.max_redirects(),
+                MAX_REDIRECTS
+            );
+        }
+        for field in self.templated_fields() {
+            let mut rest = field;
+            while let Some((_, name, after)) = next_placeholder(rest) {
+                if !TEMPLATE_VARIABLES.contains(&name) {
+                    anyhow::bail!(
+                        "Rule '{}' uses unknown placeholder '{{{{{}}}}}' (expected one of: {})",
+                        self.name,
+                        name,
+                        TEMPLATE_VARIABLES.join(", ")
+                    );
+                }
+                rest = after;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `tag` looks like a BCP 47 language tag: a two or three letter language,
+/// optionally followed by subtags such as a region (`pt-BR`) or script (`zh-Hant`)
+fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let language = subtags.next().unwrap_or_default();
+    (2..=3).contains(&language.len())
+        && language.bytes().all(|b| b.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+        })
+}
+
+/// Check a list of "exists but protected" statuses: 4xx and 5xx codes, and 2xx ones,
+/// which already count as existing, so `[200]` alone reports successes only.
+/// Informational statuses never end a request and redirects are followed.
+pub fn validate_interesting_status(statuses: &[u16]) -> Result<()> {
+    if let Some(status) = statuses
+        .iter()
+        .find(|s| !(200..300).contains(*s) && !(400..600).contains(*s))
+    {
+        anyhow::bail!(
+            "{} can't show a path exists (expected 2xx, 4xx or 5xx, e.g. 401 or 403)",
+            status
+        );
+    }
+    Ok(())
+}
+
+/// Matches a rule's signatures against a body as it streams in, so a download can
+/// stop once more of the body can't change the outcome
+#[derive(Debug)]
+pub struct SignatureScan<'a> {
+    rule: &'a Rule,
+
+    /// Whether each signature, or a translation of it, has been seen, in
+    /// `all_signatures` order
+    found: Vec<bool>,
+
+    /// Bytes of the body searched so far
+    scanned: usize,
+
+    /// Bytes re-searched from the previous chunk, so signatures split across chunks are found
+    overlap: usize,
+}
+
+impl<'a> SignatureScan<'a> {
+    pub fn new(rule: &'a Rule) -> Self {
+        Self {
+            rule,
+            found: vec![false; rule.all_signatures().count()],
+            scanned: 0,
+            overlap: rule
+                .signature_texts()
+                .map(|s| s.len().saturating_sub(1))
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Search the part of `body` (the whole body so far) that arrived since the last call;
+    /// returns whether the rule is settled
+    pub fn update(&mut self, body: &[u8]) -> bool {
+        let window = &body[self.scanned.saturating_sub(self.overlap)..];
+        for (found, group) in self.found.iter_mut().zip(self.rule.signature_groups()) {
+            *found = *found
+                || group
+                    .iter()
+                    .any(|text| memmem::find(window, text.as_bytes()).is_some());
+        }
+        self.scanned = body.len();
+
+        self.is_settled()
+    }
+
+    /// Whether the rule matches no matter what the rest of the body holds. Rules with
+    /// negative matchers are never settled early, since one could still turn up.
+    pub fn is_settled(&self) -> bool {
+        if self.rule.negative_signatures().next().is_some() {
+            return false;
+        }
+        match self.rule.match_mode {
+            MatchMode::Any => self.found.iter().any(|found| *found),
+            MatchMode::All => self.found.iter().all(|found| *found),
         }
     }
 }
 
 /// Collection of rules from a rules file
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct RuleSet {
     pub rules: Vec<Rule>,
 }
@@ -97,16 +651,26 @@ pub struct RuleSet {
 impl RuleSet {
     /// Load rules from a YAML file
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let file = File::open(path.as_ref()).context(format!(
+        let source = std::fs::read_to_string(path.as_ref()).context(format!(
             "Failed to open rules file: {}",
             path.as_ref().display()
         ))?;
 
-        let reader = BufReader::new(file);
-        let mut ruleset: RuleSet = serde_yaml::from_reader(reader).context(format!(
-            "Failed to parse rules file: {}",
-            path.as_ref().display()
-        ))?;
+        // serde_yaml stops at the first problem; name every one of them instead
+        let mut ruleset: RuleSet = serde_yaml::from_str(&source)
+            .map_err(|err| match rule_lint::lint_rules(&source) {
+                issues if issues.is_empty() => anyhow::Error::new(err),
+                issues => anyhow::anyhow!("{}", rule_lint::describe(&issues)),
+            })
+            .context(format!(
+                "Failed to parse rules file: {}",
+                path.as_ref().display()
+            ))?;
+
+        for rule in &ruleset.rules {
+            rule.validate()
+                .context(format!("Invalid rule in {}", path.as_ref().display()))?;
+        }
 
         // Sort rules by severity (highest first)
         ruleset.sort_by_severity();
@@ -118,12 +682,23 @@ impl RuleSet {
         );
 
         for rule in &ruleset.rules {
-            logger::log_rule_loaded(&rule.name, 1); // Just count the signature as 1 pattern
+            logger::log_rule_loaded(&rule.name, rule.all_signatures().count());
         }
 
         Ok(ruleset)
     }
 
+    /// Short fingerprint of the rules' definitions, so a set of rules can be recognised
+    /// across scans
+    pub fn digest(&self) -> String {
+        // FNV-1a: stable across builds, unlike the standard library's hasher
+        let json = serde_json::to_vec(&self.rules).unwrap_or_default();
+        let hash = json.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}", hash)
+    }
+
     /// Sort rules by severity (highest first)
     pub fn sort_by_severity(&mut self) {
         self.rules.sort_by(|a, b| {
@@ -138,11 +713,91 @@ impl RuleSet {
     }
 }
 
+/// One version of the rules a scan runs with
+#[derive(Debug)]
+pub struct RuleSnapshot {
+    /// Version within the scan, starting at 1 and increasing with every reload
+    pub version: u32,
+
+    /// When this version was loaded
+    pub loaded_at: DateTime<Utc>,
+
+    pub ruleset: RuleSet,
+
+    /// The rules' digest, worked out once as every finding carries it
+    pub digest: String,
+}
+
+impl RuleSnapshot {
+    pub(crate) fn new(version: u32, ruleset: RuleSet) -> Self {
+        Self {
+            version,
+            loaded_at: Utc::now(),
+            digest: ruleset.digest(),
+            ruleset,
+        }
+    }
+}
+
+/// The rules of a running scan, which can be replaced while it runs.
+///
+/// Each domain takes a snapshot before its checks start, so all of a domain's checks use
+/// the same version and domains already under way are unaffected by a reload.
+#[derive(Debug)]
+pub struct LiveRuleSet {
+    current: RwLock<Arc<RuleSnapshot>>,
+}
+
+impl LiveRuleSet {
+    pub fn new(ruleset: RuleSet) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(RuleSnapshot::new(1, ruleset))),
+        }
+    }
+
+    /// The current version of the rules
+    pub fn snapshot(&self) -> Arc<RuleSnapshot> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Make `ruleset` the next version, returning it
+    pub fn replace(&self, ruleset: RuleSet) -> Arc<RuleSnapshot> {
+        let mut current = self.current.write().unwrap();
+        *current = Arc::new(RuleSnapshot::new(current.version + 1, ruleset));
+        current.clone()
+    }
+}
+
 /// Load rules from a YAML file
 pub fn load_rules(rules_file: &str) -> Result<RuleSet> {
     RuleSet::from_file(rules_file)
 }
 
+/// Load and merge rules from several YAML files.
+///
+/// Rule names must be unique, so a rule whose name was already loaded from an
+/// earlier file is skipped.
+pub fn load_rules_files(rules_files: &[&str]) -> Result<RuleSet> {
+    let mut merged = RuleSet { rules: Vec::new() };
+
+    for rules_file in rules_files {
+        for rule in RuleSet::from_file(rules_file)?.rules {
+            if merged.rules.iter().any(|r| r.name == rule.name) {
+                warn!(
+                    "⚠️ Skipping duplicate rule '{}' from {}",
+                    rule.name, rules_file
+                );
+                continue;
+            }
+            merged.rules.push(rule);
+        }
+    }
+
+    merged.sort_by_severity();
+
+    Ok(merged)
+}
+
 /// Add a new rule to the rules file
 pub fn add_rule(yaml_file: &str) -> Result<()> {
     // This function would parse the provided YAML file and add the rules
@@ -1,106 +1,1016 @@
 use anyhow::{Context, Result};
-use reqwest::Client;
-use rusqlite::Connection;
+use bytes::{Bytes, BytesMut};
+use chrono::Utc;
+use memchr::memmem;
+use reqwest::header::{HeaderMap, CONTENT_TYPE, LOCATION, USER_AGENT};
+use reqwest::{Client, StatusCode};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::Mutex;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, warn, Instrument};
 
+use crate::anomaly;
+use crate::brute::DnsBrute;
+use crate::canary::{self, Canaries};
 use crate::config::ScanConfig;
-use crate::db;
+use crate::context::ScanContext;
+use crate::db::{self, DomainScan, PurgeFilter};
+use crate::email;
+use crate::evasion;
+use crate::evidence::{CacheValidators, Evidence};
+use crate::exit::{Classify, Failure};
+use crate::expand::{CommandExpander, TargetExpander};
+use crate::expected::{self, UnexpectedFindings};
 use crate::logger;
-use crate::resolver::DnsResolver;
-use crate::rules::RuleSet;
-use crate::utils;
+use crate::notify::{self, FindingNotification, FindingNotifier};
+use crate::otel;
+use crate::pipeline::{self, PipelineMetrics, PipelineOptions};
+use crate::redact::RedactionPolicy;
+use crate::resolver::{self, DnsResolver};
+use crate::retention;
+use crate::risk;
+use crate::rule_watch::RuleWatcher;
+use crate::rules::{LiveRuleSet, Rule, RuleSet, SignatureScan, DEFAULT_MAX_REDIRECTS};
+use crate::shutdown::{ScanInterrupted, Shutdown, ShutdownState};
+use crate::stats::ScanStats;
+use crate::store::{self, FindingDetails, PendingFinding, SharedStore, StoreKind};
+use crate::stream::{EventStream, StreamEvent};
+use crate::throttle::{AsnDb, Throttle};
+use crate::timeouts::{self, PhaseTimeout, TimeoutPhase, Timeouts};
+use crate::upload;
+use crate::user_agent::UserAgentPool;
+use crate::utils::{self, RetryPolicy};
+use crate::window::{ScanWindow, WindowGate};
+use crate::workdir::{ScanState, ScanStatus, ScanSummary, Workdir};
+use crate::writer::ResultWriter;
+
+/// Default cap on the bytes of a response body downloaded and matched
+pub const DEFAULT_MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// Statuses that show a path exists behind authentication, unless a scan or rule sets
+/// its own list
+pub const DEFAULT_INTERESTING_STATUS: &[u16] = &[401, 403];
+
+/// Rule checks in flight against one domain by default, so a large ruleset doesn't
+/// flood a single host
+pub const DEFAULT_RULE_CONCURRENCY: usize = 4;
+
+/// Per-scan settings applied to every domain
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Retry policy for HTTP checks
+    pub retry: RetryPolicy,
+
+    /// URL schemes to check each domain over
+    pub schemes: Vec<String>,
+
+    /// Ports to check each domain on (empty means the scheme's default port)
+    pub ports: Vec<u16>,
+
+    /// Consecutive failed checks before a domain's remaining checks are skipped (0 disables)
+    pub circuit_breaker: usize,
+
+    /// Rule checks in flight against one domain at a time
+    pub rule_concurrency: usize,
+
+    /// Most random delay before each request in milliseconds (0 disables)
+    pub jitter_ms: u64,
+
+    /// Count response header bytes in the network statistics
+    pub capture_headers: bool,
+
+    /// Send browser-like headers in a random order
+    pub evasion: bool,
+
+    /// Most bytes of a response body downloaded and matched
+    pub max_body_bytes: usize,
+
+    /// Error statuses that show a path exists but is protected, for rules that set none
+    pub interesting_status: Vec<u16>,
+
+    /// Time budget of each phase of a request
+    pub timeouts: Timeouts,
+
+    /// Whether requests go through a proxy, so hosts are never contacted directly
+    pub proxied: bool,
+
+    /// Secrets masked before findings are logged or stored
+    pub redaction: RedactionPolicy,
+
+    /// Project findings are labelled with
+    pub project: Option<String>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            retry: RetryPolicy::default(),
+            schemes: vec!["http".to_string()],
+            ports: Vec::new(),
+            circuit_breaker: 10,
+            rule_concurrency: DEFAULT_RULE_CONCURRENCY,
+            jitter_ms: 0,
+            capture_headers: false,
+            evasion: false,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            interesting_status: DEFAULT_INTERESTING_STATUS.to_vec(),
+            timeouts: Timeouts::default(),
+            proxied: false,
+            redaction: RedactionPolicy::default(),
+            project: None,
+        }
+    }
+}
+
+impl ScanOptions {
+    /// Build scan options from a scan configuration
+    pub fn from_config(config: &ScanConfig) -> Self {
+        Self {
+            retry: config.retry_policy(),
+            schemes: config.schemes.clone(),
+            ports: config.ports.clone(),
+            circuit_breaker: config.circuit_breaker,
+            rule_concurrency: config.rule_concurrency,
+            jitter_ms: config.jitter_ms,
+            capture_headers: config.capture_headers,
+            evasion: config.evasion,
+            max_body_bytes: config.max_body_bytes,
+            interesting_status: config.interesting_status.clone(),
+            timeouts: config.timeouts(),
+            proxied: config.proxy.is_some(),
+            // Patterns are checked by validate(); the built-in ones apply regardless
+            redaction: config.redaction_policy().unwrap_or_default(),
+            project: config.project.clone(),
+        }
+    }
+
+    /// Base URLs for every scheme/port combination of a domain. A domain given with
+    /// its own port (`example.com:8443`, `[2001:db8::1]:8443`) is only checked on it.
+    pub fn targets(&self, domain: &str) -> Vec<String> {
+        let (host, port) = utils::split_host_port(domain);
+        let ports: Vec<Option<u16>> = match port {
+            Some(port) => vec![Some(port)],
+            None if self.ports.is_empty() => vec![None],
+            None => self.ports.iter().copied().map(Some).collect(),
+        };
+
+        let mut targets = Vec::new();
+        for scheme in &self.schemes {
+            for port in &ports {
+                match utils::base_url(scheme, host, *port) {
+                    Ok(target) if !targets.contains(&target) => targets.push(target),
+                    Ok(_) => {}
+                    Err(e) => warn!("Skipping {} over {}: {:#}", domain, scheme, e),
+                }
+            }
+        }
+
+        targets
+    }
+}
+
+/// Resources shared by every domain in a scan
+#[derive(Clone)]
+pub struct ScanResources {
+    /// HTTP client used for all checks
+    pub client: Client,
+
+    /// Rules to check each domain against, replaced when they're reloaded
+    pub ruleset: Arc<LiveRuleSet>,
+
+    /// DNS resolver with its cache
+    pub resolver: Arc<DnsResolver>,
+
+    /// Where results are recorded
+    pub store: SharedStore,
+
+    /// Writes rule check outcomes to `store` in batches
+    pub writer: ResultWriter,
+
+    /// Per-scan settings
+    pub options: Arc<ScanOptions>,
+
+    /// Number of rule checks completed
+    pub tasks_completed: Arc<AtomicUsize>,
+
+    /// Number of detected findings
+    pub matches_found: Arc<AtomicUsize>,
+
+    /// Number of detected findings revalidated as unchanged since the last scan
+    pub findings_unchanged: Arc<AtomicUsize>,
+
+    /// Network statistics
+    pub stats: Arc<ScanStats>,
+
+    /// User-Agents to rotate through; the client's default is used when unset
+    pub user_agents: Option<Arc<UserAgentPool>>,
+
+    /// Live event stream findings are published to, if enabled
+    pub events: Option<Arc<EventStream>>,
+
+    /// Requests to stop the scan early
+    pub shutdown: Shutdown,
+
+    /// Concurrency limits per TLD, ASN and subnet
+    pub throttle: Arc<Throttle>,
+
+    /// Scan session what's seen of each domain is recorded under; unset, it isn't
+    /// recorded
+    pub scan_id: Option<String>,
+
+    /// Subdomain brute force run on apex domains in the input, if enabled
+    pub brute: Option<Arc<DnsBrute>>,
+
+    /// Turns each input entry into the targets scanned for it, if configured
+    pub expander: Option<Arc<dyn TargetExpander>>,
+
+    /// Domain and rule pairs marked as false positives that aren't checked
+    pub skipped: Arc<HashSet<(String, String)>>,
+
+    /// Sends findings to the finding webhooks as they're detected, if any are configured
+    pub notifier: Option<Arc<FindingNotifier>>,
+
+    /// Earlier findings to revalidate with conditional requests, by domain and rule
+    pub revalidations: Arc<HashMap<(String, String), Revalidation>>,
+
+    /// Daily window outside which no traffic is sent, if the scan has one
+    pub window: Option<Arc<WindowGate>>,
+}
+
+/// What an earlier scan detected of a domain and rule, so checking it again can ask
+/// the target whether the response changed instead of downloading it
+#[derive(Debug, Clone)]
+pub struct Revalidation {
+    /// Path, or URL on a domain with several targets, the finding was observed at
+    pub matched_path: String,
+
+    /// Digest of the rules the finding was detected with; other rules may no longer
+    /// match the same response
+    pub ruleset_digest: String,
+
+    /// Evidence of the finding, with the validators of its response
+    pub evidence: Evidence,
+}
+
+impl Revalidation {
+    /// What can be revalidated of a stored finding: a detection with evidence carrying
+    /// cache validators
+    pub fn from_finding(finding: &db::Finding) -> Option<Self> {
+        let evidence = finding.evidence.as_ref()?;
+        if !finding.detected || evidence.validators.is_none() {
+            return None;
+        }
+        Some(Self {
+            matched_path: finding.matched_path.clone(),
+            ruleset_digest: finding.ruleset_digest.clone()?,
+            evidence: evidence.clone(),
+        })
+    }
+}
+
+impl ScanResources {
+    /// Bundle the resources of a scan with fresh counters
+    pub fn new(
+        client: Client,
+        ruleset: RuleSet,
+        resolver: Arc<DnsResolver>,
+        store: SharedStore,
+        options: ScanOptions,
+    ) -> Self {
+        Self {
+            client,
+            ruleset: Arc::new(LiveRuleSet::new(ruleset)),
+            resolver,
+            writer: ResultWriter::start(store.clone()),
+            store,
+            stats: Arc::new(ScanStats::new(options.capture_headers)),
+            options: Arc::new(options),
+            tasks_completed: Arc::new(AtomicUsize::new(0)),
+            matches_found: Arc::new(AtomicUsize::new(0)),
+            findings_unchanged: Arc::new(AtomicUsize::new(0)),
+            user_agents: None,
+            events: None,
+            shutdown: Shutdown::default(),
+            throttle: Arc::new(Throttle::default()),
+            scan_id: None,
+            brute: None,
+            expander: None,
+            skipped: Arc::default(),
+            notifier: None,
+            revalidations: Arc::default(),
+            window: None,
+        }
+    }
+
+    /// Rotate requests through a pool of User-Agents
+    pub fn with_user_agents(mut self, user_agents: UserAgentPool) -> Self {
+        self.user_agents = Some(Arc::new(user_agents));
+        self
+    }
+
+    /// Write findings to the stores of a sharded database's shards, each from a thread
+    /// of its own (see [`store::open_shard_writers`])
+    pub fn with_shard_writers(mut self, shards: Vec<SharedStore>) -> Self {
+        if !shards.is_empty() {
+            self.writer = ResultWriter::start_sharded(self.store.clone(), shards);
+        }
+        self
+    }
+
+    /// Publish findings to a live event stream
+    pub fn with_events(mut self, events: Arc<EventStream>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Limit concurrent requests to domains sharing a TLD, ASN or subnet
+    pub fn with_throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = Arc::new(throttle);
+        self
+    }
+
+    /// Brute force subdomains of apex domains, scanning the ones that resolve
+    pub fn with_dns_brute(mut self, brute: DnsBrute) -> Self {
+        self.brute = Some(Arc::new(brute));
+        self
+    }
+
+    /// Send findings to webhooks as they're detected
+    pub fn with_notifier(mut self, notifier: FindingNotifier) -> Self {
+        self.notifier = Some(Arc::new(notifier));
+        self
+    }
+
+    /// Revalidate these earlier findings with conditional requests
+    pub fn with_revalidations(
+        mut self,
+        revalidations: Vec<((String, String), Revalidation)>,
+    ) -> Self {
+        self.revalidations = Arc::new(revalidations.into_iter().collect());
+        self
+    }
+
+    /// Validators to send with a rule's request to `url`, if an earlier scan detected
+    /// the rule there with the same rules
+    pub fn validators_for(
+        &self,
+        ctx: &ScanContext,
+        rule: &Rule,
+        url: &str,
+    ) -> Option<&CacheValidators> {
+        if self.revalidations.is_empty() {
+            return None;
+        }
+        let revalidation = self
+            .revalidations
+            .get(&(ctx.domain.clone(), rule.name.clone()))?;
+        let same_url = if ctx.targets.len() == 1 {
+            revalidation.matched_path == rule.path
+        } else {
+            revalidation.matched_path == url
+        };
+        if !same_url || revalidation.ruleset_digest != ctx.rules.digest {
+            return None;
+        }
+        revalidation.evidence.validators.as_ref()
+    }
+
+    /// Scan the targets each input entry expands into instead of the entry itself
+    pub fn with_expander(mut self, expander: Arc<dyn TargetExpander>) -> Self {
+        self.expander = Some(expander);
+        self
+    }
+
+    /// Only send traffic inside a daily window, pausing outside it
+    pub fn with_window(mut self, window: ScanWindow) -> Self {
+        self.window = Some(Arc::new(WindowGate::new(window)));
+        self
+    }
+
+    /// Wait until the scan window, if any, is open
+    pub async fn wait_for_window(&self) {
+        if let Some(window) = &self.window {
+            window.wait().await;
+        }
+    }
+
+    /// Leave out the checks of these domain and rule pairs
+    pub fn with_skipped(mut self, skipped: HashSet<(String, String)>) -> Self {
+        self.skipped = Arc::new(skipped);
+        self
+    }
+
+    /// Whether the check of a rule against a domain is left out
+    pub fn is_skipped(&self, domain: &str, rule_name: &str) -> bool {
+        !self.skipped.is_empty()
+            && self
+                .skipped
+                .contains(&(domain.to_string(), rule_name.to_string()))
+    }
+
+    /// Record what's seen of each domain under a scan session
+    pub fn with_scan_id(mut self, scan_id: &str) -> Self {
+        self.scan_id = Some(scan_id.to_string());
+        self
+    }
+
+    /// Record what preparing a domain found: its addresses and technologies, or why
+    /// it couldn't be scanned
+    pub async fn record_domain(&self, domain: &str, prepared: &Result<ScanContext>) {
+        let Some(scan_id) = &self.scan_id else {
+            return;
+        };
+
+        let mut scan = DomainScan {
+            scan_id: scan_id.clone(),
+            domain: domain.to_string(),
+            scanned_at: Utc::now(),
+            ips: Vec::new(),
+            technologies: Vec::new(),
+            error: None,
+            certificate: None,
+        };
+        match prepared {
+            Ok(ctx) => {
+                scan.ips = ctx.ips.iter().map(|ip| ip.to_string()).collect();
+                scan.technologies = ctx.technologies.clone();
+                scan.certificate = ctx.certificate.clone();
+            }
+            Err(e) => scan.error = Some(format!("{:#}", e)),
+        }
+        self.writer.write_domain(scan).await;
+    }
+
+    /// User-Agent to use for the next check against `domain`, if rotating
+    pub fn user_agent(&self, domain: &str) -> Option<String> {
+        self.user_agents
+            .as_ref()
+            .map(|pool| pool.pick(domain).to_string())
+    }
+
+    /// Fetch a URL with a GET request, recording it in the scan statistics
+    pub async fn fetch(&self, url: &str, user_agent: Option<&str>) -> Result<Page> {
+        self.fetch_following(url, user_agent, DEFAULT_MAX_REDIRECTS)
+            .await
+    }
+
+    /// Fetch a URL with a GET request, following at most `max_redirects` redirects.
+    ///
+    /// Every hop is recorded in the scan statistics, and the URLs redirected to are
+    /// kept in the page's `redirects`. Once the limit is reached the redirect response
+    /// itself is returned.
+    pub async fn fetch_following(
+        &self,
+        url: &str,
+        user_agent: Option<&str>,
+        max_redirects: usize,
+    ) -> Result<Page> {
+        let (response, redirects) = self.follow(url, user_agent, max_redirects, None).await?;
+        self.read(response, redirects, |_| false).await
+    }
+
+    /// Fetch a rule's URL with a single GET, following the rule's redirect policy.
+    ///
+    /// The body is only downloaded when the status shows the path exists and the
+    /// response's Content-Type passes the rule's `content_type` filter; otherwise the page
+    /// is returned with an empty body. The download stops as soon as the rule is known to
+    /// match. With `validators` the request is conditional, and a 304 Not Modified is
+    /// returned as is.
+    pub async fn fetch_for_rule(
+        &self,
+        ctx: &ScanContext,
+        url: &str,
+        user_agent: Option<&str>,
+        rule: &Rule,
+        validators: Option<&CacheValidators>,
+    ) -> Result<Page> {
+        let (response, redirects) = self
+            .follow(url, user_agent, rule.max_redirects(), validators)
+            .await?;
+
+        // A miss is settled by its status alone
+        if !path_exists(response.status().as_u16(), rule.max_redirects()) {
+            let mut page = Page::without_body(&response);
+            page.redirects = redirects;
+            self.stats.record_response(page.status, &page.headers, 0);
+            return Ok(page);
+        }
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        if !rule.accepts_content_type(content_type) {
+            debug!(
+                "⏭️ Skipping {} body of {}",
+                content_type.unwrap_or("untyped"),
+                url
+            );
+            let mut page = Page::without_body(&response);
+            page.redirects = redirects;
+            self.stats.record_response(page.status, &page.headers, 0);
+            return Ok(page);
+        }
+
+        // A response that could be the wildcard page is read in full to compare against it
+        let may_be_wildcard = ctx
+            .soft_404
+            .as_ref()
+            .is_some_and(|fingerprint| fingerprint.status == response.status().as_u16());
+        let mut scan = SignatureScan::new(rule);
+        self.read(response, redirects, |body| {
+            !may_be_wildcard && scan.update(body)
+        })
+        .await
+    }
+
+    /// Send GET requests, following at most `max_redirects` redirects, and return the
+    /// final response unread with the URLs redirected to. Every request is made
+    /// conditional on `validators`, if given.
+    async fn follow(
+        &self,
+        url: &str,
+        user_agent: Option<&str>,
+        max_redirects: usize,
+        validators: Option<&CacheValidators>,
+    ) -> Result<(reqwest::Response, Vec<String>)> {
+        let mut current = url::Url::parse(url).context(format!("Invalid URL: {}", url))?;
+        let mut redirects = Vec::new();
+
+        loop {
+            let mut builder = self.client.get(current.clone());
+            if let Some(validators) = validators {
+                builder = validators.apply(builder);
+            }
+            let response = self.send(builder, user_agent).await?;
+
+            if redirects.len() < max_redirects {
+                if let Some(next) = redirect_target(&current, &response) {
+                    self.stats
+                        .record_response(response.status().as_u16(), response.headers(), 0);
+                    redirects.push(next.to_string());
+                    current = next;
+                    continue;
+                }
+            }
+
+            return Ok((response, redirects));
+        }
+    }
+
+    /// Download a response into a page, up to the scan's body size limit or until `done`
+    /// says the body so far is enough, recording it in the scan statistics
+    async fn read(
+        &self,
+        response: reqwest::Response,
+        redirects: Vec<String>,
+        done: impl FnMut(&[u8]) -> bool,
+    ) -> Result<Page> {
+        let mut page = Page::read_limited(response, self.options.max_body_bytes, done)
+            .await
+            .inspect_err(|e| {
+                // The body is only bounded by the request's total budget
+                if e.downcast_ref::<reqwest::Error>()
+                    .is_some_and(reqwest::Error::is_timeout)
+                {
+                    self.stats.record_timeout(TimeoutPhase::Total);
+                }
+            })?;
+        page.redirects = redirects;
+        self.stats
+            .record_response(page.status, &page.headers, page.body.len());
+
+        Ok(page)
+    }
+
+    async fn send(
+        &self,
+        mut builder: reqwest::RequestBuilder,
+        user_agent: Option<&str>,
+    ) -> Result<reqwest::Response> {
+        if self.options.evasion {
+            for (name, value) in evasion::browser_headers() {
+                builder = builder.header(name, value);
+            }
+        }
+        if let Some(user_agent) = user_agent {
+            builder = builder.header(USER_AGENT, user_agent);
+        }
+        let request = builder.build()?;
+        let url = request.url().clone();
+        self.stats.record_request(&request);
+
+        // The client bounds connecting and the whole request; the wait for the response
+        // headers once connected is bounded here
+        let timeouts = &self.options.timeouts;
+        let error = match tokio::time::timeout(
+            timeouts.response_headers(),
+            self.client
+                .execute(request)
+                .instrument(otel::span!("http", url = %url)),
+        )
+        .await
+        {
+            Ok(Ok(response)) => return Ok(response),
+            Ok(Err(e)) if e.is_timeout() => {
+                let phase = if !e.is_connect() {
+                    TimeoutPhase::Total
+                } else if self.options.proxied {
+                    TimeoutPhase::Connect
+                } else {
+                    timeouts::classify_handshake(&url, timeouts.connect).await
+                };
+                self.stats.record_timeout(phase);
+                let budget = match phase {
+                    TimeoutPhase::Total => timeouts.total,
+                    _ => timeouts.handshake(),
+                };
+                PhaseTimeout { phase, budget }.to_string()
+            }
+            Ok(Err(e)) => {
+                self.stats.record_error(&e);
+                e.to_string()
+            }
+            Err(_) => {
+                self.stats.record_timeout(TimeoutPhase::FirstByte);
+                PhaseTimeout {
+                    phase: TimeoutPhase::FirstByte,
+                    budget: timeouts.first_byte,
+                }
+                .to_string()
+            }
+        };
+
+        Err(anyhow::anyhow!("Failed to fetch {}: {}", url, error))
+    }
+}
+
+/// Where a redirect response points, resolved against the URL that returned it
+fn redirect_target(url: &url::Url, response: &reqwest::Response) -> Option<url::Url> {
+    if !response.status().is_redirection() {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    url.join(location).ok()
+}
+
+/// Settings used to build the HTTP client
+#[derive(Debug, Clone, Default)]
+pub struct HttpClientConfig {
+    /// Time budget of each phase of a request
+    pub timeouts: Timeouts,
+
+    /// Proxy URL (http://, https:// or socks5://), optionally with credentials
+    pub proxy: Option<String>,
+
+    /// Proxy credentials as `user:password`, overriding any in the proxy URL
+    pub proxy_auth: Option<String>,
+
+    /// Randomize the TLS fingerprint and present as a browser
+    pub evasion: bool,
+}
+
+impl HttpClientConfig {
+    /// Build HTTP client settings from a scan configuration
+    pub fn from_config(config: &ScanConfig) -> Self {
+        Self {
+            timeouts: config.timeouts(),
+            proxy: config.proxy.clone(),
+            proxy_auth: config.proxy_auth.clone(),
+            evasion: config.evasion,
+        }
+    }
+}
 
 /// Create an optimized HTTP client
+#[allow(dead_code)]
 pub fn create_http_client(timeout_secs: u64, connect_timeout_secs: u64) -> Result<Client> {
-    let timeout = Duration::from_secs(timeout_secs);
-    let connect_timeout = Duration::from_secs(connect_timeout_secs);
+    build_http_client(&HttpClientConfig {
+        timeouts: Timeouts {
+            total: Duration::from_secs(timeout_secs),
+            connect: Duration::from_secs(connect_timeout_secs),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
 
+/// Create an optimized HTTP client from the given settings
+pub fn build_http_client(settings: &HttpClientConfig) -> Result<Client> {
     // Create a connection pool using reqwest's connection manager
-    let client = Client::builder()
-        .timeout(timeout)
-        .connect_timeout(connect_timeout)
+    let mut builder = Client::builder()
+        .timeout(settings.timeouts.total)
+        .connect_timeout(settings.timeouts.handshake())
         .tcp_keepalive(Some(Duration::from_secs(30)))
         .tcp_nodelay(true)
+        .tls_info(true) // Keeps the certificates of hosts scanned over https
         .pool_idle_timeout(Some(Duration::from_secs(90)))
         .pool_max_idle_per_host(10) // Allow up to 10 idle connections per host
-        .use_rustls_tls() // Use RustTLS for better performance
-        .user_agent("FATT Security Scanner") // Set a user agent
-        .redirect(reqwest::redirect::Policy::limited(3)) // Limit redirects
-        .build()
-        .context("Failed to build HTTP client")?;
+        .redirect(reqwest::redirect::Policy::none()); // Redirects are followed per rule
+
+    builder = if settings.evasion {
+        // Randomized TLS fingerprint and a browser User-Agent
+        builder
+            .use_preconfigured_tls(evasion::tls_config()?)
+            .user_agent(evasion::BROWSER_USER_AGENT)
+    } else {
+        builder
+            .use_rustls_tls() // Use RustTLS for better performance
+            .user_agent("FATT Security Scanner") // Set a user agent
+    };
+
+    // Route all requests through the proxy, if configured
+    if let Some(proxy_url) = &settings.proxy {
+        let mut proxy =
+            reqwest::Proxy::all(proxy_url).context(format!("Invalid proxy URL: {}", proxy_url))?;
+
+        if let Some(auth) = &settings.proxy_auth {
+            let (username, password) = auth.split_once(':').unwrap_or((auth.as_str(), ""));
+            proxy = proxy.basic_auth(username, password);
+        }
+
+        builder = builder.proxy(proxy);
+        debug!("🔀 Routing requests through proxy");
+    }
+
+    let client = builder.build().context("Failed to build HTTP client")?;
 
     debug!("📡 Created optimized HTTP client");
 
     Ok(client)
 }
 
-/// Run a scanning session
+/// Run a scanning session, in its working directory if one is configured
 pub async fn run_scan(config: ScanConfig) -> Result<()> {
+    let Some(dir) = config.workdir.clone() else {
+        return scan(config, None).await;
+    };
+
+    let workdir = Workdir::create(&dir)?;
+    workdir.begin()?;
+    logger::set_log_file(&workdir.log_file())?;
+    info!("📁 Working directory: {}", workdir.root().display());
+
+    let result = scan(config, Some(&workdir)).await;
+    if let Err(e) = &result {
+        if let Err(state_error) = workdir.fail(e) {
+            error!("❌ Failed to record scan failure: {:#}", state_error);
+        }
+    }
+    logger::close_log_file();
+
+    result
+}
+
+/// Run a scan, keeping its results, state and summary in `workdir` if given
+async fn scan(config: ScanConfig, workdir: Option<&Workdir>) -> Result<()> {
     // Validate configuration
-    config.validate()?;
+    config.validate().classify(Failure::Config)?;
     config.log_config();
 
     let start_time = Instant::now();
+    let scan_started_at = Utc::now();
 
     // Load rules
-    let ruleset = crate::rules::load_rules(&config.rules_file).context("Failed to load rules")?;
+    let mut rules_files = vec![config.rules_file.as_str()];
+    rules_files.extend(config.extra_rules_files.iter().map(String::as_str));
+    let ruleset = crate::rules::load_rules_files(&rules_files).classify(Failure::Rules)?;
 
     if ruleset.rules.is_empty() {
         warn!("⚠️ No rules loaded from {}", config.rules_file);
         return Ok(());
     }
 
-    // Initialize database
-    let db_conn = Arc::new(Mutex::new(
-        db::init_db(&config.db_path).context("Failed to initialize database")?,
-    ));
+    // Load expected findings (accepted risk), if any
+    let expected_findings = match &config.expected_file {
+        Some(expected_file) => Some(
+            expected::load_expected(expected_file).context("Failed to load expected findings")?,
+        ),
+        None => None,
+    };
+
+    // Load the canaries checked before the scan, if any
+    let canaries: Option<Canaries> = match &config.canaries_file {
+        Some(canaries_file) => {
+            let canaries = canary::load_canaries(canaries_file).classify(Failure::Config)?;
+            canaries.validate(&ruleset).classify(Failure::Config)?;
+            Some(canaries)
+        }
+        None => None,
+    };
+
+    // Open the result store
+    let db_path = match workdir {
+        // A shared PostgreSQL database stays where it is
+        Some(workdir) if config.store_kind() != StoreKind::Postgres => workdir
+            .results_path(config.store)
+            .to_string_lossy()
+            .into_owned(),
+        _ => config.db_path.clone(),
+    };
+    let store = store::open_store(config.store, &db_path, config.db_shards, config.db_tuning)
+        .classify(Failure::Database)?;
+    let shard_writers =
+        store::open_shard_writers(config.store, &db_path, config.db_shards, config.db_tuning)
+            .classify(Failure::Database)?;
+
+    let scan_id = uuid::Uuid::new_v4().to_string();
 
     // Initialize DNS resolver
     let resolver = Arc::new(
-        DnsResolver::new("cache", config.dns_cache_size)
+        DnsResolver::new(resolver::CACHE_DIR, config.dns_cache_size)
             .await
             .context("Failed to initialize DNS resolver")?,
     );
 
-    // Load domains
-    let domains = utils::read_domains(&config.input_file).context("Failed to read domains")?;
+    // Count domains up front for progress reporting; they're streamed during the scan
+    let inputs = config.inputs();
+    let mut total_lines = 0;
+    for input in &inputs {
+        total_lines += utils::count_lines(&input.file)
+            .context(format!("Failed to read domains from {}", input.file))
+            .classify(Failure::Input)?;
+    }
 
-    if domains.is_empty() {
-        warn!("⚠️ No domains loaded from {}", config.input_file);
+    if total_lines == 0 {
+        let files: Vec<&str> = inputs.iter().map(|input| input.file.as_str()).collect();
+        warn!("⚠️ No domains loaded from {}", files.join(", "));
         return Ok(());
     }
 
+    // Track progress in the working directory's state file
+    let mut state = ScanState::running(&scan_id, scan_started_at, total_lines);
+    if let Some(workdir) = workdir {
+        workdir.write_state(&state)?;
+    }
+
     // Create high-performance HTTP client
-    let client = create_http_client(config.http_timeout, config.connect_timeout)?;
+    let client = build_http_client(&HttpClientConfig::from_config(&config))?;
 
-    // Counter for matches found
-    let matches_found = Arc::new(AtomicUsize::new(0));
-    let domains_processed = Arc::new(AtomicUsize::new(0));
-    let tasks_completed = Arc::new(AtomicUsize::new(0));
+    let checks_per_domain = ruleset.rules.len();
+    let mut resources = ScanResources::new(
+        client,
+        ruleset,
+        resolver,
+        store.clone(),
+        ScanOptions::from_config(&config),
+    )
+    .with_shard_writers(shard_writers)
+    .with_scan_id(&scan_id);
+
+    if let Some(user_agent_file) = &config.user_agent_file {
+        let pool = UserAgentPool::from_file(user_agent_file, config.sticky_user_agent)?;
+        info!("🎭 Rotating through {} User-Agents", pool.len());
+        resources = resources.with_user_agents(pool);
+    }
+
+    let throttle_policy = config.throttle_policy();
+    if !throttle_policy.is_unlimited() {
+        let asn_db = match &config.asn_file {
+            Some(asn_file) => {
+                let asn_db = AsnDb::from_file(asn_file)?;
+                info!("🌐 Loaded {} ASN ranges", asn_db.len());
+                Some(asn_db)
+            }
+            None => None,
+        };
+        resources = resources.with_throttle(Throttle::new(throttle_policy, asn_db));
+    }
+
+    if config.dns_brute {
+        info!(
+            "🔎 Brute forcing subdomains of apex domains at {} queries/s",
+            config.dns_brute_rate
+        );
+        resources = resources.with_dns_brute(DnsBrute::new(config.dns_brute_rate));
+    }
+
+    if let Some(expand_command) = &config.expand_command {
+        info!(
+            "🧩 Expanding input entries with `{}`, {} at a time",
+            expand_command, config.expand_concurrency
+        );
+        resources = resources.with_expander(Arc::new(CommandExpander::new(
+            expand_command,
+            config.expand_concurrency,
+        )));
+    }
+
+    // Checks marked as false positives with `results fp --skip-scans` aren't run
+    let skipped: HashSet<(String, String)> = store
+        .lock()
+        .await
+        .false_positives()?
+        .into_iter()
+        .filter(|fp| fp.skip_scans)
+        .map(|fp| (fp.domain, fp.rule_name))
+        .collect();
+    if !skipped.is_empty() {
+        info!(
+            "🙈 Skipping {} checks marked as false positives",
+            skipped.len()
+        );
+        resources = resources.with_skipped(skipped);
+    }
+
+    // Findings detected before are requested conditionally, so an unchanged response
+    // isn't downloaded and matched again
+    let revalidations: Vec<_> = store
+        .lock()
+        .await
+        .revalidatable()?
+        .iter()
+        .filter_map(|finding| {
+            let key = (finding.domain.clone(), finding.rule_name.clone());
+            Revalidation::from_finding(finding).map(|revalidation| (key, revalidation))
+        })
+        .collect();
+    if !revalidations.is_empty() {
+        debug!(
+            "♻️ Revalidating {} earlier findings with conditional requests",
+            revalidations.len()
+        );
+        resources = resources.with_revalidations(revalidations);
+    }
+
+    if let Some(window) = &config.scan_window {
+        resources = resources.with_window(window.parse()?);
+    }
+
+    // Nothing is sent outside the scan window, canary checks included
+    resources.wait_for_window().await;
+
+    // Abort before touching the real targets if the canaries show a broken setup
+    if let Some(canaries) = &canaries {
+        canary::preflight(canaries, &resources).await?;
+    }
+
+    // Record the scan session
+    store.lock().await.start_scan(&scan_id, scan_started_at)?;
+    info!("🆔 Scan session: {}", scan_id);
+    if let Some(profile) = config.profile {
+        store.lock().await.record_profile(&scan_id, profile)?;
+    }
+    if let Some(project) = &config.project {
+        store.lock().await.record_project(&scan_id, project)?;
+    }
 
-    // Chunk domains for batch processing
-    let batch_size = 100; // Default batch size if not specified
-    let domain_chunks = utils::chunk_vector(domains, batch_size);
-    let total_domains = domain_chunks.iter().map(|chunk| chunk.len()).sum::<usize>();
-    let total_tasks = total_domains * ruleset.rules.len();
+    // Results are attributable to the version of the rules they were checked with
+    store
+        .lock()
+        .await
+        .record_ruleset(&scan_id, &resources.ruleset.snapshot())?;
+    let _rule_watcher = if config.hot_reload_rules {
+        Some(RuleWatcher::start(
+            &rules_files,
+            resources.ruleset.clone(),
+            store.clone(),
+            &scan_id,
+        )?)
+    } else {
+        None
+    };
+
+    // Stream findings and progress to live subscribers
+    if let Some(addr) = &config.stream_listen {
+        let events = Arc::new(EventStream::listen(addr, config.stream_token.as_deref()).await?);
+        events.publish(StreamEvent::Started {
+            scan_id: scan_id.clone(),
+            domains: total_lines,
+            checks: total_lines * checks_per_domain,
+        });
+        resources = resources.with_events(events);
+    }
+    if let Some(notifier) = FindingNotifier::start(
+        build_http_client(&HttpClientConfig::default())?,
+        &config.webhooks,
+        &config.emails,
+        &config.incidents,
+    ) {
+        resources = resources.with_notifier(notifier);
+    }
+    let domains_processed = Arc::new(AtomicUsize::new(0));
+    let metrics = Arc::new(PipelineMetrics::default());
+    let pipeline_options = PipelineOptions::from_config(&config);
 
     info!(
         "🚀 Starting scan of {} domains with {} rules ({} total checks)",
-        total_domains,
-        ruleset.rules.len(),
-        total_tasks
+        total_lines,
+        checks_per_domain,
+        total_lines * checks_per_domain
     );
 
     // Status update task
     let status_interval = Duration::from_secs(3);
     let domains_processed_clone = domains_processed.clone();
-    let tasks_completed_clone = tasks_completed.clone();
-    let total_domains_clone = total_domains;
-    let total_tasks_clone = total_tasks;
+    let tasks_completed_clone = resources.tasks_completed.clone();
+    let metrics_clone = metrics.clone();
+    let total_tasks_clone = total_lines * checks_per_domain;
+    let mut state_tracking = workdir.map(|workdir| (workdir.clone(), state.clone()));
+    let matches_found_clone = resources.matches_found.clone();
+    let events_clone = resources.events.clone();
 
     // Spawn status update task
     let status_handle = tokio::spawn(async move {
@@ -109,267 +1019,799 @@ pub async fn run_scan(config: ScanConfig) -> Result<()> {
             interval.tick().await;
             let domains_done = domains_processed_clone.load(Ordering::Relaxed);
             let tasks_done = tasks_completed_clone.load(Ordering::Relaxed);
-            let domains_percent =
-                (domains_done as f64 / total_domains_clone as f64 * 100.0) as usize;
+            let domains_percent = (domains_done as f64 / total_lines as f64 * 100.0) as usize;
             let tasks_percent = (tasks_done as f64 / total_tasks_clone as f64 * 100.0) as usize;
+            let queued = metrics_clone.snapshot();
+            otel::record_queue_depths(&queued);
 
             info!(
-                "📊 Status: {}/{} domains ({}%), {}/{} tasks ({}%)",
+                "📊 Status: {}/{} domains ({}%), {}/{} tasks ({}%), queued: {}",
                 domains_done,
-                total_domains_clone,
+                total_lines,
                 domains_percent,
                 tasks_done,
                 total_tasks_clone,
-                tasks_percent
+                tasks_percent,
+                queued
             );
 
-            if domains_done >= total_domains_clone && tasks_done >= total_tasks_clone {
-                break;
+            if let Some(events) = &events_clone {
+                events.publish(StreamEvent::Progress {
+                    domains_processed: domains_done,
+                    domains_total: total_lines,
+                    checks_completed: tasks_done,
+                    checks_total: total_tasks_clone,
+                    findings: matches_found_clone.load(Ordering::Relaxed),
+                    batches: None,
+                });
             }
-        }
-    });
-
-    // Process domains in batches
-    for (i, chunk) in domain_chunks.iter().enumerate() {
-        info!(
-            "📦 Processing batch {}/{} ({} domains)",
-            i + 1,
-            domain_chunks.len(),
-            chunk.len()
-        );
 
-        // Process each domain in the batch concurrently
-        let client_clone = client.clone();
-        let ruleset_clone = ruleset.clone();
-        let resolver_clone = resolver.clone();
-        let db_conn_clone = db_conn.clone();
-        let tasks_completed_clone = tasks_completed.clone();
-        let matches_found_clone = matches_found.clone();
-        let domains_processed_clone = domains_processed.clone();
-
-        // Create a stream of futures for concurrent processing
-        let mut handles = Vec::with_capacity(chunk.len());
-
-        // Create tasks for each domain
-        for domain in chunk {
-            let domain = domain.clone();
-            let client = client_clone.clone();
-            let ruleset = ruleset_clone.clone();
-            let resolver = resolver_clone.clone();
-            let db_conn = db_conn_clone.clone();
-            let tasks_completed = tasks_completed_clone.clone();
-            let matches_found = matches_found_clone.clone();
-            let domains_processed = domains_processed_clone.clone();
-
-            // Spawn a task for each domain
-            let handle = tokio::spawn(async move {
-                let result = scan_domain(
-                    &domain,
-                    &client,
-                    &ruleset,
-                    &resolver,
-                    db_conn,
-                    tasks_completed,
-                    matches_found,
-                )
-                .await;
-
-                // Always increment domain counter
-                domains_processed.fetch_add(1, Ordering::Relaxed);
-
-                result
-            });
+            if let Some((workdir, state)) = &mut state_tracking {
+                state.domains_processed = domains_done;
+                state.updated_at = Utc::now();
+                if let Err(e) = workdir.write_state(state) {
+                    debug!("Failed to update scan state: {:#}", e);
+                }
+            }
 
-            handles.push(handle);
+            if domains_done >= total_lines && tasks_done >= total_tasks_clone {
+                break;
+            }
         }
+    });
 
-        // Wait for all futures to complete
-        let results = futures::future::join_all(handles).await;
+    // Ctrl-C stops reading domains; pressed again, it abandons the checks in progress
+    let _ctrl_c = resources.shutdown.listen_for_ctrl_c();
 
-        // Count errors
-        let error_count = results
-            .iter()
-            .filter(|r| r.is_err() || r.as_ref().ok().is_none_or(|r| r.is_err()))
-            .count();
-        if error_count > 0 {
-            debug!("⚠️ Batch completed with {} errors", error_count);
-        }
-    }
+    // Stream domains through the resolve/fetch/match/write pipeline
+    let pipeline_result = pipeline::run_pipeline(
+        &inputs,
+        &resources,
+        &pipeline_options,
+        metrics.clone(),
+        domains_processed.clone(),
+    )
+    .await;
 
     // Cancel the status update task once all work is done
     status_handle.abort();
 
+    let total_domains = pipeline_result.context("Scan pipeline failed")?;
+    let total_tasks = resources.tasks_completed.load(Ordering::Relaxed);
+    let stopped = resources.shutdown.state();
+    let incomplete = stopped != ShutdownState::Running;
+    debug!("📊 Peak queue depths: {}", metrics.peaks());
+
     // Calculate stats
     let elapsed = start_time.elapsed();
     let elapsed_secs = elapsed.as_secs_f64();
-    let matches = matches_found.load(Ordering::Relaxed);
+    let matches = resources.matches_found.load(Ordering::Relaxed);
 
     // Log stats
     logger::log_scan_stats(total_domains, total_tasks, matches, elapsed_secs);
+    logger::log_network_stats(&resources.stats.snapshot());
+    let unchanged_findings = resources.findings_unchanged.load(Ordering::Relaxed);
+    if unchanged_findings > 0 {
+        info!(
+            "♻️ Unchanged findings: {} of {} answered 304 Not Modified since the last scan",
+            unchanged_findings, matches
+        );
+    }
+    if resources.throttle.waits() > 0 {
+        info!(
+            "🐢 Throttled requests: {} waited for a TLD, ASN or subnet slot",
+            resources.throttle.waits()
+        );
+    }
+    let interrupted = ScanInterrupted {
+        processed: domains_processed.load(Ordering::Relaxed),
+        total: total_lines,
+    };
+    if incomplete {
+        warn!(
+            "⚠️ Scan incomplete: stopped after {} of {} domains",
+            interrupted.processed, interrupted.total
+        );
+    }
 
-    Ok(())
-}
+    if let Some(events) = &resources.events {
+        events
+            .finish(StreamEvent::Finished {
+                scan_id: scan_id.clone(),
+                domains: total_domains,
+                findings: matches,
+                elapsed_secs,
+            })
+            .await;
+    }
+    if let Some(notifier) = &resources.notifier {
+        notifier.finish().await;
+    }
 
-/// Scan a domain with all rules in the ruleset
-pub async fn scan_domain(
-    domain: &str,
-    client: &Client,
-    ruleset: &RuleSet,
-    resolver: &DnsResolver,
-    db_conn: Arc<Mutex<Connection>>,
-    tasks_completed: Arc<AtomicUsize>,
-    matches_found: Arc<AtomicUsize>,
-) -> Result<()> {
-    // Resolve domain to IP
-    match resolver.lookup(domain).await {
-        Ok(ip) => {
-            debug!(
-                "🔍 Scanning domain: {} ({})",
-                domain,
-                ip.unwrap_or_else(|| "unresolved".to_string())
+    let mut results = store.lock().await;
+    let detected = results
+        .detected_since(scan_started_at)
+        .context("Failed to query findings from this scan")?;
+
+    // Score the scan against the latest rules, which include any added by a reload
+    let rules = resources.ruleset.snapshot();
+    let ruleset = &rules.ruleset;
+    let risk_report = risk::score_findings(&detected, ruleset, &config.risk_weights);
+    results.finish_scan(&scan_id, total_domains, &risk_report)?;
+    results.record_scan_findings(&scan_id, &detected)?;
+    if incomplete {
+        results.mark_incomplete(&scan_id)?;
+    }
+    logger::log_risk_score(risk_report.total, risk_report.per_domain.len());
+
+    // A forced stop keeps what has been recorded and skips the follow-up work
+    if stopped == ShutdownState::Forced {
+        drop(results);
+        if let Some(workdir) = workdir {
+            state.status = ScanStatus::Interrupted;
+            state.domains_processed = interrupted.processed;
+            state.updated_at = Utc::now();
+            workdir.write_state(&state)?;
+        }
+        return Err(interrupted.into());
+    }
+
+    // Track per-rule counts and compare them against recent scans. A partial scan's
+    // counts would drag down the baseline, so they're left out of it.
+    let mut rule_counts = anomaly::count_by_rule(&detected);
+    for rule in &ruleset.rules {
+        rule_counts.entry(rule.name.clone()).or_insert(0);
+    }
+    if !incomplete {
+        results.record_rule_counts(&scan_id, &rule_counts)?;
+    }
+
+    let (baseline_scans, baseline) =
+        results.rule_baseline(&scan_id, config.anomaly_baseline_scans)?;
+    let mut anomalies = Vec::new();
+    if baseline_scans > 0 && !incomplete {
+        anomalies = anomaly::detect_anomalies(
+            &rule_counts,
+            &baseline,
+            config.anomaly_factor,
+            config.anomaly_min_count,
+        );
+        for anomaly in &anomalies {
+            warn!(
+                "🚨 Anomaly: rule '{}' matched {} domains ({:.1}x the baseline of {:.1} over {} scans)",
+                anomaly.rule_name,
+                anomaly.current,
+                anomaly.ratio(),
+                anomaly.baseline,
+                baseline_scans
             );
+        }
+    }
 
-            // Create a vector of futures for parallel rule checking
-            let mut rule_futures = Vec::with_capacity(ruleset.rules.len());
-
-            // Process each rule in parallel
-            for rule in &ruleset.rules {
-                let domain = domain.to_string();
-                let client = client.clone();
-                let rule = rule.clone();
-                let db_conn = db_conn.clone();
-                let matches_found = matches_found.clone();
-
-                // Create a future for this rule check
-                let rule_future = async move {
-                    // Construct target URL from rule path
-                    let url = format!("http://{}{}", domain, rule.path);
-
-                    // Check if path exists
-                    match check_path(&client, &url).await {
-                        Ok(true) => {
-                            // Check if it matches the signature
-                            match check_signature(&client, &url, &rule.signature).await {
-                                Ok(true) => {
-                                    info!(
-                                        "🔴 Match found: {} - {} ({})",
-                                        domain, rule.name, rule.path
-                                    );
-                                    logger::log_success(&domain, &rule.name, &rule.path);
-
-                                    // Store in database
-                                    let conn = db_conn.lock().await;
-                                    if let Err(e) = db::insert_finding(
-                                        &conn, &domain, &rule.name, &rule.path, true,
-                                    ) {
-                                        error!("Failed to insert finding: {}", e);
-                                    }
-
-                                    // Increment match counter
-                                    matches_found.fetch_add(1, Ordering::Relaxed);
-
-                                    Ok(())
-                                }
-                                Ok(false) => {
-                                    // No match, but path exists
-                                    let conn = db_conn.lock().await;
-                                    if let Err(e) = db::insert_finding(
-                                        &conn, &domain, &rule.name, &rule.path, false,
-                                    ) {
-                                        error!("Failed to insert finding: {}", e);
-                                    }
-
-                                    Ok(())
-                                }
-                                Err(e) => {
-                                    debug!(
-                                        "🔶 Error checking signature for {} - {}: {}",
-                                        domain, rule.path, e
-                                    );
-                                    Err(e)
-                                }
-                            }
-                        }
-                        Ok(false) => {
-                            // Path doesn't exist, nothing to do
-                            debug!("❌ Path not found: {} - {}", domain, rule.path);
-                            Ok(())
-                        }
-                        Err(e) => {
-                            debug!("🔶 Error checking path: {} - {}: {}", domain, rule.path, e);
-                            Err(e)
+    // Compare against the expected findings
+    let unexpected = match &expected_findings {
+        Some(expected_findings) => {
+            let unexpected = expected_findings.unexpected(&detected);
+
+            info!(
+                "📋 {} detected findings, {} expected, {} unexpected",
+                detected.len(),
+                detected.len() - unexpected.len(),
+                unexpected.len()
+            );
+
+            for finding in &unexpected {
+                warn!(
+                    "🚨 Unexpected finding: {} - {} ({})",
+                    finding.domain, finding.rule_name, finding.matched_path
+                );
+            }
+
+            unexpected.len()
+        }
+        None => 0,
+    };
+
+    // Write configured exports and notify, even when the scan is about to fail
+    if !config.sinks.is_empty() {
+        // Findings marked as false positives are left out and notes attached, as in
+        // `results export`
+        let exported = results
+            .findings()
+            .and_then(|findings| Ok((findings, results.false_positives()?, results.notes()?)));
+        match exported {
+            Ok((mut findings, false_positives, notes)) => {
+                let false_positives: HashSet<(String, String)> = false_positives
+                    .into_iter()
+                    .map(|fp| (fp.domain, fp.rule_name))
+                    .collect();
+                findings.retain(|finding| !store::is_false_positive(&false_positives, finding));
+                for finding in &mut findings {
+                    resources.options.redaction.redact_finding(finding);
+                }
+                db::attach_notes(&mut findings, notes);
+                for sink in &config.sinks {
+                    let write =
+                        |path: &str| db::export_split(&findings, path, &sink.format, sink.split_by);
+                    let written = match sink.upload_target() {
+                        Ok(Some(target)) => {
+                            let s3 = sink.s3.clone().unwrap_or_default();
+                            upload::upload_export(&s3, &target, write).await.map(|_| ())
                         }
+                        Ok(None) => write(&sink.path),
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = written {
+                        error!(
+                            "❌ Failed to write {} export to {}: {:#}",
+                            sink.format, sink.path, e
+                        );
                     }
-                };
+                }
+            }
+            Err(e) => error!("❌ Failed to read findings for export: {:#}", e),
+        }
+    }
 
-                rule_futures.push(rule_future);
+    // Keep long-running stores from filling up with stale rows
+    if let Some(age) = &config.purge_older_than {
+        let filter = PurgeFilter {
+            not_detected: config.purge_not_detected,
+            ..PurgeFilter::older_than(retention::parse_age(age)?)
+        };
+        match results.purge_findings(&filter) {
+            Ok(removed) => info!("🧹 Purged {} findings older than {}", removed, age),
+            Err(e) => error!("❌ Failed to purge old findings: {:#}", e),
+        }
+    }
+    drop(results);
+
+    let notification = notify::ScanNotification {
+        scan_id: scan_id.clone(),
+        domains: total_domains,
+        findings: detected.len(),
+        unexpected,
+        risk_score: risk_report.total,
+        anomalies,
+    };
+    if !config.webhooks.is_empty() {
+        let client = build_http_client(&HttpClientConfig::default())?;
+        notify::notify_all(&client, &config.webhooks, &notification).await;
+    }
+    if !config.emails.is_empty() {
+        // The digest's CSV holds evidence, so it's masked like any export
+        let mut findings = detected.clone();
+        for finding in &mut findings {
+            resources.options.redaction.redact_finding(finding);
+        }
+        email::send_digests(&config.emails, &notification, &findings).await;
+    }
+
+    if let Some(workdir) = workdir {
+        workdir.write_summary(&ScanSummary {
+            scan_id: scan_id.clone(),
+            started_at: scan_started_at,
+            finished_at: Utc::now(),
+            elapsed_secs,
+            domains: total_domains,
+            checks: total_tasks,
+            findings: detected.len(),
+            unexpected,
+            risk_score: risk_report.total,
+            network: resources.stats.snapshot(),
+            incomplete,
+        })?;
+
+        state.status = if incomplete {
+            ScanStatus::Interrupted
+        } else {
+            ScanStatus::Finished
+        };
+        state.domains_processed = domains_processed.load(Ordering::Relaxed);
+        state.updated_at = Utc::now();
+        workdir.write_state(&state)?;
+        info!(
+            "📁 Scan summary written to {}",
+            workdir.summary_file().display()
+        );
+    }
+
+    // Fail on anything new
+    if unexpected > 0 {
+        return Err(UnexpectedFindings(unexpected).into());
+    }
+    if incomplete {
+        return Err(interrupted.into());
+    }
+
+    Ok(())
+}
+
+/// Run scans repeatedly, waiting `interval_secs` between the end of one scan and the next
+pub async fn watch_scan(config: ScanConfig, interval_secs: u64) -> Result<()> {
+    let window: Option<ScanWindow> = config
+        .scan_window
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .classify(Failure::Config)?;
+    let mut iteration = 0;
+
+    loop {
+        // Scans only start inside the window; one running when it closes pauses
+        let closed = window.and_then(|window| Some((window, window.until_open(Utc::now())?)));
+        if let Some((window, wait)) = closed {
+            info!(
+                "⏸️ Outside scan window {}, next scan in {}s",
+                window,
+                wait.as_secs()
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = tokio::signal::ctrl_c() => {
+                    info!("⏹️ Watch mode stopped");
+                    return Ok(());
+                }
             }
+        }
 
-            // Execute all rule checks in parallel with a concurrency limit
-            let results = futures::future::join_all(rule_futures).await;
+        iteration += 1;
+        info!("👀 Watch mode: starting scan #{}", iteration);
 
-            // Increment task counter for all completed tasks
-            tasks_completed.fetch_add(ruleset.rules.len(), Ordering::Relaxed);
+        if let Err(e) = run_scan(config.clone()).await {
+            if e.is::<ScanInterrupted>() {
+                info!("⏹️ Watch mode stopped");
+                return Err(e);
+            }
+            error!("❌ Scan #{} failed: {:#}", iteration, e);
+        }
 
-            // Check if any errors occurred
-            let errors: Vec<_> = results.into_iter().filter_map(|r| r.err()).collect();
-            if !errors.is_empty() {
-                debug!(
-                    "❌ Some rule checks failed for {}: {} errors",
-                    domain,
-                    errors.len()
-                );
+        info!("💤 Next scan in {}s", interval_secs);
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(interval_secs)) => {}
+            _ = tokio::signal::ctrl_c() => {
+                info!("⏹️ Watch mode stopped");
+                return Ok(());
             }
+        }
+    }
+}
 
-            Ok(())
+/// Record the result of checking a rule against a domain: log and store it
+pub async fn record_outcome(
+    ctx: &ScanContext,
+    resources: &ScanResources,
+    rule: &Rule,
+    result: Result<Option<RuleOutcome>>,
+) -> Result<()> {
+    let outcome = match result {
+        Ok(Some(outcome)) => outcome,
+        Ok(None) => {
+            // Path doesn't exist on any target, nothing to do
+            debug!("❌ Path not found: {} - {}", ctx.domain, rule.path);
+            return Ok(());
         }
         Err(e) => {
-            debug!("❌ Failed to resolve domain: {}: {}", domain, e);
+            debug!("🔶 Error checking {} - {}: {}", ctx.domain, rule.path, e);
+            return Err(e);
+        }
+    };
 
-            // Increment task counter for all rules that would have been checked
-            tasks_completed.fetch_add(ruleset.rules.len(), Ordering::Relaxed);
+    // With a single target the path is enough; otherwise keep the full URL, as for a
+    // domain switched to https, so the finding shows the scheme it was found over
+    let matched_path = if ctx.targets.len() == 1 && ctx.scheme_switch.is_none() {
+        rule.path.as_str()
+    } else {
+        outcome.url.as_str()
+    };
 
-            Err(anyhow::anyhow!("Failed to resolve domain: {}", domain))
+    // Secrets in what was observed are masked before anything is logged or stored
+    let redaction = &resources.options.redaction;
+    let matched_path = redaction.redact(matched_path);
+    let redirect_chain: Vec<String> = outcome
+        .redirect_chain
+        .iter()
+        .map(|url| redaction.redact(url).into_owned())
+        .collect();
+    let evidence = outcome
+        .evidence
+        .as_ref()
+        .map(|evidence| redaction.redact_evidence(evidence));
+
+    if outcome.protected {
+        info!(
+            "🔒 Protected path found: {} - {} ({}, status {})",
+            ctx.domain, rule.name, matched_path, outcome.status
+        );
+    } else if outcome.detected {
+        info!(
+            "🔴 Match found: {} - {} ({})",
+            ctx.domain, rule.name, matched_path
+        );
+    }
+    if outcome.detected {
+        logger::log_success(&ctx.domain, &rule.name, &matched_path);
+        if !redirect_chain.is_empty() {
+            debug!(
+                "↪️ {} redirected through {}",
+                matched_path,
+                redirect_chain.join(" -> ")
+            );
+        }
+        if ctx.is_disallowed(&rule.path) {
+            debug!(
+                "🤖 {} is disallowed by robots.txt on {}",
+                rule.path, ctx.domain
+            );
+        }
+
+        // Increment match counter
+        resources.matches_found.fetch_add(1, Ordering::Relaxed);
+        otel::record_finding(rule.severity.as_ref());
+        if evidence.as_ref().is_some_and(|evidence| evidence.unchanged) {
+            resources.findings_unchanged.fetch_add(1, Ordering::Relaxed);
+        }
+
+        if let Some(events) = &resources.events {
+            events.publish(StreamEvent::Finding {
+                domain: ctx.domain.clone(),
+                rule: rule.name.clone(),
+                severity: rule.severity.clone(),
+                path: matched_path.to_string(),
+                tag: ctx.tag.clone(),
+                status: outcome.status,
+                redirect_chain: redirect_chain.clone(),
+                remediation: rule.remediation.clone(),
+                found_at: Utc::now(),
+            });
+        }
+        if let Some(notifier) = &resources.notifier {
+            notifier.notify(FindingNotification {
+                scan_id: resources.scan_id.clone().unwrap_or_default(),
+                domain: ctx.domain.clone(),
+                rule: rule.name.clone(),
+                severity: rule.severity.clone(),
+                path: matched_path.to_string(),
+                status: outcome.status,
+                tag: ctx.tag.clone(),
+                project: resources.options.project.clone(),
+                found_at: Utc::now(),
+            });
         }
     }
+
+    // Store the result
+    let details = FindingDetails {
+        user_agent: outcome.user_agent.as_deref(),
+        redirect_chain: &redirect_chain,
+        tag: ctx.tag.as_deref(),
+        status: Some(outcome.status),
+        ruleset_version: Some(ctx.rules.version),
+        ruleset_digest: Some(&ctx.rules.digest),
+        evidence: evidence.as_ref(),
+        project: resources.options.project.as_deref(),
+    };
+    resources
+        .writer
+        .write(PendingFinding::new(
+            &ctx.domain,
+            &rule.name,
+            &matched_path,
+            outcome.detected,
+            &details,
+        ))
+        .await;
+
+    Ok(())
 }
 
-/// Check if a path exists by making a HEAD request
-pub async fn check_path(client: &Client, url: &str) -> Result<bool> {
-    // First try a HEAD request to see if the path exists without downloading content
-    match client.head(url).send().await {
-        Ok(response) => Ok(response.status().is_success()),
-        Err(e) => {
-            debug!("HEAD request failed for {}: {}", url, e);
-            // Fall back to a GET if HEAD fails, some servers don't support HEAD
-            match client.get(url).send().await {
-                Ok(response) => Ok(response.status().is_success()),
-                Err(e) => {
-                    debug!("GET request also failed for {}: {}", url, e);
-                    Err(anyhow::anyhow!("Failed to check path: {}", e))
-                }
+/// Outcome of checking a rule against a domain's targets
+#[derive(Debug, Clone)]
+pub struct RuleOutcome {
+    /// URL the outcome was observed at
+    pub url: String,
+
+    /// Whether the signature matched or the path is protected
+    pub detected: bool,
+
+    /// Whether the path answered with one of the rule's interesting error statuses
+    pub protected: bool,
+
+    /// Status of the response the outcome was observed in
+    pub status: u16,
+
+    /// User-Agent sent with the check, if rotating
+    pub user_agent: Option<String>,
+
+    /// URLs the matching request was redirected through, ending with the final one
+    pub redirect_chain: Vec<String>,
+
+    /// Part of the body the signature matched in
+    pub evidence: Option<Evidence>,
+}
+
+/// Responses to a rule's path from the targets of a domain
+#[derive(Debug)]
+pub struct FetchedRule {
+    /// URL and response for every target where the path exists or is protected, in
+    /// target order
+    pub pages: Vec<(String, Page)>,
+
+    /// User-Agent sent with the requests, if rotating
+    pub user_agent: Option<String>,
+
+    /// Evidence of the earlier finding, when a target answered 304 Not Modified to a
+    /// conditional request for it
+    pub unchanged: Option<Evidence>,
+}
+
+/// Check a rule against every target of a domain.
+///
+/// Returns the first target where the signature matched, otherwise the first target where
+/// the path exists, or `None` if it exists nowhere. Errors are only returned when every
+/// target failed.
+pub async fn check_rule(
+    ctx: &ScanContext,
+    resources: &ScanResources,
+    rule: &Rule,
+) -> Result<Option<RuleOutcome>> {
+    Ok(match_rule(rule, fetch_rule(ctx, resources, rule).await?))
+}
+
+/// Fetch a rule's path from every target of a domain, keeping the responses where it exists.
+///
+/// Each target gets one GET: its status decides whether the path exists and its body is
+/// what the signatures are matched against. Responses with one of the rule's interesting
+/// statuses are kept as protected, unless the host answers missing paths the same way.
+/// Wildcard (soft-404) responses are dropped. A path detected by an earlier scan is
+/// requested conditionally, and a 304 Not Modified keeps it detected as unchanged.
+/// Errors are only returned when every target failed.
+pub async fn fetch_rule(
+    ctx: &ScanContext,
+    resources: &ScanResources,
+    rule: &Rule,
+) -> Result<FetchedRule> {
+    let retry = resources.options.retry;
+    let max_redirects = rule.max_redirects();
+    let user_agent = resources.user_agent(&ctx.domain);
+    let mut pages = Vec::new();
+    let mut errors = Vec::new();
+    let mut unchanged = None;
+
+    for base_url in &ctx.targets {
+        if ctx.breaker.is_open() {
+            errors.push(anyhow::anyhow!("Circuit breaker open for {}", ctx.domain));
+            continue;
+        }
+
+        let url = match utils::build_url(base_url, &rule.path) {
+            Ok(url) => url,
+            Err(e) => {
+                errors.push(e);
+                continue;
             }
+        };
+        if resources.options.jitter_ms > 0 {
+            utils::random_backoff(0, resources.options.jitter_ms).await;
         }
+
+        let validators = resources.validators_for(ctx, rule, &url);
+        let page = match retry
+            .run(|| resources.fetch_for_rule(ctx, &url, user_agent.as_deref(), rule, validators))
+            .await
+        {
+            Ok(page) => {
+                ctx.breaker.record_success();
+                page
+            }
+            Err(e) => {
+                ctx.breaker.record_failure();
+                errors.push(e);
+                continue;
+            }
+        };
+
+        if page.status == StatusCode::NOT_MODIFIED.as_u16() && validators.is_some() {
+            debug!("♻️ {} is unchanged since the last scan", url);
+            if unchanged.is_none() {
+                let key = (ctx.domain.clone(), rule.name.clone());
+                unchanged = resources
+                    .revalidations
+                    .get(&key)
+                    .map(|revalidation| revalidation.evidence.revalidated());
+            }
+            pages.push((url, page));
+            continue;
+        }
+
+        if !path_exists(page.status, max_redirects) {
+            if !rule.is_interesting_status(page.status, &resources.options.interesting_status) {
+                continue;
+            }
+            // A host answering every path this way reveals nothing about this one
+            if ctx.is_wildcard_status(page.status) {
+                debug!("🃏 Every path answers {} on {}", page.status, ctx.domain);
+                continue;
+            }
+        }
+
+        if ctx.is_soft_404(&page, &rule.path) {
+            debug!("🃏 Wildcard response for {}", url);
+            continue;
+        }
+
+        pages.push((url, page));
+    }
+
+    if pages.is_empty() && !errors.is_empty() && errors.len() == ctx.targets.len() {
+        return Err(errors.remove(0));
     }
+
+    Ok(FetchedRule {
+        pages,
+        user_agent,
+        unchanged,
+    })
 }
 
-/// Check if a signature exists in the response body
-pub async fn check_signature(client: &Client, url: &str, signature: &str) -> Result<bool> {
-    // Get the path content
-    match client.get(url).send().await {
-        Ok(response) => {
-            // Check if the response is successful
-            if response.status().is_success() {
-                // Get the response text and check for signature
-                let body = response.text().await?;
-                Ok(body.contains(signature))
+/// Decide a rule's outcome from its fetched responses: the first one whose body matches,
+/// otherwise the first protected one, otherwise the first one where the path exists
+/// A response revalidated as unchanged matches as it did before.
+pub fn match_rule(rule: &Rule, fetched: FetchedRule) -> Option<RuleOutcome> {
+    let FetchedRule {
+        pages,
+        user_agent,
+        unchanged,
+    } = fetched;
+
+    // Only interesting statuses make it this far as errors
+    let is_protected = |page: &Page| page.status >= 400;
+    let revalidated = unchanged.is_some();
+    let is_unchanged =
+        |page: &Page| revalidated && page.status == StatusCode::NOT_MODIFIED.as_u16();
+
+    let matched = pages.iter().position(|(_, page)| {
+        is_unchanged(page)
+            || ((page.is_success() || page.is_redirect()) && rule.matches(&page.body))
+    });
+    let protected = pages.iter().position(|(_, page)| is_protected(page));
+    let detected = matched.is_some() || protected.is_some();
+    let (url, page) = pages.into_iter().nth(matched.or(protected).unwrap_or(0))?;
+    let evidence = match (matched, unchanged) {
+        (Some(_), Some(unchanged)) if is_unchanged(&page) => Some(unchanged),
+        (Some(_), _) => rule.match_offset(&page.body).map(|offset| {
+            let evidence =
+                Evidence::capture(page.status, &page.body, offset).with_validators(&page.headers);
+            if page.complete {
+                evidence
             } else {
-                Ok(false)
+                evidence.with_truncated_body()
+            }
+        }),
+        (None, _) => None,
+    };
+
+    // The status an unchanged response was first matched with stays the finding's
+    let status = match &evidence {
+        Some(evidence) if evidence.unchanged => evidence.status,
+        _ => page.status,
+    };
+
+    Some(RuleOutcome {
+        url,
+        detected,
+        protected: is_protected(&page),
+        status,
+        user_agent,
+        redirect_chain: page.redirects,
+        evidence,
+    })
+}
+
+/// Whether a response status shows that a rule's path exists: any 2xx, or a redirect the
+/// rule doesn't follow, which is the response it is after
+fn path_exists(status: u16, max_redirects: usize) -> bool {
+    (200..300).contains(&status) || (max_redirects == 0 && (300..400).contains(&status))
+}
+
+/// A fetched HTTP response
+#[derive(Debug, Clone)]
+pub struct Page {
+    /// HTTP status code
+    pub status: u16,
+
+    /// Response headers
+    pub headers: HeaderMap,
+
+    /// Response body, as received
+    pub body: Bytes,
+
+    /// URLs redirected to before this response, in order; empty without redirects
+    pub redirects: Vec<String>,
+
+    /// DER-encoded certificate the server presented, for responses over https
+    pub certificate: Option<Bytes>,
+
+    /// Whether `body` is the whole body, read to its end rather than cut off at the
+    /// size limit or once enough was seen
+    pub complete: bool,
+}
+
+impl Page {
+    /// Read a response into a page, keeping at most `limit` bytes of the body.
+    ///
+    /// The body is streamed a chunk at a time, so an oversized response is never held in
+    /// memory; after each chunk `done` sees the body so far and can end the download early.
+    /// The page is `complete` only if the body was read to its end anyway.
+    pub async fn read_limited(
+        mut response: reqwest::Response,
+        limit: usize,
+        mut done: impl FnMut(&[u8]) -> bool,
+    ) -> Result<Self> {
+        let page = Self::without_body(&response);
+        let length = response.content_length();
+        let mut body = BytesMut::with_capacity((length.unwrap_or(0) as usize).min(limit));
+        let mut complete = true;
+
+        while let Some(chunk) = response.chunk().await? {
+            let room = limit - body.len();
+            body.extend_from_slice(&chunk[..chunk.len().min(room)]);
+
+            if body.len() >= limit {
+                debug!("✂️ Body of {} cut off at {} bytes", response.url(), limit);
+                complete = false;
+                break;
+            }
+            if done(&body) {
+                // Enough is known, but the body may still have ended with this chunk
+                complete = match length {
+                    Some(length) => body.len() as u64 == length,
+                    None => response.chunk().await?.is_none(),
+                };
+                break;
             }
         }
-        Err(e) => {
-            debug!("Error checking signature: {}", e);
-            Err(anyhow::anyhow!("Failed to check signature: {}", e))
+
+        Ok(Self {
+            body: body.freeze(),
+            complete,
+            ..page
+        })
+    }
+
+    /// A page with a response's status and headers, leaving its body unread
+    pub fn without_body(response: &reqwest::Response) -> Self {
+        Self {
+            status: response.status().as_u16(),
+            headers: response.headers().clone(),
+            body: Bytes::new(),
+            redirects: Vec::new(),
+            certificate: response
+                .extensions()
+                .get::<reqwest::tls::TlsInfo>()
+                .and_then(|info| info.peer_certificate())
+                .map(Bytes::copy_from_slice),
+            complete: false,
         }
     }
+
+    /// Whether the response has a 2xx status
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The body as text, decoded lossily; borrowed when it is valid UTF-8
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    /// Whether the body contains `needle`, compared byte for byte
+    pub fn contains(&self, needle: &str) -> bool {
+        memmem::find(&self.body, needle.as_bytes()).is_some()
+    }
+
+    /// Whether the response has a 3xx status
+    pub fn is_redirect(&self) -> bool {
+        (300..400).contains(&self.status)
+    }
 }
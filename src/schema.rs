@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use schemars::schema_for;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use tracing::info;
+
+use crate::config_file;
+use crate::manifest::Manifest;
+use crate::rules::RuleSet;
+
+/// A file format `fatt schema` describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaKind {
+    /// Rules YAML files
+    Rules,
+    /// Scan manifests (`scan.yaml`)
+    Manifest,
+    /// Config files (`fatt.toml`)
+    Config,
+}
+
+impl FromStr for SchemaKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "rules" => Ok(SchemaKind::Rules),
+            "manifest" => Ok(SchemaKind::Manifest),
+            "config" => Ok(SchemaKind::Config),
+            other => anyhow::bail!(
+                "Unknown schema: {} (expected rules, manifest or config)",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SchemaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaKind::Rules => write!(f, "rules"),
+            SchemaKind::Manifest => write!(f, "manifest"),
+            SchemaKind::Config => write!(f, "config"),
+        }
+    }
+}
+
+/// JSON Schema of a file format, generated from the models the files are read into
+pub fn schema(kind: SchemaKind) -> Result<serde_json::Value> {
+    Ok(match kind {
+        SchemaKind::Rules => serde_json::json!(schema_for!(RuleSet)),
+        SchemaKind::Manifest => serde_json::json!(schema_for!(Manifest)),
+        SchemaKind::Config => config_file::schema()?,
+    })
+}
+
+/// Write a schema to a file, or to stdout without one
+pub fn write_schema(kind: SchemaKind, output: Option<&str>) -> Result<()> {
+    let json =
+        serde_json::to_string_pretty(&schema(kind)?).context("Failed to serialize schema")?;
+
+    match output {
+        Some(path) => {
+            fs::write(path, json + "\n")
+                .context(format!("Failed to write schema file: {}", path))?;
+            info!("📐 Wrote {} schema to {}", kind, path);
+        }
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
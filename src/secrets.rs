@@ -0,0 +1,163 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer};
+use serde_yaml::Value;
+use std::fmt::Display;
+use std::fs;
+use std::process::Command;
+use std::str::FromStr;
+
+/// Expand `${...}` references in a string.
+///
+/// Supported forms:
+/// - `${NAME}` or `${env:NAME}`: environment variable
+/// - `${NAME:-fallback}`: environment variable with a default
+/// - `${file:/run/secrets/token}`: contents of a file, trailing newline removed
+/// - `${exec:pass show fatt/webhook}`: stdout of a shell command, trailing newline removed
+///
+/// `$${` produces a literal `${`.
+pub fn interpolate(input: &str) -> Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        // `$${` escapes the reference
+        if rest[..start].ends_with('$') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        output.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .context(format!("Unterminated ${{...}} reference in: {}", input))?;
+        let reference = &rest[start + 2..start + end];
+        output.push_str(&resolve(reference)?);
+        rest = &rest[start + end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Resolve the inside of a `${...}` reference
+pub fn resolve(reference: &str) -> Result<String> {
+    let (provider, argument) = match reference.split_once(':') {
+        Some((provider @ ("env" | "file" | "exec"), argument)) => (provider, argument),
+        _ => ("env", reference),
+    };
+
+    match provider {
+        "file" => {
+            let contents = fs::read_to_string(argument)
+                .context(format!("Failed to read secret file: {}", argument))?;
+            Ok(trim_newline(contents))
+        }
+        "exec" => {
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(argument)
+                .output()
+                .context(format!("Failed to run secret command: {}", argument))?;
+            if !output.status.success() {
+                anyhow::bail!("Secret command failed ({}): {}", output.status, argument);
+            }
+            Ok(trim_newline(String::from_utf8(output.stdout).context(
+                format!("Secret command printed invalid UTF-8: {}", argument),
+            )?))
+        }
+        _ => {
+            let (name, default) = match argument.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (argument, None),
+            };
+            match (std::env::var(name), default) {
+                (Ok(value), _) => Ok(value),
+                (Err(_), Some(default)) => Ok(default.to_string()),
+                (Err(_), None) => anyhow::bail!("Environment variable {} is not set", name),
+            }
+        }
+    }
+}
+
+/// Expand references in every string of a parsed YAML document.
+///
+/// Expanded values stay strings, even when a secret looks like a number; numeric and
+/// boolean settings read them with [`from_str_or_value`].
+pub fn interpolate_value(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(s) if s.contains("${") => *s = interpolate(s)?,
+        Value::String(_) => {}
+        Value::Sequence(items) => {
+            for item in items {
+                interpolate_value(item)?;
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (_, item) in mapping.iter_mut() {
+                interpolate_value(item)?;
+            }
+        }
+        Value::Tagged(tagged) => interpolate_value(&mut tagged.value)?,
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+
+    Ok(())
+}
+
+/// A setting written as itself or as a string, such as the one left by
+/// `concurrency: ${SCAN_CONCURRENCY}`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StrOrValue<T> {
+    Value(T),
+    Str(String),
+}
+
+impl<T> StrOrValue<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    fn parse<E: serde::de::Error>(self) -> std::result::Result<T, E> {
+        match self {
+            StrOrValue::Value(value) => Ok(value),
+            StrOrValue::Str(s) => s
+                .trim()
+                .parse()
+                .map_err(|e| E::custom(format!("invalid value {:?}: {}", s, e))),
+        }
+    }
+}
+
+/// Deserialize a number or boolean that may also be given as a string
+pub fn from_str_or_value<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    StrOrValue::deserialize(deserializer)?.parse()
+}
+
+/// Deserialize an optional number or boolean that may also be given as a string
+pub fn option_from_str_or_value<'de, D, T>(
+    deserializer: D,
+) -> std::result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    Option::<StrOrValue<T>>::deserialize(deserializer)?
+        .map(StrOrValue::parse)
+        .transpose()
+}
+
+fn trim_newline(mut value: String) -> String {
+    while value.ends_with('\n') || value.ends_with('\r') {
+        value.pop();
+    }
+    value
+}
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::exit;
+
+/// How far a scan has been asked to stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShutdownState {
+    /// Scanning normally
+    Running,
+    /// No new domains are started; the ones in progress finish
+    Draining,
+    /// Work in progress is abandoned; outcomes already decided are still written
+    Forced,
+}
+
+/// Error a scan ends with when it was stopped before every domain was scanned
+#[derive(Debug, thiserror::Error)]
+#[error("Scan interrupted after {processed} of {total} domains")]
+pub struct ScanInterrupted {
+    pub processed: usize,
+    pub total: usize,
+}
+
+/// Stop requests for a running scan, shared by every pipeline stage.
+///
+/// The first request drains the scan, the second forces it to stop.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    state: Arc<watch::Sender<ShutdownState>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(watch::channel(ShutdownState::Running).0),
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn state(&self) -> ShutdownState {
+        *self.state.borrow()
+    }
+
+    /// Whether new domains should no longer be started
+    pub fn is_stopping(&self) -> bool {
+        self.state() >= ShutdownState::Draining
+    }
+
+    /// Ask the scan to stop, escalating from draining to forced; returns the new state
+    pub fn request(&self) -> ShutdownState {
+        self.state.send_modify(|state| {
+            *state = match state {
+                ShutdownState::Running => ShutdownState::Draining,
+                _ => ShutdownState::Forced,
+            }
+        });
+        self.state()
+    }
+
+    /// Wait until the scan is forced to stop
+    pub async fn forced(&self) {
+        let mut rx = self.state.subscribe();
+        let _ = rx.wait_for(|state| *state == ShutdownState::Forced).await;
+    }
+
+    /// Turn Ctrl-C presses into stop requests until the returned listener is dropped.
+    ///
+    /// A third press exits on the spot, for when even writing the results hangs.
+    pub fn listen_for_ctrl_c(&self) -> CtrlCListener {
+        let shutdown = self.clone();
+        CtrlCListener(tokio::spawn(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if shutdown.state() == ShutdownState::Forced {
+                    warn!("⏹️ Quitting immediately");
+                    std::process::exit(exit::INTERRUPTED.into());
+                }
+                match shutdown.request() {
+                    ShutdownState::Draining => warn!(
+                        "⏹️ Stopping: finishing domains in progress (press Ctrl-C again to quit now)"
+                    ),
+                    _ => warn!("⏹️ Quitting: writing results already checked"),
+                }
+            }
+        }))
+    }
+}
+
+/// Handles Ctrl-C for a scan while it's alive
+pub struct CtrlCListener(JoinHandle<()>);
+
+impl Drop for CtrlCListener {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
@@ -0,0 +1,496 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use crate::config::ScanConfig;
+use crate::scanner::{self, HttpClientConfig};
+use crate::shutdown::ScanInterrupted;
+use crate::utils;
+
+/// Certificate Transparency search API queried by `ct:` sources
+pub const CRT_SH_URL: &str = "https://crt.sh/";
+
+/// Most messages taken from a Kafka topic in one poll
+#[cfg(feature = "kafka")]
+const KAFKA_BATCH: usize = 10_000;
+
+/// A feed of domains to scan. Each poll returns the domains that appeared since the
+/// previous one, so a long-running scan can pick up new assets as they're discovered.
+#[async_trait]
+pub trait TargetSource: Send {
+    /// Domains that appeared since the last poll, or `None` once the source has
+    /// nothing more to give. A poll doesn't wait for new domains to appear.
+    async fn poll(&mut self) -> Result<Option<Vec<String>>>;
+}
+
+/// Where a source's domains come from, as given to `--source`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceSpec {
+    /// Lines appended to a file (`file:PATH`)
+    File(String),
+    /// Lines read from standard input (`stdin` or `-`)
+    Stdin,
+    /// Names in certificates logged for a domain and its subdomains (`ct:DOMAIN`)
+    Ct(String),
+    /// An endpoint returning domains, one per line or as a JSON array (`http(s)://...`)
+    Http(String),
+    /// Messages on a Kafka topic, one or more domains per message
+    /// (`kafka://BROKERS/TOPIC`)
+    Kafka { brokers: String, topic: String },
+}
+
+impl FromStr for SourceSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "stdin" || s == "-" {
+            return Ok(SourceSpec::Stdin);
+        }
+        if s.starts_with("http://") || s.starts_with("https://") {
+            url::Url::parse(s).map_err(|e| anyhow::anyhow!("Invalid source URL {}: {}", s, e))?;
+            return Ok(SourceSpec::Http(s.to_string()));
+        }
+        if let Some(rest) = s.strip_prefix("kafka://") {
+            return match rest.split_once('/') {
+                Some((brokers, topic)) if !brokers.is_empty() && !topic.is_empty() => {
+                    Ok(SourceSpec::Kafka {
+                        brokers: brokers.to_string(),
+                        topic: topic.to_string(),
+                    })
+                }
+                _ => anyhow::bail!("Invalid Kafka source {}: expected kafka://BROKERS/TOPIC", s),
+            };
+        }
+
+        match s.split_once(':') {
+            Some(("file", path)) if !path.is_empty() => Ok(SourceSpec::File(path.to_string())),
+            Some(("ct", domain)) if !domain.is_empty() => {
+                Ok(SourceSpec::Ct(utils::normalize_domain(domain)))
+            }
+            _ => anyhow::bail!(
+                "Invalid target source: {} (expected file:PATH, stdin, ct:DOMAIN, an http(s) URL or kafka://BROKERS/TOPIC)",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SourceSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceSpec::File(path) => write!(f, "file:{}", path),
+            SourceSpec::Stdin => write!(f, "stdin"),
+            SourceSpec::Ct(domain) => write!(f, "ct:{}", domain),
+            SourceSpec::Http(url) => write!(f, "{}", url),
+            SourceSpec::Kafka { brokers, topic } => write!(f, "kafka://{}/{}", brokers, topic),
+        }
+    }
+}
+
+impl SourceSpec {
+    /// Open the source; `client` makes the requests of HTTP and CT sources
+    pub fn open(&self, client: &Client) -> Result<Box<dyn TargetSource>> {
+        Ok(match self {
+            SourceSpec::File(path) => Box::new(FileSource::new(path)),
+            SourceSpec::Stdin => Box::new(LineSource::new(BufReader::new(tokio::io::stdin()))),
+            SourceSpec::Ct(domain) => Box::new(CtSource::new(client.clone(), CRT_SH_URL, domain)),
+            SourceSpec::Http(url) => Box::new(HttpSource::new(client.clone(), url)),
+            #[cfg(feature = "kafka")]
+            SourceSpec::Kafka { brokers, topic } => Box::new(KafkaSource::connect(brokers, topic)?),
+            #[cfg(not(feature = "kafka"))]
+            SourceSpec::Kafka { .. } => {
+                anyhow::bail!(
+                    "This build of fatt has no Kafka support (enable the `kafka` feature)"
+                )
+            }
+        })
+    }
+}
+
+/// The domains in lines of text, skipping blank lines and comments
+fn domains_in(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+/// A file that new domains are appended to, such as the output of a discovery job.
+///
+/// Each poll reads the lines added since the last one. A last line without a newline
+/// is left for the next poll, in case it's still being written, unless the file
+/// hasn't grown since.
+pub struct FileSource {
+    path: String,
+    /// Bytes already read
+    offset: u64,
+    /// Size of the file at the last poll
+    last_len: u64,
+}
+
+impl FileSource {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            offset: 0,
+            last_len: 0,
+        }
+    }
+}
+
+#[async_trait]
+impl TargetSource for FileSource {
+    async fn poll(&mut self) -> Result<Option<Vec<String>>> {
+        let mut file = File::open(&self.path)
+            .context(format!("Failed to open domains file: {}", self.path))?;
+        let len = file.metadata()?.len();
+        if len < self.offset {
+            // Truncated or replaced: start over
+            self.offset = 0;
+        }
+
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut text = String::new();
+        file.read_to_string(&mut text)
+            .context(format!("Failed to read {}", self.path))?;
+
+        let complete = match text.rfind('\n') {
+            _ if len == self.last_len => text.len(),
+            Some(end) => end + 1,
+            None => 0,
+        };
+        self.offset += complete as u64;
+        self.last_len = len;
+
+        Ok(Some(domains_in(&text[..complete])))
+    }
+}
+
+/// Lines read from a stream such as standard input, in the background so a poll
+/// never waits for the next one
+pub struct LineSource {
+    lines: mpsc::UnboundedReceiver<Result<String>>,
+}
+
+impl LineSource {
+    pub fn new<R: AsyncBufRead + Send + Unpin + 'static>(reader: R) -> Self {
+        let (tx, lines) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut reader = reader.lines();
+            loop {
+                let line = match reader.next_line().await {
+                    Ok(Some(line)) => Ok(line),
+                    Ok(None) => return,
+                    Err(e) => Err(anyhow::Error::from(e).context("Failed to read domains")),
+                };
+                let failed = line.is_err();
+                if tx.send(line).is_err() || failed {
+                    return;
+                }
+            }
+        });
+
+        Self { lines }
+    }
+}
+
+#[async_trait]
+impl TargetSource for LineSource {
+    async fn poll(&mut self) -> Result<Option<Vec<String>>> {
+        let mut text = String::new();
+        loop {
+            match self.lines.try_recv() {
+                Ok(line) => {
+                    text.push_str(&line?);
+                    text.push('\n');
+                }
+                Err(mpsc::error::TryRecvError::Empty) => break,
+                // The stream ended: hand over what was left, then report the end
+                Err(mpsc::error::TryRecvError::Disconnected) if text.is_empty() => return Ok(None),
+                Err(mpsc::error::TryRecvError::Disconnected) => break,
+            }
+        }
+
+        Ok(Some(domains_in(&text)))
+    }
+}
+
+/// One certificate in a crt.sh search
+#[derive(Debug, Deserialize)]
+struct CtEntry {
+    id: i64,
+    name_value: String,
+}
+
+/// Names in certificates logged for a domain and its subdomains, found through a
+/// crt.sh-compatible Certificate Transparency search API. Each poll returns the names
+/// in certificates logged since the last one.
+pub struct CtSource {
+    client: Client,
+    api: String,
+    domain: String,
+    /// Highest certificate ID seen
+    last_id: i64,
+}
+
+impl CtSource {
+    pub fn new(client: Client, api: &str, domain: &str) -> Self {
+        Self {
+            client,
+            api: api.to_string(),
+            domain: domain.to_string(),
+            last_id: 0,
+        }
+    }
+}
+
+#[async_trait]
+impl TargetSource for CtSource {
+    async fn poll(&mut self) -> Result<Option<Vec<String>>> {
+        let entries: Vec<CtEntry> = self
+            .client
+            .get(&self.api)
+            .query(&[
+                ("q", format!("%.{}", self.domain).as_str()),
+                ("output", "json"),
+            ])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context(format!("Failed to search CT logs for {}", self.domain))?
+            .json()
+            .await
+            .context(format!(
+                "Failed to parse CT search results for {}",
+                self.domain
+            ))?;
+
+        let suffix = format!(".{}", self.domain);
+        let mut domains = Vec::new();
+        let mut last_id = self.last_id;
+        for entry in entries.iter().filter(|entry| entry.id > self.last_id) {
+            last_id = last_id.max(entry.id);
+            for name in entry.name_value.lines() {
+                // A wildcard certificate says the parent domain is in use
+                let name = utils::normalize_domain(name.trim_start_matches("*."));
+                if name == self.domain || name.ends_with(&suffix) {
+                    domains.push(name);
+                }
+            }
+        }
+        self.last_id = last_id;
+
+        Ok(Some(domains))
+    }
+}
+
+/// An endpoint of an asset inventory returning domains, one per line or as a JSON
+/// array of names or of objects with a `domain` field
+pub struct HttpSource {
+    client: Client,
+    url: String,
+}
+
+impl HttpSource {
+    pub fn new(client: Client, url: &str) -> Self {
+        Self {
+            client,
+            url: url.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl TargetSource for HttpSource {
+    async fn poll(&mut self) -> Result<Option<Vec<String>>> {
+        let body = self
+            .client
+            .get(&self.url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context(format!("Failed to fetch targets from {}", self.url))?
+            .text()
+            .await
+            .context(format!("Failed to read targets from {}", self.url))?;
+
+        if !body.trim_start().starts_with('[') {
+            return Ok(Some(domains_in(&body)));
+        }
+
+        let items: Vec<serde_json::Value> = serde_json::from_str(&body)
+            .context(format!("Failed to parse targets from {}", self.url))?;
+        let domains = items
+            .iter()
+            .filter_map(|item| match item {
+                serde_json::Value::String(domain) => Some(domain.as_str()),
+                item => item.get("domain").and_then(|domain| domain.as_str()),
+            })
+            .map(String::from)
+            .collect();
+
+        Ok(Some(domains))
+    }
+}
+
+/// Messages on a Kafka topic, each holding one or more domains, one per line
+#[cfg(feature = "kafka")]
+pub struct KafkaSource {
+    consumer: rdkafka::consumer::StreamConsumer,
+}
+
+#[cfg(feature = "kafka")]
+impl KafkaSource {
+    /// Join the `fatt` consumer group on a topic, starting from the earliest
+    /// uncommitted message
+    pub fn connect(brokers: &str, topic: &str) -> Result<Self> {
+        use rdkafka::consumer::Consumer;
+
+        let consumer: rdkafka::consumer::StreamConsumer = rdkafka::ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("group.id", "fatt")
+            .set("auto.offset.reset", "earliest")
+            .set("enable.auto.commit", "true")
+            .create()
+            .context(format!("Failed to connect to Kafka at {}", brokers))?;
+        consumer
+            .subscribe(&[topic])
+            .context(format!("Failed to subscribe to Kafka topic {}", topic))?;
+
+        Ok(Self { consumer })
+    }
+}
+
+#[cfg(feature = "kafka")]
+#[async_trait]
+impl TargetSource for KafkaSource {
+    async fn poll(&mut self) -> Result<Option<Vec<String>>> {
+        use rdkafka::Message;
+
+        let mut domains = Vec::new();
+        for _ in 0..KAFKA_BATCH {
+            let message = match tokio::time::timeout(
+                Duration::from_millis(100),
+                self.consumer.recv(),
+            )
+            .await
+            {
+                Ok(message) => message.context("Failed to read from Kafka")?,
+                // Nothing more for now
+                Err(_) => break,
+            };
+            if let Some(payload) = message.payload() {
+                domains.extend(domains_in(&String::from_utf8_lossy(payload)));
+            }
+        }
+
+        Ok(Some(domains))
+    }
+}
+
+/// Scan domains from live sources as they appear: every `interval_secs`, poll each
+/// source and scan the domains not seen before as a scan of their own. Stops when
+/// every source is exhausted or on Ctrl-C.
+///
+/// Domains are remembered for as long as this runs, so one reported again by a
+/// source (or by several) is only scanned the first time.
+pub async fn follow_sources(
+    config: ScanConfig,
+    specs: &[SourceSpec],
+    interval_secs: u64,
+) -> Result<()> {
+    let client = scanner::build_http_client(&HttpClientConfig::default())?;
+    let mut sources = specs
+        .iter()
+        .map(|spec| Ok((spec.to_string(), spec.open(&client)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let batch_dir = tempfile::tempdir().context("Failed to create a directory for batches")?;
+    let mut seen = HashSet::new();
+    let mut iteration = 0;
+
+    info!(
+        "🛰️ Following {} target sources every {}s",
+        sources.len(),
+        interval_secs
+    );
+    loop {
+        let mut fresh = Vec::new();
+        let mut exhausted = Vec::new();
+        for (index, (name, source)) in sources.iter_mut().enumerate() {
+            match source.poll().await {
+                Ok(Some(domains)) => {
+                    let before = fresh.len();
+                    for domain in domains {
+                        let domain = utils::normalize_domain(&domain);
+                        if seen.insert(domain.clone()) {
+                            fresh.push(domain);
+                        }
+                    }
+                    debug!("{} new domains from {}", fresh.len() - before, name);
+                }
+                Ok(None) => {
+                    info!("🛰️ {} has no more domains", name);
+                    exhausted.push(index);
+                }
+                Err(e) => warn!("⚠️ Failed to poll {}: {:#}", name, e),
+            }
+        }
+        for index in exhausted.into_iter().rev() {
+            sources.remove(index);
+        }
+
+        if !fresh.is_empty() {
+            iteration += 1;
+            info!(
+                "🛰️ {} new domains, starting scan #{}",
+                fresh.len(),
+                iteration
+            );
+
+            let batch_file = batch_dir.path().join(format!("batch-{}.txt", iteration));
+            let mut file = File::create(&batch_file).context("Failed to write batch")?;
+            for domain in &fresh {
+                writeln!(file, "{}", domain)?;
+            }
+            drop(file);
+
+            let mut batch = config.clone();
+            batch.input_file = batch_file.to_string_lossy().into_owned();
+            batch.input_tag = None;
+            batch.extra_inputs.clear();
+            let result = scanner::run_scan(batch).await;
+            std::fs::remove_file(&batch_file).ok();
+            if let Err(e) = result {
+                if e.is::<ScanInterrupted>() {
+                    info!("⏹️ Stopped following target sources");
+                    return Err(e);
+                }
+                error!("❌ Scan #{} failed: {:#}", iteration, e);
+            }
+        }
+
+        if sources.is_empty() {
+            info!("🛰️ Every target source is exhausted");
+            return Ok(());
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(interval_secs)) => {}
+            _ = tokio::signal::ctrl_c() => {
+                info!("⏹️ Stopped following target sources");
+                return Ok(());
+            }
+        }
+    }
+}
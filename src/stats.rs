@@ -0,0 +1,147 @@
+use reqwest::header::HeaderMap;
+use reqwest::Request;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::otel;
+use crate::timeouts::TimeoutPhase;
+
+/// Network accounting for a scan.
+///
+/// Counters are updated from every check, so they are atomics rather than
+/// being threaded back through the rule futures.
+#[derive(Debug, Default)]
+pub struct ScanStats {
+    /// Whether response header bytes count towards the download total
+    capture_headers: bool,
+    requests: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    tls_failures: AtomicU64,
+    timeouts: AtomicU64,
+    timeout_phases: Mutex<BTreeMap<TimeoutPhase, u64>>,
+    connect_failures: AtomicU64,
+    status_counts: Mutex<BTreeMap<u16, u64>>,
+}
+
+/// Point-in-time copy of the scan statistics
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    pub requests: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub tls_failures: u64,
+    pub timeouts: u64,
+    /// Timeouts by the phase they expired in
+    #[serde(default)]
+    pub timeout_phases: BTreeMap<TimeoutPhase, u64>,
+    pub connect_failures: u64,
+    pub status_counts: BTreeMap<u16, u64>,
+}
+
+impl ScanStats {
+    /// Create empty statistics, optionally counting response header bytes
+    pub fn new(capture_headers: bool) -> Self {
+        Self {
+            capture_headers,
+            ..Default::default()
+        }
+    }
+
+    /// Record an outgoing request
+    pub fn record_request(&self, request: &Request) {
+        // Request line plus headers; the client adds a few default headers we can't see
+        let size = request.method().as_str().len()
+            + request.url().as_str().len()
+            + header_bytes(request.headers())
+            + request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map_or(0, |body| body.len());
+
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    /// Record a received response
+    pub fn record_response(&self, status: u16, headers: &HeaderMap, body_len: usize) {
+        otel::record_response(status);
+        let mut size = body_len;
+        if self.capture_headers {
+            size += header_bytes(headers);
+        }
+        self.bytes_received
+            .fetch_add(size as u64, Ordering::Relaxed);
+
+        let mut status_counts = self.status_counts.lock().unwrap();
+        *status_counts.entry(status).or_insert(0) += 1;
+    }
+
+    /// Record a failed request, classifying it by cause. A timeout is put down to
+    /// connecting or to the request as a whole; use `record_timeout` when its phase is known.
+    pub fn record_error(&self, error: &reqwest::Error) {
+        if error.is_timeout() {
+            self.record_timeout(if error.is_connect() {
+                TimeoutPhase::Connect
+            } else {
+                TimeoutPhase::Total
+            });
+        } else if is_tls_error(error) {
+            self.tls_failures.fetch_add(1, Ordering::Relaxed);
+        } else if error.is_connect() {
+            self.connect_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record a timeout in `phase`
+    pub fn record_timeout(&self, phase: TimeoutPhase) {
+        self.timeouts.fetch_add(1, Ordering::Relaxed);
+        *self
+            .timeout_phases
+            .lock()
+            .unwrap()
+            .entry(phase)
+            .or_insert(0) += 1;
+    }
+
+    /// Copy the current counters
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            tls_failures: self.tls_failures.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            timeout_phases: self.timeout_phases.lock().unwrap().clone(),
+            connect_failures: self.connect_failures.load(Ordering::Relaxed),
+            status_counts: self.status_counts.lock().unwrap().clone(),
+        }
+    }
+}
+
+/// Approximate wire size of a header block (`name: value\r\n` per header)
+fn header_bytes(headers: &HeaderMap) -> usize {
+    headers
+        .iter()
+        .map(|(name, value)| name.as_str().len() + value.len() + 4)
+        .sum()
+}
+
+/// reqwest doesn't expose TLS errors directly, so look for them in the source chain
+fn is_tls_error(error: &reqwest::Error) -> bool {
+    let mut source = error.source();
+    while let Some(err) = source {
+        let message = err.to_string().to_lowercase();
+        if message.contains("certificate")
+            || message.contains("handshake")
+            || message.contains("tls")
+        {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
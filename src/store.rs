@@ -0,0 +1,1696 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::cert_inventory::SiteCertificate;
+use crate::db::{
+    self, DomainScan, FalsePositive, Finding, FindingNote, ListFormat, ListQuery, PurgeFilter,
+    ScanSession, ShardedDb, SplitBy, TimeRange,
+};
+use crate::evidence::Evidence;
+use crate::migrate::{self, MigrationReport};
+#[cfg(feature = "postgres")]
+use crate::pg::{self, PgStore};
+use crate::profile::ScanProfile;
+use crate::redact::RedactionPolicy;
+use crate::risk::RiskReport;
+use crate::rules::{RuleSet, RuleSnapshot};
+use crate::summary::{self, GroupBy};
+
+/// What was observed alongside a rule check's outcome
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FindingDetails<'a> {
+    /// User-Agent sent with the check, if rotating
+    pub user_agent: Option<&'a str>,
+
+    /// URLs the request was redirected through, ending with the final one
+    pub redirect_chain: &'a [String],
+
+    /// Tag of the input the domain was read from
+    pub tag: Option<&'a str>,
+
+    /// HTTP status of the response the outcome was observed in
+    pub status: Option<u16>,
+
+    /// Version of the scan's rules the check used
+    pub ruleset_version: Option<u32>,
+
+    /// Digest of the rules the check used
+    pub ruleset_digest: Option<&'a str>,
+
+    /// Part of the body the signature matched in
+    pub evidence: Option<&'a Evidence>,
+
+    /// Project the scan was run for
+    pub project: Option<&'a str>,
+}
+
+/// The outcome of a rule check waiting to be written, owning what a
+/// [`FindingDetails`] borrows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingFinding {
+    pub domain: String,
+    pub rule_name: String,
+    pub matched_path: String,
+    pub detected: bool,
+    pub user_agent: Option<String>,
+    pub redirect_chain: Vec<String>,
+    pub tag: Option<String>,
+    pub status: Option<u16>,
+    pub ruleset_version: Option<u32>,
+    pub ruleset_digest: Option<String>,
+    pub evidence: Option<Evidence>,
+    pub project: Option<String>,
+}
+
+impl PendingFinding {
+    pub fn new(
+        domain: &str,
+        rule_name: &str,
+        matched_path: &str,
+        detected: bool,
+        details: &FindingDetails,
+    ) -> Self {
+        Self {
+            domain: domain.to_string(),
+            rule_name: rule_name.to_string(),
+            matched_path: matched_path.to_string(),
+            detected,
+            user_agent: details.user_agent.map(str::to_string),
+            redirect_chain: details.redirect_chain.to_vec(),
+            tag: details.tag.map(str::to_string),
+            status: details.status,
+            ruleset_version: details.ruleset_version,
+            ruleset_digest: details.ruleset_digest.map(str::to_string),
+            evidence: details.evidence.cloned(),
+            project: details.project.map(str::to_string),
+        }
+    }
+
+    pub fn details(&self) -> FindingDetails<'_> {
+        FindingDetails {
+            user_agent: self.user_agent.as_deref(),
+            redirect_chain: &self.redirect_chain,
+            tag: self.tag.as_deref(),
+            status: self.status,
+            ruleset_version: self.ruleset_version,
+            ruleset_digest: self.ruleset_digest.as_deref(),
+            evidence: self.evidence.as_ref(),
+            project: self.project.as_deref(),
+        }
+    }
+}
+
+/// Persistence for scan results
+pub trait ResultStore: Send {
+    /// Record the start of a scan session
+    fn start_scan(&mut self, scan_id: &str, started_at: DateTime<Utc>) -> Result<()>;
+
+    /// Record a version of the rules the scan session runs with: the initial rules, and
+    /// every reload after them
+    fn record_ruleset(&mut self, scan_id: &str, rules: &RuleSnapshot) -> Result<()>;
+
+    /// Record the tuning profile the scan session runs with
+    fn record_profile(&mut self, scan_id: &str, profile: ScanProfile) -> Result<()>;
+
+    /// Record the project the scan session is run for
+    fn record_project(&mut self, scan_id: &str, project: &str) -> Result<()>;
+
+    /// Record the outcome of a rule check, replacing any earlier one for the same domain and rule
+    fn record_finding(
+        &mut self,
+        domain: &str,
+        rule_name: &str,
+        matched_path: &str,
+        detected: bool,
+        details: &FindingDetails,
+    ) -> Result<()>;
+
+    /// Record the outcomes of many rule checks, in a single transaction where the
+    /// store has them
+    fn record_findings(&mut self, batch: &[PendingFinding]) -> Result<()> {
+        for finding in batch {
+            self.record_finding(
+                &finding.domain,
+                &finding.rule_name,
+                &finding.matched_path,
+                finding.detected,
+                &finding.details(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Record what the scan saw of a domain: its addresses and technologies, or why it
+    /// couldn't be scanned
+    fn record_domain(&mut self, scan: &DomainScan) -> Result<()>;
+
+    /// Detected findings recorded at or after the given time
+    fn detected_since(&self, since: DateTime<Utc>) -> Result<Vec<Finding>>;
+
+    /// Detected findings whose evidence carries cache validators, ordered by domain and rule
+    fn revalidatable(&self) -> Result<Vec<Finding>>;
+
+    /// Record the outcome of a scan session, including per-domain risk scores
+    fn finish_scan(&mut self, scan_id: &str, domains: usize, risk: &RiskReport) -> Result<()>;
+
+    /// Mark a scan session as stopped before every domain was scanned
+    fn mark_incomplete(&mut self, scan_id: &str) -> Result<()>;
+
+    /// Keep the detected findings of a scan session, so it can be compared with others
+    fn record_scan_findings(&mut self, scan_id: &str, findings: &[Finding]) -> Result<()>;
+
+    /// Detected findings of a scan session, ordered by domain and rule
+    fn scan_findings(&self, scan_id: &str) -> Result<Vec<Finding>>;
+
+    /// Record per-rule detection counts for a scan session
+    fn record_rule_counts(&mut self, scan_id: &str, counts: &BTreeMap<String, usize>)
+        -> Result<()>;
+
+    /// Average per-rule detection counts over the most recent scans (excluding `scan_id`)
+    fn rule_baseline(&self, scan_id: &str, scans: usize) -> Result<(usize, HashMap<String, f64>)>;
+
+    /// Every finding, ordered by domain and rule
+    fn findings(&self) -> Result<Vec<Finding>>;
+
+    /// What every recorded scan saw of a domain, newest first
+    fn domain_scans(&self, domain: &str) -> Result<Vec<DomainScan>>;
+
+    /// Delete the findings a filter selects, returning how many were removed
+    fn purge_findings(&mut self, filter: &PurgeFilter) -> Result<usize>;
+
+    /// Domain and rule pairs marked as false positives, ordered by domain and rule
+    fn false_positives(&self) -> Result<Vec<FalsePositive>>;
+
+    /// Analysts' notes on findings, by domain and rule and oldest first within one
+    fn notes(&self) -> Result<Vec<FindingNote>>;
+
+    /// The certificate each host scanned over https last presented, ordered by host
+    fn certificates(&self) -> Result<Vec<SiteCertificate>>;
+}
+
+/// A result store shared by every task in a scan
+pub type SharedStore = Arc<Mutex<dyn ResultStore>>;
+
+/// Whether a database setting is a PostgreSQL connection URL rather than a file
+pub fn is_postgres_url(database: &str) -> bool {
+    database.starts_with("postgres://") || database.starts_with("postgresql://")
+}
+
+/// A database setting fit for logs: a PostgreSQL URL's password is masked
+pub fn redact_url(database: &str) -> String {
+    if !is_postgres_url(database) {
+        return database.to_string();
+    }
+    match url::Url::parse(database) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some("********"));
+            url.to_string()
+        }
+        _ => database.to_string(),
+    }
+}
+
+/// Error for a PostgreSQL database in a build without the `postgres` feature
+#[cfg(not(feature = "postgres"))]
+pub fn postgres_unavailable() -> anyhow::Error {
+    anyhow::anyhow!("This build of fatt has no PostgreSQL support (enable the postgres feature)")
+}
+
+/// Available result store backends
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreKind {
+    /// A single SQLite database file
+    #[default]
+    Sqlite,
+    /// A directory of append-only JSONL files, one per scan
+    Jsonl,
+    /// A PostgreSQL database given by a `postgres://` URL, which many workers can share
+    Postgres,
+}
+
+impl StoreKind {
+    /// Backend holding `database`: a `postgres://` URL is always PostgreSQL
+    pub fn resolve(self, database: &str) -> StoreKind {
+        if is_postgres_url(database) {
+            StoreKind::Postgres
+        } else {
+            self
+        }
+    }
+}
+
+impl FromStr for StoreKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "sqlite" => Ok(StoreKind::Sqlite),
+            "jsonl" => Ok(StoreKind::Jsonl),
+            "postgres" | "postgresql" => Ok(StoreKind::Postgres),
+            other => anyhow::bail!(
+                "Unknown result store: {} (expected sqlite, jsonl or postgres)",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for StoreKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreKind::Sqlite => write!(f, "sqlite"),
+            StoreKind::Jsonl => write!(f, "jsonl"),
+            StoreKind::Postgres => write!(f, "postgres"),
+        }
+    }
+}
+
+/// Open a result store: `path` is a database file for SQLite, a directory for JSONL,
+/// a connection URL for PostgreSQL.
+///
+/// With more than one shard, SQLite findings are split across that many files.
+/// `db_tuning` puts SQLite files in WAL mode (see [`db::init_db_with`]).
+pub fn open_store(
+    kind: StoreKind,
+    path: &str,
+    shards: usize,
+    db_tuning: bool,
+) -> Result<SharedStore> {
+    Ok(match kind.resolve(path) {
+        StoreKind::Sqlite if shards > 1 => Arc::new(Mutex::new(
+            ShardedDb::open_with(path, shards, db_tuning)
+                .context("Failed to initialize sharded database")?,
+        )),
+        StoreKind::Sqlite => Arc::new(Mutex::new(
+            db::init_db_with(path, db_tuning).context("Failed to initialize database")?,
+        )),
+        StoreKind::Jsonl => Arc::new(Mutex::new(JsonlStore::open(path)?)),
+        #[cfg(feature = "postgres")]
+        StoreKind::Postgres => Arc::new(Mutex::new(PgStore::open(path)?)),
+        #[cfg(not(feature = "postgres"))]
+        StoreKind::Postgres => return Err(postgres_unavailable()),
+    })
+}
+
+/// Stores for writing the findings of each shard of a sharded SQLite database, each on
+/// a connection of its own, so shards are written in parallel instead of one at a time
+/// behind the lock of the store [`open_store`] opens. Empty unless findings are sharded.
+pub fn open_shard_writers(
+    kind: StoreKind,
+    path: &str,
+    shards: usize,
+    db_tuning: bool,
+) -> Result<Vec<SharedStore>> {
+    if kind.resolve(path) != StoreKind::Sqlite || shards < 2 {
+        return Ok(Vec::new());
+    }
+    (0..shards)
+        .map(|index| {
+            let shard = db::init_db_with(&db::shard_path(path, index), db_tuning)
+                .context("Failed to initialize database shard")?;
+            Ok(Arc::new(Mutex::new(shard)) as SharedStore)
+        })
+        .collect()
+}
+
+/// Open an existing result store to read from, like [`open_store`] but failing
+/// rather than creating one that isn't there
+pub fn open_results(kind: StoreKind, path: &str, shards: usize) -> Result<Box<dyn ResultStore>> {
+    let kind = kind.resolve(path);
+    if kind != StoreKind::Postgres && !Path::new(path).exists() {
+        anyhow::bail!("Results not found: {}", path);
+    }
+
+    Ok(match kind {
+        StoreKind::Sqlite if shards > 1 => Box::new(ShardedDb::open(path, shards)?),
+        StoreKind::Sqlite => Box::new(db::init_db(path)?),
+        StoreKind::Jsonl => Box::new(JsonlStore::open(path)?),
+        #[cfg(feature = "postgres")]
+        StoreKind::Postgres => Box::new(PgStore::open(path)?),
+        #[cfg(not(feature = "postgres"))]
+        StoreKind::Postgres => return Err(postgres_unavailable()),
+    })
+}
+
+/// How findings are exported, and which
+#[derive(Debug, Clone, Copy)]
+pub struct ExportOptions<'a> {
+    /// csv, json or jsonl
+    pub format: &'a str,
+    /// Write one file per group instead of a single file
+    pub split_by: Option<SplitBy>,
+    pub redaction: &'a RedactionPolicy,
+    /// Only findings last scanned in this range
+    pub range: TimeRange,
+    /// Leave out checks that didn't match
+    pub detected_only: bool,
+    /// Rules whose remediation is attached to the findings, and whose severities
+    /// `group_by` severity goes by
+    pub ruleset: Option<&'a RuleSet>,
+    /// Write one aggregated row per group instead of the findings
+    pub group_by: Option<GroupBy>,
+    /// Only findings last checked for this project
+    pub project: Option<&'a str>,
+    /// Keep findings whose domain and rule are marked as false positives
+    pub include_false_positives: bool,
+}
+
+/// Export the findings in one or more stores to a file, or one file per group.
+///
+/// Secrets are masked again on the way out, as findings stored by an older version
+/// or under a narrower policy can still hold them.
+pub fn export_results(
+    kind: StoreKind,
+    databases: &[String],
+    shards: usize,
+    output_file: &str,
+    options: &ExportOptions,
+) -> Result<()> {
+    let mut findings = load_federated_findings(kind, databases, shards)?;
+    if !options.include_false_positives {
+        let false_positives = load_false_positives(kind, databases, shards)?;
+        findings.retain(|finding| !is_false_positive(&false_positives, finding));
+    }
+    findings.retain(|finding| {
+        options.range.contains(finding.scanned_at)
+            && (finding.detected || !options.detected_only)
+            && options
+                .project
+                .is_none_or(|project| finding.project.as_deref() == Some(project))
+    });
+    for finding in &mut findings {
+        options.redaction.redact_finding(finding);
+    }
+    if let Some(ruleset) = options.ruleset {
+        db::attach_remediation(&mut findings, ruleset);
+    }
+    db::attach_notes(&mut findings, load_notes(kind, databases, shards)?);
+
+    if let Some(group_by) = options.group_by {
+        if options.split_by.is_some() {
+            anyhow::bail!("A grouped export can't also be split into several files");
+        }
+        let groups = summary::group_findings(&findings, group_by, options.ruleset)?;
+        return summary::export_groups(&groups, group_by, output_file, options.format);
+    }
+
+    db::export_split(&findings, output_file, options.format, options.split_by)
+}
+
+/// Domain and rule pairs marked as false positives in any of several stores
+pub fn load_false_positives(
+    kind: StoreKind,
+    databases: &[String],
+    shards: usize,
+) -> Result<HashSet<(String, String)>> {
+    let mut false_positives = HashSet::new();
+    for database in expand_databases(databases)? {
+        let store = open_results(kind, &database, shards)?;
+        false_positives.extend(
+            store
+                .false_positives()?
+                .into_iter()
+                .map(|fp| (fp.domain, fp.rule_name)),
+        );
+    }
+
+    Ok(false_positives)
+}
+
+/// Notes on findings in any of several stores
+pub fn load_notes(
+    kind: StoreKind,
+    databases: &[String],
+    shards: usize,
+) -> Result<Vec<FindingNote>> {
+    let mut notes = Vec::new();
+    for database in expand_databases(databases)? {
+        notes.extend(open_results(kind, &database, shards)?.notes()?);
+    }
+
+    Ok(notes)
+}
+
+/// Whether a finding's domain and rule are among the pairs marked as false positives
+pub fn is_false_positive(false_positives: &HashSet<(String, String)>, finding: &Finding) -> bool {
+    false_positives.contains(&(finding.domain.clone(), finding.rule_name.clone()))
+}
+
+/// Every finding in a store
+pub fn load_findings(kind: StoreKind, path: &str, shards: usize) -> Result<Vec<Finding>> {
+    Ok(match kind.resolve(path) {
+        StoreKind::Sqlite => db::load_findings(path, shards)?,
+        StoreKind::Jsonl => JsonlStore::open(path)?.findings()?,
+        #[cfg(feature = "postgres")]
+        StoreKind::Postgres => PgStore::open(path)?.findings()?,
+        #[cfg(not(feature = "postgres"))]
+        StoreKind::Postgres => return Err(postgres_unavailable()),
+    })
+}
+
+/// Expand database arguments: a pattern with `*`, `?` or `[` stands for every file
+/// or JSONL directory it matches, in path order. Duplicates are dropped, and a
+/// pattern matching nothing is an error rather than an empty report.
+pub fn expand_databases(databases: &[String]) -> Result<Vec<String>> {
+    let mut expanded = Vec::new();
+    for database in databases {
+        if is_postgres_url(database) || !database.contains(['*', '?', '[']) {
+            expanded.push(database.clone());
+            continue;
+        }
+
+        let matches = glob::glob(database)
+            .context(format!("Invalid database pattern: {}", database))?
+            .map(|path| Ok(path?.to_string_lossy().to_string()))
+            .collect::<Result<Vec<_>>>()?;
+        if matches.is_empty() {
+            anyhow::bail!("No databases match {}", database);
+        }
+        expanded.extend(matches);
+    }
+
+    let mut seen = HashSet::new();
+    expanded.retain(|database| seen.insert(database.clone()));
+
+    Ok(expanded)
+}
+
+/// Every finding in several stores, as if they'd been merged with `results merge`:
+/// where more than one checked a domain and rule, the latest check wins
+pub fn load_federated_findings(
+    kind: StoreKind,
+    databases: &[String],
+    shards: usize,
+) -> Result<Vec<Finding>> {
+    let databases = expand_databases(databases)?;
+    if let [database] = databases.as_slice() {
+        return load_findings(kind, database, shards);
+    }
+
+    let mut sets = Vec::with_capacity(databases.len());
+    for database in &databases {
+        if kind.resolve(database) != StoreKind::Postgres && !Path::new(database).exists() {
+            anyhow::bail!("Results not found: {}", database);
+        }
+        sets.push(load_findings(kind, database, shards)?);
+    }
+
+    Ok(union_findings(sets))
+}
+
+/// Findings from several stores with one per domain and rule, the most recently
+/// scanned, ordered by domain and rule
+pub fn union_findings(sets: Vec<Vec<Finding>>) -> Vec<Finding> {
+    let mut latest: BTreeMap<(String, String), Finding> = BTreeMap::new();
+    for finding in sets.into_iter().flatten() {
+        let key = (finding.domain.clone(), finding.rule_name.clone());
+        match latest.get(&key) {
+            Some(kept) if kept.scanned_at >= finding.scanned_at => {}
+            _ => {
+                latest.insert(key, finding);
+            }
+        }
+    }
+
+    latest.into_values().collect()
+}
+
+/// Delete the findings a filter selects from a store, returning how many were removed
+pub fn purge_findings(
+    kind: StoreKind,
+    path: &str,
+    shards: usize,
+    filter: &PurgeFilter,
+) -> Result<usize> {
+    match kind.resolve(path) {
+        StoreKind::Sqlite if shards > 1 => ShardedDb::open(path, shards)?.purge_findings(filter),
+        StoreKind::Sqlite => db::purge_findings(&db::init_db(path)?, filter),
+        StoreKind::Jsonl => JsonlStore::open(path)?.purge_findings(filter),
+        #[cfg(feature = "postgres")]
+        StoreKind::Postgres => PgStore::open(path)?.purge_findings(filter),
+        #[cfg(not(feature = "postgres"))]
+        StoreKind::Postgres => Err(postgres_unavailable()),
+    }
+}
+
+/// Every scan session in a store, newest first
+pub fn load_scan_sessions(kind: StoreKind, path: &str) -> Result<Vec<ScanSession>> {
+    Ok(match kind.resolve(path) {
+        // Sessions are kept in the primary database, never in the shards
+        StoreKind::Sqlite => db::get_scan_sessions(&db::init_db(path)?, i64::MAX as usize)?,
+        StoreKind::Jsonl => JsonlStore::open(path)?.scan_sessions()?,
+        #[cfg(feature = "postgres")]
+        StoreKind::Postgres => PgStore::open(path)?.scan_sessions(i64::MAX as usize)?,
+        #[cfg(not(feature = "postgres"))]
+        StoreKind::Postgres => return Err(postgres_unavailable()),
+    })
+}
+
+/// Bring a SQLite database and its shards, or a PostgreSQL database, up to date
+/// with the schema, returning what was applied to each
+pub fn migrate_database(database: &str, shards: usize) -> Result<Vec<(String, MigrationReport)>> {
+    if is_postgres_url(database) {
+        #[cfg(feature = "postgres")]
+        {
+            let report = pg::migrate_database(database)?;
+            let database = redact_url(database);
+            log_migration(&database, &report);
+            return Ok(vec![(database, report)]);
+        }
+        #[cfg(not(feature = "postgres"))]
+        return Err(postgres_unavailable());
+    }
+
+    let mut files = vec![database.to_string()];
+    if shards > 1 {
+        files.extend(db::findings_files(database, shards));
+    }
+
+    files
+        .into_iter()
+        .map(|file| {
+            if !Path::new(&file).exists() {
+                anyhow::bail!("Database not found: {}", file);
+            }
+            let mut conn =
+                Connection::open(&file).context(format!("Failed to open database: {}", file))?;
+            let report = migrate::migrate(&mut conn)?;
+            log_migration(&file, &report);
+            Ok((file, report))
+        })
+        .collect()
+}
+
+fn log_migration(database: &str, report: &MigrationReport) {
+    for (version, name) in &report.applied {
+        info!("🔧 Applied migration {} to {}: {}", version, database, name);
+    }
+    if report.applied.is_empty() {
+        info!(
+            "✅ {} is up to date at schema version {}",
+            database, report.version
+        );
+    } else {
+        info!(
+            "✅ Migrated {} to schema version {}",
+            database, report.version
+        );
+    }
+}
+
+/// List a sorted page of findings from SQLite databases or PostgreSQL URLs. With
+/// several databases the page is taken from their findings combined, the latest
+/// check of each domain and rule winning.
+pub fn list_results(
+    databases: &[String],
+    shards: usize,
+    query: &ListQuery,
+    ruleset: Option<&RuleSet>,
+    format: ListFormat,
+) -> Result<()> {
+    let databases = expand_databases(databases)?;
+    if let [database] = databases.as_slice() {
+        return db::print_findings(&find_results(database, shards, query, ruleset)?, format);
+    }
+
+    // A database's first page may not survive the union, so every match is needed
+    let everything = ListQuery {
+        offset: 0,
+        limit: usize::MAX,
+        ..query.clone()
+    };
+    let mut sets = Vec::with_capacity(databases.len());
+    for database in &databases {
+        if !is_postgres_url(database) && !Path::new(database).exists() {
+            anyhow::bail!("Results not found: {}", database);
+        }
+        sets.push(find_results(database, shards, &everything, ruleset)?);
+    }
+
+    db::print_findings(&query.page(union_findings(sets), ruleset), format)
+}
+
+/// The page of findings a `results list` query asks for from one database
+fn find_results(
+    database: &str,
+    shards: usize,
+    query: &ListQuery,
+    ruleset: Option<&RuleSet>,
+) -> Result<Vec<Finding>> {
+    if !is_postgres_url(database) {
+        return db::find_results(database, shards, query, ruleset);
+    }
+
+    #[cfg(feature = "postgres")]
+    return PgStore::open(database)?.list_findings(query, ruleset);
+    #[cfg(not(feature = "postgres"))]
+    Err(postgres_unavailable())
+}
+
+/// List scan sessions from a SQLite database or a PostgreSQL URL, only those run for
+/// `project` if given
+pub fn list_scans(database: &str, limit: usize, project: Option<&str>) -> Result<()> {
+    if !is_postgres_url(database) {
+        return db::list_scans(database, limit, project);
+    }
+
+    #[cfg(feature = "postgres")]
+    {
+        // Fetch one extra session so the oldest listed scan still gets a trend
+        let fetch = match project {
+            Some(_) => usize::MAX,
+            None => limit + 1,
+        };
+        let sessions = PgStore::open(database)?.scan_sessions(fetch)?;
+        db::print_scans(&db::of_project(sessions, project), limit);
+        Ok(())
+    }
+    #[cfg(not(feature = "postgres"))]
+    Err(postgres_unavailable())
+}
+
+impl ResultStore for Connection {
+    fn start_scan(&mut self, scan_id: &str, started_at: DateTime<Utc>) -> Result<()> {
+        db::start_scan_session(self, scan_id, started_at)
+    }
+
+    fn record_ruleset(&mut self, scan_id: &str, rules: &RuleSnapshot) -> Result<()> {
+        db::record_ruleset(self, scan_id, rules)
+    }
+
+    fn record_profile(&mut self, scan_id: &str, profile: ScanProfile) -> Result<()> {
+        db::record_scan_profile(self, scan_id, profile)
+    }
+
+    fn record_project(&mut self, scan_id: &str, project: &str) -> Result<()> {
+        db::record_scan_project(self, scan_id, project)
+    }
+
+    fn record_finding(
+        &mut self,
+        domain: &str,
+        rule_name: &str,
+        matched_path: &str,
+        detected: bool,
+        details: &FindingDetails,
+    ) -> Result<()> {
+        write_finding(self, domain, rule_name, matched_path, detected, details)
+    }
+
+    fn record_findings(&mut self, batch: &[PendingFinding]) -> Result<()> {
+        write_batch(self, batch.iter())
+    }
+
+    fn record_domain(&mut self, scan: &DomainScan) -> Result<()> {
+        db::record_domain_scan(self, scan)
+    }
+
+    fn detected_since(&self, since: DateTime<Utc>) -> Result<Vec<Finding>> {
+        db::get_detected_since(self, since)
+    }
+
+    fn revalidatable(&self) -> Result<Vec<Finding>> {
+        db::get_revalidatable(self)
+    }
+
+    fn finish_scan(&mut self, scan_id: &str, domains: usize, risk: &RiskReport) -> Result<()> {
+        db::finish_scan_session(self, scan_id, domains, risk)
+    }
+
+    fn mark_incomplete(&mut self, scan_id: &str) -> Result<()> {
+        db::mark_scan_incomplete(self, scan_id)
+    }
+
+    fn record_scan_findings(&mut self, scan_id: &str, findings: &[Finding]) -> Result<()> {
+        db::record_scan_findings(self, scan_id, findings)
+    }
+
+    fn scan_findings(&self, scan_id: &str) -> Result<Vec<Finding>> {
+        db::get_scan_findings(self, scan_id)
+    }
+
+    fn record_rule_counts(
+        &mut self,
+        scan_id: &str,
+        counts: &BTreeMap<String, usize>,
+    ) -> Result<()> {
+        db::record_rule_counts(self, scan_id, counts)
+    }
+
+    fn rule_baseline(&self, scan_id: &str, scans: usize) -> Result<(usize, HashMap<String, f64>)> {
+        db::get_rule_baseline(self, scan_id, scans)
+    }
+
+    fn findings(&self) -> Result<Vec<Finding>> {
+        db::get_all_findings(self)
+    }
+
+    fn domain_scans(&self, domain: &str) -> Result<Vec<DomainScan>> {
+        db::get_domain_scans(self, domain)
+    }
+
+    fn purge_findings(&mut self, filter: &PurgeFilter) -> Result<usize> {
+        db::purge_findings(self, filter)
+    }
+
+    fn false_positives(&self) -> Result<Vec<FalsePositive>> {
+        db::get_false_positives(self)
+    }
+
+    fn notes(&self) -> Result<Vec<FindingNote>> {
+        db::get_all_notes(self)
+    }
+
+    fn certificates(&self) -> Result<Vec<SiteCertificate>> {
+        db::get_certificates(self)
+    }
+}
+
+/// Write the outcome of a rule check to a SQLite database
+fn write_finding(
+    conn: &Connection,
+    domain: &str,
+    rule_name: &str,
+    matched_path: &str,
+    detected: bool,
+    details: &FindingDetails,
+) -> Result<()> {
+    db::upsert_finding(conn, domain, rule_name, matched_path, detected, details)
+}
+
+/// Write the outcomes of many rule checks to a SQLite database in one transaction
+fn write_batch<'a>(
+    conn: &mut Connection,
+    batch: impl Iterator<Item = &'a PendingFinding>,
+) -> Result<()> {
+    let tx = conn
+        .transaction()
+        .context("Failed to start a transaction")?;
+    for finding in batch {
+        write_finding(
+            &tx,
+            &finding.domain,
+            &finding.rule_name,
+            &finding.matched_path,
+            finding.detected,
+            &finding.details(),
+        )?;
+    }
+    tx.commit().context("Failed to commit findings")?;
+
+    Ok(())
+}
+
+impl ResultStore for ShardedDb {
+    fn start_scan(&mut self, scan_id: &str, started_at: DateTime<Utc>) -> Result<()> {
+        db::start_scan_session(&self.primary, scan_id, started_at)
+    }
+
+    fn record_ruleset(&mut self, scan_id: &str, rules: &RuleSnapshot) -> Result<()> {
+        db::record_ruleset(&self.primary, scan_id, rules)
+    }
+
+    fn record_profile(&mut self, scan_id: &str, profile: ScanProfile) -> Result<()> {
+        db::record_scan_profile(&self.primary, scan_id, profile)
+    }
+
+    fn record_project(&mut self, scan_id: &str, project: &str) -> Result<()> {
+        db::record_scan_project(&self.primary, scan_id, project)
+    }
+
+    fn record_finding(
+        &mut self,
+        domain: &str,
+        rule_name: &str,
+        matched_path: &str,
+        detected: bool,
+        details: &FindingDetails,
+    ) -> Result<()> {
+        self.shard(domain)
+            .record_finding(domain, rule_name, matched_path, detected, details)
+    }
+
+    fn record_findings(&mut self, batch: &[PendingFinding]) -> Result<()> {
+        let shards = self.shards.len();
+        for (index, shard) in self.shards.iter_mut().enumerate() {
+            let mut findings = batch
+                .iter()
+                .filter(|finding| db::shard_index(&finding.domain, shards) == index)
+                .peekable();
+            if findings.peek().is_some() {
+                write_batch(shard, findings)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn record_domain(&mut self, scan: &DomainScan) -> Result<()> {
+        db::record_domain_scan(&self.primary, scan)
+    }
+
+    fn detected_since(&self, since: DateTime<Utc>) -> Result<Vec<Finding>> {
+        self.get_detected_since(since)
+    }
+
+    fn revalidatable(&self) -> Result<Vec<Finding>> {
+        self.get_revalidatable()
+    }
+
+    fn finish_scan(&mut self, scan_id: &str, domains: usize, risk: &RiskReport) -> Result<()> {
+        db::finish_scan_session(&self.primary, scan_id, domains, risk)
+    }
+
+    fn mark_incomplete(&mut self, scan_id: &str) -> Result<()> {
+        db::mark_scan_incomplete(&self.primary, scan_id)
+    }
+
+    fn record_scan_findings(&mut self, scan_id: &str, findings: &[Finding]) -> Result<()> {
+        db::record_scan_findings(&self.primary, scan_id, findings)
+    }
+
+    fn scan_findings(&self, scan_id: &str) -> Result<Vec<Finding>> {
+        db::get_scan_findings(&self.primary, scan_id)
+    }
+
+    fn record_rule_counts(
+        &mut self,
+        scan_id: &str,
+        counts: &BTreeMap<String, usize>,
+    ) -> Result<()> {
+        db::record_rule_counts(&self.primary, scan_id, counts)
+    }
+
+    fn rule_baseline(&self, scan_id: &str, scans: usize) -> Result<(usize, HashMap<String, f64>)> {
+        db::get_rule_baseline(&self.primary, scan_id, scans)
+    }
+
+    fn findings(&self) -> Result<Vec<Finding>> {
+        self.get_all_findings()
+    }
+
+    fn domain_scans(&self, domain: &str) -> Result<Vec<DomainScan>> {
+        db::get_domain_scans(&self.primary, domain)
+    }
+
+    fn purge_findings(&mut self, filter: &PurgeFilter) -> Result<usize> {
+        ShardedDb::purge_findings(self, filter)
+    }
+
+    /// Marks are kept in the shard of their finding
+    fn false_positives(&self) -> Result<Vec<FalsePositive>> {
+        let mut false_positives = Vec::new();
+        for shard in &self.shards {
+            false_positives.extend(db::get_false_positives(shard)?);
+        }
+        false_positives.sort_by(|a, b| (&a.domain, &a.rule_name).cmp(&(&b.domain, &b.rule_name)));
+
+        Ok(false_positives)
+    }
+
+    /// Notes are kept in the shard of their finding
+    fn notes(&self) -> Result<Vec<FindingNote>> {
+        let mut notes = Vec::new();
+        for shard in &self.shards {
+            notes.extend(db::get_all_notes(shard)?);
+        }
+        notes.sort_by(|a, b| {
+            (&a.domain, &a.rule_name, a.note.created_at).cmp(&(
+                &b.domain,
+                &b.rule_name,
+                b.note.created_at,
+            ))
+        });
+
+        Ok(notes)
+    }
+
+    fn certificates(&self) -> Result<Vec<SiteCertificate>> {
+        db::get_certificates(&self.primary)
+    }
+}
+
+/// One line of a scan's JSONL file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record {
+    ScanStarted {
+        scan_id: String,
+        started_at: DateTime<Utc>,
+    },
+    Finding {
+        domain: String,
+        rule_name: String,
+        matched_path: String,
+        detected: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        user_agent: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        redirect_chain: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ruleset_version: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ruleset_digest: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        evidence: Option<Evidence>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        project: Option<String>,
+        scanned_at: DateTime<Utc>,
+    },
+    Ruleset {
+        version: u32,
+        loaded_at: DateTime<Utc>,
+        rules: usize,
+        digest: String,
+    },
+    Profile {
+        profile: ScanProfile,
+    },
+    Project {
+        project: String,
+    },
+    RuleCounts {
+        counts: BTreeMap<String, usize>,
+    },
+    ScanFinished {
+        finished_at: DateTime<Utc>,
+        domains: usize,
+        risk: RiskReport,
+    },
+    /// The scan was stopped before every domain was scanned
+    ScanIncomplete,
+    Domain(DomainScan),
+}
+
+/// A scan read back from its JSONL file
+#[derive(Debug, Default)]
+struct ScanLog {
+    scan_id: String,
+    started_at: Option<DateTime<Utc>>,
+    findings: Vec<Record>,
+    domains: Vec<DomainScan>,
+    rule_counts: Option<BTreeMap<String, usize>>,
+    finished: Option<(DateTime<Utc>, usize, RiskReport)>,
+    incomplete: bool,
+    profile: Option<ScanProfile>,
+    project: Option<String>,
+    /// Digest of the rules the scan started with
+    ruleset_digest: Option<String>,
+}
+
+/// Result store writing one append-only JSONL file per scan into a directory.
+///
+/// Needs no file locking, so it works where SQLite's doesn't (read-only root
+/// filesystems with a writable results mount, NFS). Later records for the same
+/// domain and rule replace earlier ones when the files are read back.
+pub struct JsonlStore {
+    dir: PathBuf,
+
+    /// File of the scan in progress
+    file: Option<File>,
+}
+
+impl JsonlStore {
+    /// Open a results directory, creating it if needed
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).context(format!(
+            "Failed to create results directory: {}",
+            dir.display()
+        ))?;
+
+        debug!("JSONL result store opened: {}", dir.display());
+
+        Ok(Self { dir, file: None })
+    }
+
+    /// Path of a scan's file
+    pub fn scan_file(&self, scan_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", scan_id))
+    }
+
+    fn append(&mut self, record: &Record) -> Result<()> {
+        let file = self
+            .file
+            .as_mut()
+            .context("No scan session started in JSONL result store")?;
+
+        let mut line = serde_json::to_string(record).context("Failed to serialize record")?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+            .context("Failed to append to JSONL result file")?;
+
+        Ok(())
+    }
+
+    /// Read every scan in the directory, oldest first
+    fn read_scans(&self) -> Result<Vec<ScanLog>> {
+        let mut scans = Vec::new();
+
+        for entry in fs::read_dir(&self.dir).context(format!(
+            "Failed to read results directory: {}",
+            self.dir.display()
+        ))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "jsonl") {
+                scans.push(read_scan(&path)?);
+            }
+        }
+
+        scans.sort_by(|a, b| {
+            a.started_at
+                .cmp(&b.started_at)
+                .then_with(|| a.scan_id.cmp(&b.scan_id))
+        });
+
+        Ok(scans)
+    }
+
+    /// The latest finding for each domain and rule across all scans
+    fn latest_findings(&self) -> Result<Vec<Finding>> {
+        Ok(latest_of(self.read_scans()?))
+    }
+
+    /// Every scan session, newest first
+    pub fn scan_sessions(&self) -> Result<Vec<ScanSession>> {
+        let mut sessions: Vec<ScanSession> = self
+            .read_scans()?
+            .into_iter()
+            .filter_map(|scan| {
+                let started_at = scan.started_at?;
+                let (finished_at, domains, risk) = match scan.finished {
+                    Some((finished_at, domains, risk)) => (Some(finished_at), domains, risk),
+                    None => (None, 0, RiskReport::default()),
+                };
+                Some(ScanSession {
+                    id: scan.scan_id,
+                    started_at,
+                    finished_at,
+                    domains,
+                    findings: risk.per_domain.values().map(|d| d.findings).sum(),
+                    risk_score: risk.total,
+                    incomplete: scan.incomplete,
+                    profile: scan.profile,
+                    project: scan.project,
+                    ruleset_digest: scan.ruleset_digest,
+                })
+            })
+            .collect();
+        sessions.reverse();
+
+        Ok(sessions)
+    }
+}
+
+/// The latest finding for each domain and rule across `scans`, oldest first
+fn latest_of(scans: Vec<ScanLog>) -> Vec<Finding> {
+    let mut latest: BTreeMap<(String, String), Finding> = BTreeMap::new();
+
+    for scan in scans {
+        for record in scan.findings {
+            if let Record::Finding {
+                domain,
+                rule_name,
+                matched_path,
+                detected,
+                redirect_chain,
+                tag,
+                status,
+                ruleset_version,
+                ruleset_digest,
+                evidence,
+                project,
+                scanned_at,
+                ..
+            } = record
+            {
+                latest.insert(
+                    (domain.clone(), rule_name.clone()),
+                    Finding {
+                        id: 0,
+                        domain,
+                        rule_name,
+                        matched_path,
+                        detected,
+                        scanned_at,
+                        redirect_chain,
+                        tag,
+                        status,
+                        ruleset_version,
+                        ruleset_digest,
+                        evidence,
+                        project,
+                        remediation: None,
+                        notes: Vec::new(),
+                    },
+                );
+            }
+        }
+    }
+
+    latest
+        .into_values()
+        .enumerate()
+        .map(|(i, finding)| Finding {
+            id: i as i64 + 1,
+            ..finding
+        })
+        .collect()
+}
+
+/// Parse a scan file, skipping lines that can't be read (such as one cut short by a crash)
+fn read_scan(path: &Path) -> Result<ScanLog> {
+    let file = File::open(path).context(format!("Failed to open {}", path.display()))?;
+    let mut scan = ScanLog {
+        scan_id: path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.context(format!("Failed to read {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<Record>(&line) {
+            Ok(Record::ScanStarted {
+                scan_id,
+                started_at,
+            }) => {
+                scan.scan_id = scan_id;
+                scan.started_at = Some(started_at);
+            }
+            Ok(record @ Record::Finding { .. }) => scan.findings.push(record),
+            Ok(Record::RuleCounts { counts }) => scan.rule_counts = Some(counts),
+            Ok(Record::ScanFinished {
+                finished_at,
+                domains,
+                risk,
+            }) => scan.finished = Some((finished_at, domains, risk)),
+            Ok(Record::ScanIncomplete) => scan.incomplete = true,
+            Ok(Record::Profile { profile }) => scan.profile = Some(profile),
+            Ok(Record::Project { project }) => scan.project = Some(project),
+            Ok(Record::Domain(domain)) => scan.domains.push(domain),
+            Ok(Record::Ruleset {
+                version: 1, digest, ..
+            }) => scan.ruleset_digest = Some(digest),
+            Ok(Record::Ruleset { .. }) => {}
+            Err(e) => warn!(
+                "⚠️ Skipping unreadable line {} in {}: {}",
+                number + 1,
+                path.display(),
+                e
+            ),
+        }
+    }
+
+    Ok(scan)
+}
+
+/// What importing a scan file wrote to a database
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub scan_id: String,
+    /// Whether the scan session was new to the database, rather than imported before
+    pub new_session: bool,
+    /// Rule check outcomes in the file
+    pub findings: usize,
+    /// Outcomes written: those newer than the database's for the same domain and rule
+    pub written: usize,
+}
+
+/// Import scan files written by the JSONL store (`--store jsonl`), or directories of
+/// them, into a SQLite database: the ad-hoc or air-gapped workers' way of feeding
+/// central results.
+///
+/// Each file is checked in full before any of it is written, and is imported in one
+/// transaction under its own scan session. Findings are upserted as by `results merge`,
+/// the latest check of a domain and rule winning, so importing a file twice is harmless.
+pub fn import_results(database: &str, paths: &[String], format: &str) -> Result<Vec<ImportReport>> {
+    if !format.eq_ignore_ascii_case("jsonl") {
+        anyhow::bail!("Unsupported import format: {} (expected jsonl)", format);
+    }
+    if is_postgres_url(database) {
+        anyhow::bail!("Results can only be imported into a SQLite database");
+    }
+
+    let mut files = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            let mut scans: Vec<PathBuf> = fs::read_dir(path)
+                .context(format!("Failed to read {}", path.display()))?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<_>>()?;
+            scans.retain(|scan| scan.extension().is_some_and(|ext| ext == "jsonl"));
+            scans.sort();
+            files.extend(scans);
+        } else if path.exists() {
+            files.push(path.to_path_buf());
+        } else {
+            anyhow::bail!("File not found: {}", path.display());
+        }
+    }
+
+    let mut conn = db::init_db(database)?;
+    let mut reports = Vec::with_capacity(files.len());
+    for file in files {
+        let report = import_scan_file(&mut conn, &file)
+            .context(format!("Failed to import {}", file.display()))?;
+        info!(
+            "📥 Imported {} of {} findings from {} (scan {}{})",
+            report.written,
+            report.findings,
+            file.display(),
+            report.scan_id,
+            if report.new_session {
+                ""
+            } else {
+                ", already imported"
+            }
+        );
+        reports.push(report);
+    }
+
+    Ok(reports)
+}
+
+/// Import one scan file into a SQLite database
+pub fn import_scan_file(conn: &mut Connection, path: &Path) -> Result<ImportReport> {
+    let records = read_records(path)?;
+    let Some(Record::ScanStarted {
+        scan_id,
+        started_at,
+    }) = records.first()
+    else {
+        anyhow::bail!(
+            "{} doesn't start with a scan_started record; is it a scan file written by --store jsonl?",
+            path.display()
+        );
+    };
+    if scan_id.trim().is_empty() {
+        anyhow::bail!("{} has an empty scan ID", path.display());
+    }
+
+    let tx = conn
+        .transaction()
+        .context("Failed to start a transaction")?;
+    let mut report = ImportReport {
+        scan_id: scan_id.clone(),
+        new_session: !db::scan_session_exists(&tx, scan_id)?,
+        ..Default::default()
+    };
+    if report.new_session {
+        db::start_scan_session(&tx, scan_id, *started_at)?;
+    }
+
+    let mut detected: BTreeMap<(String, String), Finding> = BTreeMap::new();
+    for record in &records[1..] {
+        match record {
+            Record::ScanStarted { .. } => {
+                anyhow::bail!("{} holds more than one scan", path.display())
+            }
+            Record::Finding {
+                domain,
+                rule_name,
+                matched_path,
+                detected: is_detected,
+                user_agent,
+                redirect_chain,
+                tag,
+                status,
+                ruleset_version,
+                ruleset_digest,
+                evidence,
+                project,
+                scanned_at,
+            } => {
+                let finding = Finding {
+                    id: 0,
+                    domain: domain.clone(),
+                    rule_name: rule_name.clone(),
+                    matched_path: matched_path.clone(),
+                    detected: *is_detected,
+                    scanned_at: *scanned_at,
+                    redirect_chain: redirect_chain.clone(),
+                    tag: tag.clone(),
+                    status: *status,
+                    ruleset_version: *ruleset_version,
+                    ruleset_digest: ruleset_digest.clone(),
+                    evidence: evidence.clone(),
+                    project: project.clone(),
+                    remediation: None,
+                    notes: Vec::new(),
+                };
+                report.findings += 1;
+                if db::import_finding(&tx, &finding, user_agent.as_deref())? {
+                    report.written += 1;
+                }
+
+                let key = (finding.domain.clone(), finding.rule_name.clone());
+                if finding.detected {
+                    detected.insert(key, finding);
+                } else {
+                    detected.remove(&key);
+                }
+            }
+            Record::Ruleset {
+                version,
+                loaded_at,
+                rules,
+                digest,
+            } => db::record_loaded_ruleset(&tx, scan_id, *version, *loaded_at, *rules, digest)?,
+            Record::Profile { profile } => db::record_scan_profile(&tx, scan_id, *profile)?,
+            Record::Project { project } => db::record_scan_project(&tx, scan_id, project)?,
+            Record::RuleCounts { counts } => db::record_rule_counts(&tx, scan_id, counts)?,
+            Record::ScanFinished {
+                finished_at,
+                domains,
+                risk,
+            } => {
+                db::finish_scan_session(&tx, scan_id, *domains, risk)?;
+                db::record_scan_finished_at(&tx, scan_id, *finished_at)?;
+            }
+            Record::ScanIncomplete => db::mark_scan_incomplete(&tx, scan_id)?,
+            Record::Domain(scan) => db::record_domain_scan(
+                &tx,
+                &DomainScan {
+                    scan_id: scan_id.clone(),
+                    ..scan.clone()
+                },
+            )?,
+        }
+    }
+    db::write_scan_findings(&tx, scan_id, &detected.into_values().collect::<Vec<_>>())?;
+    tx.commit().context("Failed to commit the import")?;
+
+    Ok(report)
+}
+
+/// Read every record of a scan file, refusing lines that can't be read or findings
+/// without a domain or rule, unlike a store reading back its own files
+fn read_records(path: &Path) -> Result<Vec<Record>> {
+    let file = File::open(path).context(format!("Failed to open {}", path.display()))?;
+    let mut records = Vec::new();
+
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.context(format!("Failed to read {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record: Record = serde_json::from_str(&line)
+            .context(format!("Invalid record on line {}", number + 1))?;
+        if let Record::Finding {
+            domain, rule_name, ..
+        } = &record
+        {
+            if domain.trim().is_empty() || domain.contains(char::is_whitespace) {
+                anyhow::bail!("Invalid domain on line {}: {:?}", number + 1, domain);
+            }
+            if rule_name.trim().is_empty() {
+                anyhow::bail!("Finding without a rule on line {}", number + 1);
+            }
+        }
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+impl ResultStore for JsonlStore {
+    fn start_scan(&mut self, scan_id: &str, started_at: DateTime<Utc>) -> Result<()> {
+        let path = self.scan_file(scan_id);
+        let file = OpenOptions::new()
+            .append(true)
+            .create_new(true)
+            .open(&path)
+            .context(format!("Failed to create {}", path.display()))?;
+
+        self.file = Some(file);
+        self.append(&Record::ScanStarted {
+            scan_id: scan_id.to_string(),
+            started_at,
+        })
+    }
+
+    fn record_ruleset(&mut self, _scan_id: &str, rules: &RuleSnapshot) -> Result<()> {
+        self.append(&Record::Ruleset {
+            version: rules.version,
+            loaded_at: rules.loaded_at,
+            rules: rules.ruleset.rules.len(),
+            digest: rules.digest.clone(),
+        })
+    }
+
+    fn record_profile(&mut self, _scan_id: &str, profile: ScanProfile) -> Result<()> {
+        self.append(&Record::Profile { profile })
+    }
+
+    fn record_project(&mut self, _scan_id: &str, project: &str) -> Result<()> {
+        self.append(&Record::Project {
+            project: project.to_string(),
+        })
+    }
+
+    fn record_finding(
+        &mut self,
+        domain: &str,
+        rule_name: &str,
+        matched_path: &str,
+        detected: bool,
+        details: &FindingDetails,
+    ) -> Result<()> {
+        self.append(&Record::Finding {
+            domain: domain.to_string(),
+            rule_name: rule_name.to_string(),
+            matched_path: matched_path.to_string(),
+            detected,
+            user_agent: details.user_agent.map(str::to_string),
+            redirect_chain: details.redirect_chain.to_vec(),
+            tag: details.tag.map(str::to_string),
+            status: details.status,
+            ruleset_version: details.ruleset_version,
+            ruleset_digest: details.ruleset_digest.map(str::to_string),
+            evidence: details.evidence.cloned(),
+            project: details.project.map(str::to_string),
+            scanned_at: Utc::now(),
+        })
+    }
+
+    fn record_domain(&mut self, scan: &DomainScan) -> Result<()> {
+        self.append(&Record::Domain(scan.clone()))
+    }
+
+    fn detected_since(&self, since: DateTime<Utc>) -> Result<Vec<Finding>> {
+        Ok(self
+            .latest_findings()?
+            .into_iter()
+            .filter(|finding| finding.detected && finding.scanned_at >= since)
+            .collect())
+    }
+
+    fn revalidatable(&self) -> Result<Vec<Finding>> {
+        Ok(self
+            .latest_findings()?
+            .into_iter()
+            .filter(|finding| {
+                finding.detected
+                    && finding
+                        .evidence
+                        .as_ref()
+                        .is_some_and(|evidence| evidence.validators.is_some())
+            })
+            .collect())
+    }
+
+    fn finish_scan(&mut self, _scan_id: &str, domains: usize, risk: &RiskReport) -> Result<()> {
+        self.append(&Record::ScanFinished {
+            finished_at: Utc::now(),
+            domains,
+            risk: risk.clone(),
+        })
+    }
+
+    fn mark_incomplete(&mut self, _scan_id: &str) -> Result<()> {
+        self.append(&Record::ScanIncomplete)
+    }
+
+    fn record_scan_findings(&mut self, _scan_id: &str, _findings: &[Finding]) -> Result<()> {
+        // The scan's file already holds them
+        Ok(())
+    }
+
+    fn scan_findings(&self, scan_id: &str) -> Result<Vec<Finding>> {
+        let path = self.scan_file(scan_id);
+        if !path.exists() {
+            anyhow::bail!("Unknown scan: {}", scan_id);
+        }
+
+        Ok(latest_of(vec![read_scan(&path)?])
+            .into_iter()
+            .filter(|finding| finding.detected)
+            .collect())
+    }
+
+    fn record_rule_counts(
+        &mut self,
+        _scan_id: &str,
+        counts: &BTreeMap<String, usize>,
+    ) -> Result<()> {
+        self.append(&Record::RuleCounts {
+            counts: counts.clone(),
+        })
+    }
+
+    fn rule_baseline(&self, scan_id: &str, scans: usize) -> Result<(usize, HashMap<String, f64>)> {
+        let recent: Vec<BTreeMap<String, usize>> = self
+            .read_scans()?
+            .into_iter()
+            .rev()
+            .filter(|scan| scan.scan_id != scan_id)
+            .filter_map(|scan| scan.rule_counts)
+            .take(scans)
+            .collect();
+
+        let mut totals: HashMap<String, f64> = HashMap::new();
+        for counts in &recent {
+            for (rule_name, detected) in counts {
+                *totals.entry(rule_name.clone()).or_insert(0.0) += *detected as f64;
+            }
+        }
+
+        let baseline = totals
+            .into_iter()
+            .map(|(rule_name, total)| (rule_name, total / recent.len() as f64))
+            .collect();
+
+        Ok((recent.len(), baseline))
+    }
+
+    fn findings(&self) -> Result<Vec<Finding>> {
+        self.latest_findings()
+    }
+
+    fn domain_scans(&self, domain: &str) -> Result<Vec<DomainScan>> {
+        let mut scans: Vec<DomainScan> = self
+            .read_scans()?
+            .into_iter()
+            .flat_map(|scan| scan.domains)
+            .filter(|scan| scan.domain == domain)
+            .collect();
+        scans.reverse();
+
+        Ok(scans)
+    }
+
+    /// Purging goes by each domain and rule's latest finding, and removes every record
+    /// of it from every scan file, so an older record can't take its place
+    fn purge_findings(&mut self, filter: &PurgeFilter) -> Result<usize> {
+        let purged: HashSet<(String, String)> = self
+            .latest_findings()?
+            .into_iter()
+            .filter(|finding| filter.matches(finding))
+            .map(|finding| (finding.domain, finding.rule_name))
+            .collect();
+        if purged.is_empty() {
+            return Ok(0);
+        }
+
+        for entry in fs::read_dir(&self.dir).context(format!(
+            "Failed to read results directory: {}",
+            self.dir.display()
+        ))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "jsonl") {
+                rewrite_without(&path, &purged)?;
+            }
+        }
+
+        Ok(purged.len())
+    }
+
+    /// Findings in JSONL files can't be marked as false positives
+    fn false_positives(&self) -> Result<Vec<FalsePositive>> {
+        Ok(Vec::new())
+    }
+
+    /// Nor can they be annotated
+    fn notes(&self) -> Result<Vec<FindingNote>> {
+        Ok(Vec::new())
+    }
+
+    /// Certificates are kept with the domain records of the scan files; the one seen
+    /// last wins
+    fn certificates(&self) -> Result<Vec<SiteCertificate>> {
+        let mut certificates: BTreeMap<String, SiteCertificate> = BTreeMap::new();
+        for certificate in self
+            .read_scans()?
+            .into_iter()
+            .flat_map(|scan| scan.domains)
+            .filter_map(|scan| scan.certificate)
+        {
+            match certificates.get(&certificate.host) {
+                Some(seen) if seen.seen_at > certificate.seen_at => {}
+                _ => {
+                    certificates.insert(certificate.host.clone(), certificate);
+                }
+            }
+        }
+
+        Ok(certificates.into_values().collect())
+    }
+}
+
+/// Rewrite a scan file without the findings of the given domains and rules. The file
+/// is replaced in one rename, so a crash leaves either the old or the new contents.
+fn rewrite_without(path: &Path, purged: &HashSet<(String, String)>) -> Result<()> {
+    let contents =
+        fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+
+    let mut kept = String::with_capacity(contents.len());
+    let mut removed = false;
+    for line in contents.lines() {
+        if let Ok(Record::Finding {
+            domain, rule_name, ..
+        }) = serde_json::from_str::<Record>(line)
+        {
+            if purged.contains(&(domain, rule_name)) {
+                removed = true;
+                continue;
+            }
+        }
+        kept.push_str(line);
+        kept.push('\n');
+    }
+    if !removed {
+        return Ok(());
+    }
+
+    let staging = path.with_extension("jsonl.tmp");
+    fs::write(&staging, kept).context(format!("Failed to write {}", staging.display()))?;
+    fs::rename(&staging, path).context(format!("Failed to replace {}", path.display()))?;
+
+    Ok(())
+}
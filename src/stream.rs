@@ -0,0 +1,334 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{debug, info, warn};
+
+use crate::rules::Severity;
+
+/// Path the event stream is served on
+pub const STREAM_PATH: &str = "/events";
+
+/// Environment variable holding the event stream's token, where no flag gives it
+pub const TOKEN_VAR: &str = "FATT_STREAM_TOKEN";
+
+/// Events buffered per subscriber before a slow one starts missing them
+const BUFFER: usize = 1024;
+
+/// Largest request head read from a subscriber
+const MAX_REQUEST: usize = 8192;
+
+/// Interval between keep-alive comments, so idle connections stay open and dead ones
+/// are noticed
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// How long the end of a scan waits for subscribers to receive the final event
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// An event published to stream subscribers while a scan runs, or by a master about
+/// its workers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// The scan has started
+    Started {
+        scan_id: String,
+        domains: usize,
+        checks: usize,
+    },
+
+    /// A rule matched on a domain
+    Finding {
+        domain: String,
+        rule: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        severity: Option<Severity>,
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+        status: u16,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        redirect_chain: Vec<String>,
+        /// What to do about the finding, from the rule
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        remediation: Option<String>,
+        found_at: DateTime<Utc>,
+    },
+
+    /// Periodic progress of the scan
+    Progress {
+        domains_processed: usize,
+        domains_total: usize,
+        checks_completed: usize,
+        checks_total: usize,
+        findings: usize,
+        /// Batches of a master's scan reported by its workers
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        batches: Option<BatchProgress>,
+    },
+
+    /// The scan has finished; subscribers are disconnected after it
+    Finished {
+        scan_id: String,
+        domains: usize,
+        findings: usize,
+        elapsed_secs: f64,
+    },
+
+    /// Periodic list of the workers registered with a master
+    Workers { workers: Vec<WorkerSummary> },
+}
+
+/// How many of a master's batches are done
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BatchProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+/// A worker registered with a master, as streamed to subscribers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkerSummary {
+    pub id: String,
+    pub max_concurrency: usize,
+    pub active_scans: usize,
+    pub completed_scans: usize,
+    pub findings: usize,
+}
+
+impl StreamEvent {
+    /// SSE event name
+    fn name(&self) -> &'static str {
+        match self {
+            StreamEvent::Started { .. } => "started",
+            StreamEvent::Finding { .. } => "finding",
+            StreamEvent::Progress { .. } => "progress",
+            StreamEvent::Finished { .. } => "finished",
+            StreamEvent::Workers { .. } => "workers",
+        }
+    }
+
+    /// The event as an SSE message
+    fn to_sse(&self) -> Result<String> {
+        let data = serde_json::to_string(self).context("Failed to serialize stream event")?;
+        Ok(format!("event: {}\ndata: {}\n\n", self.name(), data))
+    }
+}
+
+/// A Server-Sent Events endpoint streaming a scan's findings and progress live.
+///
+/// Subscribers `GET /events` and receive every event published from then on; one that
+/// falls too far behind skips the events it missed rather than slowing the scan. With
+/// a token, they must send it as `Authorization: Bearer TOKEN`.
+pub struct EventStream {
+    tx: broadcast::Sender<StreamEvent>,
+    addr: SocketAddr,
+    acceptor: JoinHandle<()>,
+    connections: Arc<Mutex<JoinSet<()>>>,
+}
+
+impl EventStream {
+    /// Start serving on `addr`; `:PORT` listens on localhost only. Findings are
+    /// sensitive, so listening where other hosts can connect requires a token.
+    pub async fn listen(addr: &str, token: Option<&str>) -> Result<Self> {
+        let bind_addr = match addr.strip_prefix(':') {
+            Some(port) => format!("127.0.0.1:{}", port),
+            None => addr.to_string(),
+        };
+        let listener = TcpListener::bind(&bind_addr)
+            .await
+            .context(format!("Failed to bind event stream to {}", addr))?;
+        let addr = listener.local_addr()?;
+        if !addr.ip().is_loopback() && token.is_none() {
+            anyhow::bail!(
+                "Event stream on {} is reachable from other hosts; set a token for it (--stream-token)",
+                addr
+            );
+        }
+        let token: Option<Arc<str>> = token.map(Arc::from);
+
+        let (tx, _) = broadcast::channel(BUFFER);
+        let connections = Arc::new(Mutex::new(JoinSet::new()));
+
+        let acceptor = {
+            let tx = tx.clone();
+            let connections = connections.clone();
+            tokio::spawn(async move {
+                loop {
+                    let (socket, peer) = match listener.accept().await {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            warn!("⚠️ Failed to accept event stream connection: {}", e);
+                            continue;
+                        }
+                    };
+
+                    let rx = tx.subscribe();
+                    let token = token.clone();
+                    connections.lock().unwrap().spawn(async move {
+                        if let Err(e) = serve(socket, rx, token.as_deref()).await {
+                            debug!("Event stream subscriber {} disconnected: {:#}", peer, e);
+                        }
+                    });
+                }
+            })
+        };
+
+        info!("📡 Streaming scan events on http://{}{}", addr, STREAM_PATH);
+
+        Ok(Self {
+            tx,
+            addr,
+            acceptor,
+            connections,
+        })
+    }
+
+    /// Address the stream is listening on
+    #[allow(dead_code)]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Send an event to every current subscriber
+    pub fn publish(&self, event: StreamEvent) {
+        // Without subscribers the event is simply dropped
+        let _ = self.tx.send(event);
+    }
+
+    /// Publish the final event, give subscribers a moment to receive it and stop serving
+    pub async fn finish(&self, event: StreamEvent) {
+        self.publish(event);
+        self.acceptor.abort();
+
+        let mut connections = std::mem::take(&mut *self.connections.lock().unwrap());
+        let drained = tokio::time::timeout(DRAIN_TIMEOUT, async {
+            while connections.join_next().await.is_some() {}
+        })
+        .await;
+        if drained.is_err() {
+            debug!("Event stream subscribers still connected after the scan, closing");
+        }
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.acceptor.abort();
+    }
+}
+
+/// Answer one subscriber: check the request, then forward events until it goes away
+/// or the scan finishes
+async fn serve(
+    mut socket: TcpStream,
+    mut rx: broadcast::Receiver<StreamEvent>,
+    token: Option<&str>,
+) -> Result<()> {
+    let head = read_request_head(&mut socket).await?;
+    let mut lines = head.lines();
+    let mut parts = lines.next().unwrap_or("").split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = target.split('?').next().unwrap_or("");
+
+    if path != STREAM_PATH {
+        socket
+            .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .await?;
+        return Ok(());
+    }
+    if method != "GET" {
+        socket
+            .write_all(
+                b"HTTP/1.1 405 Method Not Allowed\r\nAllow: GET\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            )
+            .await?;
+        return Ok(());
+    }
+    if let Some(token) = token {
+        let authorized = lines
+            .filter_map(|line| line.split_once(':'))
+            .filter(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+            .any(|(_, value)| bearer_token(value) == Some(token));
+        if !authorized {
+            socket
+                .write_all(
+                    b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Bearer\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await?;
+            return Ok(());
+        }
+    }
+
+    socket
+        .write_all(
+            b"HTTP/1.1 200 OK\r\n\
+              Content-Type: text/event-stream\r\n\
+              Cache-Control: no-cache\r\n\
+              Connection: keep-alive\r\n\r\n",
+        )
+        .await?;
+    socket.flush().await?;
+
+    let mut keep_alive = tokio::time::interval(KEEP_ALIVE);
+    keep_alive.tick().await;
+
+    loop {
+        tokio::select! {
+            event = rx.recv() => match event {
+                Ok(event) => {
+                    socket.write_all(event.to_sse()?.as_bytes()).await?;
+                    socket.flush().await?;
+                    if matches!(event, StreamEvent::Finished { .. }) {
+                        return Ok(());
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    debug!("Event stream subscriber fell behind, skipped {} events", missed);
+                    socket
+                        .write_all(format!(": skipped {} events\n\n", missed).as_bytes())
+                        .await?;
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            _ = keep_alive.tick() => {
+                socket.write_all(b": keep-alive\n\n").await?;
+                socket.flush().await?;
+            }
+        }
+    }
+}
+
+/// The token of an `Authorization: Bearer TOKEN` header value
+fn bearer_token(value: &str) -> Option<&str> {
+    let (scheme, token) = value.trim().split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then_some(token.trim())
+}
+
+/// Read a request up to the blank line ending its headers; returns the request line
+/// and headers
+async fn read_request_head(socket: &mut TcpStream) -> Result<String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+
+    while !buffer.windows(4).any(|window| window == b"\r\n\r\n") {
+        if buffer.len() > MAX_REQUEST {
+            anyhow::bail!("Request head too large");
+        }
+        let read = socket.read(&mut chunk).await?;
+        if read == 0 {
+            anyhow::bail!("Connection closed before the request was complete");
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    Ok(String::from_utf8_lossy(&buffer).to_string())
+}
@@ -0,0 +1,512 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use tracing::info;
+
+use crate::db::{self, Finding, ScanSession};
+use crate::risk::risk_change_percent;
+use crate::rules::{self, RuleSet};
+use crate::store::{self, StoreKind};
+use crate::utils;
+
+/// Severity of findings whose rule isn't in the rules file
+pub(crate) const UNKNOWN_SEVERITY: &str = "unknown";
+
+/// Aggregate figures for the results in a store
+#[derive(Debug, Default, Serialize)]
+pub struct ResultsSummary {
+    /// Domain and rule pairs checked
+    pub checks: usize,
+    /// Checks where the rule matched
+    pub detected: usize,
+    /// Share of checks where the rule matched
+    pub hit_rate: f64,
+    /// Distinct domains checked
+    pub domains: usize,
+    /// Distinct domains with at least one detected finding
+    pub affected_domains: usize,
+    /// Detected findings per rule severity; empty without the rules
+    pub by_severity: BTreeMap<String, usize>,
+    /// Detected findings per rule
+    pub by_rule: BTreeMap<String, usize>,
+    /// Detected findings per top-level domain
+    pub by_tld: BTreeMap<String, usize>,
+    pub scans: ScanTiming,
+    /// Risk score of each finished scan session, newest first
+    pub risk: Vec<ScanRisk>,
+}
+
+/// How long the recorded scan sessions took
+#[derive(Debug, Default, Serialize)]
+pub struct ScanTiming {
+    pub sessions: usize,
+    /// Sessions that finished
+    pub finished: usize,
+    /// Sessions stopped before every domain was scanned
+    pub incomplete: usize,
+    pub first_started: Option<DateTime<Utc>>,
+    pub last_finished: Option<DateTime<Utc>>,
+    /// Duration of each finished session, added up
+    pub total_secs: f64,
+    pub average_secs: f64,
+    pub longest_secs: f64,
+    /// Domains scanned per second by the finished sessions
+    pub domains_per_sec: f64,
+}
+
+/// Risk score of one scan session and its change from the finished session before it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScanRisk {
+    pub scan_id: String,
+    pub started_at: DateTime<Utc>,
+    pub risk_score: f64,
+    /// Percentage change, if the session before it scored above zero
+    pub change_percent: Option<f64>,
+}
+
+/// Summarise findings and scan sessions; `ruleset` gives the rules' severities
+pub fn summarize(
+    findings: &[Finding],
+    sessions: &[ScanSession],
+    ruleset: Option<&RuleSet>,
+) -> ResultsSummary {
+    let severities = ruleset.map(severity_names);
+
+    let mut summary = ResultsSummary {
+        checks: findings.len(),
+        ..Default::default()
+    };
+    let mut domains = HashSet::new();
+    let mut affected = HashSet::new();
+
+    for finding in findings {
+        domains.insert(finding.domain.as_str());
+        if !finding.detected {
+            continue;
+        }
+
+        summary.detected += 1;
+        affected.insert(finding.domain.as_str());
+        *summary
+            .by_rule
+            .entry(finding.rule_name.clone())
+            .or_default() += 1;
+        let tld = rules::top_level_domain(&finding.domain).unwrap_or_else(|| "(ip)".to_string());
+        *summary.by_tld.entry(tld).or_default() += 1;
+        if let Some(severities) = &severities {
+            let severity = severities
+                .get(finding.rule_name.as_str())
+                .map(String::as_str)
+                .unwrap_or(UNKNOWN_SEVERITY);
+            *summary.by_severity.entry(severity.to_string()).or_default() += 1;
+        }
+    }
+
+    summary.domains = domains.len();
+    summary.affected_domains = affected.len();
+    if summary.checks > 0 {
+        summary.hit_rate = summary.detected as f64 / summary.checks as f64;
+    }
+    summary.scans = scan_timing(sessions);
+    summary.risk = scan_risk(sessions);
+
+    summary
+}
+
+fn scan_risk(sessions: &[ScanSession]) -> Vec<ScanRisk> {
+    // Unfinished sessions haven't been scored yet
+    let mut finished: Vec<&ScanSession> = sessions
+        .iter()
+        .filter(|session| session.finished_at.is_some())
+        .collect();
+    finished.sort_by_key(|session| std::cmp::Reverse(session.started_at));
+
+    finished
+        .iter()
+        .enumerate()
+        .map(|(i, session)| ScanRisk {
+            scan_id: session.id.clone(),
+            started_at: session.started_at,
+            risk_score: session.risk_score,
+            change_percent: finished
+                .get(i + 1)
+                .and_then(|previous| risk_change_percent(previous.risk_score, session.risk_score)),
+        })
+        .collect()
+}
+
+/// Severity of each rule by name; rules without one count as info
+pub(crate) fn severity_names(ruleset: &RuleSet) -> HashMap<&str, String> {
+    ruleset
+        .rules
+        .iter()
+        .map(|rule| {
+            (
+                rule.name.as_str(),
+                rule.severity
+                    .as_ref()
+                    .map(|severity| severity.to_string())
+                    .unwrap_or_else(|| "info".to_string()),
+            )
+        })
+        .collect()
+}
+
+fn scan_timing(sessions: &[ScanSession]) -> ScanTiming {
+    let mut timing = ScanTiming {
+        sessions: sessions.len(),
+        incomplete: sessions.iter().filter(|session| session.incomplete).count(),
+        first_started: sessions.iter().map(|session| session.started_at).min(),
+        last_finished: sessions
+            .iter()
+            .filter_map(|session| session.finished_at)
+            .max(),
+        ..Default::default()
+    };
+
+    let mut domains = 0;
+    for session in sessions {
+        let Some(finished_at) = session.finished_at else {
+            continue;
+        };
+        let secs = (finished_at - session.started_at).num_milliseconds().max(0) as f64 / 1000.0;
+        timing.finished += 1;
+        timing.total_secs += secs;
+        timing.longest_secs = timing.longest_secs.max(secs);
+        domains += session.domains;
+    }
+    if timing.finished > 0 {
+        timing.average_secs = timing.total_secs / timing.finished as f64;
+    }
+    if timing.total_secs > 0.0 {
+        timing.domains_per_sec = domains as f64 / timing.total_secs;
+    }
+
+    timing
+}
+
+/// Print aggregate figures for the results in one or more stores; several are
+/// summarised together, as if merged. A `project` narrows them to its scans and the
+/// findings last checked by them.
+pub fn show_stats(
+    kind: StoreKind,
+    databases: &[String],
+    shards: usize,
+    rules_file: Option<&str>,
+    top: usize,
+    project: Option<&str>,
+    format: &str,
+) -> Result<()> {
+    let databases = store::expand_databases(databases)?;
+    let mut sessions = Vec::new();
+    for database in &databases {
+        if kind.resolve(database) != StoreKind::Postgres && !Path::new(database).exists() {
+            anyhow::bail!("Results not found: {}", database);
+        }
+        sessions.extend(store::load_scan_sessions(kind, database)?);
+    }
+    let mut seen = HashSet::new();
+    sessions.retain(|session| seen.insert(session.id.clone()));
+    let sessions = db::of_project(sessions, project);
+
+    let mut findings = store::load_federated_findings(kind, &databases, shards)?;
+    if let Some(project) = project {
+        findings.retain(|finding| finding.project.as_deref() == Some(project));
+    }
+    let ruleset = rules_file
+        .map(|file| rules::load_rules(file).context(format!("Failed to load rules: {}", file)))
+        .transpose()?;
+    let summary = summarize(&findings, &sessions, ruleset.as_ref());
+
+    match format.to_lowercase().as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&summary)?),
+        "text" => {
+            let databases: Vec<String> = databases
+                .iter()
+                .map(|database| store::redact_url(database))
+                .collect();
+            print_summary(&databases.join(", "), &summary, top)
+        }
+        _ => anyhow::bail!("Unsupported stats format: {}", format),
+    }
+
+    Ok(())
+}
+
+fn print_summary(database: &str, summary: &ResultsSummary, top: usize) {
+    println!("📊 Results in {}", database);
+    println!(
+        "\nChecks: {}  Detected: {}  Hit rate: {:.2}%",
+        summary.checks,
+        summary.detected,
+        summary.hit_rate * 100.0
+    );
+    println!(
+        "Domains: {}  With findings: {}",
+        summary.domains, summary.affected_domains
+    );
+
+    if !summary.by_severity.is_empty() {
+        println!("\nBy severity:");
+        for severity in [
+            "critical",
+            "high",
+            "medium",
+            "low",
+            "info",
+            UNKNOWN_SEVERITY,
+        ] {
+            if let Some(count) = summary.by_severity.get(severity) {
+                println!("  {:<10} {}", severity, count);
+            }
+        }
+    }
+    print_top("By rule", &summary.by_rule, top);
+    print_top("By TLD", &summary.by_tld, top);
+
+    let scans = &summary.scans;
+    println!(
+        "\nScans: {} ({} finished, {} incomplete)",
+        scans.sessions, scans.finished, scans.incomplete
+    );
+    if let (Some(first), Some(last)) = (scans.first_started, scans.last_finished) {
+        println!(
+            "  From {} to {}",
+            first.format("%Y-%m-%d %H:%M:%S"),
+            last.format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+    if scans.finished > 0 {
+        println!(
+            "  Total {}, average {}, longest {}, {:.1} domains/s",
+            utils::format_duration(scans.total_secs),
+            utils::format_duration(scans.average_secs),
+            utils::format_duration(scans.longest_secs),
+            scans.domains_per_sec
+        );
+    }
+
+    if !summary.risk.is_empty() {
+        println!("\nRisk by scan:");
+        for scan in summary.risk.iter().take(top) {
+            println!(
+                "  {}  {:<38} {:>8.1}  {}",
+                scan.started_at.format("%Y-%m-%d %H:%M:%S"),
+                scan.scan_id,
+                scan.risk_score,
+                scan.change_percent
+                    .map(|percent| format!("{:+.1}%", percent))
+                    .unwrap_or_else(|| "-".to_string())
+            );
+        }
+        if summary.risk.len() > top {
+            println!("  … and {} more", summary.risk.len() - top);
+        }
+    }
+}
+
+/// Print the `top` largest counts, largest first
+fn print_top(title: &str, counts: &BTreeMap<String, usize>, top: usize) {
+    if counts.is_empty() {
+        return;
+    }
+
+    let mut sorted: Vec<(&String, &usize)> = counts.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    println!("\n{}:", title);
+    for (name, count) in sorted.iter().take(top) {
+        println!("  {:<30} {}", name, count);
+    }
+    if sorted.len() > top {
+        println!("  … and {} more", sorted.len() - top);
+    }
+}
+
+/// What a summary export aggregates findings by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Rule,
+    Domain,
+    /// Severity of the findings' rules, from the rules file
+    Severity,
+}
+
+impl FromStr for GroupBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "rule" => Ok(GroupBy::Rule),
+            "domain" => Ok(GroupBy::Domain),
+            "severity" => Ok(GroupBy::Severity),
+            other => anyhow::bail!(
+                "Unknown export grouping: {} (expected rule, domain or severity)",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GroupBy::Rule => write!(f, "rule"),
+            GroupBy::Domain => write!(f, "domain"),
+            GroupBy::Severity => write!(f, "severity"),
+        }
+    }
+}
+
+/// Aggregate figures for the findings sharing a rule, domain or severity
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FindingGroup {
+    /// The rule, domain or severity the findings share
+    pub group: String,
+    pub findings: usize,
+    /// Findings where the rule matched
+    pub detected: usize,
+    /// Distinct domains with a detected finding
+    pub domains: usize,
+    /// Distinct rules with a detected finding
+    pub rules: usize,
+    /// Earliest and latest time one of the findings was scanned
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Aggregate findings by rule, domain or severity, ordered by group. Grouping by
+/// severity needs the rules; findings whose rule isn't in them are "unknown".
+pub fn group_findings(
+    findings: &[Finding],
+    group_by: GroupBy,
+    ruleset: Option<&RuleSet>,
+) -> Result<Vec<FindingGroup>> {
+    let severities = match (group_by, ruleset) {
+        (GroupBy::Severity, None) => {
+            anyhow::bail!("Grouping by severity needs the rules file (--rules)")
+        }
+        (_, ruleset) => ruleset.map(severity_names).unwrap_or_default(),
+    };
+
+    let mut groups: BTreeMap<&str, (FindingGroup, HashSet<&str>, HashSet<&str>)> = BTreeMap::new();
+    for finding in findings {
+        let key = match group_by {
+            GroupBy::Rule => finding.rule_name.as_str(),
+            GroupBy::Domain => finding.domain.as_str(),
+            GroupBy::Severity => severities
+                .get(finding.rule_name.as_str())
+                .map(String::as_str)
+                .unwrap_or(UNKNOWN_SEVERITY),
+        };
+        let (group, domains, rules) = groups.entry(key).or_insert_with(|| {
+            (
+                FindingGroup {
+                    group: key.to_string(),
+                    findings: 0,
+                    detected: 0,
+                    domains: 0,
+                    rules: 0,
+                    first_seen: finding.scanned_at,
+                    last_seen: finding.scanned_at,
+                },
+                HashSet::new(),
+                HashSet::new(),
+            )
+        });
+        group.findings += 1;
+        group.first_seen = group.first_seen.min(finding.scanned_at);
+        group.last_seen = group.last_seen.max(finding.scanned_at);
+        if finding.detected {
+            group.detected += 1;
+            domains.insert(finding.domain.as_str());
+            rules.insert(finding.rule_name.as_str());
+        }
+    }
+
+    Ok(groups
+        .into_values()
+        .map(|(mut group, domains, rules)| {
+            group.domains = domains.len();
+            group.rules = rules.len();
+            group
+        })
+        .collect())
+}
+
+/// Write aggregated findings to a file in the given format (csv, json or jsonl)
+pub fn export_groups(
+    groups: &[FindingGroup],
+    group_by: GroupBy,
+    output_file: &str,
+    format: &str,
+) -> Result<()> {
+    if let Some(parent) = Path::new(output_file).parent() {
+        if !parent.exists() {
+            create_dir_all(parent).context("Failed to create output directory")?;
+        }
+    }
+
+    match format.to_lowercase().as_str() {
+        "csv" => {
+            let mut writer = csv::Writer::from_path(output_file)?;
+            let group_header = match group_by {
+                GroupBy::Rule => "Rule",
+                GroupBy::Domain => "Domain",
+                GroupBy::Severity => "Severity",
+            };
+            writer.write_record([
+                group_header,
+                "Findings",
+                "Detected",
+                "Domains",
+                "Rules",
+                "First Seen",
+                "Last Seen",
+            ])?;
+            for group in groups {
+                writer.write_record([
+                    &group.group,
+                    &group.findings.to_string(),
+                    &group.detected.to_string(),
+                    &group.domains.to_string(),
+                    &group.rules.to_string(),
+                    &group.first_seen.to_rfc3339(),
+                    &group.last_seen.to_rfc3339(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+        "json" => {
+            let json = serde_json::to_string_pretty(groups)
+                .context("Failed to serialize groups to JSON")?;
+            std::fs::write(output_file, json).context("Failed to write JSON to output file")?;
+        }
+        "jsonl" | "ndjson" => {
+            let file = File::create(output_file).context("Failed to create JSONL output file")?;
+            let mut writer = BufWriter::new(file);
+            for group in groups {
+                serde_json::to_writer(&mut writer, group)
+                    .context("Failed to serialize group to JSONL")?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+        }
+        _ => anyhow::bail!("Unsupported export format: {}", format),
+    }
+
+    info!(
+        "✅ Exported {} groups by {} to {}",
+        groups.len(),
+        group_by,
+        output_file
+    );
+
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::rules;
+use crate::utils;
+
+/// Most concurrent requests to domains sharing a TLD, an ASN or an IP /24 (0 is unlimited)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThrottlePolicy {
+    /// Requests to domains under the same top-level domain
+    pub per_tld: usize,
+
+    /// Requests to addresses announced by the same autonomous system
+    pub per_asn: usize,
+
+    /// Requests to addresses in the same IPv4 /24 (IPv6 /48)
+    pub per_subnet: usize,
+}
+
+impl ThrottlePolicy {
+    /// Whether no limit is set
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A group of domains sharing a concurrency limit
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ThrottleKey {
+    Tld(String),
+    Asn(u32),
+    /// Network address of the subnet
+    Subnet(IpAddr),
+}
+
+impl fmt::Display for ThrottleKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThrottleKey::Tld(tld) => write!(f, ".{}", tld),
+            ThrottleKey::Asn(asn) => write!(f, "AS{}", asn),
+            ThrottleKey::Subnet(ip @ IpAddr::V4(_)) => write!(f, "{}/24", ip),
+            ThrottleKey::Subnet(ip @ IpAddr::V6(_)) => write!(f, "{}/48", ip),
+        }
+    }
+}
+
+/// IP ranges and the autonomous systems announcing them, loaded from an
+/// [iptoasn](https://iptoasn.com/) style TSV file
+/// (`range_start  range_end  AS_number  country  description`)
+#[derive(Debug, Default)]
+pub struct AsnDb {
+    /// Non-overlapping ranges as IPv6 (IPv4 mapped), sorted by start
+    ranges: Vec<(u128, u128, u32)>,
+}
+
+impl AsnDb {
+    /// Load ranges from a TSV file; unrouted ranges (AS 0) are skipped
+    pub fn from_file(path: &str) -> Result<Self> {
+        let lines =
+            utils::read_lines(path).context(format!("Failed to read ASN file: {}", path))?;
+
+        let mut ranges = Vec::new();
+        for (number, line) in lines.iter().enumerate() {
+            let range = parse_range(line).context(format!(
+                "Invalid ASN range on line {} of {}",
+                number + 1,
+                path
+            ))?;
+            if range.2 != 0 {
+                ranges.push(range);
+            }
+        }
+        if ranges.is_empty() {
+            anyhow::bail!("No ASN ranges found in {}", path);
+        }
+        ranges.sort_unstable();
+
+        Ok(Self { ranges })
+    }
+
+    /// Number of routed ranges
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Whether no range was loaded (never true for a loaded database)
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Autonomous system announcing `ip`, if it's routed
+    pub fn lookup(&self, ip: IpAddr) -> Option<u32> {
+        let ip = to_u128(ip);
+        let next = self.ranges.partition_point(|&(start, _, _)| start <= ip);
+        let &(_, end, asn) = self.ranges.get(next.checked_sub(1)?)?;
+        (ip <= end).then_some(asn)
+    }
+}
+
+/// Parse the start, end and AS number of a TSV line
+fn parse_range(line: &str) -> Result<(u128, u128, u32)> {
+    let mut fields = line.split('\t');
+    let mut next = |what: &str| fields.next().context(format!("Missing {}", what));
+    let start: IpAddr = next("range start")?.trim().parse()?;
+    let end: IpAddr = next("range end")?.trim().parse()?;
+    let asn: u32 = next("AS number")?.trim().trim_start_matches("AS").parse()?;
+
+    Ok((to_u128(start), to_u128(end), asn))
+}
+
+fn to_u128(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u128::from(ip.to_ipv6_mapped()),
+        IpAddr::V6(ip) => u128::from(ip),
+    }
+}
+
+/// Concurrency limits shared by the domains of a TLD, an ASN or a subnet.
+///
+/// Inputs often list thousands of domains hosted by one provider; hammering it tends to
+/// get the scanner blocked provider-wide, which hides exposures on every one of them.
+/// Each group's slots are created on first use and dropped again once idle.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    policy: ThrottlePolicy,
+    asn_db: Option<AsnDb>,
+    slots: Mutex<HashMap<ThrottleKey, Arc<Semaphore>>>,
+    /// Group count above which idle groups are dropped
+    prune_at: AtomicUsize,
+    /// Requests that had to wait for a slot
+    waits: AtomicUsize,
+}
+
+/// Slots held for one request, released when dropped
+#[derive(Debug)]
+pub struct ThrottlePermit {
+    _permits: Vec<OwnedSemaphorePermit>,
+}
+
+/// Groups tracked before idle ones are first dropped
+const PRUNE_MIN: usize = 1024;
+
+impl Throttle {
+    /// Create a throttle for `policy`, looking up ASNs in `asn_db`
+    pub fn new(policy: ThrottlePolicy, asn_db: Option<AsnDb>) -> Self {
+        Self {
+            policy,
+            asn_db,
+            slots: Mutex::new(HashMap::new()),
+            prune_at: AtomicUsize::new(PRUNE_MIN),
+            waits: AtomicUsize::new(0),
+        }
+    }
+
+    /// Groups a domain's requests count against, given its resolved addresses.
+    ///
+    /// Only groups with a limit are returned, always in the same order, so requests
+    /// taking several slots can't deadlock each other.
+    pub fn keys(&self, domain: &str, ips: &[IpAddr]) -> Vec<ThrottleKey> {
+        let mut keys = Vec::new();
+
+        if self.policy.per_tld > 0 {
+            if let Some(tld) = rules::top_level_domain(domain) {
+                keys.push(ThrottleKey::Tld(tld));
+            }
+        }
+
+        let Some(&ip) = ips.first() else {
+            return keys;
+        };
+        if self.policy.per_asn > 0 {
+            if let Some(asn) = self.asn_db.as_ref().and_then(|db| db.lookup(ip)) {
+                keys.push(ThrottleKey::Asn(asn));
+            }
+        }
+        if self.policy.per_subnet > 0 {
+            keys.push(ThrottleKey::Subnet(subnet(ip)));
+        }
+
+        keys
+    }
+
+    /// Wait for a slot in every group of `keys`
+    pub async fn acquire(&self, keys: &[ThrottleKey]) -> ThrottlePermit {
+        let mut permits = Vec::with_capacity(keys.len());
+        let mut waited = false;
+
+        for key in keys {
+            let semaphore = self.semaphore(key);
+            let permit = match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => permit,
+                Err(_) => {
+                    waited = true;
+                    semaphore
+                        .acquire_owned()
+                        .await
+                        .expect("throttle semaphores are never closed")
+                }
+            };
+            permits.push(permit);
+        }
+        if waited {
+            self.waits.fetch_add(1, Ordering::Relaxed);
+        }
+
+        ThrottlePermit { _permits: permits }
+    }
+
+    /// Number of requests that had to wait for a slot
+    pub fn waits(&self) -> usize {
+        self.waits.load(Ordering::Relaxed)
+    }
+
+    fn limit(&self, key: &ThrottleKey) -> usize {
+        match key {
+            ThrottleKey::Tld(_) => self.policy.per_tld,
+            ThrottleKey::Asn(_) => self.policy.per_asn,
+            ThrottleKey::Subnet(_) => self.policy.per_subnet,
+        }
+    }
+
+    /// The slots of a group, created on first use
+    fn semaphore(&self, key: &ThrottleKey) -> Arc<Semaphore> {
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+
+        // A group nobody holds a slot of can be recreated when needed again
+        if slots.len() >= self.prune_at.load(Ordering::Relaxed) {
+            slots.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+            self.prune_at
+                .store((slots.len() * 2).max(PRUNE_MIN), Ordering::Relaxed);
+        }
+
+        slots
+            .entry(key.clone())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.limit(key))))
+            .clone()
+    }
+}
+
+/// Network address of the /24 (IPv6: /48) containing `ip`
+fn subnet(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            IpAddr::from([a, b, c, 0])
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            IpAddr::V6(Ipv6Addr::new(
+                segments[0],
+                segments[1],
+                segments[2],
+                0,
+                0,
+                0,
+                0,
+                0,
+            ))
+        }
+    }
+}
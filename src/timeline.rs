@@ -0,0 +1,363 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveTime, TimeZone, Timelike, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::warn;
+
+use crate::db;
+use crate::rules::{self, RuleSet};
+use crate::store::{self, StoreKind};
+use crate::summary::{self, UNKNOWN_SEVERITY};
+
+/// Severities given a column of their own in CSV output, most severe first
+const SEVERITIES: [&str; 6] = [
+    "critical",
+    "high",
+    "medium",
+    "low",
+    "info",
+    UNKNOWN_SEVERITY,
+];
+
+/// Length of the periods a timeline counts findings over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    Hour,
+    Day,
+    /// Weeks starting on Monday
+    Week,
+    Month,
+}
+
+impl Bucket {
+    /// Start of the period a time falls in
+    pub fn start(self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let date = time.date_naive();
+        let start = match self {
+            Bucket::Hour => date.and_time(NaiveTime::MIN) + Duration::hours(time.hour().into()),
+            Bucket::Day => date.and_time(NaiveTime::MIN),
+            Bucket::Week => {
+                let monday = date - Duration::days(date.weekday().num_days_from_monday().into());
+                monday.and_time(NaiveTime::MIN)
+            }
+            Bucket::Month => date.with_day(1).unwrap_or(date).and_time(NaiveTime::MIN),
+        };
+        Utc.from_utc_datetime(&start)
+    }
+
+    /// Start of the period after the one starting at `start`
+    pub fn next(self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Bucket::Hour => start + Duration::hours(1),
+            Bucket::Day => start + Duration::days(1),
+            Bucket::Week => start + Duration::weeks(1),
+            Bucket::Month => start + Months::new(1),
+        }
+    }
+
+    /// How a period starting at `start` is shown in text and CSV output
+    fn label(self, start: DateTime<Utc>) -> String {
+        let format = match self {
+            Bucket::Hour => "%Y-%m-%d %H:00",
+            Bucket::Day | Bucket::Week => "%Y-%m-%d",
+            Bucket::Month => "%Y-%m",
+        };
+        start.format(format).to_string()
+    }
+}
+
+impl FromStr for Bucket {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "hour" => Ok(Bucket::Hour),
+            "day" => Ok(Bucket::Day),
+            "week" => Ok(Bucket::Week),
+            "month" => Ok(Bucket::Month),
+            other => anyhow::bail!(
+                "Unknown timeline bucket: {} (expected hour, day, week or month)",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Bucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bucket::Hour => write!(f, "hour"),
+            Bucket::Day => write!(f, "day"),
+            Bucket::Week => write!(f, "week"),
+            Bucket::Month => write!(f, "month"),
+        }
+    }
+}
+
+/// What one scan session found, as far as the timeline is concerned
+#[derive(Debug, Clone, Default)]
+pub struct ScanRecord {
+    pub started_at: DateTime<Utc>,
+    /// Domain and rule of each finding the scan detected
+    pub detected: Vec<(String, String)>,
+    /// Domains the scan checked; a finding on one of them the scan no longer detects
+    /// counts as fixed. Domains with a detected finding count as checked too.
+    pub domains: HashSet<String>,
+}
+
+/// Findings that appeared and went away within one period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimelineBucket {
+    pub start: DateTime<Utc>,
+    /// Scan sessions started in the period
+    pub scans: usize,
+    /// Findings detected that weren't open before, per severity
+    pub new: BTreeMap<String, usize>,
+    /// Open findings a scan of their domain no longer detected, per severity
+    pub fixed: BTreeMap<String, usize>,
+    /// Findings open at the end of the period
+    pub open: usize,
+}
+
+impl TimelineBucket {
+    fn new(start: DateTime<Utc>) -> Self {
+        TimelineBucket {
+            start,
+            scans: 0,
+            new: BTreeMap::new(),
+            fixed: BTreeMap::new(),
+            open: 0,
+        }
+    }
+}
+
+/// Replay scan sessions oldest first, counting the findings each one opened and fixed
+/// per period. Every period from the first scan's to the last one's is included, empty
+/// or not, so charts don't skip the gaps. `ruleset` gives the rules' severities;
+/// without it, every finding's is "unknown".
+pub fn build_timeline(
+    scans: &[ScanRecord],
+    bucket: Bucket,
+    ruleset: Option<&RuleSet>,
+) -> Vec<TimelineBucket> {
+    let severities = ruleset.map(summary::severity_names).unwrap_or_default();
+    let severity = |rule: &str| -> String {
+        severities
+            .get(rule)
+            .cloned()
+            .unwrap_or_else(|| UNKNOWN_SEVERITY.to_string())
+    };
+
+    let mut scans: Vec<&ScanRecord> = scans.iter().collect();
+    scans.sort_by_key(|scan| scan.started_at);
+    let (Some(first), Some(last)) = (scans.first(), scans.last()) else {
+        return Vec::new();
+    };
+
+    let mut timeline = Vec::new();
+    let mut start = bucket.start(first.started_at);
+    let end = bucket.start(last.started_at);
+    while start <= end {
+        timeline.push(TimelineBucket::new(start));
+        start = bucket.next(start);
+    }
+
+    let mut open: HashSet<(String, String)> = HashSet::new();
+    let mut current = 0;
+    for scan in scans {
+        let start = bucket.start(scan.started_at);
+        while timeline[current].start < start {
+            timeline[current].open = open.len();
+            current += 1;
+        }
+        let period = &mut timeline[current];
+        period.scans += 1;
+
+        let detected: HashSet<(String, String)> = scan.detected.iter().cloned().collect();
+        let checked: HashSet<&str> = scan
+            .domains
+            .iter()
+            .map(String::as_str)
+            .chain(detected.iter().map(|(domain, _)| domain.as_str()))
+            .collect();
+        open.retain(|key| {
+            let fixed = checked.contains(key.0.as_str()) && !detected.contains(key);
+            if fixed {
+                *period.fixed.entry(severity(&key.1)).or_default() += 1;
+            }
+            !fixed
+        });
+        for key in detected {
+            if !open.contains(&key) {
+                *period.new.entry(severity(&key.1)).or_default() += 1;
+                open.insert(key);
+            }
+        }
+    }
+    timeline[current].open = open.len();
+
+    timeline
+}
+
+/// Read the scan sessions of a store, and the findings each kept, for the timeline.
+/// Scans from before findings were kept per scan are left out.
+pub fn load_history(
+    kind: StoreKind,
+    database: &str,
+    shards: usize,
+    project: Option<&str>,
+) -> Result<Vec<ScanRecord>> {
+    let results = store::open_results(kind, database, shards)?;
+    let sessions = db::of_project(store::load_scan_sessions(kind, database)?, project);
+
+    let mut scans = Vec::new();
+    let mut scan_ids = Vec::new();
+    for session in sessions {
+        let findings = match results.scan_findings(&session.id) {
+            Ok(findings) => findings,
+            Err(e) => {
+                warn!(
+                    "⚠️ Leaving scan {} out of the timeline: {:#}",
+                    session.id, e
+                );
+                continue;
+            }
+        };
+        scans.push(ScanRecord {
+            started_at: session.started_at,
+            detected: findings
+                .into_iter()
+                .filter(|finding| finding.detected)
+                .map(|finding| (finding.domain, finding.rule_name))
+                .collect(),
+            domains: HashSet::new(),
+        });
+        scan_ids.push(session.id);
+    }
+
+    // Only domains that ever had a finding can have one fixed
+    let domains: BTreeSet<&str> = scans
+        .iter()
+        .flat_map(|scan| scan.detected.iter().map(|(domain, _)| domain.as_str()))
+        .collect();
+    let mut checked: HashMap<String, HashSet<String>> = HashMap::new();
+    for domain in domains {
+        for domain_scan in results.domain_scans(domain)? {
+            checked
+                .entry(domain_scan.scan_id)
+                .or_default()
+                .insert(domain_scan.domain);
+        }
+    }
+    for (scan, id) in scans.iter_mut().zip(&scan_ids) {
+        if let Some(domains) = checked.remove(id) {
+            scan.domains = domains;
+        }
+    }
+
+    Ok(scans)
+}
+
+/// Print counts of new and fixed findings per period and severity
+pub fn show_timeline(
+    kind: StoreKind,
+    database: &str,
+    shards: usize,
+    rules_file: Option<&str>,
+    bucket: Bucket,
+    project: Option<&str>,
+    format: &str,
+) -> Result<()> {
+    if kind.resolve(database) != StoreKind::Postgres && !Path::new(database).exists() {
+        anyhow::bail!("Results not found: {}", database);
+    }
+    let ruleset = rules_file
+        .map(|file| rules::load_rules(file).context(format!("Failed to load rules: {}", file)))
+        .transpose()?;
+    let scans = load_history(kind, database, shards, project)?;
+    let timeline = build_timeline(&scans, bucket, ruleset.as_ref());
+
+    match format.to_lowercase().as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&timeline)?),
+        "csv" => write_csv(&timeline, bucket, std::io::stdout())?,
+        "text" => print_timeline(&store::redact_url(database), &timeline, bucket),
+        _ => anyhow::bail!("Unsupported timeline format: {}", format),
+    }
+
+    Ok(())
+}
+
+/// Write a timeline as CSV, one row per period with a new and a fixed column per
+/// severity
+pub fn write_csv<W: std::io::Write>(
+    timeline: &[TimelineBucket],
+    bucket: Bucket,
+    output: W,
+) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(output);
+
+    let mut header = vec![
+        "Period".to_string(),
+        "Scans".to_string(),
+        "New".to_string(),
+        "Fixed".to_string(),
+        "Open".to_string(),
+    ];
+    for severity in SEVERITIES {
+        header.push(format!("New {}", severity));
+        header.push(format!("Fixed {}", severity));
+    }
+    writer.write_record(&header)?;
+
+    for period in timeline {
+        let mut record = vec![
+            bucket.label(period.start),
+            period.scans.to_string(),
+            period.new.values().sum::<usize>().to_string(),
+            period.fixed.values().sum::<usize>().to_string(),
+            period.open.to_string(),
+        ];
+        for severity in SEVERITIES {
+            record.push(period.new.get(severity).copied().unwrap_or(0).to_string());
+            record.push(period.fixed.get(severity).copied().unwrap_or(0).to_string());
+        }
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn print_timeline(database: &str, timeline: &[TimelineBucket], bucket: Bucket) {
+    println!("📈 Findings per {} in {}", bucket, database);
+    if timeline.is_empty() {
+        println!("\nNo scans recorded");
+        return;
+    }
+
+    println!(
+        "\n{:<18} {:>6} {:>6} {:>6} {:>6}  New by severity",
+        "Period", "Scans", "New", "Fixed", "Open"
+    );
+    for period in timeline {
+        let by_severity: Vec<String> = SEVERITIES
+            .iter()
+            .filter_map(|severity| {
+                let count = period.new.get(*severity)?;
+                Some(format!("{} {}", count, severity))
+            })
+            .collect();
+        println!(
+            "{:<18} {:>6} {:>6} {:>6} {:>6}  {}",
+            bucket.label(period.start),
+            period.scans,
+            period.new.values().sum::<usize>(),
+            period.fixed.values().sum::<usize>(),
+            period.open,
+            by_severity.join(", ")
+        );
+    }
+}
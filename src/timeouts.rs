@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+/// Phase of a request a timeout expired in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutPhase {
+    /// Resolving the domain
+    Dns,
+    /// Opening the TCP connection
+    Connect,
+    /// Negotiating TLS on an open connection
+    Tls,
+    /// Waiting for the response headers once connected
+    FirstByte,
+    /// The request as a whole, body included
+    Total,
+}
+
+impl fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TimeoutPhase::Dns => "DNS",
+            TimeoutPhase::Connect => "connect",
+            TimeoutPhase::Tls => "TLS handshake",
+            TimeoutPhase::FirstByte => "first byte",
+            TimeoutPhase::Total => "total",
+        })
+    }
+}
+
+/// Time budget of each phase of a request.
+///
+/// The HTTP client can only bound connection setup as a whole, so the TCP connect and
+/// TLS handshake share one budget of `connect + tls`; a connection that runs out of it
+/// is attributed to the phase that stalled. The first byte is awaited for `first_byte`
+/// after that, and `total` caps the whole request, body included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    pub dns: Duration,
+    pub connect: Duration,
+    pub tls: Duration,
+    pub first_byte: Duration,
+    pub total: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            dns: Duration::from_secs(5),
+            connect: Duration::from_secs(5),
+            tls: Duration::from_secs(5),
+            first_byte: Duration::from_secs(10),
+            total: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Timeouts {
+    /// Budget for setting up a connection: TCP connect and TLS handshake
+    pub fn handshake(&self) -> Duration {
+        self.connect + self.tls
+    }
+
+    /// Budget from sending a request until its response headers, connection included
+    pub fn response_headers(&self) -> Duration {
+        self.handshake() + self.first_byte
+    }
+}
+
+/// A request that ran out of one of its budgets
+#[derive(Debug, thiserror::Error)]
+#[error("{phase} timeout after {budget:?}")]
+pub struct PhaseTimeout {
+    pub phase: TimeoutPhase,
+    pub budget: Duration,
+}
+
+/// Tell whether a connection to `url` that ran out of its handshake budget stalled
+/// connecting or negotiating TLS, by checking if the host accepts a TCP connection
+/// within the connect budget.
+///
+/// Only for direct connections: behind a proxy the host isn't contacted directly.
+pub async fn classify_handshake(url: &url::Url, connect: Duration) -> TimeoutPhase {
+    if url.scheme() != "https" {
+        return TimeoutPhase::Connect;
+    }
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return TimeoutPhase::Connect;
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    match tokio::time::timeout(connect, TcpStream::connect((host, port))).await {
+        Ok(Ok(_)) => TimeoutPhase::Tls,
+        _ => TimeoutPhase::Connect,
+    }
+}
@@ -0,0 +1,311 @@
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::stream::{BatchProgress, StreamEvent, WorkerSummary, STREAM_PATH};
+use crate::utils;
+
+/// Most recent findings kept on screen
+pub const RECENT_FINDINGS: usize = 15;
+
+/// Shortest interval between redraws, so a burst of findings doesn't flicker
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Clear the terminal and move the cursor home
+const CLEAR: &str = "\x1b[2J\x1b[H";
+
+/// Events from a remote scan's or master's event stream
+pub struct Subscription {
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+}
+
+impl Subscription {
+    /// Subscribe to the event stream at `connect`: a `HOST:PORT` or a full URL,
+    /// sending `token` if the stream requires one
+    pub async fn connect(connect: &str, token: Option<&str>) -> Result<Self> {
+        let url = stream_url(connect)?;
+        let mut request = reqwest::Client::new()
+            .get(&url)
+            .header("Accept", "text/event-stream");
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .context(format!("Failed to connect to {}", url))?
+            .error_for_status()
+            .context(format!("No event stream at {}", url))?;
+
+        Ok(Self {
+            response,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// The next event, or `None` once the stream ends
+    pub async fn next_event(&mut self) -> Result<Option<StreamEvent>> {
+        loop {
+            while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+                let message: Vec<u8> = self.buffer.drain(..end + 2).collect();
+                if let Some(event) = parse_message(&String::from_utf8_lossy(&message))? {
+                    return Ok(Some(event));
+                }
+            }
+
+            match self
+                .response
+                .chunk()
+                .await
+                .context("Lost connection to the event stream")?
+            {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// The URL of the event stream at `connect`
+fn stream_url(connect: &str) -> Result<String> {
+    if connect.starts_with("http://") || connect.starts_with("https://") {
+        return Ok(connect.to_string());
+    }
+
+    let (host, port) = utils::split_host_port(connect);
+    utils::build_url(&utils::base_url("http", host, port)?, STREAM_PATH)
+}
+
+/// The event in one SSE message; comments, such as keep-alives, carry none
+fn parse_message(message: &str) -> Result<Option<StreamEvent>> {
+    let data: Vec<&str> = message
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    if data.is_empty() {
+        return Ok(None);
+    }
+
+    match serde_json::from_str(&data.join("\n")) {
+        Ok(event) => Ok(Some(event)),
+        Err(e) => {
+            // Events from a newer fatt are skipped rather than ending the session
+            debug!("Skipping unknown stream event: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// What the dashboard knows about the remote scan and workers
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    source: String,
+    scan_id: Option<String>,
+    domains_processed: usize,
+    domains_total: usize,
+    checks_completed: usize,
+    checks_total: usize,
+    findings: usize,
+    batches: Option<BatchProgress>,
+    recent: VecDeque<String>,
+    workers: Vec<WorkerSummary>,
+    finished: Option<String>,
+}
+
+impl Dashboard {
+    /// An empty dashboard for the stream at `source`
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the remote scan has finished
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    /// Update the dashboard with an event
+    pub fn apply(&mut self, event: StreamEvent) {
+        match event {
+            StreamEvent::Started {
+                scan_id,
+                domains,
+                checks,
+            } => {
+                self.scan_id = Some(scan_id);
+                self.domains_total = domains;
+                self.checks_total = checks;
+            }
+            StreamEvent::Finding {
+                domain,
+                rule,
+                severity,
+                path,
+                status,
+                found_at,
+                ..
+            } => {
+                self.findings += 1;
+                self.recent.push_front(format!(
+                    "{}  {:<8}  {}  {}{}  [{}]",
+                    found_at.format("%H:%M:%S"),
+                    severity.map(|s| s.to_string()).unwrap_or_default(),
+                    rule,
+                    domain,
+                    path,
+                    status
+                ));
+                self.recent.truncate(RECENT_FINDINGS);
+            }
+            StreamEvent::Progress {
+                domains_processed,
+                domains_total,
+                checks_completed,
+                checks_total,
+                findings,
+                batches,
+            } => {
+                self.domains_processed = domains_processed;
+                self.domains_total = domains_total;
+                self.checks_completed = checks_completed;
+                self.checks_total = checks_total;
+                self.batches = batches;
+                // Subscribers that joined mid-scan missed the earlier findings
+                self.findings = self.findings.max(findings);
+            }
+            StreamEvent::Finished {
+                scan_id,
+                domains,
+                findings,
+                elapsed_secs,
+            } => {
+                self.scan_id = Some(scan_id);
+                self.domains_processed = domains;
+                self.findings = findings;
+                self.checks_completed = self.checks_total;
+                self.finished = Some(format!(
+                    "Finished: {} domains, {} findings in {:.1}s",
+                    domains, findings, elapsed_secs
+                ));
+            }
+            StreamEvent::Workers { workers } => self.workers = workers,
+        }
+    }
+
+    /// The dashboard as text
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "fatt - {}{}",
+            self.source,
+            self.scan_id
+                .as_ref()
+                .map(|id| format!(" - scan {}", id))
+                .unwrap_or_default()
+        )];
+        lines.push(String::new());
+
+        if self.domains_total > 0 || self.checks_total > 0 {
+            lines.push(format!(
+                "Domains   {}",
+                progress_bar(self.domains_processed, self.domains_total)
+            ));
+            lines.push(format!(
+                "Checks    {}",
+                progress_bar(self.checks_completed, self.checks_total)
+            ));
+            if let Some(batches) = self.batches {
+                lines.push(format!(
+                    "Batches   {}",
+                    progress_bar(batches.done, batches.total)
+                ));
+            }
+            lines.push(format!("Findings  {}", self.findings));
+            lines.push(String::new());
+        }
+
+        if !self.workers.is_empty() {
+            lines.push(format!("Workers ({})", self.workers.len()));
+            for worker in &self.workers {
+                lines.push(format!(
+                    "  {:<24} active {:>4}/{:<4} completed {:>8}  findings {:>6}",
+                    worker.id,
+                    worker.active_scans,
+                    worker.max_concurrency,
+                    worker.completed_scans,
+                    worker.findings
+                ));
+            }
+            lines.push(String::new());
+        }
+
+        lines.push("Recent findings".to_string());
+        if self.recent.is_empty() {
+            lines.push("  (none yet)".to_string());
+        }
+        for finding in &self.recent {
+            lines.push(format!("  {}", finding));
+        }
+
+        if let Some(finished) = &self.finished {
+            lines.push(String::new());
+            lines.push(finished.clone());
+        }
+
+        lines.join("\n") + "\n"
+    }
+}
+
+fn progress_bar(done: usize, total: usize) -> String {
+    const WIDTH: usize = 30;
+    let fraction = if total == 0 {
+        0.0
+    } else {
+        (done as f64 / total as f64).min(1.0)
+    };
+    let filled = (fraction * WIDTH as f64).round() as usize;
+
+    format!(
+        "[{}{}] {}/{} ({:.0}%)",
+        "#".repeat(filled),
+        "-".repeat(WIDTH - filled),
+        done,
+        total,
+        fraction * 100.0
+    )
+}
+
+/// Follow a remote scan or master in the terminal until its scan finishes
+pub async fn run_tui(connect: &str, token: Option<&str>) -> Result<()> {
+    let mut subscription = Subscription::connect(connect, token).await?;
+    let mut dashboard = Dashboard::new(connect);
+    let mut stdout = std::io::stdout();
+    let mut last_draw: Option<Instant> = None;
+
+    draw(&mut stdout, &dashboard)?;
+    while let Some(event) = subscription.next_event().await? {
+        dashboard.apply(event);
+        if dashboard.is_finished() || last_draw.is_none_or(|t| t.elapsed() >= REDRAW_INTERVAL) {
+            draw(&mut stdout, &dashboard)?;
+            last_draw = Some(Instant::now());
+        }
+    }
+
+    draw(&mut stdout, &dashboard)?;
+    if !dashboard.is_finished() {
+        anyhow::bail!("{} closed the event stream", connect);
+    }
+
+    Ok(())
+}
+
+fn draw(stdout: &mut std::io::Stdout, dashboard: &Dashboard) -> Result<()> {
+    write!(stdout, "{}{}", CLEAR, dashboard.render())?;
+    stdout.flush()?;
+    Ok(())
+}
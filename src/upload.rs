@@ -0,0 +1,338 @@
+//! Upload of results exports to S3-compatible object storage, such as AWS S3 or MinIO,
+//! so scans on ephemeral machines keep their output
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::info;
+
+/// Time allowed for uploading one file
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Region requests are signed for when neither the configuration nor the environment
+/// sets one
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// Location of an object, written `s3://bucket/key`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Url {
+    pub bucket: String,
+    pub key: String,
+}
+
+impl S3Url {
+    /// Whether a path names an object rather than a local file
+    pub fn is_s3(path: &str) -> bool {
+        path.starts_with("s3://")
+    }
+
+    /// Last part of the key, which exports are written under before uploading
+    pub fn file_name(&self) -> &str {
+        self.key.rsplit('/').next().unwrap_or(&self.key)
+    }
+
+    /// Object next to this one, named `file_name`
+    pub fn sibling(&self, file_name: &str) -> S3Url {
+        let key = match self.key.rsplit_once('/') {
+            Some((prefix, _)) => format!("{}/{}", prefix, file_name),
+            None => file_name.to_string(),
+        };
+        S3Url {
+            bucket: self.bucket.clone(),
+            key,
+        }
+    }
+}
+
+impl FromStr for S3Url {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let location = s
+            .strip_prefix("s3://")
+            .context(format!("Not an S3 URL (expected s3://bucket/key): {}", s))?;
+        match location.split_once('/') {
+            Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() && !key.ends_with('/') => {
+                Ok(S3Url {
+                    bucket: bucket.to_string(),
+                    key: key.to_string(),
+                })
+            }
+            _ => anyhow::bail!("S3 URL needs a bucket and a key (s3://bucket/key): {}", s),
+        }
+    }
+}
+
+impl fmt::Display for S3Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket, self.key)
+    }
+}
+
+/// Where and as whom exports are uploaded. Unset fields are taken from the usual AWS
+/// environment variables: `AWS_ENDPOINT_URL`, `AWS_REGION`, `AWS_ACCESS_KEY_ID`,
+/// `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct S3Config {
+    /// Endpoint of an S3-compatible service, such as `http://minio:9000`; objects are
+    /// addressed by path on it. AWS S3 when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+
+    /// Region requests are signed for [default: us-east-1]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_key_id: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_access_key: Option<String>,
+
+    /// Token of temporary credentials
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_token: Option<String>,
+}
+
+impl S3Config {
+    /// Fill unset fields from the environment and check the credentials are complete
+    pub fn resolve(&self) -> Result<S3Signer> {
+        let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let endpoint = self
+            .endpoint
+            .clone()
+            .or_else(|| env("AWS_ENDPOINT_URL_S3"))
+            .or_else(|| env("AWS_ENDPOINT_URL"));
+        if let Some(endpoint) = &endpoint {
+            url::Url::parse(endpoint).context(format!("Invalid S3 endpoint: {}", endpoint))?;
+        }
+
+        Ok(S3Signer {
+            endpoint,
+            region: self
+                .region
+                .clone()
+                .or_else(|| env("AWS_REGION"))
+                .or_else(|| env("AWS_DEFAULT_REGION"))
+                .unwrap_or_else(|| DEFAULT_REGION.to_string()),
+            access_key_id: self
+                .access_key_id
+                .clone()
+                .or_else(|| env("AWS_ACCESS_KEY_ID"))
+                .context("S3 uploads need an access key ID (set AWS_ACCESS_KEY_ID)")?,
+            secret_access_key: self
+                .secret_access_key
+                .clone()
+                .or_else(|| env("AWS_SECRET_ACCESS_KEY"))
+                .context("S3 uploads need a secret access key (set AWS_SECRET_ACCESS_KEY)")?,
+            session_token: self
+                .session_token
+                .clone()
+                .or_else(|| env("AWS_SESSION_TOKEN")),
+        })
+    }
+}
+
+/// Resolved S3 settings, signing requests with AWS Signature Version 4
+#[derive(Debug, Clone)]
+pub struct S3Signer {
+    pub endpoint: Option<String>,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl S3Signer {
+    /// URL of an object: under the endpoint's path when one is set, and on the
+    /// bucket's virtual host on AWS otherwise
+    pub fn object_url(&self, target: &S3Url) -> Result<url::Url> {
+        let base = match &self.endpoint {
+            Some(endpoint) => format!("{}/{}", endpoint.trim_end_matches('/'), target.bucket),
+            None => format!("https://{}.s3.{}.amazonaws.com", target.bucket, self.region),
+        };
+        let url = format!("{}/{}", base, uri_encode(&target.key));
+        url::Url::parse(&url).context(format!("Invalid S3 object URL: {}", url))
+    }
+
+    /// Headers authorizing a request with `payload` as body, signed at `now`
+    pub fn sign(
+        &self,
+        method: &str,
+        url: &url::Url,
+        payload: &[u8],
+        now: DateTime<Utc>,
+    ) -> Vec<(&'static str, String)> {
+        let date_time = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(payload));
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        // Canonical headers, sorted by name
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", date_time.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+
+        // The URL's path is already encoded the way S3 expects
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method,
+            url.path(),
+            url.query().unwrap_or_default(),
+            canonical_headers,
+            signed_headers,
+            payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            date_time,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = [date.as_str(), self.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(
+                format!("AWS4{}", self.secret_access_key).into_bytes(),
+                |key, part| hmac_sha256(&key, part.as_bytes()),
+            );
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        headers.retain(|(name, _)| *name != "host");
+        headers.push((
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key_id, scope, signed_headers, signature
+            ),
+        ));
+        headers
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encode an object key for its URL path, keeping RFC 3986's unreserved
+/// characters and the slashes between parts
+fn uri_encode(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Content type of an export, by its file extension
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => "text/csv",
+        Some("json") => "application/json",
+        Some("jsonl" | "ndjson") => "application/x-ndjson",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Upload a local file as the object at `target`
+pub async fn upload_file(
+    client: &Client,
+    signer: &S3Signer,
+    target: &S3Url,
+    path: &Path,
+) -> Result<()> {
+    let body = tokio::fs::read(path)
+        .await
+        .context(format!("Failed to read {}", path.display()))?;
+    let url = signer.object_url(target)?;
+
+    let headers = signer.sign("PUT", &url, &body, Utc::now());
+    let mut request = client
+        .put(url)
+        .header("content-type", content_type(path))
+        .timeout(UPLOAD_TIMEOUT);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    let response = request
+        .body(body)
+        .send()
+        .await
+        .context(format!("Failed to upload to {}", target))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!(
+            "Upload to {} failed with {}: {}",
+            target,
+            status,
+            body.trim()
+        );
+    }
+    Ok(())
+}
+
+/// Write an export into a staging directory with `write`, which gets the path to
+/// write to, and upload every file it wrote next to `target`'s key: the one export,
+/// or each file of a split one. Returns the objects uploaded.
+pub async fn upload_export(
+    config: &S3Config,
+    target: &S3Url,
+    write: impl FnOnce(&str) -> Result<()>,
+) -> Result<Vec<S3Url>> {
+    let signer = config.resolve()?;
+    let staging = tempfile::tempdir().context("Failed to create a staging directory")?;
+    let path = staging.path().join(target.file_name());
+    write(&path.to_string_lossy())?;
+
+    let mut files: Vec<_> = std::fs::read_dir(staging.path())?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    files.sort();
+
+    let client = Client::new();
+    let mut uploaded = Vec::new();
+    for file in files {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let object = target.sibling(&name);
+        upload_file(&client, &signer, &object, &file).await?;
+        info!("☁️ Uploaded {}", object);
+        uploaded.push(object);
+    }
+    Ok(uploaded)
+}
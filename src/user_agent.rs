@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::utils;
+
+/// A list of User-Agent strings to rotate through during a scan
+#[derive(Debug)]
+pub struct UserAgentPool {
+    agents: Vec<String>,
+
+    /// Keep one User-Agent per domain instead of rotating on every check
+    sticky: bool,
+
+    next: AtomicUsize,
+}
+
+impl UserAgentPool {
+    /// Create a pool from a non-empty list of User-Agent strings
+    pub fn new(agents: Vec<String>, sticky: bool) -> Result<Self> {
+        if agents.is_empty() {
+            anyhow::bail!("User-Agent list is empty");
+        }
+
+        Ok(Self {
+            agents,
+            sticky,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Load User-Agents from a file, one per line (blank lines and `#` comments ignored)
+    pub fn from_file(path: &str, sticky: bool) -> Result<Self> {
+        let agents =
+            utils::read_lines(path).context(format!("Failed to read User-Agent file: {}", path))?;
+
+        Self::new(agents, sticky).context(format!("No User-Agents found in {}", path))
+    }
+
+    /// Number of User-Agents in the pool
+    pub fn len(&self) -> usize {
+        self.agents.len()
+    }
+
+    /// Whether the pool is empty (never true for a constructed pool)
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+    }
+
+    /// Pick the User-Agent for a check against `domain`
+    pub fn pick(&self, domain: &str) -> &str {
+        let index = if self.sticky {
+            let mut hasher = DefaultHasher::new();
+            domain.to_lowercase().hash(&mut hasher);
+            hasher.finish() as usize
+        } else {
+            self.next.fetch_add(1, Ordering::Relaxed)
+        };
+
+        &self.agents[index % self.agents.len()]
+    }
+}
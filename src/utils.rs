@@ -1,7 +1,8 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use rand::prelude::*;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::net::Ipv6Addr;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::Semaphore;
@@ -25,11 +26,39 @@ pub fn read_domains(file_path: &str) -> Result<Vec<String>> {
     Ok(unique_domains)
 }
 
-/// Normalize a domain name by removing leading/trailing whitespace
-/// and converting to lowercase
-#[allow(dead_code)]
+/// Count the non-empty, non-comment lines of a file without loading it into memory
+pub fn count_lines(file_path: &str) -> Result<usize> {
+    let reader = BufReader::new(File::open(file_path)?);
+
+    let mut count = 0;
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+/// Normalize a domain name to the one spelling it's scanned and stored under: without
+/// surrounding whitespace or the trailing dot of a fully qualified name, lowercased,
+/// and with internationalized labels in their ASCII (punycode) form. A port is kept,
+/// so `Bücher.Example.:8443` becomes `xn--bcher-kva.example:8443`.
 pub fn normalize_domain(domain: &str) -> String {
-    domain.trim().to_lowercase()
+    let (host, port) = split_host_port(domain.trim());
+    let host = host.strip_suffix('.').unwrap_or(host);
+    let host = match url::Host::parse(host) {
+        Ok(url::Host::Domain(ascii)) if !host.is_ascii() => ascii,
+        _ => host.to_lowercase(),
+    };
+
+    match port {
+        Some(port) if host.parse::<Ipv6Addr>().is_ok() => format!("[{}]:{}", host, port),
+        Some(port) => format!("{}:{}", host, port),
+        None => host,
+    }
 }
 
 /// Check if a string is a valid domain name
@@ -43,6 +72,9 @@ pub fn is_valid_domain(domain: &str) -> bool {
         return false;
     }
 
+    // A fully qualified name ends in the root label's dot
+    let domain = domain.strip_suffix('.').unwrap_or(domain);
+
     // Check if empty
     if domain.is_empty() {
         return false;
@@ -86,14 +118,59 @@ pub fn is_valid_domain(domain: &str) -> bool {
     true
 }
 
-/// Build a URL with optional HTTP/HTTPS scheme
-#[allow(dead_code)]
-pub fn build_url(domain: &str, path: &str) -> String {
-    let domain = normalize_domain(domain);
-    let base_url = if domain.starts_with("http://") || domain.starts_with("https://") {
-        domain
+/// Split a target into its host and port: `example.com:8080`, `[2001:db8::1]:8443`,
+/// or a bare IPv6 address such as `2001:db8::1`, whose colons aren't a port.
+/// IPv6 hosts are returned without their brackets.
+pub fn split_host_port(target: &str) -> (&str, Option<u16>) {
+    if let Some((host, rest)) = target
+        .strip_prefix('[')
+        .and_then(|target| target.split_once(']'))
+    {
+        return (
+            host,
+            rest.strip_prefix(':').and_then(|port| port.parse().ok()),
+        );
+    }
+    if target.parse::<Ipv6Addr>().is_ok() {
+        return (target, None);
+    }
+
+    match target.rsplit_once(':') {
+        Some((host, port)) => match port.parse() {
+            Ok(port) => (host, Some(port)),
+            Err(_) => (target, None),
+        },
+        None => (target, None),
+    }
+}
+
+/// Base URL of a host over a scheme, without a trailing slash: IPv6 addresses are
+/// bracketed and the scheme's default port is left out (`https://[2001:db8::1]:8443`)
+pub fn base_url(scheme: &str, host: &str, port: Option<u16>) -> Result<String> {
+    let mut url = Url::parse(&format!("{}://localhost", scheme))
+        .context(format!("Invalid URL scheme: {}", scheme))?;
+    let host = match host.parse::<Ipv6Addr>() {
+        Ok(address) => format!("[{}]", address),
+        Err(_) => host.to_string(),
+    };
+    url.set_host(Some(&host))
+        .context(format!("Invalid host: {}", host))?;
+    url.set_port(port)
+        .map_err(|_| anyhow::anyhow!("{} URLs can't have a port", scheme))?;
+
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+/// Build the URL of a path on a target: a base URL, or a domain or address with an
+/// optional port, which is reached over HTTPS
+pub fn build_url(target: &str, path: &str) -> Result<String> {
+    let target = target.trim();
+    let base = if target.contains("://") {
+        Url::parse(target).context(format!("Invalid URL: {}", target))?
     } else {
-        format!("https://{}", domain)
+        let target = normalize_domain(target);
+        let (host, port) = split_host_port(&target);
+        Url::parse(&base_url("https", host, port)?)?
     };
 
     // Ensure path starts with / if non-empty
@@ -103,13 +180,10 @@ pub fn build_url(domain: &str, path: &str) -> String {
         format!("/{}", path)
     };
 
-    // Combine and normalize URL
-    let mut url = format!("{}{}", base_url, path);
-    if let Ok(parsed_url) = Url::parse(&url) {
-        url = parsed_url.to_string();
-    }
-
-    url
+    let url = format!("{}{}", base.as_str().trim_end_matches('/'), path);
+    Ok(Url::parse(&url)
+        .context(format!("Invalid URL: {}", url))?
+        .to_string())
 }
 
 /// Split a vector into chunks of a specified size
@@ -119,7 +193,7 @@ pub fn chunk_vector<T: Clone>(vec: Vec<T>, chunk_size: usize) -> Vec<Vec<T>> {
         return vec![vec];
     }
 
-    let chunks = vec.len() / chunk_size + if vec.len() % chunk_size > 0 { 1 } else { 0 };
+    let chunks = vec.len().div_ceil(chunk_size);
     let mut result = Vec::with_capacity(chunks);
 
     for i in 0..chunks {
@@ -148,15 +222,80 @@ pub fn format_duration(seconds: f64) -> String {
     }
 }
 
+/// Format a byte count with binary units
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
 /// Create a random backoff delay between min_ms and max_ms
-#[allow(dead_code)]
 pub async fn random_backoff(min_ms: u64, max_ms: u64) {
-    let mut rng = rand::thread_rng();
-    let backoff_ms = rng.gen_range(min_ms..=max_ms);
+    // Keep the thread-local RNG out of the await so the future stays Send
+    let backoff_ms = rand::thread_rng().gen_range(min_ms..=max_ms);
     debug!(" Backing off for {}ms", backoff_ms);
     sleep(Duration::from_millis(backoff_ms)).await;
 }
 
+/// Retry settings for operations that can fail transiently
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    pub retries: u32,
+
+    /// Base backoff in milliseconds, doubled after every failed attempt
+    pub backoff_ms: u64,
+}
+
+impl RetryPolicy {
+    /// Create a new retry policy
+    pub fn new(retries: u32, backoff_ms: u64) -> Self {
+        Self {
+            retries,
+            backoff_ms,
+        }
+    }
+
+    /// Backoff range before the given retry (0-based), as (min_ms, max_ms)
+    pub fn backoff_range(&self, attempt: u32) -> (u64, u64) {
+        let delay = self.backoff_ms.saturating_mul(1u64 << attempt.min(16));
+        (delay, delay.saturating_mul(2))
+    }
+
+    /// Run an operation, retrying failures with exponential backoff and jitter
+    pub async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: futures::Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.retries => {
+                    debug!("🔁 Attempt {} failed, retrying: {}", attempt + 1, e);
+                    let (min_ms, max_ms) = self.backoff_range(attempt);
+                    random_backoff(min_ms, max_ms).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
 /// Process a batch of items with bounded concurrency
 #[allow(dead_code)]
 pub async fn process_batch<T, F, Fut>(
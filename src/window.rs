@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::info;
+
+/// Longest a paused scan sleeps before checking the clock again, so a changed system
+/// clock or a daylight saving shift can't oversleep the window
+const RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A daily span of time scans may send traffic in, such as `22:00-06:00` or
+/// `22:00-06:00 Europe/Berlin`. A window whose end is before its start runs past
+/// midnight. Times are in UTC unless a time zone is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+    tz: Tz,
+}
+
+impl FromStr for ScanWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split_whitespace();
+        let span = parts
+            .next()
+            .context("A scan window needs a start and an end, such as 22:00-06:00")?;
+        let tz = match parts.next() {
+            Some(tz) => tz
+                .parse::<Tz>()
+                .map_err(|_| anyhow::anyhow!("Unknown time zone: {}", tz))?,
+            None => Tz::UTC,
+        };
+        if parts.next().is_some() {
+            anyhow::bail!("Invalid scan window: {} (expected HH:MM-HH:MM [ZONE])", s);
+        }
+
+        let (start, end) = span
+            .split_once('-')
+            .context(format!("Invalid scan window: {} (expected HH:MM-HH:MM)", s))?;
+        let time = |value: &str| {
+            NaiveTime::parse_from_str(value, "%H:%M")
+                .context(format!("Invalid time in scan window: {}", value))
+        };
+        let (start, end) = (time(start)?, time(end)?);
+        if start == end {
+            anyhow::bail!("A scan window can't start and end at the same time");
+        }
+
+        Ok(Self { start, end, tz })
+    }
+}
+
+impl fmt::Display for ScanWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} {}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            self.tz
+        )
+    }
+}
+
+impl ScanWindow {
+    /// Whether traffic may be sent at `at`
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let time = at.with_timezone(&self.tz).time();
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// When the window next opens after `at`; `at` itself when it's open
+    pub fn next_open(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        if self.contains(at) {
+            return at;
+        }
+        let today = at.with_timezone(&self.tz).date_naive();
+        [today, today + ChronoDuration::days(1)]
+            .into_iter()
+            .map(|date| self.opening_on(date))
+            .find(|open| *open > at)
+            .unwrap_or_else(|| self.opening_on(today + ChronoDuration::days(2)))
+    }
+
+    /// How long until the window opens, or none when it's open at `at`
+    pub fn until_open(&self, at: DateTime<Utc>) -> Option<Duration> {
+        let open = self.next_open(at);
+        (open > at).then(|| (open - at).to_std().unwrap_or_default())
+    }
+
+    /// The window's start on a local date; a start skipped by a daylight saving shift
+    /// moves to the first minute after it
+    fn opening_on(&self, date: NaiveDate) -> DateTime<Utc> {
+        let mut local = date.and_time(self.start);
+        loop {
+            if let Some(open) = self.tz.from_local_datetime(&local).earliest() {
+                return open.with_timezone(&Utc);
+            }
+            local += ChronoDuration::minutes(1);
+        }
+    }
+}
+
+/// Holds back the scan's traffic while it's outside its window, logging each pause
+/// and resume once for every worker waiting on it
+#[derive(Debug)]
+pub struct WindowGate {
+    window: ScanWindow,
+    paused: AtomicBool,
+}
+
+impl WindowGate {
+    pub fn new(window: ScanWindow) -> Self {
+        Self {
+            window,
+            paused: AtomicBool::new(false),
+        }
+    }
+
+    /// Wait until the window is open
+    pub async fn wait(&self) {
+        loop {
+            let now = Utc::now();
+            let Some(wait) = self.window.until_open(now) else {
+                if self.paused.swap(false, Ordering::Relaxed) {
+                    info!("▶️ Scan window {} is open, resuming", self.window);
+                }
+                return;
+            };
+            if !self.paused.swap(true, Ordering::Relaxed) {
+                info!(
+                    "⏸️ Outside scan window {}, pausing until {}",
+                    self.window,
+                    self.window.next_open(now).with_timezone(&self.window.tz)
+                );
+            }
+            tokio::time::sleep(wait.min(RECHECK_INTERVAL)).await;
+        }
+    }
+}
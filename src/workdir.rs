@@ -0,0 +1,208 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::stats::StatsSnapshot;
+use crate::store::StoreKind;
+
+/// A scan's working directory, holding everything the scan produces:
+///
+/// ```text
+/// <workdir>/
+///   results.sqlite   findings, with their evidence, and scan sessions (results/ with
+///                    the JSONL store)
+///   logs/fatt.log    scan log
+///   state.json       progress of the scan, updated while it runs
+///   summary.json     totals, risk and network statistics of the finished scan
+/// ```
+#[derive(Debug, Clone)]
+pub struct Workdir {
+    root: PathBuf,
+}
+
+/// Where a scan is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanStatus {
+    Running,
+    Finished,
+    /// Stopped with Ctrl-C before every domain was scanned
+    Interrupted,
+    Failed,
+}
+
+/// Contents of `state.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanState {
+    /// Scan session ID, once the session has been recorded
+    pub scan_id: Option<String>,
+    pub status: ScanStatus,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub domains_total: usize,
+    pub domains_processed: usize,
+    /// Why the scan failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ScanState {
+    /// State of a scan that has just started
+    pub fn running(scan_id: &str, started_at: DateTime<Utc>, domains_total: usize) -> Self {
+        Self {
+            scan_id: Some(scan_id.to_string()),
+            status: ScanStatus::Running,
+            started_at,
+            updated_at: Utc::now(),
+            domains_total,
+            domains_processed: 0,
+            error: None,
+        }
+    }
+}
+
+/// Contents of `summary.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanSummary {
+    pub scan_id: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub elapsed_secs: f64,
+    pub domains: usize,
+    pub checks: usize,
+    pub findings: usize,
+    pub unexpected: usize,
+    pub risk_score: f64,
+    pub network: StatsSnapshot,
+    /// Whether the scan was stopped before every domain was scanned
+    #[serde(default)]
+    pub incomplete: bool,
+}
+
+impl Workdir {
+    /// Refer to a working directory without touching the filesystem
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Create a working directory and its subdirectories, if they don't exist yet
+    pub fn create<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let workdir = Self::new(root);
+        let logs = workdir.logs_dir();
+        fs::create_dir_all(&logs)
+            .context(format!("Failed to create directory: {}", logs.display()))?;
+
+        Ok(workdir)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Results database, or directory for the JSONL store
+    pub fn results_path(&self, store: StoreKind) -> PathBuf {
+        match store {
+            StoreKind::Sqlite | StoreKind::Postgres => self.root.join("results.sqlite"),
+            StoreKind::Jsonl => self.root.join("results"),
+        }
+    }
+
+    pub fn logs_dir(&self) -> PathBuf {
+        self.root.join("logs")
+    }
+
+    pub fn log_file(&self) -> PathBuf {
+        self.logs_dir().join("fatt.log")
+    }
+
+    pub fn state_file(&self) -> PathBuf {
+        self.root.join("state.json")
+    }
+
+    pub fn summary_file(&self) -> PathBuf {
+        self.root.join("summary.json")
+    }
+
+    /// Read `state.json`, if the scan got far enough to write it
+    pub fn read_state(&self) -> Result<Option<ScanState>> {
+        let path = self.state_file();
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents =
+            fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+        let state = serde_json::from_str(&contents)
+            .context(format!("Failed to parse {}", path.display()))?;
+
+        Ok(Some(state))
+    }
+
+    /// Replace `state.json`
+    pub fn write_state(&self, state: &ScanState) -> Result<()> {
+        write_json(&self.state_file(), state)
+    }
+
+    /// Start a new scan in the directory, replacing the state and removing the summary a
+    /// previous scan left, so a scan failing early isn't taken for the finished one
+    pub fn begin(&self) -> Result<()> {
+        let now = Utc::now();
+        self.write_state(&ScanState {
+            scan_id: None,
+            status: ScanStatus::Running,
+            started_at: now,
+            updated_at: now,
+            domains_total: 0,
+            domains_processed: 0,
+            error: None,
+        })?;
+
+        let summary = self.summary_file();
+        if summary.exists() {
+            fs::remove_file(&summary).context(format!("Failed to remove {}", summary.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Mark the scan as failed, unless it already finished or failed
+    pub fn fail(&self, error: &anyhow::Error) -> Result<()> {
+        let now = Utc::now();
+        let mut state = self.read_state()?.unwrap_or(ScanState {
+            scan_id: None,
+            status: ScanStatus::Running,
+            started_at: now,
+            updated_at: now,
+            domains_total: 0,
+            domains_processed: 0,
+            error: None,
+        });
+        if state.status != ScanStatus::Running {
+            return Ok(());
+        }
+
+        state.status = ScanStatus::Failed;
+        state.updated_at = now;
+        state.error = Some(format!("{:#}", error));
+        self.write_state(&state)
+    }
+
+    /// Replace `summary.json`
+    pub fn write_summary(&self, summary: &ScanSummary) -> Result<()> {
+        write_json(&self.summary_file(), summary)
+    }
+}
+
+/// Write a JSON file through a temporary file, so readers never see it half-written
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value).context("Failed to serialize JSON")?;
+    let temp = path.with_extension("json.tmp");
+
+    fs::write(&temp, json).context(format!("Failed to write {}", temp.display()))?;
+    fs::rename(&temp, path).context(format!("Failed to write {}", path.display()))?;
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use std::thread;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error};
+
+use crate::db::{self, DomainScan};
+use crate::otel;
+use crate::store::{PendingFinding, SharedStore};
+
+/// Most findings written in one transaction
+pub const BATCH_SIZE: usize = 500;
+
+/// Findings waiting to be written before senders wait for the writer to catch up
+const QUEUE_CAPACITY: usize = 10_000;
+
+enum Message {
+    Finding(PendingFinding),
+    Domain(DomainScan),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Writes the outcomes of rule checks to the result store.
+///
+/// Scan tasks hand outcomes over a bounded queue instead of locking the store, and a
+/// dedicated thread writes them in batches, one transaction per batch, so a scan
+/// doesn't wait on each insert. With shards, each has a queue, thread and store of its
+/// own, so they're written in parallel. The threads stop once every handle is dropped.
+#[derive(Debug, Clone)]
+pub struct ResultWriter {
+    queue: mpsc::Sender<Message>,
+    shards: Vec<mpsc::Sender<Message>>,
+}
+
+impl ResultWriter {
+    /// Start writing to `store`
+    pub fn start(store: SharedStore) -> Self {
+        Self::start_sharded(store, Vec::new())
+    }
+
+    /// Start writing to `store`, except for findings, which go to the store of their
+    /// domain's shard in `shards` if there are any
+    pub fn start_sharded(store: SharedStore, shards: Vec<SharedStore>) -> Self {
+        Self {
+            queue: spawn_writer("fatt-writer".to_string(), store),
+            shards: shards
+                .into_iter()
+                .enumerate()
+                .map(|(index, shard)| spawn_writer(format!("fatt-writer-{}", index), shard))
+                .collect(),
+        }
+    }
+
+    /// Queue the outcome of a rule check to be written
+    pub async fn write(&self, finding: PendingFinding) {
+        let queue = match self.shards.len() {
+            0 => &self.queue,
+            shards => &self.shards[db::shard_index(&finding.domain, shards)],
+        };
+        if queue.send(Message::Finding(finding)).await.is_err() {
+            error!("Failed to store finding: result writer stopped");
+        }
+    }
+
+    /// Queue what a scan saw of a domain to be written
+    pub async fn write_domain(&self, scan: DomainScan) {
+        if self.queue.send(Message::Domain(scan)).await.is_err() {
+            error!("Failed to store domain scan: result writer stopped");
+        }
+    }
+
+    /// Wait until every outcome queued so far is written
+    pub async fn flush(&self) {
+        for queue in std::iter::once(&self.queue).chain(&self.shards) {
+            let (done, written) = oneshot::channel();
+            if queue.send(Message::Flush(done)).await.is_ok() {
+                let _ = written.await;
+            }
+        }
+    }
+}
+
+/// Start a thread writing what's queued to `store`, returning its queue
+fn spawn_writer(name: String, store: SharedStore) -> mpsc::Sender<Message> {
+    let (queue, pending) = mpsc::channel(QUEUE_CAPACITY);
+
+    // Writes block, so they're kept off the runtime's threads
+    thread::Builder::new()
+        .name(name)
+        .spawn(move || write_batches(&store, pending))
+        .expect("Failed to start result writer thread");
+
+    queue
+}
+
+/// Write queued findings until the queue closes, taking whatever has piled up since
+/// the last batch as the next one
+fn write_batches(store: &SharedStore, mut pending: mpsc::Receiver<Message>) {
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut domains = Vec::new();
+    let mut flushes = Vec::new();
+
+    while let Some(message) = pending.blocking_recv() {
+        let mut next = Some(message);
+        while let Some(message) = next {
+            match message {
+                Message::Finding(finding) => batch.push(finding),
+                Message::Domain(scan) => domains.push(scan),
+                Message::Flush(done) => flushes.push(done),
+            }
+            next = if batch.len() < BATCH_SIZE {
+                pending.try_recv().ok()
+            } else {
+                None
+            };
+        }
+
+        if !batch.is_empty() {
+            write_batch(store, &batch);
+            batch.clear();
+        }
+        if !domains.is_empty() {
+            let mut store = store.blocking_lock();
+            for scan in domains.drain(..) {
+                if let Err(e) = store.record_domain(&scan) {
+                    error!("Failed to store domain scan: {}", e);
+                }
+            }
+        }
+        for done in flushes.drain(..) {
+            let _ = done.send(());
+        }
+    }
+}
+
+/// Write a batch in one transaction, falling back to one finding at a time if that
+/// fails so one bad finding doesn't lose the rest
+fn write_batch(store: &SharedStore, batch: &[PendingFinding]) {
+    let _span = otel::span!("db_write", findings = batch.len()).entered();
+    let mut store = store.blocking_lock();
+    match store.record_findings(batch) {
+        Ok(()) => debug!("💾 Stored {} findings", batch.len()),
+        Err(e) => {
+            debug!("Failed to store a batch of {} findings: {}", batch.len(), e);
+            for finding in batch {
+                if let Err(e) = store.record_finding(
+                    &finding.domain,
+                    &finding.rule_name,
+                    &finding.matched_path,
+                    finding.detected,
+                    &finding.details(),
+                ) {
+                    error!("Failed to store finding: {}", e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use chrono::Utc;
+use fatt::anomaly;
+use fatt::db;
+use std::collections::{BTreeMap, HashMap};
+use tempfile::tempdir;
+
+fn counts(pairs: &[(&str, usize)]) -> BTreeMap<String, usize> {
+    pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+}
+
+#[test]
+fn test_detect_anomalies() {
+    let baseline: HashMap<String, f64> = [
+        ("Steady".to_string(), 20.0),
+        ("Spike".to_string(), 10.0),
+        ("Drop".to_string(), 50.0),
+        ("Tiny".to_string(), 1.0),
+    ]
+    .into_iter()
+    .collect();
+
+    let current = counts(&[
+        ("Steady", 25),
+        ("Spike", 100),
+        ("Drop", 2),
+        ("Tiny", 3),
+        ("New Noise", 40),
+    ]);
+
+    let anomalies = anomaly::detect_anomalies(&current, &baseline, 10.0, 5);
+    let names: Vec<&str> = anomalies.iter().map(|a| a.rule_name.as_str()).collect();
+
+    assert_eq!(names, vec!["Drop", "New Noise", "Spike"]);
+
+    let spike = anomalies.iter().find(|a| a.rule_name == "Spike").unwrap();
+    assert_eq!(spike.ratio(), 10.0);
+
+    let new_noise = anomalies
+        .iter()
+        .find(|a| a.rule_name == "New Noise")
+        .unwrap();
+    assert!(new_noise.ratio().is_infinite());
+}
+
+#[test]
+fn test_rule_baseline() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("test.sqlite");
+    let conn = db::init_db(db_path.to_str().unwrap())?;
+
+    // No history yet
+    let (scans, _) = db::get_rule_baseline(&conn, "current", 5)?;
+    assert_eq!(scans, 0);
+
+    for (scan_id, detected) in [("scan-1", 4), ("scan-2", 8), ("current", 100)] {
+        db::start_scan_session(&conn, scan_id, Utc::now())?;
+        db::record_rule_counts(&conn, scan_id, &counts(&[("Rule", detected)]))?;
+    }
+
+    // The current scan is excluded from its own baseline
+    let (scans, baseline) = db::get_rule_baseline(&conn, "current", 5)?;
+    assert_eq!(scans, 2);
+    assert_eq!(baseline["Rule"], 6.0);
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use fatt::brute::{self, DnsBrute};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+fn ip(ip: &str) -> IpAddr {
+    ip.parse().unwrap()
+}
+
+#[test]
+fn test_is_apex() {
+    assert!(brute::is_apex("example.com"));
+    assert!(brute::is_apex("Example.COM"));
+    assert!(brute::is_apex("example.co.uk"));
+
+    assert!(!brute::is_apex("www.example.com"));
+    assert!(!brute::is_apex("api.example.co.uk"));
+    assert!(!brute::is_apex("co.uk"));
+    assert!(!brute::is_apex("localhost"));
+    assert!(!brute::is_apex("example.com:8443"));
+    assert!(!brute::is_apex("192.0.2.1"));
+    assert!(!brute::is_apex("[2001:db8::1]"));
+}
+
+#[tokio::test]
+async fn test_discover_subdomains() {
+    let records: HashMap<String, IpAddr> = [
+        ("www.example.com", "192.0.2.10"),
+        ("api.example.com", "192.0.2.11"),
+        ("staging.example.com", "192.0.2.12"),
+        ("www.other.com", "192.0.2.20"),
+    ]
+    .into_iter()
+    .map(|(host, addr)| (host.to_string(), ip(addr)))
+    .collect();
+
+    let brute = DnsBrute::new(10_000);
+    let found = brute
+        .discover("Example.com", |host| {
+            let ips = records.get(&host).copied().into_iter().collect();
+            async move { ips }
+        })
+        .await;
+
+    assert_eq!(
+        found,
+        vec!["api.example.com", "staging.example.com", "www.example.com"]
+    );
+}
+
+#[tokio::test]
+async fn test_discover_skips_wildcard_dns() {
+    // Every name resolves to the wildcard address, and one also to its own
+    let brute = DnsBrute::new(10_000);
+    let found = brute
+        .discover("wild.com", |host| async move {
+            let mut ips = vec![ip("198.51.100.1")];
+            if host == "vpn.wild.com" {
+                ips.push(ip("198.51.100.2"));
+            }
+            ips
+        })
+        .await;
+
+    assert_eq!(found, vec!["vpn.wild.com"]);
+}
@@ -0,0 +1,120 @@
+use anyhow::Result;
+use fatt::canary::{self, Canaries, Canary};
+use fatt::db;
+use fatt::rules::{Rule, RuleSet, Severity};
+use fatt::scanner::{self, ScanOptions, ScanResources};
+use std::io::Write;
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+use tokio::sync::Mutex;
+use wiremock::matchers::path;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn ruleset() -> RuleSet {
+    RuleSet {
+        rules: vec![
+            Rule::new("Env", "/.env", "APP_KEY=", "desc", Severity::High),
+            Rule::new("Git", "/.git/HEAD", "ref:", "desc", Severity::Medium),
+        ],
+    }
+}
+
+fn canary(domain: &str, expect: &[&str], absent: &[&str]) -> Canary {
+    Canary {
+        domain: domain.to_string(),
+        expect: expect.iter().map(|name| name.to_string()).collect(),
+        absent: absent.iter().map(|name| name.to_string()).collect(),
+    }
+}
+
+async fn setup() -> Result<(MockServer, ScanResources)> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.env"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("APP_KEY=secret"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(path("/.git/HEAD"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_server)
+        .await;
+
+    let resources = ScanResources::new(
+        scanner::create_http_client(5, 2)?,
+        ruleset(),
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
+        Arc::new(Mutex::new(db::init_db(":memory:")?)),
+        ScanOptions::default(),
+    );
+
+    Ok((mock_server, resources))
+}
+
+#[tokio::test]
+async fn test_canaries_as_expected() -> Result<()> {
+    let (mock_server, resources) = setup().await?;
+    let hostname = mock_server.uri().replace("http://", "");
+
+    let canaries = Canaries {
+        canaries: vec![canary(&hostname, &["Env"], &["Git"])],
+    };
+    assert!(canary::check_canaries(&canaries, &resources)
+        .await
+        .is_empty());
+    canary::preflight(&canaries, &resources).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_canaries_detect_misconfiguration() -> Result<()> {
+    let (mock_server, resources) = setup().await?;
+    let hostname = mock_server.uri().replace("http://", "");
+
+    let canaries = Canaries {
+        canaries: vec![
+            // An expected finding that doesn't show up
+            canary(&hostname, &["Git"], &[]),
+            // A finding on a domain that should be clean
+            canary(&hostname, &[], &[]),
+        ],
+    };
+    let problems = canary::check_canaries(&canaries, &resources).await;
+    assert_eq!(problems.len(), 2);
+    assert!(problems[0].contains("Git should detect /.git/HEAD"));
+    assert!(problems[1].contains("Env detected /.env, which should be absent"));
+
+    let error = canary::preflight(&canaries, &resources).await.unwrap_err();
+    assert!(error.is::<canary::CanaryCheckFailed>());
+
+    // Canaries aren't recorded as findings
+    let findings = resources.store.lock().await.findings()?;
+    assert!(findings.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_canaries_from_file() -> Result<()> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(
+        file,
+        "canaries:\n  - domain: canary.example.com\n    expect: [Env]\n    absent: [Git]\n  - domain: clean.example.com"
+    )?;
+
+    let canaries = canary::load_canaries(file.path().to_str().unwrap())?;
+    assert_eq!(canaries.canaries.len(), 2);
+    assert_eq!(canaries.canaries[0].expect, vec!["Env"]);
+    assert!(canaries.canaries[1].absent.is_empty());
+    canaries.validate(&ruleset())?;
+
+    let unknown = Canaries {
+        canaries: vec![canary("canary.example.com", &["Backup"], &[])],
+    };
+    assert!(unknown.validate(&ruleset()).is_err());
+
+    let mut empty = NamedTempFile::new()?;
+    writeln!(empty, "canaries: []")?;
+    assert!(canary::load_canaries(empty.path().to_str().unwrap()).is_err());
+
+    Ok(())
+}
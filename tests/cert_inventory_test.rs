@@ -0,0 +1,121 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use fatt::cert_inventory::SiteCertificate;
+use fatt::db::{self, DomainScan};
+use fatt::store::{JsonlStore, ResultStore};
+use tempfile::tempdir;
+
+/// Self-signed certificate for example.com, valid through 2026
+const EXAMPLE_CERT: &[u8] = include_bytes!("data/example.com.der");
+
+fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap()
+}
+
+fn domain_scan(scan_id: &str, certificate: Option<SiteCertificate>) -> DomainScan {
+    DomainScan {
+        scan_id: scan_id.to_string(),
+        domain: "example.com".to_string(),
+        scanned_at: Utc::now(),
+        ips: Vec::new(),
+        technologies: Vec::new(),
+        error: None,
+        certificate,
+    }
+}
+
+#[test]
+fn test_parse_certificate() -> Result<()> {
+    let seen_at = at(2026, 6, 1);
+    let certificate = SiteCertificate::parse("example.com", EXAMPLE_CERT, seen_at)?;
+
+    assert_eq!(certificate.host, "example.com");
+    assert_eq!(certificate.subject, "CN=example.com, O=Example");
+    assert_eq!(certificate.issuer, "CN=example.com, O=Example");
+    assert_eq!(
+        certificate.sans,
+        vec!["example.com", "www.example.com", "192.0.2.10"]
+    );
+    assert!(!certificate.serial.is_empty());
+    assert_eq!(certificate.not_before, at(2025, 1, 1));
+    assert_eq!(certificate.not_after, at(2027, 1, 1));
+    assert_eq!(certificate.seen_at, seen_at);
+
+    assert!(SiteCertificate::parse("example.com", b"not a certificate", seen_at).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_expires_within() -> Result<()> {
+    let certificate = SiteCertificate::parse("example.com", EXAMPLE_CERT, Utc::now())?;
+
+    assert!(certificate.expires_within(Duration::days(30), at(2026, 12, 15)));
+    assert!(!certificate.expires_within(Duration::days(30), at(2026, 11, 15)));
+    // Already expired
+    assert!(certificate.expires_within(Duration::days(30), at(2027, 2, 1)));
+    assert_eq!(certificate.remaining(at(2026, 12, 1)).num_days(), 31);
+
+    Ok(())
+}
+
+#[test]
+fn test_sqlite_certificates() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let path = temp_dir.path().join("results.sqlite");
+    let mut conn = db::init_db(path.to_str().unwrap())?;
+
+    let first = SiteCertificate::parse("example.com", EXAMPLE_CERT, at(2026, 1, 1))?;
+    conn.record_domain(&domain_scan("scan-1", Some(first)))?;
+    // Scans that didn't get a certificate leave the last one in place
+    conn.record_domain(&domain_scan("scan-2", None))?;
+    let certificates = conn.certificates()?;
+    assert_eq!(certificates.len(), 1);
+    assert_eq!(certificates[0].seen_at, at(2026, 1, 1));
+
+    // One certificate is kept per host, the one seen last
+    let second = SiteCertificate::parse("example.com", EXAMPLE_CERT, at(2026, 2, 1))?;
+    conn.record_domain(&domain_scan("scan-3", Some(second.clone())))?;
+    assert_eq!(conn.certificates()?, vec![second]);
+
+    Ok(())
+}
+
+#[test]
+fn test_jsonl_certificates() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut store = JsonlStore::open(temp_dir.path())?;
+
+    let first = SiteCertificate::parse("example.com", EXAMPLE_CERT, at(2026, 1, 1))?;
+    let second = SiteCertificate::parse("example.com", EXAMPLE_CERT, at(2026, 2, 1))?;
+    store.start_scan("scan-1", at(2026, 1, 1))?;
+    store.record_domain(&domain_scan("scan-1", Some(first)))?;
+    store.start_scan("scan-2", at(2026, 2, 1))?;
+    store.record_domain(&domain_scan("scan-2", Some(second.clone())))?;
+
+    assert_eq!(store.certificates()?, vec![second]);
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_certificates() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let path = |name: &str| temp_dir.path().join(name).to_str().unwrap().to_string();
+
+    let newer = SiteCertificate::parse("example.com", EXAMPLE_CERT, at(2026, 2, 1))?;
+    let older = SiteCertificate::parse("example.com", EXAMPLE_CERT, at(2026, 1, 1))?;
+    db::record_certificate(&db::init_db(&path("a.sqlite"))?, &newer)?;
+    db::record_certificate(&db::init_db(&path("b.sqlite"))?, &older)?;
+
+    db::merge_databases(
+        &path("merged.sqlite"),
+        &[path("a.sqlite"), path("b.sqlite")],
+    )?;
+    assert_eq!(
+        db::get_certificates(&db::init_db(&path("merged.sqlite"))?)?,
+        vec![newer]
+    );
+
+    Ok(())
+}
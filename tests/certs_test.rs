@@ -0,0 +1,269 @@
+#![cfg(feature = "distributed")]
+
+use anyhow::Result;
+use fatt::certs::{CertAuthority, WorkerCertFiles, DEFAULT_VALIDITY_DAYS};
+use fatt::config::ScanConfig;
+use fatt::distributed::{self, MasterConfig, MasterTls, WorkerConfig, WorkerTls};
+use fatt::utils::RetryPolicy;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::tempdir;
+use tokio::net::TcpListener;
+
+fn cert_der(files: &WorkerCertFiles) -> Vec<u8> {
+    CertificateDer::from_pem_file(&files.cert).unwrap().to_vec()
+}
+
+fn worker_config(
+    worker_id: &str,
+    master: &str,
+    ca: &CertAuthority,
+    files: &WorkerCertFiles,
+) -> WorkerConfig {
+    WorkerConfig {
+        worker_id: worker_id.to_string(),
+        master: master.to_string(),
+        concurrency: 1,
+        reconnect: RetryPolicy::default(),
+        scan: ScanConfig::default(),
+        tls: Some(WorkerTls {
+            ca_cert: ca.cert_path(),
+            cert: Some(files.cert.clone()),
+            key: Some(files.key.clone()),
+            server_name: None,
+        }),
+    }
+}
+
+/// Start a master requiring certificates from `ca`, returning its address
+async fn start_master(ca: &CertAuthority) -> Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = format!("localhost:{}", listener.local_addr()?.port());
+    let config = MasterConfig {
+        listen: addr.clone(),
+        ca: Some(ca.clone()),
+        server_names: vec!["localhost".to_string()],
+        tls: None,
+        stream_listen: None,
+        stream_token: None,
+        scan: None,
+    };
+    tokio::spawn(async move { distributed::serve_master(listener, &config, None).await });
+
+    Ok(addr)
+}
+
+#[test]
+fn test_issue_and_verify_worker_certificate() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let ca = CertAuthority::init(&temp_dir.path().join("ca"))?;
+
+    let files = ca.issue("worker-1", DEFAULT_VALIDITY_DAYS, temp_dir.path())?;
+    assert_eq!(files.cert, temp_dir.path().join("worker-1.pem"));
+    assert!(files.key.exists());
+
+    let issued = ca.issued()?;
+    assert_eq!(issued.len(), 1);
+    assert_eq!(issued[0].worker_id, "worker-1");
+    assert!(issued[0].revoked_at.is_none());
+
+    ca.verify_worker(&cert_der(&files), "worker-1")?;
+
+    // Initialising again keeps the existing CA and its registry
+    let reopened = CertAuthority::init(&temp_dir.path().join("ca"))?;
+    assert_eq!(reopened.issued()?, issued);
+
+    Ok(())
+}
+
+#[test]
+fn test_reject_certificate_of_another_worker() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let ca = CertAuthority::init(&temp_dir.path().join("ca"))?;
+    let files = ca.issue("worker-1", DEFAULT_VALIDITY_DAYS, temp_dir.path())?;
+
+    let err = ca.verify_worker(&cert_der(&files), "worker-2").unwrap_err();
+    assert!(err.to_string().contains("presented by worker worker-2"));
+
+    Ok(())
+}
+
+#[test]
+fn test_revoke_one_worker() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let ca = CertAuthority::init(&temp_dir.path().join("ca"))?;
+    let compromised = ca.issue("worker-1", DEFAULT_VALIDITY_DAYS, temp_dir.path())?;
+    let other = ca.issue("worker-2", DEFAULT_VALIDITY_DAYS, temp_dir.path())?;
+
+    assert_eq!(ca.revoke("worker-1")?, 1);
+    assert_eq!(ca.revoke("worker-1")?, 0);
+
+    let err = ca
+        .verify_worker(&cert_der(&compromised), "worker-1")
+        .unwrap_err();
+    assert!(err.to_string().contains("revoked"));
+    ca.verify_worker(&cert_der(&other), "worker-2")?;
+
+    // A new certificate brings the worker back
+    let reissued = ca.issue("worker-1", DEFAULT_VALIDITY_DAYS, temp_dir.path())?;
+    ca.verify_worker(&cert_der(&reissued), "worker-1")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_reject_certificate_unknown_to_registry() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let ca = CertAuthority::init(&temp_dir.path().join("ca"))?;
+    let other_ca = CertAuthority::init(&temp_dir.path().join("other-ca"))?;
+    let files = other_ca.issue("worker-1", DEFAULT_VALIDITY_DAYS, temp_dir.path())?;
+
+    let err = ca.verify_worker(&cert_der(&files), "worker-1").unwrap_err();
+    assert!(err.to_string().contains("not issued by this CA"));
+
+    Ok(())
+}
+
+#[test]
+fn test_reject_invalid_worker_id() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let ca = CertAuthority::init(&temp_dir.path().join("ca"))?;
+
+    assert!(ca
+        .issue("../worker", DEFAULT_VALIDITY_DAYS, temp_dir.path())
+        .is_err());
+    assert!(CertAuthority::open(Path::new("/nonexistent/ca")).is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_master_registers_only_workers_with_valid_certificates() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let ca = CertAuthority::init(&temp_dir.path().join("ca"))?;
+    let trusted = ca.issue("tls-worker-ok", DEFAULT_VALIDITY_DAYS, temp_dir.path())?;
+    let revoked = ca.issue("tls-worker-revoked", DEFAULT_VALIDITY_DAYS, temp_dir.path())?;
+    ca.revoke("tls-worker-revoked")?;
+    let master = start_master(&ca).await?;
+
+    // The trusted worker registers and keeps waiting for work
+    let config = worker_config("tls-worker-ok", &master, &ca, &trusted);
+    let worker = tokio::spawn(async move { distributed::start_worker(&config).await });
+    let mut registered = false;
+    for _ in 0..50 {
+        if distributed::connected_workers()
+            .await
+            .contains(&"tls-worker-ok".to_string())
+        {
+            registered = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(registered);
+    worker.abort();
+
+    // The revoked worker, and a worker claiming another's certificate, are dropped
+    for (worker_id, files) in [
+        ("tls-worker-revoked", &revoked),
+        ("tls-worker-impostor", &trusted),
+    ] {
+        let config = worker_config(worker_id, &master, &ca, files);
+        let result =
+            tokio::time::timeout(Duration::from_secs(10), distributed::start_worker(&config))
+                .await?;
+        assert!(result.is_err());
+        assert!(!distributed::connected_workers()
+            .await
+            .contains(&worker_id.to_string()));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_master_with_its_own_certificate() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let cert_path = temp_dir.path().join("master.pem");
+    let key_path = temp_dir.path().join("master-key.pem");
+    let master_cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])?;
+    std::fs::write(&cert_path, master_cert.cert.pem())?;
+    std::fs::write(&key_path, master_cert.key_pair.serialize_pem())?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let master = format!("localhost:{}", listener.local_addr()?.port());
+    let config = MasterConfig {
+        listen: master.clone(),
+        ca: None,
+        server_names: Vec::new(),
+        tls: Some(MasterTls {
+            cert: cert_path.clone(),
+            key: key_path,
+        }),
+        stream_listen: None,
+        stream_token: None,
+        scan: None,
+    };
+    tokio::spawn(async move { distributed::serve_master(listener, &config, None).await });
+
+    // A worker that trusts the certificate registers over TLS without one of its own
+    let worker_config = |worker_id: &str, tls: Option<WorkerTls>| WorkerConfig {
+        worker_id: worker_id.to_string(),
+        master: master.clone(),
+        concurrency: 1,
+        reconnect: RetryPolicy::default(),
+        scan: ScanConfig::default(),
+        tls,
+    };
+    let trusting = worker_config(
+        "tls-worker-anonymous",
+        Some(WorkerTls {
+            ca_cert: cert_path,
+            cert: None,
+            key: None,
+            server_name: None,
+        }),
+    );
+    let worker = tokio::spawn(async move { distributed::start_worker(&trusting).await });
+    let mut registered = false;
+    for _ in 0..50 {
+        if distributed::connected_workers()
+            .await
+            .contains(&"tls-worker-anonymous".to_string())
+        {
+            registered = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(registered);
+    worker.abort();
+
+    // Plain TCP, or a certificate from a CA the worker doesn't trust, gets nowhere
+    let ca = CertAuthority::init(&temp_dir.path().join("ca"))?;
+    for (worker_id, tls) in [
+        ("tcp-worker", None),
+        (
+            "tls-worker-distrusting",
+            Some(WorkerTls {
+                ca_cert: ca.cert_path(),
+                cert: None,
+                key: None,
+                server_name: None,
+            }),
+        ),
+    ] {
+        let config = worker_config(worker_id, tls);
+        let result =
+            tokio::time::timeout(Duration::from_secs(10), distributed::start_worker(&config))
+                .await?;
+        assert!(result.is_err());
+        assert!(!distributed::connected_workers()
+            .await
+            .contains(&worker_id.to_string()));
+    }
+
+    Ok(())
+}
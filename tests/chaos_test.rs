@@ -0,0 +1,128 @@
+#![cfg(feature = "chaos")]
+
+use anyhow::Result;
+use fatt::chaos::{self, ChaosPolicy};
+use fatt::config::ScanConfig;
+use fatt::distributed::{self, WorkerConfig, WorkerMessage, WorkerStatus};
+use fatt::utils::RetryPolicy;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+const BATCHES: usize = 20;
+
+/// Longest the master waits for a result before giving up on the connection
+const RESULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn batches() -> BTreeMap<String, Vec<String>> {
+    (0..BATCHES)
+        .map(|i| {
+            (
+                format!("batch-{}", i),
+                vec![format!("host{}.example.com", i)],
+            )
+        })
+        .collect()
+}
+
+/// Keep a worker connected to `master`, reconnecting whenever its connection fails,
+/// the way a supervisor restarts it
+fn supervise_worker(master: String) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let config = WorkerConfig {
+            worker_id: "chaos-worker".to_string(),
+            master,
+            concurrency: 1,
+            tls: None,
+            reconnect: RetryPolicy::default(),
+            scan: ScanConfig::default(),
+        };
+        loop {
+            if distributed::start_worker(&config).await.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+}
+
+/// Hand out batches to whichever connection the worker comes back on, resending every
+/// batch without a result until all have one
+async fn dispatch(listener: &TcpListener, batches: &BTreeMap<String, Vec<String>>) {
+    let mut pending = batches.clone();
+
+    while !pending.is_empty() {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (mut reader, mut writer) = socket.into_split();
+
+        match distributed::read_message(&mut reader).await {
+            Ok(WorkerMessage::Register { .. }) => {}
+            _ => continue,
+        }
+
+        let heartbeat = WorkerMessage::Heartbeat {
+            worker_id: "chaos-worker".to_string(),
+            status: WorkerStatus::default(),
+        };
+        if distributed::write_message(&mut writer, &heartbeat)
+            .await
+            .is_err()
+        {
+            continue;
+        }
+        for (batch_id, domains) in &pending {
+            let request = WorkerMessage::ScanRequest {
+                domains: domains.clone(),
+                batch_id: batch_id.clone(),
+                rules: Vec::new(),
+            };
+            if distributed::write_message(&mut writer, &request)
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+
+        // A dropped, corrupted or stalled connection leaves its batches pending
+        while !pending.is_empty() {
+            match tokio::time::timeout(RESULT_TIMEOUT, distributed::read_message(&mut reader)).await
+            {
+                Ok(Ok(WorkerMessage::ScanResult { batch_id, .. })) => {
+                    // A corrupted message is never taken for another batch
+                    assert!(
+                        batches.contains_key(&batch_id),
+                        "Unknown batch {}",
+                        batch_id
+                    );
+                    pending.remove(&batch_id);
+                }
+                Ok(Ok(_)) => {}
+                _ => break,
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_batches_survive_chaos() -> Result<()> {
+    chaos::install(ChaosPolicy {
+        disconnect: 0.05,
+        corrupt: 0.05,
+        heartbeat_delay: Duration::from_millis(200),
+        seed: 4812,
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let worker = supervise_worker(listener.local_addr()?.to_string());
+
+    let batches = batches();
+    tokio::time::timeout(Duration::from_secs(60), dispatch(&listener, &batches))
+        .await
+        .expect("Batches were lost under chaos");
+
+    chaos::clear();
+    worker.abort();
+
+    Ok(())
+}
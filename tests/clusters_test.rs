@@ -0,0 +1,104 @@
+use anyhow::Result;
+use fatt::clusters;
+use fatt::db;
+use fatt::evidence::Evidence;
+use fatt::store::FindingDetails;
+use rusqlite::Connection;
+use tempfile::tempdir;
+
+const PARKED: &[u8] = b"<html>This domain may be for sale</html>";
+
+fn detect(conn: &Connection, domain: &str, rule: &str, body: &[u8]) -> Result<()> {
+    let evidence = Evidence::capture(200, body, 0);
+    let details = FindingDetails {
+        evidence: Some(&evidence),
+        ..Default::default()
+    };
+    db::upsert_finding(conn, domain, rule, "/", true, &details)
+}
+
+#[test]
+fn test_find_clusters() -> Result<()> {
+    let conn = db::init_db(":memory:")?;
+    for domain in ["a.example.com", "b.example.com", "c.example.com"] {
+        detect(&conn, domain, "Env", PARKED)?;
+    }
+    detect(&conn, "a.example.com", "Git", PARKED)?;
+    detect(&conn, "b.example.com", "Git", PARKED)?;
+    detect(&conn, "d.example.com", "Env", b"DB_PASSWORD=hunter2")?;
+    // Only partly read, so its body may not be the parked page at all
+    let partial = Evidence::capture(200, PARKED, 0).with_truncated_body();
+    let details = FindingDetails {
+        evidence: Some(&partial),
+        ..Default::default()
+    };
+    db::upsert_finding(&conn, "f.example.com", "Env", "/", true, &details)?;
+    // Not detected, so not part of any cluster
+    db::insert_finding(&conn, "e.example.com", "Env", "/.env", false)?;
+
+    let findings = db::get_all_findings(&conn)?;
+    let found = clusters::find_clusters(&findings, clusters::DEFAULT_MIN_SIZE);
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].rule_name, "Env");
+    assert_eq!(
+        found[0].domains,
+        ["a.example.com", "b.example.com", "c.example.com"]
+    );
+    assert_eq!(found[0].id.len(), 12);
+    assert!(found[0].body_hash.starts_with(&found[0].id));
+    assert_eq!(found[1].rule_name, "Git");
+    assert_eq!(found[1].body_hash, found[0].body_hash);
+
+    // A lone response is a cluster of one
+    assert_eq!(clusters::find_clusters(&findings, 1).len(), 3);
+    assert!(clusters::find_clusters(&findings, 4).is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_dismiss_cluster() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_file = db_path.to_str().unwrap();
+
+    for i in 0..2 {
+        let conn = db::init_db(&db::shard_path(db_file, i))?;
+        detect(&conn, &format!("a.shard{}.example.com", i), "Env", PARKED)?;
+        detect(&conn, &format!("b.shard{}.example.com", i), "Git", PARKED)?;
+        detect(
+            &conn,
+            &format!("c.shard{}.example.com", i),
+            "Env",
+            b"DB_PASSWORD=x",
+        )?;
+    }
+    let conn = db::init_db(&db::shard_path(db_file, 0))?;
+    let id = clusters::find_clusters(&db::get_all_findings(&conn)?, 1)
+        .into_iter()
+        .find(|cluster| cluster.rule_name == "Git")
+        .unwrap()
+        .id;
+
+    assert!(clusters::dismiss_cluster(db_file, 2, "zz", None, false).is_err());
+    assert!(clusters::dismiss_cluster(db_file, 2, "", None, false).is_err());
+
+    assert_eq!(
+        clusters::dismiss_cluster(db_file, 2, &id, Some("Env"), true)?,
+        2
+    );
+    let mut dismissed = Vec::new();
+    for i in 0..2 {
+        let conn = db::init_db(&db::shard_path(db_file, i))?;
+        dismissed.extend(db::get_false_positives(&conn)?);
+    }
+    assert_eq!(dismissed.len(), 2);
+    assert!(dismissed
+        .iter()
+        .all(|fp| fp.rule_name == "Env" && fp.skip_scans && fp.domain.starts_with("a.")));
+
+    // Without a rule, the cluster covers every rule that returned the body
+    assert_eq!(clusters::dismiss_cluster(db_file, 2, &id, None, false)?, 4);
+
+    Ok(())
+}
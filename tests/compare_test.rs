@@ -0,0 +1,100 @@
+use fatt::compare::{self, FieldMapping};
+use fatt::db::Finding;
+mod test_helpers;
+
+/// A finding at a path of its own
+fn finding(domain: &str, rule_name: &str, matched_path: &str, detected: bool) -> Finding {
+    Finding {
+        matched_path: matched_path.to_string(),
+        ..test_helpers::finding(domain, rule_name, detected)
+    }
+}
+
+const NUCLEI_JSONL: &str = r#"
+{"template-id":"git-config","info":{"name":"Git Config Disclosure","severity":"medium"},"host":"https://a.example.com","matched-at":"https://a.example.com/.git/config"}
+{"template-id":"env-file","info":{"name":"Env File","severity":"high"},"host":"b.example.com:443","matched-at":"https://B.example.com:443/.env/"}
+{"template-id":"phpinfo","info":{"name":"phpinfo","severity":"low"},"host":"c.example.com","matched-at":"http://c.example.com/phpinfo.php"}
+"#;
+
+#[test]
+fn test_parse_nuclei_output() {
+    let external = compare::parse_external(NUCLEI_JSONL, &FieldMapping::nuclei()).unwrap();
+    assert_eq!(external.len(), 3);
+    assert_eq!(external[1].domain, "b.example.com");
+    assert_eq!(external[1].path, "/.env");
+    assert_eq!(external[1].name.as_deref(), Some("env-file"));
+
+    // A JSON array, as written by -json-export, reads the same
+    let array = format!(
+        "[{}]",
+        NUCLEI_JSONL.trim().lines().collect::<Vec<_>>().join(",")
+    );
+    assert_eq!(
+        compare::parse_external(&array, &FieldMapping::nuclei()).unwrap(),
+        external
+    );
+
+    assert!(compare::parse_external("{not json", &FieldMapping::nuclei()).is_err());
+}
+
+#[test]
+fn test_parse_with_a_custom_mapping() {
+    let mapping = FieldMapping {
+        domain: "target.host".to_string(),
+        path: "target.path".to_string(),
+        name: Some("check".to_string()),
+    };
+    let external = compare::parse_external(
+        r#"[
+            {"check": "exposed-env", "target": {"host": "a.example.com:8443", "path": ".env"}},
+            {"check": "no-path", "target": {"host": "a.example.com"}}
+        ]"#,
+        &mapping,
+    )
+    .unwrap();
+
+    // Entries without a path are skipped
+    assert_eq!(external.len(), 1);
+    assert_eq!(external[0].domain, "a.example.com");
+    assert_eq!(external[0].path, "/.env");
+}
+
+#[test]
+fn test_compare_findings() {
+    let external = compare::parse_external(NUCLEI_JSONL, &FieldMapping::nuclei()).unwrap();
+    let findings = vec![
+        finding("a.example.com", "Git Config", "/.git/config", true),
+        // A full URL, as kept on a domain with several targets
+        finding(
+            "b.example.com",
+            "Env File",
+            "https://b.example.com:8443/.env",
+            true,
+        ),
+        finding("d.example.com", "Backup", "/backup.zip", true),
+        // Not detected, so not compared
+        finding("c.example.com", "phpinfo", "/phpinfo.php", false),
+    ];
+
+    let comparison = compare::compare_findings(&findings, &external);
+    let both: Vec<_> = comparison
+        .both
+        .iter()
+        .map(|shared| (shared.domain.as_str(), shared.path.as_str()))
+        .collect();
+    assert_eq!(
+        both,
+        vec![
+            ("a.example.com", "/.git/config"),
+            ("b.example.com", "/.env")
+        ]
+    );
+    assert_eq!(comparison.both[0].rules, vec!["Git Config"]);
+    assert_eq!(comparison.both[0].other, vec!["git-config"]);
+
+    assert_eq!(comparison.fatt_only.len(), 1);
+    assert_eq!(comparison.fatt_only[0].domain, "d.example.com");
+    assert_eq!(comparison.other_only.len(), 1);
+    assert_eq!(comparison.other_only[0].path, "/phpinfo.php");
+    assert!((comparison.overlap() - 0.5).abs() < f64::EPSILON);
+}
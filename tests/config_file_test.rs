@@ -0,0 +1,179 @@
+use anyhow::Result;
+use clap::{Arg, ArgAction, Command};
+use fatt::config_file::{self, EffectiveConfig, Source};
+use std::fs;
+use tempfile::tempdir;
+
+const CONFIG: &str = r#"
+input = "domains.txt"
+rules = ["rules.yaml", "extra.yaml"]
+concurrency = 50
+schemes = ["http", "https"]
+
+[profiles.prod]
+concurrency = 200
+timeout = 30
+"#;
+
+#[test]
+fn test_layer_precedence_and_sources() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let path = temp_dir.path().join("fatt.toml");
+    fs::write(&path, CONFIG)?;
+    let path = path.to_str().unwrap();
+
+    let effective = EffectiveConfig::load(
+        Some(path),
+        Some("prod"),
+        &[
+            "timeout=45".to_string(),
+            "ports=8080,8443".to_string(),
+            "interesting_status=401,403".to_string(),
+            "throttle_subnet=4".to_string(),
+        ],
+    )?;
+    let config = &effective.config;
+
+    assert_eq!(config.concurrency, 200);
+    assert_eq!(config.http_timeout, 45);
+    assert_eq!(config.ports, vec![8080, 8443]);
+    assert_eq!(config.interesting_status, vec![401, 403]);
+    assert_eq!(config.throttle_subnet, 4);
+    assert_eq!(config.schemes, vec!["http", "https"]);
+    assert_eq!(
+        config.input_file,
+        temp_dir.path().join("domains.txt").to_string_lossy()
+    );
+    assert_eq!(
+        config.extra_rules_files,
+        vec![temp_dir.path().join("extra.yaml").to_string_lossy()]
+    );
+
+    assert_eq!(effective.source("schemes"), &Source::File(path.to_string()));
+    assert_eq!(
+        effective.source("concurrency"),
+        &Source::Profile("prod".to_string())
+    );
+    assert_eq!(effective.source("timeout"), &Source::Flag);
+    assert_eq!(effective.source("circuit_breaker"), &Source::Default);
+
+    Ok(())
+}
+
+#[test]
+fn test_empty_interesting_status_reports_successes_only() -> Result<()> {
+    let effective = EffectiveConfig::load(None, None, &["interesting_status=".to_string()])?;
+    assert!(effective.config.interesting_status.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_unknown_keys_are_rejected() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let path = temp_dir.path().join("fatt.toml");
+    let path_str = path.to_str().unwrap();
+
+    fs::write(&path, "concurency = 50\n")?;
+    let err = EffectiveConfig::load(Some(path_str), None, &[]).unwrap_err();
+    assert!(format!("{:#}", err).contains("Unknown config key concurency"));
+
+    fs::write(&path, "[profiles.prod]\nretrys = 3\n")?;
+    assert!(EffectiveConfig::load(Some(path_str), Some("prod"), &[]).is_err());
+    assert!(EffectiveConfig::load(Some(path_str), Some("staging"), &[]).is_err());
+
+    assert!(EffectiveConfig::load(None, None, &["schemas=https".to_string()]).is_err());
+    assert!(EffectiveConfig::load(None, None, &["concurrency=lots".to_string()]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_env_overrides_and_secret_masking() -> Result<()> {
+    std::env::set_var("FATT_RETRIES", "7");
+    std::env::set_var("FATT_PROXY_AUTH", "scanner:hunter2");
+
+    let effective = EffectiveConfig::load(None, None, &[])?;
+    assert_eq!(effective.config.retries, 7);
+    assert_eq!(
+        effective.source("retries"),
+        &Source::Env("FATT_RETRIES".to_string())
+    );
+    assert_eq!(
+        effective.config.proxy_auth.as_deref(),
+        Some("scanner:hunter2")
+    );
+
+    let values = effective.values();
+    let (_, proxy_auth, _) = values
+        .iter()
+        .find(|(key, _, _)| *key == "proxy_auth")
+        .unwrap();
+    assert_eq!(proxy_auth, "********");
+
+    // Flags take precedence over the environment
+    let effective = EffectiveConfig::load(None, None, &["retries=1".to_string()])?;
+    assert_eq!(effective.config.retries, 1);
+
+    std::env::remove_var("FATT_RETRIES");
+    std::env::remove_var("FATT_PROXY_AUTH");
+
+    Ok(())
+}
+
+#[test]
+fn test_command_line_overrides_every_layer() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let path = temp_dir.path().join("fatt.toml");
+    fs::write(&path, CONFIG)?;
+    let path = path.to_str().unwrap();
+
+    let command = Command::new("scan")
+        .arg(Arg::new("input").long("input").action(ArgAction::Append))
+        .arg(Arg::new("concurrency").long("concurrency"))
+        .arg(Arg::new("timeout").long("timeout").default_value("10"))
+        .arg(
+            Arg::new("dns_brute")
+                .long("dns-brute")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("ports")
+                .long("ports")
+                .value_delimiter(',')
+                .action(ArgAction::Append),
+        );
+    let matches = command.try_get_matches_from([
+        "scan",
+        "--input",
+        "a.txt",
+        "--concurrency",
+        "7",
+        "--dns-brute",
+        "--ports",
+        "80,443",
+    ])?;
+
+    let effective = EffectiveConfig::load_with(
+        Some(path),
+        Some("prod"),
+        &["concurrency=3".to_string(), "timeout=45".to_string()],
+        config_file::command_line(&matches),
+    )?;
+    let config = &effective.config;
+
+    assert_eq!(config.concurrency, 7);
+    assert_eq!(effective.source("concurrency"), &Source::CommandLine);
+    // Left at its default, so it doesn't override --set
+    assert_eq!(config.http_timeout, 45);
+    assert!(config.dns_brute);
+    assert_eq!(config.ports, vec![80, 443]);
+    assert_eq!(config.input_file, "a.txt");
+    assert_eq!(config.schemes, vec!["http", "https"]);
+
+    // Without any configuration, a scan runs with the normal profile's tuning
+    let effective = EffectiveConfig::load(None, None, &[])?;
+    assert_eq!(effective.config.concurrency, 100);
+    assert_eq!(effective.config.profile, None);
+
+    Ok(())
+}
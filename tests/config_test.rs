@@ -1,6 +1,7 @@
-use fatt::config::ScanConfig;
+#![allow(clippy::field_reassign_with_default)]
+
+use fatt::config::{InputSource, ScanConfig};
 use tempfile::tempdir;
-use tracing;
 mod test_helpers;
 use test_helpers::LogCapture;
 
@@ -28,6 +29,7 @@ mod tests {
         assert_eq!(config.dns_cache_size, 10000);
         assert!(!config.quiet);
         assert!(!config.dns_only);
+        assert_eq!(config.interesting_status, vec![401, 403]);
     }
 
     #[test]
@@ -100,6 +102,81 @@ mod tests {
 
         let validation_result = config.validate();
         assert!(validation_result.is_ok());
+
+        // Evasion has to be explicitly authorized
+        config.evasion = true;
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("--authorized-testing"));
+
+        config.authorized_testing = true;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_input_source_parse() {
+        let tagged: InputSource = "prod.txt:prod".parse().unwrap();
+        assert_eq!(tagged, InputSource::new("prod.txt", Some("prod")));
+        assert_eq!(tagged.to_string(), "prod.txt:prod");
+
+        let plain: InputSource = "domains.txt".parse().unwrap();
+        assert_eq!(plain, InputSource::new("domains.txt", None));
+
+        // A colon that isn't followed by a tag belongs to the file name
+        let drive: InputSource = "C:\\scans\\domains.txt".parse().unwrap();
+        assert_eq!(drive.file, "C:\\scans\\domains.txt");
+        assert!(drive.tag.is_none());
+
+        assert!("".parse::<InputSource>().is_err());
+        assert!(InputSource::new("a.txt", Some("no/slashes"))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_scan_config_inputs() {
+        let temp_dir = tempdir().unwrap();
+        let prod = temp_dir.path().join("prod.txt");
+        std::fs::write(&prod, "example.com\n").unwrap();
+
+        let mut config = ScanConfig::default();
+        config.input_file = prod.to_string_lossy().to_string();
+        config.input_tag = Some("prod".to_string());
+        config.extra_inputs = vec![InputSource::new("missing.txt", Some("staging"))];
+
+        let inputs = config.inputs();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].tag.as_deref(), Some("prod"));
+
+        // Every input has to exist
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("missing.txt"));
+    }
+
+    #[test]
+    fn test_scan_config_purge_settings() {
+        let temp_dir = tempdir().unwrap();
+        let input = temp_dir.path().join("domains.txt");
+        std::fs::write(&input, "example.com\n").unwrap();
+
+        let mut config = ScanConfig::default();
+        config.input_file = input.to_string_lossy().to_string();
+        config.rules_file = "rules.yaml".to_string();
+
+        config.purge_not_detected = true;
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("--purge-older-than"));
+
+        config.purge_older_than = Some("soon".to_string());
+        assert!(config.validate().is_err());
     }
 
     // A more direct test approach for logging
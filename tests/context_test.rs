@@ -0,0 +1,119 @@
+use bytes::Bytes;
+use fatt::context::{self, CircuitBreaker, Soft404};
+use fatt::scanner::Page;
+use reqwest::header::{HeaderMap, HeaderValue};
+
+#[test]
+fn test_parse_robots_disallow() {
+    let robots = "\
+User-agent: Googlebot
+Disallow: /only-for-google
+
+User-agent: *
+User-agent: Bingbot
+Disallow: /admin/ # staff only
+Disallow: /backup
+Disallow:
+Allow: /public
+";
+
+    assert_eq!(
+        context::parse_robots_disallow(robots),
+        vec!["/admin/".to_string(), "/backup".to_string()]
+    );
+    assert!(context::parse_robots_disallow("").is_empty());
+}
+
+#[test]
+fn test_detect_technologies() {
+    let mut headers = HeaderMap::new();
+    headers.insert("server", HeaderValue::from_static("nginx/1.25.3"));
+    headers.insert("x-powered-by", HeaderValue::from_static("PHP/8.2.1"));
+
+    let page = Page {
+        status: 200,
+        headers,
+        body: r#"<link href="/wp-content/themes/site/style.css">"#.into(),
+        redirects: Vec::new(),
+        certificate: None,
+        complete: true,
+    };
+
+    assert_eq!(
+        context::detect_technologies(&page),
+        vec!["PHP", "WordPress", "nginx"]
+    );
+}
+
+#[test]
+fn test_circuit_breaker() {
+    let breaker = CircuitBreaker::new(2);
+    breaker.record_failure();
+    assert!(!breaker.is_open());
+
+    // A success resets the streak
+    breaker.record_success();
+    breaker.record_failure();
+    assert!(!breaker.is_open());
+    breaker.record_failure();
+    assert!(breaker.is_open());
+
+    // A zero threshold disables the breaker
+    let disabled = CircuitBreaker::new(0);
+    disabled.record_failure();
+    assert!(!disabled.is_open());
+}
+
+fn binary_page(body: &'static [u8]) -> Page {
+    Page {
+        status: 404,
+        headers: HeaderMap::new(),
+        body: Bytes::from_static(body),
+        redirects: Vec::new(),
+        certificate: None,
+        complete: true,
+    }
+}
+
+#[test]
+fn test_soft_404_with_binary_body() {
+    // Not valid UTF-8, and the requested path is echoed back
+    let probe = binary_page(b"\xff\xfeNo such page: /fatt-probe-1234\x00");
+    let soft_404 = Soft404::from_page(&probe, "/fatt-probe-1234");
+
+    assert!(soft_404.matches(&binary_page(b"\xff\xfeNo such page: /.env\x00"), "/.env"));
+    assert!(!soft_404.matches(&binary_page(b"\xff\xfeAPP_KEY=secret\x00"), "/.env"));
+    assert_eq!(
+        probe.text(),
+        "\u{fffd}\u{fffd}No such page: /fatt-probe-1234\0"
+    );
+}
+
+#[test]
+fn test_suggests_tls_only() {
+    // A failed connection or a rejected plain-http request
+    assert!(context::suggests_tls_only(None));
+    assert!(context::suggests_tls_only(Some(400)));
+
+    assert!(!context::suggests_tls_only(Some(404)));
+    assert!(!context::suggests_tls_only(Some(200)));
+}
+
+#[test]
+fn test_https_equivalent() {
+    assert_eq!(
+        context::https_equivalent("http://example.com").as_deref(),
+        Some("https://example.com")
+    );
+    // The default http port moves to the default https one, an explicit port stays
+    assert_eq!(
+        context::https_equivalent("http://example.com:80").as_deref(),
+        Some("https://example.com")
+    );
+    assert_eq!(
+        context::https_equivalent("http://[2001:db8::1]:8443").as_deref(),
+        Some("https://[2001:db8::1]:8443")
+    );
+    assert_eq!(context::https_equivalent("https://example.com"), None);
+    assert_eq!(context::https_equivalent("not a url"), None);
+}
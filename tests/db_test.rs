@@ -1,5 +1,6 @@
 use fatt::db;
 use fatt::rules::Severity;
+use fatt::store::FindingDetails;
 use rusqlite::{params, Connection};
 use tempfile::tempdir;
 
@@ -39,6 +40,33 @@ fn test_db_initialization() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_db_tuning() -> anyhow::Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("tuned.sqlite");
+    let db_file = db_path.to_str().unwrap();
+    let journal_mode = |conn: &Connection| {
+        conn.query_row("PRAGMA journal_mode", [], |row| row.get::<_, String>(0))
+    };
+    let synchronous =
+        |conn: &Connection| conn.query_row("PRAGMA synchronous", [], |row| row.get::<_, i64>(0));
+
+    {
+        let conn = db::init_db(db_file)?;
+        assert_eq!(journal_mode(&conn)?, "wal");
+        assert_eq!(synchronous(&conn)?, 1); // NORMAL
+        let busy_timeout: i64 = conn.query_row("PRAGMA busy_timeout", [], |row| row.get(0))?;
+        assert_eq!(busy_timeout as u128, db::BUSY_TIMEOUT.as_millis());
+    }
+
+    // Without tuning, a database tuned before goes back to a rollback journal
+    let conn = db::init_db_with(db_file, false)?;
+    assert_eq!(journal_mode(&conn)?, "delete");
+    assert_eq!(synchronous(&conn)?, 2); // FULL, SQLite's default
+
+    Ok(())
+}
+
 #[test]
 fn test_record_finding() -> anyhow::Result<()> {
     // Create in-memory database for testing
@@ -164,7 +192,7 @@ fn test_get_unique_domains_count() -> anyhow::Result<()> {
     )?;
 
     // Insert sample data with some duplicate domains
-    let domains = vec!["example.com", "test.com", "example.com", "demo.com"];
+    let domains = ["example.com", "test.com", "example.com", "demo.com"];
 
     for (i, domain) in domains.iter().enumerate() {
         let rule_name = format!("rule-{}", i);
@@ -180,3 +208,450 @@ fn test_get_unique_domains_count() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_shard_routing() {
+    assert_eq!(
+        db::shard_path("data/results.sqlite", 2),
+        "data/results-shard2.sqlite"
+    );
+    assert_eq!(db::shard_path("results", 0), "results-shard0");
+
+    // A domain always lands in the same shard, regardless of case
+    let index = db::shard_index("example.com", 4);
+    assert!(index < 4);
+    assert_eq!(db::shard_index("EXAMPLE.com", 4), index);
+    assert_eq!(db::shard_index("example.com", 1), 0);
+}
+
+#[test]
+fn test_sharded_findings_and_merge() -> anyhow::Result<()> {
+    use fatt::store::ResultStore;
+
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_file = db_path.to_str().unwrap();
+
+    let domains: Vec<String> = (0..20).map(|i| format!("host{}.example.com", i)).collect();
+    {
+        let mut sharded = db::ShardedDb::open(db_file, 3)?;
+        sharded.start_scan("scan-1", chrono::Utc::now())?;
+        for domain in &domains {
+            sharded.record_finding(
+                domain,
+                "Git",
+                "/.git/HEAD",
+                true,
+                &FindingDetails::default(),
+            )?;
+        }
+
+        // Findings are spread over the shards but read back as one set
+        let per_shard: Vec<usize> = sharded
+            .shards
+            .iter()
+            .map(|shard| db::get_all_findings(shard).map(|f| f.len()))
+            .collect::<anyhow::Result<_>>()?;
+        assert_eq!(per_shard.iter().sum::<usize>(), 20);
+        assert!(per_shard.iter().all(|&count| count < 20));
+        assert_eq!(sharded.findings()?.len(), 20);
+
+        // Scan sessions stay in the primary database
+        assert_eq!(db::get_scan_sessions(&sharded.primary, 10)?.len(), 1);
+        assert!(db::get_all_findings(&sharded.primary)?.is_empty());
+    }
+
+    let output = temp_dir.path().join("findings.json");
+    db::export_results(db_file, 3, output.to_str().unwrap(), "json", None)?;
+    let exported: Vec<serde_json::Value> =
+        serde_json::from_str(&std::fs::read_to_string(&output)?)?;
+    assert_eq!(exported.len(), 20);
+
+    assert_eq!(db::merge_shards(db_file, 3)?, 20);
+    let conn = db::init_db(db_file)?;
+    assert_eq!(db::get_all_findings(&conn)?.len(), 20);
+
+    // Merging a missing shard is an error rather than a silent partial merge
+    assert!(db::merge_shards(db_file, 4).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_databases() -> anyhow::Result<()> {
+    let temp_dir = tempdir()?;
+    let path = |name: &str| temp_dir.path().join(name).to_str().unwrap().to_string();
+    let (run_a, run_b, master) = (path("a.sqlite"), path("b.sqlite"), path("master.sqlite"));
+
+    let a = db::init_db(&run_a)?;
+    db::start_scan_session(&a, "scan-a", chrono::Utc::now())?;
+    db::insert_finding(&a, "a.com", "Git", "/.git/HEAD", true)?;
+    db::insert_finding(&a, "shared.com", "Git", "/.git/HEAD", false)?;
+    // Run b scanned shared.com after run a did
+    let b = db::init_db(&run_b)?;
+    db::start_scan_session(&b, "scan-b", chrono::Utc::now())?;
+    db::insert_finding(&b, "shared.com", "Git", "/.git/HEAD", true)?;
+    b.execute(
+        "UPDATE findings SET scanned_at = datetime('now', '+1 hour') WHERE domain = 'shared.com'",
+        [],
+    )?;
+    db::insert_finding(&b, "b.com", "Env", "/.env", true)?;
+
+    db::merge_databases(&master, &[run_a.clone(), run_b.clone()])?;
+    let conn = db::init_db(&master)?;
+    let findings: Vec<(String, bool)> = db::get_all_findings(&conn)?
+        .into_iter()
+        .map(|finding| (finding.domain, finding.detected))
+        .collect();
+    assert_eq!(
+        findings,
+        vec![
+            ("a.com".to_string(), true),
+            ("b.com".to_string(), true),
+            ("shared.com".to_string(), true),
+        ]
+    );
+    assert_eq!(db::get_scan_sessions(&conn, 10)?.len(), 2);
+
+    // Merging again in the other order keeps the newer result, and copies no
+    // scan twice
+    db::merge_databases(&master, &[run_b, run_a.clone()])?;
+    assert_eq!(db::get_all_findings(&conn)?.len(), 3);
+    assert!(db::get_all_findings(&conn)?
+        .iter()
+        .all(|finding| finding.detected));
+    assert_eq!(db::get_scan_sessions(&conn, 10)?.len(), 2);
+
+    assert!(db::merge_databases(&master, &[path("missing.sqlite")]).is_err());
+    assert!(db::merge_databases(&run_a, std::slice::from_ref(&run_a)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_export_split_by_tag() -> anyhow::Result<()> {
+    use fatt::store::ResultStore;
+
+    let temp_dir = tempdir()?;
+    let mut conn = db::init_db(temp_dir.path().join("results.sqlite").to_str().unwrap())?;
+    for (domain, tag) in [
+        ("a.example.com", Some("prod")),
+        ("b.example.com", Some("staging")),
+        ("c.example.com", Some("prod")),
+        ("d.example.com", None),
+    ] {
+        let details = FindingDetails {
+            tag,
+            ..Default::default()
+        };
+        conn.record_finding(domain, "Git", "/.git/HEAD", true, &details)?;
+    }
+
+    let findings = conn.findings()?;
+    assert_eq!(findings[0].tag.as_deref(), Some("prod"));
+    assert!(findings[3].tag.is_none());
+
+    let output = temp_dir.path().join("findings.json");
+    db::export_split(
+        &findings,
+        output.to_str().unwrap(),
+        "json",
+        Some(db::SplitBy::Tag),
+    )?;
+
+    // One file per tag, untagged findings in their own
+    assert!(!output.exists());
+    let read = |name: &str| -> anyhow::Result<Vec<serde_json::Value>> {
+        let path = temp_dir.path().join(name);
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    };
+    let prod = read("findings-prod.json")?;
+    assert_eq!(prod.len(), 2);
+    assert!(prod.iter().all(|finding| finding["tag"] == "prod"));
+    assert_eq!(read("findings-staging.json")?.len(), 1);
+    assert_eq!(read("findings-untagged.json")?.len(), 1);
+
+    assert_eq!("TAG".parse::<db::SplitBy>()?, db::SplitBy::Tag);
+    assert!("rule".parse::<db::SplitBy>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_export_jsonl() -> anyhow::Result<()> {
+    let temp_dir = tempdir()?;
+    let conn = db::init_db(temp_dir.path().join("results.sqlite").to_str().unwrap())?;
+    db::insert_finding(&conn, "a.example.com", "Git", "/.git/HEAD", true)?;
+    db::insert_finding(&conn, "b.example.com", "Env", "/.env", false)?;
+
+    let output = temp_dir.path().join("findings.jsonl");
+    db::export_findings(
+        &db::get_all_findings(&conn)?,
+        output.to_str().unwrap(),
+        "jsonl",
+    )?;
+
+    // One compact finding per line
+    let contents = std::fs::read_to_string(&output)?;
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 2);
+    let first: serde_json::Value = serde_json::from_str(lines[0])?;
+    assert_eq!(first["domain"], "a.example.com");
+    assert_eq!(first["rule_name"], "Git");
+    assert!(contents.ends_with('\n'));
+
+    Ok(())
+}
+
+#[test]
+fn test_write_findings_for_listing() -> anyhow::Result<()> {
+    let temp_dir = tempdir()?;
+    let conn = db::init_db(temp_dir.path().join("results.sqlite").to_str().unwrap())?;
+    db::insert_finding(&conn, "a.example.com", "Git", "/.git/HEAD", true)?;
+    db::insert_finding(&conn, "b.example.com", "Env", "/.env", false)?;
+    let findings = db::get_all_findings(&conn)?;
+
+    let write = |format: &str| -> anyhow::Result<String> {
+        let mut out = Vec::new();
+        db::write_findings(&findings, format.parse()?, &mut out)?;
+        Ok(String::from_utf8(out)?)
+    };
+
+    let json: serde_json::Value = serde_json::from_str(&write("json")?)?;
+    assert_eq!(json.as_array().unwrap().len(), 2);
+    assert_eq!(json[0]["domain"], "a.example.com");
+
+    let csv = write("csv")?;
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("ID,Domain,Rule,Path,Detected"));
+    assert!(lines[1].contains(",a.example.com,Git,/.git/HEAD,true,"));
+
+    let tsv = write("TSV")?;
+    let fields: Vec<&str> = tsv.lines().nth(2).unwrap().split('\t').collect();
+    assert_eq!(fields[1..5], ["b.example.com", "Env", "/.env", "false"]);
+
+    assert!(write("table")?.contains("Total results: 2"));
+    assert!("xml".parse::<db::ListFormat>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_list_results_sorted_and_paged() -> anyhow::Result<()> {
+    use fatt::rules::{Rule, RuleSet};
+
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_file = db_path.to_str().unwrap();
+    {
+        let mut sharded = db::ShardedDb::open(db_file, 2)?;
+        for (domain, rule) in [
+            ("c.example.com", "Git"),
+            ("a.example.com", "Env"),
+            ("d.example.com", "Backup"),
+            ("b.example.com", "Git"),
+        ] {
+            fatt::store::ResultStore::record_finding(
+                &mut sharded,
+                domain,
+                rule,
+                "/",
+                true,
+                &FindingDetails::default(),
+            )?;
+        }
+    }
+
+    let list = |query: &db::ListQuery, ruleset: Option<&RuleSet>| -> anyhow::Result<Vec<String>> {
+        Ok(db::find_results(db_file, 2, query, ruleset)?
+            .into_iter()
+            .map(|finding| finding.domain)
+            .collect())
+    };
+
+    let by_domain = db::ListQuery {
+        sort: db::SortBy::Domain,
+        order: db::SortBy::Domain.default_order(),
+        limit: 2,
+        ..Default::default()
+    };
+    assert_eq!(list(&by_domain, None)?, ["a.example.com", "b.example.com"]);
+
+    let next_page = db::ListQuery {
+        offset: 2,
+        ..by_domain.clone()
+    };
+    assert_eq!(list(&next_page, None)?, ["c.example.com", "d.example.com"]);
+
+    let reversed = db::ListQuery {
+        order: db::SortOrder::Desc,
+        offset: 3,
+        ..by_domain.clone()
+    };
+    assert_eq!(list(&reversed, None)?, ["a.example.com"]);
+
+    // Checks that didn't match are left out when asked
+    fatt::store::ResultStore::record_finding(
+        &mut db::ShardedDb::open(db_file, 2)?,
+        "e.example.com",
+        "Git",
+        "/",
+        false,
+        &FindingDetails::default(),
+    )?;
+    let everything = db::ListQuery {
+        limit: 10,
+        ..next_page.clone()
+    };
+    assert_eq!(list(&everything, None)?.len(), 3);
+    let detected_only = db::ListQuery {
+        detected_only: true,
+        ..everything.clone()
+    };
+    assert_eq!(
+        list(&detected_only, None)?,
+        ["c.example.com", "d.example.com"]
+    );
+
+    // Severity comes from the rules; rules it doesn't know sort last
+    let ruleset = RuleSet {
+        rules: vec![
+            Rule::new("Env", "/.env", "APP_KEY=", "desc", Severity::Critical),
+            Rule::new("Git", "/.git/HEAD", "ref:", "desc", Severity::Medium),
+        ],
+    };
+    let by_severity = db::ListQuery {
+        sort: db::SortBy::Severity,
+        order: db::SortOrder::Desc,
+        detected_only: true,
+        limit: 10,
+        ..Default::default()
+    };
+    let domains = list(&by_severity, Some(&ruleset))?;
+    assert_eq!(domains.len(), 4);
+    assert_eq!(domains[0], "a.example.com");
+    assert_eq!(domains[3], "d.example.com");
+    assert!(list(&by_severity, None).is_err());
+
+    assert_eq!("Scanned_At".parse::<db::SortBy>()?, db::SortBy::ScannedAt);
+    assert!("size".parse::<db::SortBy>().is_err());
+    assert_eq!("asc".parse::<db::SortOrder>()?, db::SortOrder::Asc);
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_time_range() -> anyhow::Result<()> {
+    use chrono::{Duration, TimeZone, Utc};
+
+    let midnight = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+    assert_eq!(db::parse_time("2024-05-01")?, midnight);
+    assert_eq!(
+        db::parse_time("2024-05-01 12:30:00")?,
+        midnight + Duration::minutes(750)
+    );
+    assert_eq!(db::parse_time("2024-05-01T02:00:00+02:00")?, midnight);
+    let ago = db::parse_time("12h")?;
+    assert!((Utc::now() - Duration::hours(12) - ago).num_seconds().abs() < 5);
+    assert!(db::parse_time("last night").is_err());
+
+    let range = db::TimeRange::parse(Some("2024-05-01"), Some("2024-05-02"))?;
+    assert!(range.contains(midnight));
+    assert!(!range.contains(midnight - Duration::seconds(1)));
+    assert!(db::TimeRange::parse(Some("2024-05-02"), Some("2024-05-01")).is_err());
+    assert!(db::TimeRange::default().contains(midnight));
+
+    Ok(())
+}
+
+#[test]
+fn test_get_findings_in_time_range() -> anyhow::Result<()> {
+    let conn = db::init_db(":memory:")?;
+    db::insert_finding(&conn, "old.com", "Git", "/.git/HEAD", true)?;
+    db::insert_finding(&conn, "new.com", "Git", "/.git/HEAD", true)?;
+    db::insert_finding(&conn, "rfc.com", "Git", "/.git/HEAD", true)?;
+    conn.execute(
+        "UPDATE findings SET scanned_at = '2024-05-01 08:00:00' WHERE domain = 'old.com'",
+        [],
+    )?;
+    conn.execute(
+        "UPDATE findings SET scanned_at = '2024-05-02T08:00:00+00:00' WHERE domain = 'rfc.com'",
+        [],
+    )?;
+
+    let domains = |range: &db::TimeRange| -> anyhow::Result<Vec<String>> {
+        Ok(db::get_findings_by_domain(&conn, None, range, 10)?
+            .into_iter()
+            .map(|finding| finding.domain)
+            .collect())
+    };
+
+    let all = domains(&db::TimeRange::default())?;
+    assert_eq!(all, vec!["new.com", "rfc.com", "old.com"]);
+
+    let last_night = db::TimeRange::parse(Some("2024-05-02"), Some("2024-05-03"))?;
+    assert_eq!(domains(&last_night)?, vec!["rfc.com"]);
+
+    let recent = db::TimeRange::parse(Some("1d"), None)?;
+    assert_eq!(domains(&recent)?, vec!["new.com"]);
+
+    let findings = db::get_findings_by_rule(&conn, Some("Git"), &last_night, 10)?;
+    assert_eq!(findings.len(), 1);
+    assert_eq!(
+        findings[0].scanned_at.to_rfc3339(),
+        "2024-05-02T08:00:00+00:00"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_upsert_finding_replaces_details() -> anyhow::Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("test.sqlite");
+    let conn = db::init_db(db_path.to_str().unwrap())?;
+
+    let chain = vec!["https://a.example.com/admin/".to_string()];
+    db::upsert_finding(
+        &conn,
+        "a.example.com",
+        "Admin",
+        "/admin",
+        true,
+        &FindingDetails {
+            user_agent: Some("agent/1"),
+            redirect_chain: &chain,
+            tag: Some("prod"),
+            status: Some(200),
+            ..Default::default()
+        },
+    )?;
+    db::upsert_finding(
+        &conn,
+        "a.example.com",
+        "Admin",
+        "/admin",
+        false,
+        &FindingDetails {
+            status: Some(404),
+            ..Default::default()
+        },
+    )?;
+
+    let finding = db::get_domain_findings(&conn, "a.example.com")?
+        .pop()
+        .unwrap();
+    assert!(!finding.detected);
+    assert_eq!(finding.status, Some(404));
+    assert!(finding.redirect_chain.is_empty());
+    assert_eq!(finding.tag, None);
+
+    let user_agent: Option<String> = conn.query_row(
+        "SELECT user_agent FROM findings WHERE domain = 'a.example.com'",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(user_agent.as_deref(), Some("agent/1"));
+    Ok(())
+}
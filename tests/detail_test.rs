@@ -0,0 +1,249 @@
+use anyhow::Result;
+use fatt::db;
+use fatt::db::TimeRange;
+use fatt::detail;
+use fatt::evidence::Evidence;
+use fatt::redact::RedactionPolicy;
+use fatt::rules::{Rule, RuleSet, Severity};
+use fatt::store::{self, ExportOptions, FindingDetails, ResultStore, StoreKind};
+use tempfile::tempdir;
+
+#[test]
+fn test_find_finding_with_related() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_file = db_path.to_str().unwrap();
+
+    let conn = db::init_db(db_file)?;
+    let id = db::insert_finding(&conn, "a.example.com", "Git", "/.git/HEAD", true)?;
+    db::upsert_finding(
+        &conn,
+        "a.example.com",
+        "Git",
+        "/.git/HEAD",
+        true,
+        &FindingDetails {
+            evidence: Some(&Evidence::capture(200, b"ref: refs/heads/main", 0)),
+            ..Default::default()
+        },
+    )?;
+    let env = db::insert_finding(&conn, "a.example.com", "Env", "/.env", true)?;
+    db::insert_finding(&conn, "a.example.com", "Backup", "/backup.zip", false)?;
+    db::insert_finding(&conn, "b.example.com", "Git", "/.git/HEAD", true)?;
+
+    let mut git = Rule::new("Git", "/.git/HEAD", "ref:", "Exposed Git", Severity::High);
+    git.remediation = Some("Block /.git".to_string());
+    let ruleset = RuleSet { rules: vec![git] };
+
+    let detail = detail::find_finding(db_file, 1, id, Some(&ruleset))?;
+    assert_eq!(detail.finding.domain, "a.example.com");
+    assert_eq!(detail.finding.matched_path, "/.git/HEAD");
+    assert_eq!(detail.description.as_deref(), Some("Exposed Git"));
+    assert_eq!(detail.severity, Some(Severity::High));
+    assert_eq!(detail.finding.remediation.as_deref(), Some("Block /.git"));
+    assert_eq!(
+        detail.finding.evidence.as_ref().map(|e| e.snippet.as_str()),
+        Some("ref: refs/heads/main")
+    );
+
+    // Only the domain's other detected findings are related
+    let related: Vec<i64> = detail.related.iter().map(|f| f.id).collect();
+    assert_eq!(related, [env]);
+
+    // Without the rules there's nothing about the rule to show
+    let detail = detail::find_finding(db_file, 1, env, None)?;
+    assert!(detail.description.is_none());
+    assert!(detail.severity.is_none());
+
+    let json = serde_json::to_value(&detail)?;
+    assert_eq!(json["rule_name"], "Env");
+    assert_eq!(json["related"][0]["rule_name"], "Git");
+
+    assert!(detail::find_finding(db_file, 1, 999, None).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_find_finding_in_shards() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_file = db_path.to_str().unwrap();
+
+    {
+        let mut sharded = db::ShardedDb::open(db_file, 2)?;
+        sharded.start_scan("scan-1", chrono::Utc::now())?;
+        for i in 0..11 {
+            sharded.record_finding(
+                &format!("host{}.example.com", i),
+                "Git",
+                "/.git/HEAD",
+                true,
+                &FindingDetails::default(),
+            )?;
+        }
+    }
+
+    // Each shard numbers its findings from 1, so low IDs are ambiguous
+    let err = detail::find_finding(db_file, 2, 1, None).unwrap_err();
+    assert!(err.to_string().contains("merge"));
+
+    // 11 domains can't split evenly, so the larger shard has IDs the other lacks
+    let counts: Vec<usize> = (0..2)
+        .map(|i| {
+            let conn = db::init_db(&db::shard_path(db_file, i))?;
+            Ok(db::get_all_findings(&conn)?.len())
+        })
+        .collect::<Result<_>>()?;
+    assert_ne!(counts[0], counts[1]);
+    let id = *counts.iter().max().unwrap() as i64;
+    assert_eq!(detail::find_finding(db_file, 2, id, None)?.finding.id, id);
+
+    Ok(())
+}
+
+#[test]
+fn test_annotate_finding() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_file = db_path.to_str().unwrap();
+
+    let conn = db::init_db(db_file)?;
+    let id = db::insert_finding(&conn, "example.com", "Git", "/.git/HEAD", true)?;
+
+    detail::annotate(
+        db_file,
+        1,
+        id,
+        "Accepted: internal mirror, see SEC-123",
+        Some("alice"),
+    )?;
+    detail::annotate(db_file, 1, id, "  Re-checked after the deploy  ", None)?;
+    assert!(detail::annotate(db_file, 1, id, "   ", None).is_err());
+    assert!(detail::annotate(db_file, 1, 999, "Unknown", None).is_err());
+
+    let detail = detail::find_finding(db_file, 1, id, None)?;
+    let notes: Vec<(&str, Option<&str>)> = detail
+        .notes
+        .iter()
+        .map(|n| (n.note.as_str(), n.author.as_deref()))
+        .collect();
+    assert_eq!(
+        notes,
+        [
+            ("Accepted: internal mirror, see SEC-123", Some("alice")),
+            ("Re-checked after the deploy", None)
+        ]
+    );
+
+    // Exports carry the notes with their findings
+    let output = temp_dir.path().join("export.csv");
+    store::export_results(
+        StoreKind::Sqlite,
+        &[db_file.to_string()],
+        1,
+        output.to_str().unwrap(),
+        &ExportOptions {
+            format: "csv",
+            split_by: None,
+            redaction: &RedactionPolicy::default(),
+            range: TimeRange::default(),
+            detected_only: true,
+            ruleset: None,
+            group_by: None,
+            project: None,
+            include_false_positives: false,
+        },
+    )?;
+    let csv = std::fs::read_to_string(&output)?;
+    assert!(csv.lines().next().unwrap().ends_with(",Notes"));
+    assert!(csv.contains("alice: Accepted: internal mirror, see SEC-123; "));
+    assert!(csv.contains(": Re-checked after the deploy"));
+
+    // Notes belong to the domain and rule, so they survive the finding being re-checked
+    db::insert_finding(&conn, "example.com", "Git", "/.git/config", false)?;
+    assert_eq!(db::get_notes(&conn, "example.com", "Git")?.len(), 2);
+
+    // and follow it into a merged database
+    let merged = temp_dir.path().join("merged.sqlite");
+    let merged = merged.to_str().unwrap();
+    db::merge_databases(merged, &[db_file.to_string()])?;
+    db::merge_databases(merged, &[db_file.to_string()])?;
+    let merged = db::init_db(merged)?;
+    let notes = db::get_notes(&merged, "example.com", "Git")?;
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[0].author.as_deref(), Some("alice"));
+
+    Ok(())
+}
+
+#[test]
+fn test_false_positive_is_left_out_of_exports() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_file = db_path.to_str().unwrap();
+
+    let conn = db::init_db(db_file)?;
+    let noise = db::insert_finding(&conn, "example.com", "Git", "/.git/HEAD", true)?;
+    db::insert_finding(&conn, "example.com", "Env", "/.env", true)?;
+    db::insert_finding(&conn, "other.example.com", "Git", "/.git/HEAD", true)?;
+
+    detail::mark_false_positive(db_file, 1, noise, true, false)?;
+    assert!(detail::mark_false_positive(db_file, 1, 999, false, false).is_err());
+
+    let detail = detail::find_finding(db_file, 1, noise, None)?;
+    assert!(detail.false_positive.is_some_and(|fp| fp.skip_scans));
+
+    let pair = |domain: &str, rule: &str| (domain.to_string(), rule.to_string());
+    let export = |include_false_positives: bool| -> Result<Vec<(String, String)>> {
+        let output = temp_dir.path().join("export.json");
+        store::export_results(
+            StoreKind::Sqlite,
+            &[db_file.to_string()],
+            1,
+            output.to_str().unwrap(),
+            &ExportOptions {
+                format: "json",
+                split_by: None,
+                redaction: &RedactionPolicy::default(),
+                range: TimeRange::default(),
+                detected_only: true,
+                ruleset: None,
+                group_by: None,
+                project: None,
+                include_false_positives,
+            },
+        )?;
+        let findings: Vec<serde_json::Value> =
+            serde_json::from_str(&std::fs::read_to_string(output)?)?;
+        Ok(findings
+            .iter()
+            .map(|f| {
+                pair(
+                    f["domain"].as_str().unwrap(),
+                    f["rule_name"].as_str().unwrap(),
+                )
+            })
+            .collect())
+    };
+
+    // The mark belongs to the domain and rule, so it outlives a re-check
+    db::insert_finding(&conn, "example.com", "Git", "/.git/config", true)?;
+    assert_eq!(
+        export(false)?,
+        [pair("example.com", "Env"), pair("other.example.com", "Git")]
+    );
+    assert_eq!(export(true)?.len(), 3);
+
+    // and follows it into a merged database
+    let merged = temp_dir.path().join("merged.sqlite");
+    db::merge_databases(merged.to_str().unwrap(), &[db_file.to_string()])?;
+    let merged = db::init_db(merged.to_str().unwrap())?;
+    assert_eq!(db::get_false_positives(&merged)?.len(), 1);
+
+    detail::mark_false_positive(db_file, 1, noise, false, true)?;
+    assert!(db::get_false_positives(&conn)?.is_empty());
+    assert_eq!(export(false)?.len(), 3);
+
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use chrono::Utc;
+use fatt::db::{self, Finding};
+use fatt::diff::{self, DiffSide};
+use fatt::risk::{RiskReport, RiskWeights};
+use fatt::store::{FindingDetails, JsonlStore, ResultStore, StoreKind};
+use tempfile::tempdir;
+mod test_helpers;
+use test_helpers::finding;
+
+fn keys(findings: &[Finding]) -> Vec<(&str, &str)> {
+    findings
+        .iter()
+        .map(|finding| (finding.domain.as_str(), finding.rule_name.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_diff_findings() {
+    let from = vec![
+        finding("a.com", "Env", true),
+        finding("a.com", "Git", true),
+        finding("b.com", "Env", true),
+    ];
+    let to = vec![
+        finding("a.com", "Env", true),
+        // No longer detected counts as resolved
+        finding("a.com", "Git", false),
+        finding("c.com", "Env", true),
+    ];
+
+    let diff = diff::diff_findings(&from, &to);
+    assert_eq!(keys(&diff.new), vec![("c.com", "Env")]);
+    assert_eq!(
+        keys(&diff.resolved),
+        vec![("a.com", "Git"), ("b.com", "Env")]
+    );
+    assert_eq!(keys(&diff.unchanged), vec![("a.com", "Env")]);
+    // Findings stored without a digest can't tell whether the rules changed
+    assert!(diff.from_rulesets.is_empty());
+    assert!(!diff.rules_changed());
+}
+
+#[test]
+fn test_diff_rules_changed() {
+    let checked_with = |domain: &str, digest: &str| Finding {
+        ruleset_digest: Some(digest.to_string()),
+        ..finding(domain, "Git", true)
+    };
+
+    let diff = diff::diff_findings(
+        &[checked_with("a.com", "aaaa"), checked_with("b.com", "aaaa")],
+        &[checked_with("a.com", "aaaa")],
+    );
+    assert_eq!(diff.from_rulesets, ["aaaa"]);
+    assert!(!diff.rules_changed());
+
+    let diff = diff::diff_findings(
+        &[checked_with("a.com", "aaaa"), checked_with("b.com", "aaaa")],
+        &[checked_with("a.com", "bbbb")],
+    );
+    assert_eq!(diff.to_rulesets, ["bbbb"]);
+    assert!(diff.rules_changed());
+    assert_eq!(diff.resolved[0].ruleset_digest.as_deref(), Some("aaaa"));
+}
+
+#[test]
+fn test_db_scan_findings() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let conn = db::init_db(db_path.to_str().unwrap())?;
+
+    db::start_scan_session(&conn, "scan-1", Utc::now())?;
+    db::record_scan_findings(
+        &conn,
+        "scan-1",
+        &[finding("b.com", "Env", true), finding("a.com", "Git", true)],
+    )?;
+    db::finish_scan_session(&conn, "scan-1", 2, &RiskReport::default())?;
+
+    let findings = db::get_scan_findings(&conn, "scan-1")?;
+    assert_eq!(keys(&findings), vec![("a.com", "Git"), ("b.com", "Env")]);
+    assert!(findings.iter().all(|finding| finding.detected));
+
+    assert!(db::get_scan_findings(&conn, "scan-2").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_jsonl_scan_findings() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut store = JsonlStore::open(temp_dir.path())?;
+
+    store.start_scan("scan-1", Utc::now())?;
+    let details = FindingDetails::default();
+    store.record_finding("a.com", "Env", "/.env", true, &details)?;
+    store.record_finding("a.com", "Git", "/.git/HEAD", false, &details)?;
+    store.finish_scan("scan-1", 1, &RiskReport::default())?;
+
+    let findings = store.scan_findings("scan-1")?;
+    assert_eq!(keys(&findings), vec![("a.com", "Env")]);
+
+    assert!(store.scan_findings("scan-2").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_diff_results_requires_two_sides() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let database = db_path.to_str().unwrap().to_string();
+    db::init_db(&database)?;
+
+    let side = DiffSide {
+        database: database.clone(),
+        scan_id: None,
+    };
+    assert!(diff::diff_results(
+        StoreKind::Sqlite,
+        1,
+        &side,
+        &side,
+        None,
+        &RiskWeights::default(),
+        "text"
+    )
+    .is_err());
+
+    let missing = DiffSide {
+        database: temp_dir
+            .path()
+            .join("missing.sqlite")
+            .to_string_lossy()
+            .into(),
+        scan_id: None,
+    };
+    assert!(diff::diff_results(
+        StoreKind::Sqlite,
+        1,
+        &side,
+        &missing,
+        None,
+        &RiskWeights::default(),
+        "text"
+    )
+    .is_err());
+    assert!(diff::diff_results(
+        StoreKind::Sqlite,
+        1,
+        &missing,
+        &side,
+        None,
+        &RiskWeights::default(),
+        "json"
+    )
+    .is_err());
+
+    Ok(())
+}
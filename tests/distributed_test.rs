@@ -0,0 +1,580 @@
+#![cfg(feature = "distributed")]
+
+use anyhow::Result;
+use fatt::config::ScanConfig;
+use fatt::db;
+use fatt::distributed::{
+    self, BatchState, MasterConfig, MasterJob, MasterScan, QueueProgress, ScanFinding, ScanRule,
+    WorkQueue, WorkerCapabilities, WorkerConfig, WorkerMessage, WorkerScanner, WorkerStatus,
+};
+use fatt::evidence::Evidence;
+use fatt::pipeline::PipelineOptions;
+use fatt::resolver::DnsResolver;
+use fatt::rules::{Rule, Severity};
+use fatt::scanner::{self, ScanOptions};
+use fatt::stream::{EventStream, StreamEvent};
+use fatt::utils::RetryPolicy;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+use tokio::net::{TcpListener, TcpStream};
+use wiremock::matchers::path;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn scan_request(batch_id: &str) -> WorkerMessage {
+    WorkerMessage::ScanRequest {
+        domains: vec!["a.example.com".to_string(), "b.example.com".to_string()],
+        batch_id: batch_id.to_string(),
+        rules: vec![ScanRule::from_rule(&Rule::new(
+            "Git Config",
+            "/.git/config",
+            "[core]",
+            "desc",
+            Severity::Critical,
+        ))
+        .unwrap()],
+    }
+}
+
+#[tokio::test]
+async fn test_message_round_trip() -> Result<()> {
+    let mut frame = Vec::new();
+    distributed::write_message(&mut frame, &scan_request("batch-1")).await?;
+
+    match distributed::read_message(&mut frame.as_slice()).await? {
+        WorkerMessage::ScanRequest {
+            domains,
+            batch_id,
+            rules,
+        } => {
+            assert_eq!(batch_id, "batch-1");
+            assert_eq!(domains.len(), 2);
+            assert_eq!(rules[0].to_rule()?.signature, "[core]");
+        }
+        other => panic!("Unexpected message: {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_worker_scans_batch() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.git/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[core]\n\tbare = false"))
+        .mount(&mock_server)
+        .await;
+    let domain = mock_server.uri().replace("http://", "");
+
+    let scanner = WorkerScanner::new(
+        scanner::create_http_client(5, 2)?,
+        Arc::new(DnsResolver::new_for_testing()?),
+        ScanOptions::default(),
+        PipelineOptions {
+            resolvers: 4,
+            fetchers: 4,
+            ..Default::default()
+        },
+    );
+    let rules = [
+        ScanRule::from_rule(&Rule::new(
+            "Git Config",
+            "/.git/config",
+            "[core]",
+            "desc",
+            Severity::Critical,
+        ))?,
+        ScanRule::from_rule(&Rule::new(
+            "Env File",
+            "/.env",
+            "APP_KEY=",
+            "desc",
+            Severity::High,
+        ))?,
+    ];
+
+    let findings = scanner
+        .scan_batch(std::slice::from_ref(&domain), &rules)
+        .await?;
+    let detected: Vec<&ScanFinding> = findings.iter().filter(|f| f.detected).collect();
+    assert_eq!(detected.len(), 1);
+    assert_eq!(detected[0].domain, domain);
+    assert_eq!(detected[0].rule_name, "Git Config");
+    assert_eq!(detected[0].matched_path, "/.git/config");
+    assert_eq!(detected[0].status, Some(200));
+    let evidence = detected[0].evidence()?.unwrap();
+    assert!(evidence.snippet.contains("[core]"));
+    assert!(evidence.body_hash.is_some());
+
+    // Counted in the status the worker sends in its heartbeats
+    let status = scanner.status();
+    assert_eq!(status.active_scans, 0);
+    assert_eq!(status.completed_scans, 1);
+    assert_eq!(status.findings, 1);
+
+    // A rule that doesn't hold up is refused rather than scanned with
+    let invalid = ScanRule {
+        name: "Broken".to_string(),
+        definition: "{\"name\": \"Broken\"}".to_string(),
+    };
+    assert!(scanner.scan_batch(&[domain], &[invalid]).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_worker_scans_with_its_settings() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.git/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[core]\n\tbare = false"))
+        .mount(&mock_server)
+        .await;
+    let port = mock_server.address().port();
+
+    // The domain is only reachable on the port the worker is configured with
+    let mut config = reconnecting_worker(mock_server.address().to_string(), 0);
+    config.scan.ports = vec![port];
+    let scanner = WorkerScanner::from_config(&config).await?;
+    let rules = [ScanRule::from_rule(&Rule::new(
+        "Git Config",
+        "/.git/config",
+        "[core]",
+        "desc",
+        Severity::Critical,
+    ))?];
+
+    let findings = scanner
+        .scan_batch(&["127.0.0.1".to_string()], &rules)
+        .await?;
+    assert!(findings.iter().any(|finding| finding.detected));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reject_corrupted_message() -> Result<()> {
+    let mut frame = Vec::new();
+    distributed::write_message(&mut frame, &scan_request("batch-1")).await?;
+
+    // Every byte of the message is covered by the checksum
+    for offset in 8..frame.len() {
+        let mut corrupted = frame.clone();
+        corrupted[offset] ^= 0x01;
+        let error = distributed::read_message(&mut corrupted.as_slice())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("checksum mismatch"));
+    }
+
+    // A corrupted length doesn't allocate the whole of it
+    let mut oversized = frame.clone();
+    oversized[..4].copy_from_slice(&u32::MAX.to_be_bytes());
+    let error = distributed::read_message(&mut oversized.as_slice())
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("too large"));
+
+    Ok(())
+}
+
+#[test]
+fn test_work_queue() -> Result<()> {
+    let domains: Vec<String> = (1..=5).map(|i| format!("host{}.example.com", i)).collect();
+    let mut queue = WorkQueue::new(&domains, 2);
+    let now = Instant::now();
+    assert_eq!(
+        queue.progress(),
+        QueueProgress {
+            pending: 3,
+            assigned: 0,
+            done: 0
+        }
+    );
+
+    let first = queue.assign("w1", 1, now).unwrap();
+    assert_eq!(first.id, "batch-1");
+    assert_eq!(first.domains, ["host1.example.com", "host2.example.com"]);
+    assert_eq!(queue.assign("w2", 2, now).unwrap().id, "batch-2");
+    assert_eq!(queue.assigned_to(2).unwrap().id, "batch-2");
+
+    // A worker that leaves gives its batch back, to be handed out next
+    assert_eq!(queue.release(1), ["batch-1"]);
+    assert!(queue.assigned_to(1).is_none());
+    assert_eq!(queue.assign("w3", 3, now).unwrap().id, "batch-1");
+    // Another connection of the same worker keeps its own
+    assert!(queue.release(4).is_empty());
+    assert_eq!(queue.assigned_to(3).unwrap().id, "batch-1");
+
+    // So does one that goes quiet
+    let later = now + Duration::from_secs(60);
+    assert!(queue.expire(Duration::from_secs(60), later).is_empty());
+    let expired = queue.expire(Duration::from_secs(30), later);
+    assert_eq!(expired.len(), 2);
+    assert_eq!(queue.state("batch-2"), Some(&BatchState::Pending));
+
+    // Results still count if they arrive after the batch was taken back, once
+    assert!(queue.complete("batch-2")?);
+    assert!(!queue.complete("batch-2")?);
+    assert!(queue.complete("batch-9").is_err());
+    assert_eq!(queue.assign("w1", 5, later).unwrap().id, "batch-1");
+    assert_eq!(queue.assign("w2", 6, later).unwrap().id, "batch-3");
+    assert!(queue.assign("w3", 7, later).is_none());
+
+    assert!(queue.complete("batch-1")?);
+    assert!(!queue.is_finished());
+    assert!(queue.complete("batch-3")?);
+    assert!(queue.is_finished());
+    assert_eq!(queue.progress().done, 3);
+
+    Ok(())
+}
+
+async fn register(addr: std::net::SocketAddr, worker_id: &str) -> Result<TcpStream> {
+    let mut worker = TcpStream::connect(addr).await?;
+    let register = WorkerMessage::Register {
+        worker_id: worker_id.to_string(),
+        capabilities: WorkerCapabilities {
+            max_concurrency: 1,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        },
+    };
+    distributed::write_message(&mut worker, &register).await?;
+    Ok(worker)
+}
+
+/// The next batch the master hands a worker, skipping heartbeats
+async fn next_batch(worker: &mut TcpStream) -> Result<(String, Vec<String>, usize)> {
+    loop {
+        let message =
+            tokio::time::timeout(Duration::from_secs(5), distributed::read_message(worker))
+                .await??;
+        if let WorkerMessage::ScanRequest {
+            batch_id,
+            domains,
+            rules,
+        } = message
+        {
+            return Ok((batch_id, domains, rules.len()));
+        }
+    }
+}
+
+async fn report(worker: &mut TcpStream, batch_id: &str, domains: &[String]) -> Result<()> {
+    let result = WorkerMessage::ScanResult {
+        worker_id: "results-worker".to_string(),
+        batch_id: batch_id.to_string(),
+        findings: domains
+            .iter()
+            .map(|domain| {
+                let detected = domain == "a.example.com";
+                ScanFinding {
+                    domain: domain.clone(),
+                    rule_name: "Git Config".to_string(),
+                    matched_path: "/.git/config".to_string(),
+                    detected,
+                    status: Some(if detected { 200 } else { 404 }),
+                    redirect_chain: Vec::new(),
+                    evidence: detected.then(|| {
+                        serde_json::to_string(&Evidence::capture(200, b"[core]", 0)).unwrap()
+                    }),
+                }
+            })
+            .collect(),
+    };
+    distributed::write_message(worker, &result).await
+}
+
+#[tokio::test]
+async fn test_master_dispatches_batches_and_records_results() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let path = |name: &str| temp_dir.path().join(name).to_string_lossy().to_string();
+    std::fs::write(
+        path("rules.yaml"),
+        "rules:\n  - name: Git Config\n    path: /.git/config\n    signature: \"[core]\"\n",
+    )?;
+    std::fs::write(path("domains.txt"), "a.example.com\nb.example.com\n")?;
+
+    let scan = MasterScan {
+        rules_file: path("rules.yaml"),
+        input_file: path("domains.txt"),
+        input_tag: Some("prod".to_string()),
+        database: path("results.sqlite"),
+        project: Some("acme".to_string()),
+        batch_size: 1,
+        batch_timeout: distributed::DEFAULT_BATCH_TIMEOUT,
+    };
+    let job = Arc::new(MasterJob::load(&scan).await?);
+    assert_eq!(job.domains, vec!["a.example.com", "b.example.com"]);
+    assert_eq!(job.progress().await.pending, 2);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let config = MasterConfig {
+        listen: addr.to_string(),
+        ca: None,
+        server_names: Vec::new(),
+        tls: None,
+        stream_listen: None,
+        stream_token: None,
+        scan: Some(scan.clone()),
+    };
+    let master_job = job.clone();
+    tokio::spawn(
+        async move { distributed::serve_master(listener, &config, Some(master_job)).await },
+    );
+
+    // A worker that leaves mid-batch gives it back
+    let mut quitter = register(addr, "quitting-worker").await?;
+    let (batch_id, domains, rules) = next_batch(&mut quitter).await?;
+    assert_eq!(batch_id, "batch-1");
+    assert_eq!(domains, ["a.example.com"]);
+    assert_eq!(rules, 1);
+    drop(quitter);
+    let mut released = false;
+    for _ in 0..50 {
+        if job.progress().await.pending == 2 {
+            released = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(released);
+
+    // The next worker is handed it first
+    let mut stale = register(addr, "results-worker").await?;
+    let (batch_id, _, _) = next_batch(&mut stale).await?;
+    assert_eq!(batch_id, "batch-1");
+
+    // Reconnecting before the master noticed the old connection was gone hands the
+    // batch to the new one, which the old one closing later doesn't take back
+    let mut worker = register(addr, "results-worker").await?;
+    let (batch_id, domains, _) = next_batch(&mut worker).await?;
+    assert_eq!(batch_id, "batch-1");
+    drop(stale);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(distributed::connected_workers()
+        .await
+        .contains(&"results-worker".to_string()));
+    assert_eq!(job.progress().await.assigned, 1);
+
+    // The master keeps the status the worker sends in its heartbeats
+    let status = WorkerStatus {
+        active_scans: 1,
+        completed_scans: 7,
+        findings: 3,
+        uptime_seconds: 60,
+    };
+    let heartbeat = WorkerMessage::Heartbeat {
+        worker_id: "results-worker".to_string(),
+        status,
+    };
+    distributed::write_message(&mut worker, &heartbeat).await?;
+    let mut updated = false;
+    for _ in 0..50 {
+        let summaries = distributed::worker_summaries().await;
+        if summaries.iter().any(|summary| {
+            summary.id == "results-worker"
+                && (
+                    summary.active_scans,
+                    summary.completed_scans,
+                    summary.findings,
+                ) == (1, 7, 3)
+        }) {
+            updated = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(updated);
+
+    // Then the rest as it reports each
+    report(&mut worker, &batch_id, &domains).await?;
+    let (batch_id, domains, _) = next_batch(&mut worker).await?;
+    assert_eq!(
+        (batch_id.as_str(), domains.as_slice()),
+        ("batch-2", &["b.example.com".to_string()][..])
+    );
+    report(&mut worker, &batch_id, &domains).await?;
+
+    // The findings are written and the scan session finished
+    let conn = db::init_db(&scan.database)?;
+    let mut finished = false;
+    for _ in 0..50 {
+        let sessions = db::get_scan_sessions(&conn, 1)?;
+        if sessions
+            .first()
+            .is_some_and(|session| session.finished_at.is_some())
+        {
+            finished = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(finished);
+    let findings = db::get_all_findings(&conn)?;
+    assert_eq!(findings.len(), 2);
+
+    // With the details the worker reported and those of the master's scan
+    let found = findings.iter().find(|finding| finding.detected).unwrap();
+    assert_eq!(found.domain, "a.example.com");
+    assert_eq!(found.status, Some(200));
+    assert!(found.evidence.as_ref().unwrap().body_hash.is_some());
+    assert_eq!(found.tag.as_deref(), Some("prod"));
+    assert_eq!(found.project.as_deref(), Some("acme"));
+    assert_eq!(found.ruleset_version, Some(1));
+    assert_eq!(
+        found.ruleset_digest.as_deref(),
+        Some(job.ruleset.digest().as_str())
+    );
+    assert_eq!(job.progress().await.done, 2);
+
+    // The worker is forgotten once it disconnects
+    drop(worker);
+    for _ in 0..50 {
+        if !distributed::connected_workers()
+            .await
+            .contains(&"results-worker".to_string())
+        {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Worker still registered after disconnecting");
+}
+
+#[tokio::test]
+async fn test_master_publishes_progress_and_findings() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let path = |name: &str| temp_dir.path().join(name).to_string_lossy().to_string();
+    std::fs::write(
+        path("rules.yaml"),
+        "rules:\n  - name: Git Config\n    path: /.git/config\n    signature: \"[core]\"\n    severity: critical\n",
+    )?;
+    std::fs::write(path("domains.txt"), "a.example.com\nb.example.com\n")?;
+    let scan = MasterScan {
+        rules_file: path("rules.yaml"),
+        input_file: path("domains.txt"),
+        input_tag: None,
+        database: path("results.sqlite"),
+        project: None,
+        batch_size: 1,
+        batch_timeout: distributed::DEFAULT_BATCH_TIMEOUT,
+    };
+    let job = MasterJob::load(&scan).await?;
+
+    let events = Arc::new(EventStream::listen("127.0.0.1:0", None).await?);
+    job.publish_to(events.clone());
+    let response = reqwest::get(format!("http://{}/events", events.local_addr())).await?;
+
+    let finding = |domain: &str, detected: bool| ScanFinding {
+        domain: domain.to_string(),
+        rule_name: "Git Config".to_string(),
+        matched_path: "/.git/config".to_string(),
+        detected,
+        status: Some(if detected { 200 } else { 404 }),
+        redirect_chain: Vec::new(),
+        evidence: None,
+    };
+    job.record_results("worker-1", "batch-1", &[finding("a.example.com", true)])
+        .await?;
+    job.record_results("worker-1", "batch-2", &[finding("b.example.com", false)])
+        .await?;
+
+    let finished = StreamEvent::Finished {
+        scan_id: job.scan_id.clone(),
+        domains: 2,
+        findings: 1,
+        elapsed_secs: 0.5,
+    };
+    let (_, body) = tokio::join!(events.finish(finished), response.text());
+    let body = body?;
+
+    // One event per detected finding, with its rule's severity
+    assert_eq!(body.matches("event: finding").count(), 1);
+    assert!(body.contains(
+        "\"domain\":\"a.example.com\",\"rule\":\"Git Config\",\"severity\":\"critical\""
+    ));
+    // And the progress after each batch
+    assert_eq!(body.matches("event: progress").count(), 2);
+    assert!(body.contains("\"domains_processed\":1,\"domains_total\":2"));
+    assert!(body.contains("\"findings\":1,\"batches\":{\"done\":2,\"total\":2}"));
+
+    Ok(())
+}
+
+/// Accept a worker's connection and registration on `listener`, answering it as the
+/// master does, and return the connection
+async fn accept_registration(listener: &TcpListener) -> Result<(TcpStream, String)> {
+    let (mut socket, _) =
+        tokio::time::timeout(Duration::from_secs(10), listener.accept()).await??;
+    let worker_id = match distributed::read_message(&mut socket).await? {
+        WorkerMessage::Register { worker_id, .. } => worker_id,
+        other => anyhow::bail!("Expected a registration, got {:?}", other),
+    };
+    let heartbeat = WorkerMessage::Heartbeat {
+        worker_id: worker_id.clone(),
+        status: Default::default(),
+    };
+    distributed::write_message(&mut socket, &heartbeat).await?;
+    Ok((socket, worker_id))
+}
+
+fn reconnecting_worker(master: String, retries: u32) -> WorkerConfig {
+    WorkerConfig {
+        worker_id: "reconnecting-worker".to_string(),
+        master,
+        concurrency: 1,
+        tls: None,
+        reconnect: RetryPolicy::new(retries, 10),
+        scan: ScanConfig::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_worker_reconnects_after_losing_the_master() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let config = reconnecting_worker(listener.local_addr()?.to_string(), 5);
+    let worker = tokio::spawn(async move { distributed::start_worker(&config).await });
+
+    // The master drops the connection, as a restart does, and the worker registers again
+    let (socket, worker_id) = accept_registration(&listener).await?;
+    assert_eq!(worker_id, "reconnecting-worker");
+    drop(socket);
+    let (mut socket, worker_id) = accept_registration(&listener).await?;
+    assert_eq!(worker_id, "reconnecting-worker");
+
+    // Until the master asks it to stop
+    let shutdown = WorkerMessage::Shutdown { worker_id };
+    distributed::write_message(&mut socket, &shutdown).await?;
+    tokio::time::timeout(Duration::from_secs(10), worker).await???;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_worker_gives_up_reconnecting() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let config = reconnecting_worker(listener.local_addr()?.to_string(), 2);
+    let worker = tokio::spawn(async move { distributed::start_worker(&config).await });
+
+    // The master goes away for good
+    let (socket, _) = accept_registration(&listener).await?;
+    drop(listener);
+    drop(socket);
+
+    let error = tokio::time::timeout(Duration::from_secs(10), worker)
+        .await??
+        .unwrap_err();
+    assert!(format!("{:#}", error).contains("Gave up reconnecting to master after 2 attempts"));
+
+    // Without retries, a lost master stops the worker straight away
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let config = reconnecting_worker(listener.local_addr()?.to_string(), 0);
+    let worker = tokio::spawn(async move { distributed::start_worker(&config).await });
+    let (socket, _) = accept_registration(&listener).await?;
+    drop(socket);
+    let result = tokio::time::timeout(Duration::from_secs(10), worker).await??;
+    assert!(result.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use anyhow::Result;
+use chrono::{Duration, TimeZone, Utc};
+use fatt::doctor::{self, CheckStatus};
+use fatt::scanner;
+use tempfile::tempdir;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[test]
+fn test_open_files_limit_against_concurrency() {
+    assert_eq!(
+        doctor::check_open_files(100, Some(1024)).status,
+        CheckStatus::Ok
+    );
+    assert_eq!(
+        doctor::check_open_files(100, Some(u64::MAX)).status,
+        CheckStatus::Ok
+    );
+    assert_eq!(
+        doctor::check_open_files(100, None).status,
+        CheckStatus::Warning
+    );
+
+    let check = doctor::check_open_files(1000, Some(256));
+    assert_eq!(check.status, CheckStatus::Failed);
+    assert!(check.hint.unwrap().contains("ulimit -n 1064"));
+}
+
+#[test]
+fn test_clock_skew() {
+    let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+
+    assert_eq!(
+        doctor::check_clock(now, Some(now + Duration::seconds(3))).status,
+        CheckStatus::Ok
+    );
+    assert_eq!(
+        doctor::check_clock(now, Some(now - Duration::minutes(10))).status,
+        CheckStatus::Warning
+    );
+    assert_eq!(
+        doctor::check_clock(now, Some(now + Duration::days(2))).status,
+        CheckStatus::Failed
+    );
+    assert_eq!(doctor::check_clock(now, None).status, CheckStatus::Warning);
+}
+
+#[test]
+fn test_database_writability() -> Result<()> {
+    let dir = tempdir()?;
+
+    // A database that doesn't exist yet is checked without being created
+    let path = dir.path().join("results.sqlite");
+    let path = path.to_string_lossy();
+    assert_eq!(doctor::check_database(&path).status, CheckStatus::Ok);
+    assert!(!dir.path().join("results.sqlite").exists());
+
+    fatt::db::init_db(&path)?;
+    assert_eq!(doctor::check_database(&path).status, CheckStatus::Ok);
+
+    // Something that isn't a database can't be written to
+    let bogus = dir.path().join("bogus.sqlite");
+    std::fs::write(&bogus, "not a database")?;
+    let check = doctor::check_database(&bogus.to_string_lossy());
+    assert_eq!(check.status, CheckStatus::Failed);
+    assert!(check.hint.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn test_cache_dir_is_created() -> Result<()> {
+    let dir = tempdir()?;
+    let cache = dir.path().join("cache");
+
+    let check = doctor::check_writable_dir("DNS cache directory", &cache);
+    assert_eq!(check.status, CheckStatus::Ok);
+    assert!(cache.is_dir());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_http_check_reads_server_time() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200).insert_header("Date", "Sat, 01 Jun 2024 12:00:00 GMT"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let client = scanner::create_http_client(5, 2)?;
+    let (check, server_time) = doctor::check_http(&client, &mock_server.uri()).await;
+    assert_eq!(check.status, CheckStatus::Ok);
+    assert_eq!(check.name, "Outbound HTTP");
+    assert_eq!(
+        server_time,
+        Some(Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap())
+    );
+
+    // Nothing listens on the discard port
+    let (check, server_time) = doctor::check_http(&client, "http://127.0.0.1:9").await;
+    assert_eq!(check.status, CheckStatus::Failed);
+    assert!(server_time.is_none());
+
+    Ok(())
+}
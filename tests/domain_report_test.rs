@@ -0,0 +1,147 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use fatt::db::{self, DomainScan};
+use fatt::domain_report;
+use fatt::risk::RiskWeights;
+use fatt::rules::{Rule, RuleSet, Severity};
+use fatt::store::{JsonlStore, ResultStore};
+use tempfile::tempdir;
+
+fn domain_scan(scan_id: &str, minutes_ago: i64, error: Option<&str>) -> DomainScan {
+    DomainScan {
+        scan_id: scan_id.to_string(),
+        domain: "example.com".to_string(),
+        scanned_at: Utc::now() - Duration::minutes(minutes_ago),
+        ips: vec![format!("192.0.2.{}", minutes_ago)],
+        technologies: vec!["nginx".to_string()],
+        error: error.map(str::to_string),
+        certificate: None,
+    }
+}
+
+#[test]
+fn test_domain_report() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let mut conn = db::init_db(db_path.to_str().unwrap())?;
+
+    db::insert_finding(&conn, "example.com", "Git", "/.git/HEAD", true)?;
+    db::insert_finding(&conn, "example.com", "Env", "/.env", true)?;
+    db::insert_finding(&conn, "example.com", "Backup", "/backup.zip", false)?;
+    db::insert_finding(&conn, "other.com", "Git", "/.git/HEAD", true)?;
+    let findings = db::get_all_findings(&conn)?;
+    let by_rule = |domain: &str, rule: &str| {
+        findings
+            .iter()
+            .filter(|f| f.domain == domain && f.rule_name == rule)
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    // Git comes and goes, Env is always there, and the newest scan failed
+    let scans = [
+        ("scan-1", 40, None, vec!["Git", "Env"]),
+        ("scan-2", 30, None, vec!["Env"]),
+        ("scan-3", 20, None, vec!["Git", "Env"]),
+        ("scan-4", 10, Some("DNS resolution failed"), vec![]),
+    ];
+    for (scan_id, minutes_ago, error, rules) in &scans {
+        conn.start_scan(scan_id, Utc::now())?;
+        conn.record_domain(&domain_scan(scan_id, *minutes_ago, *error))?;
+        let mut detected: Vec<_> = rules
+            .iter()
+            .flat_map(|rule| by_rule("example.com", rule))
+            .collect();
+        detected.extend(by_rule("other.com", "Git"));
+        conn.record_scan_findings(scan_id, &detected)?;
+    }
+
+    let ruleset = RuleSet {
+        rules: vec![
+            Rule::new("Git", "/.git/HEAD", "ref:", "Exposed Git", Severity::High),
+            Rule::new(
+                "Env",
+                "/.env",
+                "APP_KEY=",
+                "Exposed env",
+                Severity::Critical,
+            ),
+        ],
+    };
+    let report = domain_report::build_report(
+        &conn,
+        "example.com",
+        10,
+        Some(&ruleset),
+        &RiskWeights::default(),
+    )?;
+
+    // The latest scan failed, so addresses come from the one before it
+    assert_eq!(report.ips, ["192.0.2.20"]);
+    assert_eq!(report.technologies, ["nginx"]);
+    assert_eq!(report.last_scanned_at, Some(report.history[0].scanned_at));
+
+    // Only detected findings, most severe first
+    let rules: Vec<&str> = report
+        .findings
+        .iter()
+        .map(|f| f.finding.rule_name.as_str())
+        .collect();
+    assert_eq!(rules, ["Env", "Git"]);
+    assert_eq!(report.findings[0].severity, Some(Severity::Critical));
+
+    let history: Vec<&str> = report.history.iter().map(|h| h.scan_id.as_str()).collect();
+    assert_eq!(history, ["scan-4", "scan-3", "scan-2", "scan-1"]);
+    assert_eq!(
+        report.history[0].error.as_deref(),
+        Some("DNS resolution failed")
+    );
+    assert_eq!(report.history[1].detected, ["Env", "Git"]);
+
+    // Scored with the rules' severities: critical 10 and high 5
+    assert_eq!(report.risk_score, Some(15.0));
+    let risk: Vec<Option<f64>> = report.history.iter().map(|h| h.risk_score).collect();
+    assert_eq!(risk, [None, Some(15.0), Some(10.0), Some(15.0)]);
+
+    // Failed scans don't count as the rule going away
+    assert_eq!(report.flaps.len(), 1);
+    assert_eq!(report.flaps[0].rule_name, "Git");
+    assert_eq!(report.flaps[0].detected, 2);
+    assert_eq!(report.flaps[0].scans, 3);
+    assert_eq!(report.flaps[0].changes, 2);
+
+    // A shorter history only looks at the most recent scans
+    let report =
+        domain_report::build_report(&conn, "example.com", 2, None, &RiskWeights::default())?;
+    assert_eq!(report.history.len(), 2);
+    // Without the rules there's no telling the risk
+    assert!(report.risk_score.is_none());
+    assert!(report.flaps.is_empty());
+
+    let report =
+        domain_report::build_report(&conn, "unknown.com", 10, None, &RiskWeights::default())?;
+    assert!(report.last_scanned_at.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_jsonl_domain_scans() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut store = JsonlStore::open(temp_dir.path())?;
+
+    store.start_scan("scan-1", Utc::now())?;
+    store.record_domain(&domain_scan("scan-1", 20, None))?;
+    store.start_scan("scan-2", Utc::now())?;
+    store.record_domain(&domain_scan("scan-2", 10, Some("connection refused")))?;
+
+    let scans = store.domain_scans("example.com")?;
+    assert_eq!(scans.len(), 2);
+    assert_eq!(scans[0].scan_id, "scan-2");
+    assert_eq!(scans[0].error.as_deref(), Some("connection refused"));
+    assert_eq!(scans[1].ips, ["192.0.2.20"]);
+    assert_eq!(scans[1].technologies, ["nginx"]);
+    assert!(store.domain_scans("other.com")?.is_empty());
+
+    Ok(())
+}
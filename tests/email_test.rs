@@ -0,0 +1,154 @@
+use anyhow::Result;
+use chrono::Utc;
+use fatt::db::Finding;
+use fatt::email::{self, EmailConfig, EmailMode, SmtpConfig, SmtpSecurity};
+use fatt::notify::{FindingNotification, ScanNotification};
+use fatt::rules::Severity;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+mod test_helpers;
+
+fn email_config(port: u16, mode: EmailMode) -> EmailConfig {
+    EmailConfig {
+        smtp: SmtpConfig {
+            host: "127.0.0.1".to_string(),
+            port: Some(port),
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+        },
+        from: "FATT <fatt@example.com>".to_string(),
+        to: vec!["security@example.com".to_string()],
+        mode,
+        min_severity: Severity::High,
+        only_on_findings: false,
+    }
+}
+
+fn finding_notification(severity: Option<Severity>) -> FindingNotification {
+    FindingNotification {
+        scan_id: "scan-1".to_string(),
+        domain: "a.example.com".to_string(),
+        rule: "Git Config".to_string(),
+        severity,
+        path: "/.git/config".to_string(),
+        status: 200,
+        found_at: Utc::now(),
+        ..Default::default()
+    }
+}
+
+fn finding(domain: &str) -> Finding {
+    Finding {
+        matched_path: "/.git/config".to_string(),
+        status: Some(200),
+        ..test_helpers::finding(domain, "Git Config", true)
+    }
+}
+
+/// Accept one SMTP session and return the message it delivered
+async fn receive_one(listener: TcpListener) -> Result<String> {
+    let (stream, _) = listener.accept().await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    writer.write_all(b"220 localhost ESMTP\r\n").await?;
+
+    let mut data = String::new();
+    let mut in_data = false;
+    while let Some(line) = lines.next_line().await? {
+        if in_data {
+            if line == "." {
+                in_data = false;
+                writer.write_all(b"250 OK\r\n").await?;
+            } else {
+                data.push_str(&line);
+                data.push('\n');
+            }
+            continue;
+        }
+        let command = line.to_ascii_uppercase();
+        if command.starts_with("DATA") {
+            in_data = true;
+            writer.write_all(b"354 Go ahead\r\n").await?;
+        } else if command.starts_with("QUIT") {
+            writer.write_all(b"221 Bye\r\n").await?;
+            break;
+        } else {
+            writer.write_all(b"250 OK\r\n").await?;
+        }
+    }
+
+    Ok(data)
+}
+
+#[test]
+fn test_email_config_validation() {
+    let mut email = email_config(25, EmailMode::Digest);
+    assert!(email.validate().is_ok());
+
+    email.to = vec!["not an address".to_string()];
+    assert!(email.validate().is_err());
+
+    email.to.clear();
+    assert!(email.validate().is_err());
+
+    let mut email = email_config(25, EmailMode::Digest);
+    email.smtp.username = Some("fatt".to_string());
+    assert!(email.validate().is_err());
+    email.smtp.password = Some("secret".to_string());
+    assert!(email.validate().is_ok());
+}
+
+#[test]
+fn test_alerts_on_severity() -> Result<()> {
+    let email = email_config(25, EmailMode::Alerts);
+    assert!(email.alerts_on(&finding_notification(Some(Severity::Critical))));
+    assert!(email.alerts_on(&finding_notification(Some(Severity::High))));
+    assert!(!email.alerts_on(&finding_notification(Some(Severity::Medium))));
+    assert!(!email.alerts_on(&finding_notification(None)));
+
+    // Digests never alert
+    let digest = email_config(25, EmailMode::Digest);
+    assert!(!digest.alerts_on(&finding_notification(Some(Severity::Critical))));
+
+    let message = email.alert_message(&finding_notification(Some(Severity::Critical)))?;
+    let message = String::from_utf8(message.formatted())?;
+    assert!(message.contains("Subject: FATT critical finding: Git Config on a.example.com"));
+    assert!(message.contains("Path: /.git/config"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_digest_is_sent_with_csv() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let server = tokio::spawn(receive_one(listener));
+
+    let summary = ScanNotification {
+        scan_id: "scan-1".to_string(),
+        domains: 25,
+        findings: 25,
+        ..Default::default()
+    };
+    let findings: Vec<Finding> = (0..25)
+        .map(|i| finding(&format!("host{}.example.com", i)))
+        .collect();
+    let emails = vec![
+        email_config(port, EmailMode::Digest),
+        // Alert emails aren't sent a digest
+        email_config(1, EmailMode::Alerts),
+    ];
+    email::send_digests(&emails, &summary, &findings).await;
+
+    let message = server.await??;
+    assert!(message.contains("Subject: FATT scan finished: 25 findings"));
+    assert!(message.contains("To: security@example.com"));
+    assert!(message.contains("Domains: 25"));
+    assert!(message.contains("host0.example.com  Git Config  /.git/config"));
+    assert!(message.contains("... and 5 more in the attached CSV"));
+    assert!(message.contains("filename=\"fatt-scan-1.csv\""));
+    assert!(message.contains("Content-Type: text/csv"));
+
+    Ok(())
+}
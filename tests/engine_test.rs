@@ -0,0 +1,83 @@
+use anyhow::Result;
+use fatt::prelude::*;
+use std::io::Write;
+use tempfile::{tempdir, NamedTempFile};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[test]
+fn test_builder_sets_config() {
+    let config = ScanConfig::builder()
+        .input_file("targets.txt")
+        .rules_file("rules.yaml")
+        .concurrency(25)
+        .database("findings.sqlite")
+        .schemes(&["https"])
+        .interesting_status(&[401, 403])
+        .build();
+
+    assert_eq!(config.input_file, "targets.txt");
+    assert_eq!(config.concurrency, 25);
+    assert_eq!(config.db_path, "findings.sqlite");
+    assert_eq!(config.schemes, vec!["https".to_string()]);
+    assert_eq!(config.interesting_status, vec![401, 403]);
+    assert_eq!(config.store, StoreKind::Sqlite);
+    assert_eq!(config.http_timeout, ScanConfig::default().http_timeout);
+}
+
+#[test]
+fn test_engine_rejects_invalid_config() {
+    let config = ScanConfig::builder()
+        .input_file("does-not-exist.txt")
+        .build();
+    let error = ScanEngine::new(config).unwrap_err();
+    assert!(matches!(error, FattError::Input(_)));
+    assert!(error.to_string().contains("does-not-exist.txt"));
+
+    let input = NamedTempFile::new().unwrap();
+    let rules = NamedTempFile::new().unwrap();
+    let config = ScanConfig::builder()
+        .input_file(input.path().to_string_lossy())
+        .rules_file(rules.path().to_string_lossy())
+        .concurrency(0)
+        .build();
+    let error = ScanEngine::new(config).unwrap_err();
+    assert!(matches!(error, FattError::Config(_)));
+    assert_eq!(error.exit_code(), fatt::exit::CONFIG_ERROR);
+}
+
+#[tokio::test]
+async fn test_engine_scans_and_reads_findings() -> Result<()> {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/.git/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[core]\nbare = false"))
+        .mount(&server)
+        .await;
+
+    let mut input = NamedTempFile::new()?;
+    writeln!(input, "{}", server.address())?;
+    let mut rules = NamedTempFile::new()?;
+    writeln!(
+        rules,
+        "rules:\n  - name: Git Config\n    path: /.git/config\n    signature: \"[core]\""
+    )?;
+    let dir = tempdir()?;
+
+    let engine = ScanEngine::new(
+        ScanConfig::builder()
+            .input_file(input.path().to_string_lossy())
+            .rules_file(rules.path().to_string_lossy())
+            .database(dir.path().join("results.sqlite").to_string_lossy())
+            .quiet(true)
+            .build(),
+    )?;
+    engine.run().await?;
+
+    let findings: Vec<Finding> = engine.findings()?;
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].rule_name, "Git Config");
+    assert!(findings[0].detected);
+
+    Ok(())
+}
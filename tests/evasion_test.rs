@@ -0,0 +1,72 @@
+use anyhow::Result;
+use fatt::evasion;
+use fatt::rules::RuleSet;
+use fatt::scanner::{self, HttpClientConfig, ScanOptions, ScanResources};
+use rusqlite::Connection;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[test]
+fn test_randomized_tls_config() -> Result<()> {
+    let default_suites = rustls::crypto::ring::default_provider().cipher_suites;
+
+    let config = evasion::tls_config()?;
+    let suites = &config.crypto_provider().cipher_suites;
+
+    // Same suites on offer, only the order changes
+    assert_eq!(suites.len(), default_suites.len());
+    for suite in &default_suites {
+        assert!(suites.contains(suite));
+    }
+    assert_eq!(config.alpn_protocols, vec![b"http/1.1".to_vec()]);
+
+    Ok(())
+}
+
+#[test]
+fn test_browser_headers() {
+    let headers = evasion::browser_headers();
+    let names: HashSet<&str> = headers.iter().map(|(name, _)| name.as_str()).collect();
+
+    assert_eq!(names.len(), headers.len());
+    assert!(names.contains("accept"));
+    assert!(names.contains("accept-language"));
+}
+
+#[tokio::test]
+async fn test_evasion_requests() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/"))
+        .and(header("pragma", "no-cache"))
+        .and(header("upgrade-insecure-requests", "1"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+
+    let client = scanner::build_http_client(&HttpClientConfig {
+        evasion: true,
+        ..Default::default()
+    })?;
+    let options = ScanOptions {
+        evasion: true,
+        ..Default::default()
+    };
+    let resources = ScanResources::new(
+        client,
+        RuleSet { rules: vec![] },
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
+        Arc::new(Mutex::new(Connection::open_in_memory()?)),
+        options,
+    );
+
+    let page = resources
+        .fetch(&format!("{}/", mock_server.uri()), None)
+        .await?;
+    assert_eq!(page.status, 200);
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use fatt::evidence::{CacheValidators, Evidence, MAX_SNIPPET_BYTES};
+use fatt::{Rule, Severity};
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, LAST_MODIFIED};
+
+#[test]
+fn test_snippet_surrounds_the_match() {
+    let body = format!(
+        "{}[core]\n\tbare = false{}",
+        "x".repeat(500),
+        "y".repeat(500)
+    );
+    let offset = body.find("[core]").unwrap();
+
+    let evidence = Evidence::capture(200, body.as_bytes(), offset);
+    assert_eq!(evidence.status, 200);
+    assert_eq!(evidence.offset, 500);
+    assert_eq!(evidence.snippet.len(), MAX_SNIPPET_BYTES);
+    assert!(evidence.snippet.starts_with(&"x".repeat(64)));
+    assert!(evidence.snippet[64..].starts_with("[core]"));
+}
+
+#[test]
+fn test_snippet_of_a_short_body() {
+    let evidence = Evidence::capture(200, b"APP_KEY=base64:abc", 0);
+    assert_eq!(evidence.snippet, "APP_KEY=base64:abc");
+
+    let evidence = Evidence::capture(200, b"", 0);
+    assert_eq!(evidence.snippet, "");
+}
+
+#[test]
+fn test_snippet_drops_split_characters() {
+    // Each 'é' is two bytes; 63 of them put the snippet's start inside one
+    let body = format!("{}secret", "é".repeat(63));
+    let offset = body.find("secret").unwrap();
+
+    let evidence = Evidence::capture(200, body.as_bytes(), offset);
+    assert!(!evidence.snippet.contains(char::REPLACEMENT_CHARACTER));
+    assert!(evidence.snippet.ends_with("secret"));
+}
+
+#[test]
+fn test_body_hash_of_the_whole_body() {
+    let page = b"<html>This domain is parked</html>";
+    let first = Evidence::capture(200, page, 6);
+    let second = Evidence::capture(404, page, 0);
+    assert_eq!(first.body_hash, second.body_hash);
+    assert_eq!(first.body_hash.as_ref().map(String::len), Some(64));
+
+    let other = Evidence::capture(200, b"<html>This domain is for sale</html>", 6);
+    assert_ne!(first.body_hash, other.body_hash);
+
+    // A body only partly read has no hash, as it would cover an arbitrary prefix
+    let truncated = Evidence::capture(200, page, 6).with_truncated_body();
+    assert!(truncated.truncated);
+    assert_eq!(truncated.body_hash, None);
+}
+
+#[test]
+fn test_cache_validators() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(CacheValidators::from_headers(&headers), None);
+
+    headers.insert(ETAG, HeaderValue::from_static("W/\"abc\""));
+    headers.insert(
+        LAST_MODIFIED,
+        HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+    );
+    let evidence = Evidence::capture(200, b"secret", 0).with_validators(&headers);
+    let validators = evidence.validators.as_ref().unwrap();
+    assert_eq!(validators.etag.as_deref(), Some("W/\"abc\""));
+    assert_eq!(
+        validators.last_modified.as_deref(),
+        Some("Wed, 21 Oct 2015 07:28:00 GMT")
+    );
+
+    let revalidated = evidence.revalidated();
+    assert!(revalidated.unchanged);
+    assert_eq!(revalidated.snippet, "secret");
+}
+
+#[test]
+fn test_evidence_without_validators_still_parses() {
+    let evidence: Evidence =
+        serde_json::from_str(r#"{"status": 200, "offset": 0, "snippet": "secret"}"#).unwrap();
+    assert_eq!(evidence.validators, None);
+    assert!(!evidence.unchanged);
+    assert_eq!(evidence.body_hash, None);
+
+    // Nothing is added to evidence that has neither
+    let json = serde_json::to_value(Evidence::capture(200, b"secret", 0)).unwrap();
+    assert!(json.get("validators").is_none());
+    assert!(json.get("unchanged").is_none());
+}
+
+#[test]
+fn test_match_offset_is_the_earliest_signature() {
+    let rule = Rule::new("Env", "/.env", "DB_PASSWORD=", "desc", Severity::High)
+        .with_signatures(&["APP_KEY="], fatt::rules::MatchMode::Any);
+
+    assert_eq!(
+        rule.match_offset("APP_ENV=prod\nAPP_KEY=x\nDB_PASSWORD=y"),
+        Some(13)
+    );
+    assert_eq!(rule.match_offset("nothing here"), None);
+}
@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use fatt::canary::CanaryCheckFailed;
+use fatt::config::ScanConfig;
+use fatt::exit::{self, Classify, Failure};
+use fatt::expected::UnexpectedFindings;
+use fatt::scanner;
+use fatt::shutdown::ScanInterrupted;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+fn failing(failure: Option<Failure>) -> Result<()> {
+    let result: Result<()> = Err(anyhow::anyhow!("boom"));
+    match failure {
+        Some(failure) => result.classify(failure),
+        None => result,
+    }
+}
+
+#[test]
+fn test_exit_code_by_failure_class() {
+    let code = |failure| exit::exit_code(&failing(failure).unwrap_err());
+
+    assert_eq!(code(None), exit::FAILURE);
+    assert_eq!(code(Some(Failure::Config)), exit::CONFIG_ERROR);
+    assert_eq!(code(Some(Failure::Input)), exit::INPUT_ERROR);
+    assert_eq!(code(Some(Failure::Rules)), exit::RULES_ERROR);
+    assert_eq!(code(Some(Failure::Database)), exit::DATABASE_ERROR);
+}
+
+#[test]
+fn test_exit_code_keeps_the_most_specific_class() {
+    let error = failing(Some(Failure::Input))
+        .context("Scan failed")
+        .classify(Failure::Config)
+        .unwrap_err();
+
+    assert_eq!(exit::exit_code(&error), exit::INPUT_ERROR);
+}
+
+#[test]
+fn test_exit_code_for_scan_outcomes() {
+    let interrupted = anyhow::Error::from(ScanInterrupted {
+        processed: 3,
+        total: 10,
+    });
+    assert_eq!(exit::exit_code(&interrupted), exit::INTERRUPTED);
+
+    let unexpected = anyhow::Error::from(UnexpectedFindings(2)).context("Scan #1 failed");
+    assert_eq!(exit::exit_code(&unexpected), exit::UNEXPECTED_FINDINGS);
+
+    let canary = anyhow::Error::from(CanaryCheckFailed(1));
+    assert_eq!(exit::exit_code(&canary), exit::CANARY_FAILED);
+}
+
+#[test]
+fn test_exit_code_for_sqlite_errors() {
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    let error = conn
+        .execute("SELECT * FROM missing", [])
+        .context("Failed to query findings")
+        .unwrap_err();
+
+    assert_eq!(exit::exit_code(&error), exit::DATABASE_ERROR);
+}
+
+#[tokio::test]
+async fn test_run_scan_exit_codes() -> Result<()> {
+    let mut input = NamedTempFile::new()?;
+    writeln!(input, "example.com")?;
+
+    let mut config = ScanConfig {
+        input_file: "does-not-exist.txt".to_string(),
+        ..Default::default()
+    };
+    let error = scanner::run_scan(config.clone()).await.unwrap_err();
+    assert_eq!(exit::exit_code(&error), exit::INPUT_ERROR);
+
+    config.input_file = input.path().to_string_lossy().into_owned();
+    config.rules_file = "does-not-exist.yaml".to_string();
+    let error = scanner::run_scan(config.clone()).await.unwrap_err();
+    assert_eq!(exit::exit_code(&error), exit::RULES_ERROR);
+
+    let rules = NamedTempFile::new()?;
+    config.rules_file = rules.path().to_string_lossy().into_owned();
+    config.concurrency = 0;
+    let error = scanner::run_scan(config).await.unwrap_err();
+    assert_eq!(exit::exit_code(&error), exit::CONFIG_ERROR);
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use anyhow::Result;
+use fatt::expand::{CommandExpander, TargetExpander};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_command_expander() -> Result<()> {
+    let expander = CommandExpander::new(
+        r#"printf 'www.%s\n\n# inventory\napi.%s\n' "$1" "$FATT_ENTRY""#,
+        2,
+    );
+
+    let targets = expander.expand("example.com").await?;
+    assert_eq!(targets, vec!["www.example.com", "api.example.com"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_command_expander_keeps_the_entry_out_of_the_script() -> Result<()> {
+    let expander = CommandExpander::new(r#"printf '%s\n' "$1""#, 1);
+
+    let targets = expander.expand("svc; echo injected").await?;
+    assert_eq!(targets, vec!["svc; echo injected"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_command_expander_failures() {
+    let failing = CommandExpander::new("echo 'no such service' >&2; exit 3", 1);
+    let err = failing.expand("payments").await.unwrap_err();
+    assert!(format!("{:#}", err).contains("no such service"));
+
+    let slow = CommandExpander::new("sleep 5", 1).with_timeout(Duration::from_millis(100));
+    let err = slow.expand("payments").await.unwrap_err();
+    assert!(format!("{:#}", err).contains("timed out"));
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use chrono::Utc;
+use fatt::db;
+use fatt::expected::{self, ExpectedFinding, ExpectedFindings};
+use tempfile::tempdir;
+mod test_helpers;
+use test_helpers::finding;
+
+#[test]
+fn test_load_expected_from_file() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let file_path = temp_dir.path().join("expected.yaml");
+    std::fs::write(
+        &file_path,
+        r#"expected:
+  - domain: example.com
+    rule: "Git Repository - HEAD"
+    reason: "Public mirror"
+  - domain: test.com
+    rule: "Swagger UI"
+"#,
+    )?;
+
+    let expected = expected::load_expected(file_path.to_str().unwrap())?;
+
+    assert_eq!(expected.expected.len(), 2);
+    assert_eq!(
+        expected.expected[0].reason,
+        Some("Public mirror".to_string())
+    );
+    assert!(expected.expected[1].reason.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_unexpected_findings() {
+    let expected = ExpectedFindings {
+        expected: vec![ExpectedFinding {
+            domain: "Example.com".to_string(),
+            rule: "Git Repository - HEAD".to_string(),
+            reason: None,
+        }],
+    };
+
+    // Domain comparison is case-insensitive, rule names are exact
+    assert!(expected.is_expected("example.com", "Git Repository - HEAD"));
+    assert!(!expected.is_expected("example.com", "Swagger UI"));
+
+    let findings = vec![
+        finding("example.com", "Git Repository - HEAD", true),
+        finding("example.com", "Swagger UI", true),
+        finding("other.com", "Git Repository - HEAD", true),
+    ];
+
+    let unexpected = expected.unexpected(&findings);
+    assert_eq!(unexpected.len(), 2);
+    assert!(unexpected
+        .iter()
+        .all(|f| f.domain == "other.com" || f.rule_name == "Swagger UI"));
+}
+
+#[test]
+fn test_get_detected_since() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("test.sqlite");
+    let conn = db::init_db(db_path.to_str().unwrap())?;
+
+    let before = Utc::now() - chrono::Duration::seconds(1);
+    db::insert_finding(&conn, "example.com", "rule-1", "/admin", true)?;
+    db::insert_finding(&conn, "example.com", "rule-2", "/login", false)?;
+
+    // Only detected findings are returned
+    let detected = db::get_detected_since(&conn, before)?;
+    assert_eq!(detected.len(), 1);
+    assert_eq!(detected[0].rule_name, "rule-1");
+
+    // Nothing was recorded in the future
+    let later = Utc::now() + chrono::Duration::hours(1);
+    assert!(db::get_detected_since(&conn, later)?.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use chrono::Utc;
+use fatt::incident::{self, IncidentConfig, IncidentService};
+use fatt::notify::{FindingNotification, FindingNotifier};
+use fatt::rules::Severity;
+use fatt::scanner;
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn incident(service: IncidentService, url: Option<String>) -> IncidentConfig {
+    IncidentConfig {
+        service,
+        key: "integration-key".to_string(),
+        min_severity: Severity::High,
+        url,
+    }
+}
+
+fn finding(domain: &str, severity: Severity) -> FindingNotification {
+    FindingNotification {
+        scan_id: "scan-1".to_string(),
+        domain: domain.to_string(),
+        rule: "Git Config".to_string(),
+        severity: Some(severity),
+        path: "/.git/config".to_string(),
+        status: 200,
+        found_at: Utc::now(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_incident_config_validation() {
+    let mut config = incident(IncidentService::Pagerduty, None);
+    assert!(config.validate().is_ok());
+
+    config.url = Some("not a url".to_string());
+    assert!(config.validate().is_err());
+
+    let mut config = incident(IncidentService::Opsgenie, None);
+    config.key = " ".to_string();
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_alerts_on_severity() {
+    let config = incident(IncidentService::Pagerduty, None);
+    assert!(config.alerts_on(&finding("example.com", Severity::Critical)));
+    assert!(config.alerts_on(&finding("example.com", Severity::High)));
+    assert!(!config.alerts_on(&finding("example.com", Severity::Medium)));
+
+    let mut unknown = finding("example.com", Severity::Critical);
+    unknown.severity = None;
+    assert!(!config.alerts_on(&unknown));
+}
+
+#[test]
+fn test_dedup_key() {
+    assert_eq!(
+        incident::dedup_key("example.com", "Git Config"),
+        "fatt:example.com:Git Config"
+    );
+    // The same domain and rule always share a key
+    assert_eq!(
+        incident::dedup_key("example.com", "Git Config"),
+        incident::dedup_key("example.com", "Git Config")
+    );
+    assert_ne!(
+        incident::dedup_key("example.com", "Git Config"),
+        incident::dedup_key("example.com", "Env File")
+    );
+
+    // Too long for PagerDuty, so hashed
+    let long_rule = "x".repeat(300);
+    let key = incident::dedup_key("example.com", &long_rule);
+    assert!(key.len() <= 255);
+    assert_eq!(key, incident::dedup_key("example.com", &long_rule));
+}
+
+#[test]
+fn test_payloads() {
+    let finding = finding("example.com", Severity::Critical);
+
+    let pagerduty = incident(IncidentService::Pagerduty, None).payload(&finding);
+    assert_eq!(pagerduty["routing_key"], "integration-key");
+    assert_eq!(pagerduty["event_action"], "trigger");
+    assert_eq!(pagerduty["dedup_key"], "fatt:example.com:Git Config");
+    assert_eq!(pagerduty["payload"]["severity"], "critical");
+    assert_eq!(pagerduty["payload"]["source"], "example.com");
+    assert_eq!(
+        pagerduty["payload"]["summary"],
+        "FATT: Git Config on example.com"
+    );
+    assert_eq!(
+        pagerduty["payload"]["custom_details"]["Path"],
+        "/.git/config"
+    );
+
+    let opsgenie = incident(IncidentService::Opsgenie, None).payload(&finding);
+    assert_eq!(opsgenie["alias"], "fatt:example.com:Git Config");
+    assert_eq!(opsgenie["priority"], "P1");
+    assert_eq!(opsgenie["message"], "FATT: Git Config on example.com");
+    assert!(opsgenie.get("routing_key").is_none());
+}
+
+#[tokio::test]
+async fn test_finding_notifier_raises_alerts() -> Result<()> {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v2/enqueue"))
+        .and(body_partial_json(serde_json::json!({
+            "dedup_key": "fatt:a.example.com:Git Config",
+            "event_action": "trigger",
+        })))
+        .respond_with(ResponseTemplate::new(202))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v2/alerts"))
+        .and(header("authorization", "GenieKey integration-key"))
+        .and(body_partial_json(serde_json::json!({
+            "alias": "fatt:a.example.com:Git Config",
+        })))
+        .respond_with(ResponseTemplate::new(202))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let incidents = vec![
+        incident(
+            IncidentService::Pagerduty,
+            Some(format!("{}/v2/enqueue", mock_server.uri())),
+        ),
+        incident(
+            IncidentService::Opsgenie,
+            Some(format!("{}/v2/alerts", mock_server.uri())),
+        ),
+    ];
+
+    let client = scanner::create_http_client(5, 2)?;
+    let notifier = FindingNotifier::start(client, &[], &[], &incidents).unwrap();
+    notifier.notify(finding("a.example.com", Severity::Critical));
+    // Below the minimum severity
+    notifier.notify(finding("b.example.com", Severity::Low));
+    notifier.finish().await;
+    assert_eq!(notifier.dropped(), 0);
+
+    Ok(())
+}
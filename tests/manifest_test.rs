@@ -0,0 +1,285 @@
+use anyhow::Result;
+use fatt::anomaly::RuleAnomaly;
+use fatt::manifest::Manifest;
+use fatt::notify::{self, ScanNotification, WebhookConfig, WebhookEvent, WebhookFormat};
+use fatt::scanner;
+use std::fs;
+use tempfile::tempdir;
+use wiremock::matchers::{body_partial_json, header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[test]
+fn test_manifest_to_scan_config() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let manifest_path = temp_dir.path().join("scan.yaml");
+    fs::write(
+        &manifest_path,
+        r#"
+name: nightly
+targets:
+  file: domains.txt
+rules:
+  - rules.yaml
+  - /etc/fatt/extra.yaml
+tuning:
+  concurrency: 25
+  timeout: 7
+  schemes: [http, https]
+  risk_weights: "critical=20"
+sinks:
+  - format: csv
+    path: out/findings.csv
+notifications:
+  webhooks:
+    - url: https://hooks.example.com/abc
+schedule:
+  interval_secs: 3600
+"#,
+    )?;
+
+    let manifest = Manifest::from_file(manifest_path.to_str().unwrap())?;
+    assert_eq!(manifest.name.as_deref(), Some("nightly"));
+    assert_eq!(manifest.schedule.as_ref().unwrap().interval_secs, 3600);
+
+    let config = manifest.to_scan_config()?;
+    let base = temp_dir.path();
+
+    // Relative paths follow the manifest, absolute ones are kept
+    assert_eq!(
+        config.input_file,
+        base.join("domains.txt").to_string_lossy()
+    );
+    assert_eq!(config.rules_file, base.join("rules.yaml").to_string_lossy());
+    assert_eq!(config.extra_rules_files, vec!["/etc/fatt/extra.yaml"]);
+    assert_eq!(
+        config.db_path,
+        base.join("results.sqlite").to_string_lossy()
+    );
+    assert_eq!(
+        config.sinks[0].path,
+        base.join("out/findings.csv").to_string_lossy()
+    );
+
+    assert_eq!(config.concurrency, 25);
+    assert_eq!(config.http_timeout, 7);
+    assert_eq!(config.schemes, vec!["http", "https"]);
+    assert_eq!(config.risk_weights.critical, 20.0);
+    assert_eq!(config.webhooks.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_manifest_tagged_targets() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let manifest_path = temp_dir.path().join("scan.yaml");
+    fs::write(
+        &manifest_path,
+        r#"
+targets:
+  - file: prod.txt
+    tag: prod
+  - file: staging.txt
+    tag: staging
+rules: [rules.yaml]
+sinks:
+  - format: csv
+    path: findings.csv
+    split_by: tag
+"#,
+    )?;
+
+    let config = Manifest::from_file(manifest_path.to_str().unwrap())?.to_scan_config()?;
+    let base = temp_dir.path();
+
+    let inputs = config.inputs();
+    assert_eq!(inputs.len(), 2);
+    assert_eq!(inputs[0].file, base.join("prod.txt").to_string_lossy());
+    assert_eq!(inputs[0].tag.as_deref(), Some("prod"));
+    assert_eq!(inputs[1].file, base.join("staging.txt").to_string_lossy());
+    assert_eq!(inputs[1].tag.as_deref(), Some("staging"));
+    assert_eq!(config.sinks[0].split_by, Some(fatt::db::SplitBy::Tag));
+
+    Ok(())
+}
+
+#[test]
+fn test_manifest_rejects_unknown_keys() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let manifest_path = temp_dir.path().join("scan.yaml");
+    fs::write(
+        &manifest_path,
+        "targets:\n  file: domains.txt\nrules: [rules.yaml]\ntuning:\n  concurency: 5\n",
+    )?;
+
+    let err = Manifest::from_file(manifest_path.to_str().unwrap()).unwrap_err();
+    assert!(format!("{:#}", err).contains("concurency"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_webhook_notification() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .and(body_partial_json(
+            serde_json::json!({ "scan_id": "scan-1", "findings": 0 }),
+        ))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let notification = ScanNotification {
+        scan_id: "scan-1".to_string(),
+        domains: 10,
+        findings: 0,
+        unexpected: 0,
+        risk_score: 0.0,
+        anomalies: Vec::new(),
+    };
+    let webhooks = vec![
+        WebhookConfig {
+            url: format!("{}/hook", mock_server.uri()),
+            on: WebhookEvent::ScanFinished,
+            format: WebhookFormat::Json,
+            only_on_findings: false,
+            secret: None,
+            template: None,
+        },
+        // Skipped: nothing was found
+        WebhookConfig {
+            url: format!("{}/hook", mock_server.uri()),
+            on: WebhookEvent::ScanFinished,
+            format: WebhookFormat::Json,
+            only_on_findings: true,
+            secret: None,
+            template: None,
+        },
+    ];
+
+    let client = scanner::create_http_client(5, 2)?;
+    notify::notify_all(&client, &webhooks, &notification).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_webhook_notification_of_anomalies() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .and(body_partial_json(serde_json::json!({
+            "findings": 0,
+            "anomalies": [{ "rule_name": "Git Config", "current": 0 }],
+        })))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // Nothing was found, but a rule that used to match everywhere stopped matching
+    let notification = ScanNotification {
+        scan_id: "scan-1".to_string(),
+        domains: 10,
+        findings: 0,
+        anomalies: vec![RuleAnomaly {
+            rule_name: "Git Config".to_string(),
+            baseline: 40.0,
+            current: 0,
+        }],
+        ..Default::default()
+    };
+    let webhooks = vec![WebhookConfig {
+        url: format!("{}/hook", mock_server.uri()),
+        on: WebhookEvent::ScanFinished,
+        format: WebhookFormat::Json,
+        only_on_findings: true,
+        secret: None,
+        template: None,
+    }];
+
+    let client = scanner::create_http_client(5, 2)?;
+    notify::notify_all(&client, &webhooks, &notification).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_signed_webhook_notification() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .and(header_exists(notify::TIMESTAMP_HEADER))
+        .and(header_exists(notify::SIGNATURE_HEADER))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let notification = ScanNotification {
+        scan_id: "scan-1".to_string(),
+        domains: 10,
+        findings: 2,
+        unexpected: 0,
+        risk_score: 12.5,
+        anomalies: Vec::new(),
+    };
+    let webhook = WebhookConfig {
+        url: format!("{}/hook", mock_server.uri()),
+        on: WebhookEvent::ScanFinished,
+        format: WebhookFormat::Json,
+        only_on_findings: false,
+        secret: Some("s3cret".to_string()),
+        template: None,
+    };
+
+    let client = scanner::create_http_client(5, 2)?;
+    notify::send_webhook(&client, &webhook, &notification).await?;
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let request = &requests[0];
+    let header = |name: &str| {
+        request
+            .headers
+            .iter()
+            .find(|(key, _)| key.as_str().eq_ignore_ascii_case(name))
+            .map(|(_, values)| values.last().as_str().to_string())
+            .unwrap()
+    };
+    let timestamp: i64 = header(notify::TIMESTAMP_HEADER).parse()?;
+    let signature = &header(notify::SIGNATURE_HEADER);
+    let now = chrono::Utc::now().timestamp();
+
+    assert!(notify::verify_signature(
+        "s3cret",
+        timestamp,
+        &request.body,
+        signature,
+        now,
+        300
+    ));
+    // Wrong secret, tampered body, or replayed too late
+    assert!(!notify::verify_signature(
+        "other",
+        timestamp,
+        &request.body,
+        signature,
+        now,
+        300
+    ));
+    assert!(!notify::verify_signature(
+        "s3cret", timestamp, b"{}", signature, now, 300
+    ));
+    assert!(!notify::verify_signature(
+        "s3cret",
+        timestamp,
+        &request.body,
+        signature,
+        now + 600,
+        300
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+use fatt::db;
+use fatt::exit;
+use fatt::migrate;
+use fatt::store;
+use rusqlite::Connection;
+use tempfile::tempdir;
+
+#[test]
+fn test_new_database_is_at_latest_version() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("results.sqlite");
+
+    let conn = db::init_db(&path.to_string_lossy())?;
+    assert_eq!(migrate::schema_version(&conn)?, migrate::latest_version());
+
+    let recorded: u32 = conn.query_row("SELECT COUNT(*) FROM schema_migrations", [], |row| {
+        row.get(0)
+    })?;
+    assert_eq!(recorded as usize, migrate::MIGRATIONS.len());
+
+    Ok(())
+}
+
+#[test]
+fn test_untracked_database_is_upgraded_in_place() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("results.sqlite");
+    let path = path.to_string_lossy();
+
+    // The schema before columns were added and migrations were tracked
+    let conn = Connection::open(path.as_ref())?;
+    conn.execute_batch(
+        "CREATE TABLE findings (
+            id INTEGER PRIMARY KEY,
+            domain TEXT,
+            rule_name TEXT,
+            matched_path TEXT,
+            detected INTEGER,
+            scanned_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(domain, rule_name)
+        );
+        INSERT INTO findings (domain, rule_name, matched_path, detected)
+            VALUES ('example.com', 'Git Config', '/.git/config', 1);",
+    )?;
+    drop(conn);
+    assert_eq!(
+        migrate::schema_version(&Connection::open(path.as_ref())?)?,
+        0
+    );
+
+    let conn = db::init_db(&path)?;
+    assert_eq!(migrate::schema_version(&conn)?, migrate::latest_version());
+
+    let findings = db::get_all_findings(&conn)?;
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].domain, "example.com");
+    assert_eq!(findings[0].status, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_migrate_is_idempotent() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("results.sqlite");
+    let path = path.to_string_lossy();
+    drop(db::init_db(&path)?);
+
+    let reports = store::migrate_database(&path, 1)?;
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].1.applied.is_empty());
+    assert_eq!(reports[0].1.version, migrate::latest_version());
+
+    // Shards are migrated along with the database
+    drop(db::ShardedDb::open(&path, 2)?);
+    assert_eq!(store::migrate_database(&path, 2)?.len(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_newer_schema_is_refused() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("results.sqlite");
+    let path = path.to_string_lossy();
+
+    let conn = db::init_db(&path)?;
+    conn.execute(
+        "INSERT INTO schema_migrations (version, name) VALUES (?, 'from the future')",
+        [migrate::latest_version() + 1],
+    )?;
+    drop(conn);
+
+    let error = db::init_db(&path).unwrap_err();
+    assert!(error.to_string().contains("upgrade fatt"));
+    assert_eq!(exit::exit_code(&error), exit::DATABASE_ERROR);
+
+    Ok(())
+}
+
+#[test]
+fn test_missing_database_is_not_created() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("missing.sqlite");
+
+    assert!(store::migrate_database(&path.to_string_lossy(), 1).is_err());
+    assert!(!path.exists());
+
+    Ok(())
+}
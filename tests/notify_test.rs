@@ -0,0 +1,212 @@
+use anyhow::Result;
+use chrono::Utc;
+use fatt::notify::{
+    self, FindingNotification, FindingNotifier, ScanNotification, WebhookConfig, WebhookEvent,
+    WebhookFormat,
+};
+use fatt::rules::Severity;
+use fatt::scanner;
+use wiremock::matchers::{body_json, body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn finding(domain: &str) -> FindingNotification {
+    FindingNotification {
+        scan_id: "scan-1".to_string(),
+        domain: domain.to_string(),
+        rule: "Git Config".to_string(),
+        severity: Some(Severity::High),
+        path: "/.git/config".to_string(),
+        status: 200,
+        found_at: Utc::now(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_render_template() -> Result<()> {
+    let notification = serde_json::to_value(finding("example.com"))?;
+    let body = notify::render_template(
+        r#"{"text": "{{rule}} on {{ domain }}", "status": {{status}}, "tag": "{{tag}}"}"#,
+        &notification,
+    )?;
+
+    let body: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(
+        body,
+        serde_json::json!({ "text": "Git Config on example.com", "status": 200, "tag": "" })
+    );
+
+    // Values are escaped so they can't break out of the template's strings
+    let mut quoted = finding("example.com");
+    quoted.rule = r#"Say "hi""#.to_string();
+    let body = notify::render_template(r#"{"text": "{{rule}}"}"#, &serde_json::to_value(quoted)?)?;
+    let body: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(body["text"], r#"Say "hi""#);
+
+    assert!(notify::render_template(r#"{"text": {{rule}}}"#, &notification).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_webhook_config_validation() {
+    let mut webhook = WebhookConfig::on_finding("https://hooks.example.com/fatt");
+    assert_eq!(webhook.on, WebhookEvent::Finding);
+    assert!(webhook.validate().is_ok());
+
+    webhook.template = Some(r#"{"text": "{{domain}}: {{rule}}"}"#.to_string());
+    assert!(webhook.validate().is_ok());
+
+    webhook.template = Some(r#"{"text": "{{domain}}""#.to_string());
+    assert!(webhook.validate().is_err());
+
+    assert!(WebhookConfig::on_finding("not a url").validate().is_err());
+}
+
+#[tokio::test]
+async fn test_finding_notifier_retries_and_flushes() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    // The first attempt fails and is retried
+    Mock::given(method("POST"))
+        .and(path("/findings"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/findings"))
+        .and(body_partial_json(
+            serde_json::json!({ "scan_id": "scan-1", "rule": "Git Config" }),
+        ))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat"))
+        .and(body_json(
+            serde_json::json!({ "text": "Git Config at /.git/config" }),
+        ))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let mut chat = WebhookConfig::on_finding(&format!("{}/chat", mock_server.uri()));
+    chat.template = Some(r#"{"text": "{{rule}} at {{path}}"}"#.to_string());
+    let webhooks = vec![
+        WebhookConfig::on_finding(&format!("{}/findings", mock_server.uri())),
+        chat,
+        // Not notified of findings
+        WebhookConfig {
+            on: WebhookEvent::ScanFinished,
+            ..WebhookConfig::on_finding(&format!("{}/summary", mock_server.uri()))
+        },
+    ];
+
+    let client = scanner::create_http_client(5, 2)?;
+    let notifier = FindingNotifier::start(client, &webhooks, &[], &[]).unwrap();
+    notifier.notify(finding("a.example.com"));
+    notifier.notify(finding("b.example.com"));
+    notifier.finish().await;
+    assert_eq!(notifier.dropped(), 0);
+
+    // The queue is closed once the scan is over
+    notifier.notify(finding("c.example.com"));
+    assert_eq!(notifier.dropped(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_finding_notifier_needs_finding_webhooks() -> Result<()> {
+    let client = scanner::create_http_client(5, 2)?;
+    let webhooks = vec![WebhookConfig {
+        on: WebhookEvent::ScanFinished,
+        ..WebhookConfig::on_finding("https://hooks.example.com/fatt")
+    }];
+    assert!(FindingNotifier::start(client, &webhooks, &[], &[]).is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_webhook_format_for_url() {
+    assert_eq!(
+        WebhookFormat::for_url("https://contoso.webhook.office.com/webhookb2/abc"),
+        WebhookFormat::Teams
+    );
+    assert_eq!(
+        WebhookFormat::for_url("https://discord.com/api/webhooks/123/token"),
+        WebhookFormat::Discord
+    );
+    assert_eq!(
+        WebhookFormat::for_url("https://hooks.example.com/fatt"),
+        WebhookFormat::Json
+    );
+
+    let webhook = WebhookConfig::on_finding("https://discord.com/api/webhooks/123/token");
+    assert_eq!(webhook.format, WebhookFormat::Discord);
+
+    // Templates only make sense for plain JSON
+    let webhook = WebhookConfig {
+        template: Some(r#"{"text": "{{rule}}"}"#.to_string()),
+        ..webhook
+    };
+    assert!(webhook.validate().is_err());
+}
+
+#[test]
+fn test_teams_payload() -> Result<()> {
+    let webhook = WebhookConfig {
+        format: WebhookFormat::Teams,
+        ..WebhookConfig::on_finding("https://hooks.example.com/fatt")
+    };
+    let body: serde_json::Value = serde_json::from_slice(&notify::webhook_payload(
+        &webhook,
+        &finding("a.example.com"),
+    )?)?;
+
+    assert_eq!(body["type"], "message");
+    let card = &body["attachments"][0]["content"];
+    assert_eq!(card["type"], "AdaptiveCard");
+    assert_eq!(card["body"][0]["text"], "Git Config on a.example.com");
+    assert_eq!(card["body"][0]["color"], "attention");
+    let facts = card["body"][1]["facts"].as_array().unwrap();
+    assert!(facts.contains(&serde_json::json!({ "title": "Path", "value": "/.git/config" })));
+
+    Ok(())
+}
+
+#[test]
+fn test_discord_payload() -> Result<()> {
+    let webhook = WebhookConfig {
+        format: WebhookFormat::Discord,
+        on: WebhookEvent::ScanFinished,
+        ..WebhookConfig::on_finding("https://hooks.example.com/fatt")
+    };
+    let mut notification = finding("a.example.com");
+    notification.path = format!("/{}", "a".repeat(2000));
+    let body: serde_json::Value =
+        serde_json::from_slice(&notify::webhook_payload(&webhook, &notification)?)?;
+
+    let embed = &body["embeds"][0];
+    assert_eq!(embed["title"], "Git Config on a.example.com");
+    assert_eq!(embed["color"], 0xE67E22);
+    let fields = embed["fields"].as_array().unwrap();
+    let path = fields.iter().find(|field| field["name"] == "Path").unwrap();
+    assert_eq!(path["value"].as_str().unwrap().chars().count(), 1024);
+
+    // A scan summary has no severity to colour it by
+    let summary = ScanNotification {
+        scan_id: "scan-1".to_string(),
+        findings: 3,
+        ..Default::default()
+    };
+    let body: serde_json::Value =
+        serde_json::from_slice(&notify::webhook_payload(&webhook, &summary)?)?;
+    assert_eq!(body["embeds"][0]["title"], "FATT scan finished: 3 findings");
+    assert_eq!(body["embeds"][0]["color"], 0x5865F2);
+
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use fatt::otel::{self, Telemetry};
+
+#[test]
+fn test_signal_url() {
+    assert_eq!(
+        otel::signal_url("http://localhost:4318", "traces"),
+        "http://localhost:4318/v1/traces"
+    );
+    assert_eq!(
+        otel::signal_url("https://tempo.example.com/otlp/", "metrics"),
+        "https://tempo.example.com/otlp/v1/metrics"
+    );
+}
+
+#[test]
+fn test_spans_are_off_until_exporting() {
+    assert!(!otel::is_exporting());
+    assert!(Telemetry::start("not a url").is_err());
+    assert!(!otel::is_exporting());
+}
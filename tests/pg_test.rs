@@ -0,0 +1,128 @@
+#![cfg(feature = "postgres")]
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use fatt::db::TimeRange;
+use fatt::evidence::Evidence;
+use fatt::migrate;
+use fatt::pg::{self, PgStore};
+use fatt::risk::RiskReport;
+use fatt::store::{FindingDetails, ResultStore};
+use std::collections::BTreeMap;
+
+/// Database the PostgreSQL tests run against; they're skipped when it isn't set
+fn test_database() -> Option<String> {
+    std::env::var("FATT_TEST_POSTGRES_URL").ok()
+}
+
+#[test]
+fn test_postgres_store_records_a_scan() -> Result<()> {
+    let Some(url) = test_database() else {
+        eprintln!("FATT_TEST_POSTGRES_URL not set, skipping");
+        return Ok(());
+    };
+    let mut store = PgStore::open(&url)?;
+
+    // Every run gets its own domain and scans so runs don't see each other's rows
+    let run = uuid::Uuid::new_v4().to_string();
+    let domain = format!("{}.example.com", run);
+    let (first, second) = (format!("{}-1", run), format!("{}-2", run));
+
+    let started_at = Utc::now() - Duration::seconds(1);
+    store.start_scan(&first, started_at)?;
+    let redirect_chain = vec![format!("http://{}/.git/HEAD", domain)];
+    let evidence = Evidence::capture(200, b"ref: refs/heads/main", 0);
+    store.record_finding(
+        &domain,
+        "Git",
+        "/.git/HEAD",
+        true,
+        &FindingDetails {
+            user_agent: Some("agent"),
+            redirect_chain: &redirect_chain,
+            tag: Some("prod"),
+            status: Some(200),
+            ruleset_version: Some(1),
+            ruleset_digest: Some("0123456789abcdef"),
+            evidence: Some(&evidence),
+            project: Some("acme"),
+        },
+    )?;
+    store.record_finding(&domain, "Env", "/.env", false, &FindingDetails::default())?;
+
+    let detected: Vec<_> = store
+        .detected_since(started_at)?
+        .into_iter()
+        .filter(|finding| finding.domain == domain)
+        .collect();
+    assert_eq!(detected.len(), 1);
+    assert_eq!(detected[0].rule_name, "Git");
+    assert_eq!(detected[0].redirect_chain, redirect_chain);
+    assert_eq!(detected[0].tag.as_deref(), Some("prod"));
+    assert_eq!(detected[0].status, Some(200));
+    assert_eq!(detected[0].ruleset_version, Some(1));
+    assert_eq!(
+        detected[0].ruleset_digest.as_deref(),
+        Some("0123456789abcdef")
+    );
+    assert_eq!(detected[0].evidence.as_ref(), Some(&evidence));
+    assert_eq!(detected[0].project.as_deref(), Some("acme"));
+
+    store.finish_scan(&first, 1, &RiskReport::default())?;
+    store.record_scan_findings(&first, &detected)?;
+    store.record_rule_counts(&first, &BTreeMap::from([("Git".to_string(), 4)]))?;
+    let kept = store.scan_findings(&first)?;
+    assert_eq!(kept.len(), 1);
+    assert_eq!(kept[0].evidence.as_ref(), Some(&evidence));
+    assert!(store.scan_findings(&run).is_err());
+
+    store.start_scan(&second, Utc::now())?;
+    store.mark_incomplete(&second)?;
+    let (scans, baseline) = store.rule_baseline(&second, 1)?;
+    assert_eq!(scans, 1);
+    assert_eq!(baseline.get("Git"), Some(&4.0));
+
+    let sessions = store.scan_sessions(10)?;
+    let session = sessions
+        .iter()
+        .find(|session| session.id == second)
+        .unwrap();
+    assert!(session.incomplete);
+
+    let listed =
+        store.query_findings(Some(&run.to_uppercase()), None, &TimeRange::default(), 10)?;
+    assert_eq!(listed.len(), 2);
+    let future = TimeRange {
+        since: Some(Utc::now() + Duration::hours(1)),
+        until: None,
+    };
+    assert!(store
+        .query_findings(Some(&run), None, &future, 10)?
+        .is_empty());
+    assert_eq!(
+        store
+            .findings()?
+            .iter()
+            .filter(|finding| finding.domain == domain)
+            .count(),
+        2
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_postgres_migrations_are_recorded() -> Result<()> {
+    let Some(url) = test_database() else {
+        eprintln!("FATT_TEST_POSTGRES_URL not set, skipping");
+        return Ok(());
+    };
+    drop(PgStore::open(&url)?);
+
+    // Opening the store already applied every migration
+    let report = pg::migrate_database(&url)?;
+    assert!(report.applied.is_empty());
+    assert_eq!(report.version, migrate::latest_version());
+
+    Ok(())
+}
@@ -0,0 +1,400 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use fatt::config::InputSource;
+use fatt::db;
+use fatt::expand::TargetExpander;
+use fatt::pipeline::{self, MatchPool, PipelineMetrics, PipelineOptions};
+use fatt::rules::{Rule, RuleSet, Severity};
+use fatt::scanner::{self, ScanOptions, ScanResources};
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+use tokio::sync::Mutex;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn resources(ruleset: RuleSet) -> Result<ScanResources> {
+    Ok(ScanResources::new(
+        scanner::create_http_client(5, 2)?,
+        ruleset,
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
+        Arc::new(Mutex::new(db::init_db(":memory:")?)),
+        ScanOptions::default(),
+    ))
+}
+
+fn domains_file(domains: &[&str]) -> Result<NamedTempFile> {
+    let mut file = NamedTempFile::new()?;
+    for domain in domains {
+        writeln!(file, "{}", domain)?;
+    }
+    Ok(file)
+}
+
+#[tokio::test]
+async fn test_pipeline_scans_unique_domains() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.env"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("APP_KEY=secret"))
+        .mount(&mock_server)
+        .await;
+
+    let ruleset = RuleSet {
+        rules: vec![
+            Rule::new("Env", "/.env", "APP_KEY=", "desc", Severity::High),
+            Rule::new("Git", "/.git/HEAD", "ref: refs/", "desc", Severity::High),
+        ],
+    };
+    let resources = resources(ruleset)?;
+
+    let hostname = mock_server.uri().replace("http://", "");
+    let input = domains_file(&[&hostname, "# comment", "", &hostname])?;
+
+    let metrics = Arc::new(PipelineMetrics::default());
+    let domains_processed = Arc::new(AtomicUsize::new(0));
+    let scanned = pipeline::run_pipeline(
+        &[InputSource::new(input.path().to_str().unwrap(), None)],
+        &resources,
+        &PipelineOptions::default(),
+        metrics.clone(),
+        domains_processed.clone(),
+    )
+    .await?;
+
+    // The duplicate is skipped but still counts towards progress
+    assert_eq!(scanned, 1);
+    assert_eq!(domains_processed.load(Ordering::Relaxed), 2);
+    assert_eq!(resources.tasks_completed.load(Ordering::Relaxed), 2);
+    assert_eq!(resources.matches_found.load(Ordering::Relaxed), 1);
+
+    // Every queue is drained once the pipeline finishes
+    assert_eq!(metrics.snapshot(), Default::default());
+    assert!(metrics.peaks().checks >= 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pipeline_queues_stay_bounded() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_server)
+        .await;
+
+    let rules = (0..20)
+        .map(|i| {
+            Rule::new(
+                &format!("Rule {}", i),
+                &format!("/path-{}", i),
+                "x",
+                "desc",
+                Severity::Low,
+            )
+        })
+        .collect();
+    let resources = resources(RuleSet { rules })?;
+
+    let hostname = mock_server.uri().replace("http://", "");
+    let input = domains_file(&[&hostname])?;
+
+    let options = PipelineOptions {
+        queue_capacity: 1,
+        resolvers: 1,
+        fetchers: 1,
+        matchers: 1,
+    };
+    let metrics = Arc::new(PipelineMetrics::default());
+    pipeline::run_pipeline(
+        &[InputSource::new(input.path().to_str().unwrap(), None)],
+        &resources,
+        &options,
+        metrics.clone(),
+        Arc::new(AtomicUsize::new(0)),
+    )
+    .await?;
+
+    // The resolver queued 20 checks but never got more than the capacity ahead
+    assert_eq!(resources.tasks_completed.load(Ordering::Relaxed), 20);
+    let peaks = metrics.peaks();
+    assert!(peaks.checks <= 1, "checks queue peaked at {}", peaks.checks);
+    assert!(peaks.pages <= 1, "pages queue peaked at {}", peaks.pages);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pipeline_missing_input_file() -> Result<()> {
+    let resources = resources(RuleSet { rules: vec![] })?;
+
+    let result = pipeline::run_pipeline(
+        &[InputSource::new("/nonexistent/domains.txt", None)],
+        &resources,
+        &PipelineOptions::default(),
+        Arc::new(PipelineMetrics::default()),
+        Arc::new(AtomicUsize::new(0)),
+    )
+    .await;
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pipeline_expands_rule_templates() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    let hostname = mock_server.uri().replace("http://", "");
+    let host = hostname.split(':').next().unwrap().to_string();
+
+    Mock::given(path(format!("/{}.sql", host)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!("-- Dump of {}", host)))
+        .mount(&mock_server)
+        .await;
+
+    let ruleset = RuleSet {
+        rules: vec![Rule::new(
+            "SQL Backup",
+            "/{{host}}.sql",
+            "-- Dump of {{host}}",
+            "desc",
+            Severity::High,
+        )],
+    };
+    let resources = resources(ruleset)?;
+    let input = domains_file(&[&hostname])?;
+
+    pipeline::run_pipeline(
+        &[InputSource::new(input.path().to_str().unwrap(), None)],
+        &resources,
+        &PipelineOptions::default(),
+        Arc::new(PipelineMetrics::default()),
+        Arc::new(AtomicUsize::new(0)),
+    )
+    .await?;
+
+    assert_eq!(resources.matches_found.load(Ordering::Relaxed), 1);
+    let findings = resources.store.lock().await.findings()?;
+    assert_eq!(findings[0].matched_path, format!("/{}.sql", host));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pipeline_tags_findings_by_input() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.env"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("APP_KEY=secret"))
+        .mount(&mock_server)
+        .await;
+
+    let ruleset = RuleSet {
+        rules: vec![Rule::new(
+            "Env",
+            "/.env",
+            "APP_KEY=",
+            "desc",
+            Severity::High,
+        )],
+    };
+    let resources = resources(ruleset)?;
+
+    // The same server under two names, and once more in the second file
+    let hostname = mock_server.uri().replace("http://", "");
+    let alias = hostname.replace("127.0.0.1", "localhost");
+    let prod = domains_file(&[&hostname])?;
+    let staging = domains_file(&[&alias, &hostname])?;
+
+    let domains_processed = Arc::new(AtomicUsize::new(0));
+    let scanned = pipeline::run_pipeline(
+        &[
+            InputSource::new(prod.path().to_str().unwrap(), Some("prod")),
+            InputSource::new(staging.path().to_str().unwrap(), Some("staging")),
+        ],
+        &resources,
+        &PipelineOptions::default(),
+        Arc::new(PipelineMetrics::default()),
+        domains_processed.clone(),
+    )
+    .await?;
+
+    assert_eq!(scanned, 2);
+    assert_eq!(domains_processed.load(Ordering::Relaxed), 3);
+
+    // A domain listed in both files keeps the tag of the first
+    let findings = resources.store.lock().await.findings()?;
+    let tag_of = |domain: &str| {
+        findings
+            .iter()
+            .find(|finding| finding.domain == domain)
+            .and_then(|finding| finding.tag.clone())
+    };
+    assert_eq!(tag_of(&hostname).as_deref(), Some("prod"));
+    assert_eq!(tag_of(&alias).as_deref(), Some("staging"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_match_pool_runs_off_the_runtime() -> Result<()> {
+    let pool = MatchPool::new(2)?;
+    assert_eq!(pool.threads(), 2);
+
+    let thread = pool
+        .run(|| std::thread::current().name().map(str::to_string))
+        .await?;
+    assert!(thread.unwrap().starts_with("fatt-match-"));
+
+    // A panicking job fails on its own and leaves the pool usable
+    let failed = pool.run(|| -> usize { panic!("bad body") }).await;
+    assert!(failed.is_err());
+    assert_eq!(pool.run(|| 1 + 1).await?, 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pipeline_skips_false_positives() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.env"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("APP_KEY=secret"))
+        .mount(&mock_server)
+        .await;
+
+    let ruleset = RuleSet {
+        rules: vec![
+            Rule::new("Env", "/.env", "APP_KEY=", "desc", Severity::High),
+            Rule::new("Git", "/.git/HEAD", "ref: refs/", "desc", Severity::High),
+        ],
+    };
+    let hostname = mock_server.uri().replace("http://", "");
+    let skipped = [(hostname.clone(), "Env".to_string())]
+        .into_iter()
+        .collect();
+    let resources = resources(ruleset)?.with_skipped(skipped);
+
+    let input = domains_file(&[&hostname])?;
+    let domains_processed = Arc::new(AtomicUsize::new(0));
+    pipeline::run_pipeline(
+        &[InputSource::new(input.path().to_str().unwrap(), None)],
+        &resources,
+        &PipelineOptions::default(),
+        Arc::new(PipelineMetrics::default()),
+        domains_processed.clone(),
+    )
+    .await?;
+
+    // The skipped check counts as done without being requested
+    assert_eq!(domains_processed.load(Ordering::Relaxed), 1);
+    assert_eq!(resources.tasks_completed.load(Ordering::Relaxed), 2);
+    assert_eq!(resources.matches_found.load(Ordering::Relaxed), 0);
+    let requests = mock_server.received_requests().await.unwrap_or_default();
+    assert!(requests.iter().all(|request| request.url.path() != "/.env"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pipeline_canonicalizes_domains() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.env"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("APP_KEY=secret"))
+        .mount(&mock_server)
+        .await;
+
+    let ruleset = RuleSet {
+        rules: vec![Rule::new(
+            "Env",
+            "/.env",
+            "APP_KEY=",
+            "desc",
+            Severity::High,
+        )],
+    };
+    let resources = resources(ruleset)?;
+
+    // The same host spelled three ways is scanned once, under one name
+    let port = mock_server.address().port();
+    let spellings = [
+        format!("localhost:{}", port),
+        format!("LocalHost.:{}", port),
+        format!(" LOCALHOST:{} ", port),
+    ];
+    let input = domains_file(&spellings.iter().map(String::as_str).collect::<Vec<_>>())?;
+
+    let scanned = pipeline::run_pipeline(
+        &[InputSource::new(input.path().to_str().unwrap(), None)],
+        &resources,
+        &PipelineOptions::default(),
+        Arc::new(PipelineMetrics::default()),
+        Arc::new(AtomicUsize::new(0)),
+    )
+    .await?;
+    resources.writer.flush().await;
+
+    assert_eq!(scanned, 1);
+    let findings = resources.store.lock().await.findings()?;
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].domain, format!("localhost:{}", port));
+
+    Ok(())
+}
+
+/// Expands service names into hosts from a fixed inventory
+struct Inventory(HashMap<String, Vec<String>>);
+
+#[async_trait]
+impl TargetExpander for Inventory {
+    async fn expand(&self, entry: &str) -> Result<Vec<String>> {
+        match self.0.get(entry) {
+            Some(hosts) => Ok(hosts.clone()),
+            None => anyhow::bail!("Unknown service {}", entry),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_pipeline_scans_expanded_targets() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.env"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("APP_KEY=secret"))
+        .mount(&mock_server)
+        .await;
+
+    let ruleset = RuleSet {
+        rules: vec![Rule::new(
+            "Env",
+            "/.env",
+            "APP_KEY=",
+            "desc",
+            Severity::High,
+        )],
+    };
+    let hostname = mock_server.uri().replace("http://", "");
+    let inventory = Inventory(HashMap::from([
+        ("Payments".to_string(), vec![hostname.clone()]),
+        // The same host twice is only scanned once
+        ("Billing".to_string(), vec![hostname.to_uppercase()]),
+        ("Retired".to_string(), vec![]),
+    ]));
+    let resources = resources(ruleset)?.with_expander(Arc::new(inventory));
+
+    let input = domains_file(&["Payments", "Billing", "Retired", "Unknown", "Payments"])?;
+    let domains_processed = Arc::new(AtomicUsize::new(0));
+    let scanned = pipeline::run_pipeline(
+        &[InputSource::new(input.path().to_str().unwrap(), None)],
+        &resources,
+        &PipelineOptions::default(),
+        Arc::new(PipelineMetrics::default()),
+        domains_processed.clone(),
+    )
+    .await?;
+
+    // Entries count towards progress; only the targets they expand into are scanned
+    assert_eq!(scanned, 1);
+    assert_eq!(domains_processed.load(Ordering::Relaxed), 5);
+    assert_eq!(resources.matches_found.load(Ordering::Relaxed), 1);
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use anyhow::Result;
+use chrono::Utc;
+use fatt::config::ScanConfig;
+use fatt::config_file::{EffectiveConfig, Source};
+use fatt::manifest::Manifest;
+use fatt::profile::ScanProfile;
+use fatt::store::{self, JsonlStore, ResultStore, StoreKind};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_parse_profile() -> Result<()> {
+    assert_eq!("stealth".parse::<ScanProfile>()?, ScanProfile::Stealth);
+    assert_eq!(
+        " Aggressive ".parse::<ScanProfile>()?,
+        ScanProfile::Aggressive
+    );
+    assert_eq!(ScanProfile::default(), ScanProfile::Normal);
+    assert_eq!(ScanProfile::Normal.to_string(), "normal");
+    assert!("turbo".parse::<ScanProfile>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_profiles_are_ordered_by_pace() {
+    let stealth = ScanProfile::Stealth.tuning();
+    let normal = ScanProfile::Normal.tuning();
+    let aggressive = ScanProfile::Aggressive.tuning();
+
+    assert!(stealth.concurrency < normal.concurrency);
+    assert!(normal.concurrency < aggressive.concurrency);
+    assert!(stealth.jitter_ms > 0);
+    assert_eq!(normal.jitter_ms, 0);
+    assert!(stealth.timeout > aggressive.timeout);
+    assert!(stealth.retry_backoff_ms > normal.retry_backoff_ms);
+    assert!(stealth.throttle_subnet > 0);
+    assert_eq!(aggressive.throttle_subnet, 0);
+}
+
+#[test]
+fn test_builder_profile_and_overrides() {
+    let config = ScanConfig::builder()
+        .profile(ScanProfile::Stealth)
+        .http_timeout(60)
+        .build();
+    let tuning = ScanProfile::Stealth.tuning();
+
+    assert_eq!(config.profile, Some(ScanProfile::Stealth));
+    assert_eq!(config.concurrency, tuning.concurrency);
+    assert_eq!(config.jitter_ms, tuning.jitter_ms);
+    assert_eq!(config.throttle_subnet, tuning.throttle_subnet);
+    assert_eq!(config.http_timeout, 60);
+}
+
+#[test]
+fn test_config_file_profile() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let path = temp_dir.path().join("fatt.toml");
+    fs::write(
+        &path,
+        r#"
+# Keys next to the profile override it, wherever they are in the table
+concurrency = 3
+profile = "stealth"
+
+[profiles.fast]
+profile = "aggressive"
+"#,
+    )?;
+    let path = path.to_str().unwrap();
+
+    let effective = EffectiveConfig::load(Some(path), None, &[])?;
+    assert_eq!(effective.config.profile, Some(ScanProfile::Stealth));
+    assert_eq!(effective.config.concurrency, 3);
+    assert_eq!(
+        effective.config.jitter_ms,
+        ScanProfile::Stealth.tuning().jitter_ms
+    );
+    assert_eq!(
+        effective.source("jitter_ms"),
+        &Source::File(path.to_string())
+    );
+
+    // A profile in a later layer replaces the earlier tuning
+    let effective = EffectiveConfig::load(Some(path), Some("fast"), &["retries=4".to_string()])?;
+    let aggressive = ScanProfile::Aggressive.tuning();
+    assert_eq!(effective.config.profile, Some(ScanProfile::Aggressive));
+    assert_eq!(effective.config.concurrency, aggressive.concurrency);
+    assert_eq!(effective.config.retries, 4);
+    assert_eq!(
+        effective.source("concurrency"),
+        &Source::Profile("fast".to_string())
+    );
+
+    assert!(EffectiveConfig::load(None, None, &["profile=turbo".to_string()]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_manifest_profile() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let manifest_path = temp_dir.path().join("scan.yaml");
+    fs::write(
+        &manifest_path,
+        r#"
+targets:
+  file: domains.txt
+rules:
+  - rules.yaml
+tuning:
+  profile: aggressive
+  timeout: 8
+"#,
+    )?;
+
+    let config = Manifest::from_file(manifest_path.to_str().unwrap())?.to_scan_config()?;
+    let aggressive = ScanProfile::Aggressive.tuning();
+    assert_eq!(config.profile, Some(ScanProfile::Aggressive));
+    assert_eq!(config.concurrency, aggressive.concurrency);
+    assert_eq!(config.retries, aggressive.retries);
+    assert_eq!(config.http_timeout, 8);
+
+    Ok(())
+}
+
+#[test]
+fn test_profile_is_recorded_with_scan_session() -> Result<()> {
+    let temp_dir = tempdir()?;
+
+    let database = temp_dir.path().join("results.sqlite");
+    let database = database.to_str().unwrap();
+    let mut sqlite = fatt::db::init_db(database)?;
+    sqlite.start_scan("scan-1", Utc::now())?;
+    sqlite.record_profile("scan-1", ScanProfile::Stealth)?;
+    sqlite.start_scan("scan-2", Utc::now())?;
+
+    let sessions = store::load_scan_sessions(StoreKind::Sqlite, database)?;
+    assert_eq!(sessions[0].id, "scan-2");
+    assert_eq!(sessions[0].profile, None);
+    assert_eq!(sessions[1].profile, Some(ScanProfile::Stealth));
+
+    let dir = temp_dir.path().join("results");
+    let mut jsonl = JsonlStore::open(&dir)?;
+    jsonl.start_scan("scan-1", Utc::now())?;
+    jsonl.record_profile("scan-1", ScanProfile::Aggressive)?;
+
+    let sessions = store::load_scan_sessions(StoreKind::Jsonl, dir.to_str().unwrap())?;
+    assert_eq!(sessions[0].profile, Some(ScanProfile::Aggressive));
+
+    Ok(())
+}
@@ -0,0 +1,327 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use fatt::db;
+use fatt::retention;
+use fatt::risk::RiskReport;
+use fatt::store::StoreKind;
+use flate2::read::GzDecoder;
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use tempfile::tempdir;
+
+/// A database with an old and a recent scan, each with one finding
+fn seed(db_path: &str) -> Result<Connection> {
+    let conn = db::init_db(db_path)?;
+
+    db::start_scan_session(&conn, "old", Utc::now() - Duration::days(100))?;
+    db::insert_finding(&conn, "old.com", "Git", "/.git/HEAD", true)?;
+    db::record_rule_counts(&conn, "old", &BTreeMap::from([("Git".to_string(), 1)]))?;
+    db::finish_scan_session(&conn, "old", 1, &RiskReport::default())?;
+    conn.execute(
+        "UPDATE findings SET scanned_at = datetime('now', '-100 days') WHERE domain = 'old.com'",
+        [],
+    )?;
+
+    db::start_scan_session(&conn, "new", Utc::now())?;
+    db::insert_finding(&conn, "new.com", "Env", "/.env", true)?;
+    db::finish_scan_session(&conn, "new", 1, &RiskReport::default())?;
+
+    Ok(conn)
+}
+
+fn count(conn: &Connection, table: &str) -> Result<i64> {
+    Ok(
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })?,
+    )
+}
+
+#[test]
+fn test_prune_archives_removed_rows() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_path = db_path.to_str().unwrap();
+    let archive_path = temp_dir.path().join("archive.jsonl.gz");
+    let archive = archive_path.to_str().unwrap();
+    let conn = seed(db_path)?;
+
+    let report = retention::prune(db_path, 1, 30, Some(archive))?;
+    assert_eq!(report.findings, 1);
+    assert_eq!(report.scans, 1);
+    assert_eq!(report.archive.as_deref(), Some(archive));
+
+    // Only the recent scan is left
+    assert_eq!(count(&conn, "findings")?, 1);
+    assert_eq!(count(&conn, "scans")?, 1);
+    assert_eq!(count(&conn, "rule_stats")?, 0);
+
+    // Every removed row is in the archive
+    let mut tables = Vec::new();
+    for line in BufReader::new(GzDecoder::new(File::open(&archive_path)?)).lines() {
+        let record: serde_json::Value = serde_json::from_str(&line?)?;
+        tables.push(record["table"].as_str().unwrap().to_string());
+        if record["table"] == "findings" {
+            assert_eq!(record["row"]["domain"], "old.com");
+        }
+    }
+    assert_eq!(tables, vec!["findings", "scans", "rule_stats"]);
+
+    let (path, findings): (String, i64) =
+        conn.query_row("SELECT path, findings FROM archives", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+    assert_eq!(path, archive);
+    assert_eq!(findings, 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_prune_keeps_rows_when_archive_fails() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_path = db_path.to_str().unwrap();
+    let conn = seed(db_path)?;
+
+    // An existing archive is never overwritten
+    let archive_path = temp_dir.path().join("archive.jsonl.gz");
+    fs::write(&archive_path, "earlier archive")?;
+    assert!(retention::prune(db_path, 1, 30, archive_path.to_str()).is_err());
+    assert_eq!(fs::read_to_string(&archive_path)?, "earlier archive");
+    assert_eq!(count(&conn, "findings")?, 2);
+    assert_eq!(count(&conn, "scans")?, 2);
+
+    // Without an archive the rows are just removed
+    let report = retention::prune(db_path, 1, 30, None)?;
+    assert_eq!(report.findings, 1);
+    assert!(report.archive.is_none());
+    assert_eq!(count(&conn, "archives")?, 0);
+
+    assert!(retention::prune(
+        temp_dir.path().join("missing.sqlite").to_str().unwrap(),
+        1,
+        30,
+        None
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_age() -> Result<()> {
+    assert_eq!(retention::parse_age("30d")?, Duration::days(30));
+    assert_eq!(retention::parse_age("30")?, Duration::days(30));
+    assert_eq!(retention::parse_age("2w")?, Duration::weeks(2));
+    assert_eq!(retention::parse_age("12h")?, Duration::hours(12));
+    assert_eq!(retention::parse_age("90m")?, Duration::minutes(90));
+    assert_eq!(retention::parse_age("45s")?, Duration::seconds(45));
+
+    assert!(retention::parse_age("").is_err());
+    assert!(retention::parse_age("0d").is_err());
+    assert!(retention::parse_age("d").is_err());
+    assert!(retention::parse_age("3y").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_purge_filters_findings() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_path = db_path.to_str().unwrap();
+    let conn = db::init_db(db_path)?;
+
+    db::insert_finding(&conn, "a.com", "Git", "/.git/HEAD", false)?;
+    db::insert_finding(&conn, "b.com", "Git", "/.git/HEAD", true)?;
+    db::insert_finding(&conn, "c.com", "Env", "/.env", false)?;
+    db::insert_finding(&conn, "new.com", "Git", "/.git/HEAD", false)?;
+    conn.execute(
+        "UPDATE findings SET scanned_at = datetime('now', '-40 days') WHERE domain != 'new.com'",
+        [],
+    )?;
+
+    // Only old not-detected findings of one rule
+    let removed = retention::purge(StoreKind::Sqlite, db_path, 1, "30d", Some("Git"), true)?;
+    assert_eq!(removed, 1);
+    let domains: Vec<String> = db::get_all_findings(&conn)?
+        .into_iter()
+        .map(|finding| finding.domain)
+        .collect();
+    assert_eq!(domains, vec!["b.com", "c.com", "new.com"]);
+
+    // Every old finding; scan sessions are left alone
+    db::start_scan_session(&conn, "old", Utc::now() - Duration::days(100))?;
+    let removed = retention::purge(StoreKind::Sqlite, db_path, 1, "30d", None, false)?;
+    assert_eq!(removed, 2);
+    assert_eq!(count(&conn, "findings")?, 1);
+    assert_eq!(count(&conn, "scans")?, 1);
+
+    assert!(retention::purge(StoreKind::Sqlite, db_path, 1, "soon", None, false).is_err());
+    assert!(retention::purge(
+        StoreKind::Sqlite,
+        temp_dir.path().join("missing.sqlite").to_str().unwrap(),
+        1,
+        "30d",
+        None,
+        false
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_purge_jsonl_findings() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let old = (Utc::now() - Duration::days(40)).to_rfc3339();
+    let new = Utc::now().to_rfc3339();
+
+    let finding = |domain: &str, detected: bool, scanned_at: &str| {
+        format!(
+            r#"{{"type":"finding","domain":"{}","rule_name":"Git","matched_path":"/.git/HEAD","detected":{},"scanned_at":"{}"}}"#,
+            domain, detected, scanned_at
+        )
+    };
+    let mut file = File::create(temp_dir.path().join("scan-1.jsonl"))?;
+    writeln!(
+        file,
+        r#"{{"type":"scan_started","scan_id":"scan-1","started_at":"{}"}}"#,
+        old
+    )?;
+    writeln!(file, "{}", finding("a.com", false, &old))?;
+    writeln!(file, "{}", finding("b.com", true, &old))?;
+    writeln!(file, "{}", finding("c.com", false, &old))?;
+    drop(file);
+    // c.com was scanned again since, so only a.com's findings are stale
+    let mut file = File::create(temp_dir.path().join("scan-2.jsonl"))?;
+    writeln!(
+        file,
+        r#"{{"type":"scan_started","scan_id":"scan-2","started_at":"{}"}}"#,
+        new
+    )?;
+    writeln!(file, "{}", finding("c.com", false, &new))?;
+    drop(file);
+
+    let dir = temp_dir.path().to_str().unwrap();
+    let removed = retention::purge(StoreKind::Jsonl, dir, 1, "30d", None, true)?;
+    assert_eq!(removed, 1);
+
+    let findings = fatt::store::load_findings(StoreKind::Jsonl, dir, 1)?;
+    let domains: Vec<&str> = findings.iter().map(|f| f.domain.as_str()).collect();
+    assert_eq!(domains, vec!["b.com", "c.com"]);
+    let contents = fs::read_to_string(temp_dir.path().join("scan-1.jsonl"))?;
+    assert!(!contents.contains("a.com"));
+    assert!(contents.contains("scan_started"));
+
+    Ok(())
+}
+
+#[test]
+fn test_delete_findings() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_file = temp_dir.path().join("results.sqlite");
+    let db_path = db_file.to_str().unwrap();
+
+    let conn = db::init_db(db_path)?;
+    db::insert_finding(&conn, "a.com", "Git", "/.git/HEAD", true)?;
+    db::insert_finding(&conn, "a.com", "Env", "/.env", true)?;
+    let false_positive = db::insert_finding(&conn, "b.com", "Env", "/.env", true)?;
+    db::insert_finding(&conn, "b.com", "Git", "/.git/HEAD", true)?;
+    db::insert_finding(&conn, "c.com", "Git", "/.git/HEAD", true)?;
+
+    let delete =
+        |filter: db::PurgeFilter| retention::delete(StoreKind::Sqlite, db_path, 1, &filter);
+
+    // Nothing to go by would delete everything
+    assert!(delete(db::PurgeFilter::default()).is_err());
+
+    let by_id = db::PurgeFilter {
+        id: Some(false_positive),
+        ..Default::default()
+    };
+    assert_eq!(delete(by_id)?, 1);
+
+    let by_domain = db::PurgeFilter {
+        domain: Some("a.com".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(delete(by_domain)?, 2);
+
+    let by_domain_and_rule = db::PurgeFilter {
+        domain: Some("c.com".to_string()),
+        rule: Some("Env".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(delete(by_domain_and_rule)?, 0);
+
+    let remaining: Vec<(String, String)> = db::get_all_findings(&conn)?
+        .into_iter()
+        .map(|finding| (finding.domain, finding.rule_name))
+        .collect();
+    assert_eq!(
+        remaining,
+        vec![
+            ("b.com".to_string(), "Git".to_string()),
+            ("c.com".to_string(), "Git".to_string())
+        ]
+    );
+
+    // IDs repeat across shards, and JSONL findings have none
+    let by_id = db::PurgeFilter {
+        id: Some(1),
+        ..Default::default()
+    };
+    assert!(retention::delete(StoreKind::Sqlite, db_path, 2, &by_id).is_err());
+    let dir = temp_dir.path().to_str().unwrap();
+    assert!(retention::delete(StoreKind::Jsonl, dir, 1, &by_id).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_vacuum_reclaims_space() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_file = db_path.to_str().unwrap();
+
+    let conn = seed(db_file)?;
+    let padding = "x".repeat(2000);
+    for i in 0..500 {
+        db::insert_finding(
+            &conn,
+            &format!("host{}.example.com", i),
+            "Backup",
+            &format!("/{}/{}", padding, i),
+            false,
+        )?;
+    }
+    let filter = db::PurgeFilter {
+        not_detected: true,
+        ..Default::default()
+    };
+    db::purge_findings(&conn, &filter)?;
+    drop(conn);
+
+    let reports = retention::vacuum(db_file, 1)?;
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert!(report.problems.is_empty());
+    assert!(report.free_pages > 0);
+    assert!(report.indexes > 0);
+    assert!(report.reclaimed() > 500 * 1000);
+    assert_eq!(report.size_after, fs::metadata(&db_path)?.len());
+
+    // Nothing that was kept is lost
+    let conn = Connection::open(&db_path)?;
+    assert_eq!(count(&conn, "findings")?, 2);
+
+    assert!(
+        retention::vacuum(&temp_dir.path().join("missing.sqlite").to_string_lossy(), 1).is_err()
+    );
+
+    Ok(())
+}
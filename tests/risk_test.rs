@@ -0,0 +1,87 @@
+use anyhow::Result;
+use chrono::Utc;
+use fatt::db;
+use fatt::risk::{self, RiskWeights};
+use fatt::rules::{Rule, RuleSet, Severity};
+use tempfile::tempdir;
+mod test_helpers;
+use test_helpers::finding;
+
+#[test]
+fn test_parse_risk_weights() -> Result<()> {
+    // Empty string keeps the defaults
+    assert_eq!("".parse::<RiskWeights>()?, RiskWeights::default());
+
+    let weights: RiskWeights = "critical=20, low=0.5".parse()?;
+    assert_eq!(weights.critical, 20.0);
+    assert_eq!(weights.high, RiskWeights::default().high);
+    assert_eq!(weights.low, 0.5);
+
+    assert!("urgent=5".parse::<RiskWeights>().is_err());
+    assert!("critical".parse::<RiskWeights>().is_err());
+    assert!("critical=lots".parse::<RiskWeights>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_score_findings() {
+    let ruleset = RuleSet {
+        rules: vec![
+            Rule::new("Critical Rule", "/a", "sig", "desc", Severity::Critical),
+            Rule::new("Low Rule", "/b", "sig", "desc", Severity::Low),
+        ],
+    };
+
+    let findings = vec![
+        finding("example.com", "Critical Rule", true),
+        finding("example.com", "Low Rule", true),
+        finding("test.com", "Low Rule", true),
+        // Not detected, so it doesn't count
+        finding("test.com", "Critical Rule", false),
+        // Unknown rules are scored as info
+        finding("other.com", "Removed Rule", true),
+    ];
+
+    let report = risk::score_findings(&findings, &ruleset, &RiskWeights::default());
+
+    assert_eq!(report.total, 12.0);
+    assert_eq!(report.per_domain["example.com"].score, 11.0);
+    assert_eq!(report.per_domain["example.com"].findings, 2);
+    assert_eq!(report.per_domain["test.com"].score, 1.0);
+    assert_eq!(report.per_domain["other.com"].score, 0.0);
+
+    assert_eq!(risk::risk_change_percent(0.0, 5.0), None);
+    assert_eq!(risk::risk_change_percent(50.0, 44.0), Some(-12.0));
+
+    let change = risk::RiskChange::new(50.0, 44.0);
+    assert_eq!(change.change_percent, Some(-12.0));
+    assert_eq!(change.to_string(), "50.0 → 44.0 (-12.0%)");
+    assert_eq!(risk::RiskChange::new(0.0, 5.0).to_string(), "0.0 → 5.0");
+}
+
+#[test]
+fn test_scan_session_roundtrip() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("test.sqlite");
+    let conn = db::init_db(db_path.to_str().unwrap())?;
+
+    let ruleset = RuleSet {
+        rules: vec![Rule::new("High Rule", "/a", "sig", "desc", Severity::High)],
+    };
+    let findings = vec![finding("example.com", "High Rule", true)];
+    let report = risk::score_findings(&findings, &ruleset, &RiskWeights::default());
+
+    db::start_scan_session(&conn, "scan-1", Utc::now())?;
+    db::finish_scan_session(&conn, "scan-1", 3, &report)?;
+
+    let sessions = db::get_scan_sessions(&conn, 10)?;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].id, "scan-1");
+    assert_eq!(sessions[0].domains, 3);
+    assert_eq!(sessions[0].findings, 1);
+    assert_eq!(sessions[0].risk_score, 5.0);
+    assert!(sessions[0].finished_at.is_some());
+
+    Ok(())
+}
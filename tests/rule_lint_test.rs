@@ -0,0 +1,107 @@
+use fatt::rule_lint::{self, IssueFormat};
+use fatt::rules::RuleSet;
+use std::fs;
+use tempfile::tempdir;
+
+const BROKEN: &str = r#"rules:
+  # Severity typo
+  - name: Git
+    path: /.git/HEAD
+    signature: "ref:"
+    severity: hgih
+
+  - name: Env
+    path: /.env
+    signture: APP_KEY
+    match: Al
+  - path: /backup.zip
+    signature: PK
+  - name: Json
+    path: /config.json
+    signature: "{"
+    content_type: json
+"#;
+
+#[test]
+fn test_lint_reports_every_invalid_rule() {
+    let issues = rule_lint::lint_rules(BROKEN);
+    let summary: Vec<_> = issues
+        .iter()
+        .map(|issue| {
+            (
+                issue.line,
+                issue.rule,
+                issue.field.as_deref(),
+                issue.suggestion.as_deref(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        summary,
+        vec![
+            (Some(6), Some(0), Some("severity"), Some("high")),
+            (Some(10), Some(1), Some("signture"), Some("signature")),
+            (Some(11), Some(1), Some("match"), Some("all")),
+            (Some(12), Some(2), None, None),
+            (Some(14), Some(3), None, None),
+        ]
+    );
+    assert!(issues[3].message.contains("'name'"));
+    assert!(issues[4].message.contains("content_type"));
+    assert_eq!(issues[0].name.as_deref(), Some("Git"));
+    assert_eq!(
+        issues[1].to_string(),
+        "line 10: rules[1] (Env).signture: unknown field (did you mean 'signature'?)"
+    );
+}
+
+#[test]
+fn test_lint_valid_and_unreadable_files() {
+    assert!(rule_lint::lint_rules(include_str!("../rules.yaml")).is_empty());
+
+    let issues = rule_lint::lint_rules("rules:\n  - name: [Git\n");
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.starts_with("invalid YAML"));
+    assert!(issues[0].line.is_some());
+
+    let issues = rule_lint::lint_rules("checks: []\n");
+    assert!(issues[0].message.contains("'rules'"));
+
+    let issues =
+        rule_lint::lint_rules("rules: [{name: Git, path: /x, signature: y, severity: 3}]\n");
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].line, None);
+    assert_eq!(issues[0].field.as_deref(), Some("severity"));
+}
+
+#[test]
+fn test_issues_as_json() -> anyhow::Result<()> {
+    let issues = rule_lint::lint_rules(BROKEN);
+    let json: serde_json::Value = serde_json::to_value(&issues)?;
+
+    assert_eq!(json[0]["line"], 6);
+    assert_eq!(json[0]["field"], "severity");
+    assert_eq!(json[0]["suggestion"], "high");
+    assert!(json[3].get("suggestion").is_none());
+    assert_eq!("JSON".parse::<IssueFormat>()?, IssueFormat::Json);
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_failure_names_every_problem() -> anyhow::Result<()> {
+    let temp_dir = tempdir()?;
+    let path = temp_dir.path().join("rules.yaml");
+    fs::write(&path, BROKEN)?;
+
+    let err = RuleSet::from_file(&path).unwrap_err();
+    let message = format!("{:#}", err);
+    assert!(message.contains("Failed to parse rules file"));
+    assert!(message.contains("line 6: rules[0] (Git).severity"));
+    assert!(message.contains("line 11: rules[1] (Env).match"));
+
+    assert!(rule_lint::validate_rules(path.to_str().unwrap(), IssueFormat::Text).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use anyhow::Result;
+use fatt::config::InputSource;
+use fatt::db;
+use fatt::pipeline::{self, PipelineMetrics, PipelineOptions};
+use fatt::rule_watch::RuleWatcher;
+use fatt::rules::{self, LiveRuleSet, Rule, RuleSet, Severity};
+use fatt::scanner::{self, ScanOptions, ScanResources};
+use fatt::store::SharedStore;
+use rusqlite::Connection;
+use std::fs;
+use std::io::Write;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::{tempdir, NamedTempFile};
+use tokio::sync::Mutex;
+use wiremock::matchers::path;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const RULES: &str = r#"
+rules:
+  - name: Env File
+    path: /.env
+    signature: "APP_KEY="
+"#;
+
+const MORE_RULES: &str = r#"
+rules:
+  - name: Env File
+    path: /.env
+    signature: "APP_KEY="
+  - name: Git HEAD
+    path: /.git/HEAD
+    signature: "ref: refs/"
+"#;
+
+#[test]
+fn test_live_ruleset_versions() {
+    let env = Rule::new("Env", "/.env", "APP_KEY=", "desc", Severity::High);
+    let live = LiveRuleSet::new(RuleSet {
+        rules: vec![env.clone()],
+    });
+
+    let first = live.snapshot();
+    assert_eq!(first.version, 1);
+
+    let git = Rule::new("Git", "/.git/HEAD", "ref: refs/", "desc", Severity::High);
+    let second = live.replace(RuleSet {
+        rules: vec![env, git],
+    });
+    assert_eq!(second.version, 2);
+    assert_eq!(live.snapshot().ruleset.rules.len(), 2);
+    assert_ne!(first.ruleset.digest(), second.ruleset.digest());
+
+    // Earlier snapshots are unaffected
+    assert_eq!(first.ruleset.rules.len(), 1);
+}
+
+/// Wait for the live rules to reach `version`
+async fn wait_for_version(rules: &LiveRuleSet, version: u32) -> bool {
+    for _ in 0..100 {
+        if rules.snapshot().version >= version {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn test_rule_watcher_reloads_changed_rules() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let rules_path = temp_dir.path().join("rules.yaml");
+    fs::write(&rules_path, RULES)?;
+    let rules_file = rules_path.to_str().unwrap();
+
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_path = db_path.to_str().unwrap();
+
+    let rules = Arc::new(LiveRuleSet::new(rules::load_rules_files(&[rules_file])?));
+    let store: SharedStore = Arc::new(Mutex::new(db::init_db(db_path)?));
+
+    let _watcher = RuleWatcher::start(&[rules_file], rules.clone(), store, "scan-1")?;
+
+    fs::write(&rules_path, MORE_RULES)?;
+    assert!(wait_for_version(&rules, 2).await, "rules were not reloaded");
+    assert_eq!(rules.snapshot().ruleset.rules.len(), 2);
+
+    // A broken file keeps the current rules
+    fs::write(&rules_path, "rules: [")?;
+    tokio::time::sleep(Duration::from_millis(800)).await;
+    assert_eq!(rules.snapshot().version, 2);
+
+    // The boundary is recorded with the scan session
+    let conn = Connection::open(db_path)?;
+    let (version, digest): (u32, String) = conn.query_row(
+        "SELECT version, digest FROM ruleset_versions WHERE scan_id = 'scan-1'",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    assert_eq!(version, 2);
+    assert_eq!(digest, rules.snapshot().ruleset.digest());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_domains_use_the_rules_current_when_they_start() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.git/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ref: refs/heads/main"))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_path = db_path.to_str().unwrap();
+
+    let env = Rule::new("Env", "/.env", "APP_KEY=", "desc", Severity::High);
+    let resources = ScanResources::new(
+        scanner::create_http_client(5, 2)?,
+        RuleSet {
+            rules: vec![env.clone()],
+        },
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
+        Arc::new(Mutex::new(db::init_db(db_path)?)),
+        ScanOptions::default(),
+    );
+
+    // The rules are reloaded before the domain starts
+    let git = Rule::new("Git", "/.git/HEAD", "ref: refs/", "desc", Severity::High);
+    let reloaded = resources.ruleset.replace(RuleSet {
+        rules: vec![env, git],
+    });
+    resources
+        .store
+        .lock()
+        .await
+        .record_ruleset("scan-1", &reloaded)?;
+
+    let hostname = mock_server.uri().replace("http://", "");
+    let mut input = NamedTempFile::new()?;
+    writeln!(input, "{}", hostname)?;
+
+    pipeline::run_pipeline(
+        &[InputSource::new(input.path().to_str().unwrap(), None)],
+        &resources,
+        &PipelineOptions::default(),
+        Arc::new(PipelineMetrics::default()),
+        Arc::new(AtomicUsize::new(0)),
+    )
+    .await?;
+
+    let conn = Connection::open(db_path)?;
+    let findings = db::get_all_findings(&conn)?;
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].rule_name, "Git");
+    assert_eq!(findings[0].ruleset_version, Some(2));
+
+    let (rules, digest): (i64, String) = conn.query_row(
+        "SELECT rules, digest FROM ruleset_versions WHERE scan_id = 'scan-1' AND version = 2",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    assert_eq!(rules, 2);
+    assert_eq!(digest, reloaded.ruleset.digest());
+
+    Ok(())
+}
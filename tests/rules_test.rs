@@ -1,5 +1,8 @@
 use anyhow::Result;
-use fatt::rules::{Rule, RuleSet, Severity};
+use chrono::NaiveDate;
+use fatt::rules::{
+    FollowRedirects, MatchMode, Rule, RuleSet, Severity, SignatureScan, TemplateVars,
+};
 use std::path::PathBuf;
 
 #[test]
@@ -67,6 +70,22 @@ fn test_load_rules_from_file() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_load_multiple_rules_files() -> Result<()> {
+    let ruleset = fatt::rules::load_rules_files(&[
+        "tests/data/rules/test-rules.yaml",
+        "tests/data/rules/extra-rules.yaml",
+    ])?;
+
+    // The duplicate "Test Rule 1" is skipped, the new rule is merged in by severity
+    assert_eq!(ruleset.rules.len(), 5);
+    assert_eq!(ruleset.rules[0].name, "Test Rule 1");
+    assert_eq!(ruleset.rules[0].path, "/admin");
+    assert_eq!(ruleset.rules[1].name, "Git Exposure");
+
+    Ok(())
+}
+
 #[test]
 fn test_ruleset_sort_by_severity() {
     // Create a ruleset with unsorted rules
@@ -94,3 +113,394 @@ fn test_ruleset_sort_by_severity() {
     assert_eq!(ruleset.rules[2].severity, Some(Severity::Medium));
     assert_eq!(ruleset.rules[3].severity, Some(Severity::Info));
 }
+
+#[test]
+fn test_multiple_signatures() {
+    let any = Rule::new("Env", "/.env", "APP_KEY=", "desc", Severity::High)
+        .with_signatures(&["DB_PASSWORD="], MatchMode::Any);
+    assert!(any.matches("APP_KEY=base64:abc"));
+    assert!(any.matches("DB_PASSWORD=secret"));
+    assert!(!any.matches("<html>Not Found</html>"));
+
+    let all = Rule::new("Env", "/.env", "", "desc", Severity::High)
+        .with_signatures(&["APP_KEY=", "DB_PASSWORD="], MatchMode::All);
+    assert!(all.matches("APP_KEY=base64:abc\nDB_PASSWORD=secret"));
+    assert!(!all.matches("APP_KEY=base64:abc"));
+}
+
+#[test]
+fn test_signature_translations() -> Result<()> {
+    let ruleset: RuleSet = serde_yaml::from_str(
+        r#"
+rules:
+  - name: Directory Listing
+    path: /backup/
+    signatures: ["Index of /", "Parent Directory"]
+    match: all
+    translations:
+      "Index of /": {de: "Index von /", fr: "Index de /"}
+      "Parent Directory": {de: "Übergeordnetes Verzeichnis"}
+"#,
+    )?;
+    let rule = &ruleset.rules[0];
+    rule.validate()?;
+    assert_eq!(
+        rule.signature_groups().next(),
+        Some(vec!["Index of /", "Index von /", "Index de /"])
+    );
+
+    // Each signature can be matched in any language, the original included
+    assert!(rule.matches("Index of /backup/ ... Parent Directory"));
+    assert!(rule.matches("Index von /backup/ ... Übergeordnetes Verzeichnis"));
+    assert!(rule.matches("Index de /backup/ ... Parent Directory"));
+    assert!(!rule.matches("Index von /backup/"));
+    assert_eq!(rule.match_offset("<h1>Index von /</h1>"), Some(4));
+
+    let mut scan = SignatureScan::new(rule);
+    assert!(!scan.update("Index de /backup/".as_bytes()));
+    assert!(scan.update("Index de /backup/ Übergeordnetes Verzeichnis".as_bytes()));
+
+    let rule = Rule::new("Admin", "/admin/", "Sign in", "desc", Severity::Medium);
+    let translated = rule
+        .clone()
+        .with_translations("Sign in", &[("pt-BR", "Entrar")]);
+    translated.validate()?;
+    assert!(translated.matches("<button>Entrar</button>"));
+
+    // Only signatures can be translated, under a language tag
+    assert!(rule
+        .clone()
+        .with_translations("Log in", &[("de", "Anmelden")])
+        .validate()
+        .is_err());
+    assert!(rule
+        .clone()
+        .with_translations("Sign in", &[("german", "Anmelden")])
+        .validate()
+        .is_err());
+    assert!(rule
+        .with_translations("Sign in", &[("de", "")])
+        .validate()
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_signatures_and_match_mode() -> Result<()> {
+    let ruleset: RuleSet = serde_yaml::from_str(
+        r#"
+rules:
+  - name: Env File
+    path: /.env
+    signatures: ["APP_KEY=", "DB_HOST="]
+    match: all
+  - name: Git Config
+    path: /.git/config
+    signature: "[core]"
+"#,
+    )?;
+
+    assert_eq!(ruleset.rules[0].match_mode, MatchMode::All);
+    assert_eq!(ruleset.rules[0].all_signatures().count(), 2);
+    assert_eq!(ruleset.rules[1].match_mode, MatchMode::Any);
+    assert!(ruleset.rules[1].validate().is_ok());
+
+    let empty: RuleSet = serde_yaml::from_str("rules:\n  - name: Empty\n    path: /x\n")?;
+    assert!(empty.rules[0].validate().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_negative_signatures() -> Result<()> {
+    let rule = Rule::new(
+        "Git Config",
+        "/.git/config",
+        "[core]",
+        "desc",
+        Severity::High,
+    )
+    .with_not_contains(&["<html"]);
+    assert!(rule.matches("[core]\n\trepositoryformatversion = 0"));
+    assert!(!rule.matches("<html><body>No page at /.git/config [core]</body></html>"));
+
+    let ruleset: RuleSet = serde_yaml::from_str(
+        r#"
+rules:
+  - name: Git Config
+    path: /.git/config
+    signature: "[core]"
+    negative_signature: "Page not found"
+    not_contains: ["<!DOCTYPE"]
+"#,
+    )?;
+    let rule = &ruleset.rules[0];
+    assert_eq!(rule.negative_signatures().count(), 2);
+    assert!(rule.matches("[core]"));
+    assert!(!rule.matches("[core] Page not found"));
+    assert!(!rule.matches("<!DOCTYPE html>[core]"));
+
+    let conflicting =
+        Rule::new("Bad", "/x", "abc", "desc", Severity::Low).with_not_contains(&["abc"]);
+    assert!(conflicting.validate().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_rule_templates() -> Result<()> {
+    let date = NaiveDate::from_ymd_opt(2025, 3, 7).unwrap();
+    let vars = TemplateVars::new("www.example.com:8443", date);
+    assert_eq!(vars.host, "www.example.com");
+    assert_eq!(TemplateVars::new("[::1]:8080", date).host, "[::1]");
+    assert_eq!(TemplateVars::new("example.com", date).host, "example.com");
+
+    let rule = Rule::new(
+        "SQL Backup",
+        "/{{host}}.sql",
+        "-- Dump of {{ host }}",
+        "desc",
+        Severity::High,
+    )
+    .with_not_contains(&["backup-{{date}}"]);
+    assert!(rule.is_template());
+    rule.validate()?;
+
+    let expanded = rule.expand(&vars);
+    assert_eq!(expanded.path, "/www.example.com.sql");
+    assert_eq!(expanded.signature, "-- Dump of www.example.com");
+    assert_eq!(expanded.not_contains, vec!["backup-2025-03-07"]);
+    assert_eq!(
+        vars.expand("/{{domain}}/{{unknown}}"),
+        "/www.example.com:8443/{{unknown}}"
+    );
+
+    // Rules without placeholders are not copied
+    let plain = Rule::new("Git", "/.git/HEAD", "ref: refs/", "desc", Severity::High);
+    assert!(!plain.is_template());
+    assert!(matches!(plain.expand(&vars), std::borrow::Cow::Borrowed(_)));
+
+    let unknown = Rule::new("Bad", "/{{hostname}}.zip", "PK", "desc", Severity::Low);
+    assert!(unknown.validate().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_follow_redirects() -> Result<()> {
+    let ruleset: RuleSet = serde_yaml::from_str(
+        r#"
+rules:
+  - name: Default
+    path: /login
+    signature: "Sign in"
+  - name: No Redirects
+    path: /logout
+    signature: "Location"
+    follow_redirects: false
+  - name: Long Chain
+    path: /sso
+    signature: "Welcome"
+    follow_redirects: 5
+"#,
+    )?;
+    let limits: Vec<usize> = ruleset.rules.iter().map(Rule::max_redirects).collect();
+    assert_eq!(limits, vec![3, 0, 5]);
+    assert_eq!(
+        ruleset.rules[1].follow_redirects,
+        Some(FollowRedirects::Enabled(false))
+    );
+
+    let excessive = Rule::new("Loop", "/", "x", "desc", Severity::Low)
+        .with_follow_redirects(FollowRedirects::Limit(50));
+    assert!(excessive.validate().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_matches_binary_body() {
+    let rule = Rule::new(
+        "SQLite Database",
+        "/db.sqlite",
+        "SQLite format 3",
+        "desc",
+        Severity::High,
+    )
+    .with_not_contains(&["<html"]);
+
+    // Not valid UTF-8 anywhere near the signature
+    assert!(rule.matches(b"SQLite format 3\x00\x10\x00\x01\x01\xff\xfe"));
+    assert!(rule.matches(bytes::Bytes::from_static(b"\x89\xffSQLite format 3")));
+    assert!(!rule.matches(b"\xff<html>SQLite format 3"));
+    assert!(!rule.matches(b"\xff\xfe\x00"));
+}
+
+#[test]
+fn test_content_type_filter() -> Result<()> {
+    let any = Rule::new("Any", "/", "x", "desc", Severity::Low);
+    assert!(any.accepts_content_type(None));
+    assert!(any.accepts_content_type(Some("image/png")));
+
+    let json = any.clone().with_content_type("application/json");
+    assert!(json.accepts_content_type(Some("application/json")));
+    assert!(json.accepts_content_type(Some("Application/JSON; charset=utf-8")));
+    assert!(!json.accepts_content_type(Some("text/html")));
+    assert!(!json.accepts_content_type(None));
+
+    let text = any.clone().with_content_type("text/*");
+    assert!(text.accepts_content_type(Some("text/plain")));
+    assert!(!text.accepts_content_type(Some("application/octet-stream")));
+
+    let ruleset: RuleSet = serde_yaml::from_str(
+        r#"
+rules:
+  - name: Actuator Env
+    path: /actuator/env
+    signature: "activeProfiles"
+    content_type: application/json
+"#,
+    )?;
+    assert_eq!(
+        ruleset.rules[0].content_type.as_deref(),
+        Some("application/json")
+    );
+
+    assert!(any.with_content_type("json").validate().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_interesting_status() -> Result<()> {
+    let rule = Rule::new("Git", "/.git/HEAD", "ref: refs/", "desc", Severity::High);
+    assert!(rule.is_interesting_status(403, &[401, 403]));
+    assert!(!rule.is_interesting_status(404, &[401, 403]));
+    assert!(!rule.is_interesting_status(403, &[]));
+
+    // The rule's list replaces the scan's
+    let own = rule.clone().with_interesting_status(&[405]);
+    assert!(own.is_interesting_status(405, &[401, 403]));
+    assert!(!own.is_interesting_status(403, &[401, 403]));
+
+    let ruleset: RuleSet = serde_yaml::from_str(
+        r#"
+rules:
+  - name: Git Directory
+    path: /.git/
+    signature: "Index of"
+    interesting_status: [401, 403]
+"#,
+    )?;
+    assert_eq!(ruleset.rules[0].interesting_status, Some(vec![401, 403]));
+    ruleset.rules[0].validate()?;
+
+    // Successes always count as existing, so listing them alone opts out
+    let ruleset: RuleSet = serde_yaml::from_str(
+        r#"
+rules:
+  - name: Admin Panel
+    path: /admin/
+    signature: "Dashboard"
+    exists_status: [200]
+"#,
+    )?;
+    assert_eq!(ruleset.rules[0].interesting_status, Some(vec![200]));
+    ruleset.rules[0].validate()?;
+
+    // As does an empty list
+    let ruleset: RuleSet = serde_yaml::from_str(
+        r#"
+rules:
+  - name: Admin Panel
+    path: /admin/
+    signature: "Dashboard"
+    exists_status: []
+"#,
+    )?;
+    ruleset.rules[0].validate()?;
+    assert!(!ruleset.rules[0].is_interesting_status(403, &[401, 403]));
+    assert!(!ruleset.rules[0].is_interesting_status(403, &[401, 403]));
+
+    // Redirects are followed and informational statuses never end a request
+    assert!(rule
+        .clone()
+        .with_interesting_status(&[302])
+        .validate()
+        .is_err());
+    assert!(rule.with_interesting_status(&[101]).validate().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_rule_remediation() -> Result<()> {
+    let ruleset: RuleSet = serde_yaml::from_str(
+        r#"
+rules:
+  - name: Git Directory
+    path: /.git/HEAD
+    signature: "ref: refs/"
+    remediation: |
+      Deny access to /.git in the web server configuration
+      and rotate any credentials committed to the repository.
+  - name: Env File
+    path: /.env
+    signature: "APP_KEY="
+"#,
+    )?;
+    assert!(ruleset.rules[0]
+        .remediation
+        .as_deref()
+        .is_some_and(|text| text.starts_with("Deny access to /.git")));
+    assert!(ruleset.rules[1].remediation.is_none());
+
+    // Rules without remediation serialise as before
+    let yaml = serde_yaml::to_string(&ruleset.rules[1])?;
+    assert!(!yaml.contains("remediation"));
+
+    let finding = |rule_name: &str| fatt::db::Finding {
+        domain: "example.com".to_string(),
+        rule_name: rule_name.to_string(),
+        matched_path: "/".to_string(),
+        detected: true,
+        scanned_at: chrono::Utc::now(),
+        ..Default::default()
+    };
+    let mut findings = vec![
+        finding("Git Directory"),
+        finding("Env File"),
+        finding("Gone"),
+    ];
+    fatt::db::attach_remediation(&mut findings, &ruleset);
+    assert_eq!(findings[0].remediation, ruleset.rules[0].remediation);
+    assert!(findings[1].remediation.is_none());
+    assert!(findings[2].remediation.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_signature_scan_streams_chunks() {
+    let rule = Rule::new("Env", "/.env", "APP_KEY=", "desc", Severity::High);
+    let mut scan = SignatureScan::new(&rule);
+
+    // The signature is split across two chunks
+    let mut body = b"DEBUG=true\nAPP_".to_vec();
+    assert!(!scan.update(&body));
+    body.extend_from_slice(b"KEY=secret");
+    assert!(scan.update(&body));
+
+    // Every signature has to turn up before an `all` rule settles
+    let all = Rule::new("Laravel", "/.env", "APP_KEY=", "desc", Severity::High)
+        .with_signatures(&["DB_PASSWORD="], MatchMode::All);
+    let mut scan = SignatureScan::new(&all);
+    assert!(!scan.update(b"APP_KEY=secret\n"));
+    assert!(scan.update(b"APP_KEY=secret\nDB_PASSWORD=hunter2"));
+
+    // A negative matcher could still appear later in the body
+    let negative = rule.clone().with_not_contains(&["<html"]);
+    let mut scan = SignatureScan::new(&negative);
+    assert!(!scan.update(b"APP_KEY=secret"));
+}
@@ -1,12 +1,25 @@
 use anyhow::Result;
-use fatt::rules::{Rule, RuleSet, Severity};
+use fatt::context::ScanContext;
+use fatt::evidence::Evidence;
+use fatt::rules::{FollowRedirects, Rule, RuleSet, Severity};
 use fatt::scanner;
 use rusqlite::Connection;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{header, method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
+mod test_helpers;
+
+fn test_resources(options: scanner::ScanOptions) -> Result<scanner::ScanResources> {
+    Ok(scanner::ScanResources::new(
+        scanner::create_http_client(5, 2)?,
+        RuleSet { rules: vec![] },
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
+        Arc::new(Mutex::new(Connection::open_in_memory()?)),
+        options,
+    ))
+}
 
 #[tokio::test]
 async fn test_http_client_creation() -> Result<()> {
@@ -25,7 +38,7 @@ async fn test_http_client_creation() -> Result<()> {
 
     // Make a request to test the client
     let response = client
-        .get(&format!("{}/test", mock_server.uri()))
+        .get(format!("{}/test", mock_server.uri()))
         .send()
         .await?;
 
@@ -35,83 +48,6 @@ async fn test_http_client_creation() -> Result<()> {
     Ok(())
 }
 
-#[tokio::test]
-async fn test_check_path() -> Result<()> {
-    // Start a mock server
-    let mock_server = MockServer::start().await;
-
-    // Set up mock endpoints for HEAD requests
-    Mock::given(method("HEAD"))
-        .and(path("/exists"))
-        .respond_with(ResponseTemplate::new(200))
-        .mount(&mock_server)
-        .await;
-
-    Mock::given(method("HEAD"))
-        .and(path("/not-exists"))
-        .respond_with(ResponseTemplate::new(404))
-        .mount(&mock_server)
-        .await;
-
-    // Set up fallback mock endpoints for GET requests
-    Mock::given(method("GET"))
-        .and(path("/exists"))
-        .respond_with(ResponseTemplate::new(200))
-        .mount(&mock_server)
-        .await;
-
-    Mock::given(method("GET"))
-        .and(path("/not-exists"))
-        .respond_with(ResponseTemplate::new(404))
-        .mount(&mock_server)
-        .await;
-
-    // Create HTTP client
-    let client = scanner::create_http_client(5, 2)?;
-
-    // Test path that exists
-    let exists_url = format!("{}/exists", mock_server.uri());
-    let exists_result = scanner::check_path(&client, &exists_url).await?;
-    assert!(exists_result);
-
-    // Test path that doesn't exist
-    let not_exists_url = format!("{}/not-exists", mock_server.uri());
-    let not_exists_result = scanner::check_path(&client, &not_exists_url).await?;
-    assert!(!not_exists_result);
-
-    Ok(())
-}
-
-#[tokio::test]
-async fn test_check_signature() -> Result<()> {
-    // Start a mock server
-    let mock_server = MockServer::start().await;
-
-    // Set up a mock endpoint with a specific signature
-    Mock::given(method("GET"))
-        .and(path("/admin"))
-        .respond_with(
-            ResponseTemplate::new(200).set_body_string("<html><title>Admin Panel</title></html>"),
-        )
-        .mount(&mock_server)
-        .await;
-
-    // Create HTTP client
-    let client = scanner::create_http_client(5, 2)?;
-
-    // Test matching signature
-    let admin_url = format!("{}/admin", mock_server.uri());
-    let matching_result =
-        scanner::check_signature(&client, &admin_url, "<title>Admin Panel</title>").await?;
-    assert!(matching_result);
-
-    // Test non-matching signature
-    let non_matching_result = scanner::check_signature(&client, &admin_url, "login form").await?;
-    assert!(!non_matching_result);
-
-    Ok(())
-}
-
 #[tokio::test]
 async fn test_scan_domain() -> Result<()> {
     // Start a mock server
@@ -126,12 +62,6 @@ async fn test_scan_domain() -> Result<()> {
         .mount(&mock_server)
         .await;
 
-    Mock::given(method("HEAD"))
-        .and(path("/admin"))
-        .respond_with(ResponseTemplate::new(200))
-        .mount(&mock_server)
-        .await;
-
     Mock::given(method("GET"))
         .and(path("/login"))
         .respond_with(ResponseTemplate::new(200).set_body_string(
@@ -140,24 +70,12 @@ async fn test_scan_domain() -> Result<()> {
         .mount(&mock_server)
         .await;
 
-    Mock::given(method("HEAD"))
-        .and(path("/login"))
-        .respond_with(ResponseTemplate::new(200))
-        .mount(&mock_server)
-        .await;
-
     Mock::given(method("GET"))
         .and(path("/not-found"))
         .respond_with(ResponseTemplate::new(404))
         .mount(&mock_server)
         .await;
 
-    Mock::given(method("HEAD"))
-        .and(path("/not-found"))
-        .respond_with(ResponseTemplate::new(404))
-        .mount(&mock_server)
-        .await;
-
     // Create ruleset for testing
     let ruleset = RuleSet {
         rules: vec![
@@ -185,49 +103,29 @@ async fn test_scan_domain() -> Result<()> {
         ],
     };
 
-    // Setup test DB in memory
-    let db_conn = Arc::new(Mutex::new(Connection::open_in_memory()?));
-
-    // Initialize schema with correct columns to match the actual implementation
-    {
-        let conn = db_conn.lock().await;
-        conn.execute(
-            "CREATE TABLE findings (
-                id INTEGER PRIMARY KEY,
-                domain TEXT NOT NULL,
-                rule_name TEXT NOT NULL,
-                matched_path TEXT NOT NULL,
-                detected INTEGER NOT NULL,
-                scanned_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                UNIQUE(domain, rule_name)
-            )",
-            [],
-        )?;
-    }
-
-    // Counters
-    let tasks_completed = Arc::new(AtomicUsize::new(0));
-    let matches_found = Arc::new(AtomicUsize::new(0));
+    // Setup test DB with the real schema
+    let temp_dir = tempfile::tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_conn = Arc::new(Mutex::new(fatt::db::init_db(db_path.to_str().unwrap())?));
 
     // Extract hostname from mock server URL
     let server_url = mock_server.uri();
     let hostname = server_url.strip_prefix("http://").unwrap_or(&server_url);
 
-    // Scan the mock domain
-    scanner::scan_domain(
-        &hostname,
-        &scanner::create_http_client(5, 2)?,
-        &ruleset,
-        &fatt::resolver::DnsResolver::new_for_testing()?,
+    let resources = scanner::ScanResources::new(
+        scanner::create_http_client(5, 2)?,
+        ruleset,
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
         db_conn.clone(),
-        tasks_completed.clone(),
-        matches_found.clone(),
-    )
-    .await?;
+        scanner::ScanOptions::default(),
+    );
+
+    // Scan the mock domain
+    test_helpers::scan_domain(hostname, &resources).await?;
 
     // Verify results
-    assert_eq!(tasks_completed.load(Ordering::Relaxed), 3); // 3 rules tested
-    assert_eq!(matches_found.load(Ordering::Relaxed), 1); // 1 match found (Admin Panel)
+    assert_eq!(resources.tasks_completed.load(Ordering::Relaxed), 3); // 3 rules tested
+    assert_eq!(resources.matches_found.load(Ordering::Relaxed), 1); // 1 match found (Admin Panel)
 
     // Verify database entries
     let conn = db_conn.lock().await;
@@ -270,3 +168,586 @@ async fn test_scan_domain() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_scan_option_targets() {
+    let mut options = scanner::ScanOptions::default();
+    assert_eq!(options.targets("example.com"), vec!["http://example.com"]);
+
+    options.schemes = vec!["http".to_string(), "https".to_string()];
+    options.ports = vec![80, 443, 8080];
+    assert_eq!(
+        options.targets("example.com"),
+        vec![
+            "http://example.com",
+            "http://example.com:443",
+            "http://example.com:8080",
+            "https://example.com:80",
+            "https://example.com",
+            "https://example.com:8080",
+        ]
+    );
+
+    // IPv6 addresses are bracketed, and a target's own port replaces the configured ones
+    assert_eq!(
+        options.targets("2001:db8::1"),
+        vec![
+            "http://[2001:db8::1]",
+            "http://[2001:db8::1]:443",
+            "http://[2001:db8::1]:8080",
+            "https://[2001:db8::1]:80",
+            "https://[2001:db8::1]",
+            "https://[2001:db8::1]:8080",
+        ]
+    );
+    assert_eq!(
+        options.targets("[2001:db8::1]:8443"),
+        vec!["http://[2001:db8::1]:8443", "https://[2001:db8::1]:8443"]
+    );
+    assert_eq!(
+        options.targets("127.0.0.1:8443"),
+        vec!["http://127.0.0.1:8443", "https://127.0.0.1:8443"]
+    );
+}
+
+#[tokio::test]
+async fn test_check_rule_ignores_wildcard_responses() -> Result<()> {
+    let mock_server = MockServer::start().await;
+
+    // Every path answers with the same catch-all page
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html>Welcome</html>"))
+        .mount(&mock_server)
+        .await;
+
+    let resources = test_resources(scanner::ScanOptions::default())?;
+    let hostname = mock_server.uri().replace("http://", "");
+
+    let ctx = ScanContext::prepare(&hostname, &resources).await?;
+    assert!(ctx.soft_404.is_some());
+
+    // The catch-all page is not evidence that the path exists
+    let rule = Rule::new("Git", "/.git/HEAD", "ref: refs/", "desc", Severity::High);
+    assert!(scanner::check_rule(&ctx, &resources, &rule)
+        .await?
+        .is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_rule_interesting_status() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.git/HEAD"))
+        .respond_with(ResponseTemplate::new(403).set_body_string("Forbidden"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(path("/admin"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&mock_server)
+        .await;
+
+    let resources = test_resources(scanner::ScanOptions {
+        interesting_status: vec![403],
+        ..Default::default()
+    })?;
+    let hostname = mock_server.uri().replace("http://", "");
+    let ctx = ScanContext::prepare(&hostname, &resources).await?;
+    assert_eq!(ctx.missing_status, Some(404));
+
+    // A 403 shows the path exists even though the signature can't be checked
+    let git = Rule::new("Git", "/.git/HEAD", "ref: refs/", "desc", Severity::High);
+    let outcome = scanner::check_rule(&ctx, &resources, &git).await?.unwrap();
+    assert!(outcome.detected);
+    assert!(outcome.protected);
+    assert_eq!(outcome.status, 403);
+    assert!(outcome.evidence.is_none());
+
+    // Other error statuses are still misses, unless the rule lists them
+    let admin = Rule::new("Admin", "/admin", "Dashboard", "desc", Severity::Medium);
+    assert!(scanner::check_rule(&ctx, &resources, &admin)
+        .await?
+        .is_none());
+    let admin = admin.with_interesting_status(&[401]);
+    let outcome = scanner::check_rule(&ctx, &resources, &admin)
+        .await?
+        .unwrap();
+    assert!(outcome.protected);
+    assert_eq!(outcome.status, 401);
+
+    // A rule's own list replaces the scan's
+    let git = git.with_interesting_status(&[]);
+    assert!(scanner::check_rule(&ctx, &resources, &git).await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_rule_ignores_wildcard_status() -> Result<()> {
+    let mock_server = MockServer::start().await;
+
+    // Every path is forbidden, so a 403 says nothing about any one of them
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(403))
+        .mount(&mock_server)
+        .await;
+
+    let resources = test_resources(scanner::ScanOptions {
+        interesting_status: vec![401, 403],
+        ..Default::default()
+    })?;
+    let hostname = mock_server.uri().replace("http://", "");
+    let ctx = ScanContext::prepare(&hostname, &resources).await?;
+
+    let rule = Rule::new("Git", "/.git/HEAD", "ref: refs/", "desc", Severity::High);
+    assert!(scanner::check_rule(&ctx, &resources, &rule)
+        .await?
+        .is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_http_client_with_proxy() -> Result<()> {
+    // The mock server stands in for a plain HTTP proxy: requests arrive with absolute URIs
+    let proxy_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/proxied"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("via proxy"))
+        .mount(&proxy_server)
+        .await;
+
+    let client = scanner::build_http_client(&scanner::HttpClientConfig {
+        proxy: Some(proxy_server.uri()),
+        proxy_auth: Some("user:secret".to_string()),
+        ..Default::default()
+    })?;
+
+    let response = client
+        .get("http://unreachable.invalid/proxied")
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "via proxy");
+
+    let requests = proxy_server.received_requests().await.unwrap();
+    assert!(requests[0]
+        .headers
+        .keys()
+        .any(|name| name.as_str() == "proxy-authorization"));
+
+    // Invalid proxy URLs are rejected up front
+    assert!(scanner::build_http_client(&scanner::HttpClientConfig {
+        proxy: Some("not a url".to_string()),
+        ..Default::default()
+    })
+    .is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_rule_sends_one_request() -> Result<()> {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/.env"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("APP_KEY=secret"))
+        .mount(&mock_server)
+        .await;
+
+    let resources = test_resources(scanner::ScanOptions::default())?;
+    let hostname = mock_server.uri().replace("http://", "");
+    let ctx = ScanContext::prepare(&hostname, &resources).await?;
+    let before = mock_server.received_requests().await.unwrap().len();
+
+    let rule = Rule::new("Env", "/.env", "APP_KEY=", "desc", Severity::High);
+    let outcome = scanner::check_rule(&ctx, &resources, &rule).await?;
+    assert!(outcome.is_some_and(|o| o.detected));
+
+    let missing = Rule::new("Git", "/.git/HEAD", "ref:", "desc", Severity::High);
+    assert!(scanner::check_rule(&ctx, &resources, &missing)
+        .await?
+        .is_none());
+
+    // Existence and the signature both come from a single GET per rule
+    let requests = mock_server.received_requests().await.unwrap();
+    let checks: Vec<_> = requests[before..]
+        .iter()
+        .map(|request| (request.method.to_string(), request.url.path().to_string()))
+        .collect();
+    assert_eq!(
+        checks,
+        vec![
+            ("GET".to_string(), "/.env".to_string()),
+            ("GET".to_string(), "/.git/HEAD".to_string()),
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_rule() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/admin"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string("<html><title>Admin Panel</title></html>"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let resources = test_resources(scanner::ScanOptions::default())?;
+    let hostname = mock_server.uri().replace("http://", "");
+    let ctx = ScanContext::prepare(&hostname, &resources).await?;
+
+    // One GET shows the path exists and checks its signature
+    let admin = Rule::new("Admin", "/admin", "<title>Admin", "desc", Severity::Medium);
+    let outcome = scanner::check_rule(&ctx, &resources, &admin)
+        .await?
+        .unwrap();
+    assert!(outcome.detected);
+    assert_eq!(outcome.status, 200);
+
+    // The path exists, but without the signature nothing is detected
+    let login = Rule::new("Login", "/admin", "login form", "desc", Severity::Medium);
+    let outcome = scanner::check_rule(&ctx, &resources, &login)
+        .await?
+        .unwrap();
+    assert!(!outcome.detected);
+
+    // A missing path isn't an outcome at all
+    let missing = Rule::new("Missing", "/missing", "<title>", "desc", Severity::Medium);
+    assert!(scanner::check_rule(&ctx, &resources, &missing)
+        .await?
+        .is_none());
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(
+        requests
+            .iter()
+            .filter(|request| request.url.path() == "/admin")
+            .count(),
+        2
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_circuit_breaker_skips_unreachable_host() -> Result<()> {
+    let options = scanner::ScanOptions {
+        retry: fatt::utils::RetryPolicy::new(0, 0),
+        circuit_breaker: 1,
+        ..Default::default()
+    };
+    let resources = test_resources(options)?;
+
+    // Nothing listens on port 1, so the soft-404 probe fails and opens the breaker
+    let ctx = ScanContext::prepare("127.0.0.1:1", &resources).await?;
+    assert!(ctx.breaker.is_open());
+
+    let rule = Rule::new("Git", "/.git/HEAD", "ref:", "desc", Severity::High);
+    let err = scanner::check_rule(&ctx, &resources, &rule)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Circuit breaker open"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_rule_redirect_policy() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    let uri = mock_server.uri();
+
+    // /login → /sso/start → /sso/done
+    Mock::given(path("/login"))
+        .respond_with(
+            ResponseTemplate::new(302)
+                .insert_header("Location", "/sso/start")
+                .set_body_string("Redirecting to SSO"),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(path("/sso/start"))
+        .respond_with(
+            ResponseTemplate::new(302)
+                .insert_header("Location", format!("{}/sso/done", uri).as_str()),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(path("/sso/done"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("Welcome back"))
+        .mount(&mock_server)
+        .await;
+
+    let resources = test_resources(scanner::ScanOptions::default())?;
+    let hostname = uri.replace("http://", "");
+    let ctx = ScanContext::prepare(&hostname, &resources).await?;
+
+    // By default the chain is followed and recorded
+    let rule = Rule::new("SSO", "/login", "Welcome back", "desc", Severity::Info);
+    let outcome = scanner::check_rule(&ctx, &resources, &rule).await?.unwrap();
+    assert!(outcome.detected);
+    assert_eq!(
+        outcome.redirect_chain,
+        vec![format!("{}/sso/start", uri), format!("{}/sso/done", uri)]
+    );
+
+    // Running out of hops mid-chain leaves nothing to match
+    let short = rule
+        .clone()
+        .with_follow_redirects(FollowRedirects::Limit(1));
+    assert!(scanner::check_rule(&ctx, &resources, &short)
+        .await?
+        .is_none());
+
+    // A rule that doesn't follow redirects matches the redirect response itself
+    let unfollowed = Rule::new("SSO", "/login", "Redirecting", "desc", Severity::Info)
+        .with_follow_redirects(FollowRedirects::Enabled(false));
+    let outcome = scanner::check_rule(&ctx, &resources, &unfollowed)
+        .await?
+        .unwrap();
+    assert!(outcome.detected);
+    assert!(outcome.redirect_chain.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_rule_content_type_gating() -> Result<()> {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(path("/actuator/env"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(r#"{"activeProfiles":[]}"#, "application/json"),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(path("/status"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw("<html>activeProfiles</html>", "text/html"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let resources = test_resources(scanner::ScanOptions::default())?;
+    let hostname = mock_server.uri().replace("http://", "");
+    let ctx = ScanContext::prepare(&hostname, &resources).await?;
+
+    let rule = Rule::new(
+        "Env",
+        "/actuator/env",
+        "activeProfiles",
+        "desc",
+        Severity::High,
+    )
+    .with_content_type("application/json");
+    let outcome = scanner::check_rule(&ctx, &resources, &rule).await?;
+    assert!(outcome.is_some_and(|o| o.detected));
+
+    // The path exists, but its HTML body is never downloaded or matched
+    let html = Rule::new(
+        "Status",
+        "/status",
+        "activeProfiles",
+        "desc",
+        Severity::High,
+    )
+    .with_content_type("application/json");
+    let outcome = scanner::check_rule(&ctx, &resources, &html).await?;
+    assert!(outcome.is_some_and(|o| !o.detected));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_body_download_is_capped() -> Result<()> {
+    let mock_server = MockServer::start().await;
+
+    let mut body = "x".repeat(4096);
+    body.push_str("APP_KEY=secret");
+    Mock::given(path("/.env"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body))
+        .mount(&mock_server)
+        .await;
+
+    let resources = test_resources(scanner::ScanOptions {
+        max_body_bytes: 1024,
+        ..Default::default()
+    })?;
+
+    let page = resources
+        .fetch(&format!("{}/.env", mock_server.uri()), None)
+        .await?;
+    assert_eq!(page.body.len(), 1024);
+
+    // The signature sits past the limit, so it is never seen
+    let hostname = mock_server.uri().replace("http://", "");
+    let ctx = ScanContext::prepare(&hostname, &resources).await?;
+    let rule = Rule::new("Env", "/.env", "APP_KEY=", "desc", Severity::High);
+    let outcome = scanner::check_rule(&ctx, &resources, &rule).await?;
+    assert!(outcome.is_some_and(|o| !o.detected));
+
+    // A match before the limit is found, but the body it's in is only partly read, so
+    // it isn't hashed
+    Mock::given(path("/config.env"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(format!("APP_KEY=x{}", "x".repeat(4096))),
+        )
+        .mount(&mock_server)
+        .await;
+    let rule = Rule::new("Env", "/config.env", "APP_KEY=", "desc", Severity::High);
+    let outcome = scanner::check_rule(&ctx, &resources, &rule).await?.unwrap();
+    let evidence = outcome.evidence.unwrap();
+    assert!(evidence.truncated);
+    assert_eq!(evidence.body_hash, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_scan_domain_limits_rule_concurrency() -> Result<()> {
+    let delay = std::time::Duration::from_millis(200);
+    let mock_server = MockServer::start().await;
+    Mock::given(wiremock::matchers::path_regex("^/file-"))
+        .respond_with(ResponseTemplate::new(404).set_delay(delay))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_server)
+        .await;
+
+    let ruleset = RuleSet {
+        rules: (0..4)
+            .map(|i| {
+                Rule::new(
+                    &format!("Rule {}", i),
+                    &format!("/file-{}", i),
+                    "secret",
+                    "desc",
+                    Severity::High,
+                )
+            })
+            .collect(),
+    };
+    let resources = scanner::ScanResources::new(
+        scanner::create_http_client(5, 2)?,
+        ruleset,
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
+        Arc::new(Mutex::new(Connection::open_in_memory()?)),
+        scanner::ScanOptions {
+            rule_concurrency: 2,
+            ..Default::default()
+        },
+    );
+
+    let started = std::time::Instant::now();
+    test_helpers::scan_domain(&mock_server.uri().replace("http://", ""), &resources).await?;
+
+    // Four slow checks, two at a time
+    assert!(started.elapsed() >= delay * 2);
+    assert_eq!(resources.tasks_completed.load(Ordering::Relaxed), 4);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_rule_captures_evidence() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.git/config"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string("# generated\n[core]\n\tbare = false\n"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let resources = test_resources(scanner::ScanOptions::default())?;
+    let hostname = mock_server.uri().replace("http://", "");
+    let ctx = ScanContext::prepare(&hostname, &resources).await?;
+
+    let rule = Rule::new(
+        "Git Config",
+        "/.git/config",
+        "[core]",
+        "desc",
+        Severity::High,
+    );
+    let outcome = scanner::check_rule(&ctx, &resources, &rule).await?.unwrap();
+    let evidence = outcome.evidence.unwrap();
+    assert_eq!(evidence.status, 200);
+    assert_eq!(evidence.offset, 12);
+    assert!(evidence.snippet.contains("bare = false"));
+
+    // The body was read to its end, so it's hashed
+    assert!(!evidence.truncated);
+    assert_eq!(
+        evidence.body_hash,
+        Evidence::capture(200, b"# generated\n[core]\n\tbare = false\n", 0).body_hash
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_rule_revalidates_unchanged_finding() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.git/config"))
+        .and(header("If-None-Match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(path("/.git/config"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("ETag", "\"v1\"")
+                .set_body_string("[core]\n\tbare = false\n"),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let rule = Rule::new(
+        "Git Config",
+        "/.git/config",
+        "[core]",
+        "desc",
+        Severity::High,
+    );
+    let hostname = mock_server.uri().replace("http://", "");
+
+    // The first scan keeps the response's validators with the evidence
+    let resources = test_resources(scanner::ScanOptions::default())?;
+    let ctx = ScanContext::prepare(&hostname, &resources).await?;
+    let first = scanner::check_rule(&ctx, &resources, &rule).await?.unwrap();
+    let evidence = first.evidence.unwrap();
+    assert_eq!(
+        evidence.validators.as_ref().unwrap().etag.as_deref(),
+        Some("\"v1\"")
+    );
+    assert!(!evidence.unchanged);
+
+    // The next one sends them back and keeps the finding on a 304
+    let revalidation = scanner::Revalidation {
+        matched_path: rule.path.clone(),
+        ruleset_digest: ctx.rules.digest.clone(),
+        evidence,
+    };
+    let resources = test_resources(scanner::ScanOptions::default())?.with_revalidations(vec![(
+        (ctx.domain.clone(), rule.name.clone()),
+        revalidation,
+    )]);
+    let ctx = ScanContext::prepare(&hostname, &resources).await?;
+    let second = scanner::check_rule(&ctx, &resources, &rule).await?.unwrap();
+    assert!(second.detected);
+    assert_eq!(second.status, 200);
+    let evidence = second.evidence.unwrap();
+    assert!(evidence.unchanged);
+    assert!(evidence.snippet.contains("bare = false"));
+
+    Ok(())
+}
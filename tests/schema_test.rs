@@ -0,0 +1,79 @@
+use anyhow::Result;
+use fatt::schema::{self, SchemaKind};
+use serde_json::Value;
+use tempfile::tempdir;
+
+fn property_names(schema: &Value) -> Vec<&str> {
+    schema["properties"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect()
+}
+
+#[test]
+fn test_rules_schema() {
+    let schema = schema::schema(SchemaKind::Rules).unwrap();
+    let rule = &schema["definitions"]["Rule"];
+
+    assert_eq!(rule["required"], serde_json::json!(["name", "path"]));
+    assert_eq!(rule["additionalProperties"], false);
+    let fields = property_names(rule);
+    assert!(fields.contains(&"match"));
+    assert!(fields.contains(&"remediation"));
+    assert!(!fields.contains(&"match_mode"));
+
+    let severities = serde_json::to_string(&schema["definitions"]["Severity"]).unwrap();
+    for severity in ["critical", "high", "medium", "low", "info"] {
+        assert!(severities.contains(severity));
+    }
+}
+
+#[test]
+fn test_manifest_schema() {
+    let schema = schema::schema(SchemaKind::Manifest).unwrap();
+
+    assert_eq!(schema["additionalProperties"], false);
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+    assert_eq!(required, ["rules", "targets"]);
+    assert!(property_names(&schema["definitions"]["Tuning"]).contains(&"profile"));
+}
+
+#[test]
+fn test_config_schema() {
+    let schema = schema::schema(SchemaKind::Config).unwrap();
+    let keys = property_names(&schema);
+
+    assert!(keys.contains(&"concurrency"));
+    assert!(keys.contains(&"profiles"));
+    assert_eq!(schema["properties"]["concurrency"]["type"], "integer");
+    assert_eq!(schema["properties"]["evasion"]["type"], "boolean");
+    assert!(serde_json::to_string(&schema["properties"]["profile"])
+        .unwrap()
+        .contains("stealth"));
+
+    // Profiles take the same keys as the top level
+    let profile = &schema["properties"]["profiles"]["additionalProperties"];
+    assert_eq!(property_names(profile).len(), keys.len() - 1);
+}
+
+#[test]
+fn test_write_schema_file() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let path = temp_dir.path().join("rules.schema.json");
+
+    schema::write_schema(SchemaKind::Rules, Some(path.to_str().unwrap()))?;
+    let written: Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    assert_eq!(written, schema::schema(SchemaKind::Rules)?);
+
+    assert_eq!("Config".parse::<SchemaKind>()?, SchemaKind::Config);
+    assert!("scanner".parse::<SchemaKind>().is_err());
+
+    Ok(())
+}
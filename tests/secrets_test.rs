@@ -0,0 +1,88 @@
+use anyhow::Result;
+use fatt::manifest::Manifest;
+use fatt::secrets;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_env_interpolation() -> Result<()> {
+    std::env::set_var("FATT_TEST_TOKEN", "s3cret");
+    std::env::remove_var("FATT_TEST_UNSET");
+
+    assert_eq!(
+        secrets::interpolate("https://hooks.example.com/${FATT_TEST_TOKEN}")?,
+        "https://hooks.example.com/s3cret"
+    );
+    assert_eq!(secrets::interpolate("${env:FATT_TEST_TOKEN}")?, "s3cret");
+    assert_eq!(
+        secrets::interpolate("${FATT_TEST_UNSET:-fallback}")?,
+        "fallback"
+    );
+    assert_eq!(
+        secrets::interpolate("$${FATT_TEST_TOKEN}")?,
+        "${FATT_TEST_TOKEN}"
+    );
+    assert_eq!(secrets::interpolate("no references")?, "no references");
+
+    assert!(secrets::interpolate("${FATT_TEST_UNSET}").is_err());
+    assert!(secrets::interpolate("${FATT_TEST_TOKEN").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_file_and_exec_providers() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let secret_file = temp_dir.path().join("token");
+    fs::write(&secret_file, "from-file\n")?;
+
+    assert_eq!(
+        secrets::interpolate(&format!("${{file:{}}}", secret_file.display()))?,
+        "from-file"
+    );
+    assert_eq!(secrets::interpolate("${exec:echo from-exec}")?, "from-exec");
+
+    assert!(secrets::interpolate("${file:/nonexistent/secret}").is_err());
+    assert!(secrets::interpolate("${exec:exit 3}").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_manifest_interpolation() -> Result<()> {
+    std::env::set_var("FATT_TEST_CONCURRENCY", "42");
+    std::env::set_var("FATT_TEST_PROXY_PASS", "user:p@ss: word");
+    std::env::set_var("FATT_TEST_STREAM_TOKEN", "123456");
+    std::env::set_var("FATT_TEST_ONLY_ON_FINDINGS", "true");
+
+    let temp_dir = tempdir()?;
+    let manifest_path = temp_dir.path().join("scan.yaml");
+    fs::write(
+        &manifest_path,
+        r#"
+targets:
+  file: domains.txt
+rules: [rules.yaml]
+tuning:
+  concurrency: ${FATT_TEST_CONCURRENCY}
+  proxy_auth: ${FATT_TEST_PROXY_PASS}
+  stream_token: ${FATT_TEST_STREAM_TOKEN}
+notifications:
+  webhooks:
+    - url: https://hooks.example.com/fatt
+      secret: ${FATT_TEST_STREAM_TOKEN}
+      only_on_findings: ${FATT_TEST_ONLY_ON_FINDINGS}
+"#,
+    )?;
+
+    let config = Manifest::from_file(manifest_path.to_str().unwrap())?.to_scan_config()?;
+    assert_eq!(config.concurrency, 42);
+    // Secret values are taken literally, even if they look like YAML
+    assert_eq!(config.proxy_auth.as_deref(), Some("user:p@ss: word"));
+    // and stay strings when they look like numbers
+    assert_eq!(config.stream_token.as_deref(), Some("123456"));
+    assert_eq!(config.webhooks[0].secret.as_deref(), Some("123456"));
+    assert!(config.webhooks[0].only_on_findings);
+
+    Ok(())
+}
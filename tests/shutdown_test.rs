@@ -0,0 +1,138 @@
+use anyhow::Result;
+use chrono::Utc;
+use fatt::config::InputSource;
+use fatt::db;
+use fatt::pipeline::{self, PipelineMetrics, PipelineOptions};
+use fatt::risk::RiskReport;
+use fatt::rules::{Rule, RuleSet, Severity};
+use fatt::scanner::{self, ScanOptions, ScanResources};
+use fatt::shutdown::{Shutdown, ShutdownState};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+use tokio::sync::Mutex;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn resources() -> Result<ScanResources> {
+    let ruleset = RuleSet {
+        rules: vec![Rule::new(
+            "Env",
+            "/.env",
+            "APP_KEY=",
+            "desc",
+            Severity::High,
+        )],
+    };
+    Ok(ScanResources::new(
+        scanner::create_http_client(30, 2)?,
+        ruleset,
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
+        Arc::new(Mutex::new(db::init_db(":memory:")?)),
+        ScanOptions::default(),
+    ))
+}
+
+#[test]
+fn test_shutdown_escalates() {
+    let shutdown = Shutdown::default();
+    assert_eq!(shutdown.state(), ShutdownState::Running);
+    assert!(!shutdown.is_stopping());
+
+    assert_eq!(shutdown.request(), ShutdownState::Draining);
+    assert!(shutdown.is_stopping());
+    assert_eq!(shutdown.request(), ShutdownState::Forced);
+    assert_eq!(shutdown.request(), ShutdownState::Forced);
+}
+
+#[tokio::test]
+async fn test_draining_reads_no_more_domains() -> Result<()> {
+    let resources = resources()?;
+    resources.shutdown.request();
+
+    let mut input = NamedTempFile::new()?;
+    writeln!(input, "a.example.com")?;
+    writeln!(input, "b.example.com")?;
+
+    let domains_processed = Arc::new(AtomicUsize::new(0));
+    let scanned = pipeline::run_pipeline(
+        &[InputSource::new(input.path().to_str().unwrap(), None)],
+        &resources,
+        &PipelineOptions::default(),
+        Arc::new(PipelineMetrics::default()),
+        domains_processed.clone(),
+    )
+    .await?;
+
+    assert_eq!(scanned, 0);
+    assert_eq!(domains_processed.load(Ordering::Relaxed), 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_forced_stop_abandons_checks_in_progress() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("APP_KEY=secret")
+                .set_delay(Duration::from_secs(20)),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let resources = Arc::new(resources()?);
+    let hostname = mock_server.uri().replace("http://", "");
+    let mut input = NamedTempFile::new()?;
+    writeln!(input, "{}", hostname)?;
+    let input_path = input.path().to_str().unwrap().to_string();
+
+    let started = Instant::now();
+    let pipeline = {
+        let resources = resources.clone();
+        tokio::spawn(async move {
+            pipeline::run_pipeline(
+                &[InputSource::new(&input_path, None)],
+                &resources,
+                &PipelineOptions::default(),
+                Arc::new(PipelineMetrics::default()),
+                Arc::new(AtomicUsize::new(0)),
+            )
+            .await
+        })
+    };
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    resources.shutdown.request();
+    resources.shutdown.request();
+
+    let scanned = tokio::time::timeout(Duration::from_secs(5), pipeline).await???;
+    assert_eq!(scanned, 1);
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert_eq!(resources.matches_found.load(Ordering::Relaxed), 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_incomplete_scan_is_recorded() -> Result<()> {
+    let conn = db::init_db(":memory:")?;
+    db::start_scan_session(&conn, "scan-1", Utc::now())?;
+    db::finish_scan_session(&conn, "scan-1", 1, &RiskReport::default())?;
+    db::start_scan_session(&conn, "scan-2", Utc::now())?;
+    db::finish_scan_session(&conn, "scan-2", 3, &RiskReport::default())?;
+    db::mark_scan_incomplete(&conn, "scan-2")?;
+
+    let sessions = db::get_scan_sessions(&conn, 10)?;
+    let incomplete: Vec<&str> = sessions
+        .iter()
+        .filter(|session| session.incomplete)
+        .map(|session| session.id.as_str())
+        .collect();
+    assert_eq!(incomplete, vec!["scan-2"]);
+
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use anyhow::Result;
+use fatt::sources::{CtSource, FileSource, HttpSource, LineSource, SourceSpec, TargetSource};
+use std::io::{Cursor, Write};
+use std::time::Duration;
+use tempfile::NamedTempFile;
+use tokio::io::BufReader;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[test]
+fn test_parse_source_spec() -> Result<()> {
+    assert_eq!("stdin".parse::<SourceSpec>()?, SourceSpec::Stdin);
+    assert_eq!("-".parse::<SourceSpec>()?, SourceSpec::Stdin);
+    assert_eq!(
+        "file:new-domains.txt".parse::<SourceSpec>()?,
+        SourceSpec::File("new-domains.txt".to_string())
+    );
+    assert_eq!(
+        "ct:Example.com".parse::<SourceSpec>()?,
+        SourceSpec::Ct("example.com".to_string())
+    );
+    assert_eq!(
+        "https://assets.internal/new".parse::<SourceSpec>()?,
+        SourceSpec::Http("https://assets.internal/new".to_string())
+    );
+    let kafka: SourceSpec = "kafka://broker1:9092,broker2:9092/discovered-assets".parse()?;
+    assert_eq!(
+        kafka,
+        SourceSpec::Kafka {
+            brokers: "broker1:9092,broker2:9092".to_string(),
+            topic: "discovered-assets".to_string(),
+        }
+    );
+    assert_eq!(
+        kafka.to_string(),
+        "kafka://broker1:9092,broker2:9092/discovered-assets"
+    );
+
+    for invalid in [
+        "",
+        "domains.txt",
+        "file:",
+        "ct:",
+        "kafka://broker",
+        "ftp://x/y",
+    ] {
+        assert!(invalid.parse::<SourceSpec>().is_err(), "{}", invalid);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_source_reads_appended_lines() -> Result<()> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "a.example.com\n# comment\n\nb.example.com")?;
+    let mut source = FileSource::new(file.path().to_str().unwrap());
+
+    assert_eq!(
+        source.poll().await?,
+        Some(vec![
+            "a.example.com".to_string(),
+            "b.example.com".to_string()
+        ])
+    );
+    assert_eq!(source.poll().await?, Some(Vec::new()));
+
+    // A line still being written waits for the next poll
+    write!(file, "c.example.com\nd.exam")?;
+    assert_eq!(
+        source.poll().await?,
+        Some(vec!["c.example.com".to_string()])
+    );
+    write!(file, "ple.com")?;
+    assert_eq!(source.poll().await?, Some(Vec::new()));
+    // ...unless the file stopped growing
+    assert_eq!(
+        source.poll().await?,
+        Some(vec!["d.example.com".to_string()])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_line_source_ends_with_stream() -> Result<()> {
+    let input = Cursor::new(b"a.example.com\nb.example.com\n".to_vec());
+    let mut source = LineSource::new(BufReader::new(input));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    assert_eq!(
+        source.poll().await?,
+        Some(vec![
+            "a.example.com".to_string(),
+            "b.example.com".to_string()
+        ])
+    );
+    assert_eq!(source.poll().await?, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ct_source_returns_new_certificates() -> Result<()> {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(query_param("q", "%.example.com"))
+        .and(query_param("output", "json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            {"id": 2, "name_value": "*.api.example.com\nwww.example.com"},
+            {"id": 1, "name_value": "example.com\nexample.org"},
+        ])))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            {"id": 3, "name_value": "new.example.com"},
+            {"id": 2, "name_value": "*.api.example.com\nwww.example.com"},
+        ])))
+        .mount(&server)
+        .await;
+
+    let mut source = CtSource::new(reqwest::Client::new(), &server.uri(), "example.com");
+    let mut domains = source.poll().await?.unwrap();
+    domains.sort();
+    assert_eq!(
+        domains,
+        vec!["api.example.com", "example.com", "www.example.com"]
+    );
+
+    // Only certificates logged since
+    assert_eq!(
+        source.poll().await?,
+        Some(vec!["new.example.com".to_string()])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_http_source_formats() -> Result<()> {
+    let server = MockServer::start().await;
+    Mock::given(path("/text"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("a.example.com\nb.example.com\n"))
+        .mount(&server)
+        .await;
+    Mock::given(path("/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            "a.example.com",
+            {"domain": "b.example.com", "first_seen": "2024-05-01"},
+            {"ip": "10.0.0.1"},
+        ])))
+        .mount(&server)
+        .await;
+    Mock::given(path("/down"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+
+    let expected = Some(vec![
+        "a.example.com".to_string(),
+        "b.example.com".to_string(),
+    ]);
+    for endpoint in ["/text", "/json"] {
+        let mut source = HttpSource::new(
+            reqwest::Client::new(),
+            &format!("{}{}", server.uri(), endpoint),
+        );
+        assert_eq!(source.poll().await?, expected);
+    }
+
+    let mut source = HttpSource::new(reqwest::Client::new(), &format!("{}/down", server.uri()));
+    assert!(source.poll().await.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use fatt::rules::RuleSet;
+use fatt::scanner::{self, ScanOptions, ScanResources};
+use rusqlite::Connection;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn resources(capture_headers: bool) -> Result<ScanResources> {
+    let options = ScanOptions {
+        capture_headers,
+        ..Default::default()
+    };
+
+    Ok(ScanResources::new(
+        scanner::create_http_client(5, 2)?,
+        RuleSet { rules: vec![] },
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
+        Arc::new(Mutex::new(Connection::open_in_memory()?)),
+        options,
+    ))
+}
+
+#[tokio::test]
+async fn test_network_stats() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/found"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("0123456789"))
+        .mount(&mock_server)
+        .await;
+
+    let resources = resources(false).await?;
+    resources
+        .fetch(&format!("{}/found", mock_server.uri()), None)
+        .await?;
+    resources
+        .fetch(&format!("{}/missing", mock_server.uri()), None)
+        .await?;
+    resources
+        .fetch(&format!("{}/gone", mock_server.uri()), None)
+        .await?;
+
+    let stats = resources.stats.snapshot();
+    assert_eq!(stats.requests, 3);
+    assert!(stats.bytes_sent > 0);
+    // Only bodies are counted without header capture
+    assert_eq!(stats.bytes_received, 10);
+    assert_eq!(stats.status_counts.get(&200), Some(&1));
+    assert_eq!(stats.status_counts.get(&404), Some(&2));
+
+    // Nothing listens on port 1
+    assert!(resources.fetch("http://127.0.0.1:1/", None).await.is_err());
+    let stats = resources.stats.snapshot();
+    assert_eq!(stats.requests, 4);
+    assert_eq!(stats.connect_failures, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_network_stats_capture_headers() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-test", "value")
+                .set_body_string("body"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let resources = resources(true).await?;
+    resources.fetch(&mock_server.uri(), None).await?;
+
+    assert!(resources.stats.snapshot().bytes_received > "body".len() as u64);
+
+    Ok(())
+}
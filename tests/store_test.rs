@@ -0,0 +1,631 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use fatt::db::TimeRange;
+use fatt::evidence::Evidence;
+use fatt::redact::RedactionPolicy;
+use fatt::risk::RiskReport;
+use fatt::rules::{Rule, RuleSet, Severity};
+use fatt::store::{self, ExportOptions, FindingDetails, JsonlStore, ResultStore, StoreKind};
+use std::collections::BTreeMap;
+use tempfile::tempdir;
+
+fn counts(pairs: &[(&str, usize)]) -> BTreeMap<String, usize> {
+    pairs
+        .iter()
+        .map(|(rule, count)| (rule.to_string(), *count))
+        .collect()
+}
+
+#[test]
+fn test_store_kind_parse() -> Result<()> {
+    assert_eq!("sqlite".parse::<StoreKind>()?, StoreKind::Sqlite);
+    assert_eq!("JSONL".parse::<StoreKind>()?, StoreKind::Jsonl);
+    assert_eq!("postgres".parse::<StoreKind>()?, StoreKind::Postgres);
+    assert!("mysql".parse::<StoreKind>().is_err());
+    assert_eq!(StoreKind::Jsonl.to_string(), "jsonl");
+
+    // A postgres:// database is PostgreSQL whatever the store setting
+    assert_eq!(
+        StoreKind::Sqlite.resolve("postgres://fatt@db/results"),
+        StoreKind::Postgres
+    );
+    assert_eq!(StoreKind::Jsonl.resolve("results.sqlite"), StoreKind::Jsonl);
+
+    Ok(())
+}
+
+#[test]
+fn test_jsonl_store_writes_one_file_per_scan() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let dir = temp_dir.path().join("results");
+    let mut store = JsonlStore::open(&dir)?;
+
+    let started_at = Utc::now() - Duration::seconds(1);
+    let agent = FindingDetails {
+        user_agent: Some("agent"),
+        ..Default::default()
+    };
+    store.start_scan("scan-1", started_at)?;
+    store.record_finding("example.com", "Git", "/.git/HEAD", true, &agent)?;
+    store.record_finding(
+        "example.com",
+        "Env",
+        "/.env",
+        false,
+        &FindingDetails::default(),
+    )?;
+    // A later result for the same domain and rule replaces the earlier one
+    store.record_finding(
+        "example.com",
+        "Env",
+        "/.env",
+        true,
+        &FindingDetails::default(),
+    )?;
+    store.record_rule_counts("scan-1", &counts(&[("Git", 1), ("Env", 1)]))?;
+    store.finish_scan("scan-1", 1, &RiskReport::default())?;
+
+    assert!(store.scan_file("scan-1").exists());
+    let lines = std::fs::read_to_string(store.scan_file("scan-1"))?;
+    assert_eq!(lines.lines().count(), 6);
+
+    let findings = store.findings()?;
+    assert_eq!(findings.len(), 2);
+    assert_eq!(findings[0].rule_name, "Env");
+    assert!(findings[0].detected);
+
+    let detected = store.detected_since(started_at)?;
+    assert_eq!(detected.len(), 2);
+    assert!(store
+        .detected_since(Utc::now() + Duration::hours(1))?
+        .is_empty());
+
+    // Starting the same scan twice would mix two sessions in one file
+    assert!(store.start_scan("scan-1", started_at).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_jsonl_store_requires_a_scan_session() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut store = JsonlStore::open(temp_dir.path())?;
+
+    assert!(store
+        .record_finding(
+            "example.com",
+            "Git",
+            "/.git/HEAD",
+            true,
+            &FindingDetails::default()
+        )
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_jsonl_rule_baseline() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let store = JsonlStore::open(temp_dir.path())?;
+
+    // No history yet
+    let (scans, _) = store.rule_baseline("current", 5)?;
+    assert_eq!(scans, 0);
+
+    let now = Utc::now();
+    for (i, (scan_id, detected)) in [("scan-1", 4), ("scan-2", 8), ("current", 100)]
+        .into_iter()
+        .enumerate()
+    {
+        let mut store = JsonlStore::open(temp_dir.path())?;
+        store.start_scan(scan_id, now + Duration::seconds(i as i64))?;
+        store.record_rule_counts(scan_id, &counts(&[("Rule", detected)]))?;
+    }
+
+    // The current scan is excluded from its own baseline
+    let (scans, baseline) = store.rule_baseline("current", 5)?;
+    assert_eq!(scans, 2);
+    assert_eq!(baseline["Rule"], 6.0);
+
+    // Only the most recent scans count
+    let (scans, baseline) = store.rule_baseline("current", 1)?;
+    assert_eq!(scans, 1);
+    assert_eq!(baseline["Rule"], 8.0);
+
+    Ok(())
+}
+
+#[test]
+fn test_jsonl_store_skips_truncated_lines() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut store = JsonlStore::open(temp_dir.path())?;
+    store.start_scan("scan-1", Utc::now())?;
+    store.record_finding(
+        "example.com",
+        "Git",
+        "/.git/HEAD",
+        true,
+        &FindingDetails::default(),
+    )?;
+
+    // Simulate a crash part-way through writing a record
+    let path = store.scan_file("scan-1");
+    let mut contents = std::fs::read_to_string(&path)?;
+    contents.push_str("{\"type\":\"finding\",\"domain\":\"exa");
+    std::fs::write(&path, contents)?;
+
+    assert_eq!(store.findings()?.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_export_from_jsonl_store() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let dir = temp_dir.path().join("results");
+    let dir = dir.to_str().unwrap();
+
+    let shared = store::open_store(StoreKind::Jsonl, dir, 1, true)?;
+    {
+        let mut results = shared.lock().await;
+        results.start_scan("scan-1", Utc::now())?;
+        results.record_finding(
+            "example.com",
+            "Git",
+            "/.git/HEAD",
+            true,
+            &FindingDetails::default(),
+        )?;
+        results.record_finding(
+            "example.com",
+            "Env",
+            "/.env",
+            false,
+            &FindingDetails::default(),
+        )?;
+    }
+
+    let output = temp_dir.path().join("out.json");
+    store::export_results(
+        StoreKind::Jsonl,
+        &[dir.to_string()],
+        1,
+        output.to_str().unwrap(),
+        &ExportOptions {
+            format: "json",
+            split_by: None,
+            redaction: &RedactionPolicy::default(),
+            range: TimeRange::default(),
+            detected_only: true,
+            ruleset: Some(&RuleSet {
+                rules: vec![
+                    Rule::new("Git", "/.git/HEAD", "ref:", "desc", Severity::High)
+                        .with_remediation("Block /.git on the web server"),
+                ],
+            }),
+            group_by: None,
+            project: None,
+            include_false_positives: false,
+        },
+    )?;
+
+    let exported: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(output)?)?;
+    assert_eq!(exported.as_array().unwrap().len(), 1);
+    assert_eq!(exported[0]["domain"], "example.com");
+    assert_eq!(exported[0]["remediation"], "Block /.git on the web server");
+
+    Ok(())
+}
+
+#[test]
+fn test_redirect_chain_is_stored() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let chain = vec![
+        "https://example.com/sso".to_string(),
+        "https://idp.example.net/login".to_string(),
+    ];
+
+    let mut sqlite = fatt::db::init_db(temp_dir.path().join("results.sqlite").to_str().unwrap())?;
+    let mut jsonl = JsonlStore::open(temp_dir.path().join("results"))?;
+    jsonl.start_scan("scan-1", Utc::now())?;
+
+    let redirected = FindingDetails {
+        redirect_chain: &chain,
+        ..Default::default()
+    };
+    let stores: [&mut dyn ResultStore; 2] = [&mut sqlite, &mut jsonl];
+    for store in stores {
+        store.record_finding("example.com", "SSO", "/login", true, &redirected)?;
+        store.record_finding(
+            "example.com",
+            "Git",
+            "/.git/HEAD",
+            true,
+            &FindingDetails::default(),
+        )?;
+
+        let findings = store.findings()?;
+        assert_eq!(findings[0].rule_name, "Git");
+        assert!(findings[0].redirect_chain.is_empty());
+        assert_eq!(findings[1].redirect_chain, chain);
+
+        // A later check without redirects clears the chain
+        store.record_finding(
+            "example.com",
+            "SSO",
+            "/login",
+            true,
+            &FindingDetails::default(),
+        )?;
+        assert!(store.findings()?[1].redirect_chain.is_empty());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_evidence_is_stored() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let evidence = Evidence::capture(200, b"APP_KEY=base64:abc", 0);
+
+    let mut sqlite = fatt::db::init_db(temp_dir.path().join("results.sqlite").to_str().unwrap())?;
+    let mut jsonl = JsonlStore::open(temp_dir.path().join("results"))?;
+    sqlite.start_scan("scan-1", Utc::now())?;
+    jsonl.start_scan("scan-1", Utc::now())?;
+
+    let matched = FindingDetails {
+        evidence: Some(&evidence),
+        ..Default::default()
+    };
+    let stores: [&mut dyn ResultStore; 2] = [&mut sqlite, &mut jsonl];
+    for store in stores {
+        store.record_finding("example.com", "Env", "/.env", true, &matched)?;
+        let findings = store.findings()?;
+        assert_eq!(findings[0].evidence.as_ref(), Some(&evidence));
+
+        // Scans keep the evidence of their findings
+        store.record_scan_findings("scan-1", &findings)?;
+        assert_eq!(
+            store.scan_findings("scan-1")?[0].evidence.as_ref(),
+            Some(&evidence)
+        );
+
+        // A later check without a match clears it
+        store.record_finding(
+            "example.com",
+            "Env",
+            "/.env",
+            false,
+            &FindingDetails::default(),
+        )?;
+        assert!(store.findings()?[0].evidence.is_none());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_revalidatable_findings() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(reqwest::header::ETAG, "\"abc\"".parse()?);
+    let cached = Evidence::capture(200, b"APP_KEY=base64:abc", 0).with_validators(&headers);
+    let uncached = Evidence::capture(200, b"ref: refs/heads/main", 0);
+
+    let mut sqlite = fatt::db::init_db(temp_dir.path().join("results.sqlite").to_str().unwrap())?;
+    let mut jsonl = JsonlStore::open(temp_dir.path().join("results"))?;
+    jsonl.start_scan("scan-1", Utc::now())?;
+
+    let stores: [&mut dyn ResultStore; 2] = [&mut sqlite, &mut jsonl];
+    for store in stores {
+        let details = |evidence| FindingDetails {
+            evidence: Some(evidence),
+            ..Default::default()
+        };
+        store.record_finding("a.example.com", "Env", "/.env", true, &details(&cached))?;
+        store.record_finding(
+            "a.example.com",
+            "Git",
+            "/.git/HEAD",
+            true,
+            &details(&uncached),
+        )?;
+        store.record_finding("b.example.com", "Env", "/.env", true, &details(&cached))?;
+        store.record_finding(
+            "b.example.com",
+            "Env",
+            "/.env",
+            false,
+            &FindingDetails::default(),
+        )?;
+
+        // Only a detection whose evidence has validators can be requested conditionally
+        let findings = store.revalidatable()?;
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].domain, "a.example.com");
+        assert_eq!(findings[0].rule_name, "Env");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_postgres_url_detection() {
+    assert!(store::is_postgres_url("postgres://fatt@db:5432/results"));
+    assert!(store::is_postgres_url("postgresql://db/results"));
+    assert!(!store::is_postgres_url("results.sqlite"));
+    assert!(!store::is_postgres_url("data/postgres/results.sqlite"));
+}
+
+#[test]
+fn test_redact_url() {
+    assert_eq!(
+        store::redact_url("postgres://fatt:hunter2@db:5432/results"),
+        "postgres://fatt:********@db:5432/results"
+    );
+    assert_eq!(
+        store::redact_url("postgres://fatt@db/results"),
+        "postgres://fatt@db/results"
+    );
+    assert_eq!(store::redact_url("results.sqlite"), "results.sqlite");
+}
+
+#[test]
+fn test_federated_findings() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let path = |name: &str| temp_dir.path().join(name).to_string_lossy().to_string();
+
+    let east = fatt::db::init_db(&path("east.sqlite"))?;
+    fatt::db::insert_finding(&east, "a.example.com", "Git", "/.git/HEAD", true)?;
+    fatt::db::insert_finding(&east, "shared.example.com", "Env", "/.env", true)?;
+    east.execute(
+        "UPDATE findings SET scanned_at = datetime('now', '-1 day') WHERE domain = 'shared.example.com'",
+        [],
+    )?;
+    let west = fatt::db::init_db(&path("west.sqlite"))?;
+    fatt::db::insert_finding(&west, "b.example.com", "Git", "/.git/HEAD", true)?;
+    fatt::db::insert_finding(&west, "shared.example.com", "Env", "/.env", false)?;
+
+    // A glob stands for every database it matches
+    let databases = store::expand_databases(&[path("*.sqlite"), path("east.sqlite")])?;
+    assert_eq!(databases, [path("east.sqlite"), path("west.sqlite")]);
+    assert!(store::expand_databases(&[path("*.jsonl")]).is_err());
+
+    // Where both checked a domain and rule, the latest check wins
+    let findings = store::load_federated_findings(StoreKind::Sqlite, &databases, 1)?;
+    let checks: Vec<(&str, bool)> = findings
+        .iter()
+        .map(|f| (f.domain.as_str(), f.detected))
+        .collect();
+    assert_eq!(
+        checks,
+        [
+            ("a.example.com", true),
+            ("b.example.com", true),
+            ("shared.example.com", false)
+        ]
+    );
+
+    let missing = [path("east.sqlite"), path("north.sqlite")];
+    assert!(store::load_federated_findings(StoreKind::Sqlite, &missing, 1).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_project_is_stored() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_file = db_path.to_str().unwrap();
+
+    let mut sqlite = fatt::db::init_db(db_file)?;
+    let mut jsonl = JsonlStore::open(temp_dir.path().join("results"))?;
+    let acme = FindingDetails {
+        project: Some("acme"),
+        ..Default::default()
+    };
+    let stores: [&mut dyn ResultStore; 2] = [&mut sqlite, &mut jsonl];
+    for store in stores {
+        store.start_scan("scan-1", Utc::now())?;
+        store.record_project("scan-1", "acme")?;
+        store.record_finding("a.example.com", "Git", "/.git/HEAD", true, &acme)?;
+        store.record_finding(
+            "b.example.com",
+            "Git",
+            "/.git/HEAD",
+            true,
+            &FindingDetails::default(),
+        )?;
+
+        let findings = store.findings()?;
+        assert_eq!(findings[0].project.as_deref(), Some("acme"));
+        assert!(findings[1].project.is_none());
+
+        store.record_scan_findings("scan-1", &findings)?;
+    }
+
+    let sessions = fatt::db::get_scan_sessions(&sqlite, 10)?;
+    assert_eq!(sessions[0].project.as_deref(), Some("acme"));
+    assert_eq!(jsonl.scan_sessions()?[0].project.as_deref(), Some("acme"));
+    assert!(fatt::db::of_project(sessions, Some("other")).is_empty());
+    assert_eq!(
+        sqlite.scan_findings("scan-1")?[0].project.as_deref(),
+        Some("acme")
+    );
+
+    // Listing a project leaves out findings checked for another, or for none
+    let query = fatt::db::ListQuery {
+        project: Some("acme"),
+        limit: 10,
+        ..Default::default()
+    };
+    let listed = fatt::db::find_results(db_file, 1, &query, None)?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].domain, "a.example.com");
+
+    Ok(())
+}
+
+#[test]
+fn test_ruleset_digest_is_stored() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let live = fatt::rules::LiveRuleSet::new(RuleSet {
+        rules: vec![Rule::new(
+            "Git",
+            "/.git/HEAD",
+            "ref:",
+            "desc",
+            Severity::High,
+        )],
+    });
+    let initial = live.snapshot();
+    let reloaded = live.replace(RuleSet {
+        rules: vec![Rule::new(
+            "Git",
+            "/.git/config",
+            "[core]",
+            "desc",
+            Severity::High,
+        )],
+    });
+    assert_ne!(initial.digest, reloaded.digest);
+
+    let mut sqlite = fatt::db::init_db(temp_dir.path().join("results.sqlite").to_str().unwrap())?;
+    let mut jsonl = JsonlStore::open(temp_dir.path().join("results"))?;
+    let checked = FindingDetails {
+        ruleset_version: Some(reloaded.version),
+        ruleset_digest: Some(&reloaded.digest),
+        ..Default::default()
+    };
+    let stores: [&mut dyn ResultStore; 2] = [&mut sqlite, &mut jsonl];
+    for store in stores {
+        store.start_scan("scan-1", Utc::now())?;
+        store.record_ruleset("scan-1", &initial)?;
+        store.record_ruleset("scan-1", &reloaded)?;
+        store.record_finding("example.com", "Git", "/.git/config", true, &checked)?;
+
+        let findings = store.findings()?;
+        assert_eq!(findings[0].ruleset_digest, Some(reloaded.digest.clone()));
+        store.record_scan_findings("scan-1", &findings)?;
+    }
+
+    // A scan session is identified by the rules it started with
+    let sessions = fatt::db::get_scan_sessions(&sqlite, 10)?;
+    assert_eq!(sessions[0].ruleset_digest, Some(initial.digest.clone()));
+    assert_eq!(
+        jsonl.scan_sessions()?[0].ruleset_digest,
+        Some(initial.digest.clone())
+    );
+    assert_eq!(
+        sqlite.scan_findings("scan-1")?[0].ruleset_digest,
+        Some(reloaded.digest.clone())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_import_jsonl_scan_files() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let database = temp_dir.path().join("central.sqlite");
+    let database = database.to_str().unwrap();
+
+    // The central database already has a newer check of one of the worker's findings
+    let conn = fatt::db::init_db(database)?;
+    fatt::db::insert_finding(&conn, "b.example.com", "Git", "/.git/HEAD", false)?;
+
+    let scan_file = temp_dir.path().join("worker-1.jsonl");
+    std::fs::write(
+        &scan_file,
+        format!(
+            "{}\n{}\n{}\n{}\n",
+            r#"{"type":"scan_started","scan_id":"worker-1","started_at":"2026-01-01T00:00:00Z"}"#,
+            r#"{"type":"finding","domain":"a.example.com","rule_name":"Git","matched_path":"/.git/HEAD","detected":true,"tag":"prod","scanned_at":"2026-01-01T00:00:05Z"}"#,
+            r#"{"type":"finding","domain":"b.example.com","rule_name":"Git","matched_path":"/.git/HEAD","detected":true,"scanned_at":"2026-01-01T00:00:06Z"}"#,
+            serde_json::json!({
+                "type": "scan_finished",
+                "finished_at": "2026-01-01T00:01:00Z",
+                "domains": 2,
+                "risk": RiskReport::default(),
+            })
+        ),
+    )?;
+    let files = [scan_file.to_str().unwrap().to_string()];
+
+    let reports = store::import_results(database, &files, "jsonl")?;
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].scan_id, "worker-1");
+    assert!(reports[0].new_session);
+    assert_eq!(reports[0].findings, 2);
+    assert_eq!(reports[0].written, 1);
+
+    let findings = fatt::db::get_all_findings(&conn)?;
+    assert_eq!(findings.len(), 2);
+    assert_eq!(findings[0].domain, "a.example.com");
+    assert_eq!(findings[0].tag.as_deref(), Some("prod"));
+    assert_eq!(
+        findings[0].scanned_at.to_rfc3339(),
+        "2026-01-01T00:00:05+00:00"
+    );
+    // The newer check in the database wins
+    assert!(!findings[1].detected);
+
+    // The findings are attributed to the worker's scan session
+    let sessions = fatt::db::get_scan_sessions(&conn, 10)?;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].id, "worker-1");
+    assert_eq!(
+        sessions[0].finished_at.map(|at| at.to_rfc3339()).as_deref(),
+        Some("2026-01-01T00:01:00+00:00")
+    );
+    assert_eq!(conn.scan_findings("worker-1")?.len(), 2);
+
+    // Importing the same file again adds no session
+    let reports = store::import_results(database, &files, "jsonl")?;
+    assert!(!reports[0].new_session);
+    assert_eq!(fatt::db::get_scan_sessions(&conn, 10)?.len(), 1);
+
+    // A directory of scan files written by the JSONL store imports as well
+    let results = temp_dir.path().join("results");
+    let mut jsonl = JsonlStore::open(&results)?;
+    jsonl.start_scan("worker-2", Utc::now())?;
+    jsonl.record_finding(
+        "c.example.com",
+        "Env",
+        "/.env",
+        true,
+        &FindingDetails::default(),
+    )?;
+    let reports =
+        store::import_results(database, &[results.to_str().unwrap().to_string()], "jsonl")?;
+    assert_eq!(reports[0].scan_id, "worker-2");
+    assert_eq!(fatt::db::get_all_findings(&conn)?.len(), 3);
+
+    // Files are checked in full before anything is written
+    let broken = temp_dir.path().join("broken.jsonl");
+    std::fs::write(
+        &broken,
+        format!(
+            "{}\n{}\n",
+            r#"{"type":"scan_started","scan_id":"broken","started_at":"2026-01-01T00:00:00Z"}"#,
+            r#"{"type":"finding","domain":"d.example.com"}"#
+        ),
+    )?;
+    let error = store::import_results(database, &[broken.to_str().unwrap().to_string()], "jsonl")
+        .unwrap_err();
+    assert!(format!("{:#}", error).contains("line 2"));
+    assert_eq!(fatt::db::get_scan_sessions(&conn, 10)?.len(), 2);
+
+    // Without a scan_started record there's nothing to attribute the findings to
+    std::fs::write(
+        &broken,
+        r#"{"type":"finding","domain":"d.example.com","rule_name":"Git","matched_path":"/","detected":true,"scanned_at":"2026-01-01T00:00:00Z"}"#,
+    )?;
+    assert!(
+        store::import_results(database, &[broken.to_str().unwrap().to_string()], "jsonl").is_err()
+    );
+    assert!(store::import_results(database, &files, "csv").is_err());
+
+    Ok(())
+}
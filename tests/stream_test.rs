@@ -0,0 +1,154 @@
+use anyhow::Result;
+use fatt::config::InputSource;
+use fatt::db;
+use fatt::pipeline::{self, PipelineMetrics, PipelineOptions};
+use fatt::rules::{Rule, RuleSet, Severity};
+use fatt::scanner::{self, ScanOptions, ScanResources};
+use fatt::stream::{EventStream, StreamEvent};
+use std::io::Write;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+use tokio::sync::Mutex;
+use wiremock::matchers::path;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn finished() -> StreamEvent {
+    StreamEvent::Finished {
+        scan_id: "scan-1".to_string(),
+        domains: 1,
+        findings: 1,
+        elapsed_secs: 0.5,
+    }
+}
+
+#[tokio::test]
+async fn test_event_stream_serves_events() -> Result<()> {
+    let events = EventStream::listen("127.0.0.1:0", None).await?;
+    let url = format!("http://{}/events", events.local_addr());
+
+    let response = reqwest::get(&url).await?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["content-type"].to_str()?,
+        "text/event-stream"
+    );
+
+    events.publish(StreamEvent::Progress {
+        domains_processed: 1,
+        domains_total: 2,
+        checks_completed: 3,
+        checks_total: 6,
+        findings: 0,
+        batches: None,
+    });
+
+    // The stream ends after the final event
+    let (_, body) = tokio::join!(events.finish(finished()), response.text());
+    let body = body?;
+
+    assert!(body.contains("event: progress\ndata: {\"type\":\"progress\",\"domains_processed\":1"));
+    assert!(body.contains("event: finished\n"));
+    assert!(body.find("event: progress") < body.find("event: finished"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_event_stream_requires_its_token() -> Result<()> {
+    // Open to other hosts, findings need a token
+    assert!(EventStream::listen("0.0.0.0:0", None).await.is_err());
+
+    let events = EventStream::listen("127.0.0.1:0", Some("s3cret")).await?;
+    let url = format!("http://{}/events", events.local_addr());
+    let client = reqwest::Client::new();
+
+    assert_eq!(client.get(&url).send().await?.status(), 401);
+    let wrong = client.get(&url).bearer_auth("guess").send().await?;
+    assert_eq!(wrong.status(), 401);
+
+    let response = client.get(&url).bearer_auth("s3cret").send().await?;
+    assert_eq!(response.status(), 200);
+    assert!(!response
+        .headers()
+        .contains_key("access-control-allow-origin"));
+    events.finish(finished()).await;
+
+    // A bare port listens on localhost only
+    let events = EventStream::listen(":0", None).await?;
+    assert!(events.local_addr().ip().is_loopback());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_event_stream_unknown_path() -> Result<()> {
+    let events = EventStream::listen("127.0.0.1:0", None).await?;
+
+    let response = reqwest::get(format!("http://{}/", events.local_addr())).await?;
+    assert_eq!(response.status(), 404);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_scan_publishes_findings() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/.env"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("APP_KEY=secret"))
+        .mount(&mock_server)
+        .await;
+
+    let events = Arc::new(EventStream::listen("127.0.0.1:0", None).await?);
+    let response = reqwest::get(format!("http://{}/events", events.local_addr())).await?;
+
+    let ruleset = RuleSet {
+        rules: vec![Rule::new(
+            "Env",
+            "/.env",
+            "APP_KEY=",
+            "desc",
+            Severity::High,
+        )],
+    };
+    let resources = ScanResources::new(
+        scanner::create_http_client(5, 2)?,
+        ruleset,
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
+        Arc::new(Mutex::new(db::init_db(":memory:")?)),
+        ScanOptions::default(),
+    )
+    .with_events(events.clone());
+
+    let hostname = mock_server.uri().replace("http://", "");
+    let mut input = NamedTempFile::new()?;
+    writeln!(input, "{}", hostname)?;
+
+    pipeline::run_pipeline(
+        &[InputSource::new(
+            input.path().to_str().unwrap(),
+            Some("prod"),
+        )],
+        &resources,
+        &PipelineOptions::default(),
+        Arc::new(PipelineMetrics::default()),
+        Arc::new(AtomicUsize::new(0)),
+    )
+    .await?;
+
+    let (_, body) = tokio::join!(events.finish(finished()), response.text());
+    let body = body?;
+
+    let finding = body
+        .split("\n\n")
+        .find_map(|message| message.strip_prefix("event: finding\ndata: "))
+        .expect("finding event");
+    let finding: serde_json::Value = serde_json::from_str(finding)?;
+    assert_eq!(finding["domain"], hostname);
+    assert_eq!(finding["rule"], "Env");
+    assert_eq!(finding["severity"], "high");
+    assert_eq!(finding["path"], "/.env");
+    assert_eq!(finding["tag"], "prod");
+
+    Ok(())
+}
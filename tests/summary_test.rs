@@ -0,0 +1,269 @@
+use anyhow::Result;
+use chrono::{Duration, TimeZone, Utc};
+use fatt::db::ScanSession;
+use fatt::risk::RiskReport;
+use fatt::rules::{Rule, RuleSet, Severity};
+use fatt::store::{self, FindingDetails, JsonlStore, ResultStore, StoreKind};
+use fatt::summary;
+use tempfile::tempdir;
+mod test_helpers;
+use test_helpers::finding;
+
+fn session(id: &str, secs: Option<i64>, domains: usize, incomplete: bool) -> ScanSession {
+    let started_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+    ScanSession {
+        id: id.to_string(),
+        started_at,
+        finished_at: secs.map(|secs| started_at + Duration::seconds(secs)),
+        domains,
+        findings: 0,
+        risk_score: 0.0,
+        incomplete,
+        profile: None,
+        project: None,
+        ruleset_digest: None,
+    }
+}
+
+#[test]
+fn test_summarize_findings() {
+    let findings = vec![
+        finding("a.com", "Env", true),
+        finding("a.com", "Git", false),
+        finding("b.org", "Env", true),
+        finding("c.org:8443", "Git", true),
+        finding("10.0.0.1", "Backup", true),
+        finding("d.com", "Env", false),
+    ];
+    let ruleset = RuleSet {
+        rules: vec![
+            Rule::new("Env", "/.env", "APP_KEY=", "desc", Severity::High),
+            Rule::new("Git", "/.git/HEAD", "ref:", "desc", Severity::Medium),
+        ],
+    };
+
+    let summary = summary::summarize(&findings, &[], Some(&ruleset));
+
+    assert_eq!(summary.checks, 6);
+    assert_eq!(summary.detected, 4);
+    assert!((summary.hit_rate - 4.0 / 6.0).abs() < 1e-9);
+    assert_eq!(summary.domains, 5);
+    assert_eq!(summary.affected_domains, 4);
+    assert_eq!(summary.by_rule["Env"], 2);
+    assert_eq!(summary.by_rule["Git"], 1);
+    assert_eq!(summary.by_rule["Backup"], 1);
+    assert_eq!(summary.by_tld["org"], 2);
+    assert_eq!(summary.by_tld["(ip)"], 1);
+    assert_eq!(summary.by_tld["com"], 1);
+    assert_eq!(summary.by_severity["high"], 2);
+    assert_eq!(summary.by_severity["medium"], 1);
+    // Rules missing from the rules file
+    assert_eq!(summary.by_severity["unknown"], 1);
+
+    // Without the rules there are no severities
+    let summary = summary::summarize(&findings, &[], None);
+    assert!(summary.by_severity.is_empty());
+}
+
+#[test]
+fn test_summarize_scan_timing() {
+    let sessions = vec![
+        session("scan-3", None, 0, false),
+        session("scan-2", Some(30), 300, true),
+        session("scan-1", Some(90), 900, false),
+    ];
+
+    let summary = summary::summarize(&[], &sessions, None);
+    let scans = &summary.scans;
+
+    assert_eq!(summary.hit_rate, 0.0);
+    assert_eq!(scans.sessions, 3);
+    assert_eq!(scans.finished, 2);
+    assert_eq!(scans.incomplete, 1);
+    assert_eq!(scans.total_secs, 120.0);
+    assert_eq!(scans.average_secs, 60.0);
+    assert_eq!(scans.longest_secs, 90.0);
+    assert_eq!(scans.domains_per_sec, 10.0);
+    assert_eq!(
+        scans.last_finished,
+        Some(Utc.with_ymd_and_hms(2024, 5, 1, 12, 1, 30).unwrap())
+    );
+}
+
+#[test]
+fn test_summarize_scan_risk() {
+    let scored = |id: &str, hours: i64, risk_score: f64| ScanSession {
+        started_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap() + Duration::hours(hours),
+        risk_score,
+        ..session(id, Some(60), 10, false)
+    };
+    let sessions = vec![
+        // Unfinished, so not scored yet
+        ScanSession {
+            finished_at: None,
+            ..scored("scan-4", 3, 0.0)
+        },
+        scored("scan-2", 1, 40.0),
+        scored("scan-1", 0, 0.0),
+        scored("scan-3", 2, 35.2),
+    ];
+
+    let summary = summary::summarize(&[], &sessions, None);
+    let scans: Vec<&str> = summary
+        .risk
+        .iter()
+        .map(|scan| scan.scan_id.as_str())
+        .collect();
+    assert_eq!(scans, ["scan-3", "scan-2", "scan-1"]);
+    assert_eq!(summary.risk[0].risk_score, 35.2);
+    assert_eq!(
+        summary.risk[0]
+            .change_percent
+            .map(|percent| percent.round()),
+        Some(-12.0)
+    );
+    // The change from a scan without risk is unknown
+    assert_eq!(summary.risk[1].change_percent, None);
+    assert_eq!(summary.risk[2].change_percent, None);
+}
+
+#[test]
+fn test_jsonl_scan_sessions() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut jsonl = JsonlStore::open(temp_dir.path())?;
+
+    jsonl.start_scan("scan-1", Utc::now() - Duration::seconds(60))?;
+    jsonl.record_finding("a.com", "Env", "/.env", true, &FindingDetails::default())?;
+    jsonl.finish_scan("scan-1", 10, &RiskReport::default())?;
+    jsonl.start_scan("scan-2", Utc::now())?;
+    jsonl.mark_incomplete("scan-2")?;
+
+    let sessions = store::load_scan_sessions(StoreKind::Jsonl, temp_dir.path().to_str().unwrap())?;
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0].id, "scan-2");
+    assert!(sessions[0].incomplete);
+    assert!(sessions[0].finished_at.is_none());
+    assert_eq!(sessions[1].id, "scan-1");
+    assert_eq!(sessions[1].domains, 10);
+    assert!(sessions[1].finished_at.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn test_show_stats_requires_results() {
+    let result = summary::show_stats(
+        StoreKind::Sqlite,
+        &["/nonexistent/results.sqlite".to_string()],
+        1,
+        None,
+        10,
+        None,
+        "text",
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_group_findings() -> Result<()> {
+    let mut old = finding("a.example.com", "Git", true);
+    old.scanned_at = Utc::now() - Duration::days(3);
+    let findings = vec![
+        old.clone(),
+        finding("a.example.com", "Env", true),
+        finding("b.example.com", "Git", true),
+        finding("c.example.com", "Git", false),
+        finding("c.example.com", "Swagger", true),
+    ];
+    let ruleset = RuleSet {
+        rules: vec![
+            Rule::new("Git", "/.git/HEAD", "ref:", "desc", Severity::High),
+            Rule::new("Env", "/.env", "APP_KEY=", "desc", Severity::High),
+        ],
+    };
+
+    let by_rule = summary::group_findings(&findings, summary::GroupBy::Rule, None)?;
+    let names: Vec<&str> = by_rule.iter().map(|g| g.group.as_str()).collect();
+    assert_eq!(names, ["Env", "Git", "Swagger"]);
+    let git = &by_rule[1];
+    assert_eq!(
+        (git.findings, git.detected, git.domains, git.rules),
+        (3, 2, 2, 1)
+    );
+    assert_eq!(git.first_seen, old.scanned_at);
+    assert!(git.last_seen > git.first_seen);
+
+    let by_domain = summary::group_findings(&findings, summary::GroupBy::Domain, None)?;
+    assert_eq!(by_domain.len(), 3);
+    assert_eq!((by_domain[0].detected, by_domain[0].rules), (2, 2));
+
+    let by_severity =
+        summary::group_findings(&findings, summary::GroupBy::Severity, Some(&ruleset))?;
+    let severities: Vec<(&str, usize)> = by_severity
+        .iter()
+        .map(|g| (g.group.as_str(), g.detected))
+        .collect();
+    assert_eq!(severities, [("high", 3), ("unknown", 1)]);
+
+    // Severities come from the rules file
+    assert!(summary::group_findings(&findings, summary::GroupBy::Severity, None).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_export_grouped() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let dir = temp_dir.path().join("results");
+    let dir = dir.to_str().unwrap();
+    let output = temp_dir.path().join("by-rule.csv");
+
+    let mut store = JsonlStore::open(dir)?;
+    store.start_scan("scan-1", Utc::now())?;
+    for domain in ["a.example.com", "b.example.com"] {
+        store.record_finding(
+            domain,
+            "Git",
+            "/.git/HEAD",
+            true,
+            &FindingDetails::default(),
+        )?;
+    }
+    store.record_finding(
+        "a.example.com",
+        "Env",
+        "/.env",
+        false,
+        &FindingDetails::default(),
+    )?;
+
+    store::export_results(
+        StoreKind::Jsonl,
+        &[dir.to_string()],
+        1,
+        output.to_str().unwrap(),
+        &store::ExportOptions {
+            format: "csv",
+            split_by: None,
+            redaction: &Default::default(),
+            range: Default::default(),
+            detected_only: true,
+            ruleset: None,
+            group_by: Some(summary::GroupBy::Rule),
+            project: None,
+            include_false_positives: false,
+        },
+    )?;
+
+    let mut reader = csv::Reader::from_path(&output)?;
+    assert_eq!(
+        reader.headers()?.iter().take(5).collect::<Vec<_>>(),
+        ["Rule", "Findings", "Detected", "Domains", "Rules"]
+    );
+    let rows: Vec<csv::StringRecord> = reader.records().collect::<Result<_, _>>()?;
+    assert_eq!(rows.len(), 1);
+    assert_eq!(&rows[0][0], "Git");
+    assert_eq!(&rows[0][3], "2");
+
+    Ok(())
+}
@@ -1,7 +1,45 @@
+// Each test file includes this module and uses only some of it
+#![allow(dead_code)]
+
+use anyhow::Result;
+use chrono::Utc;
+use fatt::config::InputSource;
+use fatt::db::Finding;
+use fatt::pipeline::{self, PipelineMetrics, PipelineOptions};
+use fatt::scanner::ScanResources;
+use std::io::Write;
+use std::sync::atomic::AtomicUsize;
 use std::sync::{Arc, Mutex};
-use tracing;
+use tempfile::NamedTempFile;
 use tracing_subscriber::prelude::*;
 
+/// A finding of a rule at `/` on a domain, scanned now
+pub fn finding(domain: &str, rule_name: &str, detected: bool) -> Finding {
+    Finding {
+        domain: domain.to_string(),
+        rule_name: rule_name.to_string(),
+        matched_path: "/".to_string(),
+        detected,
+        scanned_at: Utc::now(),
+        ..Default::default()
+    }
+}
+
+/// Scan a domain through the pipeline with its default options, as `fatt scan` would
+pub async fn scan_domain(domain: &str, resources: &ScanResources) -> Result<usize> {
+    let mut input = NamedTempFile::new()?;
+    writeln!(input, "{}", domain)?;
+
+    pipeline::run_pipeline(
+        &[InputSource::new(input.path().to_str().unwrap(), None)],
+        resources,
+        &PipelineOptions::default(),
+        Arc::new(PipelineMetrics::default()),
+        Arc::new(AtomicUsize::new(0)),
+    )
+    .await
+}
+
 /// A test utility for capturing and testing log output
 pub struct LogCapture {
     lines: Arc<Mutex<Vec<String>>>,
@@ -53,6 +91,12 @@ impl LogCapture {
     }
 }
 
+impl Default for LogCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // The actual tracing layer implementation
 struct TestLayer {
     lines: Arc<Mutex<Vec<String>>>,
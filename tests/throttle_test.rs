@@ -0,0 +1,190 @@
+use anyhow::Result;
+use fatt::config::InputSource;
+use fatt::db;
+use fatt::pipeline::{self, PipelineMetrics, PipelineOptions};
+use fatt::rules::{Rule, RuleSet, Severity};
+use fatt::scanner::{self, ScanOptions, ScanResources};
+use fatt::throttle::{AsnDb, Throttle, ThrottleKey, ThrottlePolicy};
+use std::io::Write;
+use std::net::IpAddr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+use tokio::sync::Mutex;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn ip(ip: &str) -> IpAddr {
+    ip.parse().unwrap()
+}
+
+fn asn_file() -> Result<NamedTempFile> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "1.0.0.0\t1.0.0.255\t13335\tUS\tCLOUDFLARENET")?;
+    writeln!(file, "1.0.1.0\t1.0.3.255\t0\tNone\tNot routed")?;
+    writeln!(file, "203.0.113.0\t203.0.113.255\t64500\tZZ\tEXAMPLE")?;
+    writeln!(
+        file,
+        "2001:db8::\t2001:db8:ffff:ffff:ffff:ffff:ffff:ffff\t64501\tZZ\tEXAMPLE6"
+    )?;
+    Ok(file)
+}
+
+#[test]
+fn test_asn_lookup() -> Result<()> {
+    let file = asn_file()?;
+    let db = AsnDb::from_file(file.path().to_str().unwrap())?;
+
+    // Unrouted ranges are skipped
+    assert_eq!(db.len(), 3);
+    assert_eq!(db.lookup(ip("1.0.0.1")), Some(13335));
+    assert_eq!(db.lookup(ip("1.0.2.1")), None);
+    assert_eq!(db.lookup(ip("203.0.113.255")), Some(64500));
+    assert_eq!(db.lookup(ip("203.0.114.0")), None);
+    assert_eq!(db.lookup(ip("2001:db8::1")), Some(64501));
+    assert_eq!(db.lookup(ip("0.0.0.1")), None);
+
+    let mut broken = NamedTempFile::new()?;
+    writeln!(broken, "1.0.0.0\tnot-an-ip\t13335")?;
+    assert!(AsnDb::from_file(broken.path().to_str().unwrap()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_throttle_keys() -> Result<()> {
+    let file = asn_file()?;
+    let throttle = Throttle::new(
+        ThrottlePolicy {
+            per_tld: 10,
+            per_asn: 5,
+            per_subnet: 2,
+        },
+        Some(AsnDb::from_file(file.path().to_str().unwrap())?),
+    );
+
+    let keys = throttle.keys("Shop.Example.COM:8443", &[ip("203.0.113.7")]);
+    assert_eq!(
+        keys,
+        vec![
+            ThrottleKey::Tld("com".to_string()),
+            ThrottleKey::Asn(64500),
+            ThrottleKey::Subnet(ip("203.0.113.0")),
+        ]
+    );
+    assert_eq!(keys[2].to_string(), "203.0.113.0/24");
+
+    // IP hosts have no TLD, and unknown addresses no ASN
+    assert_eq!(
+        throttle.keys("198.51.100.1", &[ip("198.51.100.1")]),
+        vec![ThrottleKey::Subnet(ip("198.51.100.0"))]
+    );
+    assert_eq!(
+        throttle.keys("example.org", &[ip("2001:db8:1:2::1")])[2].to_string(),
+        "2001:db8:1::/48"
+    );
+
+    // Groups without a limit aren't tracked
+    let unlimited = Throttle::default();
+    assert!(unlimited
+        .keys("example.com", &[ip("203.0.113.7")])
+        .is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_throttle_limits_concurrency() {
+    let throttle = Arc::new(Throttle::new(
+        ThrottlePolicy {
+            per_tld: 1,
+            ..Default::default()
+        },
+        None,
+    ));
+    let com = throttle.keys("a.example.com", &[]);
+    let org = throttle.keys("b.example.org", &[]);
+
+    let held = throttle.acquire(&com).await;
+
+    // Another TLD isn't held up
+    let other = tokio::time::timeout(Duration::from_millis(100), throttle.acquire(&org)).await;
+    assert!(other.is_ok());
+
+    // The same TLD waits for the slot to be released
+    let waiting = {
+        let throttle = throttle.clone();
+        let com = com.clone();
+        tokio::spawn(async move {
+            let _permit = throttle.acquire(&com).await;
+        })
+    };
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!waiting.is_finished());
+
+    drop(held);
+    tokio::time::timeout(Duration::from_secs(1), waiting)
+        .await
+        .expect("slot was not released")
+        .unwrap();
+    assert_eq!(throttle.waits(), 1);
+}
+
+#[tokio::test]
+async fn test_pipeline_throttles_requests_per_subnet() -> Result<()> {
+    let delay = Duration::from_millis(200);
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404).set_delay(delay))
+        .mount(&mock_server)
+        .await;
+
+    let ruleset = RuleSet {
+        rules: (0..4)
+            .map(|i| {
+                Rule::new(
+                    &format!("Rule {}", i),
+                    &format!("/file-{}", i),
+                    "secret",
+                    "desc",
+                    Severity::High,
+                )
+            })
+            .collect(),
+    };
+    let resources = ScanResources::new(
+        scanner::create_http_client(5, 2)?,
+        ruleset,
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
+        Arc::new(Mutex::new(db::init_db(":memory:")?)),
+        ScanOptions::default(),
+    )
+    .with_throttle(Throttle::new(
+        ThrottlePolicy {
+            per_subnet: 1,
+            ..Default::default()
+        },
+        None,
+    ));
+
+    let mut input = NamedTempFile::new()?;
+    writeln!(input, "{}", mock_server.uri().replace("http://", ""))?;
+
+    let started = Instant::now();
+    pipeline::run_pipeline(
+        &[InputSource::new(input.path().to_str().unwrap(), None)],
+        &resources,
+        &PipelineOptions::default(),
+        Arc::new(PipelineMetrics::default()),
+        Arc::new(AtomicUsize::new(0)),
+    )
+    .await?;
+
+    // Three fingerprinting requests, then the rule checks one at a time instead of
+    // all at once
+    assert!(started.elapsed() >= delay * 7);
+    assert!(resources.throttle.waits() > 0);
+
+    Ok(())
+}
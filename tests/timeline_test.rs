@@ -0,0 +1,166 @@
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use fatt::db::{self, DomainScan, Finding};
+use fatt::rules::{Rule, RuleSet, Severity};
+use fatt::store::StoreKind;
+use fatt::timeline::{self, Bucket, ScanRecord};
+use std::collections::HashSet;
+use tempfile::tempdir;
+
+fn at(day: u32, hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 3, day, hour, 0, 0).unwrap()
+}
+
+fn scan(started_at: DateTime<Utc>, detected: &[(&str, &str)], domains: &[&str]) -> ScanRecord {
+    ScanRecord {
+        started_at,
+        detected: detected
+            .iter()
+            .map(|(domain, rule)| (domain.to_string(), rule.to_string()))
+            .collect(),
+        domains: domains.iter().map(|domain| domain.to_string()).collect(),
+    }
+}
+
+fn ruleset() -> RuleSet {
+    RuleSet {
+        rules: vec![
+            Rule::new(
+                "Git Config",
+                "/.git/config",
+                "[core]",
+                "desc",
+                Severity::Critical,
+            ),
+            Rule::new("Env File", "/.env", "APP_KEY=", "desc", Severity::Medium),
+        ],
+    }
+}
+
+#[test]
+fn test_bucket_start() {
+    // 2026-03-04 is a Wednesday
+    let time = Utc.with_ymd_and_hms(2026, 3, 4, 13, 45, 12).unwrap();
+    assert_eq!(
+        Bucket::Hour.start(time),
+        Utc.with_ymd_and_hms(2026, 3, 4, 13, 0, 0).unwrap()
+    );
+    assert_eq!(Bucket::Day.start(time), at(4, 0));
+    assert_eq!(Bucket::Week.start(time), at(2, 0));
+    assert_eq!(Bucket::Month.start(time), at(1, 0));
+    assert_eq!(
+        Bucket::Month.next(at(1, 0)),
+        Utc.with_ymd_and_hms(2026, 4, 1, 0, 0, 0).unwrap()
+    );
+
+    assert_eq!("day".parse::<Bucket>().unwrap(), Bucket::Day);
+    assert!("year".parse::<Bucket>().is_err());
+}
+
+#[test]
+fn test_build_timeline() {
+    let scans = vec![
+        scan(
+            at(1, 9),
+            &[("a.com", "Git Config"), ("b.com", "Env File")],
+            &["a.com", "b.com"],
+        ),
+        // Fixes a.com's finding; b.com wasn't checked, so its finding stays open
+        scan(at(3, 9), &[("c.com", "Git Config")], &["a.com", "c.com"]),
+        scan(at(3, 18), &[("c.com", "Git Config")], &["c.com"]),
+    ];
+    let timeline = timeline::build_timeline(&scans, Bucket::Day, Some(&ruleset()));
+
+    // The scanless day in between is kept
+    assert_eq!(timeline.len(), 3);
+    assert_eq!(timeline[0].start, at(1, 0));
+    assert_eq!(timeline[0].scans, 1);
+    assert_eq!(timeline[0].new.get("critical"), Some(&1));
+    assert_eq!(timeline[0].new.get("medium"), Some(&1));
+    assert!(timeline[0].fixed.is_empty());
+    assert_eq!(timeline[0].open, 2);
+
+    assert_eq!(timeline[1].scans, 0);
+    assert!(timeline[1].new.is_empty());
+    assert_eq!(timeline[1].open, 2);
+
+    assert_eq!(timeline[2].scans, 2);
+    assert_eq!(timeline[2].new.get("critical"), Some(&1));
+    assert_eq!(timeline[2].fixed.get("critical"), Some(&1));
+    assert_eq!(timeline[2].open, 2);
+
+    // Without the rules, severities are unknown
+    let timeline = timeline::build_timeline(&scans, Bucket::Month, None);
+    assert_eq!(timeline.len(), 1);
+    assert_eq!(timeline[0].new.get("unknown"), Some(&3));
+    assert_eq!(timeline[0].fixed.get("unknown"), Some(&1));
+
+    assert!(timeline::build_timeline(&[], Bucket::Day, None).is_empty());
+}
+
+#[test]
+fn test_write_csv() -> Result<()> {
+    let scans = vec![scan(at(1, 9), &[("a.com", "Git Config")], &["a.com"])];
+    let timeline = timeline::build_timeline(&scans, Bucket::Day, Some(&ruleset()));
+
+    let mut output = Vec::new();
+    timeline::write_csv(&timeline, Bucket::Day, &mut output)?;
+    let csv = String::from_utf8(output)?;
+    let mut lines = csv.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("Period,Scans,New,Fixed,Open,New critical,Fixed critical,"));
+    assert!(lines.next().unwrap().starts_with("2026-03-01,1,1,0,1,1,0,"));
+
+    Ok(())
+}
+
+#[test]
+fn test_load_history() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("results.sqlite");
+    let database = path.to_str().unwrap();
+    let conn = db::init_db(database)?;
+
+    let finding = |domain: &str, rule_name: &str| Finding {
+        domain: domain.to_string(),
+        rule_name: rule_name.to_string(),
+        matched_path: "/".to_string(),
+        detected: true,
+        scanned_at: Utc::now(),
+        ..Default::default()
+    };
+    db::start_scan_session(&conn, "scan-1", at(1, 9))?;
+    db::record_scan_findings(&conn, "scan-1", &[finding("a.com", "Git Config")])?;
+    db::start_scan_session(&conn, "scan-2", at(2, 9))?;
+    db::record_domain_scan(
+        &conn,
+        &DomainScan {
+            scan_id: "scan-2".to_string(),
+            domain: "a.com".to_string(),
+            scanned_at: at(2, 9),
+            ips: Vec::new(),
+            technologies: Vec::new(),
+            error: None,
+            certificate: None,
+        },
+    )?;
+    drop(conn);
+
+    let mut scans = timeline::load_history(StoreKind::Sqlite, database, 1, None)?;
+    scans.sort_by_key(|scan| scan.started_at);
+    assert_eq!(scans.len(), 2);
+    assert_eq!(
+        scans[0].detected,
+        vec![("a.com".to_string(), "Git Config".to_string())]
+    );
+    assert!(scans[1].detected.is_empty());
+    assert_eq!(scans[1].domains, HashSet::from(["a.com".to_string()]));
+
+    let timeline = timeline::build_timeline(&scans, Bucket::Day, None);
+    assert_eq!(timeline[1].fixed.get("unknown"), Some(&1));
+    assert_eq!(timeline[1].open, 0);
+
+    Ok(())
+}
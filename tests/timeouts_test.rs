@@ -0,0 +1,125 @@
+use anyhow::Result;
+use fatt::config::ScanConfig;
+use fatt::rules::RuleSet;
+use fatt::scanner::{self, HttpClientConfig, ScanOptions, ScanResources};
+use fatt::timeouts::{TimeoutPhase, Timeouts};
+use rusqlite::Connection;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn resources(timeouts: Timeouts) -> Result<ScanResources> {
+    let client = scanner::build_http_client(&HttpClientConfig {
+        timeouts,
+        ..Default::default()
+    })?;
+    let options = ScanOptions {
+        timeouts,
+        ..Default::default()
+    };
+
+    Ok(ScanResources::new(
+        client,
+        RuleSet { rules: vec![] },
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
+        Arc::new(Mutex::new(Connection::open_in_memory()?)),
+        options,
+    ))
+}
+
+fn millis(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+async fn slow_server(delay: Duration) -> MockServer {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_delay(delay))
+        .mount(&mock_server)
+        .await;
+    mock_server
+}
+
+#[test]
+fn test_timeouts_from_config() {
+    let config = ScanConfig {
+        tls_timeout: 3,
+        first_byte_timeout: 20,
+        ..Default::default()
+    };
+
+    let timeouts = config.timeouts();
+    assert_eq!(timeouts.connect, Duration::from_secs(5));
+    assert_eq!(timeouts.tls, Duration::from_secs(3));
+    assert_eq!(timeouts.handshake(), Duration::from_secs(8));
+    assert_eq!(timeouts.response_headers(), Duration::from_secs(28));
+    assert_eq!(timeouts.total, Duration::from_secs(config.http_timeout));
+}
+
+#[tokio::test]
+async fn test_first_byte_timeout() -> Result<()> {
+    let mock_server = slow_server(Duration::from_secs(3)).await;
+    let resources = resources(Timeouts {
+        connect: millis(200),
+        tls: millis(200),
+        first_byte: millis(300),
+        ..Default::default()
+    })?;
+
+    let error = resources.fetch(&mock_server.uri(), None).await.unwrap_err();
+    assert!(error.to_string().contains("first byte timeout"));
+
+    let stats = resources.stats.snapshot();
+    assert_eq!(stats.timeouts, 1);
+    assert_eq!(stats.timeout_phases.get(&TimeoutPhase::FirstByte), Some(&1));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_total_timeout() -> Result<()> {
+    let mock_server = slow_server(Duration::from_secs(3)).await;
+    let resources = resources(Timeouts {
+        total: millis(300),
+        ..Default::default()
+    })?;
+
+    assert!(resources.fetch(&mock_server.uri(), None).await.is_err());
+    let stats = resources.stats.snapshot();
+    assert_eq!(stats.timeout_phases.get(&TimeoutPhase::Total), Some(&1));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tls_handshake_timeout() -> Result<()> {
+    // Accepts connections but never answers the TLS handshake
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+
+    let resources = resources(Timeouts {
+        connect: millis(200),
+        tls: millis(200),
+        ..Default::default()
+    })?;
+
+    let error = resources
+        .fetch(&format!("https://{}/", addr), None)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("TLS handshake timeout"));
+
+    let stats = resources.stats.snapshot();
+    assert_eq!(stats.timeout_phases.get(&TimeoutPhase::Tls), Some(&1));
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use anyhow::Result;
+use chrono::Utc;
+use fatt::rules::Severity;
+use fatt::stream::{BatchProgress, EventStream, StreamEvent, WorkerSummary};
+use fatt::tui::{Dashboard, Subscription, RECENT_FINDINGS};
+
+fn finding(domain: &str) -> StreamEvent {
+    StreamEvent::Finding {
+        domain: domain.to_string(),
+        rule: "Git".to_string(),
+        severity: Some(Severity::High),
+        path: "/.git/HEAD".to_string(),
+        tag: None,
+        status: 200,
+        redirect_chain: Vec::new(),
+        remediation: None,
+        found_at: Utc::now(),
+    }
+}
+
+fn progress(domains_processed: usize, findings: usize) -> StreamEvent {
+    StreamEvent::Progress {
+        domains_processed,
+        domains_total: 4,
+        checks_completed: domains_processed * 2,
+        checks_total: 8,
+        findings,
+        batches: None,
+    }
+}
+
+fn finished() -> StreamEvent {
+    StreamEvent::Finished {
+        scan_id: "scan-1".to_string(),
+        domains: 4,
+        findings: 2,
+        elapsed_secs: 1.5,
+    }
+}
+
+#[test]
+fn test_dashboard_renders_progress_and_findings() {
+    let mut dashboard = Dashboard::new("master:9000");
+    dashboard.apply(StreamEvent::Started {
+        scan_id: "scan-1".to_string(),
+        domains: 4,
+        checks: 8,
+    });
+    dashboard.apply(finding("example.com"));
+    dashboard.apply(progress(2, 1));
+
+    let screen = dashboard.render();
+    assert!(screen.starts_with("fatt - master:9000 - scan scan-1"));
+    assert!(screen.contains("2/4 (50%)"));
+    assert!(screen.contains("Findings  1"));
+    assert!(screen.contains("high      Git  example.com/.git/HEAD  [200]"));
+    assert!(!dashboard.is_finished());
+
+    dashboard.apply(finished());
+    let screen = dashboard.render();
+    assert!(dashboard.is_finished());
+    assert!(screen.contains("8/8 (100%)"));
+    assert!(screen.contains("Finished: 4 domains, 2 findings in 1.5s"));
+}
+
+#[test]
+fn test_dashboard_keeps_recent_findings() {
+    let mut dashboard = Dashboard::new("master:9000");
+    for i in 0..RECENT_FINDINGS + 5 {
+        dashboard.apply(finding(&format!("host{}.example.com", i)));
+    }
+
+    let screen = dashboard.render();
+    assert_eq!(screen.matches("[200]").count(), RECENT_FINDINGS);
+    // Newest first
+    assert!(screen.find("host19.example.com") < screen.find("host18.example.com"));
+    assert!(!screen.contains("host4.example.com/"));
+
+    // A subscriber that joined late trusts the scan's count
+    dashboard.apply(progress(3, 50));
+    assert!(dashboard.render().contains("Findings  50"));
+}
+
+#[test]
+fn test_dashboard_renders_workers() {
+    let mut dashboard = Dashboard::new("master:9000");
+    assert!(!dashboard.render().contains("Workers"));
+
+    dashboard.apply(StreamEvent::Workers {
+        workers: vec![WorkerSummary {
+            id: "scanner-01".to_string(),
+            max_concurrency: 10,
+            active_scans: 3,
+            completed_scans: 120,
+            findings: 4,
+        }],
+    });
+
+    let screen = dashboard.render();
+    assert!(screen.contains("Workers (1)"));
+    assert!(screen.contains("scanner-01"));
+    assert!(screen.contains("active    3/10"));
+
+    // A master's progress counts the batches its workers reported
+    dashboard.apply(StreamEvent::Progress {
+        domains_processed: 2,
+        domains_total: 4,
+        checks_completed: 4,
+        checks_total: 8,
+        findings: 1,
+        batches: Some(BatchProgress { done: 1, total: 2 }),
+    });
+    let screen = dashboard.render();
+    assert!(screen.contains("Batches"));
+    assert!(screen.contains("1/2"));
+}
+
+#[tokio::test]
+async fn test_subscription_receives_remote_events() -> Result<()> {
+    let events = EventStream::listen("127.0.0.1:0", None).await?;
+    let mut subscription = Subscription::connect(&events.local_addr().to_string(), None).await?;
+
+    events.publish(progress(1, 0));
+    events.publish(finding("example.com"));
+    let (_, received) = tokio::join!(events.finish(finished()), async {
+        let mut received = Vec::new();
+        while let Some(event) = subscription.next_event().await? {
+            received.push(event);
+        }
+        anyhow::Ok(received)
+    });
+    let received = received?;
+
+    assert_eq!(received.len(), 3);
+    assert_eq!(received[0], progress(1, 0));
+    assert!(matches!(&received[1], StreamEvent::Finding { domain, .. } if domain == "example.com"));
+    assert_eq!(received[2], finished());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_subscription_requires_event_stream() -> Result<()> {
+    let server = wiremock::MockServer::start().await;
+
+    let result = Subscription::connect(&server.uri().replace("http://", ""), None).await;
+    assert!(result.is_err());
+
+    Ok(())
+}
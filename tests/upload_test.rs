@@ -0,0 +1,128 @@
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use fatt::config::SinkConfig;
+use fatt::upload::{self, S3Config, S3Url};
+use wiremock::matchers::{body_string, header, header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn s3_config(endpoint: Option<String>) -> S3Config {
+    S3Config {
+        endpoint,
+        region: Some("us-east-1".to_string()),
+        access_key_id: Some("AKIDEXAMPLE".to_string()),
+        secret_access_key: Some("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string()),
+        session_token: None,
+    }
+}
+
+#[test]
+fn test_parse_s3_url() -> Result<()> {
+    let target: S3Url = "s3://fatt-results/reports/findings.csv".parse()?;
+    assert_eq!(target.bucket, "fatt-results");
+    assert_eq!(target.key, "reports/findings.csv");
+    assert_eq!(target.file_name(), "findings.csv");
+    assert_eq!(
+        target.sibling("findings-prod.csv").to_string(),
+        "s3://fatt-results/reports/findings-prod.csv"
+    );
+
+    assert!("s3://fatt-results".parse::<S3Url>().is_err());
+    assert!("s3://fatt-results/reports/".parse::<S3Url>().is_err());
+    assert!("reports/findings.csv".parse::<S3Url>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_upload_sink() -> Result<()> {
+    let sink = SinkConfig::upload(
+        &"s3://fatt-results/findings.jsonl".parse()?,
+        S3Config::default(),
+    )?;
+    assert_eq!(sink.format, "jsonl");
+    assert_eq!(sink.path, "s3://fatt-results/findings.jsonl");
+    assert!(sink.upload_target()?.is_some());
+
+    assert!(
+        SinkConfig::upload(&"s3://fatt-results/findings".parse()?, S3Config::default()).is_err()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_sign_request() -> Result<()> {
+    let signer = s3_config(None).resolve()?;
+    let target: S3Url = "s3://examplebucket/reports/findings 1.csv".parse()?;
+    let url = signer.object_url(&target)?;
+    assert_eq!(
+        url.as_str(),
+        "https://examplebucket.s3.us-east-1.amazonaws.com/reports/findings%201.csv"
+    );
+
+    let now = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+    let headers = signer.sign("PUT", &url, b"hello", now);
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| *header == name)
+            .map(|(_, value)| value.as_str())
+    };
+    assert_eq!(header("x-amz-date"), Some("20260102T030405Z"));
+    assert_eq!(
+        header("x-amz-content-sha256"),
+        Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+    );
+    assert_eq!(
+        header("authorization"),
+        Some(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20260102/us-east-1/s3/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, \
+             Signature=09ccb36b88e590527d4c6ee3ee7ae8d3c0b58c26186c7c33cfb18b1afb1455cb"
+        )
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upload_export() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/fatt-results/reports/findings.csv"))
+        .and(header("content-type", "text/csv"))
+        .and(header_exists("authorization"))
+        .and(body_string("Domain,Rule\n"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let target: S3Url = "s3://fatt-results/reports/findings.csv".parse()?;
+    let uploaded = upload::upload_export(&s3_config(Some(mock_server.uri())), &target, |path| {
+        std::fs::write(path, "Domain,Rule\n")?;
+        Ok(())
+    })
+    .await?;
+    assert_eq!(uploaded, vec![target.clone()]);
+
+    // A rejected upload fails the export
+    Mock::given(method("PUT"))
+        .and(path("/fatt-results/denied.csv"))
+        .respond_with(ResponseTemplate::new(403).set_body_string("AccessDenied"))
+        .mount(&mock_server)
+        .await;
+    let error = upload::upload_export(
+        &s3_config(Some(mock_server.uri())),
+        &"s3://fatt-results/denied.csv".parse()?,
+        |path| {
+            std::fs::write(path, "Domain,Rule\n")?;
+            Ok(())
+        },
+    )
+    .await
+    .unwrap_err();
+    assert!(format!("{:#}", error).contains("AccessDenied"));
+
+    Ok(())
+}
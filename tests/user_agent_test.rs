@@ -0,0 +1,98 @@
+use anyhow::Result;
+use fatt::db;
+use fatt::rules::{Rule, RuleSet, Severity};
+use fatt::scanner::{self, ScanOptions, ScanResources};
+use fatt::user_agent::UserAgentPool;
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+use tokio::sync::Mutex;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+mod test_helpers;
+
+fn agents(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn test_user_agent_rotation() -> Result<()> {
+    // Rotates on every pick
+    let pool = UserAgentPool::new(agents(&["ua-1", "ua-2", "ua-3"]), false)?;
+    let picked: Vec<&str> = (0..4).map(|_| pool.pick("example.com")).collect();
+    assert_eq!(picked, vec!["ua-1", "ua-2", "ua-3", "ua-1"]);
+
+    // Sticky pools always give a domain the same User-Agent
+    let sticky = UserAgentPool::new(agents(&["ua-1", "ua-2", "ua-3"]), true)?;
+    let first = sticky.pick("example.com");
+    assert!((0..10).all(|_| sticky.pick("Example.com") == first));
+
+    assert!(UserAgentPool::new(Vec::new(), false).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_user_agent_file() -> Result<()> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "# Desktop browsers")?;
+    writeln!(file, "Mozilla/5.0 (X11; Linux x86_64)")?;
+    writeln!(file)?;
+    writeln!(file, "Mozilla/5.0 (Windows NT 10.0; Win64; x64)")?;
+
+    let pool = UserAgentPool::from_file(file.path().to_str().unwrap(), false)?;
+    assert_eq!(pool.len(), 2);
+    assert_eq!(pool.pick("example.com"), "Mozilla/5.0 (X11; Linux x86_64)");
+
+    let empty = NamedTempFile::new()?;
+    assert!(UserAgentPool::from_file(empty.path().to_str().unwrap(), false).is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_scan_records_user_agent() -> Result<()> {
+    let mock_server = MockServer::start().await;
+
+    // Only answer when the rotated User-Agent is sent
+    Mock::given(method("GET"))
+        .and(path("/.env"))
+        .and(header("user-agent", "custom-agent"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("APP_KEY=secret"))
+        .mount(&mock_server)
+        .await;
+
+    let ruleset = RuleSet {
+        rules: vec![Rule::new(
+            "Env",
+            "/.env",
+            "APP_KEY=",
+            "desc",
+            Severity::High,
+        )],
+    };
+    let db_conn = Arc::new(Mutex::new(db::init_db(":memory:")?));
+    let resources = ScanResources::new(
+        scanner::create_http_client(5, 2)?,
+        ruleset,
+        Arc::new(fatt::resolver::DnsResolver::new_for_testing()?),
+        db_conn.clone(),
+        ScanOptions::default(),
+    )
+    .with_user_agents(UserAgentPool::new(agents(&["custom-agent"]), true)?);
+
+    let hostname = mock_server.uri().replace("http://", "");
+    test_helpers::scan_domain(&hostname, &resources).await?;
+    assert_eq!(resources.matches_found.load(Ordering::Relaxed), 1);
+
+    let conn = db_conn.lock().await;
+    let user_agent: String = conn.query_row(
+        "SELECT user_agent FROM findings WHERE rule_name = 'Env'",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(user_agent, "custom-agent");
+
+    Ok(())
+}
@@ -70,6 +70,29 @@ fn test_chunk_vector() {
     assert_eq!(chunks.len(), 0);
 }
 
+#[test]
+fn test_normalize_domain() {
+    assert_eq!(utils::normalize_domain(" Example.COM "), "example.com");
+    assert_eq!(utils::normalize_domain("example.com."), "example.com");
+    assert_eq!(
+        utils::normalize_domain("Example.com.:8443"),
+        "example.com:8443"
+    );
+    assert_eq!(
+        utils::normalize_domain("bücher.example"),
+        "xn--bcher-kva.example"
+    );
+    assert_eq!(
+        utils::normalize_domain("BÜCHER.example."),
+        "xn--bcher-kva.example"
+    );
+    assert_eq!(
+        utils::normalize_domain("[2001:DB8::1]:8443"),
+        "[2001:db8::1]:8443"
+    );
+    assert_eq!(utils::normalize_domain("2001:DB8::1"), "2001:db8::1");
+}
+
 #[test]
 fn test_is_valid_domain() {
     // Valid domains
@@ -78,13 +101,15 @@ fn test_is_valid_domain() {
     assert!(utils::is_valid_domain("sub-domain.example.co.uk"));
     assert!(utils::is_valid_domain("xn--bcher-kva.example")); // IDN
     assert!(utils::is_valid_domain("123.example.com"));
+    assert!(utils::is_valid_domain("example.com.")); // fully qualified
 
     // Invalid domains
     assert!(!utils::is_valid_domain(""));
     assert!(!utils::is_valid_domain("example"));
     assert!(!utils::is_valid_domain(".com"));
     assert!(!utils::is_valid_domain("example..com"));
-    assert!(!utils::is_valid_domain("example.com."));
+    assert!(!utils::is_valid_domain("example.com.."));
+    assert!(!utils::is_valid_domain("."));
     assert!(!utils::is_valid_domain("http://example.com"));
     assert!(!utils::is_valid_domain("example.com/path"));
     assert!(!utils::is_valid_domain("user@example.com"));
@@ -104,3 +129,90 @@ fn test_format_duration() {
     assert_eq!(utils::format_duration(86400.0), "24h 0m 0.0s");
     assert_eq!(utils::format_duration(90061.5), "25h 1m 1.5s");
 }
+
+#[test]
+fn test_format_bytes() {
+    assert_eq!(utils::format_bytes(0), "0 B");
+    assert_eq!(utils::format_bytes(1023), "1023 B");
+    assert_eq!(utils::format_bytes(1536), "1.5 KiB");
+    assert_eq!(utils::format_bytes(5 * 1024 * 1024 * 1024), "5.0 GiB");
+}
+
+#[test]
+fn test_split_host_port() {
+    assert_eq!(utils::split_host_port("example.com"), ("example.com", None));
+    assert_eq!(
+        utils::split_host_port("example.com:8080"),
+        ("example.com", Some(8080))
+    );
+    assert_eq!(utils::split_host_port("2001:db8::1"), ("2001:db8::1", None));
+    assert_eq!(utils::split_host_port("[::1]"), ("::1", None));
+    assert_eq!(utils::split_host_port("[::1]:8443"), ("::1", Some(8443)));
+}
+
+#[test]
+fn test_build_url() -> anyhow::Result<()> {
+    assert_eq!(
+        utils::build_url("Example.com", ".git/HEAD")?,
+        "https://example.com/.git/HEAD"
+    );
+    assert_eq!(
+        utils::build_url("http://example.com:8080", "/.env")?,
+        "http://example.com:8080/.env"
+    );
+    assert_eq!(
+        utils::build_url("2001:db8::1", "/.env")?,
+        "https://[2001:db8::1]/.env"
+    );
+    assert_eq!(
+        utils::build_url("[2001:db8::1]:8443", "/api?debug=1")?,
+        "https://[2001:db8::1]:8443/api?debug=1"
+    );
+    assert_eq!(
+        utils::build_url("http://[::1]:8080", "/")?,
+        "http://[::1]:8080/"
+    );
+
+    // The scheme's default port is left out
+    assert_eq!(
+        utils::base_url("https", "192.0.2.1", Some(443))?,
+        "https://192.0.2.1"
+    );
+    assert!(utils::build_url("exa mple.com", "/").is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retry_policy() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Backoff doubles on every retry
+    let policy = utils::RetryPolicy::new(3, 100);
+    assert_eq!(policy.backoff_range(0), (100, 200));
+    assert_eq!(policy.backoff_range(2), (400, 800));
+
+    // Succeeds on the third attempt with two retries
+    let attempts = AtomicU32::new(0);
+    let result = utils::RetryPolicy::new(2, 1)
+        .run(|| async {
+            if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                anyhow::bail!("transient failure")
+            }
+            Ok("done")
+        })
+        .await;
+    assert_eq!(result.unwrap(), "done");
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+    // Gives up once retries are exhausted
+    let attempts = AtomicU32::new(0);
+    let result: anyhow::Result<()> = utils::RetryPolicy::new(1, 1)
+        .run(|| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            anyhow::bail!("permanent failure")
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, TimeZone, Utc};
+use fatt::window::ScanWindow;
+use std::time::Duration;
+
+fn utc(hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 15, hour, minute, 0).unwrap()
+}
+
+#[test]
+fn test_parse_scan_window() {
+    let window: ScanWindow = "22:00-06:00".parse().unwrap();
+    assert_eq!(window.to_string(), "22:00-06:00 UTC");
+
+    let window: ScanWindow = "09:30-17:00 Europe/Berlin".parse().unwrap();
+    assert_eq!(window.to_string(), "09:30-17:00 Europe/Berlin");
+
+    assert!("22:00".parse::<ScanWindow>().is_err());
+    assert!("25:00-06:00".parse::<ScanWindow>().is_err());
+    assert!("22:00-06:00 Mars/Olympus".parse::<ScanWindow>().is_err());
+    assert!("06:00-06:00".parse::<ScanWindow>().is_err());
+}
+
+#[test]
+fn test_overnight_window() {
+    let window: ScanWindow = "22:00-06:00".parse().unwrap();
+    assert!(window.contains(utc(23, 0)));
+    assert!(window.contains(utc(5, 59)));
+    assert!(!window.contains(utc(6, 0)));
+    assert!(!window.contains(utc(12, 0)));
+
+    assert_eq!(window.until_open(utc(23, 0)), None);
+    assert_eq!(
+        window.until_open(utc(21, 30)),
+        Some(Duration::from_secs(30 * 60))
+    );
+    assert_eq!(window.next_open(utc(12, 0)), utc(22, 0));
+}
+
+#[test]
+fn test_daytime_window_in_time_zone() {
+    // Berlin is an hour ahead of UTC in January
+    let window: ScanWindow = "09:00-17:00 Europe/Berlin".parse().unwrap();
+    assert!(window.contains(utc(8, 0)));
+    assert!(!window.contains(utc(16, 0)));
+
+    // After the window closes, it next opens the following morning
+    assert_eq!(
+        window.next_open(utc(17, 0)),
+        Utc.with_ymd_and_hms(2024, 1, 16, 8, 0, 0).unwrap()
+    );
+    assert_eq!(
+        window.until_open(utc(7, 0)),
+        Some(Duration::from_secs(60 * 60))
+    );
+}
@@ -0,0 +1,116 @@
+use anyhow::Result;
+use chrono::Utc;
+use fatt::stats::StatsSnapshot;
+use fatt::store::StoreKind;
+use fatt::workdir::{ScanState, ScanStatus, ScanSummary, Workdir};
+use tempfile::tempdir;
+
+#[test]
+fn test_workdir_layout() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let root = temp_dir.path().join("runs").join("2024-06-01");
+    let workdir = Workdir::create(&root)?;
+
+    assert!(workdir.logs_dir().is_dir());
+    assert_eq!(workdir.log_file(), root.join("logs").join("fatt.log"));
+    assert_eq!(
+        workdir.results_path(StoreKind::Sqlite),
+        root.join("results.sqlite")
+    );
+    assert_eq!(workdir.results_path(StoreKind::Jsonl), root.join("results"));
+
+    // Creating it again reuses what's there
+    Workdir::create(&root)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_workdir_state() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let workdir = Workdir::create(temp_dir.path())?;
+    assert!(workdir.read_state()?.is_none());
+
+    let mut state = ScanState::running("scan-1", Utc::now(), 10);
+    workdir.write_state(&state)?;
+    state.domains_processed = 4;
+    workdir.write_state(&state)?;
+    assert_eq!(workdir.read_state()?, Some(state.clone()));
+
+    workdir.fail(&anyhow::anyhow!("Scan pipeline failed"))?;
+    let failed = workdir.read_state()?.unwrap();
+    assert_eq!(failed.status, ScanStatus::Failed);
+    assert_eq!(failed.domains_processed, 4);
+    assert_eq!(failed.error.as_deref(), Some("Scan pipeline failed"));
+
+    // A finished scan stays finished
+    state.status = ScanStatus::Finished;
+    workdir.write_state(&state)?;
+    workdir.fail(&anyhow::anyhow!("Scan produced 1 unexpected findings"))?;
+    assert_eq!(workdir.read_state()?.unwrap().status, ScanStatus::Finished);
+
+    Ok(())
+}
+
+#[test]
+fn test_workdir_failure_before_scan_started() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let workdir = Workdir::create(temp_dir.path())?;
+
+    workdir.fail(&anyhow::anyhow!("Failed to load rules"))?;
+    let state = workdir.read_state()?.unwrap();
+    assert_eq!(state.status, ScanStatus::Failed);
+    assert!(state.scan_id.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_reused_workdir_starts_over() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let workdir = Workdir::create(temp_dir.path())?;
+
+    let mut state = ScanState::running("scan-1", Utc::now(), 10);
+    state.status = ScanStatus::Finished;
+    workdir.write_state(&state)?;
+    std::fs::write(workdir.summary_file(), "{}")?;
+
+    // The next scan fails before it starts scanning
+    workdir.begin()?;
+    assert!(!workdir.summary_file().exists());
+    workdir.fail(&anyhow::anyhow!("Failed to load rules"))?;
+    let state = workdir.read_state()?.unwrap();
+    assert_eq!(state.status, ScanStatus::Failed);
+    assert!(state.scan_id.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_workdir_summary() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let workdir = Workdir::create(temp_dir.path())?;
+
+    let mut network = StatsSnapshot::default();
+    network.status_counts.insert(200, 3);
+    workdir.write_summary(&ScanSummary {
+        scan_id: "scan-1".to_string(),
+        started_at: Utc::now(),
+        finished_at: Utc::now(),
+        elapsed_secs: 1.5,
+        domains: 2,
+        checks: 6,
+        findings: 1,
+        unexpected: 0,
+        risk_score: 4.0,
+        network,
+        incomplete: false,
+    })?;
+
+    let summary: ScanSummary =
+        serde_json::from_str(&std::fs::read_to_string(workdir.summary_file())?)?;
+    assert_eq!(summary.scan_id, "scan-1");
+    assert_eq!(summary.network.status_counts[&200], 3);
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use anyhow::Result;
+use fatt::db::{self, ShardedDb};
+use fatt::store::{self, FindingDetails, PendingFinding, ResultStore, SharedStore, StoreKind};
+use fatt::writer::{ResultWriter, BATCH_SIZE};
+use std::sync::Arc;
+use tempfile::tempdir;
+use tokio::sync::Mutex;
+
+fn finding(domain: &str, rule_name: &str, detected: bool) -> PendingFinding {
+    PendingFinding::new(
+        domain,
+        rule_name,
+        "/.git/HEAD",
+        detected,
+        &FindingDetails {
+            status: Some(200),
+            tag: Some("prod"),
+            ..Default::default()
+        },
+    )
+}
+
+#[tokio::test]
+async fn test_writer_stores_every_finding_before_flush_returns() -> Result<()> {
+    let store: SharedStore = Arc::new(Mutex::new(db::init_db(":memory:")?));
+    let writer = ResultWriter::start(store.clone());
+
+    // More than one batch, written from many tasks at once
+    let count = BATCH_SIZE * 2 + 10;
+    let mut tasks = Vec::new();
+    for i in 0..count {
+        let writer = writer.clone();
+        tasks.push(tokio::spawn(async move {
+            writer
+                .write(finding(&format!("host{}.example.com", i), "Git", true))
+                .await;
+        }));
+    }
+    for task in tasks {
+        task.await?;
+    }
+    writer.flush().await;
+
+    let findings = store.lock().await.findings()?;
+    assert_eq!(findings.len(), count);
+    assert!(findings.iter().all(|finding| finding.status == Some(200)));
+    assert!(findings
+        .iter()
+        .all(|finding| finding.tag.as_deref() == Some("prod")));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_writer_keeps_the_latest_outcome() -> Result<()> {
+    let store: SharedStore = Arc::new(Mutex::new(db::init_db(":memory:")?));
+    let writer = ResultWriter::start(store.clone());
+
+    writer.write(finding("example.com", "Git", false)).await;
+    writer.write(finding("example.com", "Git", true)).await;
+    writer.flush().await;
+
+    let findings = store.lock().await.findings()?;
+    assert_eq!(findings.len(), 1);
+    assert!(findings[0].detected);
+
+    Ok(())
+}
+
+#[test]
+fn test_record_findings_in_sharded_database() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let mut sharded = ShardedDb::open(db_path.to_str().unwrap(), 3)?;
+
+    let batch: Vec<PendingFinding> = (0..20)
+        .map(|i| finding(&format!("host{}.example.com", i), "Git", true))
+        .collect();
+    sharded.record_findings(&batch)?;
+
+    // Each finding lands in its domain's shard
+    for (index, shard) in sharded.shards.iter().enumerate() {
+        for finding in db::get_all_findings(shard)? {
+            assert_eq!(db::shard_index(&finding.domain, 3), index);
+        }
+    }
+    assert_eq!(sharded.findings()?.len(), 20);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_writer_writes_shards_on_their_own() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("results.sqlite");
+    let db_file = db_path.to_str().unwrap();
+    let store = store::open_store(StoreKind::Sqlite, db_file, 3, true)?;
+    let shards = store::open_shard_writers(StoreKind::Sqlite, db_file, 3, true)?;
+    assert_eq!(shards.len(), 3);
+    assert!(store::open_shard_writers(StoreKind::Sqlite, db_file, 1, true)?.is_empty());
+    let writer = ResultWriter::start_sharded(store.clone(), shards);
+
+    // Findings don't wait for the store's lock
+    let held = store.lock().await;
+    for i in 0..20 {
+        writer
+            .write(finding(&format!("host{}.example.com", i), "Git", true))
+            .await;
+    }
+    writer.flush().await;
+    drop(held);
+
+    let sharded = ShardedDb::open(db_file, 3)?;
+    for (index, shard) in sharded.shards.iter().enumerate() {
+        for finding in db::get_all_findings(shard)? {
+            assert_eq!(db::shard_index(&finding.domain, 3), index);
+        }
+    }
+    assert_eq!(store.lock().await.findings()?.len(), 20);
+
+    Ok(())
+}